@@ -510,6 +510,7 @@ var serveCmd = &cobra.Command{
 	Short: "Start the HTTP API server",
 	RunE: func(cmd *cobra.Command, args []string) error {
 		port, _ := cmd.Flags().GetInt("port")
+		socket, _ := cmd.Flags().GetString("socket")
 
 		ctx := context.Background()
 		client, err := b2.NewFromConfig(ctx)
@@ -519,7 +520,14 @@ var serveCmd = &cobra.Command{
 
 		server := api.NewServer(client, port)
 
-		fmt.Printf("Starting API server on http://localhost:%d\n", port)
+		if socket != "" {
+			server.SetSocket(socket)
+			fmt.Printf("Starting API server on %s\n", socket)
+		} else if port == 0 {
+			fmt.Println("Starting API server on a free port")
+		} else {
+			fmt.Printf("Starting API server on http://localhost:%d\n", port)
+		}
 		fmt.Println("Press Ctrl+C to stop")
 
 		// Handle shutdown
@@ -569,6 +577,7 @@ func init() {
 
 	// Serve command
 	serveCmd.Flags().IntP("port", "p", 8080, "Port to listen on")
+	serveCmd.Flags().String("socket", "", "Listen on this Unix domain socket instead of a port")
 	rootCmd.AddCommand(serveCmd)
 }
 
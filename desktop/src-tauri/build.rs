@@ -1,3 +1,44 @@
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+// Embed the SHA-256 and size of the bundled sidecar so the app can refuse to run a
+// binary that was swapped or truncated after the build
+fn embed_sidecar_manifest() {
+  println!("cargo:rerun-if-env-changed=TAURI_ENV_TARGET_TRIPLE");
+  println!("cargo:rerun-if-env-changed=APPLE_SIGNING_IDENTITY");
+
+  // Universal macOS builds bundle the universal binary rather than the per-arch one
+  let target = std::env::var("TAURI_ENV_TARGET_TRIPLE")
+    .or_else(|_| std::env::var("TARGET"))
+    .unwrap_or_default();
+  let extension = if target.contains("windows") { ".exe" } else { "" };
+  let path = PathBuf::from("binaries").join(format!("bb-stream-{}{}", target, extension));
+  println!("cargo:rerun-if-changed={}", path.display());
+
+  // Signing rewrites the binary while bundling, so its hash is only known afterwards;
+  // signed macOS builds check the code signature instead
+  if target.contains("apple") && std::env::var("APPLE_SIGNING_IDENTITY").is_ok() {
+    println!("cargo:rustc-env=BB_SIDECAR_CODESIGNED=1");
+    return;
+  }
+
+  match std::fs::read(&path) {
+    Ok(bytes) => {
+      println!("cargo:rustc-env=BB_SIDECAR_SHA256={}", hex::encode(Sha256::digest(&bytes)));
+      println!("cargo:rustc-env=BB_SIDECAR_SIZE={}", bytes.len());
+    }
+    Err(e) => println!(
+      "cargo:warning=Sidecar {} not found ({}); it won't be verified at launch",
+      path.display(),
+      e
+    ),
+  }
+}
+
 fn main() {
+  embed_sidecar_manifest();
+  // Pinned into sidecar_update.rs to verify downloaded sidecars
+  println!("cargo:rerun-if-env-changed=BB_SIDECAR_UPDATE_PUBKEY");
   tauri_build::build()
 }
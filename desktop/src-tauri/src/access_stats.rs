@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::{listing, network, store, thumbnails, transfers, AppState};
+
+pub const SETTINGS_FILE: &str = "access_stats.json";
+const STATS_FILE: &str = "access_heatmap.json";
+
+// A prefix's score halves after a week without access
+const HALF_LIFE_MS: f64 = 7.0 * 24.0 * 3600.0 * 1000.0;
+
+// Prefixes tracked per profile; the coldest are dropped beyond this
+const MAX_PREFIXES: usize = 500;
+
+// Collected counts are written out at most this often
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+// Hot prefixes' listings are refreshed into the cache this often, and only the hottest few
+const PREFETCH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const PREFETCH_PREFIXES: usize = 5;
+
+// Scores below this aren't worth prefetching
+const MIN_PREFETCH_SCORE: f64 = 3.0;
+
+// Thumbnails pre-generated per hot prefix, from the top of its listing
+const PREGENERATE_PER_PREFIX: usize = 48;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessStatsSettings {
+    pub enabled: bool,
+}
+
+impl Default for AccessStatsSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    Listing,
+    Download,
+}
+
+// How often a folder was listed or downloaded from, with recent accesses weighing more
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PrefixHeat {
+    pub profile: String,
+    pub bucket: String,
+    pub prefix: String,
+    pub listings: u64,
+    pub downloads: u64,
+    // Decayed access count as of `last_access`
+    pub score: f64,
+    // Unix milliseconds
+    pub last_access: i64,
+}
+
+impl PrefixHeat {
+    fn score_at(&self, now: i64) -> f64 {
+        let age = (now - self.last_access).max(0) as f64;
+        self.score * 0.5f64.powf(age / HALF_LIFE_MS)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AccessHeatmap {
+    pub enabled: bool,
+    // Hottest first, scores decayed to now
+    pub prefixes: Vec<PrefixHeat>,
+}
+
+// Which remote folders are browsed and downloaded from most, to decide what to keep
+// warm in the listing and thumbnail caches. Stays on this machine.
+pub struct AccessStats {
+    settings: Mutex<AccessStatsSettings>,
+    // By profile, then "bucket/prefix"
+    prefixes: Mutex<HashMap<String, HashMap<String, PrefixHeat>>>,
+    dirty: AtomicBool,
+}
+
+impl AccessStats {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(AccessStatsSettings::default()),
+            prefixes: Mutex::new(HashMap::new()),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.settings.lock().unwrap().enabled
+    }
+
+    // Hottest prefixes of `profile`, scores decayed to now
+    fn hottest(&self, profile: &str) -> Vec<PrefixHeat> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut hot: Vec<PrefixHeat> = self
+            .prefixes
+            .lock()
+            .unwrap()
+            .get(profile)
+            .map(|prefixes| prefixes.values().cloned().collect())
+            .unwrap_or_default();
+        hot.iter_mut().for_each(|heat| heat.score = heat.score_at(now));
+        hot.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hot
+    }
+}
+
+// Note an access to `prefix` of `bucket` in the active profile
+pub fn record(state: &AppState, bucket: &str, prefix: &str, access: Access) {
+    if !state.access_stats.enabled() || bucket.is_empty() {
+        return;
+    }
+    let profile = state.active_profile.lock().unwrap().clone();
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut all = state.access_stats.prefixes.lock().unwrap();
+    let prefixes = all.entry(profile.clone()).or_default();
+    let heat = prefixes
+        .entry(format!("{}/{}", bucket, prefix))
+        .or_insert_with(|| PrefixHeat {
+            profile,
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            listings: 0,
+            downloads: 0,
+            score: 0.0,
+            last_access: now,
+        });
+    heat.score = heat.score_at(now) + 1.0;
+    heat.last_access = now;
+    match access {
+        Access::Listing => heat.listings += 1,
+        Access::Download => heat.downloads += 1,
+    }
+    if prefixes.len() > MAX_PREFIXES {
+        let coldest = prefixes
+            .iter()
+            .min_by(|a, b| a.1.score_at(now).total_cmp(&b.1.score_at(now)))
+            .map(|(key, _)| key.clone());
+        if let Some(key) = coldest {
+            prefixes.remove(&key);
+        }
+    }
+    state.access_stats.dirty.store(true, Ordering::SeqCst);
+}
+
+// Note a download through the shell, from its API path ("/api/download/bucket/key")
+pub fn record_download(state: &AppState, path: &str) {
+    let Some(rest) = path.split('?').next().and_then(|path| path.strip_prefix("/api/download/")) else {
+        return;
+    };
+    let Some((bucket, key)) = rest.split_once('/') else {
+        return;
+    };
+    let decode = |s: &str| percent_encoding::percent_decode_str(s).decode_utf8().ok().map(|s| s.into_owned());
+    let (Some(bucket), Some(key)) = (decode(bucket), decode(key)) else {
+        return;
+    };
+    let prefix = key.rfind('/').map_or("", |i| &key[..=i]);
+    record(state, &bucket, prefix, Access::Download);
+}
+
+fn save(app: &AppHandle, state: &AppState) {
+    if !state.access_stats.dirty.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    let prefixes: Vec<PrefixHeat> = state
+        .access_stats
+        .prefixes
+        .lock()
+        .unwrap()
+        .values()
+        .flat_map(|prefixes| prefixes.values().cloned())
+        .collect();
+    if let Err(e) = store::data_file(app, STATS_FILE).and_then(|path| store::save_json(&path, &prefixes)) {
+        log::warn!("Failed to save access stats: {}", e);
+    }
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => *state.access_stats.settings.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load access stats settings: {}", e),
+    }
+    if !state.access_stats.enabled() {
+        return;
+    }
+    match store::data_file(app, STATS_FILE) {
+        Ok(path) => {
+            let saved: Vec<PrefixHeat> = store::load_json(&path);
+            let mut all = state.access_stats.prefixes.lock().unwrap();
+            for heat in saved {
+                let key = format!("{}/{}", heat.bucket, heat.prefix);
+                all.entry(heat.profile.clone()).or_default().insert(key, heat);
+            }
+        }
+        Err(e) => log::error!("Failed to load access stats: {}", e),
+    }
+}
+
+// Refresh the hottest folders' listings into the cache and pre-generate their thumbnails,
+// while nothing in the foreground needs the backend
+async fn prefetch(app: &AppHandle, state: &AppState) {
+    // The network monitor only keeps this current while Wi-Fi-only is on
+    network::sample(app, state).await;
+    let profile = state.active_profile.lock().unwrap().clone();
+    let hot: Vec<PrefixHeat> = state
+        .access_stats
+        .hottest(&profile)
+        .into_iter()
+        .filter(|heat| heat.score >= MIN_PREFETCH_SCORE)
+        .take(PREFETCH_PREFIXES)
+        .collect();
+    for heat in hot {
+        let busy = transfers::any_running(state) || state.thumbnails.is_warming();
+        if busy || !state.is_healthy() || state.network.is_metered() || state.power.is_settling() {
+            return;
+        }
+        if *state.active_profile.lock().unwrap() != profile || state.shutdown.load(Ordering::SeqCst) {
+            return;
+        }
+        let listing = match listing::list_objects_cached(app, state, &heat.bucket, &heat.prefix).await {
+            Ok(listing) if !listing.stale => listing,
+            Ok(_) => continue,
+            Err(e) => {
+                log::debug!("Failed to prefetch {}/{}: {}", heat.bucket, heat.prefix, e);
+                continue;
+            }
+        };
+        // Listed names are full keys; folders end in '/'
+        let keys = listing
+            .objects
+            .into_iter()
+            .map(|object| object.name)
+            .filter(|name| !name.ends_with('/'))
+            .take(PREGENERATE_PER_PREFIX)
+            .collect();
+        let generated = thumbnails::pregenerate(app, state, &heat.bucket, keys).await;
+        if generated > 0 {
+            log::debug!("Pre-generated {} thumbnails for {}/{}", generated, heat.bucket, heat.prefix);
+        }
+    }
+}
+
+// Save collected counts and keep hot folders warm in the background
+pub fn spawn_monitor(app: AppHandle, state: Arc<AppState>) {
+    tauri::async_runtime::spawn(async move {
+        let mut prefetched: Option<Instant> = None;
+        loop {
+            if state.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            save(&app, &state);
+            let due = prefetched.map_or(true, |at| at.elapsed() >= PREFETCH_INTERVAL);
+            if due && state.access_stats.enabled() && state.is_healthy() {
+                prefetch(&app, &state).await;
+                prefetched = Some(Instant::now());
+            }
+            tokio::time::sleep(SAVE_INTERVAL).await;
+        }
+    });
+}
+
+// The active profile's most accessed folders, hottest first
+#[tauri::command]
+pub fn get_access_heatmap(state: tauri::State<Arc<AppState>>, limit: Option<usize>) -> AccessHeatmap {
+    let profile = state.active_profile.lock().unwrap().clone();
+    let mut prefixes = state.access_stats.hottest(&profile);
+    prefixes.truncate(limit.unwrap_or(usize::MAX));
+    AccessHeatmap {
+        enabled: state.access_stats.enabled(),
+        prefixes,
+    }
+}
+
+#[tauri::command]
+pub fn get_access_stats_settings(state: tauri::State<Arc<AppState>>) -> AccessStatsSettings {
+    *state.access_stats.settings.lock().unwrap()
+}
+
+// Turning access stats off forgets what was collected and stops prefetching
+#[tauri::command]
+pub fn set_access_stats_settings(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    settings: AccessStatsSettings,
+) -> Result<AccessStatsSettings, AppError> {
+    store::save_json(&store::data_file(&app, SETTINGS_FILE)?, &settings)?;
+    *state.access_stats.settings.lock().unwrap() = settings;
+    if !settings.enabled {
+        state.access_stats.prefixes.lock().unwrap().clear();
+        state.access_stats.dirty.store(false, Ordering::SeqCst);
+        let path = store::data_file(&app, STATS_FILE)?;
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(settings)
+}
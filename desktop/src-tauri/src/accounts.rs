@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::permissions::{self, Capability};
+use crate::{store, AppState};
+
+const ACCOUNTS_FILE: &str = "accounts.json";
+
+// Keychain service holding each account's secret under the account's name
+const KEYCHAIN_SERVICE: &str = "com.bbstream.desktop.accounts";
+
+// Credentials and endpoint for one storage account. Secrets live in the OS keychain,
+// never in accounts.json; files from before that are migrated on load.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AccountKind {
+    B2 {
+        key_id: String,
+        #[serde(default, skip_serializing)]
+        application_key: String,
+        #[serde(default)]
+        endpoint: Option<String>,
+    },
+    S3 {
+        endpoint: Option<String>,
+        region: Option<String>,
+        access_key_id: String,
+        #[serde(default, skip_serializing)]
+        secret_access_key: String,
+        #[serde(default)]
+        provider: Option<String>,
+    },
+    Local {
+        root: PathBuf,
+    },
+}
+
+impl AccountKind {
+    fn secret_mut(&mut self) -> Option<&mut String> {
+        match self {
+            AccountKind::B2 { application_key, .. } => Some(application_key),
+            AccountKind::S3 { secret_access_key, .. } => Some(secret_access_key),
+            AccountKind::Local { .. } => None,
+        }
+    }
+
+    fn secret(&self) -> Option<&str> {
+        match self {
+            AccountKind::B2 { application_key, .. } => Some(application_key),
+            AccountKind::S3 { secret_access_key, .. } => Some(secret_access_key),
+            AccountKind::Local { .. } => None,
+        }
+    }
+}
+
+fn keychain_entry(name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, name).map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+fn store_secret(name: &str, secret: &str) -> Result<(), String> {
+    keychain_entry(name)?
+        .set_password(secret)
+        .map_err(|e| format!("Failed to store the secret for {} in the keychain: {}", name, e))
+}
+
+fn load_secret(name: &str) -> Result<Option<String>, String> {
+    match keychain_entry(name)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read the secret for {} from the keychain: {}", name, e)),
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AccountProfile {
+    pub name: String,
+    pub kind: AccountKind,
+    // Where the profile came from, e.g. "rclone"
+    pub origin: String,
+}
+
+// What the frontend sees: everything except secrets
+#[derive(Clone, Debug, Serialize)]
+pub struct AccountSummary {
+    pub name: String,
+    pub kind: &'static str,
+    pub endpoint: Option<String>,
+    pub origin: String,
+    // The keychain had no secret for it; set_account_secret asks for it again
+    pub needs_secret: bool,
+}
+
+impl From<&AccountProfile> for AccountSummary {
+    fn from(profile: &AccountProfile) -> Self {
+        let (kind, endpoint) = match &profile.kind {
+            AccountKind::B2 { endpoint, .. } => ("b2", endpoint.clone()),
+            AccountKind::S3 { endpoint, .. } => ("s3", endpoint.clone()),
+            AccountKind::Local { root } => ("local", Some(root.display().to_string())),
+        };
+        Self {
+            name: profile.name.clone(),
+            kind,
+            endpoint,
+            origin: profile.origin.clone(),
+            needs_secret: profile.kind.secret().is_some_and(str::is_empty),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct UnsupportedRemote {
+    pub name: String,
+    pub remote_type: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RcloneImportReport {
+    pub imported: Vec<String>,
+    // Remotes whose name matches an existing profile are left untouched
+    pub skipped: Vec<String>,
+    pub unsupported: Vec<UnsupportedRemote>,
+}
+
+// Configured storage accounts, persisted in the app data directory
+pub struct Accounts {
+    profiles: Mutex<Vec<AccountProfile>>,
+}
+
+impl Accounts {
+    pub fn new() -> Self {
+        Self {
+            profiles: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Without a keychain a secret only lasts until the app quits and is asked for again
+    fn save(&self, app: &AppHandle) -> Result<(), String> {
+        let profiles = self.profiles.lock().unwrap();
+        for profile in profiles.iter() {
+            match profile.kind.secret() {
+                Some(secret) if !secret.is_empty() => {
+                    if let Err(e) = store_secret(&profile.name, secret) {
+                        log::warn!("{}", e);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let path = store::data_file(app, ACCOUNTS_FILE)?;
+        store::save_json(&path, &*profiles)
+    }
+
+    // Accounts a sidecar can serve on its own, i.e. B2 ones
+    pub fn sidecar_accounts(&self) -> Vec<String> {
+        self.profiles
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| matches!(p.kind, AccountKind::B2 { .. }))
+            .map(|p| p.name.clone())
+            .collect()
+    }
+
+    // Environment that points a sidecar at the account's credentials
+    pub fn sidecar_env(&self, name: &str) -> Result<Vec<(String, String)>, String> {
+        let profiles = self.profiles.lock().unwrap();
+        let profile = profiles
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| format!("Unknown account {}", name))?;
+        match &profile.kind {
+            AccountKind::B2 { application_key, .. } if application_key.is_empty() => {
+                Err(format!("Account {} needs its application key again", name))
+            }
+            AccountKind::B2 {
+                key_id,
+                application_key,
+                ..
+            } => Ok(vec![
+                ("BB_KEY_ID".to_string(), key_id.clone()),
+                ("BB_APP_KEY".to_string(), application_key.clone()),
+            ]),
+            _ => Err(format!("Account {} is not a B2 account, which the backend needs", name)),
+        }
+    }
+}
+
+// Load the profiles and fill in their secrets from the keychain. Secrets still in an
+// older accounts.json are moved to the keychain and dropped from the file.
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let path = match store::data_file(app, ACCOUNTS_FILE) {
+        Ok(path) => path,
+        Err(e) => {
+            log::error!("Failed to load accounts: {}", e);
+            return;
+        }
+    };
+    let mut profiles: Vec<AccountProfile> = store::load_json(&path);
+    let mut migrate = false;
+    for profile in &mut profiles {
+        let name = profile.name.clone();
+        let Some(secret) = profile.kind.secret_mut() else {
+            continue;
+        };
+        if !secret.is_empty() {
+            migrate = true;
+            continue;
+        }
+        match load_secret(&name) {
+            Ok(Some(stored)) => *secret = stored,
+            Ok(None) => log::warn!("No secret in the keychain for account {}", name),
+            Err(e) => log::error!("{}", e),
+        }
+    }
+    *state.accounts.profiles.lock().unwrap() = profiles;
+    if migrate {
+        match state.accounts.save(app) {
+            Ok(()) => log::info!("Moved account secrets from {} to the keychain", ACCOUNTS_FILE),
+            Err(e) => log::error!("Failed to move account secrets to the keychain: {}", e),
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_accounts(state: tauri::State<Arc<AppState>>) -> Vec<AccountSummary> {
+    state.accounts.profiles.lock().unwrap().iter().map(AccountSummary::from).collect()
+}
+
+// Import remotes from an rclone.conf; defaults to $RCLONE_CONFIG or ~/.config/rclone/rclone.conf
+#[tauri::command]
+pub async fn import_rclone_config(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    path: Option<String>,
+) -> Result<RcloneImportReport, AppError> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => match std::env::var_os("RCLONE_CONFIG") {
+            Some(path) => PathBuf::from(path),
+            None => app
+                .path()
+                .home_dir()
+                .map_err(|e| format!("Failed to resolve home directory: {}", e))?
+                .join(".config/rclone/rclone.conf"),
+        },
+    };
+    let detail = format!("importing {}", path.display());
+    permissions::ensure(&app, &state, Capability::ReadLocalFiles, &detail).await?;
+    let contents =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if contents.trim_start().starts_with("RCLONE_ENCRYPT_V0:") {
+        return Err(AppError::Unsupported {
+            message: "Encrypted rclone configs are not supported; decrypt it with `rclone config show` first"
+                .to_string(),
+        });
+    }
+
+    let mut report = RcloneImportReport::default();
+    let mut profiles = state.accounts.profiles.lock().unwrap();
+    for (name, section) in parse_ini(&contents) {
+        let remote_type = section.get("type").cloned().unwrap_or_default();
+        let kind = match map_remote(&remote_type, &section) {
+            Ok(kind) => kind,
+            Err(reason) => {
+                report.unsupported.push(UnsupportedRemote { name, remote_type, reason });
+                continue;
+            }
+        };
+        if profiles.iter().any(|p| p.name == name) {
+            report.skipped.push(name);
+            continue;
+        }
+        profiles.push(AccountProfile {
+            name: name.clone(),
+            kind,
+            origin: "rclone".to_string(),
+        });
+        report.imported.push(name);
+    }
+    drop(profiles);
+
+    state.accounts.save(&app)?;
+    log::info!(
+        "Imported {} rclone remotes ({} skipped, {} unsupported)",
+        report.imported.len(),
+        report.skipped.len(),
+        report.unsupported.len()
+    );
+    Ok(report)
+}
+
+// Supply an account's secret again, e.g. after the keychain lost it
+#[tauri::command]
+pub fn set_account_secret(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    name: String,
+    secret: String,
+) -> Result<(), AppError> {
+    if secret.is_empty() {
+        return Err(AppError::invalid("The secret can't be empty"));
+    }
+    {
+        let mut profiles = state.accounts.profiles.lock().unwrap();
+        let profile = profiles
+            .iter_mut()
+            .find(|p| p.name == name)
+            .ok_or_else(|| AppError::invalid(format!("Unknown account {}", name)))?;
+        let stored = profile
+            .kind
+            .secret_mut()
+            .ok_or_else(|| AppError::invalid(format!("Account {} has no secret", name)))?;
+        *stored = secret;
+    }
+    Ok(state.accounts.save(&app)?)
+}
+
+// Map one rclone remote section onto an account profile
+fn map_remote(remote_type: &str, section: &BTreeMap<String, String>) -> Result<AccountKind, String> {
+    let required = |key: &str| {
+        section
+            .get(key)
+            .filter(|v| !v.is_empty())
+            .cloned()
+            .ok_or_else(|| format!("missing `{}`", key))
+    };
+    let optional = |key: &str| section.get(key).filter(|v| !v.is_empty()).cloned();
+
+    match remote_type {
+        "b2" => Ok(AccountKind::B2 {
+            key_id: required("account")?,
+            application_key: required("key")?,
+            endpoint: optional("endpoint"),
+        }),
+        "s3" => {
+            if section.get("env_auth").is_some_and(|v| v == "true") {
+                return Err("credentials come from the environment (env_auth)".to_string());
+            }
+            Ok(AccountKind::S3 {
+                endpoint: optional("endpoint"),
+                region: optional("region"),
+                access_key_id: required("access_key_id")?,
+                secret_access_key: required("secret_access_key")?,
+                provider: optional("provider"),
+            })
+        }
+        "local" => Ok(AccountKind::Local {
+            root: PathBuf::from(optional("root").unwrap_or_else(|| "/".to_string())),
+        }),
+        "" => Err("no remote type".to_string()),
+        other => Err(format!("remote type `{}` is not supported", other)),
+    }
+}
+
+// Minimal INI parser matching rclone's config format
+fn parse_ini(contents: &str) -> Vec<(String, BTreeMap<String, String>)> {
+    let mut sections: Vec<(String, BTreeMap<String, String>)> = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_string(), BTreeMap::new()));
+        } else if let (Some((key, value)), Some((_, section))) = (line.split_once('='), sections.last_mut()) {
+            section.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    sections
+}
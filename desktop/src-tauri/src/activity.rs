@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+
+use crate::{transfers, AppState};
+
+// Periodic work runs this many times less often while nobody is looking
+const IDLE_FACTOR: u32 = 2;
+const BACKGROUND_FACTOR: u32 = 6;
+
+// Longest any periodic work is put off, however idle the app is
+const MAX_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    // A window has focus, or transfers are running
+    Active,
+    // The main window is visible but not focused, and nothing is transferring
+    Idle,
+    // The main window is hidden or minimized, and nothing is transferring
+    Background,
+}
+
+// How busy the app is, from window focus and the transfer engine, so periodic work
+// (health checks, resource sampling) slows down while nobody is looking
+pub struct Activity {
+    focused: AtomicBool,
+    // Wakes sleeping periodic work when the app becomes active again
+    wake: Notify,
+}
+
+impl Activity {
+    pub fn new() -> Self {
+        Self {
+            // Assume so until the windows report otherwise
+            focused: AtomicBool::new(true),
+            wake: Notify::new(),
+        }
+    }
+}
+
+pub fn level(app: &AppHandle, state: &AppState) -> Level {
+    if state.activity.focused.load(Ordering::SeqCst) || transfers::any_running(state) {
+        return Level::Active;
+    }
+    let shown = app
+        .get_webview_window("main")
+        .map(|window| window.is_visible().unwrap_or(true) && !window.is_minimized().unwrap_or(false))
+        .unwrap_or(false);
+    if shown {
+        Level::Idle
+    } else {
+        Level::Background
+    }
+}
+
+// `interval` stretched to how active the app is
+pub fn scaled(app: &AppHandle, state: &AppState, interval: Duration) -> Duration {
+    let factor = match level(app, state) {
+        Level::Active => return interval,
+        Level::Idle => IDLE_FACTOR,
+        Level::Background => BACKGROUND_FACTOR,
+    };
+    interval.saturating_mul(factor).min(MAX_INTERVAL.max(interval))
+}
+
+// Sleep between rounds of periodic work: `interval` while active, longer otherwise,
+// cut short as soon as the app becomes active again
+pub async fn sleep(app: &AppHandle, state: &AppState, interval: Duration) {
+    let scaled = scaled(app, state, interval);
+    if scaled == interval {
+        tokio::time::sleep(interval).await;
+        return;
+    }
+    let _ = tokio::time::timeout(scaled, state.activity.wake.notified()).await;
+}
+
+// Called for every window focus change
+pub fn focus_changed(state: &AppState, focused: bool) {
+    let was_focused = state.activity.focused.swap(focused, Ordering::SeqCst);
+    if focused && !was_focused {
+        state.activity.wake.notify_waiters();
+    }
+}
+
+// Called when a transfer starts, which makes the app active
+pub fn transfer_started(state: &AppState) {
+    state.activity.wake.notify_waiters();
+}
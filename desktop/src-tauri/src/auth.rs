@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::notifications::{self, Notification};
+use crate::{backend, emit_backend_status, emit_to_main, AppState, BackendStatus, Sidecar};
+
+// Sent as `auth-required` when a profile's storage credentials stop working
+#[derive(Clone, Debug, Serialize)]
+pub struct AuthRequired {
+    pub profile: String,
+    pub message: String,
+}
+
+// Profiles whose credentials the backend rejected. Their sidecars count as unhealthy,
+// which holds transfers, until the credentials are retried or the sidecar restarts.
+pub struct Auth {
+    required: Mutex<HashMap<String, String>>,
+}
+
+impl Auth {
+    pub fn new() -> Self {
+        Self {
+            required: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_required(&self, profile: &str) -> bool {
+        self.required.lock().unwrap().contains_key(profile)
+    }
+
+    // Why the profile's credentials were rejected, if they were
+    pub fn rejection(&self, profile: &str) -> Option<String> {
+        self.required.lock().unwrap().get(profile).cloned()
+    }
+}
+
+// Whether an error from a backend call means the credentials were rejected: the
+// backend answers 401 for expired or revoked keys and 403 for keys lacking access
+pub fn is_auth_error(error: &str) -> bool {
+    error.contains("status: 401") || error.contains("status: 403")
+}
+
+// Mark `sidecar` as needing new credentials and ask the user for them, once until
+// they are fixed
+pub fn require(app: &AppHandle, state: &AppState, sidecar: &Sidecar, message: String) {
+    let first = state
+        .auth
+        .required
+        .lock()
+        .unwrap()
+        .insert(sidecar.profile.clone(), message.clone())
+        .is_none();
+    if !first {
+        return;
+    }
+    log::warn!("Credentials of profile {} were rejected: {}", sidecar.profile, message);
+    sidecar.is_healthy.store(false, Ordering::SeqCst);
+    emit_backend_status(app, sidecar, BackendStatus::AuthRequired { message: message.clone() });
+
+    let profile = sidecar.profile.clone();
+    emit_to_main(app, "auth-required", AuthRequired { profile: profile.clone(), message });
+    notifications::notify(
+        app,
+        state,
+        Notification::new(
+            "Sign in again",
+            "Backblaze B2 rejected the saved credentials. Transfers are paused until you sign in again.",
+        )
+        .action("sign_in", "Sign In"),
+        Some(Box::new(move |app: &AppHandle, action: &str| {
+            if action == "sign_in" {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+                emit_to_main(app, "auth-sign-in", profile);
+            }
+        })),
+    );
+}
+
+// Called with errors of calls to the active backend; true if it was an auth failure
+pub fn report(app: &AppHandle, state: &AppState, error: &str) -> bool {
+    if !is_auth_error(error) {
+        return false;
+    }
+    require(app, state, &state.sidecar(), error.to_string());
+    true
+}
+
+// Forget a rejection, e.g. once the sidecar restarts with new credentials
+pub fn clear(state: &AppState, profile: &str) -> bool {
+    state.auth.required.lock().unwrap().remove(profile).is_some()
+}
+
+// Check a profile's credentials again after the user signed in, resuming its
+// transfers if they work
+#[tauri::command]
+pub async fn retry_auth(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    profile: Option<String>,
+) -> Result<(), AppError> {
+    let sidecar = match profile {
+        Some(profile) => state
+            .sidecars
+            .lock()
+            .unwrap()
+            .get(&profile)
+            .cloned()
+            .ok_or_else(|| AppError::invalid(format!("Unknown profile: {}", profile)))?,
+        None => state.sidecar(),
+    };
+    let base_url = sidecar.base_url();
+    if let Err(e) = backend::list_buckets(&backend::shared_client(&base_url), &base_url).await {
+        if is_auth_error(&e) {
+            return Err(AppError::Unauthorized {
+                message: "The credentials are still rejected".to_string(),
+            });
+        }
+        return Err(e.into());
+    }
+    if clear(&state, &sidecar.profile) {
+        log::info!("Credentials of profile {} work again", sidecar.profile);
+        sidecar.is_healthy.store(true, Ordering::SeqCst);
+        emit_backend_status(&app, &sidecar, BackendStatus::Healthy);
+    }
+    Ok(())
+}
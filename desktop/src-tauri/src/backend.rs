@@ -0,0 +1,544 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::bandwidth::{self, Throttle};
+use crate::session;
+
+// Object metadata as returned by GET /api/buckets/{name}/files
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RemoteObject {
+    pub name: String,
+    pub size: i64,
+    #[serde(default)]
+    pub content_type: String,
+    #[serde(default)]
+    pub timestamp: i64,
+}
+
+// Bucket metadata as returned by GET /api/buckets
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct RemoteBucket {
+    pub name: String,
+    #[serde(default, rename = "Type")]
+    pub bucket_type: String,
+}
+
+// Base URL of the bundled sidecar listening on the given port. It carries the shell's
+// session credential, which reqwest sends as Basic auth on every request.
+pub fn local_base_url(port: u16) -> String {
+    format!("http://shell:{}@localhost:{}", session::shell_credential(), port)
+}
+
+// Host name standing in for a sidecar's Unix socket in its base URL
+const SOCKET_HOST_SUFFIX: &str = ".socket";
+
+fn pooled(builder: reqwest::ClientBuilder) -> reqwest::Client {
+    builder
+        .connect_timeout(Duration::from_secs(5))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .build()
+        .expect("default TLS backend is available")
+}
+
+// A sidecar listening on a Unix socket rather than a port, with its pooled client
+struct Socket {
+    path: PathBuf,
+    client: reqwest::Client,
+}
+
+// Sockets by the host standing in for them
+fn sockets() -> &'static Mutex<HashMap<String, Socket>> {
+    static SOCKETS: OnceLock<Mutex<HashMap<String, Socket>>> = OnceLock::new();
+    SOCKETS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn with_socket(builder: reqwest::ClientBuilder, path: &Path) -> reqwest::ClientBuilder {
+    #[cfg(unix)]
+    let builder = builder.unix_socket(path);
+    // Socket transport is refused by the sidecar settings elsewhere
+    #[cfg(not(unix))]
+    let _ = path;
+    builder
+}
+
+// Base URL of the bundled sidecar listening on a Unix socket. Clients from
+// client_builder and shared_client send requests for it over the socket.
+pub fn socket_base_url(instance_id: &str, path: &Path) -> String {
+    let host = format!("{}{}", instance_id, SOCKET_HOST_SUFFIX);
+    sockets().lock().unwrap().entry(host.clone()).or_insert_with(|| Socket {
+        path: path.to_path_buf(),
+        client: pooled(with_socket(reqwest::Client::builder(), path)),
+    });
+    format!("http://shell:{}@{}", session::shell_credential(), host)
+}
+
+// Stop routing to a socket once its sidecar is gone
+pub fn forget_socket(path: &Path) {
+    sockets().lock().unwrap().retain(|_, socket| socket.path != path);
+}
+
+fn socket_host(base_url: &str) -> Option<String> {
+    let url = Url::parse(base_url).ok()?;
+    url.host_str().filter(|host| host.ends_with(SOCKET_HOST_SUFFIX)).map(str::to_string)
+}
+
+// Whether requests to `base_url` go over a Unix socket rather than TCP
+pub fn is_socket_url(base_url: &str) -> bool {
+    socket_host(base_url).is_some()
+}
+
+// Unix socket the backend at `base_url` listens on, if it does
+pub fn socket_path(base_url: &str) -> Option<PathBuf> {
+    socket_host(base_url).and_then(|host| sockets().lock().unwrap().get(&host).map(|s| s.path.clone()))
+}
+
+// Builder for clients that talk to the backend at `base_url`, connecting through its
+// Unix socket when it listens on one
+pub fn client_builder(base_url: &str) -> reqwest::ClientBuilder {
+    match socket_path(base_url) {
+        Some(path) => with_socket(reqwest::Client::builder(), &path),
+        None => reqwest::Client::builder(),
+    }
+}
+
+// Client shared by calls that don't need their own timeouts, so connections to the
+// sidecar are pooled and reused
+pub fn shared_client(base_url: &str) -> reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    let socket_client =
+        socket_host(base_url).and_then(|host| sockets().lock().unwrap().get(&host).map(|s| s.client.clone()));
+    socket_client.unwrap_or_else(|| CLIENT.get_or_init(|| pooled(reqwest::Client::builder())).clone())
+}
+
+// Build an API URL from path segments; each segment is percent-encoded separately
+pub fn api_url(base_url: &str, segments: &[&str]) -> Result<Url, String> {
+    let mut url = Url::parse(base_url).map_err(|e| format!("Invalid backend URL {}: {}", base_url, e))?;
+    url.path_segments_mut()
+        .map_err(|_| format!("Invalid backend URL {}", base_url))?
+        .pop_if_empty()
+        .push("api")
+        .extend(segments.iter().flat_map(|s| s.split('/')));
+    Ok(url)
+}
+
+// URL that streams an object's contents
+pub fn download_url(base_url: &str, bucket: &str, key: &str) -> Result<Url, String> {
+    api_url(base_url, &["download", bucket, key])
+}
+
+// URL that accepts a raw request body as the object's contents
+pub fn stream_upload_url(base_url: &str, bucket: &str, key: &str) -> Result<Url, String> {
+    let mut url = api_url(base_url, &["upload", "stream"])?;
+    url.query_pairs_mut().append_pair("bucket", bucket).append_pair("path", key);
+    Ok(url)
+}
+
+// A failed backend request. Transient failures come from the backend being unreachable
+// or briefly unavailable rather than from the request, so trying again may work.
+#[derive(Debug)]
+pub struct RequestError {
+    pub message: String,
+    pub transient: bool,
+}
+
+impl RequestError {
+    // The request never got an answer
+    pub fn send(message: String) -> Self {
+        Self { message, transient: true }
+    }
+
+    pub fn status(message: String, status: reqwest::StatusCode) -> Self {
+        Self {
+            message,
+            transient: is_transient_status(status),
+        }
+    }
+
+    pub fn other(message: String) -> Self {
+        Self {
+            message,
+            transient: false,
+        }
+    }
+}
+
+impl From<RequestError> for String {
+    fn from(e: RequestError) -> Self {
+        e.message
+    }
+}
+
+// Statuses the sidecar or a proxy in front of it returns while briefly unavailable
+pub fn is_transient_status(status: reqwest::StatusCode) -> bool {
+    use reqwest::StatusCode;
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+// List every object under a prefix
+pub async fn list_objects(
+    client: &reqwest::Client,
+    base_url: &str,
+    bucket: &str,
+    prefix: &str,
+) -> Result<Vec<RemoteObject>, String> {
+    request_objects(client, base_url, bucket, prefix).await.map_err(String::from)
+}
+
+// list_objects, telling transient failures apart
+pub async fn request_objects(
+    client: &reqwest::Client,
+    base_url: &str,
+    bucket: &str,
+    prefix: &str,
+) -> Result<Vec<RemoteObject>, RequestError> {
+    let mut url = api_url(base_url, &["buckets", bucket, "files"]).map_err(RequestError::other)?;
+    url.query_pairs_mut().append_pair("prefix", prefix);
+
+    let resp = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| RequestError::send(format!("Failed to list {}/{}: {}", bucket, prefix, e)))?;
+    let status = resp.status();
+    if !status.is_success() {
+        let message = format!("Listing {}/{} returned status: {}", bucket, prefix, status);
+        return Err(RequestError::status(message, status));
+    }
+
+    // The backend encodes an empty listing as null
+    let objects: Option<Vec<RemoteObject>> = resp
+        .json()
+        .await
+        .map_err(|e| RequestError::other(format!("Invalid listing response: {}", e)))?;
+    Ok(objects.unwrap_or_default())
+}
+
+// List all buckets visible to the configured account
+pub async fn list_buckets(client: &reqwest::Client, base_url: &str) -> Result<Vec<RemoteBucket>, String> {
+    request_buckets(client, base_url).await.map_err(String::from)
+}
+
+// list_buckets, telling transient failures apart
+pub async fn request_buckets(client: &reqwest::Client, base_url: &str) -> Result<Vec<RemoteBucket>, RequestError> {
+    let resp = client
+        .get(api_url(base_url, &["buckets"]).map_err(RequestError::other)?)
+        .send()
+        .await
+        .map_err(|e| RequestError::send(format!("Failed to list buckets: {}", e)))?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(RequestError::status(format!("Listing buckets returned status: {}", status), status));
+    }
+    let buckets: Option<Vec<RemoteBucket>> = resp
+        .json()
+        .await
+        .map_err(|e| RequestError::other(format!("Invalid bucket list response: {}", e)))?;
+    Ok(buckets.unwrap_or_default())
+}
+
+// Wrap a byte stream as a request body that counts bytes and aborts once `cancel` is set
+pub fn metered_body<S, E>(stream: S, cancel: Arc<AtomicBool>, counter: Arc<AtomicU64>) -> reqwest::Body
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    reqwest::Body::wrap_stream(stream.map(move |chunk| {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(std::io::Error::other("cancelled"));
+        }
+        let chunk = chunk.map_err(std::io::Error::other)?;
+        counter.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        Ok(chunk)
+    }))
+}
+
+// Upload a streamed body as the contents of bucket/key
+pub async fn upload_stream(
+    client: &reqwest::Client,
+    base_url: &str,
+    bucket: &str,
+    key: &str,
+    body: reqwest::Body,
+) -> Result<(), String> {
+    let resp = client
+        .post(stream_upload_url(base_url, bucket, key)?)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to upload {}: {}", key, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Uploading {} returned status: {}", key, resp.status()));
+    }
+    Ok(())
+}
+
+// Failure reason reported when a local file changes while it is being uploaded
+pub const SOURCE_CHANGED: &str = "source-changed";
+
+// How often a file that keeps changing is re-uploaded before giving up
+pub const SOURCE_CHANGE_RETRIES: usize = 3;
+
+// Read size for local uploads; the file is re-checked for changes once per chunk
+const UPLOAD_CHUNK_SIZE: usize = 256 * 1024;
+
+// What to do when a local file changes mid-upload
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceChangePolicy {
+    // Start the file over from the beginning, a few times at most
+    #[default]
+    Restart,
+    // Fail the file with a source-changed error
+    Abort,
+}
+
+pub fn file_version(path: &Path) -> std::io::Result<(u64, SystemTime)> {
+    let meta = std::fs::metadata(path)?;
+    Ok((meta.len(), meta.modified()?))
+}
+
+// Upload a local file to bucket/key, checking its size and mtime before sending each
+// chunk so a file written to mid-transfer never ends up stored torn
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_local_file(
+    client: &reqwest::Client,
+    base_url: &str,
+    bucket: &str,
+    key: &str,
+    path: &Path,
+    policy: SourceChangePolicy,
+    throttle: &Throttle,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let mut attempt = 0;
+    loop {
+        let version = file_version(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+        let changed = Arc::new(AtomicBool::new(false));
+        let watched = {
+            let path = path.to_path_buf();
+            let changed = Arc::clone(&changed);
+            tokio_util::io::ReaderStream::with_capacity(file, UPLOAD_CHUNK_SIZE).map(move |chunk| {
+                if file_version(&path).ok() != Some(version) {
+                    changed.store(true, Ordering::SeqCst);
+                    return Err(std::io::Error::other(SOURCE_CHANGED));
+                }
+                chunk
+            })
+        };
+        counter.store(0, Ordering::Relaxed);
+        let throttled = bandwidth::throttled(watched, throttle.clone());
+        let body = metered_body(throttled, Arc::clone(cancel), Arc::clone(counter));
+        let result = upload_stream(client, base_url, bucket, key, body).await;
+        if !changed.load(Ordering::SeqCst) {
+            return result;
+        }
+
+        attempt += 1;
+        log::warn!("{} changed while uploading (attempt {})", path.display(), attempt);
+        if policy == SourceChangePolicy::Abort || attempt > SOURCE_CHANGE_RETRIES {
+            return Err(format!("{}: {} changed while it was being uploaded", SOURCE_CHANGED, path.display()));
+        }
+        // Give whatever is writing the file a moment to finish
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+// A part of a large file the backend has stored
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LargeFilePart {
+    pub number: u32,
+    pub sha1: String,
+    pub size: u64,
+}
+
+#[derive(Deserialize)]
+struct LargeFileStarted {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct LargeFileParts {
+    parts: Vec<LargeFilePart>,
+}
+
+fn large_file_url(base_url: &str, bucket: &str, segments: &[&str]) -> Result<Url, String> {
+    let mut url = api_url(base_url, &[&["upload", "large"][..], segments].concat())?;
+    url.query_pairs_mut().append_pair("bucket", bucket);
+    Ok(url)
+}
+
+// Start uploading bucket/key a part at a time; returns the large file's id
+pub async fn start_large_file(
+    client: &reqwest::Client,
+    base_url: &str,
+    bucket: &str,
+    key: &str,
+    sha1: &str,
+) -> Result<String, String> {
+    let resp = client
+        .post(api_url(base_url, &["upload", "large", "start"])?)
+        .json(&serde_json::json!({ "bucket": bucket, "path": key, "sha1": sha1 }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to start uploading {}: {}", key, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Starting upload of {} returned status: {}", key, resp.status()));
+    }
+    let started: LargeFileStarted = resp
+        .json()
+        .await
+        .map_err(|e| format!("Invalid large file response for {}: {}", key, e))?;
+    Ok(started.id)
+}
+
+// Parts stored so far, or None once the large file is gone (finished, cancelled or
+// expired) and has to be started over
+pub async fn large_file_parts(
+    client: &reqwest::Client,
+    base_url: &str,
+    bucket: &str,
+    id: &str,
+) -> Result<Option<Vec<LargeFilePart>>, String> {
+    let resp = client
+        .get(large_file_url(base_url, bucket, &[id, "parts"])?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to list uploaded parts: {}", e))?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !resp.status().is_success() {
+        return Err(format!("Listing uploaded parts returned status: {}", resp.status()));
+    }
+    let listed: LargeFileParts = resp
+        .json()
+        .await
+        .map_err(|e| format!("Invalid parts response: {}", e))?;
+    Ok(Some(listed.parts))
+}
+
+// Store part `part.number` of a large file; the backend checks the body against
+// `part.sha1`
+pub async fn upload_large_file_part(
+    client: &reqwest::Client,
+    base_url: &str,
+    bucket: &str,
+    id: &str,
+    part: &LargeFilePart,
+    body: reqwest::Body,
+) -> Result<(), String> {
+    let number = part.number.to_string();
+    let resp = client
+        .put(large_file_url(base_url, bucket, &[id, "parts", &number])?)
+        .header("X-Bz-Content-Sha1", &part.sha1)
+        .header(reqwest::header::CONTENT_LENGTH, part.size)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to upload part {}: {}", part.number, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Uploading part {} returned status: {}", part.number, resp.status()));
+    }
+    Ok(())
+}
+
+// Assemble a large file's parts into the object
+pub async fn finish_large_file(client: &reqwest::Client, base_url: &str, bucket: &str, id: &str) -> Result<(), String> {
+    let resp = client
+        .post(large_file_url(base_url, bucket, &[id, "finish"])?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to finish large file: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Finishing large file returned status: {}", resp.status()));
+    }
+    Ok(())
+}
+
+// Discard an unfinished large file and the parts stored for it
+pub async fn cancel_large_file(client: &reqwest::Client, base_url: &str, bucket: &str, id: &str) -> Result<(), String> {
+    let resp = client
+        .delete(large_file_url(base_url, bucket, &[id])?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to cancel large file: {}", e))?;
+    if !resp.status().is_success() && resp.status() != reqwest::StatusCode::NOT_FOUND {
+        return Err(format!("Cancelling large file returned status: {}", resp.status()));
+    }
+    Ok(())
+}
+
+// Delete the latest version of bucket/key
+pub async fn delete_object(client: &reqwest::Client, base_url: &str, bucket: &str, key: &str) -> Result<(), String> {
+    let resp = client
+        .delete(api_url(base_url, &["delete", bucket, key])?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to delete {}: {}", key, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Deleting {} returned status: {}", key, resp.status()));
+    }
+    Ok(())
+}
+
+// Hashes B2 stored for an object, as returned by GET /api/checksums/{bucket}/{key}
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct StoredChecksums {
+    pub size: i64,
+    // Upload time in Unix milliseconds
+    pub timestamp: i64,
+    #[serde(rename = "SHA1", default)]
+    pub sha1: String,
+}
+
+pub async fn object_checksums(
+    client: &reqwest::Client,
+    base_url: &str,
+    bucket: &str,
+    key: &str,
+) -> Result<StoredChecksums, String> {
+    let resp = client
+        .get(api_url(base_url, &["checksums", bucket, key])?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get checksums of {}: {}", key, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Getting checksums of {} returned status: {}", key, resp.status()));
+    }
+    resp.json()
+        .await
+        .map_err(|e| format!("Invalid checksums response for {}: {}", key, e))
+}
+
+// Stop the backend accepting webview session tokens older than `generation`
+pub async fn revoke_sessions(client: &reqwest::Client, base_url: &str, generation: u64) -> Result<(), String> {
+    let resp = client
+        .post(api_url(base_url, &["session", "revoke"])?)
+        .json(&serde_json::json!({ "min_generation": generation }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to revoke sessions: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Revoking sessions returned status: {}", resp.status()));
+    }
+    Ok(())
+}
@@ -0,0 +1,403 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::{copy_urls, emit_to_main, log_files, AppState};
+
+// Lines kept across all sidecars; the oldest go first
+const CAPACITY: usize = 5000;
+
+// New lines are sent to the frontend at most this often, batched
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+// Lines returned by get_backend_logs when no limit is given
+const DEFAULT_LIMIT: usize = 500;
+
+// Lines a sidecar may log per second; the rest of that second is suppressed, apart
+// from every SAMPLE_EVERY-th so the log still shows what the flood is
+const LINES_PER_SEC: u32 = 200;
+const SAMPLE_EVERY: u64 = 1000;
+
+// Longer lines are cut, e.g. a sidecar dumping a response body
+const MAX_LINE_BYTES: usize = 16 * 1024;
+
+// Lines waiting for the next `backend-log` batch; the oldest are dropped beyond this,
+// and the frontend can fetch them with get_backend_logs
+const MAX_PENDING: usize = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl From<Level> for log::Level {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Debug => log::Level::Debug,
+            Level::Info => log::Level::Info,
+            Level::Warn => log::Level::Warn,
+            Level::Error => log::Level::Error,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LogLine {
+    // Increases with every line, so the frontend can ask for what it hasn't seen
+    pub seq: u64,
+    // Unix milliseconds
+    pub at: i64,
+    pub profile: String,
+    pub level: Level,
+    pub stream: Stream,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub message: String,
+    // The rest of a JSON log record's attributes
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LogFilter {
+    pub profile: Option<String>,
+    pub min_level: Option<Level>,
+    // Case-insensitive text the line must contain
+    pub contains: Option<String>,
+    // Only lines after this seq
+    pub after_seq: Option<u64>,
+}
+
+impl LogFilter {
+    fn matches(&self, line: &LogLine, contains: Option<&str>) -> bool {
+        self.profile.as_ref().map_or(true, |profile| *profile == line.profile)
+            && self.min_level.map_or(true, |level| line.level >= level)
+            && self.after_seq.map_or(true, |seq| line.seq > seq)
+            && contains.map_or(true, |text| line.text().to_lowercase().contains(text))
+    }
+}
+
+impl LogLine {
+    // The line as written to the Rust log: message, then fields as key=value
+    fn text(&self) -> String {
+        let mut text = match &self.target {
+            Some(target) => format!("{}: {}", target, self.message),
+            None => self.message.clone(),
+        };
+        for (key, value) in &self.fields {
+            // Strings without their JSON quotes
+            match value {
+                Value::String(value) => text.push_str(&format!(" {}={}", key, value)),
+                value => text.push_str(&format!(" {}={}", key, value)),
+            }
+        }
+        text
+    }
+}
+
+// Sent as `backend-log-flood` when a sidecar starts logging faster than
+// LINES_PER_SEC, and again once it calms down
+#[derive(Clone, Debug, Serialize)]
+pub struct LogFlood {
+    pub profile: String,
+    pub flooding: bool,
+    // Lines suppressed during the flood; set when it ends
+    pub suppressed: u64,
+}
+
+// How fast a sidecar is logging, in one-second windows
+struct Flood {
+    window_start: Instant,
+    lines: u32,
+    // Suppressed in the current window, for its marker line
+    suppressed: u64,
+    flooding: bool,
+    // Suppressed since the flood started
+    total: u64,
+}
+
+impl Flood {
+    fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            lines: 0,
+            suppressed: 0,
+            flooding: false,
+            total: 0,
+        }
+    }
+}
+
+// Whether a line is kept, and what to report first
+struct Admission {
+    keep: bool,
+    // Lines suppressed in the window that just ended
+    marker: Option<u64>,
+    event: Option<LogFlood>,
+}
+
+// Recent sidecar output, for a live console in the frontend. The Rust log gets
+// the same lines; a sidecar logging faster than LINES_PER_SEC is throttled in both.
+pub struct BackendLogs {
+    lines: Mutex<VecDeque<LogLine>>,
+    next_seq: AtomicU64,
+    // Lines not yet sent as `backend-log`
+    pending: Mutex<Vec<LogLine>>,
+    floods: Mutex<HashMap<String, Flood>>,
+}
+
+impl BackendLogs {
+    pub fn new() -> Self {
+        Self {
+            lines: Mutex::new(VecDeque::with_capacity(CAPACITY)),
+            next_seq: AtomicU64::new(1),
+            pending: Mutex::new(Vec::new()),
+            floods: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn admit(&self, profile: &str) -> Admission {
+        let mut floods = self.floods.lock().unwrap();
+        let flood = floods.entry(profile.to_string()).or_insert_with(Flood::new);
+        let mut admission = Admission {
+            keep: true,
+            marker: None,
+            event: None,
+        };
+        if flood.window_start.elapsed() >= Duration::from_secs(1) {
+            admission.marker = Some(std::mem::take(&mut flood.suppressed)).filter(|n| *n > 0);
+            // A whole window without suppression ends the flood
+            if admission.marker.is_none() && flood.flooding {
+                flood.flooding = false;
+                admission.event = Some(LogFlood {
+                    profile: profile.to_string(),
+                    flooding: false,
+                    suppressed: std::mem::take(&mut flood.total),
+                });
+            }
+            flood.window_start = Instant::now();
+            flood.lines = 0;
+        }
+
+        flood.lines += 1;
+        if flood.lines > LINES_PER_SEC {
+            flood.suppressed += 1;
+            flood.total += 1;
+            if !flood.flooding {
+                flood.flooding = true;
+                admission.event = Some(LogFlood {
+                    profile: profile.to_string(),
+                    flooding: true,
+                    suppressed: 0,
+                });
+            }
+            admission.keep = flood.total % SAMPLE_EVERY == 0;
+        }
+        admission
+    }
+}
+
+// Cut a line down to MAX_LINE_BYTES, on a character boundary
+fn truncate(line: &str) -> std::borrow::Cow<'_, str> {
+    if line.len() <= MAX_LINE_BYTES {
+        return line.into();
+    }
+    let mut end = MAX_LINE_BYTES;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}… ({} bytes cut)", &line[..end], line.len() - end).into()
+}
+
+// Keys of a JSON log record that aren't fields: slog's own, and what names the
+// component that logged it
+const TIME_KEYS: [&str; 2] = ["time", "ts"];
+const LEVEL_KEYS: [&str; 2] = ["level", "lvl"];
+const MESSAGE_KEYS: [&str; 2] = ["msg", "message"];
+const TARGET_KEYS: [&str; 3] = ["target", "component", "logger"];
+
+fn take_string(record: &mut Map<String, Value>, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| match record.remove(*key)? {
+        Value::String(value) => Some(value),
+        value => Some(value.to_string()),
+    })
+}
+
+fn parse_level(level: &str) -> Level {
+    // slog writes levels above or below the named ones as e.g. "INFO+2"
+    let name = level.split(['+', '-']).next().unwrap_or(level);
+    match name.to_ascii_lowercase().as_str() {
+        "debug" | "trace" => Level::Debug,
+        "warn" | "warning" => Level::Warn,
+        "error" | "fatal" | "panic" => Level::Error,
+        _ => Level::Info,
+    }
+}
+
+// Split a line of sidecar output into a log line. The sidecar logs JSON records
+// to stderr; anything else is kept as it is, at the level of its stream.
+fn parse(seq: u64, profile: &str, stream: Stream, line: &str) -> LogLine {
+    let mut entry = LogLine {
+        seq,
+        at: chrono::Utc::now().timestamp_millis(),
+        profile: profile.to_string(),
+        level: match stream {
+            Stream::Stdout => Level::Info,
+            Stream::Stderr => Level::Warn,
+        },
+        stream,
+        target: None,
+        message: line.to_string(),
+        fields: Map::new(),
+    };
+    let Ok(Value::Object(mut record)) = serde_json::from_str::<Value>(line) else {
+        return entry;
+    };
+    if let Some(level) = take_string(&mut record, &LEVEL_KEYS) {
+        entry.level = parse_level(&level);
+    }
+    if let Some(at) = take_string(&mut record, &TIME_KEYS)
+        .and_then(|time| chrono::DateTime::parse_from_rfc3339(&time).ok())
+    {
+        entry.at = at.timestamp_millis();
+    }
+    entry.target = take_string(&mut record, &TARGET_KEYS);
+    entry.message = take_string(&mut record, &MESSAGE_KEYS).unwrap_or_default();
+    entry.fields = record;
+    entry
+}
+
+// Log a line of a sidecar's output, keep it and send it to the frontend with the
+// next batch, unless the sidecar is flooding its log
+pub fn record(app: &AppHandle, state: &Arc<AppState>, profile: &str, stream: Stream, line: &str) {
+    let line = line.trim_end();
+    if line.is_empty() {
+        return;
+    }
+    let logs = &state.backend_logs;
+    let admission = logs.admit(profile);
+    if let Some(event) = admission.event {
+        if event.flooding {
+            log::warn!("Sidecar for profile {} is flooding its log; suppressing lines", profile);
+        }
+        emit_to_main(app, "backend-log-flood", event);
+    }
+    if let Some(suppressed) = admission.marker {
+        let mut marker = parse(logs.next_seq.fetch_add(1, Ordering::SeqCst), profile, stream, "");
+        marker.level = Level::Warn;
+        marker.message = format!("[{} lines suppressed]", suppressed);
+        keep(app, state, marker);
+    }
+    if admission.keep {
+        let entry = parse(logs.next_seq.fetch_add(1, Ordering::SeqCst), profile, stream, &truncate(line));
+        keep(app, state, entry);
+    }
+}
+
+// Write a line to the Rust log, the recent lines and the next batch
+fn keep(app: &AppHandle, state: &Arc<AppState>, entry: LogLine) {
+    let logs = &state.backend_logs;
+    let profile = &entry.profile;
+    log::log!(
+        target: log_files::SIDECAR_TARGET,
+        entry.level.into(),
+        "[bb-stream:{}] {}",
+        profile,
+        entry.text()
+    );
+    {
+        let mut lines = logs.lines.lock().unwrap();
+        if lines.len() == CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(entry.clone());
+    }
+
+    let mut pending = logs.pending.lock().unwrap();
+    if pending.len() >= MAX_PENDING {
+        let excess = pending.len() + 1 - MAX_PENDING;
+        pending.drain(..excess);
+    }
+    pending.push(entry);
+    // The first line of a batch schedules sending it
+    if pending.len() == 1 {
+        let app = app.clone();
+        let state = Arc::clone(state);
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(EMIT_INTERVAL).await;
+            let batch = std::mem::take(&mut *state.backend_logs.pending.lock().unwrap());
+            emit_to_main(&app, "backend-log", batch);
+        });
+    }
+}
+
+// The newest `limit` lines matching `filter`, oldest first
+#[tauri::command]
+pub fn get_backend_logs(
+    state: tauri::State<Arc<AppState>>,
+    filter: Option<LogFilter>,
+    limit: Option<usize>,
+) -> Vec<LogLine> {
+    let filter = filter.unwrap_or_default();
+    let contains = filter.contains.as_ref().map(|text| text.to_lowercase());
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    let lines = state.backend_logs.lines.lock().unwrap();
+    let mut matching: Vec<LogLine> = lines
+        .iter()
+        .rev()
+        .filter(|line| filter.matches(line, contains.as_deref()))
+        .take(limit)
+        .cloned()
+        .collect();
+    matching.reverse();
+    matching
+}
+
+// Copy the lines with the given seqs to the clipboard, one per line with its time,
+// level and profile; returns how many were still in the buffer to copy
+#[tauri::command]
+pub fn copy_backend_logs(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    seqs: Vec<u64>,
+) -> Result<usize, AppError> {
+    let seqs: HashSet<u64> = seqs.into_iter().collect();
+    let text: Vec<String> = {
+        let lines = state.backend_logs.lines.lock().unwrap();
+        lines
+            .iter()
+            .filter(|line| seqs.contains(&line.seq))
+            .map(|line| {
+                let at = chrono::DateTime::from_timestamp_millis(line.at)
+                    .map(|at| at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+                    .unwrap_or_default();
+                let level = format!("{:?}", line.level).to_uppercase();
+                format!("{} {} [{}] {}", at, level, line.profile, line.text())
+            })
+            .collect()
+    };
+    let copied = text.len();
+    if copied == 0 {
+        return Err(AppError::invalid("Those lines are no longer in the log buffer"));
+    }
+    copy_urls::write_clipboard(&app, text.join("\n"))?;
+    Ok(copied)
+}
@@ -0,0 +1,385 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::{activity, backend, emit_to_main, AppState};
+
+const SCRAPE_INTERVAL: Duration = Duration::from_secs(10);
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Rates and percentiles are taken across this many scrapes, about a minute
+const WINDOW: usize = 7;
+
+// Aggregates kept for dashboards, about an hour
+const HISTORY: usize = 360;
+
+// The scraper's own requests, left out of the request figures
+const METRICS_ROUTE: &str = "/metrics";
+
+const REQUESTS: &str = "bbstream_http_requests_total";
+const DURATION_BUCKET: &str = "bbstream_http_request_duration_seconds_bucket";
+const DURATION_SUM: &str = "bbstream_http_request_duration_seconds_sum";
+const DURATION_COUNT: &str = "bbstream_http_request_duration_seconds_count";
+
+type Labels = BTreeMap<String, String>;
+
+// One line of the Prometheus text format
+struct Sample {
+    name: String,
+    labels: Labels,
+    value: f64,
+}
+
+struct Scrape {
+    // Unix milliseconds
+    at: i64,
+    samples: Vec<Sample>,
+}
+
+impl Scrape {
+    fn sum(&self, name: &str, filter: impl Fn(&Labels) -> bool) -> f64 {
+        self.samples
+            .iter()
+            .filter(|s| s.name == name && filter(&s.labels))
+            .map(|s| s.value)
+            .sum()
+    }
+
+    fn gauge(&self, name: &str) -> Option<f64> {
+        self.samples.iter().find(|s| s.name == name).map(|s| s.value)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RouteMetrics {
+    // Router pattern, e.g. /api/buckets/{name}/files
+    pub route: String,
+    pub requests_per_sec: f64,
+    // Responses with a 5xx status
+    pub errors_per_sec: f64,
+    pub mean_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+}
+
+// The backend's request rates and latencies over the last minute or so, with its
+// runtime gauges as of the latest scrape. Percentiles are None without requests.
+#[derive(Clone, Debug, Serialize)]
+pub struct BackendMetrics {
+    // Unix milliseconds
+    pub at: i64,
+    pub profile: String,
+    // What the rates cover; 0 until a second scrape came in
+    pub window_secs: f64,
+    pub requests_per_sec: f64,
+    pub errors_per_sec: f64,
+    pub bytes_in_per_sec: f64,
+    pub bytes_out_per_sec: f64,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub in_flight: Option<f64>,
+    pub goroutines: Option<f64>,
+    pub heap_bytes: Option<f64>,
+    pub websocket_clients: Option<f64>,
+    pub uptime_secs: Option<f64>,
+    // Busiest first
+    pub routes: Vec<RouteMetrics>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BackendMetricsReport {
+    pub current: Option<BackendMetrics>,
+    // Oldest first, one per scrape
+    pub history: Vec<BackendMetrics>,
+}
+
+// Recent scrapes of the active sidecar's /metrics and what was made of them
+pub struct Metrics {
+    scrapes: Mutex<VecDeque<Scrape>>,
+    history: Mutex<VecDeque<BackendMetrics>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            scrapes: Mutex::new(VecDeque::new()),
+            history: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+// Read a label set such as {route="/a",le="0.5"}, returning it and what follows
+fn parse_labels(body: &str) -> Option<(Labels, &str)> {
+    let mut labels = Labels::new();
+    let mut key = String::new();
+    let mut chars = body.char_indices();
+    loop {
+        let (i, c) = chars.next()?;
+        match c {
+            '}' => return Some((labels, &body[i + 1..])),
+            ',' | ' ' => {}
+            '=' => {
+                if chars.next()?.1 != '"' {
+                    return None;
+                }
+                let mut value = String::new();
+                loop {
+                    match chars.next()?.1 {
+                        '"' => break,
+                        '\\' => match chars.next()?.1 {
+                            'n' => value.push('\n'),
+                            c => value.push(c),
+                        },
+                        c => value.push(c),
+                    }
+                }
+                labels.insert(std::mem::take(&mut key), value);
+            }
+            c => key.push(c),
+        }
+    }
+}
+
+fn parse_value(value: &str) -> Option<f64> {
+    match value {
+        "+Inf" => Some(f64::INFINITY),
+        "-Inf" => Some(f64::NEG_INFINITY),
+        value => value.parse().ok(),
+    }
+}
+
+fn parse_line(line: &str) -> Option<Sample> {
+    let split = line.find(|c: char| c == '{' || c.is_whitespace())?;
+    let (name, rest) = line.split_at(split);
+    let (labels, rest) = match rest.strip_prefix('{') {
+        Some(body) => parse_labels(body)?,
+        None => (Labels::new(), rest),
+    };
+    // A timestamp may follow the value; the scrape time is used instead
+    let value = parse_value(rest.split_whitespace().next()?)?;
+    Some(Sample {
+        name: name.to_string(),
+        labels,
+        value,
+    })
+}
+
+fn parse(text: &str) -> Vec<Sample> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(parse_line)
+        .collect()
+}
+
+// Like Prometheus' histogram_quantile: find the bucket the rank falls in and
+// interpolate linearly inside it. `buckets` holds (upper bound, cumulative count),
+// ascending, ending with +Inf.
+fn quantile(q: f64, buckets: &[(f64, f64)]) -> Option<f64> {
+    let total = buckets.last()?.1;
+    if total <= 0.0 {
+        return None;
+    }
+    let rank = q * total;
+    let (mut lower, mut below) = (0.0, 0.0);
+    for &(bound, count) in buckets {
+        if count >= rank {
+            // Past the largest finite bound all that is known is the bound itself
+            if bound.is_infinite() {
+                return Some(lower);
+            }
+            let in_bucket = count - below;
+            let fraction = if in_bucket > 0.0 { (rank - below) / in_bucket } else { 0.0 };
+            return Some(lower + (bound - lower) * fraction);
+        }
+        lower = bound;
+        below = count;
+    }
+    None
+}
+
+fn ms(seconds: Option<f64>) -> Option<f64> {
+    seconds.map(|s| s * 1000.0)
+}
+
+// Latency histogram counts gained between two scrapes, for one route or all of them
+fn bucket_deltas(newest: &Scrape, oldest: &Scrape, route: Option<&str>) -> Vec<(f64, f64)> {
+    let matches = |labels: &Labels| match route {
+        Some(route) => labels.get("route").is_some_and(|r| r == route),
+        None => labels.get("route").map_or(true, |r| r != METRICS_ROUTE),
+    };
+    let bounds: BTreeSet<String> = newest
+        .samples
+        .iter()
+        .filter(|s| s.name == DURATION_BUCKET && matches(&s.labels))
+        .filter_map(|s| s.labels.get("le").cloned())
+        .collect();
+    let mut buckets: Vec<(f64, f64)> = bounds
+        .iter()
+        .filter_map(|le| {
+            let bound = parse_value(le)?;
+            let at = |scrape: &Scrape| {
+                scrape.sum(DURATION_BUCKET, |labels| matches(labels) && labels.get("le") == Some(le))
+            };
+            Some((bound, (at(newest) - at(oldest)).max(0.0)))
+        })
+        .collect();
+    buckets.sort_by(|a, b| a.0.total_cmp(&b.0));
+    buckets
+}
+
+fn aggregate(profile: &str, newest: &Scrape, oldest: &Scrape) -> BackendMetrics {
+    let window_secs = (newest.at - oldest.at) as f64 / 1000.0;
+    let per_sec = |delta: f64| if window_secs > 0.0 { delta.max(0.0) / window_secs } else { 0.0 };
+    let delta = |name: &str, filter: &dyn Fn(&Labels) -> bool| newest.sum(name, filter) - oldest.sum(name, filter);
+    let not_scraper = |labels: &Labels| labels.get("route").map_or(true, |r| r != METRICS_ROUTE);
+    let is_error = |labels: &Labels| labels.get("status").is_some_and(|s| s.starts_with('5'));
+
+    let routes: BTreeSet<&str> = newest
+        .samples
+        .iter()
+        .filter(|s| s.name == REQUESTS)
+        .filter_map(|s| s.labels.get("route").map(String::as_str))
+        .filter(|route| *route != METRICS_ROUTE)
+        .collect();
+    let mut route_metrics: Vec<RouteMetrics> = routes
+        .into_iter()
+        .filter_map(|route| {
+            let this = |labels: &Labels| labels.get("route").is_some_and(|r| r == route);
+            let requests = delta(REQUESTS, &this);
+            if requests <= 0.0 {
+                return None;
+            }
+            let count = delta(DURATION_COUNT, &this);
+            let buckets = bucket_deltas(newest, oldest, Some(route));
+            Some(RouteMetrics {
+                route: route.to_string(),
+                requests_per_sec: per_sec(requests),
+                errors_per_sec: per_sec(delta(REQUESTS, &|labels| this(labels) && is_error(labels))),
+                mean_ms: (count > 0.0).then(|| delta(DURATION_SUM, &this) / count * 1000.0),
+                p50_ms: ms(quantile(0.5, &buckets)),
+                p95_ms: ms(quantile(0.95, &buckets)),
+                p99_ms: ms(quantile(0.99, &buckets)),
+            })
+        })
+        .collect();
+    route_metrics.sort_by(|a, b| b.requests_per_sec.total_cmp(&a.requests_per_sec));
+
+    let buckets = bucket_deltas(newest, oldest, None);
+    BackendMetrics {
+        at: newest.at,
+        profile: profile.to_string(),
+        window_secs,
+        requests_per_sec: per_sec(delta(REQUESTS, &not_scraper)),
+        errors_per_sec: per_sec(delta(REQUESTS, &|labels| not_scraper(labels) && is_error(labels))),
+        bytes_in_per_sec: per_sec(delta("bbstream_http_request_bytes_total", &|_| true)),
+        bytes_out_per_sec: per_sec(delta("bbstream_http_response_bytes_total", &|_| true)),
+        p50_ms: ms(quantile(0.5, &buckets)),
+        p95_ms: ms(quantile(0.95, &buckets)),
+        p99_ms: ms(quantile(0.99, &buckets)),
+        in_flight: newest.gauge("bbstream_http_requests_in_flight"),
+        goroutines: newest.gauge("bbstream_goroutines"),
+        heap_bytes: newest.gauge("bbstream_heap_bytes"),
+        websocket_clients: newest.gauge("bbstream_websocket_clients"),
+        uptime_secs: newest.gauge("bbstream_uptime_seconds"),
+        routes: route_metrics,
+    }
+}
+
+async fn scrape(state: &AppState) -> Result<Scrape, String> {
+    let base_url = state.base_url();
+    let resp = backend::shared_client(&base_url)
+        .get(format!("{}/metrics", base_url))
+        .timeout(SCRAPE_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("/metrics returned status: {}", resp.status()));
+    }
+    let text = resp.text().await.map_err(|e| e.to_string())?;
+    Ok(Scrape {
+        at: chrono::Utc::now().timestamp_millis(),
+        samples: parse(&text),
+    })
+}
+
+// Scrape the active sidecar's /metrics while it is healthy, emitting
+// `backend-metrics` with each aggregate
+pub fn spawn_monitor(app: AppHandle, state: Arc<AppState>) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_profile = String::new();
+        // Older backends don't serve metrics; say so once per profile
+        let mut warned = false;
+
+        loop {
+            if state.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            activity::sleep(&app, &state, SCRAPE_INTERVAL).await;
+            if !state.is_healthy() {
+                continue;
+            }
+
+            let profile = state.active_profile.lock().unwrap().clone();
+            let latest = match scrape(&state).await {
+                Ok(latest) => latest,
+                Err(e) => {
+                    if !warned {
+                        log::warn!("Failed to scrape backend metrics: {}", e);
+                        warned = true;
+                    }
+                    continue;
+                }
+            };
+            warned = false;
+
+            let metrics = {
+                let mut scrapes = state.backend_metrics.scrapes.lock().unwrap();
+                // Counters start over with a new profile or a restarted sidecar
+                let restarted = scrapes.back().is_some_and(|previous| {
+                    latest.gauge("bbstream_uptime_seconds") < previous.gauge("bbstream_uptime_seconds")
+                });
+                if profile != last_profile || restarted {
+                    scrapes.clear();
+                    last_profile = profile.clone();
+                }
+                scrapes.push_back(latest);
+                while scrapes.len() > WINDOW {
+                    scrapes.pop_front();
+                }
+                let newest = scrapes.back().expect("just pushed");
+                let oldest = scrapes.front().expect("just pushed");
+                aggregate(&profile, newest, oldest)
+            };
+
+            let mut history = state.backend_metrics.history.lock().unwrap();
+            history.push_back(metrics.clone());
+            while history.len() > HISTORY {
+                history.pop_front();
+            }
+            drop(history);
+            emit_to_main(&app, "backend-metrics", metrics);
+        }
+    });
+}
+
+// The latest aggregate and, when `history` is set, the ones before it
+#[tauri::command]
+pub fn get_backend_metrics(state: tauri::State<Arc<AppState>>, history: Option<bool>) -> BackendMetricsReport {
+    let all = state.backend_metrics.history.lock().unwrap();
+    BackendMetricsReport {
+        current: all.back().cloned(),
+        history: if history.unwrap_or(false) {
+            all.iter().cloned().collect()
+        } else {
+            Vec::new()
+        },
+    }
+}
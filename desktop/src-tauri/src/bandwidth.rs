@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use chrono::{Datelike, Timelike};
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::transfer_queue::Direction;
+use crate::{store, test_harness, AppState};
+
+const SETTINGS_FILE: &str = "bandwidth.json";
+
+// Lower limits would stall a transfer for minutes per chunk
+const MIN_LIMIT: u64 = 16 * 1024;
+
+// Caps on how fast the shell's own transfers move data. The caps can apply only
+// during work hours, e.g. to keep video calls smooth.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandwidthSettings {
+    // Bytes per second across all transfers in each direction; None is unlimited
+    pub upload_limit: Option<u64>,
+    pub download_limit: Option<u64>,
+    pub work_hours_only: bool,
+    // Minutes after local midnight; the window wraps past midnight when end < start
+    pub start_minute: u32,
+    pub end_minute: u32,
+    pub weekdays_only: bool,
+}
+
+impl Default for BandwidthSettings {
+    fn default() -> Self {
+        Self {
+            upload_limit: None,
+            download_limit: None,
+            work_hours_only: false,
+            start_minute: 9 * 60,
+            end_minute: 17 * 60,
+            weekdays_only: true,
+        }
+    }
+}
+
+impl BandwidthSettings {
+    fn in_effect(&self, now: chrono::DateTime<chrono::Local>) -> bool {
+        if !self.work_hours_only {
+            return true;
+        }
+        if self.weekdays_only && now.weekday().number_from_monday() > 5 {
+            return false;
+        }
+        let minute = now.hour() * 60 + now.minute();
+        if self.start_minute <= self.end_minute {
+            (self.start_minute..self.end_minute).contains(&minute)
+        } else {
+            minute >= self.start_minute || minute < self.end_minute
+        }
+    }
+
+    fn limit(&self, direction: Direction) -> Option<u64> {
+        match direction {
+            Direction::Upload => self.upload_limit,
+            Direction::Download => self.download_limit,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.start_minute >= 24 * 60 || self.end_minute >= 24 * 60 {
+            return Err("Work hours must start and end within the day".to_string());
+        }
+        [self.upload_limit, self.download_limit].into_iter().flatten().try_for_each(validate_limit)
+    }
+}
+
+fn validate_limit(limit: u64) -> Result<(), String> {
+    if limit < MIN_LIMIT {
+        return Err(format!("Bandwidth limits must be at least {} KiB/s", MIN_LIMIT / 1024));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct BandwidthStatus {
+    #[serde(flatten)]
+    pub settings: BandwidthSettings,
+    // Whether the caps apply right now
+    pub in_effect: bool,
+}
+
+// Bytes that may be sent, refilled at the limit's rate. Taking more than there is
+// runs into debt, which later takers wait out, so concurrent transfers share it.
+struct TokenBucket {
+    tokens: f64,
+    at: Instant,
+}
+
+impl TokenBucket {
+    fn new() -> Self {
+        Self {
+            tokens: 0.0,
+            at: Instant::now(),
+        }
+    }
+
+    // How long to wait before sending `bytes` under `limit`
+    fn take(&mut self, bytes: u64, limit: Option<u64>) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.at).as_secs_f64();
+        self.at = now;
+        let Some(rate) = limit.map(|limit| limit as f64) else {
+            self.tokens = 0.0;
+            return Duration::ZERO;
+        };
+        // At most a second's worth builds up while idle
+        self.tokens = (self.tokens + elapsed * rate).min(rate) - bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+pub struct Bandwidth {
+    settings: Mutex<BandwidthSettings>,
+    uploads: Mutex<TokenBucket>,
+    downloads: Mutex<TokenBucket>,
+    // Limits set on single transfers, by transfer id
+    transfers: Mutex<HashMap<String, (u64, TokenBucket)>>,
+}
+
+impl Bandwidth {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(BandwidthSettings::default()),
+            uploads: Mutex::new(TokenBucket::new()),
+            downloads: Mutex::new(TokenBucket::new()),
+            transfers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn reserve(&self, direction: Direction, id: Option<&str>, bytes: u64) -> Duration {
+        let settings = *self.settings.lock().unwrap();
+        let limit = settings.limit(direction).filter(|_| settings.in_effect(test_harness::now()));
+        let global = match direction {
+            Direction::Upload => &self.uploads,
+            Direction::Download => &self.downloads,
+        };
+        let wait = global.lock().unwrap().take(bytes, limit);
+        let mut transfers = self.transfers.lock().unwrap();
+        match id.and_then(|id| transfers.get_mut(id)) {
+            Some((limit, bucket)) => wait.max(bucket.take(bytes, Some(*limit))),
+            None => wait,
+        }
+    }
+}
+
+// Paces one transfer to the global cap for its direction and to its own limit
+#[derive(Clone)]
+pub struct Throttle {
+    state: Arc<AppState>,
+    direction: Direction,
+    id: Option<String>,
+}
+
+impl Throttle {
+    pub fn new(state: &Arc<AppState>, direction: Direction, id: Option<&str>) -> Self {
+        Self {
+            state: Arc::clone(state),
+            direction,
+            id: id.map(str::to_string),
+        }
+    }
+
+    pub async fn take(&self, bytes: usize) {
+        wait(&self.state, self.direction, self.id.as_deref(), bytes).await;
+    }
+}
+
+// Wait until `bytes` more of transfer `id` may go through
+pub async fn wait(state: &AppState, direction: Direction, id: Option<&str>, bytes: usize) {
+    let wait = state.bandwidth.reserve(direction, id, bytes as u64);
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+// Hold each chunk of a byte stream back until the throttle lets it through
+pub fn throttled<S, E>(stream: S, throttle: Throttle) -> impl Stream<Item = Result<Bytes, E>> + Send + 'static
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Send + 'static,
+{
+    stream.then(move |chunk| {
+        let throttle = throttle.clone();
+        async move {
+            if let Ok(bytes) = &chunk {
+                throttle.take(bytes.len()).await;
+            }
+            chunk
+        }
+    })
+}
+
+// Drop a finished transfer's own limit
+pub fn forget(state: &AppState, id: &str) {
+    state.bandwidth.transfers.lock().unwrap().remove(id);
+}
+
+pub fn restore(app: &AppHandle, state: &AppState) {
+    let settings: BandwidthSettings = match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load bandwidth limits: {}", e);
+            return;
+        }
+    };
+    match settings.validate() {
+        Ok(()) => *state.bandwidth.settings.lock().unwrap() = settings,
+        Err(e) => log::warn!("Ignoring saved bandwidth limits: {}", e),
+    }
+}
+
+#[tauri::command]
+pub fn get_bandwidth_limit(state: tauri::State<Arc<AppState>>) -> BandwidthStatus {
+    let settings = *state.bandwidth.settings.lock().unwrap();
+    BandwidthStatus {
+        settings,
+        in_effect: settings.in_effect(test_harness::now()),
+    }
+}
+
+// Change the global caps; running transfers slow down or speed up right away
+#[tauri::command]
+pub fn set_bandwidth_limit(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    limits: BandwidthSettings,
+) -> Result<BandwidthStatus, AppError> {
+    limits.validate().map_err(AppError::invalid)?;
+    store::save_json(&store::data_file(&app, SETTINGS_FILE)?, &limits)?;
+    *state.bandwidth.settings.lock().unwrap() = limits;
+    log::info!(
+        "Bandwidth limits set to {:?} up, {:?} down",
+        limits.upload_limit,
+        limits.download_limit
+    );
+    Ok(get_bandwidth_limit(state))
+}
+
+// Cap a single transfer, on top of the global cap; None lifts its own limit. Lasts
+// until the transfer finishes.
+#[tauri::command]
+pub fn set_transfer_bandwidth_limit(
+    state: tauri::State<Arc<AppState>>,
+    id: String,
+    limit: Option<u64>,
+) -> Result<(), AppError> {
+    let mut transfers = state.bandwidth.transfers.lock().unwrap();
+    match limit {
+        Some(limit) => {
+            validate_limit(limit).map_err(AppError::invalid)?;
+            transfers.insert(id, (limit, TokenBucket::new()));
+        }
+        None => {
+            transfers.remove(&id);
+        }
+    }
+    Ok(())
+}
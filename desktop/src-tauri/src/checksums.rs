@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::{backend, print, version, AppState};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hashes {
+    pub sha1: String,
+    pub sha256: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ObjectChecksums {
+    pub bucket: String,
+    pub key: String,
+    // SHA-1 B2 stored at upload; None for large files uploaded without one, or while
+    // the backend is unavailable
+    pub stored_sha1: Option<String>,
+    // Downloaded copy the local hashes were computed from, if any
+    pub local_path: Option<String>,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+    // Whether the computed SHA-1 matches the stored one, when both are known
+    pub verified: Option<bool>,
+}
+
+// Hashes computed so far, keyed by what they were computed from plus a stamp
+// (size and modification or upload time) that changes when the content does
+pub struct Checksums {
+    computed: Mutex<HashMap<String, (String, Hashes)>>,
+}
+
+impl Checksums {
+    pub fn new() -> Self {
+        Self {
+            computed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, source: &str, stamp: &str) -> Option<Hashes> {
+        let computed = self.computed.lock().unwrap();
+        computed.get(source).filter(|(s, _)| s == stamp).map(|(_, hashes)| hashes.clone())
+    }
+
+    fn remember(&self, source: String, stamp: String, hashes: Hashes) {
+        self.computed.lock().unwrap().insert(source, (stamp, hashes));
+    }
+}
+
+#[derive(Default)]
+struct Hasher {
+    sha1: Sha1,
+    sha256: Sha256,
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        self.sha1.update(data);
+        self.sha256.update(data);
+    }
+
+    fn finish(self) -> Hashes {
+        Hashes {
+            sha1: hex::encode(self.sha1.finalize()),
+            sha256: hex::encode(self.sha256.finalize()),
+        }
+    }
+}
+
+fn hash_file(path: &Path) -> Result<Hashes, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Hasher::default();
+    let mut buf = vec![0u8; 256 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if n == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+// Hash an object by streaming it from the backend without keeping a copy
+async fn hash_remote(base_url: &str, bucket: &str, key: &str) -> Result<Hashes, String> {
+    let client = backend::client_builder(base_url)
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut resp = client
+        .get(backend::download_url(base_url, bucket, key)?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", key, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Downloading {} returned status: {}", key, resp.status()));
+    }
+    let mut hasher = Hasher::default();
+    while let Some(chunk) = resp.chunk().await.map_err(|e| format!("Failed to download {}: {}", key, e))? {
+        hasher.update(&chunk);
+    }
+    Ok(hasher.finish())
+}
+
+// Hashes of the downloaded copy at `path`, computed once per version of the file
+pub async fn local_hashes(state: &AppState, path: &Path) -> Result<Hashes, String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis());
+    let source = path.to_string_lossy().to_string();
+    let stamp = format!("{}:{}", metadata.len(), modified);
+    if let Some(hashes) = state.checksums.get(&source, &stamp) {
+        return Ok(hashes);
+    }
+
+    let owned = path.to_path_buf();
+    let hashes = tauri::async_runtime::spawn_blocking(move || hash_file(&owned))
+        .await
+        .map_err(|e| format!("Failed to hash {}: {}", path.display(), e))??;
+    state.checksums.remember(source, stamp, hashes.clone());
+    Ok(hashes)
+}
+
+// Stored hashes of an object, plus SHA-1 and SHA-256 computed from a downloaded copy
+// so files sent to third parties can be verified. Without a local copy the hashes are
+// only computed when `compute` is set, by streaming the whole object once.
+#[tauri::command]
+pub async fn get_object_checksums(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    bucket: String,
+    key: String,
+    compute: Option<bool>,
+) -> Result<ObjectChecksums, AppError> {
+    let base_url = state.base_url();
+    let supported = version::ensure_capability(&state, "checksums", |c| c.checksums).is_ok();
+    let stored = if state.is_healthy() && supported {
+        let client = backend::client_builder(&base_url)
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Some(backend::object_checksums(&client, &base_url, &bucket, &key).await?)
+    } else {
+        None
+    };
+
+    let local_path = print::cached_copy_path(&app, &bucket, &key)?;
+    let local_path = local_path.is_file().then_some(local_path);
+    let hashes = match (&local_path, &stored) {
+        (Some(path), _) => Some(local_hashes(&state, path).await?),
+        (None, Some(stored)) if compute.unwrap_or(false) => {
+            let source = format!("{}/{}", bucket, key);
+            let stamp = format!("{}:{}", stored.size, stored.timestamp);
+            match state.checksums.get(&source, &stamp) {
+                Some(hashes) => Some(hashes),
+                None => {
+                    log::info!("Hashing {}/{} from the backend", bucket, key);
+                    let hashes = hash_remote(&base_url, &bucket, &key).await?;
+                    state.checksums.remember(source, stamp, hashes.clone());
+                    Some(hashes)
+                }
+            }
+        }
+        (None, None) if compute.unwrap_or(false) => {
+            return Err(AppError::BackendUnreachable {
+                message: "Backend is unavailable and there is no downloaded copy to hash".to_string(),
+            })
+        }
+        (None, _) => None,
+    };
+
+    let stored_sha1 = stored.map(|s| s.sha1).filter(|sha1| !sha1.is_empty());
+    let verified = match (&stored_sha1, &hashes) {
+        (Some(stored), Some(hashes)) => Some(stored.eq_ignore_ascii_case(&hashes.sha1)),
+        _ => None,
+    };
+    Ok(ObjectChecksums {
+        bucket,
+        key,
+        stored_sha1,
+        local_path: local_path.map(|p| p.to_string_lossy().to_string()),
+        sha1: hashes.as_ref().map(|h| h.sha1.clone()),
+        sha256: hashes.map(|h| h.sha256),
+        verified,
+    })
+}
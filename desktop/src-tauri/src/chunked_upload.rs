@@ -0,0 +1,340 @@
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tauri::AppHandle;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::backend::{self, LargeFilePart};
+use crate::bandwidth::{self, Throttle};
+use crate::{checksums, store, AppState};
+
+// Where interrupted part-by-part uploads got to
+const CHECKPOINTS_FILE: &str = "chunked_uploads.json";
+
+// Files at least this big go up a part at a time when the backend supports it
+pub const CHUNKED_THRESHOLD: u64 = 256 * 1024 * 1024;
+
+// Parts are read into memory whole. Above B2's 5 MB minimum, and small enough to
+// get through the backend's request timeout on a slow link.
+const MIN_PART_SIZE: u64 = 8 * 1024 * 1024;
+
+// B2 takes at most this many parts per file
+const MAX_PARTS: u64 = 10_000;
+
+// Tries at one part before the upload fails
+const PART_ATTEMPTS: u32 = 5;
+
+// A part is sent in pieces this big so progress and cancelling stay responsive
+const SEND_CHUNK_SIZE: usize = 256 * 1024;
+
+// A large file being uploaded from local_path, and the parts the backend confirmed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Checkpoint {
+    bucket: String,
+    key: String,
+    local_path: PathBuf,
+    // What the file looked like when its parts were read; a changed file starts over
+    size: u64,
+    modified: SystemTime,
+    part_size: u64,
+    large_file_id: String,
+    // The whole file's SHA-1, which B2 keeps as large_file_sha1 for verifying the
+    // finished object. Empty in checkpoints from before it was recorded.
+    #[serde(default)]
+    sha1: String,
+    parts: Vec<LargeFilePart>,
+}
+
+impl Checkpoint {
+    fn is_for(&self, bucket: &str, key: &str, path: &Path) -> bool {
+        self.bucket == bucket && self.key == key && self.local_path == path
+    }
+}
+
+pub struct ChunkedUploads {
+    checkpoints: Mutex<Vec<Checkpoint>>,
+}
+
+impl ChunkedUploads {
+    pub fn new() -> Self {
+        Self {
+            checkpoints: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn find(&self, bucket: &str, key: &str, path: &Path) -> Option<Checkpoint> {
+        let checkpoints = self.checkpoints.lock().unwrap();
+        checkpoints.iter().find(|c| c.is_for(bucket, key, path)).cloned()
+    }
+
+    fn put(&self, app: &AppHandle, checkpoint: &Checkpoint) {
+        {
+            let mut checkpoints = self.checkpoints.lock().unwrap();
+            checkpoints.retain(|c| !c.is_for(&checkpoint.bucket, &checkpoint.key, &checkpoint.local_path));
+            checkpoints.push(checkpoint.clone());
+        }
+        self.save(app);
+    }
+
+    fn remove(&self, app: &AppHandle, bucket: &str, key: &str, path: &Path) {
+        self.checkpoints.lock().unwrap().retain(|c| !c.is_for(bucket, key, path));
+        self.save(app);
+    }
+
+    fn save(&self, app: &AppHandle) {
+        let checkpoints = self.checkpoints.lock().unwrap();
+        let result = store::data_file(app, CHECKPOINTS_FILE).and_then(|path| store::save_json(&path, &*checkpoints));
+        if let Err(e) = result {
+            log::error!("Failed to persist upload checkpoints: {}", e);
+        }
+    }
+}
+
+// Load the checkpoints of uploads the last exit interrupted; each continues when
+// the same file is next uploaded to the same place, e.g. from the transfer queue
+pub fn restore(app: &AppHandle, state: &AppState) {
+    let checkpoints: Vec<Checkpoint> = match store::data_file(app, CHECKPOINTS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load upload checkpoints: {}", e);
+            return;
+        }
+    };
+    if !checkpoints.is_empty() {
+        log::info!("{} interrupted upload(s) can continue from a checkpoint", checkpoints.len());
+    }
+    *state.chunked_uploads.checkpoints.lock().unwrap() = checkpoints;
+}
+
+// Whether a file of `size` bytes goes up a part at a time
+pub fn wants_parts(state: &AppState, size: u64) -> bool {
+    size >= CHUNKED_THRESHOLD && state.versions.capabilities().is_some_and(|c| c.large_file_parts)
+}
+
+fn part_size(size: u64) -> u64 {
+    MIN_PART_SIZE.max(size.div_ceil(MAX_PARTS))
+}
+
+// Upload a local file to bucket/key a part at a time, checkpointing each part the
+// backend confirms. An upload of the same unchanged file interrupted by a crash,
+// network drop or sidecar restart continues after the last confirmed part.
+#[allow(clippy::too_many_arguments)]
+pub async fn upload(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    bucket: &str,
+    key: &str,
+    path: &Path,
+    throttle: &Throttle,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let mut attempt = 0;
+    let result = loop {
+        let result = upload_once(app, state, bucket, key, path, throttle, cancel, counter).await;
+        match &result {
+            Err(e) if e.starts_with(backend::SOURCE_CHANGED) && attempt < backend::SOURCE_CHANGE_RETRIES => {
+                attempt += 1;
+                log::warn!("{} changed while uploading (attempt {})", path.display(), attempt);
+                // Give whatever is writing the file a moment to finish
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            _ => break result,
+        }
+    };
+    // The parts of an upload the user cancelled won't be needed; one cut off by
+    // quitting continues next time
+    if result.is_err() && cancel.load(Ordering::SeqCst) && !state.shutdown.load(Ordering::SeqCst) {
+        discard(app, state, bucket, key, path).await;
+    }
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn upload_once(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    bucket: &str,
+    key: &str,
+    path: &Path,
+    throttle: &Throttle,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let version = backend::file_version(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let (size, modified) = version;
+    let checkpoint = match state.chunked_uploads.find(bucket, key, path) {
+        Some(checkpoint) if checkpoint.sha1.is_empty() => {
+            log::info!("Upload of {} has no whole-file SHA-1 to verify with; starting over", path.display());
+            discard(app, state, bucket, key, path).await;
+            None
+        }
+        Some(checkpoint) if checkpoint.size == size && checkpoint.modified == modified => {
+            confirm(state, checkpoint).await?
+        }
+        Some(_) => {
+            log::info!("{} changed since its upload was interrupted; starting over", path.display());
+            discard(app, state, bucket, key, path).await;
+            None
+        }
+        None => None,
+    };
+    let mut checkpoint = match checkpoint {
+        Some(checkpoint) => checkpoint,
+        None => start(state, bucket, key, path, size, modified).await?,
+    };
+    state.chunked_uploads.put(app, &checkpoint);
+
+    let confirmed: u64 = checkpoint.parts.iter().map(|part| part.size).sum();
+    if confirmed > 0 {
+        log::info!("Continuing upload of {} after {} of {} bytes", path.display(), confirmed, size);
+    }
+    counter.store(confirmed, Ordering::Relaxed);
+
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    for number in 1..=size.div_ceil(checkpoint.part_size) as u32 {
+        if checkpoint.parts.iter().any(|part| part.number == number) {
+            continue;
+        }
+        if cancel.load(Ordering::SeqCst) {
+            return Err("Cancelled".to_string());
+        }
+        let offset = u64::from(number - 1) * checkpoint.part_size;
+        let len = checkpoint.part_size.min(size - offset);
+        let data = read_part(&mut file, offset, len)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        // Parts read from a file being written to would store it torn
+        if backend::file_version(path).ok() != Some(version) {
+            discard(app, state, bucket, key, path).await;
+            return Err(format!("{}: {} changed while it was being uploaded", backend::SOURCE_CHANGED, path.display()));
+        }
+
+        let part = LargeFilePart {
+            number,
+            sha1: hex::encode(Sha1::digest(&data[..])),
+            size: len,
+        };
+        send_part(state, &checkpoint, &part, data, throttle, cancel, counter).await?;
+        checkpoint.parts.push(part);
+        state.chunked_uploads.put(app, &checkpoint);
+    }
+
+    let base_url = state.base_url();
+    let client = backend::shared_client(&base_url);
+    backend::finish_large_file(&client, &base_url, bucket, &checkpoint.large_file_id).await?;
+    state.chunked_uploads.remove(app, bucket, key, path);
+    Ok(())
+}
+
+async fn start(
+    state: &AppState,
+    bucket: &str,
+    key: &str,
+    path: &Path,
+    size: u64,
+    modified: SystemTime,
+) -> Result<Checkpoint, String> {
+    // Read once more up front; the hashes are kept for verifying the upload after
+    let sha1 = checksums::local_hashes(state, path).await?.sha1;
+    let base_url = state.base_url();
+    let client = backend::shared_client(&base_url);
+    let large_file_id = backend::start_large_file(&client, &base_url, bucket, key, &sha1).await?;
+    Ok(Checkpoint {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        local_path: path.to_path_buf(),
+        size,
+        modified,
+        part_size: part_size(size),
+        large_file_id,
+        sha1,
+        parts: Vec::new(),
+    })
+}
+
+// Keep only the checkpointed parts the backend still has; None when the large file
+// itself is gone and the upload has to start over
+async fn confirm(state: &AppState, mut checkpoint: Checkpoint) -> Result<Option<Checkpoint>, String> {
+    let base_url = state.base_url();
+    let client = backend::shared_client(&base_url);
+    let stored = backend::large_file_parts(&client, &base_url, &checkpoint.bucket, &checkpoint.large_file_id).await?;
+    let Some(stored) = stored else {
+        log::info!("Upload of {} can't continue; starting over", checkpoint.key);
+        return Ok(None);
+    };
+    checkpoint.parts.retain(|part| stored.contains(part));
+    Ok(Some(checkpoint))
+}
+
+async fn read_part(file: &mut tokio::fs::File, offset: u64, len: u64) -> std::io::Result<Bytes> {
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut data = vec![0; len as usize];
+    file.read_exact(&mut data).await?;
+    Ok(Bytes::from(data))
+}
+
+// Send one part, trying again after a failure once the backend is back up
+async fn send_part(
+    state: &AppState,
+    checkpoint: &Checkpoint,
+    part: &LargeFilePart,
+    data: Bytes,
+    throttle: &Throttle,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let before = counter.load(Ordering::Relaxed);
+    let mut attempt = 0;
+    loop {
+        let chunks: Vec<Result<Bytes, std::io::Error>> = (0..data.len())
+            .step_by(SEND_CHUNK_SIZE)
+            .map(|start| Ok(data.slice(start..data.len().min(start + SEND_CHUNK_SIZE))))
+            .collect();
+        let stream = bandwidth::throttled(futures_util::stream::iter(chunks), throttle.clone());
+        let body = backend::metered_body(stream, Arc::clone(cancel), Arc::clone(counter));
+        let base_url = state.base_url();
+        let client = backend::shared_client(&base_url);
+        let id = &checkpoint.large_file_id;
+        let result = backend::upload_large_file_part(&client, &base_url, &checkpoint.bucket, id, part, body).await;
+        let Err(e) = result else {
+            return Ok(());
+        };
+
+        counter.store(before, Ordering::Relaxed);
+        attempt += 1;
+        if cancel.load(Ordering::SeqCst) || attempt >= PART_ATTEMPTS {
+            return Err(e);
+        }
+        log::warn!("{} (attempt {}); trying again", e, attempt);
+        tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+        // The sidecar may be restarting
+        while !state.is_healthy() {
+            if cancel.load(Ordering::SeqCst) || state.shutdown.load(Ordering::SeqCst) {
+                return Err("Cancelled".to_string());
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+// Forget the checkpoint for bucket/key and have B2 drop the parts stored for it
+async fn discard(app: &AppHandle, state: &AppState, bucket: &str, key: &str, path: &Path) {
+    let Some(checkpoint) = state.chunked_uploads.find(bucket, key, path) else {
+        return;
+    };
+    state.chunked_uploads.remove(app, bucket, key, path);
+    let base_url = state.base_url();
+    let client = backend::shared_client(&base_url);
+    if let Err(e) = backend::cancel_large_file(&client, &base_url, bucket, &checkpoint.large_file_id).await {
+        log::warn!("Failed to discard the uploaded parts of {}: {}", key, e);
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::{emit_to_main, AppState};
+
+// Skew beyond which time-limited links are likely to look expired or not yet valid
+const SIGNIFICANT_SKEW_MS: i64 = 2 * 60 * 1000;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ClockSkew {
+    // Server clock minus local clock; add to local time to get server time
+    pub offset_ms: i64,
+    pub significant: bool,
+    // False until the backend has answered at least once
+    pub measured: bool,
+    // Local time-zone offset from UTC, for rendering server timestamps
+    pub utc_offset_minutes: i32,
+}
+
+// Offset between the local clock and the backend's, measured from HTTP Date headers
+pub struct Clock {
+    offset_ms: AtomicI64,
+    measured: AtomicBool,
+    warned: AtomicBool,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            offset_ms: AtomicI64::new(0),
+            measured: AtomicBool::new(false),
+            warned: AtomicBool::new(false),
+        }
+    }
+
+    fn snapshot(&self) -> ClockSkew {
+        let offset_ms = self.offset_ms.load(Ordering::SeqCst);
+        ClockSkew {
+            offset_ms,
+            significant: offset_ms.abs() >= SIGNIFICANT_SKEW_MS,
+            measured: self.measured.load(Ordering::SeqCst),
+            utc_offset_minutes: chrono::Local::now().offset().local_minus_utc() / 60,
+        }
+    }
+}
+
+fn unix_ms(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64
+}
+
+// Record a server Date header seen on a response to a request sent at `sent`, and warn
+// the frontend when the skew becomes (or stops being) significant
+pub fn record(app: &AppHandle, state: &Arc<AppState>, server_date: &str, sent: SystemTime, rtt: Duration) {
+    let Ok(server) = chrono::DateTime::parse_from_rfc2822(server_date) else {
+        return;
+    };
+    // Assume the server stamped the response halfway through the round trip; the Date
+    // header only has one-second precision, so ignore sub-second differences
+    let local_mid = unix_ms(sent) + rtt.as_millis() as i64 / 2;
+    let mut offset = server.timestamp_millis() - local_mid;
+    if offset.abs() < 1000 {
+        offset = 0;
+    }
+
+    let clock = &state.clock;
+    clock.offset_ms.store(offset, Ordering::SeqCst);
+    clock.measured.store(true, Ordering::SeqCst);
+
+    let significant = offset.abs() >= SIGNIFICANT_SKEW_MS;
+    if clock.warned.swap(significant, Ordering::SeqCst) != significant {
+        if significant {
+            log::warn!("Local clock is off from the backend by {} ms", offset);
+        }
+        emit_to_main(app, "clock-skew", clock.snapshot());
+    }
+}
+
+#[tauri::command]
+pub fn get_clock_skew(state: tauri::State<Arc<AppState>>) -> ClockSkew {
+    state.clock.snapshot()
+}
+
+// Convert a local Unix time (ms) to backend time, e.g. when computing a link's expiry
+#[tauri::command]
+pub fn to_server_time(state: tauri::State<Arc<AppState>>, local_ms: i64) -> i64 {
+    local_ms + state.clock.offset_ms.load(Ordering::SeqCst)
+}
+
+// Convert a backend Unix time (ms), e.g. a link's expiry, to the local clock
+#[tauri::command]
+pub fn to_local_time(state: tauri::State<Arc<AppState>>, server_ms: i64) -> i64 {
+    server_ms - state.clock.offset_ms.load(Ordering::SeqCst)
+}
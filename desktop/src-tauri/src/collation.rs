@@ -0,0 +1,59 @@
+use std::cmp::Ordering;
+use std::sync::OnceLock;
+
+use icu_collator::{Collator, CollatorOptions, Numeric};
+use icu_locid::Locale;
+
+use crate::backend::{RemoteBucket, RemoteObject};
+
+// The user's locale, read once; POSIX forms like en_US.UTF-8 are normalised to en-US
+fn user_locale() -> &'static Locale {
+    static LOCALE: OnceLock<Locale> = OnceLock::new();
+    LOCALE.get_or_init(|| {
+        let Some(raw) = sys_locale::get_locale() else {
+            return Locale::UND;
+        };
+        let tag = raw.split(['.', '@']).next().unwrap_or_default().replace('_', "-");
+        Locale::try_from_bytes(tag.as_bytes()).unwrap_or(Locale::UND)
+    })
+}
+
+// Orders names the way a person reading them in their own language would:
+// accents and case follow the locale's rules and digit runs compare by value,
+// so "file2" sorts before "file10"
+pub struct NaturalOrder {
+    collator: Option<Collator>,
+}
+
+impl NaturalOrder {
+    pub fn for_user_locale() -> Self {
+        let mut options = CollatorOptions::new();
+        options.numeric = Some(Numeric::On);
+        let collator = match Collator::try_new(&user_locale().into(), options) {
+            Ok(collator) => Some(collator),
+            Err(e) => {
+                log::warn!("Failed to load collation for {}, sorting by code point: {}", user_locale(), e);
+                None
+            }
+        };
+        Self { collator }
+    }
+
+    // Names the collator considers equal still get a stable, deterministic order
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let collated = self.collator.as_ref().map_or(Ordering::Equal, |c| c.compare(a, b));
+        collated.then_with(|| a.cmp(b))
+    }
+}
+
+// Sorting stays on the Rust side so the webview never has to order a folder with
+// tens of thousands of entries itself
+pub fn sort_objects(objects: &mut [RemoteObject]) {
+    let order = NaturalOrder::for_user_locale();
+    objects.sort_by(|a, b| order.compare(&a.name, &b.name));
+}
+
+pub fn sort_buckets(buckets: &mut [RemoteBucket]) {
+    let order = NaturalOrder::for_user_locale();
+    buckets.sort_by(|a, b| order.compare(&a.name, &b.name));
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, LogicalPosition, Manager, WebviewWindow, Wry};
+
+use crate::AppState;
+use crate::error::AppError;
+
+// Prefix that keeps frontend-defined ids apart from the app menu's ids
+const ID_PREFIX: &str = "ctx:";
+
+// Declarative context menu entry sent by the frontend
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContextMenuItem {
+    Item {
+        id: String,
+        label: String,
+        #[serde(default = "enabled_by_default")]
+        enabled: bool,
+        #[serde(default)]
+        accelerator: Option<String>,
+    },
+    Check {
+        id: String,
+        label: String,
+        checked: bool,
+        #[serde(default = "enabled_by_default")]
+        enabled: bool,
+    },
+    Submenu {
+        label: String,
+        #[serde(default = "enabled_by_default")]
+        enabled: bool,
+        items: Vec<ContextMenuItem>,
+    },
+    Separator,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+// Window-relative position in logical pixels
+#[derive(Debug, Deserialize)]
+pub struct MenuPosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+// Payload of `context-menu-selected`
+#[derive(Clone, Serialize)]
+struct ContextMenuSelection {
+    id: String,
+    window: String,
+}
+
+fn build_items(app: &AppHandle, items: &[ContextMenuItem]) -> tauri::Result<Vec<Box<dyn IsMenuItem<Wry>>>> {
+    let mut built: Vec<Box<dyn IsMenuItem<Wry>>> = Vec::with_capacity(items.len());
+    for item in items {
+        match item {
+            ContextMenuItem::Item { id, label, enabled, accelerator } => built.push(Box::new(MenuItem::with_id(
+                app,
+                format!("{}{}", ID_PREFIX, id),
+                label,
+                *enabled,
+                accelerator.as_deref(),
+            )?)),
+            ContextMenuItem::Check { id, label, checked, enabled } => built.push(Box::new(CheckMenuItem::with_id(
+                app,
+                format!("{}{}", ID_PREFIX, id),
+                label,
+                *enabled,
+                *checked,
+                None::<&str>,
+            )?)),
+            ContextMenuItem::Submenu { label, enabled, items } => {
+                let children = build_items(app, items)?;
+                let refs: Vec<&dyn IsMenuItem<Wry>> = children.iter().map(|c| c.as_ref()).collect();
+                built.push(Box::new(Submenu::with_items(app, label, *enabled, &refs)?));
+            }
+            ContextMenuItem::Separator => built.push(Box::new(PredefinedMenuItem::separator(app)?)),
+        }
+    }
+    Ok(built)
+}
+
+// Show a native context menu in the calling window; the chosen id comes back as a
+// `context-menu-selected` event
+#[tauri::command]
+pub fn show_context_menu(
+    app: AppHandle,
+    window: WebviewWindow,
+    state: tauri::State<Arc<AppState>>,
+    items: Vec<ContextMenuItem>,
+    position: Option<MenuPosition>,
+) -> Result<(), AppError> {
+    let built = build_items(&app, &items).map_err(|e| format!("Failed to build context menu: {}", e))?;
+    let refs: Vec<&dyn IsMenuItem<Wry>> = built.iter().map(|b| b.as_ref()).collect();
+    let menu = Menu::with_items(&app, &refs).map_err(|e| format!("Failed to build context menu: {}", e))?;
+
+    *state.context_menu_window.lock().unwrap() = Some(window.label().to_string());
+    match position {
+        Some(pos) => window.popup_menu_at(&menu, LogicalPosition::new(pos.x, pos.y)),
+        None => window.popup_menu(&menu),
+    }
+    .map_err(|e| format!("Failed to show context menu: {}", e).into())
+}
+
+// Forward a context menu selection to the window that opened the menu. Returns false
+// for ids that don't belong to a frontend-built context menu.
+pub fn handle_menu_event(app: &AppHandle, id: &str) -> bool {
+    let Some(id) = id.strip_prefix(ID_PREFIX) else {
+        return false;
+    };
+    let state: tauri::State<Arc<AppState>> = app.state();
+    let Some(window) = state.context_menu_window.lock().unwrap().clone() else {
+        return true;
+    };
+    let _ = app.emit_to(
+        window.as_str(),
+        "context-menu-selected",
+        ContextMenuSelection {
+            id: id.to_string(),
+            window: window.clone(),
+        },
+    );
+    true
+}
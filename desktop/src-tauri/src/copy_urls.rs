@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::error::AppError;
+use crate::{backend, emit_to_main, operations, selection, session, webview_base_url, AppState};
+
+// Progress is reported every this many links
+const PROGRESS_EVERY: usize = 200;
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlListFormat {
+    // One URL per line
+    Plain,
+    // A bulleted list of links named after the files
+    Markdown,
+    // A <ul> of links named after the files
+    Html,
+}
+
+#[derive(Clone, Serialize)]
+struct CopyUrlsProgress {
+    operation_id: String,
+    done: usize,
+    total: usize,
+}
+
+fn display_name(key: &str) -> &str {
+    key.rsplit('/').next().filter(|name| !name.is_empty()).unwrap_or(key)
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('[', "\\[").replace(']', "\\]")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_list(format: UrlListFormat, links: &[(String, String)]) -> String {
+    match format {
+        UrlListFormat::Plain => links.iter().map(|(_, url)| url.as_str()).collect::<Vec<_>>().join("\n"),
+        UrlListFormat::Markdown => links
+            .iter()
+            .map(|(key, url)| format!("- [{}]({})", escape_markdown(display_name(key)), url))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        UrlListFormat::Html => {
+            let items: String = links
+                .iter()
+                .map(|(key, url)| {
+                    format!(
+                        "  <li><a href=\"{}\">{}</a></li>\n",
+                        escape_html(url),
+                        escape_html(display_name(key))
+                    )
+                })
+                .collect();
+            format!("<ul>\n{}</ul>", items)
+        }
+    }
+}
+
+// A link to an object that works outside the app for a day, carrying a token that
+// opens only that object
+fn download_link(state: &AppState, origin: &str, bucket: &str, key: &str) -> Result<String, String> {
+    let mut url = backend::download_url(origin, bucket, key)?;
+    url.query_pairs_mut()
+        .append_pair("token", &session::download_token(state, bucket, key));
+    Ok(url.to_string())
+}
+
+// Replace the clipboard's contents with `text`
+pub fn write_clipboard(app: &AppHandle, text: String) -> Result<(), String> {
+    app.clipboard()
+        .write_text(text)
+        .map_err(|e| format!("Failed to copy to the clipboard: {}", e))
+}
+
+// Copy links to every selected object at once, as plain lines or a Markdown or
+// HTML list. The objects come as `keys` or a `selection` token. Selected folders
+// (keys ending in /) contribute everything under them. Reports `copy-urls-progress`
+// while building large lists; returns how many links were copied.
+#[tauri::command]
+pub async fn copy_urls(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    bucket: String,
+    keys: Option<Vec<String>>,
+    selection: Option<String>,
+    format: UrlListFormat,
+    operation_id: Option<String>,
+) -> Result<usize, AppError> {
+    let keys = selection::resolve(&state, &bucket, keys, selection)?;
+    let origin = webview_base_url(&state).ok_or("The backend isn't listening yet")?;
+    let operation = operations::begin(
+        &app,
+        operation_id,
+        "copy_urls",
+        "Copy URLs",
+        Arc::new(AtomicBool::new(false)),
+    );
+    let cancel = Arc::clone(operation.cancel_flag());
+
+    let mut objects = Vec::new();
+    for key in keys {
+        if !key.ends_with('/') {
+            objects.push(key);
+            continue;
+        }
+        let base_url = state.base_url();
+        let listed = backend::list_objects(&backend::shared_client(&base_url), &base_url, &bucket, &key).await?;
+        objects.extend(listed.into_iter().map(|object| object.name).filter(|name| !name.ends_with('/')));
+        if cancel.load(Ordering::SeqCst) {
+            return Err(AppError::Cancelled);
+        }
+    }
+
+    let total = objects.len();
+    let mut links = Vec::with_capacity(total);
+    for (i, key) in objects.into_iter().enumerate() {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(AppError::Cancelled);
+        }
+        let url = download_link(&state, &origin, &bucket, &key)?;
+        links.push((key, url));
+        if (i + 1) % PROGRESS_EVERY == 0 {
+            let progress = CopyUrlsProgress {
+                operation_id: operation.id().to_string(),
+                done: i + 1,
+                total,
+            };
+            emit_to_main(&app, "copy-urls-progress", progress);
+        }
+    }
+
+    let text = format_list(format, &links);
+    write_clipboard(&app, text)?;
+    log::info!("Copied {} URLs from bucket {}", total, bucket);
+    Ok(total)
+}
+
+// A link to one object, as copy_urls makes them
+#[tauri::command]
+pub fn get_download_link(state: tauri::State<Arc<AppState>>, bucket: String, key: String) -> Result<String, AppError> {
+    let origin = webview_base_url(&state).ok_or("The backend isn't listening yet")?;
+    Ok(download_link(&state, &origin, &bucket, &key)?)
+}
@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_shell::process::TerminatedPayload;
+
+use crate::{emit_to_main, AppState, Sidecar};
+
+// Stderr lines kept per sidecar; enough for a Go panic and its goroutine trace
+const TAIL_LINES: usize = 200;
+
+// What a spawned sidecar has written to stderr lately, and when it started
+pub struct CrashLog {
+    stderr: Mutex<VecDeque<String>>,
+    started_at: Mutex<Option<Instant>>,
+    last: Mutex<Option<CrashReport>>,
+}
+
+impl CrashLog {
+    pub fn new() -> Self {
+        Self {
+            stderr: Mutex::new(VecDeque::with_capacity(TAIL_LINES)),
+            started_at: Mutex::new(None),
+            last: Mutex::new(None),
+        }
+    }
+
+    // A new process was spawned; its output starts a fresh tail
+    pub fn started(&self) {
+        self.stderr.lock().unwrap().clear();
+        *self.started_at.lock().unwrap() = Some(Instant::now());
+    }
+
+    pub fn record_stderr(&self, line: &str) {
+        let mut stderr = self.stderr.lock().unwrap();
+        if stderr.len() == TAIL_LINES {
+            stderr.pop_front();
+        }
+        stderr.push_back(line.trim_end().to_string());
+    }
+
+    // How long the current process has been running, if one was spawned
+    pub fn uptime(&self) -> Option<Duration> {
+        self.started_at.lock().unwrap().map(|at| at.elapsed())
+    }
+
+    pub fn last(&self) -> Option<CrashReport> {
+        self.last.lock().unwrap().clone()
+    }
+}
+
+// Sent as `backend-crash` when a sidecar exits on its own, for bug reports
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub profile: String,
+    pub exit_code: Option<i32>,
+    // Set when the process was killed by a signal (Unix only)
+    pub signal: Option<i32>,
+    pub uptime_secs: Option<u64>,
+    // Last stderr lines, oldest first
+    pub stderr_tail: Vec<String>,
+    // Unix seconds
+    pub crashed_at: i64,
+}
+
+// Build and emit the report for a sidecar that just terminated unexpectedly
+pub fn report(app: &AppHandle, sidecar: &Sidecar, status: &TerminatedPayload) -> CrashReport {
+    let log = &sidecar.crash_log;
+    let report = CrashReport {
+        profile: sidecar.profile.clone(),
+        exit_code: status.code,
+        signal: status.signal,
+        uptime_secs: log.uptime().map(|uptime| uptime.as_secs()),
+        stderr_tail: log.stderr.lock().unwrap().iter().cloned().collect(),
+        crashed_at: chrono::Utc::now().timestamp(),
+    };
+    *log.last.lock().unwrap() = Some(report.clone());
+    emit_to_main(app, "backend-crash", report.clone());
+    report
+}
+
+impl CrashReport {
+    // One-line summary for logs and the crashed status
+    pub fn summary(&self) -> String {
+        let how = match (self.exit_code, self.signal) {
+            (_, Some(signal)) => format!("was killed by signal {}", signal),
+            (Some(code), None) => format!("exited with code {}", code),
+            (None, None) => "exited".to_string(),
+        };
+        match self.uptime_secs {
+            Some(secs) => format!("Process {} after {}s", how, secs),
+            None => format!("Process {}", how),
+        }
+    }
+}
+
+// The active profile's last crash, e.g. for a bug report opened after the event
+#[tauri::command]
+pub fn get_backend_crash_report(state: tauri::State<Arc<AppState>>) -> Option<CrashReport> {
+    state.sidecar().crash_log.last()
+}
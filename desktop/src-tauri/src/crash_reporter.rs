@@ -0,0 +1,357 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::crash_report::CrashReport;
+use crate::error::AppError;
+use crate::status_history::{self, StatusChange};
+use crate::{log_files, store, AppState};
+
+pub const SETTINGS_FILE: &str = "crash_reporter.json";
+
+// Reports are kept here, one JSON file each, until deleted
+const REPORTS_DIR: &str = "crashes";
+
+// Oldest reports beyond this many are deleted as new ones are written
+const MAX_REPORTS: usize = 20;
+
+// Context captured with every report
+const STATUS_CHANGES: usize = 20;
+const LOG_LINES: usize = 200;
+
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashKind {
+    // The shell itself panicked
+    Shell,
+    // A sidecar exited on its own
+    Backend,
+}
+
+// A crash written to disk. Nothing leaves the machine unless the user submits it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StoredCrash {
+    pub id: String,
+    pub kind: CrashKind,
+    // Unix milliseconds
+    pub at: i64,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub summary: String,
+    // Shell panics: where, on which thread, and the backtrace
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: Option<String>,
+    // Backend crashes: the sidecar's own report, with its stderr tail
+    pub backend: Option<CrashReport>,
+    // Last backend status transitions across profiles, oldest first
+    pub status_changes: Vec<StatusChange>,
+    // Last lines of the shell log, with credentials in URLs masked
+    pub log_tail: Vec<String>,
+    // Unix milliseconds, once sent
+    pub submitted_at: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CrashSummary {
+    pub id: String,
+    pub kind: CrashKind,
+    pub at: i64,
+    pub summary: String,
+    pub submitted_at: Option<i64>,
+}
+
+// Uploading is off until the user turns it on and gives a collector to send to
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrashReporterSettings {
+    pub enabled: bool,
+    // Receives each report as a JSON POST
+    pub endpoint: String,
+    // Whether the log tail goes along; status transitions always do
+    pub include_logs: bool,
+}
+
+impl CrashReporterSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.endpoint.is_empty() {
+            return Ok(());
+        }
+        let url = reqwest::Url::parse(&self.endpoint).map_err(|e| format!("Invalid crash report URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("The crash report URL must use http or https".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub struct CrashReporter {
+    settings: Mutex<CrashReporterSettings>,
+}
+
+impl CrashReporter {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(CrashReporterSettings::default()),
+        }
+    }
+}
+
+// Mask the user:password part of URLs, e.g. the sidecar's base URL with the shell's credential
+pub fn redact(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(scheme_end) = rest.find("://") {
+        let (head, tail) = rest.split_at(scheme_end + 3);
+        out.push_str(head);
+        let authority_end = tail.find(|c: char| c == '/' || c.is_whitespace()).unwrap_or(tail.len());
+        match tail[..authority_end].rfind('@') {
+            Some(at) => {
+                out.push_str("***");
+                rest = &tail[at..];
+            }
+            None => rest = tail,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn reports_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = store::data_file(app, REPORTS_DIR)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+fn report_path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    // Ids are generated here; anything else could point outside the directory
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid crash report id {}", id));
+    }
+    Ok(reports_dir(app)?.join(format!("{}.json", id)))
+}
+
+fn new_crash(app: &AppHandle, state: &AppState, kind: CrashKind, summary: String) -> StoredCrash {
+    let at = chrono::Utc::now().timestamp_millis();
+    StoredCrash {
+        id: format!("{}-{}", at, uuid::Uuid::new_v4().simple()),
+        kind,
+        at,
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        summary,
+        location: None,
+        thread: None,
+        backtrace: None,
+        backend: None,
+        status_changes: status_history::recent(state, STATUS_CHANGES),
+        log_tail: log_files::tail(app, LOG_LINES).iter().map(|line| redact(line)).collect(),
+        submitted_at: None,
+    }
+}
+
+fn save(app: &AppHandle, crash: &StoredCrash) -> Result<PathBuf, String> {
+    let path = report_path(app, &crash.id)?;
+    store::save_json(&path, crash)?;
+    prune(app);
+    Ok(path)
+}
+
+fn load(app: &AppHandle, id: &str) -> Result<StoredCrash, String> {
+    let path = report_path(app, id)?;
+    let bytes = std::fs::read(&path).map_err(|_| format!("Unknown crash report {}", id))?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("Unreadable crash report {}: {}", id, e))
+}
+
+fn all(app: &AppHandle) -> Vec<StoredCrash> {
+    let Ok(entries) = reports_dir(app).and_then(|dir| std::fs::read_dir(&dir).map_err(|e| e.to_string())) else {
+        return Vec::new();
+    };
+    let mut crashes: Vec<StoredCrash> = entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| serde_json::from_slice(&std::fs::read(entry.path()).ok()?).ok())
+        .collect();
+    crashes.sort_by_key(|crash| std::cmp::Reverse(crash.at));
+    crashes
+}
+
+// Keep the newest MAX_REPORTS
+fn prune(app: &AppHandle) {
+    for crash in all(app).into_iter().skip(MAX_REPORTS) {
+        if let Ok(path) = report_path(app, &crash.id) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+// Write a panic report before the process goes down; the default hook still prints it
+fn install_panic_hook(app: &AppHandle) {
+    let app = app.clone();
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "Box<dyn Any>".to_string(),
+            },
+        };
+        let state = app.state::<Arc<AppState>>();
+        let mut crash = new_crash(&app, &state, CrashKind::Shell, format!("Panic: {}", redact(&message)));
+        crash.location = info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        crash.thread = std::thread::current().name().map(str::to_string);
+        crash.backtrace = Some(std::backtrace::Backtrace::force_capture().to_string());
+        match save(&app, &crash) {
+            Ok(path) => log::error!("Panic report written to {}", path.display()),
+            Err(e) => log::error!("Failed to write panic report: {}", e),
+        }
+        previous(info);
+    }));
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    install_panic_hook(app);
+    let settings: CrashReporterSettings = match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load crash reporter settings: {}", e);
+            return;
+        }
+    };
+    match settings.validate() {
+        Ok(()) => *state.crash_reporter.settings.lock().unwrap() = settings,
+        Err(e) => log::warn!("Ignoring saved crash reporter settings: {}", e),
+    }
+}
+
+// Keep a sidecar's crash on disk alongside the shell's own
+pub fn record_backend(app: &AppHandle, state: &AppState, report: &CrashReport) {
+    let mut crash = new_crash(
+        app,
+        state,
+        CrashKind::Backend,
+        format!("Backend {}: {}", report.profile, report.summary()),
+    );
+    crash.backend = Some(CrashReport {
+        stderr_tail: report.stderr_tail.iter().map(|line| redact(line)).collect(),
+        ..report.clone()
+    });
+    if let Err(e) = save(app, &crash) {
+        log::warn!("Failed to write backend crash report: {}", e);
+    }
+}
+
+// Crash reports on disk, newest first
+#[tauri::command]
+pub fn list_crash_reports(app: AppHandle) -> Vec<CrashSummary> {
+    all(&app)
+        .into_iter()
+        .map(|crash| CrashSummary {
+            id: crash.id,
+            kind: crash.kind,
+            at: crash.at,
+            summary: crash.summary,
+            submitted_at: crash.submitted_at,
+        })
+        .collect()
+}
+
+// A whole report, to show the user exactly what submitting it would send
+#[tauri::command]
+pub fn get_crash_report(app: AppHandle, id: String) -> Result<StoredCrash, AppError> {
+    Ok(load(&app, &id)?)
+}
+
+#[tauri::command]
+pub fn delete_crash_report(app: AppHandle, id: String) -> Result<(), AppError> {
+    let path = report_path(&app, &id)?;
+    std::fs::remove_file(&path).map_err(|e| format!("Failed to delete crash report {}: {}", id, e).into())
+}
+
+// Upload a report. Needs reporting turned on in the settings and `consent` given for
+// this report, so nothing is ever sent without the user asking.
+#[tauri::command]
+pub async fn submit_crash_report(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    id: String,
+    consent: bool,
+    note: Option<String>,
+) -> Result<StoredCrash, AppError> {
+    let settings = state.crash_reporter.settings.lock().unwrap().clone();
+    if !consent {
+        return Err(AppError::invalid("Crash reports are only sent with your consent"));
+    }
+    if !settings.enabled || settings.endpoint.is_empty() {
+        return Err(AppError::invalid("Crash reporting is turned off"));
+    }
+    let mut crash = load(&app, &id)?;
+    if crash.submitted_at.is_some() {
+        return Err(AppError::invalid("This crash report was already sent"));
+    }
+
+    let mut body = serde_json::to_value(&crash).map_err(|e| e.to_string())?;
+    if !settings.include_logs {
+        body["log_tail"] = serde_json::Value::Array(Vec::new());
+    }
+    if let Some(note) = note.filter(|note| !note.trim().is_empty()) {
+        body["note"] = serde_json::Value::String(note);
+    }
+    let client = reqwest::Client::builder()
+        .timeout(SUBMIT_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let resp = client
+        .post(&settings.endpoint)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send the crash report: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(AppError::Status {
+            status: resp.status().as_u16(),
+            message: format!("Sending the crash report returned status: {}", resp.status()),
+        });
+    }
+
+    crash.submitted_at = Some(chrono::Utc::now().timestamp_millis());
+    store::save_json(&report_path(&app, &id)?, &crash)?;
+    log::info!("Sent crash report {}", id);
+    Ok(crash)
+}
+
+#[tauri::command]
+pub fn get_crash_reporter_settings(state: tauri::State<Arc<AppState>>) -> CrashReporterSettings {
+    state.crash_reporter.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn configure_crash_reporter(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    enabled: Option<bool>,
+    endpoint: Option<String>,
+    include_logs: Option<bool>,
+) -> Result<CrashReporterSettings, AppError> {
+    let mut settings = state.crash_reporter.settings.lock().unwrap().clone();
+    settings.enabled = enabled.unwrap_or(settings.enabled);
+    settings.endpoint = endpoint.map(|e| e.trim().to_string()).unwrap_or(settings.endpoint);
+    settings.include_logs = include_logs.unwrap_or(settings.include_logs);
+    settings.validate().map_err(AppError::invalid)?;
+    if settings.enabled && settings.endpoint.is_empty() {
+        return Err(AppError::invalid("Crash reporting needs a URL to send reports to"));
+    }
+
+    let path = store::data_file(&app, SETTINGS_FILE)?;
+    store::save_json(&path, &settings)?;
+    *state.crash_reporter.settings.lock().unwrap() = settings.clone();
+    Ok(settings)
+}
@@ -0,0 +1,363 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::error::AppError;
+use crate::{crash_reporter, file_manager, health, status_history, store, version, AppState};
+
+const README: &str = "\
+bb-stream diagnostics
+=====================
+
+Collected to help track down a problem. Nothing was sent anywhere; attach this
+file to a bug report yourself.
+
+system.json          OS, app and backend versions, and each profile's backend
+                     with its port or socket.
+app-info.json        What the About panel shows: versions, platform and the
+                     app's folders.
+status-history.json  Recent backend status changes of every profile.
+config/              The app's settings and the backend's config.yaml. Access
+                     keys, passwords, tokens and request headers are replaced
+                     with [redacted].
+logs/                The app's log (bb-stream*.log) and captured backend output
+                     (sidecar*.log), with credentials in URLs masked.
+";
+
+// Settings files copied into config/; job lists, histories and accounts are left out
+const SETTINGS_FILES: [&str; 13] = [
+    "sidecar_config.json",
+    "health_checks.json",
+    "external_server.json",
+    "telemetry.json",
+    "crash_reporter.json",
+    "wifi_only.json",
+    "quiet_hours.json",
+    "thumbnails.json",
+    "permissions.json",
+    "settings_sync.json",
+    "view_prefs.json",
+    "access_stats.json",
+    "scratch.json",
+];
+
+// Keys whose values are replaced, matched case-insensitively as part of the key
+const SECRET_KEYS: [&str; 7] = ["secret", "password", "token", "key", "credential", "authorization", "headers"];
+
+const REDACTED: &str = "[redacted]";
+
+#[derive(Serialize)]
+struct ProfileInfo {
+    profile: String,
+    active: bool,
+    running: bool,
+    healthy: bool,
+    pid: Option<u32>,
+    // 0 until a port is known, and with the socket transport
+    port: u16,
+    socket: Option<String>,
+    // Whether an already-running server is used instead of a spawned one
+    external: bool,
+}
+
+#[derive(Serialize)]
+struct SystemInfo {
+    // Unix milliseconds
+    generated_at: i64,
+    app_version: String,
+    os: String,
+    os_version: Option<String>,
+    kernel_version: Option<String>,
+    arch: String,
+    backend_version: Option<String>,
+    expected_api_version: u32,
+    version_mismatch: Option<version::VersionMismatch>,
+    profiles: Vec<ProfileInfo>,
+    health_checks: health::HealthSettings,
+}
+
+// What an About panel or bug report needs to know about this install
+#[derive(Clone, Debug, Serialize)]
+pub struct AppInfo {
+    pub app_version: String,
+    // None until the backend has been reached
+    pub sidecar_version: Option<String>,
+    pub tauri_version: String,
+    pub webview_version: Option<String>,
+    // E.g. aarch64-apple-darwin
+    pub platform: String,
+    pub data_dir: Option<PathBuf>,
+    pub log_dir: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    // The active backend's; None with the socket transport or before it is listening
+    pub port: Option<u16>,
+    pub socket: Option<PathBuf>,
+}
+
+fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_KEYS.iter().any(|secret| key.contains(secret))
+}
+
+// Replace the values of secret-looking keys, and everything under them
+fn redact_json(value: &mut Value, secret: bool) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                redact_json(value, secret || is_secret(key));
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact_json(item, secret)),
+        Value::Null => {}
+        _ if secret => *value = Value::String(REDACTED.to_string()),
+        Value::String(s) => *s = crash_reporter::redact(s),
+        _ => {}
+    }
+}
+
+// config.yaml is flat `key: value` lines; redact the values of secret-looking keys
+fn redact_yaml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        match line.split_once(':') {
+            Some((key, value)) if is_secret(key.trim()) && !value.trim().is_empty() => {
+                out.push_str(key);
+                out.push_str(": ");
+                out.push_str(REDACTED);
+            }
+            _ => out.push_str(&crash_reporter::redact(line)),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn system_info(app: &AppHandle, state: &AppState) -> SystemInfo {
+    let active = state.active_profile.lock().unwrap().clone();
+    let sidecars: Vec<_> = state.sidecars.lock().unwrap().values().cloned().collect();
+    let mut profiles: Vec<ProfileInfo> = sidecars
+        .iter()
+        .map(|sidecar| ProfileInfo {
+            profile: sidecar.profile.clone(),
+            active: sidecar.profile == active,
+            running: sidecar.is_running(),
+            healthy: sidecar.is_healthy.load(Ordering::SeqCst),
+            pid: sidecar.pid(),
+            port: sidecar.port.load(Ordering::SeqCst),
+            socket: sidecar.socket.lock().unwrap().as_ref().map(|path| path.display().to_string()),
+            external: sidecar.external_url().is_some(),
+        })
+        .collect();
+    profiles.sort_by(|a, b| a.profile.cmp(&b.profile));
+    SystemInfo {
+        generated_at: chrono::Utc::now().timestamp_millis(),
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS.to_string(),
+        os_version: sysinfo::System::long_os_version(),
+        kernel_version: sysinfo::System::kernel_version(),
+        arch: std::env::consts::ARCH.to_string(),
+        backend_version: state.versions.running(),
+        expected_api_version: version::EXPECTED_API_VERSION,
+        version_mismatch: state.versions.mismatch(),
+        profiles,
+        health_checks: state.health_checks.settings(),
+    }
+}
+
+pub fn app_info(app: &AppHandle, state: &AppState) -> AppInfo {
+    let sidecar = state.sidecar();
+    let port = sidecar.port.load(Ordering::SeqCst);
+    let socket = sidecar.socket.lock().unwrap().clone();
+    let paths = app.path();
+    AppInfo {
+        app_version: app.package_info().version.to_string(),
+        sidecar_version: state.versions.running(),
+        tauri_version: tauri::VERSION.to_string(),
+        webview_version: tauri::webview_version().ok(),
+        platform: tauri::utils::platform::target_triple()
+            .unwrap_or_else(|_| format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS)),
+        data_dir: paths.app_data_dir().ok(),
+        log_dir: paths.app_log_dir().ok(),
+        cache_dir: paths.app_cache_dir().ok(),
+        port: (port != 0).then_some(port),
+        socket,
+    }
+}
+
+struct Bundle {
+    zip: ZipWriter<File>,
+    options: SimpleFileOptions,
+}
+
+impl Bundle {
+    fn add(&mut self, name: &str, bytes: &[u8]) -> Result<(), String> {
+        self.zip
+            .start_file(name, self.options)
+            .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+        self.zip.write_all(bytes).map_err(|e| format!("Failed to add {}: {}", name, e))
+    }
+
+    fn add_json<T: Serialize>(&mut self, name: &str, value: &T) -> Result<(), String> {
+        let bytes = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+        self.add(name, &bytes)
+    }
+
+    // Copied line by line so rotated-size logs aren't read into memory whole
+    fn add_log(&mut self, name: &str, path: &Path) -> Result<(), String> {
+        let file = File::open(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        self.zip
+            .start_file(name, self.options)
+            .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+        for line in BufReader::new(file).split(b'\n') {
+            let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let line = crash_reporter::redact(&String::from_utf8_lossy(&line));
+            writeln!(self.zip, "{}", line).map_err(|e| format!("Failed to add {}: {}", name, e))?;
+        }
+        Ok(())
+    }
+}
+
+// Everything collected up front, so the zip can be written off the async runtime
+struct Contents {
+    system: SystemInfo,
+    app: AppInfo,
+    history: Vec<status_history::StatusChange>,
+    settings: Vec<(String, Value)>,
+    backend_config: Option<String>,
+    logs: Vec<PathBuf>,
+}
+
+fn collect(app: &AppHandle, state: &AppState) -> Contents {
+    let mut settings = Vec::new();
+    for name in SETTINGS_FILES {
+        let Ok(path) = store::data_file(app, name) else {
+            continue;
+        };
+        if !path.exists() {
+            continue;
+        }
+        let mut value: Value = store::load_json(&path);
+        redact_json(&mut value, false);
+        settings.push((name.to_string(), value));
+    }
+    // What spawned sidecars actually get, including defaults for what isn't saved
+    let mut effective = serde_json::to_value(state.sidecar_config.settings()).unwrap_or_default();
+    redact_json(&mut effective, false);
+    settings.push(("sidecar_config.effective.json".to_string(), effective));
+
+    let backend_config = app
+        .path()
+        .home_dir()
+        .ok()
+        .and_then(|home| std::fs::read_to_string(home.join(".config").join("bb-stream").join("config.yaml")).ok())
+        .map(|text| redact_yaml(&text));
+
+    let mut logs: Vec<PathBuf> = app
+        .path()
+        .app_log_dir()
+        .ok()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+                .collect()
+        })
+        .unwrap_or_default();
+    logs.sort();
+
+    Contents {
+        system: system_info(app, state),
+        app: app_info(app, state),
+        history: status_history::recent(state, usize::MAX),
+        settings,
+        backend_config,
+        logs,
+    }
+}
+
+fn write_bundle(path: &Path, contents: Contents) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut bundle = Bundle {
+        zip: ZipWriter::new(file),
+        options: SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+    };
+    bundle.add("README.txt", README.as_bytes())?;
+    bundle.add_json("system.json", &contents.system)?;
+    bundle.add_json("app-info.json", &contents.app)?;
+    bundle.add_json("status-history.json", &contents.history)?;
+    for (name, value) in &contents.settings {
+        bundle.add_json(&format!("config/{}", name), value)?;
+    }
+    if let Some(text) = &contents.backend_config {
+        bundle.add("config/config.yaml", text.as_bytes())?;
+    }
+    for log in &contents.logs {
+        let Some(name) = log.file_name() else {
+            continue;
+        };
+        let name = format!("logs/{}", name.to_string_lossy());
+        // A log that can't be read shouldn't cost the rest of the bundle
+        if let Err(e) = bundle.add_log(&name, log) {
+            log::warn!("Leaving {} out of the diagnostics: {}", log.display(), e);
+        }
+    }
+    bundle
+        .zip
+        .finish()
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+// Write a diagnostics zip into `dest` (the downloads folder by default) and show it
+pub async fn export(app: &AppHandle, dest: Option<PathBuf>) -> Result<PathBuf, String> {
+    let dest = match dest {
+        Some(dest) => dest,
+        None => app
+            .path()
+            .download_dir()
+            .or_else(|_| app.path().home_dir())
+            .map_err(|e| format!("Failed to resolve the downloads folder: {}", e))?,
+    };
+    if !dest.is_dir() {
+        return Err(format!("{} is not a folder", dest.display()));
+    }
+    let now = chrono::Local::now();
+    let path = dest.join(format!("bb-stream-diagnostics-{}.zip", now.format("%Y%m%d-%H%M%S")));
+
+    let state = Arc::clone(&app.state::<Arc<AppState>>());
+    let contents = collect(app, &state);
+    let target = path.clone();
+    tauri::async_runtime::spawn_blocking(move || write_bundle(&target, contents))
+        .await
+        .map_err(|e| format!("Failed to write diagnostics: {}", e))??;
+    log::info!("Wrote diagnostics to {}", path.display());
+
+    if let Err(e) = file_manager::reveal(&path) {
+        log::warn!("{}", e);
+    }
+    Ok(path)
+}
+
+// Bundle logs, status history, redacted settings and version and port info into one
+// zip for a bug report. Returns the zip's path.
+#[tauri::command]
+pub async fn export_diagnostics(app: AppHandle, dest: Option<String>) -> Result<String, AppError> {
+    let path = export(&app, dest.map(PathBuf::from)).await?;
+    Ok(path.display().to_string())
+}
+
+// Versions, platform, folders and port of this install, for an About panel
+#[tauri::command]
+pub fn get_app_info(app: AppHandle, state: tauri::State<Arc<AppState>>) -> AppInfo {
+    app_info(&app, &state)
+}
@@ -0,0 +1,309 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::backend::SourceChangePolicy;
+use crate::bandwidth::Throttle;
+use crate::error::AppError;
+use crate::notifications::{self, Notification};
+use crate::transfer_queue::Direction;
+use crate::{auth, backend, emit_to_main, import, network, operations, store, suggestions, transfers, version, AppState};
+
+pub const DESTINATION_FILE: &str = "drop_destination.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Destination {
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+}
+
+#[derive(Default)]
+struct DropBatch {
+    cancel: Arc<AtomicBool>,
+    // Objects already uploaded, deleted again if the batch is undone
+    uploaded: Vec<(String, String)>,
+}
+
+#[derive(Clone, Serialize)]
+struct DropBatchEvent {
+    id: String,
+    destination: Destination,
+    files: usize,
+    uploaded: usize,
+    failed: usize,
+    // Why the most recent failed file failed, e.g. "source-changed: ..."
+    last_error: Option<String>,
+    done: bool,
+}
+
+// Files dropped on the dock icon are uploaded to the folder open in the main window,
+// or to the configured default destination when no folder is open. Tray icons don't
+// receive drops on any platform Tauri supports, so the dock is the only target.
+pub struct DockDrop {
+    current: Mutex<Option<Destination>>,
+    default: Mutex<Option<Destination>>,
+    batches: Mutex<HashMap<String, DropBatch>>,
+}
+
+impl DockDrop {
+    pub fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+            default: Mutex::new(None),
+            batches: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, DESTINATION_FILE) {
+        Ok(path) => *state.dock_drop.default.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load drop destination: {}", e),
+    }
+}
+
+// Remember the folder the frontend is showing so drops land there
+#[tauri::command]
+pub fn set_current_folder(state: tauri::State<Arc<AppState>>, bucket: Option<String>, prefix: Option<String>) {
+    *state.dock_drop.current.lock().unwrap() = bucket.map(|bucket| Destination {
+        bucket,
+        prefix: prefix.unwrap_or_default(),
+    });
+}
+
+#[tauri::command]
+pub fn get_drop_destination(state: tauri::State<Arc<AppState>>) -> Option<Destination> {
+    state.dock_drop.default.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_drop_destination(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    destination: Option<Destination>,
+) -> Result<(), AppError> {
+    *state.dock_drop.default.lock().unwrap() = destination.clone();
+    Ok(store::save_json(&store::data_file(&app, DESTINATION_FILE)?, &destination)?)
+}
+
+// Queue dropped files and folders for upload
+pub fn enqueue(app: &AppHandle, paths: Vec<PathBuf>) {
+    let state = Arc::clone(&app.state::<Arc<AppState>>());
+    if paths.is_empty() {
+        return;
+    }
+
+    let destination = state
+        .dock_drop
+        .current
+        .lock()
+        .unwrap()
+        .clone()
+        .or_else(|| state.dock_drop.default.lock().unwrap().clone());
+    if let Err(e) = version::ensure_compatible(&state) {
+        notifications::notify(app, &state, Notification::new("Nothing uploaded", e.to_string()), None);
+        return;
+    }
+    let Some(destination) = destination else {
+        notifications::notify(
+            app,
+            &state,
+            Notification::new(
+                "Nothing uploaded",
+                "Open a folder or choose a default upload destination, then drop the files again.",
+            ),
+            None,
+        );
+        return;
+    };
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    // Expand folders; keys keep the dropped folder's name as their first segment
+    let mut files: Vec<(PathBuf, String)> = Vec::new();
+    for path in paths {
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        if path.is_dir() {
+            match import::walk_local(&path, &cancel) {
+                Ok(entries) => files.extend(
+                    entries
+                        .into_iter()
+                        .map(|e| (path.join(&e.key), format!("{}/{}", name, e.key))),
+                ),
+                Err(e) => log::warn!("Skipping dropped folder: {}", e),
+            }
+        } else {
+            files.push((path, name));
+        }
+    }
+
+    state.dock_drop.batches.lock().unwrap().insert(
+        id.clone(),
+        DropBatch {
+            cancel: Arc::clone(&cancel),
+            uploaded: Vec::new(),
+        },
+    );
+
+    let folder = if destination.prefix.is_empty() {
+        destination.bucket.clone()
+    } else {
+        format!("{}/{}", destination.bucket, destination.prefix.trim_end_matches('/'))
+    };
+    let batch_id = id.clone();
+    notifications::notify(
+        app,
+        &state,
+        Notification::new("Uploading dropped files", format!("{} file(s) to {}", files.len(), folder))
+            .action("undo", "Undo"),
+        Some(Box::new(move |app: &AppHandle, action: &str| {
+            if action == "undo" {
+                undo(app, &batch_id);
+            }
+        })),
+    );
+
+    let operation = operations::begin(
+        app,
+        Some(id.clone()),
+        "dock_drop",
+        format!("Upload dropped files to {}", folder),
+        Arc::clone(&cancel),
+    );
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        upload_batch(&app, &state, &id, &destination, files, &cancel).await;
+        drop(operation);
+    });
+}
+
+async fn upload_batch(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    id: &str,
+    destination: &Destination,
+    files: Vec<(PathBuf, String)>,
+    cancel: &Arc<AtomicBool>,
+) {
+    transfers::track(
+        app,
+        id,
+        "dock_drop",
+        files
+            .iter()
+            .map(|(path, name)| (name.clone(), std::fs::metadata(path).map(|m| m.len()).unwrap_or(0), false))
+            .collect(),
+    );
+    let mut event = DropBatchEvent {
+        id: id.to_string(),
+        destination: destination.clone(),
+        files: files.len(),
+        uploaded: 0,
+        failed: 0,
+        last_error: None,
+        done: false,
+    };
+    let mut learned = Vec::new();
+
+    let mut queue = VecDeque::from(files);
+    while let Some((path, name)) = queue.pop_front() {
+        if cancel.load(Ordering::SeqCst) {
+            break;
+        }
+        while !state.is_healthy() && !state.shutdown.load(Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if !network::wait_for_wifi(app, state, id, &name, size, cancel).await {
+            break;
+        }
+
+        let base_url = state.base_url();
+        let client = backend::shared_client(&base_url);
+        let uploaded = Arc::new(AtomicU64::new(0));
+        transfers::start_file(state, id, &name, &uploaded);
+        let result = match import::dest_key(&destination.prefix, &name) {
+            Ok(key) => backend::upload_local_file(
+                &client,
+                &base_url,
+                &destination.bucket,
+                &key,
+                &path,
+                SourceChangePolicy::Restart,
+                &Throttle::new(state, Direction::Upload, Some(id)),
+                cancel,
+                &uploaded,
+            )
+            .await
+            .map(|()| key),
+            Err(e) => Err(e),
+        };
+        transfers::finish_file(state, id, &name, &result, cancel.load(Ordering::SeqCst));
+
+        match result {
+            Ok(key) => {
+                event.uploaded += 1;
+                let recorded = state
+                    .dock_drop
+                    .batches
+                    .lock()
+                    .unwrap()
+                    .get_mut(id)
+                    .map(|batch| batch.uploaded.push((destination.bucket.clone(), key.clone())))
+                    .is_some();
+                // Undone while this file was in flight
+                if !recorded {
+                    let _ = backend::delete_object(&client, &base_url, &destination.bucket, &key).await;
+                    break;
+                }
+                learned.push(name);
+            }
+            Err(_) if cancel.load(Ordering::SeqCst) => break,
+            // Rejected credentials: the file waits with the rest until the user signs in
+            Err(e) if auth::report(app, state, &e) => {
+                queue.push_front((path, name));
+                continue;
+            }
+            Err(e) => {
+                log::error!("Dropped file upload failed: {}", e);
+                event.failed += 1;
+                event.last_error = Some(e);
+            }
+        }
+        emit_to_main(app, "dock-drop-progress", event.clone());
+    }
+
+    transfers::finish(state, id);
+    suggestions::learn(app, state, &destination.bucket, &destination.prefix, &learned);
+    event.done = true;
+    emit_to_main(app, "dock-drop-progress", event);
+}
+
+// Stop a drop batch and delete whatever it already uploaded
+fn undo(app: &AppHandle, id: &str) {
+    let state = Arc::clone(&app.state::<Arc<AppState>>());
+    let Some(batch) = state.dock_drop.batches.lock().unwrap().remove(id) else {
+        return;
+    };
+    batch.cancel.store(true, Ordering::SeqCst);
+    log::info!("Undoing dock drop {} ({} uploaded)", id, batch.uploaded.len());
+
+    tauri::async_runtime::spawn(async move {
+        let base_url = state.base_url();
+        let client = backend::shared_client(&base_url);
+        for (bucket, key) in batch.uploaded {
+            if let Err(e) = backend::delete_object(&client, &base_url, &bucket, &key).await {
+                log::error!("Failed to undo upload: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,166 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::{backend, emit_to_main, profiles, store, AppState, Sidecar, DEFAULT_PROFILE};
+
+// How long a sidecar gets to checkpoint its jobs before it is restarted regardless
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long the replacement gets to become healthy before its jobs are given up on
+// until the next launch
+const RESUME_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    // Asking the sidecar to checkpoint its running jobs
+    Draining,
+    // Checkpointed; waiting for the new process
+    Restarting,
+    Resumed,
+    // The jobs are kept on disk and tried again at the next launch
+    Failed,
+}
+
+// Sent as `backend-restart-progress` while a restart carries running jobs over
+#[derive(Clone, Debug, Serialize)]
+pub struct RestartProgress {
+    pub profile: String,
+    pub phase: Phase,
+    // Sync and watch jobs being carried over
+    pub jobs: usize,
+    pub error: Option<String>,
+}
+
+fn checkpoint_file(profile: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        "restart_checkpoint.json".to_string()
+    } else {
+        format!("restart_checkpoint-{}.json", profiles::slug(profile))
+    }
+}
+
+// Jobs in a checkpoint, which is otherwise passed through unread
+fn job_count(checkpoint: &serde_json::Value) -> usize {
+    ["syncs", "watches"]
+        .iter()
+        .filter_map(|field| checkpoint.get(field)?.as_array().map(Vec::len))
+        .sum()
+}
+
+fn progress(app: &AppHandle, sidecar: &Sidecar, phase: Phase, jobs: usize, error: Option<String>) {
+    let profile = sidecar.profile.clone();
+    emit_to_main(app, "backend-restart-progress", RestartProgress { profile, phase, jobs, error });
+}
+
+// Before a deliberate restart: have a healthy sidecar stop taking new jobs and
+// checkpoint the running ones, saved to disk in case the app goes down too. None when
+// there is nothing to carry over or the sidecar can't drain.
+pub async fn drain(app: &AppHandle, state: &AppState, sidecar: &Sidecar) -> Option<serde_json::Value> {
+    let supported = state.versions.capabilities().is_some_and(|c| c.drain);
+    if !supported || sidecar.external_url().is_some() || !sidecar.is_healthy.load(Ordering::SeqCst) {
+        return None;
+    }
+    progress(app, sidecar, Phase::Draining, 0, None);
+
+    let base_url = sidecar.base_url();
+    let drained = async {
+        let resp = backend::shared_client(&base_url)
+            .post(backend::api_url(&base_url, &["drain"])?)
+            .timeout(DRAIN_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("Drain returned status: {}", resp.status()));
+        }
+        resp.json::<serde_json::Value>().await.map_err(|e| e.to_string())
+    };
+    let checkpoint = match drained.await {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => {
+            log::warn!("Sidecar for profile {} didn't drain; running jobs are lost: {}", sidecar.profile, e);
+            progress(app, sidecar, Phase::Failed, 0, Some(e));
+            return None;
+        }
+    };
+
+    let jobs = job_count(&checkpoint);
+    if jobs == 0 {
+        return None;
+    }
+    log::info!("Carrying {} jobs of profile {} over the restart", jobs, sidecar.profile);
+    let saved = store::data_file(app, &checkpoint_file(&sidecar.profile))
+        .and_then(|path| store::save_json(&path, &checkpoint));
+    if let Err(e) = saved {
+        log::error!("Failed to save restart checkpoint: {}", e);
+    }
+    progress(app, sidecar, Phase::Restarting, jobs, None);
+    Some(checkpoint)
+}
+
+// Once the restarted sidecar is healthy, start the checkpointed jobs on it
+pub fn spawn_resume(app: AppHandle, state: Arc<AppState>, sidecar: Arc<Sidecar>, checkpoint: serde_json::Value) {
+    tauri::async_runtime::spawn(async move {
+        let jobs = job_count(&checkpoint);
+        let deadline = Instant::now() + RESUME_WAIT;
+        while !sidecar.is_healthy.load(Ordering::SeqCst) {
+            if state.shutdown.load(Ordering::SeqCst) {
+                return;
+            }
+            if Instant::now() >= deadline {
+                let e = "The backend didn't come back in time; jobs resume at the next launch".to_string();
+                progress(&app, &sidecar, Phase::Failed, jobs, Some(e));
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+
+        let base_url = sidecar.base_url();
+        let resumed = async {
+            let resp = backend::shared_client(&base_url)
+                .post(backend::api_url(&base_url, &["resume"])?)
+                .json(&checkpoint)
+                .timeout(DRAIN_TIMEOUT)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            if !resp.status().is_success() {
+                return Err(format!("Resume returned status: {}", resp.status()));
+            }
+            Ok(())
+        };
+        match resumed.await {
+            Ok(()) => {
+                log::info!("Resumed {} jobs of profile {} after the restart", jobs, sidecar.profile);
+                if let Ok(path) = store::data_file(&app, &checkpoint_file(&sidecar.profile)) {
+                    let _ = std::fs::remove_file(path);
+                }
+                progress(&app, &sidecar, Phase::Resumed, jobs, None);
+            }
+            Err(e) => {
+                log::error!("Failed to resume jobs of profile {}: {}", sidecar.profile, e);
+                progress(&app, &sidecar, Phase::Failed, jobs, Some(e));
+            }
+        }
+    });
+}
+
+// Resume the default profile's jobs if the app went down in the middle of a restart
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let Ok(path) = store::data_file(app, &checkpoint_file(DEFAULT_PROFILE)) else {
+        return;
+    };
+    if !path.exists() {
+        return;
+    }
+    let Some(checkpoint) = store::load_json::<Option<serde_json::Value>>(&path) else {
+        return;
+    };
+    log::info!("Resuming {} jobs checkpointed before the last exit", job_count(&checkpoint));
+    spawn_resume(app.clone(), Arc::clone(state), state.sidecar(), checkpoint);
+}
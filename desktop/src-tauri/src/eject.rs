@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sysinfo::Disks;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::import::{self, ImportJob, ImportSource, ImportStatus};
+use crate::notifications::{self, Notification};
+use crate::{backend, AppState};
+
+// Tries to lock a volume before ejecting it, half a second apart
+#[cfg(target_os = "windows")]
+const EJECT_LOCK_ATTEMPTS: u32 = 10;
+
+// A mounted removable volume, e.g. a camera's SD card
+#[derive(Clone, Debug)]
+struct RemovableVolume {
+    mount_point: PathBuf,
+    // OS device name, e.g. /dev/sdb1
+    device: String,
+}
+
+// The removable volume holding `path`, if it is on one
+fn removable_volume(path: &Path) -> Option<RemovableVolume> {
+    let disks = Disks::new_with_refreshed_list();
+    let disk = disks
+        .list()
+        .iter()
+        .filter(|d| path.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())?;
+    disk.is_removable().then(|| RemovableVolume {
+        mount_point: disk.mount_point().to_path_buf(),
+        device: disk.name().to_string_lossy().to_string(),
+    })
+}
+
+fn import_root(job: &ImportJob) -> Option<&Path> {
+    match &job.source {
+        ImportSource::Local { path } => Some(path),
+        ImportSource::S3(_) => None,
+    }
+}
+
+// Check that every file still on the card is in the bucket with the same size, so
+// nothing is lost if the card is wiped after ejecting
+async fn verify(state: &AppState, job: &ImportJob, root: &Path) -> Result<(), String> {
+    if !state.is_healthy() {
+        return Err("Backend is unavailable, so the import can't be verified".to_string());
+    }
+    let owned = root.to_path_buf();
+    let entries = tauri::async_runtime::spawn_blocking(move || import::walk_local(&owned, &AtomicBool::new(false)))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let base_url = state.base_url();
+    let client = backend::client_builder(&base_url)
+        .timeout(Duration::from_secs(60))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let uploaded: HashMap<String, i64> = backend::list_objects(&client, &base_url, &job.bucket, &job.prefix)
+        .await?
+        .into_iter()
+        .map(|o| (o.name, o.size))
+        .collect();
+
+    let missing = entries
+        .iter()
+        .filter(|e| {
+            let key = import::dest_key(&job.prefix, &e.key).ok();
+            key.and_then(|key| uploaded.get(&key).copied()) != Some(e.size as i64)
+        })
+        .count();
+    if missing > 0 {
+        return Err(format!("{} file(s) on the card are missing from {} or differ", missing, job.bucket));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn run(command: &mut Command) -> Result<(), String> {
+    let output = command.output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+// Unmounting flushes pending writes before the volume goes away
+#[cfg(target_os = "macos")]
+fn eject_volume(volume: &RemovableVolume) -> Result<(), String> {
+    run(Command::new("diskutil").arg("eject").arg(&volume.mount_point))
+}
+
+#[cfg(target_os = "windows")]
+fn eject_volume(volume: &RemovableVolume) -> Result<(), String> {
+    use std::ffi::c_void;
+    use windows::core::HSTRING;
+    use windows::Win32::Foundation::{CloseHandle, GENERIC_READ, GENERIC_WRITE};
+    use windows::Win32::Storage::FileSystem::{
+        CreateFileW, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+    };
+    use windows::Win32::System::Ioctl::{
+        FSCTL_DISMOUNT_VOLUME, FSCTL_LOCK_VOLUME, IOCTL_STORAGE_EJECT_MEDIA, IOCTL_STORAGE_MEDIA_REMOVAL,
+        PREVENT_MEDIA_REMOVAL,
+    };
+    use windows::Win32::System::IO::DeviceIoControl;
+
+    // The volume's device, e.g. \\.\E:
+    let drive = volume.mount_point.to_string_lossy().trim_end_matches('\\').to_string();
+    let device = unsafe {
+        CreateFileW(
+            &HSTRING::from(format!(r"\\.\{}", drive)),
+            (GENERIC_READ | GENERIC_WRITE).0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAGS_AND_ATTRIBUTES(0),
+            None,
+        )
+    }
+    .map_err(|e| e.to_string())?;
+    let control = |code: u32, input: Option<*const c_void>, size: u32| {
+        let mut returned = 0u32;
+        unsafe { DeviceIoControl(device, code, input, size, None, 0, Some(&mut returned), None) }
+    };
+
+    // Locking fails while another program has a file open on the volume; give it a
+    // moment to let go
+    let mut locked = control(FSCTL_LOCK_VOLUME, None, 0);
+    for _ in 0..EJECT_LOCK_ATTEMPTS {
+        if locked.is_ok() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(500));
+        locked = control(FSCTL_LOCK_VOLUME, None, 0);
+    }
+    let allow_removal = PREVENT_MEDIA_REMOVAL { PreventMediaRemoval: false };
+    let result = locked
+        .and_then(|()| control(FSCTL_DISMOUNT_VOLUME, None, 0))
+        .and_then(|()| {
+            control(
+                IOCTL_STORAGE_MEDIA_REMOVAL,
+                Some(&allow_removal as *const PREVENT_MEDIA_REMOVAL as *const c_void),
+                std::mem::size_of::<PREVENT_MEDIA_REMOVAL>() as u32,
+            )
+        })
+        .and_then(|()| control(IOCTL_STORAGE_EJECT_MEDIA, None, 0));
+    unsafe {
+        let _ = CloseHandle(device);
+    }
+    result.map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn eject_volume(volume: &RemovableVolume) -> Result<(), String> {
+    run(Command::new("udisksctl").args(["unmount", "-b", &volume.device]))
+        .map_err(|e| format!("{} (is udisks2 installed?)", e))?;
+    // Cutting power lets the card be pulled safely; not every reader supports it
+    if let Err(e) = run(Command::new("udisksctl").args(["power-off", "-b", &volume.device])) {
+        log::warn!("Unmounted {} but could not power it off: {}", volume.device, e);
+    }
+    Ok(())
+}
+
+// Verify a completed import from removable media and eject the volume it came from
+pub async fn eject_import_source_for(state: &AppState, id: &str) -> Result<String, String> {
+    let job = state.imports.job(id).ok_or_else(|| format!("Unknown import {}", id))?;
+    if job.status != ImportStatus::Completed {
+        return Err(format!("Import {} hasn't completed", id));
+    }
+    let root = import_root(&job).ok_or("Only imports from a local folder can be ejected")?;
+    let volume = removable_volume(root)
+        .ok_or_else(|| format!("{} is not on a mounted removable volume", root.display()))?;
+
+    verify(state, &job, root).await?;
+    log::info!("Ejecting {} after import {}", volume.mount_point.display(), id);
+    let name = volume.mount_point.to_string_lossy().to_string();
+    tauri::async_runtime::spawn_blocking(move || eject_volume(&volume))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to eject {}: {}", name, e))?;
+    Ok(name)
+}
+
+async fn eject_and_notify(app: &AppHandle, id: &str) {
+    let state = Arc::clone(&app.state::<Arc<AppState>>());
+    let notification = match eject_import_source_for(&state, id).await {
+        Ok(name) => Notification::new("Safe to remove", format!("{} was verified and ejected.", name)),
+        Err(e) => {
+            log::warn!("Not ejecting after import {}: {}", id, e);
+            Notification::new("Card not ejected", e)
+        }
+    };
+    notifications::notify(app, &state, notification, None);
+}
+
+// Called when an import completes: eject its card right away when the job asked for
+// that, otherwise offer to
+pub fn import_completed(app: &AppHandle, state: &AppState, job: &ImportJob) {
+    let Some(volume) = import_root(job).and_then(removable_volume) else {
+        return;
+    };
+    let id = job.id.clone();
+    if job.eject_when_done {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move { eject_and_notify(&app, &id).await });
+        return;
+    }
+    notifications::notify(
+        app,
+        state,
+        Notification::new(
+            "Import complete",
+            format!("Everything was copied from {}.", volume.mount_point.display()),
+        )
+        .action("eject", "Eject"),
+        Some(Box::new(move |app: &AppHandle, action: &str| {
+            if action == "eject" {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move { eject_and_notify(&app, &id).await });
+            }
+        })),
+    );
+}
+
+// Menu action: eject the card of the most recent completed import still mounted
+pub fn eject_latest(app: &AppHandle) {
+    let state = app.state::<Arc<AppState>>();
+    let latest = state.imports.jobs().into_iter().rev().find(|job| {
+        job.status == ImportStatus::Completed && import_root(job).and_then(removable_volume).is_some()
+    });
+    match latest {
+        Some(job) => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move { eject_and_notify(&app, &job.id).await });
+        }
+        None => notifications::notify(
+            app,
+            &state,
+            Notification::new("Nothing to eject", "No completed import came from a card that is still inserted."),
+            None,
+        ),
+    }
+}
+
+#[tauri::command]
+pub async fn eject_import_source(state: tauri::State<'_, Arc<AppState>>, id: String) -> Result<String, AppError> {
+    Ok(eject_import_source_for(&state, &id).await?)
+}
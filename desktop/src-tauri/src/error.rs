@@ -0,0 +1,132 @@
+use std::fmt;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::BackendStatus;
+
+// What a command failed with. The frontend gets `kind` to branch on and `message` to
+// show, plus the fields of the variant. Helpers may keep returning String errors: those
+// arrive as `other`, and the `?` conversions below go both ways.
+#[derive(Clone, Debug)]
+pub enum AppError {
+    // No port was free for the sidecar to listen on
+    PortUnavailable { port: u16 },
+    // The sidecar process couldn't be started
+    SidecarSpawnFailed { source: String },
+    // The backend isn't up or refused the connection; nothing was sent
+    BackendUnreachable { message: String },
+    // The request was sent, but its answer never arrived
+    Interrupted { message: String },
+    // The backend answered with an error status
+    Status { status: u16, message: String },
+    // The account's key was rejected or lacks access
+    Unauthorized { message: String },
+    // The backend speaks a different API than the shell
+    Incompatible { message: String },
+    // The backend can't do this at all
+    Unsupported { message: String },
+    // Not while transfers or another operation are running
+    Busy { message: String },
+    // Something about the request itself is wrong
+    Invalid { message: String },
+    // Stopped at the user's request
+    Cancelled,
+    // Gave up waiting for the backend; `status` is where it stood then, None before
+    // its first status
+    Timeout {
+        timeout_ms: u64,
+        status: Option<BackendStatus>,
+    },
+    Other { message: String },
+}
+
+impl AppError {
+    pub fn invalid(message: impl Into<String>) -> Self {
+        AppError::Invalid { message: message.into() }
+    }
+
+    pub fn busy(message: impl Into<String>) -> Self {
+        AppError::Busy { message: message.into() }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::PortUnavailable { .. } => "port_unavailable",
+            AppError::SidecarSpawnFailed { .. } => "sidecar_spawn_failed",
+            AppError::BackendUnreachable { .. } => "backend_unreachable",
+            AppError::Interrupted { .. } => "interrupted",
+            AppError::Status { .. } => "status",
+            AppError::Unauthorized { .. } => "unauthorized",
+            AppError::Incompatible { .. } => "incompatible",
+            AppError::Unsupported { .. } => "unsupported",
+            AppError::Busy { .. } => "busy",
+            AppError::Invalid { .. } => "invalid",
+            AppError::Cancelled => "cancelled",
+            AppError::Timeout { .. } => "timeout",
+            AppError::Other { .. } => "other",
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::PortUnavailable { port } => write!(f, "Port {} and the ones to fall back on are taken", port),
+            AppError::SidecarSpawnFailed { source } => write!(f, "The backend couldn't be started: {}", source),
+            AppError::Cancelled => f.write_str("Cancelled"),
+            AppError::Timeout { timeout_ms, .. } => {
+                write!(f, "The backend wasn't healthy within {} ms", timeout_ms)
+            }
+            AppError::BackendUnreachable { message }
+            | AppError::Interrupted { message }
+            | AppError::Status { message, .. }
+            | AppError::Unauthorized { message }
+            | AppError::Incompatible { message }
+            | AppError::Unsupported { message }
+            | AppError::Busy { message }
+            | AppError::Invalid { message }
+            | AppError::Other { message } => f.write_str(message),
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("AppError", 4)?;
+        error.serialize_field("kind", self.kind())?;
+        error.serialize_field("message", &self.to_string())?;
+        match self {
+            AppError::PortUnavailable { port } => error.serialize_field("port", port)?,
+            AppError::SidecarSpawnFailed { source } => error.serialize_field("source", source)?,
+            AppError::Status { status, .. } => error.serialize_field("status", status)?,
+            AppError::Timeout { timeout_ms, status } => {
+                error.serialize_field("timeout_ms", timeout_ms)?;
+                error.serialize_field("backend_status", status)?;
+            }
+            _ => {}
+        }
+        error.end()
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Other { message }
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Other {
+            message: message.to_string(),
+        }
+    }
+}
+
+// For helpers that still return String errors
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,195 @@
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::{backend, quit, replay, AppState, Sidecar};
+
+// How long connecting may take, and how often a quiet connection checks that it still
+// belongs to the active sidecar
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+// Reconnect delays double from the first up to the last
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+// Sent to every window as `stream:status` when the connection comes or goes
+#[derive(Clone, Debug, Serialize)]
+pub struct StreamStatus {
+    pub profile: String,
+    pub connected: bool,
+}
+
+// The one connection to the active backend's live events, which every window hears
+// through Tauri events instead of opening its own
+pub struct EventStream {
+    status: Mutex<StreamStatus>,
+}
+
+impl EventStream {
+    pub fn new() -> Self {
+        Self {
+            status: Mutex::new(StreamStatus {
+                profile: String::new(),
+                connected: false,
+            }),
+        }
+    }
+}
+
+fn set_status(app: &AppHandle, state: &AppState, profile: &str, connected: bool) {
+    let status = StreamStatus {
+        profile: profile.to_string(),
+        connected,
+    };
+    *state.event_stream.status.lock().unwrap() = status.clone();
+    replay::record(state, "stream:status", &status);
+    let _ = app.emit("stream:status", status);
+}
+
+// The backend's live-event endpoint, with the credential from `base_url` in the token
+// query parameter it accepts
+fn ws_url(base_url: &str) -> Result<reqwest::Url, String> {
+    let mut url = backend::api_url(base_url, &["ws"])?;
+    let token = url
+        .password()
+        .map(|password| percent_encoding::percent_decode_str(password).decode_utf8_lossy().into_owned());
+    let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+    url.set_scheme(scheme).map_err(|_| format!("Invalid backend URL {}", base_url))?;
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    if let Some(token) = token {
+        url.query_pairs_mut().append_pair("token", &token);
+    }
+    Ok(url)
+}
+
+// Re-emit a backend event to every window under a namespaced name, keeping the
+// backend's own type in the payload
+fn forward(app: &AppHandle, state: &AppState, text: &str) {
+    let event: serde_json::Value = match serde_json::from_str(text) {
+        Ok(event) => event,
+        Err(e) => {
+            log::debug!("Ignoring unreadable backend event: {}", e);
+            return;
+        }
+    };
+    let kind = event.get("type").and_then(|kind| kind.as_str()).unwrap_or_default();
+    quit::observe(state, kind, &event);
+    let name = match kind {
+        // Only about the connection itself
+        "connected" | "pong" => return,
+        kind if kind.ends_with("_progress") => "stream:progress",
+        kind if kind.ends_with("_complete") => "stream:completed",
+        _ => "stream:event",
+    };
+    let _ = app.emit(name, event);
+}
+
+// Whether the connection opened as `instance_id` still belongs to the active sidecar
+fn is_current(state: &AppState, sidecar: &Sidecar, instance_id: &str) -> bool {
+    !state.shutdown.load(Ordering::SeqCst)
+        && state.is_active(sidecar)
+        && *sidecar.instance_id.lock().unwrap() == instance_id
+}
+
+// Pass events on until the connection drops, or Ok once the sidecar was replaced
+async fn relay<S>(
+    app: &AppHandle,
+    state: &AppState,
+    sidecar: &Sidecar,
+    mut ws: WebSocketStream<S>,
+) -> Result<(), String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let instance_id = sidecar.instance_id.lock().unwrap().clone();
+    set_status(app, state, &sidecar.profile, true);
+    log::debug!("Receiving live events from profile {}", sidecar.profile);
+    let result = loop {
+        // Pings are answered while reading
+        match tokio::time::timeout(CHECK_INTERVAL, ws.next()).await {
+            Ok(Some(Ok(Message::Text(text)))) => forward(app, state, text.as_str()),
+            Ok(Some(Ok(Message::Close(_)))) | Ok(None) => break Err("the backend closed the connection".to_string()),
+            Ok(Some(Ok(_))) | Err(_) => {}
+            Ok(Some(Err(e))) => break Err(e.to_string()),
+        }
+        if !is_current(state, sidecar, &instance_id) {
+            break Ok(());
+        }
+    };
+    let _ = ws.close(None).await;
+    quit::stream_lost(state);
+    set_status(app, state, &sidecar.profile, false);
+    result
+}
+
+// Connect to `sidecar`'s live events and relay them. `connected` is set once the
+// connection was made, so a drop after that is retried right away.
+async fn run(app: &AppHandle, state: &AppState, sidecar: &Sidecar, connected: &mut bool) -> Result<(), String> {
+    let base_url = sidecar.base_url();
+    let url = ws_url(&base_url)?;
+    #[cfg(unix)]
+    if let Some(path) = backend::socket_path(&base_url) {
+        let handshake = async {
+            let stream = tokio::net::UnixStream::connect(&path).await.map_err(|e| e.to_string())?;
+            tokio_tungstenite::client_async(url.as_str(), stream)
+                .await
+                .map_err(|e| e.to_string())
+        };
+        let (ws, _) = tokio::time::timeout(CONNECT_TIMEOUT, handshake)
+            .await
+            .map_err(|_| "timed out connecting".to_string())??;
+        *connected = true;
+        return relay(app, state, sidecar, ws).await;
+    }
+    let (ws, _) = tokio::time::timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(url.as_str()))
+        .await
+        .map_err(|_| "timed out connecting".to_string())?
+        .map_err(|e| e.to_string())?;
+    *connected = true;
+    relay(app, state, sidecar, ws).await
+}
+
+// Keep a connection to whichever sidecar is active, reconnecting after restarts and
+// profile switches
+pub fn spawn(app: AppHandle, state: Arc<AppState>) {
+    tauri::async_runtime::spawn(async move {
+        let mut delay = RECONNECT_MIN;
+        while !state.shutdown.load(Ordering::SeqCst) {
+            let sidecar = state.sidecar();
+            if !sidecar.is_healthy.load(Ordering::SeqCst) {
+                tokio::time::sleep(CHECK_INTERVAL).await;
+                continue;
+            }
+            let mut connected = false;
+            let result = run(&app, &state, &sidecar, &mut connected).await;
+            if connected {
+                delay = RECONNECT_MIN;
+            }
+            match result {
+                // Replaced; the next sidecar is connected to once healthy
+                Ok(()) => continue,
+                Err(e) => log::debug!("Live events from profile {} interrupted: {}", sidecar.profile, e),
+            }
+            tokio::time::sleep(delay).await;
+            if !connected {
+                delay = (delay * 2).min(RECONNECT_MAX);
+            }
+        }
+    });
+}
+
+// Whether live events are coming in, for windows that opened after the last
+// `stream:status`
+#[tauri::command]
+pub fn get_event_stream_status(state: tauri::State<Arc<AppState>>) -> StreamStatus {
+    state.event_stream.status.lock().unwrap().clone()
+}
@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::operations;
+
+// Kept next to the exported files so the layout is understandable without the app
+const README: &str = "\
+bb-stream local data export
+===========================
+
+This folder holds everything bb-stream keeps on this computer, copied as-is.
+Nothing here was uploaded anywhere.
+
+app-data/    Application state: saved accounts, remote copy and import jobs
+             (with their progress checkpoints) and the dock drop destination.
+             All files are JSON. accounts.json contains access keys in plain
+             text; store this export somewhere safe.
+cache/       Downloaded content: cached folder listings (listings/) and files
+             downloaded for printing (print/<bucket>/<key>). Files ending in
+             .part were still downloading and are incomplete.
+backend/     The backend's config.yaml (B2 credentials, default bucket, API port).
+manifest.json  Every exported file with its size, plus anything that could
+             not be read.
+";
+
+#[derive(Serialize)]
+struct ManifestEntry {
+    path: String,
+    size: u64,
+}
+
+#[derive(Serialize)]
+struct Manifest {
+    exported_at: String,
+    app_version: String,
+    files: Vec<ManifestEntry>,
+    skipped: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportReport {
+    pub path: String,
+    pub files: usize,
+    pub bytes: u64,
+    // Files that could not be copied, with the reason
+    pub skipped: Vec<String>,
+}
+
+// Copy every file under `source` into `target`, recording what was copied. Failures
+// are collected instead of aborting: a partial export beats none when things are broken.
+fn copy_tree(source: &Path, target: &Path, root: &Path, manifest: &mut Manifest, cancel: &AtomicBool) {
+    let entries = match std::fs::read_dir(source) {
+        Ok(entries) => entries,
+        Err(e) => {
+            if source.exists() {
+                manifest.skipped.push(format!("{}: {}", source.display(), e));
+            }
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        if cancel.load(Ordering::SeqCst) {
+            return;
+        }
+        let from = entry.path();
+        let to = target.join(entry.file_name());
+        if from.is_dir() {
+            copy_tree(&from, &to, root, manifest, cancel);
+        } else {
+            copy_file(&from, &to, root, manifest);
+        }
+    }
+}
+
+fn copy_file(from: &Path, to: &Path, root: &Path, manifest: &mut Manifest) {
+    let result = to
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::copy(from, to));
+    match result {
+        Ok(size) => manifest.files.push(ManifestEntry {
+            path: to.strip_prefix(root).unwrap_or(to).to_string_lossy().replace('\\', "/"),
+            size,
+        }),
+        Err(e) => manifest.skipped.push(format!("{}: {}", from.display(), e)),
+    }
+}
+
+fn export_to(
+    sources: Vec<(PathBuf, &'static str)>,
+    dest: &Path,
+    version: String,
+    cancel: &AtomicBool,
+) -> Result<ExportReport, String> {
+    let now = chrono::Local::now();
+    let root = dest.join(format!("bb-stream-export-{}", now.format("%Y%m%d-%H%M%S")));
+    std::fs::create_dir_all(&root).map_err(|e| format!("Failed to create {}: {}", root.display(), e))?;
+
+    let mut manifest = Manifest {
+        exported_at: now.to_rfc3339(),
+        app_version: version,
+        files: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for (source, name) in sources {
+        if source.is_dir() {
+            copy_tree(&source, &root.join(name), &root, &mut manifest, cancel);
+        } else if source.exists() {
+            let file_name = source.file_name().unwrap_or_default();
+            copy_file(&source, &root.join(name).join(file_name), &root, &mut manifest);
+        }
+    }
+    // Leave what was copied so far; the user can delete the folder
+    if cancel.load(Ordering::SeqCst) {
+        return Err(format!("Export cancelled; partial copy left in {}", root.display()));
+    }
+
+    std::fs::write(root.join("README.txt"), README).map_err(|e| format!("Failed to write README: {}", e))?;
+    let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    std::fs::write(root.join("manifest.json"), manifest_json)
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    Ok(ExportReport {
+        path: root.to_string_lossy().to_string(),
+        files: manifest.files.len(),
+        bytes: manifest.files.iter().map(|f| f.size).sum(),
+        skipped: manifest.skipped,
+    })
+}
+
+// Recovery hatch: copy all locally held data into a new folder under `dest`. Works
+// without the backend, so it stays usable when nothing else does.
+#[tauri::command]
+pub async fn export_local_data(
+    app: AppHandle,
+    dest: String,
+    operation_id: Option<String>,
+) -> Result<ExportReport, AppError> {
+    let dest = PathBuf::from(dest);
+    if !dest.is_dir() {
+        return Err(AppError::invalid(format!("{} is not a folder", dest.display())));
+    }
+
+    let paths = app.path();
+    let mut sources = Vec::new();
+    if let Ok(dir) = paths.app_data_dir() {
+        sources.push((dir, "app-data"));
+    }
+    if let Ok(dir) = paths.app_cache_dir() {
+        sources.push((dir, "cache"));
+    }
+    if let Ok(home) = paths.home_dir() {
+        sources.push((home.join(".config").join("bb-stream").join("config.yaml"), "backend"));
+    }
+    // Exporting into a folder being exported would copy the export into itself
+    if sources.iter().any(|(source, _)| dest.starts_with(source)) {
+        return Err(AppError::invalid("Choose a folder outside the app's own data folders"));
+    }
+    let version = app.package_info().version.to_string();
+
+    log::info!("Exporting local data to {}", dest.display());
+    let operation = operations::begin(
+        &app,
+        operation_id,
+        "export",
+        "Export local data",
+        Arc::new(AtomicBool::new(false)),
+    );
+    let cancel = Arc::clone(operation.cancel_flag());
+    let report = tauri::async_runtime::spawn_blocking(move || export_to(sources, &dest, version, &cancel))
+        .await
+        .map_err(|e| format!("Export failed: {}", e))??;
+    log::info!(
+        "Exported {} files ({} bytes) to {}, {} skipped",
+        report.files,
+        report.bytes,
+        report.path,
+        report.skipped.len()
+    );
+    Ok(report)
+}
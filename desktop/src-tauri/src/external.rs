@@ -0,0 +1,156 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::{
+    emit_backend_status, kill_sidecar, pidfile, start_sidecar_sync, store, AppState, BackendStatus, Sidecar,
+    DEFAULT_PROFILE,
+};
+
+const SETTINGS_FILE: &str = "external_server.json";
+
+// `--server <url>` (or `--server=<url>`) uses that server for this run, whatever the
+// saved preference says
+const CLI_FLAG: &str = "--server";
+
+// An already-running bb-stream server, e.g. on a NAS, that the default profile uses
+// instead of spawning the bundled sidecar
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExternalServer {
+    pub url: Option<String>,
+    // Sent as the password of Basic auth; the server accepts it like X-API-Key
+    pub api_key: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ExternalServerInfo {
+    pub url: Option<String>,
+    pub has_api_key: bool,
+    // True when the URL came from the command line rather than the preference
+    pub from_command_line: bool,
+}
+
+fn parse(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid server URL {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!("Server URL {} must be an http:// or https:// address", url));
+    }
+    Ok(parsed)
+}
+
+// Base URL requests go to, with the API key embedded so every client sends it
+fn base_url(server: &ExternalServer) -> Result<Option<String>, String> {
+    let Some(url) = &server.url else {
+        return Ok(None);
+    };
+    let mut url = parse(url)?;
+    if let Some(key) = server.api_key.as_deref().filter(|k| !k.is_empty()) {
+        if url.set_username("shell").is_err() || url.set_password(Some(key)).is_err() {
+            return Err(format!("Can't add credentials to {}", display_url(url.as_str())));
+        }
+    }
+    Ok(Some(url.as_str().trim_end_matches('/').to_string()))
+}
+
+// A base URL with any credentials removed, for logs and the webview
+pub fn display_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.as_str().trim_end_matches('/').to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+fn from_args() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == CLI_FLAG {
+            return args.next();
+        }
+        if let Some(url) = arg.strip_prefix(CLI_FLAG).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(url.to_string());
+        }
+    }
+    None
+}
+
+fn saved(app: &AppHandle) -> ExternalServer {
+    match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load external server setting: {}", e);
+            ExternalServer::default()
+        }
+    }
+}
+
+fn default_sidecar(state: &AppState) -> Arc<Sidecar> {
+    Arc::clone(&state.sidecars.lock().unwrap()[DEFAULT_PROFILE])
+}
+
+// Point the default profile at the configured server before it would be spawned
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let mut server = saved(app);
+    if let Some(url) = from_args() {
+        server.url = Some(url);
+    }
+    match base_url(&server) {
+        Ok(url) => *default_sidecar(state).external_url.lock().unwrap() = url,
+        Err(e) => log::error!("Ignoring external server: {}", e),
+    }
+}
+
+#[tauri::command]
+pub fn get_external_server(app: AppHandle, state: tauri::State<Arc<AppState>>) -> ExternalServerInfo {
+    let saved = saved(&app);
+    let from_command_line = from_args().is_some();
+    ExternalServerInfo {
+        url: default_sidecar(&state).external_url().map(|url| display_url(&url)),
+        has_api_key: saved.api_key.is_some(),
+        from_command_line,
+    }
+}
+
+// Use an external server from now on, or go back to the bundled sidecar when `url` is
+// None. Takes effect right away: the current sidecar or connection is replaced.
+#[tauri::command]
+pub fn set_external_server(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    url: Option<String>,
+    api_key: Option<String>,
+) -> Result<ExternalServerInfo, AppError> {
+    let server = ExternalServer {
+        url: url.filter(|u| !u.trim().is_empty()),
+        api_key: api_key.filter(|k| !k.is_empty()),
+    };
+    let external_url = base_url(&server).map_err(AppError::invalid)?;
+    store::save_json(&store::data_file(&app, SETTINGS_FILE)?, &server)?;
+
+    let sidecar = default_sidecar(&state);
+    log::info!(
+        "Default profile now uses {}",
+        external_url.as_deref().map(display_url).unwrap_or_else(|| "the bundled sidecar".to_string())
+    );
+    kill_sidecar(&sidecar);
+    pidfile::clear(&app, &sidecar.profile);
+    sidecar.is_healthy.store(false, Ordering::SeqCst);
+    sidecar.port.store(0, Ordering::SeqCst);
+    sidecar.crashes.lock().unwrap().clear();
+    *sidecar.external_url.lock().unwrap() = external_url;
+
+    if let Err(e) = start_sidecar_sync(&app, &state, &sidecar) {
+        log::error!("Failed to start sidecar: {}", e);
+        emit_backend_status(&app, &sidecar, BackendStatus::Crashed { error: e.to_string() });
+        return Err(e);
+    }
+    Ok(get_external_server(app, state))
+}
@@ -0,0 +1,493 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::header::{CONTENT_RANGE, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::AppError;
+use crate::remote_path::RemotePath;
+use crate::transfer_queue::Direction;
+use crate::{
+    auth, backend, bandwidth, emit_to_main, file_upload, jobs, network, operations, resources, resume, store,
+    transfer_queue, transfers, verify, version, AppState,
+};
+
+const DOWNLOADS_FILE: &str = "downloads.json";
+
+// Attempts before a download gives up; each continues where the last one stopped
+const MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileDownload {
+    pub id: String,
+    pub bucket: String,
+    pub key: String,
+    // Where the finished file goes; until then it is written next to it as
+    // <name>.<id>.bbstream-part
+    pub dest: PathBuf,
+    // Null until the backend has answered
+    pub size: Option<u64>,
+    // Last-Modified of the object the .part holds the start of, so a replaced object
+    // starts over instead of being stitched onto the old one
+    pub validator: Option<String>,
+    pub status: DownloadStatus,
+    pub transferred: u64,
+    pub error: Option<String>,
+}
+
+// Downloads of single objects to disk. Bytes go to a part file beside the
+// destination, which a later run continues with a ranged request and which is
+// renamed into place only once it holds the whole object.
+pub struct FileDownloads {
+    downloads: Mutex<Vec<FileDownload>>,
+    cancel: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl FileDownloads {
+    pub fn new() -> Self {
+        Self {
+            downloads: Mutex::new(Vec::new()),
+            cancel: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn job(&self, id: &str) -> Option<FileDownload> {
+        self.downloads.lock().unwrap().iter().find(|d| d.id == id).cloned()
+    }
+
+    pub fn jobs(&self) -> Vec<FileDownload> {
+        self.downloads.lock().unwrap().clone()
+    }
+
+    fn update<F: FnOnce(&mut FileDownload)>(&self, id: &str, f: F) -> Option<FileDownload> {
+        let mut downloads = self.downloads.lock().unwrap();
+        let download = downloads.iter_mut().find(|d| d.id == id)?;
+        f(download);
+        Some(download.clone())
+    }
+
+    fn save(&self, app: &AppHandle) {
+        let downloads = self.downloads.lock().unwrap();
+        let result = store::data_file(app, DOWNLOADS_FILE).and_then(|path| store::save_json(&path, &*downloads));
+        if let Err(e) = result {
+            log::error!("Failed to persist downloads: {}", e);
+        }
+    }
+}
+
+// Load persisted downloads; the ones running at exit wait for the user to resume or
+// discard them, and continue from their part file when resumed
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let mut downloads: Vec<FileDownload> = match store::data_file(app, DOWNLOADS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load downloads: {}", e);
+            return;
+        }
+    };
+    let mut interrupted = false;
+    for download in downloads.iter_mut().filter(|d| d.status == DownloadStatus::Running) {
+        // Stopped until the user chooses to resume it, or the transfer queue that
+        // started it gets to it again
+        download.status = DownloadStatus::Cancelled;
+        if !transfer_queue::owns(state, &download.id) {
+            resume::hold(state, &download.id);
+        }
+        interrupted = true;
+    }
+    *state.downloads.downloads.lock().unwrap() = downloads;
+    if interrupted {
+        state.downloads.save(app);
+    }
+}
+
+// The file a download writes to until it is complete. Named after the job so it
+// never picks up, or overwrites, another download's or the user's own file.
+fn part_path(dest: &Path, id: &str) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.bbstream-part", id));
+    dest.with_file_name(name)
+}
+
+// Download bucket/remote_path to `dest`, replacing whatever is there only once the
+// whole object has arrived. Progress is reported as `transfer-progress`, and status
+// changes as `download-progress`.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(bucket = %bucket), err)]
+pub async fn download_file(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    bucket: String,
+    remote_path: String,
+    dest: PathBuf,
+) -> Result<FileDownload, AppError> {
+    start(&app, &state, uuid::Uuid::new_v4().to_string(), bucket, &remote_path, dest)
+}
+
+// Check a download can go ahead and start it under `id`, for download_file and the
+// transfer queue
+pub fn start(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    id: String,
+    bucket: String,
+    remote_path: &str,
+    dest: PathBuf,
+) -> Result<FileDownload, AppError> {
+    version::ensure_compatible(state)?;
+    let remote_path = RemotePath::parse(remote_path).map_err(AppError::invalid)?;
+    if remote_path.is_root() {
+        return Err(AppError::invalid("Choose a file to download"));
+    }
+    if dest.file_name().is_none() || dest.is_dir() {
+        return Err(AppError::invalid(format!("{} is not a file name", dest.display())));
+    }
+    if !dest.parent().is_some_and(Path::is_dir) {
+        return Err(AppError::invalid(format!("The folder for {} doesn't exist", dest.display())));
+    }
+    let busy = state
+        .downloads
+        .cancel
+        .lock()
+        .unwrap()
+        .keys()
+        .any(|id| state.downloads.job(id).is_some_and(|d| d.dest == dest));
+    if busy {
+        return Err(AppError::busy(format!("{} is already being downloaded", dest.display())));
+    }
+
+    let download = FileDownload {
+        id,
+        bucket,
+        key: remote_path.into(),
+        dest,
+        size: None,
+        validator: None,
+        status: DownloadStatus::Running,
+        transferred: 0,
+        error: None,
+    };
+    state.downloads.downloads.lock().unwrap().push(download.clone());
+    state.downloads.save(app);
+
+    spawn_download(app.clone(), Arc::clone(state), download.id.clone());
+    Ok(download)
+}
+
+#[tauri::command]
+pub fn list_downloads(state: tauri::State<Arc<AppState>>) -> Vec<FileDownload> {
+    state.downloads.jobs()
+}
+
+// Stop a download where it is; the part file stays for resume_transfer
+#[tauri::command]
+pub fn pause_transfer(app: AppHandle, state: tauri::State<Arc<AppState>>, id: String) -> Result<(), AppError> {
+    if state.downloads.job(&id).is_none() {
+        return Err(AppError::invalid(format!("Unknown download {}", id)));
+    }
+    jobs::pause_job(app, state, id)
+}
+
+// Continue a paused, cancelled or failed download from where its part file ends
+#[tauri::command]
+pub async fn resume_transfer(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    id: String,
+) -> Result<(), AppError> {
+    if state.downloads.job(&id).is_none() {
+        return Err(AppError::invalid(format!("Unknown download {}", id)));
+    }
+    jobs::retry_job(app, state, id).await
+}
+
+pub fn cancel_download(state: &AppState, id: &str) -> Result<(), AppError> {
+    match state.downloads.cancel.lock().unwrap().get(id) {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        None => Err(AppError::invalid(format!("Download {} is not active", id))),
+    }
+}
+
+pub fn resume_download(app: AppHandle, state: &Arc<AppState>, id: String) -> Result<(), AppError> {
+    if state.downloads.cancel.lock().unwrap().contains_key(&id) {
+        return Err(AppError::busy(format!("Download {} is already active", id)));
+    }
+    let download = state
+        .downloads
+        .update(&id, |download| {
+            if matches!(download.status, DownloadStatus::Failed | DownloadStatus::Cancelled) {
+                download.status = DownloadStatus::Running;
+                download.error = None;
+            }
+        })
+        .ok_or_else(|| AppError::invalid(format!("Unknown download {}", id)))?;
+    if download.status != DownloadStatus::Running {
+        return Err(AppError::invalid(format!("Download {} has already completed", id)));
+    }
+
+    spawn_download(app, Arc::clone(state), id);
+    Ok(())
+}
+
+fn report(app: &AppHandle, download: &FileDownload) {
+    jobs::download_updated(app, download);
+    emit_to_main(app, "download-progress", download.clone());
+}
+
+fn spawn_download(app: AppHandle, state: Arc<AppState>, id: String) {
+    let Some(download) = state.downloads.job(&id) else {
+        return;
+    };
+    let cancel = Arc::new(AtomicBool::new(false));
+    state.downloads.cancel.lock().unwrap().insert(id.clone(), Arc::clone(&cancel));
+    let label = format!("Download {}", download.key);
+    let operation = operations::begin(&app, Some(id.clone()), "download", label, Arc::clone(&cancel));
+
+    let counter = Arc::new(AtomicU64::new(download.transferred));
+    let total = Arc::new(AtomicU64::new(download.size.unwrap_or(0)));
+    let running = Arc::new(AtomicBool::new(true));
+    file_upload::spawn_reporter(&app, &id, &download.bucket, &download.key, &total, &counter, &running);
+
+    tauri::async_runtime::spawn(async move {
+        let result = run_download(&app, &state, &id, &cancel, &counter, &total).await;
+        running.store(false, Ordering::SeqCst);
+        state.downloads.cancel.lock().unwrap().remove(&id);
+        transfers::finish(&state, &id);
+        drop(operation);
+
+        let download = state.downloads.update(&id, |download| {
+            download.transferred = counter.load(Ordering::Relaxed);
+            match &result {
+                Ok(()) => download.status = DownloadStatus::Completed,
+                Err(_) if cancel.load(Ordering::SeqCst) => download.status = DownloadStatus::Cancelled,
+                Err(e) => {
+                    log::error!("Downloading {} failed: {}", download.key, e);
+                    download.status = DownloadStatus::Failed;
+                    download.error = Some(e.clone());
+                }
+            }
+        });
+        state.downloads.save(&app);
+        transfer_queue::finished(&app, &state, &id, &result, cancel.load(Ordering::SeqCst));
+        // A paused download keeps its own limit for when it resumes
+        if !cancel.load(Ordering::SeqCst) {
+            bandwidth::forget(&state, &id);
+        }
+        if let Some(download) = download {
+            let mut last = file_upload::progress(
+                &id,
+                &download.bucket,
+                &download.key,
+                download.transferred,
+                total.load(Ordering::Relaxed),
+                0.0,
+            );
+            last.done = true;
+            last.error = download.error.clone();
+            emit_to_main(&app, "transfer-progress", last);
+            match download.status {
+                DownloadStatus::Completed => {
+                    let (bucket, key) = (&download.bucket, &download.key);
+                    verify::spawn(&app, &state, &id, Direction::Download, bucket, key, &download.dest);
+                }
+                DownloadStatus::Failed => jobs::notify_failed(&app, &download.id),
+                _ => {}
+            }
+            report(&app, &download);
+        }
+    });
+}
+
+#[tracing::instrument(name = "transfer.download", skip_all, fields(id = %id), err)]
+async fn run_download(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    id: &str,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+    total: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let download = state.downloads.job(id).ok_or_else(|| format!("Unknown download {}", id))?;
+    while !state.is_healthy() {
+        if cancel.load(Ordering::SeqCst) || state.shutdown.load(Ordering::SeqCst) {
+            return Err("Cancelled".to_string());
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    let size = download.size.unwrap_or(0);
+    transfers::track(app, id, "download", vec![(download.key.clone(), size, false)]);
+    if !network::wait_for_wifi(app, state, id, &download.key, size, cancel).await {
+        return Err("Cancelled".to_string());
+    }
+
+    transfers::start_file(state, id, &download.key, counter);
+    let part = part_path(&download.dest, id);
+    let mut result = fetch(app, state, id, &part, cancel, counter, total).await;
+    if result.is_ok() {
+        // Only a complete file ever appears under the destination's name
+        result = tokio::fs::rename(&part, &download.dest)
+            .await
+            .map_err(|e| format!("Failed to move {} into place: {}", part.display(), e));
+    }
+    transfers::finish_file(state, id, &download.key, &result, cancel.load(Ordering::SeqCst));
+    if let Err(e) = &result {
+        auth::report(app, state, e);
+    }
+    result
+}
+
+// Download into `part`, continuing from its length and retrying with backoff
+async fn fetch(
+    app: &AppHandle,
+    state: &AppState,
+    id: &str,
+    part: &Path,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+    total: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let mut attempt = 0;
+    loop {
+        match fetch_once(app, state, id, part, cancel, counter, total).await {
+            Ok(()) => return Ok(()),
+            Err(e) if cancel.load(Ordering::SeqCst) => return Err(e),
+            Err(e) => {
+                attempt += 1;
+                if attempt >= MAX_ATTEMPTS {
+                    return Err(e);
+                }
+                log::warn!("Downloading {} failed (attempt {}): {}", part.display(), attempt, e);
+                tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+            }
+        }
+    }
+}
+
+async fn fetch_once(
+    app: &AppHandle,
+    state: &AppState,
+    id: &str,
+    part: &Path,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+    total: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    // Re-read each attempt, as an earlier one may have learned the size
+    let download = state.downloads.job(id).ok_or_else(|| format!("Unknown download {}", id))?;
+    // Without a validator nothing says which object the part file holds, so it is
+    // written again from the start
+    let offset = match download.validator {
+        Some(_) => tokio::fs::metadata(part).await.map(|m| m.len()).ok(),
+        None => None,
+    };
+    if let Some(have) = offset.filter(|have| Some(*have) == download.size) {
+        counter.store(have, Ordering::Relaxed);
+        return Ok(());
+    }
+    let offset = offset.unwrap_or(0);
+
+    let base_url = state.base_url();
+    let mut request = backend::shared_client(&base_url).get(backend::download_url(
+        &base_url,
+        &download.bucket,
+        &download.key,
+    )?);
+    if let Some(validator) = download.validator.as_ref().filter(|_| offset > 0) {
+        request = request.header(RANGE, format!("bytes={}-", offset)).header(IF_RANGE, validator);
+    }
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", download.key, e))?;
+    // A full response to a range request means the object changed since; start over
+    let append = match resp.status() {
+        StatusCode::PARTIAL_CONTENT => offset > 0,
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            let _ = tokio::fs::remove_file(part).await;
+            return Err(format!("{} changed since the download started; starting over", download.key));
+        }
+        status if status.is_success() => false,
+        status => return Err(format!("Downloading {} returned status: {}", download.key, status)),
+    };
+    let size = match resp.status() {
+        StatusCode::PARTIAL_CONTENT => resp
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|range| range.to_str().ok())
+            .and_then(|range| range.rsplit_once('/'))
+            .and_then(|(_, size)| size.parse().ok()),
+        _ => resp.content_length(),
+    };
+    let validator = resp.headers().get(LAST_MODIFIED).and_then(|v| v.to_str().ok()).map(str::to_string);
+    if offset > 0 && !append {
+        log::info!("{} changed since the download started; starting over", download.key);
+    }
+    if let Some(download) = state.downloads.update(id, |download| {
+        download.size = size;
+        download.validator = validator;
+    }) {
+        state.downloads.save(app);
+        report(app, &download);
+    }
+    total.store(size.unwrap_or(0), Ordering::Relaxed);
+
+    let have = if append { offset } else { 0 };
+    if let (Some(size), Some(dir)) = (size, download.dest.parent()) {
+        // 0 when the free space can't be told
+        let available = resources::available_space(dir);
+        if available > 0 && available < size.saturating_sub(have) {
+            return Err(format!("Not enough disk space to download {}", download.key));
+        }
+    }
+    let mut file = if append {
+        tokio::fs::OpenOptions::new().append(true).open(part).await
+    } else {
+        tokio::fs::File::create(part).await
+    }
+    .map_err(|e| format!("Failed to open {}: {}", part.display(), e))?;
+    counter.store(have, Ordering::Relaxed);
+
+    let mut body = resp.bytes_stream();
+    while let Some(chunk) = body.next().await {
+        if cancel.load(Ordering::SeqCst) {
+            let _ = file.flush().await;
+            return Err("Cancelled".to_string());
+        }
+        let chunk = chunk.map_err(|e| format!("Failed to download {}: {}", download.key, e))?;
+        bandwidth::wait(state, Direction::Download, Some(id), chunk.len()).await;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+        counter.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+    }
+    // On disk before the rename makes it the destination
+    file.sync_all()
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+
+    match size {
+        Some(size) if counter.load(Ordering::Relaxed) != size => {
+            Err(format!("Download of {} ended early", download.key))
+        }
+        _ => Ok(()),
+    }
+}
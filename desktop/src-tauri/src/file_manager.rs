@@ -0,0 +1,20 @@
+use std::path::Path;
+
+// Show the file selected in the platform file manager, or its folder where that isn't possible
+pub fn reveal(path: &Path) -> Result<(), String> {
+    let result = if cfg!(target_os = "macos") {
+        std::process::Command::new("open").arg("-R").arg(path).spawn().map(|_| ())
+    } else if cfg!(target_os = "windows") {
+        let mut select = std::ffi::OsString::from("/select,");
+        select.push(path);
+        std::process::Command::new("explorer").arg(select).spawn().map(|_| ())
+    } else {
+        open::that(path.parent().unwrap_or(path))
+    };
+    result.map_err(|e| format!("Failed to show {}: {}", path.display(), e))
+}
+
+// Open the file with its default application
+pub fn open(path: &Path) -> Result<(), String> {
+    open::that(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
@@ -0,0 +1,258 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::backend::SourceChangePolicy;
+use crate::bandwidth::{self, Throttle};
+use crate::error::AppError;
+use crate::permissions::{self, Capability};
+use crate::remote_path::RemotePath;
+use crate::transfer_queue::Direction;
+use crate::{
+    auth, backend, chunked_upload, emit_to_main, network, operations, transfer_queue, transfers, verify, version,
+    AppState,
+};
+
+// How often a running upload reports its progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+// Weight of the latest interval in the smoothed rate; lower rides out bursts
+const RATE_SMOOTHING: f64 = 0.3;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TransferProgress {
+    pub id: String,
+    pub bucket: String,
+    pub key: String,
+    pub transferred: u64,
+    pub total: u64,
+    pub bytes_per_sec: u64,
+    // Null until there is a rate to go by
+    pub eta_secs: Option<u64>,
+    pub done: bool,
+    // Set once a failed upload is done; a cancelled one has none
+    pub error: Option<String>,
+}
+
+// Smoothed throughput of a running upload
+struct Rate {
+    last: u64,
+    at: Instant,
+    bytes_per_sec: f64,
+}
+
+impl Rate {
+    fn new() -> Self {
+        Self {
+            last: 0,
+            at: Instant::now(),
+            bytes_per_sec: 0.0,
+        }
+    }
+
+    fn sample(&mut self, transferred: u64) -> f64 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.at).as_secs_f64();
+        // A restarted file counts from zero again
+        let moved = transferred.saturating_sub(self.last) as f64;
+        if elapsed > 0.0 {
+            let current = moved / elapsed;
+            self.bytes_per_sec = if self.bytes_per_sec == 0.0 {
+                current
+            } else {
+                RATE_SMOOTHING * current + (1.0 - RATE_SMOOTHING) * self.bytes_per_sec
+            };
+        }
+        self.last = transferred;
+        self.at = now;
+        self.bytes_per_sec
+    }
+}
+
+pub fn progress(
+    id: &str,
+    bucket: &str,
+    key: &str,
+    transferred: u64,
+    total: u64,
+    bytes_per_sec: f64,
+) -> TransferProgress {
+    let left = total.saturating_sub(transferred) as f64;
+    let eta_secs = (bytes_per_sec >= 1.0).then(|| (left / bytes_per_sec).ceil() as u64);
+    TransferProgress {
+        id: id.to_string(),
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        transferred,
+        total,
+        bytes_per_sec: bytes_per_sec as u64,
+        eta_secs,
+        done: false,
+        error: None,
+    }
+}
+
+// Upload one local file to bucket/remote_path without it passing through the webview.
+// The file is read a chunk at a time only as fast as the backend takes it, and
+// `transfer-progress` events report the rate and time left. Returns the upload's id,
+// which cancel_job also takes.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(bucket = %bucket), err)]
+pub async fn upload_file(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    local_path: PathBuf,
+    bucket: String,
+    remote_path: String,
+) -> Result<String, AppError> {
+    let detail = format!("uploading {}", local_path.display());
+    permissions::ensure(&app, &state, Capability::ReadLocalFiles, &detail).await?;
+    let id = uuid::Uuid::new_v4().to_string();
+    start(&app, &state, id.clone(), local_path, bucket, &remote_path)?;
+    Ok(id)
+}
+
+// Check an upload can go ahead and start it under `id`, for upload_file and the
+// transfer queue
+pub fn start(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    id: String,
+    local_path: PathBuf,
+    bucket: String,
+    remote_path: &str,
+) -> Result<(), AppError> {
+    version::ensure_compatible(state)?;
+    let remote_path = RemotePath::parse(remote_path).map_err(AppError::invalid)?;
+    if remote_path.is_root() {
+        return Err(AppError::invalid("Choose a name to upload the file as"));
+    }
+    let meta = std::fs::metadata(&local_path)
+        .map_err(|e| AppError::invalid(format!("Failed to read {}: {}", local_path.display(), e)))?;
+    if !meta.is_file() {
+        return Err(AppError::invalid(format!("{} is not a file", local_path.display())));
+    }
+
+    let key: String = remote_path.into();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let label = format!("Upload {} to {}", key, bucket);
+    let operation = operations::begin(app, Some(id.clone()), "upload", label, Arc::clone(&cancel));
+    transfers::track(app, &id, "upload", vec![(key.clone(), meta.len(), false)]);
+
+    let counter = Arc::new(AtomicU64::new(0));
+    let running = Arc::new(AtomicBool::new(true));
+    let total = Arc::new(AtomicU64::new(meta.len()));
+    spawn_reporter(app, &id, &bucket, &key, &total, &counter, &running);
+
+    let (app, state) = (app.clone(), Arc::clone(state));
+    tauri::async_runtime::spawn(async move {
+        let size = meta.len();
+        let result = run(&app, &state, &id, &bucket, &key, &local_path, size, &cancel, &counter).await;
+        running.store(false, Ordering::SeqCst);
+        let cancelled = cancel.load(Ordering::SeqCst);
+        transfers::finish_file(&state, &id, &key, &result, cancelled);
+        transfers::finish(&state, &id);
+        drop(operation);
+        transfer_queue::finished(&app, &state, &id, &result, cancelled);
+        bandwidth::forget(&state, &id);
+
+        let mut last = progress(&id, &bucket, &key, counter.load(Ordering::Relaxed), size, 0.0);
+        last.done = true;
+        match result {
+            Ok(()) => {
+                log::info!("Uploaded {} to {}/{}", local_path.display(), bucket, key);
+                verify::spawn(&app, &state, &id, Direction::Upload, &bucket, &key, &local_path);
+            }
+            Err(_) if cancelled => log::info!("Upload of {} cancelled", local_path.display()),
+            Err(e) => {
+                log::error!("Uploading {} failed: {}", local_path.display(), e);
+                last.error = Some(e);
+            }
+        }
+        emit_to_main(&app, "transfer-progress", last);
+    });
+    Ok(())
+}
+
+// Report a transfer's progress as `transfer-progress` until `running` is cleared;
+// `total` may be filled in once the transfer learns it
+pub fn spawn_reporter(
+    app: &AppHandle,
+    id: &str,
+    bucket: &str,
+    key: &str,
+    total: &Arc<AtomicU64>,
+    counter: &Arc<AtomicU64>,
+    running: &Arc<AtomicBool>,
+) {
+    let (app, id, bucket, key) = (app.clone(), id.to_string(), bucket.to_string(), key.to_string());
+    let (total, counter, running) = (Arc::clone(total), Arc::clone(counter), Arc::clone(running));
+    tauri::async_runtime::spawn(async move {
+        let mut rate = Rate::new();
+        loop {
+            tokio::time::sleep(PROGRESS_INTERVAL).await;
+            if !running.load(Ordering::SeqCst) {
+                break;
+            }
+            let transferred = counter.load(Ordering::Relaxed);
+            let bytes_per_sec = rate.sample(transferred);
+            let total = total.load(Ordering::Relaxed);
+            emit_to_main(&app, "transfer-progress", progress(&id, &bucket, &key, transferred, total, bytes_per_sec));
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(name = "transfer.upload", skip_all, fields(id = %id), err)]
+async fn run(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    id: &str,
+    bucket: &str,
+    key: &str,
+    path: &Path,
+    size: u64,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    while !state.is_healthy() {
+        if cancel.load(Ordering::SeqCst) || state.shutdown.load(Ordering::SeqCst) {
+            return Err("Cancelled".to_string());
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    if !network::wait_for_wifi(app, state, id, key, size, cancel).await {
+        return Err("Cancelled".to_string());
+    }
+
+    transfers::start_file(state, id, key, counter);
+    // Big files go up in checkpointed parts, so an interruption doesn't cost the
+    // parts already sent
+    let throttle = Throttle::new(state, Direction::Upload, Some(id));
+    let result = if chunked_upload::wants_parts(state, size) {
+        chunked_upload::upload(app, state, bucket, key, path, &throttle, cancel, counter).await
+    } else {
+        let base_url = state.base_url();
+        let client = backend::shared_client(&base_url);
+        backend::upload_local_file(
+            &client,
+            &base_url,
+            bucket,
+            key,
+            path,
+            SourceChangePolicy::Restart,
+            &throttle,
+            cancel,
+            counter,
+        )
+        .await
+    };
+    if let Err(e) = &result {
+        auth::report(app, state, e);
+    }
+    result
+}
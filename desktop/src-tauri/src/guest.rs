@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+use crate::error::AppError;
+use crate::remote_path::{self, RemotePath};
+use crate::{backend, AppState, Sidecar};
+
+// Guest windows are labelled with this prefix and nothing else is
+const LABEL_PREFIX: &str = "guest-";
+
+// The only commands a guest window may invoke; the rest are refused before they run
+const GUEST_COMMANDS: [&str; 3] = ["get_guest_scope", "list_guest_files", "read_guest_file"];
+
+// Largest file a guest can open; the whole file passes through IPC
+const MAX_READ_BYTES: u64 = 200 * 1024 * 1024;
+
+// What a guest window may see: one folder of one bucket, through one profile's backend
+#[derive(Clone, Debug, Serialize)]
+pub struct GuestScope {
+    pub profile: String,
+    pub bucket: String,
+    pub prefix: RemotePath,
+    pub title: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GuestFile {
+    // Relative to the scope's prefix
+    pub path: String,
+    pub size: i64,
+    pub content_type: String,
+    // Unix seconds
+    pub timestamp: i64,
+}
+
+// Open guest windows by label. A guest window holds no backend token, so everything
+// it shows comes through the commands below, which only read inside its scope.
+pub struct Guests {
+    scopes: Mutex<HashMap<String, GuestScope>>,
+}
+
+impl Guests {
+    pub fn new() -> Self {
+        Self {
+            scopes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn scope(&self, window: &WebviewWindow) -> Result<GuestScope, String> {
+        self.scopes
+            .lock()
+            .unwrap()
+            .get(window.label())
+            .cloned()
+            .ok_or_else(|| "Not a guest window".to_string())
+    }
+}
+
+// Drop a closed window's scope; called for every window that goes away
+pub fn window_destroyed(state: &AppState, label: &str) {
+    state.guests.scopes.lock().unwrap().remove(label);
+}
+
+// Wrap the app's command handler so guest windows can't reach anything but the guest
+// commands, whatever their page tries
+pub fn guarded<F>(handler: F) -> impl Fn(Invoke) -> bool + Send + Sync + 'static
+where
+    F: Fn(Invoke) -> bool + Send + Sync + 'static,
+{
+    move |invoke| {
+        let label = invoke.message.webview_ref().label();
+        let command = invoke.message.command();
+        if label.starts_with(LABEL_PREFIX) && !GUEST_COMMANDS.contains(&command) {
+            log::warn!("Refused {} from guest window {}", command, label);
+            let message = format!("{} isn't available in a guest window", command);
+            invoke.resolver.reject(AppError::Unauthorized { message });
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+// Open a read-only window onto bucket/prefix of the active profile, e.g. to let a
+// client browse their deliverables. Returns the window's label.
+#[tauri::command]
+pub async fn open_guest_window(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    bucket: String,
+    prefix: Option<String>,
+    title: Option<String>,
+) -> Result<String, AppError> {
+    if bucket.is_empty() {
+        return Err(AppError::invalid("A guest window needs a bucket"));
+    }
+    let prefix = RemotePath::parse(&prefix.unwrap_or_default()).map_err(AppError::invalid)?;
+    let title = title.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| match prefix.as_str() {
+        "" => bucket.clone(),
+        path => format!("{}/{}", bucket, path),
+    });
+    let label = format!("{}{}", LABEL_PREFIX, uuid::Uuid::new_v4().simple());
+    let scope = GuestScope {
+        profile: state.active_profile.lock().unwrap().clone(),
+        bucket,
+        prefix,
+        title: title.clone(),
+    };
+    state.guests.scopes.lock().unwrap().insert(label.clone(), scope);
+
+    let built = WebviewWindowBuilder::new(&app, &label, WebviewUrl::App("guest.html".into()))
+        .title(format!("{} (read-only)", title))
+        .inner_size(900.0, 640.0)
+        .min_inner_size(480.0, 320.0)
+        .build();
+    if let Err(e) = built {
+        state.guests.scopes.lock().unwrap().remove(&label);
+        return Err(format!("Failed to open the guest window: {}", e).into());
+    }
+    log::info!("Opened guest window {}", label);
+    Ok(label)
+}
+
+#[tauri::command]
+pub fn get_guest_scope(window: WebviewWindow, state: tauri::State<Arc<AppState>>) -> Result<GuestScope, AppError> {
+    Ok(state.guests.scope(&window)?)
+}
+
+fn guest_sidecar(state: &AppState, scope: &GuestScope) -> Result<Arc<Sidecar>, String> {
+    let sidecar = state.sidecars.lock().unwrap().get(&scope.profile).cloned();
+    sidecar
+        .filter(|sidecar| sidecar.is_reachable())
+        .ok_or_else(|| "The backend isn't running".to_string())
+}
+
+// Every file inside the window's scope
+#[tauri::command]
+pub async fn list_guest_files(
+    window: WebviewWindow,
+    state: tauri::State<'_, Arc<AppState>>,
+) -> Result<Vec<GuestFile>, AppError> {
+    let scope = state.guests.scope(&window)?;
+    let base_url = guest_sidecar(&state, &scope)?.base_url();
+    let objects = backend::list_objects(
+        &backend::shared_client(&base_url),
+        &base_url,
+        &scope.bucket,
+        &scope.prefix.list_prefix(),
+    )
+    .await?;
+    let mut files: Vec<GuestFile> = objects
+        .into_iter()
+        .filter_map(|o| {
+            let path = remote_path::relative_key(&scope.prefix, &o.name)?.ok()?;
+            (!path.is_root()).then(|| GuestFile {
+                path: path.as_str().to_string(),
+                size: o.size,
+                content_type: o.content_type,
+                timestamp: o.timestamp,
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+// A file's contents, by its path relative to the window's scope
+#[tauri::command]
+pub async fn read_guest_file(
+    window: WebviewWindow,
+    state: tauri::State<'_, Arc<AppState>>,
+    path: String,
+) -> Result<tauri::ipc::Response, AppError> {
+    let scope = state.guests.scope(&window)?;
+    let relative = RemotePath::parse(&path).map_err(AppError::invalid)?;
+    if relative.is_root() {
+        return Err(AppError::invalid("No file given"));
+    }
+    let key = scope.prefix.join(&relative).map_err(AppError::invalid)?;
+    let base_url = guest_sidecar(&state, &scope)?.base_url();
+
+    let resp = backend::shared_client(&base_url)
+        .get(backend::download_url(&base_url, &scope.bucket, key.as_str())?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", path, e))?;
+    if !resp.status().is_success() {
+        return Err(AppError::Status {
+            status: resp.status().as_u16(),
+            message: format!("Downloading {} returned status: {}", path, resp.status()),
+        });
+    }
+    if resp.content_length().is_some_and(|len| len > MAX_READ_BYTES) {
+        return Err(AppError::Unsupported {
+            message: format!("{} is too large to open here", path),
+        });
+    }
+    let bytes = resp
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", path, e))?;
+    Ok(tauri::ipc::Response::new(bytes.to_vec()))
+}
@@ -0,0 +1,157 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tokio::net::TcpStream;
+
+use crate::error::AppError;
+use crate::{backend, store, AppState};
+
+const SETTINGS_FILE: &str = "health_checks.json";
+
+// How the sidecar's /health endpoint is polled. Slow machines may need a longer
+// timeout; on battery a longer interval wakes the CPU less often.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthSettings {
+    pub interval_secs: u64,
+    pub timeout_secs: u64,
+    // Consecutive failed checks before the backend is reported unhealthy
+    pub failure_threshold: u32,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            interval_secs: 5,
+            timeout_secs: 2,
+            failure_threshold: 3,
+        }
+    }
+}
+
+impl HealthSettings {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=300).contains(&self.interval_secs) {
+            return Err("Health check interval must be between 1 and 300 seconds".to_string());
+        }
+        if !(1..=60).contains(&self.timeout_secs) {
+            return Err("Health check timeout must be between 1 and 60 seconds".to_string());
+        }
+        if self.timeout_secs > self.interval_secs {
+            return Err("Health check timeout can't be longer than the interval".to_string());
+        }
+        if !(1..=20).contains(&self.failure_threshold) {
+            return Err("Failure threshold must be between 1 and 20 checks".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub struct HealthChecks {
+    settings: Mutex<HealthSettings>,
+    // Reused by every check so its connection stays pooled; timeouts are set per request
+    client: reqwest::Client,
+}
+
+impl HealthChecks {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(HealthSettings::default()),
+            client: reqwest::Client::builder()
+                .pool_idle_timeout(Duration::from_secs(90))
+                .build()
+                .expect("default TLS backend is available"),
+        }
+    }
+
+    pub fn settings(&self) -> HealthSettings {
+        *self.settings.lock().unwrap()
+    }
+
+    // Client for checking the backend at `base_url`; sidecars on a Unix socket have
+    // their own pooled one
+    pub fn client(&self, base_url: &str) -> reqwest::Client {
+        if backend::is_socket_url(base_url) {
+            backend::shared_client(base_url)
+        } else {
+            self.client.clone()
+        }
+    }
+}
+
+// Cheap check before the HTTP request: whether anything accepts connections on the
+// backend's port at all. A stopped or crashed sidecar fails here without building a
+// request. Sockets are left to the HTTP check.
+pub async fn port_accepts(base_url: &str, timeout: Duration) -> Result<(), String> {
+    if backend::is_socket_url(base_url) {
+        return Ok(());
+    }
+    let url = Url::parse(base_url).map_err(|e| format!("Invalid backend URL: {}", e))?;
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return Err("Backend URL has no host and port".to_string());
+    };
+    // IPv6 hosts come bracketed
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match tokio::time::timeout(timeout, TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(format!("Nothing accepts connections on port {}: {}", port, e)),
+        Err(_) => Err(format!("Connecting to port {} timed out", port)),
+    }
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let settings: HealthSettings = match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load health check settings: {}", e);
+            return;
+        }
+    };
+    match settings.validate() {
+        Ok(()) => *state.health_checks.settings.lock().unwrap() = settings,
+        Err(e) => log::warn!("Ignoring saved health check settings: {}", e),
+    }
+}
+
+#[tauri::command]
+pub fn get_health_checks(state: tauri::State<Arc<AppState>>) -> HealthSettings {
+    state.health_checks.settings()
+}
+
+// Change any of the health check settings; the running checker picks them up on
+// its next check
+#[tauri::command]
+pub fn configure_health_checks(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    interval_secs: Option<u64>,
+    timeout_secs: Option<u64>,
+    failure_threshold: Option<u32>,
+) -> Result<HealthSettings, AppError> {
+    let mut settings = state.health_checks.settings();
+    settings.interval_secs = interval_secs.unwrap_or(settings.interval_secs);
+    settings.timeout_secs = timeout_secs.unwrap_or(settings.timeout_secs);
+    settings.failure_threshold = failure_threshold.unwrap_or(settings.failure_threshold);
+    settings.validate().map_err(AppError::invalid)?;
+
+    store::save_json(&store::data_file(&app, SETTINGS_FILE)?, &settings)?;
+    *state.health_checks.settings.lock().unwrap() = settings;
+    log::info!(
+        "Health checks every {}s, {}s timeout, unhealthy after {} failures",
+        settings.interval_secs,
+        settings.timeout_secs,
+        settings.failure_threshold
+    );
+    Ok(settings)
+}
@@ -0,0 +1,683 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::backend::SourceChangePolicy;
+use crate::bandwidth::{self, Throttle};
+use crate::error::AppError;
+use crate::permissions::{self, Capability};
+use crate::remote_path::RemotePath;
+use crate::s3::S3Location;
+use crate::transfer_queue::Direction;
+use crate::{
+    auth, backend, eject, emit_to_main, jobs, journal, network, operations, resume, store, suggestions, transfers,
+    version, AppState,
+};
+
+const IMPORTS_FILE: &str = "imports.json";
+const JOURNAL_FILE: &str = "imports.journal";
+
+// Fold the journal into a fresh snapshot after this many imported objects
+const CHECKPOINT_EVERY: usize = 25;
+
+// Give up on a run after this many files in a row failed to upload
+const MAX_CONSECUTIVE_FAILURES: usize = 10;
+
+// Failures kept per job for the final summary
+const MAX_REPORTED_FAILURES: usize = 100;
+
+// Throughput assumed for estimates until an import has measured a real one
+const DEFAULT_BYTES_PER_SEC: f64 = 8.0 * 1024.0 * 1024.0;
+
+// Where an import reads from
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImportSource {
+    Local { path: PathBuf },
+    S3(S3Location),
+}
+
+#[derive(Clone, Debug)]
+pub struct SourceEntry {
+    pub key: String,
+    pub size: u64,
+    // Unix seconds; only known for local files
+    pub modified: Option<i64>,
+}
+
+impl SourceEntry {
+    // Size and mtime, used to spot local files changed since they were imported
+    fn stamp(&self) -> Option<(u64, i64)> {
+        self.modified.map(|modified| (self.size, modified))
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportEstimate {
+    pub object_count: usize,
+    pub total_bytes: u64,
+    pub estimated_seconds: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Enumerating,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImportJob {
+    pub id: String,
+    pub source: ImportSource,
+    pub bucket: String,
+    pub prefix: String,
+    // What to do when a local source file changes while it is being uploaded
+    #[serde(default)]
+    pub on_source_change: SourceChangePolicy,
+    // Verify and eject the source's removable volume once everything is imported
+    #[serde(default)]
+    pub eject_when_done: bool,
+    pub status: ImportStatus,
+    pub total_objects: usize,
+    pub total_bytes: u64,
+    pub imported_objects: usize,
+    pub imported_bytes: u64,
+    // Files left alone this run because an earlier run already imported them unchanged
+    #[serde(default)]
+    pub skipped_objects: usize,
+    #[serde(default)]
+    pub failed_objects: usize,
+    // The first MAX_REPORTED_FAILURES failed files of the latest run
+    #[serde(default)]
+    pub failures: Vec<ImportFailure>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImportFailure {
+    pub key: String,
+    pub error: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Persisted {
+    jobs: Vec<ImportJob>,
+    // Source keys already imported per job, skipped when the job resumes
+    done: HashMap<String, HashSet<String>>,
+    // Size and mtime of imported local files per job; changed files are imported again
+    #[serde(default)]
+    stamps: HashMap<String, HashMap<String, (u64, i64)>>,
+    // Smoothed upload throughput measured by previous imports
+    bytes_per_sec: Option<f64>,
+}
+
+// Progress recorded between snapshots
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalEntry {
+    Imported {
+        id: String,
+        key: String,
+        bytes: u64,
+        stamp: Option<(u64, i64)>,
+    },
+}
+
+impl Persisted {
+    // Returns the updated job, or None if the entry was already applied
+    fn apply(&mut self, entry: JournalEntry) -> Option<ImportJob> {
+        match entry {
+            JournalEntry::Imported { id, key, bytes, stamp } => {
+                let job = self.jobs.iter_mut().find(|j| j.id == id)?;
+                if let Some(stamp) = stamp {
+                    self.stamps.entry(id.clone()).or_default().insert(key.clone(), stamp);
+                }
+                if !self.done.entry(id).or_default().insert(key) {
+                    return None;
+                }
+                job.imported_objects += 1;
+                job.imported_bytes += bytes;
+                Some(job.clone())
+            }
+        }
+    }
+}
+
+// Bulk imports from a local folder or an S3-compatible bucket into the backend
+pub struct Imports {
+    inner: Mutex<Persisted>,
+    cancel: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl Imports {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(Persisted::default()),
+            cancel: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn update<F: FnOnce(&mut ImportJob)>(&self, id: &str, f: F) -> Option<ImportJob> {
+        let mut inner = self.inner.lock().unwrap();
+        let job = inner.jobs.iter_mut().find(|j| j.id == id)?;
+        f(job);
+        Some(job.clone())
+    }
+
+    pub fn job(&self, id: &str) -> Option<ImportJob> {
+        self.inner.lock().unwrap().jobs.iter().find(|j| j.id == id).cloned()
+    }
+
+    pub fn jobs(&self) -> Vec<ImportJob> {
+        self.inner.lock().unwrap().jobs.clone()
+    }
+
+    // Snapshot the jobs and empty the journal, under one lock like remote copies do
+    fn save(&self, app: &AppHandle) {
+        let inner = self.inner.lock().unwrap();
+        let result = store::data_file(app, IMPORTS_FILE)
+            .and_then(|path| store::save_json(&path, &*inner))
+            .and_then(|()| journal::clear(app, JOURNAL_FILE));
+        if let Err(e) = result {
+            log::error!("Failed to persist import jobs: {}", e);
+        }
+    }
+
+    // Mark a file imported, journaling it before the progress is reported
+    fn mark_imported(&self, app: &AppHandle, id: &str, entry: &SourceEntry, bytes: u64) -> Option<ImportJob> {
+        let mut inner = self.inner.lock().unwrap();
+        let entry = JournalEntry::Imported {
+            id: id.to_string(),
+            key: entry.key.clone(),
+            bytes,
+            stamp: entry.stamp(),
+        };
+        if let Err(e) = journal::append(app, JOURNAL_FILE, &entry) {
+            log::error!("Failed to journal import progress: {}", e);
+        }
+        inner.apply(entry)
+    }
+
+    fn estimate_seconds(&self, total_bytes: u64) -> u64 {
+        let rate = self.inner.lock().unwrap().bytes_per_sec.unwrap_or(DEFAULT_BYTES_PER_SEC);
+        (total_bytes as f64 / rate).ceil() as u64
+    }
+
+    fn record_throughput(&self, bytes: u64, elapsed: Duration) {
+        if bytes == 0 || elapsed.is_zero() {
+            return;
+        }
+        let sample = bytes as f64 / elapsed.as_secs_f64();
+        let mut inner = self.inner.lock().unwrap();
+        inner.bytes_per_sec = Some(match inner.bytes_per_sec {
+            Some(rate) => rate * 0.8 + sample * 0.2,
+            None => sample,
+        });
+    }
+}
+
+// Load persisted imports and replay progress journaled since the last snapshot.
+// Interrupted local imports wait for the user to resume or discard them; S3 imports
+// need their secret key again, so they are parked as failed until the user resumes them.
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let mut persisted: Persisted = match store::data_file(app, IMPORTS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load import jobs: {}", e);
+            return;
+        }
+    };
+    let entries: Vec<JournalEntry> = journal::replay(app, JOURNAL_FILE);
+    let replayed = !entries.is_empty();
+    for entry in entries {
+        persisted.apply(entry);
+    }
+
+    let mut interrupted = false;
+    for job in persisted.jobs.iter_mut() {
+        if !matches!(job.status, ImportStatus::Enumerating | ImportStatus::Running) {
+            continue;
+        }
+        interrupted = true;
+        match &job.source {
+            ImportSource::S3(location) if location.access_key_id.is_some() => {
+                job.status = ImportStatus::Failed;
+                job.error = Some("Interrupted; resume with credentials to continue".to_string());
+            }
+            // Stopped until the user chooses to resume it
+            _ => {
+                job.status = ImportStatus::Cancelled;
+                resume::hold(state, &job.id);
+            }
+        }
+    }
+    *state.imports.inner.lock().unwrap() = persisted;
+    if replayed || interrupted {
+        state.imports.save(app);
+    }
+}
+
+#[tauri::command]
+pub async fn estimate_import(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    source: ImportSource,
+    operation_id: Option<String>,
+) -> Result<ImportEstimate, AppError> {
+    allow_source(&app, &state, &source).await?;
+    let operation = operations::begin(
+        &app,
+        operation_id,
+        "import_estimate",
+        "Counting files to import",
+        Arc::new(AtomicBool::new(false)),
+    );
+    let entries = enumerate(&http_client()?, &source, operation.cancel_flag()).await?;
+    let total_bytes = entries.iter().map(|e| e.size).sum();
+    Ok(ImportEstimate {
+        object_count: entries.len(),
+        total_bytes,
+        estimated_seconds: state.imports.estimate_seconds(total_bytes),
+    })
+}
+
+// Local sources are read by path, which needs the user's permission
+async fn allow_source(app: &AppHandle, state: &AppState, source: &ImportSource) -> Result<(), String> {
+    match source {
+        ImportSource::Local { path } => {
+            let detail = format!("importing {}", path.display());
+            permissions::ensure(app, state, Capability::ReadLocalFiles, &detail).await
+        }
+        ImportSource::S3(_) => Ok(()),
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(bucket = %bucket), err)]
+pub async fn start_import(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    source: ImportSource,
+    bucket: String,
+    prefix: Option<String>,
+    on_source_change: Option<SourceChangePolicy>,
+    eject_when_done: Option<bool>,
+) -> Result<ImportJob, AppError> {
+    version::ensure_compatible(&state)?;
+    version::ensure_capability(&state, "streaming uploads", |c| c.stream_upload)?;
+    let prefix = RemotePath::parse(&prefix.unwrap_or_default()).map_err(AppError::invalid)?;
+    allow_source(&app, &state, &source).await?;
+    let job = ImportJob {
+        id: uuid::Uuid::new_v4().to_string(),
+        source,
+        bucket,
+        prefix: prefix.to_string(),
+        on_source_change: on_source_change.unwrap_or_default(),
+        eject_when_done: eject_when_done.unwrap_or(false),
+        status: ImportStatus::Enumerating,
+        total_objects: 0,
+        total_bytes: 0,
+        imported_objects: 0,
+        imported_bytes: 0,
+        skipped_objects: 0,
+        failed_objects: 0,
+        failures: Vec::new(),
+        error: None,
+    };
+    state.imports.inner.lock().unwrap().jobs.push(job.clone());
+    state.imports.save(&app);
+
+    spawn_job(app, Arc::clone(&state), job.id.clone());
+    Ok(job)
+}
+
+#[tauri::command]
+pub fn list_imports(state: tauri::State<Arc<AppState>>) -> Vec<ImportJob> {
+    state.imports.jobs()
+}
+
+#[tauri::command]
+pub fn cancel_import(state: tauri::State<Arc<AppState>>, id: String) -> Result<(), AppError> {
+    match state.imports.cancel.lock().unwrap().get(&id) {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        None => Err(AppError::invalid(format!("Import {} is not active", id))),
+    }
+}
+
+// Restart a failed or cancelled import; already imported objects are skipped
+#[tauri::command]
+pub fn resume_import(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    id: String,
+    secret_access_key: Option<String>,
+) -> Result<(), AppError> {
+    version::ensure_compatible(&state)?;
+    if state.imports.cancel.lock().unwrap().contains_key(&id) {
+        return Err(AppError::busy(format!("Import {} is already active", id)));
+    }
+    let job = state
+        .imports
+        .update(&id, |job| {
+            if let (ImportSource::S3(location), Some(secret)) = (&mut job.source, secret_access_key) {
+                location.secret_access_key = Some(secret);
+            }
+            if matches!(job.status, ImportStatus::Failed | ImportStatus::Cancelled) {
+                job.status = ImportStatus::Enumerating;
+                job.error = None;
+            }
+        })
+        .ok_or_else(|| AppError::invalid(format!("Unknown import {}", id)))?;
+    if job.status != ImportStatus::Enumerating {
+        return Err(AppError::invalid(format!("Import {} has already completed", id)));
+    }
+
+    spawn_job(app, Arc::clone(&state), id);
+    Ok(())
+}
+
+// Object key a source file is imported to
+pub fn dest_key(prefix: &str, key: &str) -> Result<String, String> {
+    let key = RemotePath::sanitize(prefix)?.join(&RemotePath::sanitize(key)?)?;
+    Ok(key.into())
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn spawn_job(app: AppHandle, state: Arc<AppState>, id: String) {
+    let cancel = Arc::new(AtomicBool::new(false));
+    state.imports.cancel.lock().unwrap().insert(id.clone(), Arc::clone(&cancel));
+    let label = state
+        .imports
+        .update(&id, |_| {})
+        .map(|job| format!("Import into {}", job.bucket))
+        .unwrap_or_default();
+    let operation = operations::begin(&app, Some(id.clone()), "import", label, Arc::clone(&cancel));
+
+    tauri::async_runtime::spawn(async move {
+        let result = run_job(&app, &state, &id, &cancel).await;
+        state.imports.cancel.lock().unwrap().remove(&id);
+        transfers::finish(&state, &id);
+        drop(operation);
+
+        let job = state.imports.update(&id, |job| match result {
+            Ok(()) => job.status = ImportStatus::Completed,
+            Err(_) if cancel.load(Ordering::SeqCst) => job.status = ImportStatus::Cancelled,
+            Err(e) => {
+                log::error!("Import {} failed: {}", job.id, e);
+                job.status = ImportStatus::Failed;
+                job.error = Some(e);
+            }
+        });
+        state.imports.save(&app);
+        if let Some(job) = job {
+            if job.status == ImportStatus::Completed {
+                let imported: Vec<String> =
+                    state.imports.inner.lock().unwrap().done.get(&id).into_iter().flatten().cloned().collect();
+                suggestions::learn(&app, &state, &job.bucket, &job.prefix, &imported);
+                eject::import_completed(&app, &state, &job);
+            }
+            if job.status == ImportStatus::Failed {
+                jobs::notify_failed(&app, &job.id);
+            }
+            jobs::import_updated(&app, &job);
+            emit_to_main(&app, "import-progress", job);
+        }
+    });
+}
+
+#[tracing::instrument(name = "transfer.import", skip_all, fields(id = %id), err)]
+async fn run_job(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    id: &str,
+    cancel: &Arc<AtomicBool>,
+) -> Result<(), String> {
+    let job = state
+        .imports
+        .update(id, |_| {})
+        .ok_or_else(|| format!("Unknown import {}", id))?;
+
+    // Imports go through the bundled backend, so wait until it is up
+    while !state.is_healthy() {
+        if cancel.load(Ordering::SeqCst) || state.shutdown.load(Ordering::SeqCst) {
+            return Err("Cancelled".to_string());
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    let client = http_client()?;
+    let entries = enumerate(&client, &job.source, cancel).await?;
+    // Files imported by an earlier run are skipped unless they changed since
+    let pending: Vec<SourceEntry> = {
+        let mut inner = state.imports.inner.lock().unwrap();
+        let done = inner.done.remove(id).unwrap_or_default();
+        let mut stamps = inner.stamps.remove(id).unwrap_or_default();
+        let (unchanged, pending): (Vec<_>, Vec<_>) = entries.into_iter().partition(|entry| {
+            done.contains(&entry.key) && stamps.get(&entry.key).map_or(true, |stamp| Some(*stamp) == entry.stamp())
+        });
+        transfers::track(
+            app,
+            id,
+            "import",
+            unchanged
+                .iter()
+                .map(|e| (e.key.clone(), e.size, true))
+                .chain(pending.iter().map(|e| (e.key.clone(), e.size, false)))
+                .collect(),
+        );
+        let unchanged_keys: HashSet<String> = unchanged.into_iter().map(|e| e.key).collect();
+        stamps.retain(|key, _| unchanged_keys.contains(key));
+        inner.done.insert(id.to_string(), unchanged_keys);
+        inner.stamps.insert(id.to_string(), stamps);
+        pending
+    };
+    let skipped = state.imports.inner.lock().unwrap().done.get(id).map(|d| d.len()).unwrap_or(0);
+    if let Some(job) = state.imports.update(id, |job| {
+        job.status = ImportStatus::Running;
+        job.total_objects = skipped + pending.len();
+        job.total_bytes = pending.iter().map(|e| e.size).sum();
+        job.imported_objects = skipped;
+        job.imported_bytes = 0;
+        job.skipped_objects = skipped;
+        job.failed_objects = 0;
+        job.failures.clear();
+    }) {
+        jobs::import_updated(app, &job);
+        emit_to_main(app, "import-progress", job);
+    }
+
+    let mut since_checkpoint = 0;
+    let mut consecutive_failures = 0;
+    for entry in pending {
+        // Back off while the machine is short on memory or disk
+        if !state.resources.throttle(state, cancel).await || cancel.load(Ordering::SeqCst) {
+            state.imports.save(app);
+            return Err("Cancelled".to_string());
+        }
+
+        if !network::wait_for_wifi(app, state, id, &entry.key, entry.size, cancel).await {
+            state.imports.save(app);
+            return Err("Cancelled".to_string());
+        }
+
+        let started = Instant::now();
+        let base_url = state.base_url();
+        let backend_client = backend::shared_client(&base_url);
+        let dest_key = dest_key(&job.prefix, &entry.key);
+        let copied = Arc::new(AtomicU64::new(0));
+        let throttle = Throttle::new(state, Direction::Upload, Some(id));
+        transfers::start_file(state, id, &entry.key, &copied);
+        // Whether the source failed rather than the backend
+        let mut source_failed = false;
+        let result = match (&job.source, &dest_key) {
+            (_, Err(e)) => Err(e.clone()),
+            (ImportSource::Local { path }, Ok(dest_key)) => {
+                backend::upload_local_file(
+                    &backend_client,
+                    &base_url,
+                    &job.bucket,
+                    dest_key,
+                    &path.join(&entry.key),
+                    job.on_source_change,
+                    &throttle,
+                    cancel,
+                    &copied,
+                )
+                .await
+            }
+            (ImportSource::S3(location), Ok(dest_key)) => match location.get(&client, &entry.key).await {
+                Ok(resp) => {
+                    let stream = bandwidth::throttled(resp.bytes_stream(), throttle);
+                    let body = backend::metered_body(stream, Arc::clone(cancel), Arc::clone(&copied));
+                    backend::upload_stream(&backend_client, &base_url, &job.bucket, dest_key, body).await
+                }
+                Err(e) => {
+                    source_failed = true;
+                    Err(e)
+                }
+            },
+        };
+        transfers::finish_file(state, id, &entry.key, &result, cancel.load(Ordering::SeqCst));
+
+        // A failed file is recorded and the run carries on; resuming retries just the
+        // failures. Stop early when everything fails, e.g. the network or backend is down.
+        if let Err(e) = result {
+            if cancel.load(Ordering::SeqCst) {
+                state.imports.save(app);
+                return Err("Cancelled".to_string());
+            }
+            log::warn!("Import {} failed on {}: {}", id, entry.key, e);
+            if !source_failed && auth::report(app, state, &e) {
+                // Resuming after signing in again retries this file
+                state.imports.save(app);
+                return Err("Paused until you sign in again".to_string());
+            }
+            consecutive_failures += 1;
+            if let Some(job) = state.imports.update(id, |job| {
+                job.failed_objects += 1;
+                if job.failures.len() < MAX_REPORTED_FAILURES {
+                    job.failures.push(ImportFailure {
+                        key: entry.key.clone(),
+                        error: e.clone(),
+                    });
+                }
+            }) {
+                jobs::import_updated(app, &job);
+        emit_to_main(app, "import-progress", job);
+            }
+            if consecutive_failures >= MAX_CONSECUTIVE_FAILURES || !state.is_healthy() {
+                state.imports.save(app);
+                return Err(format!("Stopped after repeated failures; last error: {}", e));
+            }
+            continue;
+        }
+        consecutive_failures = 0;
+
+        let bytes = copied.load(Ordering::Relaxed);
+        state.imports.record_throughput(bytes, started.elapsed());
+        if let Some(job) = state.imports.mark_imported(app, id, &entry, bytes) {
+            jobs::import_updated(app, &job);
+        emit_to_main(app, "import-progress", job);
+        }
+
+        since_checkpoint += 1;
+        if since_checkpoint >= CHECKPOINT_EVERY {
+            state.imports.save(app);
+            since_checkpoint = 0;
+        }
+    }
+
+    let failed = state.imports.update(id, |_| {}).map(|job| job.failed_objects).unwrap_or(0);
+    if failed > 0 {
+        state.imports.save(app);
+        return Err(format!("{} file(s) failed to import; resume to retry them", failed));
+    }
+    let mut inner = state.imports.inner.lock().unwrap();
+    inner.done.remove(id);
+    inner.stamps.remove(id);
+    Ok(())
+}
+
+// List everything an import would copy, with keys relative to the source root
+async fn enumerate(
+    client: &reqwest::Client,
+    source: &ImportSource,
+    cancel: &Arc<AtomicBool>,
+) -> Result<Vec<SourceEntry>, String> {
+    match source {
+        ImportSource::Local { path } => {
+            let root = path.clone();
+            let cancel = Arc::clone(cancel);
+            tauri::async_runtime::spawn_blocking(move || walk_local(&root, &cancel))
+                .await
+                .map_err(|e| e.to_string())?
+        }
+        ImportSource::S3(location) => {
+            let prefix = location.prefix.as_str();
+            Ok(location
+                .list(client)
+                .await?
+                .into_iter()
+                .map(|o| SourceEntry {
+                    key: o.key.strip_prefix(prefix).unwrap_or(&o.key).trim_start_matches('/').to_string(),
+                    size: o.size,
+                    modified: None,
+                })
+                .collect())
+        }
+    }
+}
+
+// Every regular file under `root`, keyed by its '/'-separated relative path
+pub fn walk_local(root: &Path, cancel: &AtomicBool) -> Result<Vec<SourceEntry>, String> {
+    let mut entries = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        if cancel.load(Ordering::SeqCst) {
+            return Err("Cancelled".to_string());
+        }
+        let listing = std::fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+        for entry in listing.flatten() {
+            let Ok(file_type) = entry.file_type() else { continue };
+            let path = entry.path();
+            if file_type.is_dir() {
+                dirs.push(path);
+            } else if file_type.is_file() {
+                let Ok(relative) = path.strip_prefix(root) else { continue };
+                let key = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let meta = entry.metadata().ok();
+                let size = meta.as_ref().map(|m| m.len()).unwrap_or(0);
+                let modified = meta
+                    .and_then(|m| m.modified().ok())
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs() as i64);
+                entries.push(SourceEntry { key, size, modified });
+            }
+        }
+    }
+
+    Ok(entries)
+}
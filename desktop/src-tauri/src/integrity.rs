@@ -0,0 +1,87 @@
+use std::io::Read;
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+// Recorded by build.rs from the binary that was bundled
+const EXPECTED_SHA256: Option<&str> = option_env!("BB_SIDECAR_SHA256");
+const EXPECTED_SIZE: Option<&str> = option_env!("BB_SIDECAR_SIZE");
+const CODESIGNED: Option<&str> = option_env!("BB_SIDECAR_CODESIGNED");
+
+// Where the shell plugin runs the sidecar from: next to the app's own executable
+fn sidecar_path() -> Result<PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate the app: {}", e))?;
+    let dir = exe.parent().ok_or("Failed to locate the app's directory")?;
+    let name = if cfg!(windows) { "bb-stream.exe" } else { "bb-stream" };
+    Ok(dir.join(name))
+}
+
+pub fn sha256_file(path: &std::path::Path) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 256 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if n == 0 {
+            return Ok(hex::encode(hasher.finalize()));
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn verify_signature(path: &std::path::Path) -> Result<(), String> {
+    let output = std::process::Command::new("codesign")
+        .args(["--verify", "--strict"])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Failed to run codesign: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} has an invalid code signature: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn verify_signature(_path: &std::path::Path) -> Result<(), String> {
+    Ok(())
+}
+
+// Check the bundled sidecar against what was built before executing it. Builds made
+// without the binary present carry no manifest and skip the check.
+pub fn verify_sidecar() -> Result<(), String> {
+    let path = sidecar_path()?;
+    if CODESIGNED.is_some() {
+        return verify_signature(&path);
+    }
+    let Some(expected) = EXPECTED_SHA256 else {
+        log::warn!("No sidecar manifest in this build; not verifying {}", path.display());
+        return Ok(());
+    };
+
+    let size = std::fs::metadata(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if let Some(expected_size) = EXPECTED_SIZE.and_then(|s| s.parse::<u64>().ok()) {
+        if size != expected_size {
+            return Err(format!(
+                "{} is {} bytes but {} were bundled",
+                path.display(),
+                size,
+                expected_size
+            ));
+        }
+    }
+    let actual = sha256_file(&path)?;
+    if actual != expected {
+        return Err(format!("{} has SHA-256 {}, expected {}", path.display(), actual, expected));
+    }
+    Ok(())
+}
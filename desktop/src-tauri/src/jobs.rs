@@ -0,0 +1,485 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::file_download::{DownloadStatus, FileDownload};
+use crate::import::{ImportJob, ImportStatus};
+use crate::notifications::{self, Notification};
+use crate::operations::OperationInfo;
+use crate::remote_copy::{CopyStatus, RemoteCopyJob};
+use crate::url_upload::{UrlUpload, UrlUploadStatus};
+use crate::{backend, emit_to_main, file_download, import, operations, remote_copy, store, url_upload, AppState};
+
+const PAUSED_FILE: &str = "paused-jobs.json";
+
+// Backend jobs are left out of the list when the backend takes longer than this
+const BACKEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Operation kinds that are jobs of their own, listed from their subsystem instead
+const OWN_KINDS: [&str; 4] = ["import", "remote_copy", "url_upload", "download"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+// Any long-running work, in one shape for an activity center: imports and remote
+// copies, the backend's syncs and watches, and other registered operations
+#[derive(Clone, Debug, Serialize)]
+pub struct Job {
+    pub id: String,
+    // "import", "remote_copy", "url_upload", "download", "sync", "watch", or an operation's kind such as "export"
+    pub kind: String,
+    pub label: String,
+    pub status: JobStatus,
+    // Files or objects done and in total, when the job counts them
+    pub done: Option<u64>,
+    pub total: Option<u64>,
+    pub bytes: Option<u64>,
+    // What the job is doing right now, when it says
+    pub detail: Option<String>,
+    pub error: Option<String>,
+    pub can_pause: bool,
+    pub can_cancel: bool,
+    // Failed, cancelled and paused jobs pick up where they stopped
+    pub can_retry: bool,
+}
+
+impl Job {
+    fn new(id: &str, kind: &str, label: String, status: JobStatus) -> Self {
+        let active = matches!(status, JobStatus::Queued | JobStatus::Running);
+        Self {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            label,
+            status,
+            done: None,
+            total: None,
+            bytes: None,
+            detail: None,
+            error: None,
+            can_pause: false,
+            can_cancel: active,
+            can_retry: false,
+        }
+    }
+}
+
+// Jobs the user paused. The shell's jobs pause by stopping where they are and
+// resume like a retry, so this only tells a pause apart from a cancel.
+pub struct Jobs {
+    paused: Mutex<HashSet<String>>,
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Self {
+            paused: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn is_paused(&self, id: &str) -> bool {
+        self.paused.lock().unwrap().contains(id)
+    }
+}
+
+fn save_paused(app: &AppHandle, state: &AppState) {
+    let paused = state.jobs.paused.lock().unwrap().clone();
+    let result = store::data_file(app, PAUSED_FILE).and_then(|path| store::save_json(&path, &paused));
+    if let Err(e) = result {
+        log::error!("Failed to save paused jobs: {}", e);
+    }
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, PAUSED_FILE) {
+        Ok(path) => *state.jobs.paused.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load paused jobs: {}", e),
+    }
+}
+
+fn from_import(state: &AppState, job: &ImportJob) -> Job {
+    let status = match job.status {
+        ImportStatus::Enumerating | ImportStatus::Running => JobStatus::Running,
+        ImportStatus::Completed => JobStatus::Completed,
+        ImportStatus::Failed => JobStatus::Failed,
+        ImportStatus::Cancelled if state.jobs.is_paused(&job.id) => JobStatus::Paused,
+        ImportStatus::Cancelled => JobStatus::Cancelled,
+    };
+    let label = match job.prefix.as_str() {
+        "" => format!("Import into {}", job.bucket),
+        prefix => format!("Import into {}/{}", job.bucket, prefix),
+    };
+    let mut converted = Job::new(&job.id, "import", label, status);
+    converted.done = Some((job.imported_objects + job.skipped_objects) as u64);
+    converted.total = Some(job.total_objects as u64);
+    converted.bytes = Some(job.imported_bytes);
+    converted.error = job.error.clone();
+    converted.can_pause = status == JobStatus::Running;
+    converted.can_retry = matches!(status, JobStatus::Failed | JobStatus::Cancelled | JobStatus::Paused);
+    converted
+}
+
+fn from_remote_copy(state: &AppState, job: &RemoteCopyJob) -> Job {
+    let status = match job.status {
+        CopyStatus::Scheduled => JobStatus::Queued,
+        CopyStatus::Running => JobStatus::Running,
+        CopyStatus::Completed => JobStatus::Completed,
+        CopyStatus::Failed => JobStatus::Failed,
+        CopyStatus::Cancelled if state.jobs.is_paused(&job.id) => JobStatus::Paused,
+        CopyStatus::Cancelled => JobStatus::Cancelled,
+    };
+    let label = format!("Copy {} to {}", job.source.bucket, job.dest.bucket);
+    let mut converted = Job::new(&job.id, "remote_copy", label, status);
+    converted.done = Some(job.copied_objects as u64);
+    converted.total = Some(job.total_objects as u64);
+    converted.bytes = Some(job.copied_bytes);
+    converted.error = job.error.clone();
+    converted.can_pause = matches!(status, JobStatus::Queued | JobStatus::Running);
+    converted.can_retry = matches!(status, JobStatus::Failed | JobStatus::Cancelled | JobStatus::Paused);
+    converted
+}
+
+fn from_url_upload(state: &AppState, upload: &UrlUpload) -> Job {
+    let status = match upload.status {
+        UrlUploadStatus::Running => JobStatus::Running,
+        UrlUploadStatus::Completed => JobStatus::Completed,
+        UrlUploadStatus::Failed => JobStatus::Failed,
+        UrlUploadStatus::Cancelled if state.jobs.is_paused(&upload.id) => JobStatus::Paused,
+        UrlUploadStatus::Cancelled => JobStatus::Cancelled,
+    };
+    let label = format!("Upload {} to {}", upload.info.name, upload.dest.bucket);
+    let mut converted = Job::new(&upload.id, "url_upload", label, status);
+    converted.bytes = Some(upload.transferred);
+    converted.detail = Some(upload.url.clone());
+    converted.error = upload.error.clone();
+    // Without ranges a paused upload would start over, so it can only be cancelled
+    converted.can_pause = status == JobStatus::Running && upload.info.resumable;
+    converted.can_retry = matches!(status, JobStatus::Failed | JobStatus::Cancelled | JobStatus::Paused);
+    converted
+}
+
+fn from_download(state: &AppState, download: &FileDownload) -> Job {
+    let status = match download.status {
+        DownloadStatus::Running => JobStatus::Running,
+        DownloadStatus::Completed => JobStatus::Completed,
+        DownloadStatus::Failed => JobStatus::Failed,
+        DownloadStatus::Cancelled if state.jobs.is_paused(&download.id) => JobStatus::Paused,
+        DownloadStatus::Cancelled => JobStatus::Cancelled,
+    };
+    let label = format!("Download {}", download.key);
+    let mut converted = Job::new(&download.id, "download", label, status);
+    converted.bytes = Some(download.transferred);
+    converted.detail = Some(download.dest.display().to_string());
+    converted.error = download.error.clone();
+    converted.can_pause = status == JobStatus::Running;
+    converted.can_retry = matches!(status, JobStatus::Failed | JobStatus::Cancelled | JobStatus::Paused);
+    converted
+}
+
+// One of the shell's own jobs: an import, remote copy, URL upload or download
+pub fn local_job(state: &AppState, id: &str) -> Option<Job> {
+    if let Some(job) = state.imports.job(id) {
+        return Some(from_import(state, &job));
+    }
+    if let Some(job) = state.remote_copies.job(id) {
+        return Some(from_remote_copy(state, &job));
+    }
+    if let Some(upload) = state.url_uploads.job(id) {
+        return Some(from_url_upload(state, &upload));
+    }
+    state.downloads.job(id).map(|download| from_download(state, &download))
+}
+
+// Count stopped jobs as paused, so they can be resumed with retry_job or dropped with
+// cancel_job like ones the user paused
+pub fn hold(app: &AppHandle, state: &AppState, ids: &[String]) {
+    state.jobs.paused.lock().unwrap().extend(ids.iter().cloned());
+    save_paused(app, state);
+}
+
+// Tell the user one of the shell's jobs failed, with a button to run it again
+pub fn notify_failed(app: &AppHandle, id: &str) {
+    let state = app.state::<Arc<AppState>>();
+    let Some(job) = local_job(&state, id) else {
+        return;
+    };
+    let body = match &job.error {
+        Some(error) => format!("{}: {}", job.label, error),
+        None => job.label.clone(),
+    };
+    let id = id.to_string();
+    notifications::notify(
+        app,
+        &state,
+        Notification::new("Transfer failed", body).action("retry", "Retry"),
+        Some(Box::new(move |app: &AppHandle, action: &str| {
+            if action == "retry" {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = retry_job(app.clone(), app.state(), id).await {
+                        log::error!("Failed to retry a job: {}", e);
+                    }
+                });
+            }
+        })),
+    );
+}
+
+fn from_operation(info: &OperationInfo) -> Job {
+    Job::new(&info.id, &info.kind, info.label.clone(), JobStatus::Running)
+}
+
+// A sync or watch as GET /api/jobs reports it
+#[derive(Deserialize)]
+struct BackendJob {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    status: String,
+    #[serde(default)]
+    local_path: String,
+    #[serde(default)]
+    bucket: String,
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    progress: Option<String>,
+}
+
+impl BackendJob {
+    fn to_job(&self) -> Job {
+        let status = match self.status.as_str() {
+            "running" => JobStatus::Running,
+            "completed" => JobStatus::Completed,
+            "failed" => JobStatus::Failed,
+            // Drained jobs come back after the restart
+            "drained" => JobStatus::Paused,
+            _ => JobStatus::Cancelled,
+        };
+        let remote = match self.path.as_str() {
+            "" => self.bucket.clone(),
+            path => format!("{}/{}", self.bucket, path),
+        };
+        let label = match self.kind.as_str() {
+            "watch" => format!("Watch {} into {}", self.local_path, remote),
+            _ => format!("Sync {} with {}", self.local_path, remote),
+        };
+        let mut job = Job::new(&self.id, &self.kind, label, status);
+        match status {
+            JobStatus::Failed => job.error = self.progress.clone(),
+            _ => job.detail = self.progress.clone(),
+        }
+        // The backend can stop a watch and start it again, but can't interrupt a sync
+        let watch = self.kind == "watch";
+        job.can_cancel = watch && status == JobStatus::Running;
+        job.can_retry = watch && status == JobStatus::Cancelled;
+        job
+    }
+}
+
+async fn backend_jobs(state: &AppState) -> Result<Vec<BackendJob>, String> {
+    let base_url = state.base_url();
+    let resp = backend::shared_client(&base_url)
+        .get(backend::api_url(&base_url, &["jobs"])?)
+        .timeout(BACKEND_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("Listing jobs returned status: {}", resp.status()));
+    }
+    resp.json().await.map_err(|e| e.to_string())
+}
+
+async fn backend_job(state: &AppState, id: &str) -> Result<BackendJob, String> {
+    backend_jobs(state)
+        .await?
+        .into_iter()
+        .find(|job| job.id == id)
+        .ok_or_else(|| format!("Unknown job {}", id))
+}
+
+async fn post_backend(state: &AppState, segments: &[&str], body: serde_json::Value) -> Result<(), String> {
+    let base_url = state.base_url();
+    let resp = backend::shared_client(&base_url)
+        .post(backend::api_url(&base_url, segments)?)
+        .json(&body)
+        .timeout(BACKEND_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("{} returned status: {}", segments.join("/"), resp.status()));
+    }
+    Ok(())
+}
+
+fn emit(app: &AppHandle, job: Job) {
+    emit_to_main(app, "job-updated", job);
+}
+
+// Hooks for subsystems, called wherever they report progress of their own
+pub fn import_updated(app: &AppHandle, job: &ImportJob) {
+    let state = app.state::<Arc<AppState>>();
+    emit(app, from_import(&state, job));
+}
+
+pub fn remote_copy_updated(app: &AppHandle, job: &RemoteCopyJob) {
+    let state = app.state::<Arc<AppState>>();
+    emit(app, from_remote_copy(&state, job));
+}
+
+pub fn url_upload_updated(app: &AppHandle, upload: &UrlUpload) {
+    let state = app.state::<Arc<AppState>>();
+    emit(app, from_url_upload(&state, upload));
+}
+
+pub fn download_updated(app: &AppHandle, download: &FileDownload) {
+    let state = app.state::<Arc<AppState>>();
+    emit(app, from_download(&state, download));
+}
+
+pub fn operation_started(app: &AppHandle, info: &OperationInfo) {
+    if !OWN_KINDS.contains(&info.kind.as_str()) {
+        emit(app, from_operation(info));
+    }
+}
+
+pub fn operation_finished(app: &AppHandle, info: &OperationInfo, cancelled: bool) {
+    if !OWN_KINDS.contains(&info.kind.as_str()) {
+        let status = if cancelled { JobStatus::Cancelled } else { JobStatus::Completed };
+        emit(app, Job::new(&info.id, &info.kind, info.label.clone(), status));
+    }
+}
+
+// Every job the shell and the active backend know of. Backend jobs are left out
+// while the backend is down; `job-updated` events don't cover them, as the backend
+// reports their progress over its WebSocket.
+#[tauri::command]
+pub async fn list_jobs(state: tauri::State<'_, Arc<AppState>>) -> Result<Vec<Job>, AppError> {
+    let mut jobs: Vec<Job> = state.imports.jobs().iter().map(|job| from_import(&state, job)).collect();
+    let copies = state.remote_copies.jobs();
+    jobs.extend(copies.iter().map(|job| from_remote_copy(&state, job)));
+    let uploads = state.url_uploads.jobs();
+    jobs.extend(uploads.iter().map(|upload| from_url_upload(&state, upload)));
+    let downloads = state.downloads.jobs();
+    jobs.extend(downloads.iter().map(|download| from_download(&state, download)));
+    jobs.extend(
+        operations::list(&state)
+            .iter()
+            .filter(|info| !OWN_KINDS.contains(&info.kind.as_str()))
+            .map(from_operation),
+    );
+    if state.is_healthy() {
+        match backend_jobs(&state).await {
+            Ok(backend) => jobs.extend(backend.iter().map(BackendJob::to_job)),
+            Err(e) => log::warn!("Failed to list backend jobs: {}", e),
+        }
+    }
+    Ok(jobs)
+}
+
+// Stop a job where it is, to pick it up later with retry_job
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(id = %id), err)]
+pub fn pause_job(app: AppHandle, state: tauri::State<Arc<AppState>>, id: String) -> Result<(), AppError> {
+    if let Some(job) = state.imports.job(&id) {
+        if from_import(&state, &job).can_pause {
+            state.jobs.paused.lock().unwrap().insert(id.clone());
+            save_paused(&app, &state);
+            return import::cancel_import(state, id);
+        }
+    } else if let Some(job) = state.remote_copies.job(&id) {
+        if from_remote_copy(&state, &job).can_pause {
+            state.jobs.paused.lock().unwrap().insert(id.clone());
+            save_paused(&app, &state);
+            return remote_copy::cancel_remote_copy(state, id);
+        }
+    } else if let Some(upload) = state.url_uploads.job(&id) {
+        if from_url_upload(&state, &upload).can_pause {
+            state.jobs.paused.lock().unwrap().insert(id.clone());
+            save_paused(&app, &state);
+            return url_upload::cancel_url_upload(state, id);
+        }
+    } else if let Some(download) = state.downloads.job(&id) {
+        if from_download(&state, &download).can_pause {
+            state.jobs.paused.lock().unwrap().insert(id.clone());
+            save_paused(&app, &state);
+            return file_download::cancel_download(&state, &id);
+        }
+    }
+    Err(AppError::invalid(format!("Job {} can't be paused", id)))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(id = %id), err)]
+pub async fn cancel_job(app: AppHandle, state: tauri::State<'_, Arc<AppState>>, id: String) -> Result<(), AppError> {
+    if state.jobs.paused.lock().unwrap().remove(&id) {
+        // Already stopped; now it stays that way
+        save_paused(&app, &state);
+        return Ok(());
+    }
+    if state.imports.job(&id).is_some() {
+        return import::cancel_import(state, id);
+    }
+    if state.remote_copies.job(&id).is_some() {
+        return remote_copy::cancel_remote_copy(state, id);
+    }
+    if state.url_uploads.job(&id).is_some() {
+        return url_upload::cancel_url_upload(state, id);
+    }
+    if state.downloads.job(&id).is_some() {
+        return file_download::cancel_download(&state, &id);
+    }
+    if operations::cancel(&state, &id).is_ok() {
+        return Ok(());
+    }
+    let job = backend_job(&state, &id).await?.to_job();
+    if !job.can_cancel {
+        return Err(AppError::invalid(format!("Job {} can't be cancelled", id)));
+    }
+    Ok(post_backend(&state, &["watch", "stop"], serde_json::json!({ "job_id": id })).await?)
+}
+
+// Resume a paused job, or run a failed or cancelled one again; work already done is
+// skipped
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(id = %id), err)]
+pub async fn retry_job(app: AppHandle, state: tauri::State<'_, Arc<AppState>>, id: String) -> Result<(), AppError> {
+    if state.jobs.paused.lock().unwrap().remove(&id) {
+        save_paused(&app, &state);
+    }
+    if state.imports.job(&id).is_some() {
+        return import::resume_import(app.clone(), app.state(), id, None);
+    }
+    if state.remote_copies.job(&id).is_some() {
+        return remote_copy::resume_remote_copy(app.clone(), app.state(), id);
+    }
+    if state.url_uploads.job(&id).is_some() {
+        return url_upload::resume_url_upload(app.clone(), app.state(), id);
+    }
+    if state.downloads.job(&id).is_some() {
+        return file_download::resume_download(app.clone(), &state, id);
+    }
+    let backend = backend_job(&state, &id).await?;
+    if !backend.to_job().can_retry {
+        return Err(AppError::invalid(format!("Job {} can't be retried", id)));
+    }
+    let request = serde_json::json!({
+        "local_path": backend.local_path,
+        "bucket": backend.bucket,
+        "path": backend.path,
+    });
+    Ok(post_backend(&state, &["watch", "start"], request).await?)
+}
@@ -0,0 +1,67 @@
+use std::io::Write;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::store;
+
+// Write-ahead journals for the job stores. A store's JSON snapshot is only rewritten
+// every so often; every change in between is appended here as one JSON line and
+// synced before it counts, so a crash or power loss loses none of them. Loading a
+// store replays its journal over the snapshot, and saving a snapshot empties it.
+
+// Append an entry and sync it to disk
+pub fn append<T: Serialize>(app: &AppHandle, name: &str, entry: &T) -> Result<(), String> {
+    let path = store::data_file(app, name)?;
+    let mut line = serde_json::to_vec(entry).map_err(|e| e.to_string())?;
+    line.push(b'\n');
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    file.write_all(&line)
+        .and_then(|()| file.sync_data())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+// Entries appended since the last snapshot, oldest first. A torn last line, left by a
+// crash in the middle of an append, is dropped; that change never counted.
+pub fn replay<T: DeserializeOwned>(app: &AppHandle, name: &str) -> Vec<T> {
+    let path = match store::data_file(app, name) {
+        Ok(path) => path,
+        Err(e) => {
+            log::error!("Failed to open journal {}: {}", name, e);
+            return Vec::new();
+        }
+    };
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+
+    let mut entries = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => {
+                log::warn!("Stopping replay of {} at an unreadable entry: {}", path.display(), e);
+                break;
+            }
+        }
+    }
+    if !entries.is_empty() {
+        log::info!("Replayed {} journal entries from {}", entries.len(), path.display());
+    }
+    entries
+}
+
+// Empty the journal once a snapshot containing its entries has been saved
+pub fn clear(app: &AppHandle, name: &str) -> Result<(), String> {
+    let path = store::data_file(app, name)?;
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to clear {}: {}", path.display(), e)),
+    }
+}
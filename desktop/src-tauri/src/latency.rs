@@ -0,0 +1,201 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::{emit_to_main, AppState};
+
+// Samples older than this no longer count, and at most MAX_SAMPLES are kept per source
+const WINDOW: Duration = Duration::from_secs(5 * 60);
+const MAX_SAMPLES: usize = 200;
+
+// Percentiles of fewer samples say too little to call the backend slow
+const MIN_SAMPLES: usize = 5;
+
+// p95 of proxied requests at which the backend counts as slow; health checks use half
+// their timeout, since they fail at the full one
+const PROXY_SLOW: Duration = Duration::from_secs(5);
+
+// A slow backend counts as recovered once both p95s drop below this share of their threshold
+const RECOVER_RATIO: f64 = 0.8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Source {
+    // The health checker's GET /health
+    Health,
+    // Requests proxied for the frontend by backend_request and api_request
+    Proxy,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub threshold_ms: u64,
+}
+
+// Sent as `backend-slow` when the active profile's backend becomes slow or recovers
+#[derive(Clone, Debug, Serialize)]
+pub struct LatencyStatus {
+    pub profile: String,
+    pub slow: bool,
+    // Unix milliseconds
+    pub slow_since: Option<i64>,
+    pub health: LatencyStats,
+    pub proxy: LatencyStats,
+}
+
+#[derive(Default)]
+struct Tracked {
+    health: VecDeque<(Instant, u64)>,
+    proxy: VecDeque<(Instant, u64)>,
+    slow_since: Option<i64>,
+}
+
+impl Tracked {
+    fn samples(&mut self, source: Source) -> &mut VecDeque<(Instant, u64)> {
+        match source {
+            Source::Health => &mut self.health,
+            Source::Proxy => &mut self.proxy,
+        }
+    }
+}
+
+// Rolling response times of each profile's backend
+pub struct Latency {
+    profiles: Mutex<HashMap<String, Tracked>>,
+}
+
+impl Latency {
+    pub fn new() -> Self {
+        Self {
+            profiles: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[u64], p: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    Some(sorted[rank - 1])
+}
+
+fn stats(samples: &VecDeque<(Instant, u64)>, threshold: Duration) -> LatencyStats {
+    let mut sorted: Vec<u64> = samples.iter().map(|(_, ms)| *ms).collect();
+    sorted.sort_unstable();
+    LatencyStats {
+        samples: sorted.len(),
+        p50_ms: percentile(&sorted, 0.5),
+        p95_ms: percentile(&sorted, 0.95),
+        threshold_ms: threshold.as_millis() as u64,
+    }
+}
+
+fn health_threshold(state: &AppState) -> Duration {
+    state.health_checks.settings().timeout() / 2
+}
+
+// How a source's p95 compares to its threshold, once there are enough samples
+fn load(stats: &LatencyStats) -> Option<f64> {
+    match stats.p95_ms {
+        Some(p95) if stats.samples >= MIN_SAMPLES && stats.threshold_ms > 0 => {
+            Some(p95 as f64 / stats.threshold_ms as f64)
+        }
+        _ => None,
+    }
+}
+
+fn status(state: &AppState, profile: &str, tracked: &Tracked) -> LatencyStatus {
+    LatencyStatus {
+        profile: profile.to_string(),
+        slow: tracked.slow_since.is_some(),
+        slow_since: tracked.slow_since,
+        health: stats(&tracked.health, health_threshold(state)),
+        proxy: stats(&tracked.proxy, PROXY_SLOW),
+    }
+}
+
+// Add a response time for `profile`'s backend and report when that makes it slow or
+// brings it back
+pub fn record(app: &AppHandle, profile: &str, source: Source, elapsed: Duration) {
+    let state = app.state::<Arc<AppState>>();
+    let now = Instant::now();
+    let changed = {
+        let mut profiles = state.latency.profiles.lock().unwrap();
+        let tracked = profiles.entry(profile.to_string()).or_default();
+        let samples = tracked.samples(source);
+        samples.push_back((now, elapsed.as_millis() as u64));
+        while samples.len() > MAX_SAMPLES || samples.front().is_some_and(|(at, _)| now - *at > WINDOW) {
+            samples.pop_front();
+        }
+
+        let current = status(&state, profile, tracked);
+        let loads = [load(&current.health), load(&current.proxy)];
+        let slow = loads.iter().flatten().any(|load| *load >= 1.0);
+        let recovered = loads.iter().flatten().all(|load| *load < RECOVER_RATIO);
+        match tracked.slow_since {
+            None if slow => {
+                tracked.slow_since = Some(chrono::Utc::now().timestamp_millis());
+                Some(status(&state, profile, tracked))
+            }
+            Some(_) if recovered => {
+                tracked.slow_since = None;
+                Some(status(&state, profile, tracked))
+            }
+            _ => None,
+        }
+    };
+
+    if let Some(status) = changed {
+        if status.slow {
+            log::warn!(
+                "Backend of profile {} is slow: health p95 {:?}ms, requests p95 {:?}ms",
+                profile,
+                status.health.p95_ms,
+                status.proxy.p95_ms
+            );
+        } else {
+            log::info!("Backend of profile {} responds normally again", profile);
+        }
+        if *state.active_profile.lock().unwrap() == status.profile {
+            emit_to_main(app, "backend-slow", status);
+        }
+    }
+}
+
+// Send a request to `profile`'s backend and record how long the response took to
+// arrive. Timeouts count; requests that couldn't connect say nothing about speed.
+pub async fn timed<F>(app: &AppHandle, profile: &str, request: F) -> Result<reqwest::Response, reqwest::Error>
+where
+    F: Future<Output = Result<reqwest::Response, reqwest::Error>>,
+{
+    let started = Instant::now();
+    let result = request.await;
+    if result.as_ref().map_or_else(|e| e.is_timeout(), |_| true) {
+        record(app, profile, Source::Proxy, started.elapsed());
+    }
+    result
+}
+
+// p50/p95 of recent health checks and proxied requests, for the active profile unless
+// told otherwise
+#[tauri::command]
+pub fn get_health_latency(state: tauri::State<Arc<AppState>>, profile: Option<String>) -> LatencyStatus {
+    let profile = profile.unwrap_or_else(|| state.active_profile.lock().unwrap().clone());
+    let mut profiles = state.latency.profiles.lock().unwrap();
+    let Some(tracked) = profiles.get_mut(&profile) else {
+        return status(&state, &profile, &Tracked::default());
+    };
+    let now = Instant::now();
+    for source in [Source::Health, Source::Proxy] {
+        tracked.samples(source).retain(|(at, _)| now - *at <= WINDOW);
+    }
+    status(&state, &profile, tracked)
+}
@@ -1,141 +1,755 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicU16, AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 use tauri::{Manager, Emitter, AppHandle};
 use tauri::menu::{Menu, MenuItem, Submenu, PredefinedMenuItem};
 use tauri_plugin_shell::ShellExt;
 use tauri_plugin_shell::process::{CommandChild, CommandEvent};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
+use tracing::Instrument;
+
+use error::AppError;
+
+mod access_stats;
+mod accounts;
+mod activity;
+mod auth;
+mod backend;
+mod backend_logs;
+mod backend_metrics;
+mod bandwidth;
+mod checksums;
+mod chunked_upload;
+mod clock;
+mod collation;
+mod context_menu;
+mod copy_urls;
+mod crash_report;
+mod crash_reporter;
+mod diagnostics;
+mod dock_drop;
+mod drain;
+mod eject;
+mod error;
+mod event_stream;
+mod export;
+mod external;
+mod file_download;
+mod file_manager;
+mod file_upload;
+mod guest;
+mod health;
+mod import;
+mod integrity;
+mod jobs;
+mod journal;
+mod latency;
+mod listing;
+mod log_files;
+mod log_viewer;
+mod native_notifications;
+mod network;
+mod notifications;
+mod operations;
+mod pending_action;
+mod pending_delete;
+mod permissions;
+mod pidfile;
+mod power;
+mod print;
+mod profiles;
+mod proxy;
+mod quiet_hours;
+mod quit;
+mod read_retry;
+mod remote_copy;
+mod remote_path;
+mod replay;
+mod resources;
+mod resume;
+mod s3;
+mod safe_mode;
+mod scratch;
+mod selection;
+mod session;
+mod settings_sync;
+mod share;
+mod splash;
+mod status_history;
+mod telemetry;
+mod sidecar_config;
+mod sidecar_metrics;
+mod sidecar_update;
+mod store;
+mod suggestions;
+mod test_harness;
+mod thumbnails;
+mod transfer_queue;
+mod transfers;
+mod url_upload;
+mod verify;
+mod version;
+mod view_prefs;
+mod watchdog;
+
+// Sidecar restart policy: exponential backoff from RESTART_BASE_DELAY, capped at
+// RESTART_MAX_DELAY, giving up after MAX_CRASHES crashes within CRASH_WINDOW
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(1);
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(60);
+const MAX_CRASHES: usize = 5;
+const CRASH_WINDOW: Duration = Duration::from_secs(5 * 60);
 
 // Backend status states
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
-enum BackendStatus {
+pub enum BackendStatus {
     Starting,
     Healthy,
     Unhealthy,
+    // Alive but not ready to serve requests, e.g. warming up or B2 unreachable
+    Degraded { reason: String },
     Crashed { error: String },
     Restarting,
+    // Stopped on request; stays down until started again
+    Stopped,
+    // Running, but speaks a different API than this shell expects
+    VersionMismatch { message: String },
+    // Running, but its storage credentials were rejected; transfers wait for new ones
+    AuthRequired { message: String },
 }
 
-// Global state for the sidecar process
-struct AppState {
-    sidecar: Mutex<Option<CommandChild>>,
+// Profile whose sidecar runs with the sidecar's own configuration
+const DEFAULT_PROFILE: &str = "default";
+
+// One sidecar process and its supervision state. Every profile gets its own, so
+// storage accounts stay isolated in separate processes.
+struct Sidecar {
+    profile: String,
+    // Extra environment for the process, e.g. the profile's account credentials
+    env: Vec<(String, String)>,
+    child: Mutex<Option<CommandChild>>,
     port: AtomicU16,
+    // Set instead of the port when the sidecar listens on a Unix socket
+    socket: Mutex<Option<PathBuf>>,
     is_healthy: AtomicBool,
-    shutdown: AtomicBool,
     restart_tx: Mutex<Option<mpsc::Sender<()>>>,
+    // When recent crashes happened, for backoff and crash-loop detection
+    crashes: Mutex<VecDeque<Instant>>,
+    // Identifies the currently spawned process; echoed back on /health
+    instance_id: Mutex<String>,
+    // Base URL, with credentials, of an already-running server used instead of
+    // spawning a process
+    external_url: Mutex<Option<String>>,
+    // Recent stderr and the last crash, for bug reports
+    crash_log: crash_report::CrashLog,
+    // Last status emitted for this sidecar and when (Unix milliseconds), for windows
+    // that missed the event and for waiting until it is healthy
+    status: watch::Sender<Option<(BackendStatus, i64)>>,
 }
 
-impl AppState {
-    fn new() -> Self {
+impl Sidecar {
+    fn new(profile: &str, env: Vec<(String, String)>) -> Self {
         Self {
-            sidecar: Mutex::new(None),
+            profile: profile.to_string(),
+            env,
+            child: Mutex::new(None),
             port: AtomicU16::new(0),
+            socket: Mutex::new(None),
             is_healthy: AtomicBool::new(false),
-            shutdown: AtomicBool::new(false),
             restart_tx: Mutex::new(None),
+            crashes: Mutex::new(VecDeque::new()),
+            instance_id: Mutex::new(String::new()),
+            external_url: Mutex::new(None),
+            crash_log: crash_report::CrashLog::new(),
+            status: watch::channel(None).0,
+        }
+    }
+
+    fn external_url(&self) -> Option<String> {
+        self.external_url.lock().unwrap().clone()
+    }
+
+    // Where requests go: the external server, or the spawned process's socket or port
+    fn base_url(&self) -> String {
+        if let Some(url) = self.external_url() {
+            return url;
+        }
+        match self.socket.lock().unwrap().as_deref() {
+            Some(path) => backend::socket_base_url(&self.instance_id.lock().unwrap(), path),
+            None => backend::local_base_url(self.port.load(Ordering::SeqCst)),
+        }
+    }
+
+    // Whether there is an address to send requests to yet
+    fn is_reachable(&self) -> bool {
+        self.external_url.lock().unwrap().is_some()
+            || self.socket.lock().unwrap().is_some()
+            || self.port.load(Ordering::SeqCst) != 0
+    }
+
+    // Stop listening for requests on the socket, if there is one; a killed sidecar
+    // leaves its file behind
+    fn clear_socket(&self) {
+        if let Some(path) = self.socket.lock().unwrap().take() {
+            backend::forget_socket(&path);
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.child.lock().unwrap().as_ref().map(|child| child.pid())
+    }
+
+    fn is_running(&self) -> bool {
+        self.child.lock().unwrap().is_some()
+    }
+
+    fn request_restart(&self) {
+        if let Some(tx) = self.restart_tx.lock().unwrap().as_ref() {
+            let _ = tx.try_send(());
         }
     }
 }
 
+// Global state for the sidecar processes
+struct AppState {
+    // Sidecars by profile name
+    sidecars: Mutex<HashMap<String, Arc<Sidecar>>>,
+    // Profile the app is working with; its sidecar serves every command
+    active_profile: Mutex<String>,
+    shutdown: AtomicBool,
+    remote_copies: remote_copy::RemoteCopies,
+    url_uploads: url_upload::UrlUploads,
+    downloads: file_download::FileDownloads,
+    imports: import::Imports,
+    accounts: accounts::Accounts,
+    resources: resources::Resources,
+    // Label of the most recently focused webview window
+    focused_window: Mutex<Option<String>>,
+    // Window that opened the most recent frontend context menu
+    context_menu_window: Mutex<Option<String>>,
+    notifications: notifications::Notifications,
+    dock_drop: dock_drop::DockDrop,
+    clock: clock::Clock,
+    listings: listing::ListingCache,
+    operations: operations::Operations,
+    jobs: jobs::Jobs,
+    settings_sync: settings_sync::SettingsSync,
+    transfers: transfers::Transfers,
+    transfer_queue: transfer_queue::TransferQueue,
+    bandwidth: bandwidth::Bandwidth,
+    verifier: verify::Verifier,
+    health_checks: health::HealthChecks,
+    checksums: checksums::Checksums,
+    chunked_uploads: chunked_upload::ChunkedUploads,
+    sessions: session::Sessions,
+    sidecar_metrics: sidecar_metrics::SidecarMetrics,
+    sidecar_config: sidecar_config::SidecarConfig,
+    versions: version::Versions,
+    network: network::Network,
+    sidecar_updates: sidecar_update::SidecarUpdates,
+    quiet_hours: quiet_hours::QuietHours,
+    thumbnails: thumbnails::Thumbnails,
+    view_prefs: view_prefs::ViewPrefsStore,
+    upload_history: suggestions::UploadHistory,
+    permissions: permissions::Permissions,
+    splash: splash::Splash,
+    activity: activity::Activity,
+    auth: auth::Auth,
+    power: power::Power,
+    safe_mode: safe_mode::SafeMode,
+    backend_logs: backend_logs::BackendLogs,
+    status_log: status_history::StatusLog,
+    guests: guest::Guests,
+    backend_metrics: backend_metrics::Metrics,
+    read_breakers: read_retry::ReadBreakers,
+    telemetry: telemetry::Telemetry,
+    crash_reporter: crash_reporter::CrashReporter,
+    pending_resume: resume::PendingResume,
+    pending_actions: pending_action::PendingActions,
+    latency: latency::Latency,
+    access_stats: access_stats::AccessStats,
+    scratch: scratch::Scratch,
+    selections: selection::Selections,
+    event_stream: event_stream::EventStream,
+    replay: replay::Replay,
+    quit: quit::Quit,
+}
+
+impl AppState {
+    fn new() -> Self {
+        let default = Arc::new(Sidecar::new(DEFAULT_PROFILE, Vec::new()));
+        Self {
+            sidecars: Mutex::new(HashMap::from([(DEFAULT_PROFILE.to_string(), default)])),
+            active_profile: Mutex::new(DEFAULT_PROFILE.to_string()),
+            shutdown: AtomicBool::new(false),
+            remote_copies: remote_copy::RemoteCopies::new(),
+            url_uploads: url_upload::UrlUploads::new(),
+            downloads: file_download::FileDownloads::new(),
+            imports: import::Imports::new(),
+            accounts: accounts::Accounts::new(),
+            resources: resources::Resources::new(),
+            focused_window: Mutex::new(None),
+            context_menu_window: Mutex::new(None),
+            notifications: notifications::Notifications::new(),
+            dock_drop: dock_drop::DockDrop::new(),
+            clock: clock::Clock::new(),
+            listings: listing::ListingCache::new(),
+            operations: operations::Operations::new(),
+            jobs: jobs::Jobs::new(),
+            settings_sync: settings_sync::SettingsSync::new(),
+            transfers: transfers::Transfers::new(),
+            transfer_queue: transfer_queue::TransferQueue::new(),
+            bandwidth: bandwidth::Bandwidth::new(),
+            verifier: verify::Verifier::new(),
+            health_checks: health::HealthChecks::new(),
+            checksums: checksums::Checksums::new(),
+            chunked_uploads: chunked_upload::ChunkedUploads::new(),
+            sessions: session::Sessions::new(),
+            sidecar_metrics: sidecar_metrics::SidecarMetrics::new(),
+            sidecar_config: sidecar_config::SidecarConfig::new(),
+            versions: version::Versions::new(),
+            network: network::Network::new(),
+            sidecar_updates: sidecar_update::SidecarUpdates::new(),
+            quiet_hours: quiet_hours::QuietHours::new(),
+            thumbnails: thumbnails::Thumbnails::new(),
+            view_prefs: view_prefs::ViewPrefsStore::new(),
+            upload_history: suggestions::UploadHistory::new(),
+            permissions: permissions::Permissions::new(),
+            splash: splash::Splash::new(),
+            activity: activity::Activity::new(),
+            auth: auth::Auth::new(),
+            power: power::Power::new(),
+            safe_mode: safe_mode::SafeMode::new(),
+            backend_logs: backend_logs::BackendLogs::new(),
+            status_log: status_history::StatusLog::new(),
+            guests: guest::Guests::new(),
+            backend_metrics: backend_metrics::Metrics::new(),
+            read_breakers: read_retry::ReadBreakers::new(),
+            telemetry: telemetry::Telemetry::new(),
+            crash_reporter: crash_reporter::CrashReporter::new(),
+            pending_resume: resume::PendingResume::new(),
+            pending_actions: pending_action::PendingActions::new(),
+            latency: latency::Latency::new(),
+            access_stats: access_stats::AccessStats::new(),
+            scratch: scratch::Scratch::new(),
+            selections: selection::Selections::new(),
+            event_stream: event_stream::EventStream::new(),
+            replay: replay::Replay::new(),
+            quit: quit::Quit::new(),
+        }
+    }
+
+    // The active profile's sidecar
+    fn sidecar(&self) -> Arc<Sidecar> {
+        let active = self.active_profile.lock().unwrap().clone();
+        Arc::clone(&self.sidecars.lock().unwrap()[&active])
+    }
+
+    fn is_active(&self, sidecar: &Sidecar) -> bool {
+        *self.active_profile.lock().unwrap() == sidecar.profile
+    }
+
+    // Port of the active sidecar; 0 while it isn't listening
+    fn port(&self) -> u16 {
+        self.sidecar().port.load(Ordering::SeqCst)
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.sidecar().is_healthy.load(Ordering::SeqCst)
+    }
+
+    // Base URL of the active backend, carrying the credential it expects
+    fn base_url(&self) -> String {
+        self.sidecar().base_url()
+    }
+}
+
 #[tauri::command]
 fn get_api_port(state: tauri::State<Arc<AppState>>) -> u16 {
-    state.port.load(Ordering::SeqCst)
+    state.port()
+}
+
+// Where the active profile's backend stands, as the last `backend-status` said, with
+// the process details behind it
+#[derive(Clone, serde::Serialize)]
+struct BackendStatusInfo {
+    profile: String,
+    status: BackendStatus,
+    // Unix milliseconds of the last status change; None before the first one
+    since: Option<i64>,
+    healthy: bool,
+    // 0 while not listening, and with the socket transport
+    port: u16,
+    socket: Option<String>,
+    pid: Option<u32>,
+    // How long the spawned process has been running; None when none is
+    uptime_secs: Option<u64>,
+    // Whether an already-running server is used instead of a spawned one
+    external: bool,
 }
 
+// Current backend status for windows opened or reloaded after the `backend-status`
+// events they would have needed
 #[tauri::command]
+fn get_backend_status(state: tauri::State<Arc<AppState>>) -> BackendStatusInfo {
+    let sidecar = state.sidecar();
+    let last = sidecar.status.borrow().clone();
+    let (status, since) = match last {
+        Some((status, at)) => (status, Some(at)),
+        None => (BackendStatus::Starting, None),
+    };
+    let socket = sidecar.socket.lock().unwrap().as_ref().map(|path| path.display().to_string());
+    BackendStatusInfo {
+        profile: sidecar.profile.clone(),
+        status,
+        since,
+        healthy: sidecar.is_healthy.load(Ordering::SeqCst),
+        port: sidecar.port.load(Ordering::SeqCst),
+        socket,
+        pid: sidecar.pid(),
+        uptime_secs: sidecar
+            .crash_log
+            .uptime()
+            .filter(|_| sidecar.is_running())
+            .map(|uptime| uptime.as_secs()),
+        external: sidecar.external_url().is_some(),
+    }
+}
+
+// Resolve as soon as the active profile's backend is healthy, right away if it already
+// is, or fail with a timeout error after `timeout_ms`
+#[tauri::command]
+async fn wait_for_healthy(state: tauri::State<'_, Arc<AppState>>, timeout_ms: u64) -> Result<(), AppError> {
+    let sidecar = state.sidecar();
+    let mut status = sidecar.status.subscribe();
+    let healthy = status.wait_for(|status| matches!(status, Some((BackendStatus::Healthy, _))));
+    if let Ok(Ok(_)) = tokio::time::timeout(Duration::from_millis(timeout_ms), healthy).await {
+        return Ok(());
+    }
+    let status = sidecar.status.borrow().as_ref().map(|(status, _)| status.clone());
+    Err(AppError::Timeout { timeout_ms, status })
+}
+
+// Base URL the webview reaches the active backend at, without credentials; None while
+// the sidecar isn't listening, or when it listens on a socket the webview can't open
+fn webview_base_url(state: &AppState) -> Option<String> {
+    let sidecar = state.sidecar();
+    match sidecar.external_url() {
+        Some(url) => Some(external::display_url(&url)),
+        None => {
+            let port = sidecar.port.load(Ordering::SeqCst);
+            (port != 0).then(|| format!("http://localhost:{}", port))
+        }
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(name = "sidecar.restart_requested", skip_all)]
 fn restart_backend(state: tauri::State<Arc<AppState>>) {
-    if let Some(tx) = state.restart_tx.lock().unwrap().as_ref() {
-        let _ = tx.try_send(());
+    state.sidecar().request_restart();
+}
+
+// Start the sidecar after `stop_backend`; a no-op while it is already running
+#[tauri::command]
+fn start_backend(app: AppHandle, state: tauri::State<Arc<AppState>>) -> Result<(), AppError> {
+    start_if_stopped(&app, &state, &state.sidecar())
+}
+
+// Start a sidecar unless it is already running, forgetting its earlier crashes
+fn start_if_stopped(app: &AppHandle, state: &Arc<AppState>, sidecar: &Arc<Sidecar>) -> Result<(), AppError> {
+    if sidecar.is_running() {
+        return Ok(());
     }
+    sidecar.crashes.lock().unwrap().clear();
+    if let Err(e) = start_sidecar_sync(app, state, sidecar) {
+        log::error!("Failed to start sidecar: {}", e);
+        emit_backend_status(app, sidecar, BackendStatus::Crashed { error: e.to_string() });
+        return Err(e);
+    }
+    Ok(())
+}
+
+// Stop the sidecar without quitting, e.g. to free memory or before switching
+// profiles. It is not restarted until `start_backend` or `restart_backend`.
+#[tauri::command]
+#[tracing::instrument(name = "sidecar.stop", skip_all)]
+fn stop_backend(app: AppHandle, state: tauri::State<Arc<AppState>>) {
+    let sidecar = state.sidecar();
+    log::info!("Stopping BB Stream sidecar for profile {} on request", sidecar.profile);
+    kill_sidecar(&sidecar);
+    sidecar.is_healthy.store(false, Ordering::SeqCst);
+    sidecar.port.store(0, Ordering::SeqCst);
+    pidfile::clear(&app, &sidecar.profile);
+    emit_backend_status(&app, &sidecar, BackendStatus::Stopped);
+    // Tokens issued so far shouldn't outlive the paused session
+    tauri::async_runtime::spawn(async move { session::revoke(&app).await });
 }
 
-// Find an available port
-fn find_available_port() -> Option<u16> {
-    // Try the default port first
-    if portpicker::is_free(8765) {
-        return Some(8765);
+// Forget past crashes and try the sidecar again, e.g. after a crash loop gave up
+#[tauri::command]
+fn reset_backend_restarts(state: tauri::State<Arc<AppState>>) {
+    let sidecar = state.sidecar();
+    sidecar.crashes.lock().unwrap().clear();
+    sidecar.request_restart();
+}
+
+// Record a crash and decide how long to wait before restarting, or None when the
+// sidecar keeps crashing and should be left down
+fn next_restart_delay(sidecar: &Sidecar) -> Option<Duration> {
+    let mut crashes = sidecar.crashes.lock().unwrap();
+    let now = Instant::now();
+    while crashes.front().is_some_and(|at| now.duration_since(*at) > CRASH_WINDOW) {
+        crashes.pop_front();
     }
-    // Otherwise pick a random available port
-    portpicker::pick_unused_port()
+    crashes.push_back(now);
+    if crashes.len() > MAX_CRASHES {
+        return None;
+    }
+
+    let delay = RESTART_BASE_DELAY
+        .saturating_mul(1 << (crashes.len() - 1))
+        .min(RESTART_MAX_DELAY);
+    // Up to 50% jitter so several instances don't restart in lockstep
+    let jitter = uuid::Uuid::new_v4().as_u128() as u64 % (delay.as_millis() as u64 / 2 + 1);
+    Some(delay + Duration::from_millis(jitter))
 }
 
 // Start the sidecar process - must be called from sync context
-fn start_sidecar_sync(app: &AppHandle, state: &Arc<AppState>) -> Result<(), String> {
-    // Find an available port
-    let port = find_available_port().ok_or("No available ports")?;
-    state.port.store(port, Ordering::SeqCst);
+#[tracing::instrument(name = "sidecar.start", skip_all, fields(profile = %sidecar.profile), err)]
+fn start_sidecar_sync(app: &AppHandle, state: &Arc<AppState>, sidecar: &Arc<Sidecar>) -> Result<(), AppError> {
+    // The sidecar binds a free port or a socket itself and reports it on stdout; until
+    // then where it listens is unknown
+    sidecar.port.store(0, Ordering::SeqCst);
+    sidecar.clear_socket();
 
-    log::info!("Starting BB Stream sidecar on port {}", port);
+    log::info!("Starting BB Stream sidecar for profile {}", sidecar.profile);
+    // Whatever was rejected before, the new process is judged afresh
+    auth::clear(state, &sidecar.profile);
 
     // Emit starting status
-    emit_backend_status(app, BackendStatus::Starting);
+    emit_backend_status(app, sidecar, BackendStatus::Starting);
+
+    if let Some(url) = sidecar.external_url() {
+        connect_external(app, state, sidecar, &url);
+        return Ok(());
+    }
 
     let shell = app.shell();
-    let sidecar_command = shell
-        .sidecar("bb-stream")
-        .map_err(|e| format!("Failed to create sidecar command: {}", e))?;
+    let sidecar_command = match sidecar_update::staged_binary(app, state) {
+        Some(path) => {
+            log::info!("Running updated sidecar {}", path.display());
+            shell.command(path)
+        }
+        None => {
+            // Never execute a sidecar that was replaced or truncated since the build
+            if let Err(e) = integrity::verify_sidecar() {
+                log::error!("Sidecar integrity check failed: {}", e);
+                return Err(AppError::SidecarSpawnFailed {
+                    source: format!("integrity check failed: {}", e),
+                });
+            }
+            shell.sidecar("bb-stream").map_err(|e| AppError::SidecarSpawnFailed {
+                source: format!("Failed to create sidecar command: {}", e),
+            })?
+        }
+    };
 
+    // Fresh id per spawn; the health checker only trusts responses carrying it
+    let instance_id = uuid::Uuid::new_v4().to_string();
+    *sidecar.instance_id.lock().unwrap() = instance_id.clone();
+    state.versions.reset();
+    state.sidecar_config.started(&sidecar.profile);
+
+    let settings = state.sidecar_config.settings();
+    let socket = (settings.transport == sidecar_config::Transport::Socket)
+        .then(|| sidecar_config::socket_path(&instance_id));
+    let port = match socket {
+        Some(_) => 0,
+        None => port_for(app, sidecar, &settings)?,
+    };
     let (rx, child) = sidecar_command
-        .args(["serve", "--port", &port.to_string()])
+        .args(sidecar_config::args(socket.as_deref(), port))
+        .envs(sidecar_config::command_env(state, sidecar, &instance_id))
+        // Own process group (Job Object on Windows), so killing it also stops any
+        // helpers it started
+        .set_process_group(true)
         .spawn()
-        .map_err(|e| format!("Failed to spawn sidecar: {}", e))?;
+        .map_err(|e| AppError::SidecarSpawnFailed { source: e.to_string() })?;
+
+    pidfile::record(app, &sidecar.profile, child.pid());
+    sidecar.crash_log.started();
 
     // Store the child process
     {
-        let mut guard = state.sidecar.lock().unwrap();
+        let mut guard = sidecar.child.lock().unwrap();
         *guard = Some(child);
     }
 
     // Spawn output handler
     let app_handle = app.clone();
     let state_clone = Arc::clone(state);
-    spawn_output_handler(app_handle, state_clone, rx);
-
-    // Spawn health check loop
-    let app_handle = app.clone();
-    let state_clone = Arc::clone(state);
-    spawn_health_checker(app_handle, state_clone);
+    spawn_output_handler(app_handle, state_clone, Arc::clone(sidecar), rx, instance_id);
 
     Ok(())
 }
 
+// Sent as `backend-port-fallback` when the preferred port was taken
+#[derive(Clone, serde::Serialize)]
+struct PortFallback {
+    profile: String,
+    preferred_port: u16,
+    port: u16,
+}
+
+// Port to spawn a sidecar on, warning the user when their preferred one was taken.
+// Fails when one was asked for and not even the OS could find another.
+fn port_for(app: &AppHandle, sidecar: &Sidecar, settings: &sidecar_config::SidecarSettings) -> Result<u16, AppError> {
+    let choice = sidecar_config::choose_port(settings);
+    let requested = settings.preferred_port.or(settings.port_range.map(|range| range.start));
+    if let (0, Some(port)) = (choice.port, requested) {
+        return Err(AppError::PortUnavailable { port });
+    }
+    if let Some(preferred_port) = choice.preferred_taken {
+        log::warn!(
+            "Port {} is taken; the sidecar for profile {} uses port {} instead",
+            preferred_port,
+            sidecar.profile,
+            choice.port
+        );
+        let fallback = PortFallback {
+            profile: sidecar.profile.clone(),
+            preferred_port,
+            port: choice.port,
+        };
+        emit_to_main(app, "backend-port-fallback", fallback);
+    }
+    Ok(choice.port)
+}
+
+// Watch an already-running server instead of spawning one. Only the health checker
+// runs; there is no process to restart.
+fn connect_external(app: &AppHandle, state: &Arc<AppState>, sidecar: &Arc<Sidecar>, url: &str) {
+    log::info!("Using external BB Stream server {}", external::display_url(url));
+    // Identifies this connection to its health checker, which stands down when the
+    // id changes; an external server doesn't echo it
+    let instance_id = uuid::Uuid::new_v4().to_string();
+    *sidecar.instance_id.lock().unwrap() = instance_id.clone();
+    sidecar.is_healthy.store(false, Ordering::SeqCst);
+    state.versions.reset();
+    spawn_health_checker(app.clone(), Arc::clone(state), Arc::clone(sidecar), instance_id);
+}
+
+// Where a spawned sidecar listens
+enum Listening {
+    Port(u16),
+    Socket(PathBuf),
+}
+
+// From the sidecar's "READY port=<n>" or "READY socket=<path>" line, printed once it
+// is listening
+fn parse_ready_line(line: &str) -> Option<Listening> {
+    let line = line.trim().strip_prefix("READY ")?;
+    if let Some(path) = line.strip_prefix("socket=") {
+        return Some(Listening::Socket(PathBuf::from(path)));
+    }
+    line.strip_prefix("port=")?.parse().ok().map(Listening::Port)
+}
+
 // Spawn the output handler task
 fn spawn_output_handler(
     app_handle: AppHandle,
     state: Arc<AppState>,
-    mut rx: tauri_plugin_shell::process::CommandEvents,
+    sidecar: Arc<Sidecar>,
+    mut rx: tauri::async_runtime::Receiver<CommandEvent>,
+    instance_id: String,
 ) {
     tauri::async_runtime::spawn(async move {
         while let Some(event) = rx.recv().await {
             match event {
                 CommandEvent::Stdout(line) => {
                     let msg = String::from_utf8_lossy(&line);
-                    log::info!("[bb-stream] {}", msg);
+                    backend_logs::record(&app_handle, &state, &sidecar.profile, backend_logs::Stream::Stdout, &msg);
+
+                    // Health checks start once the sidecar says where it listens
+                    if let Some(listening) = parse_ready_line(&msg) {
+                        match listening {
+                            Listening::Port(port) => {
+                                log::info!(
+                                    "BB Stream sidecar for profile {} listening on port {}",
+                                    sidecar.profile,
+                                    port
+                                );
+                                sidecar.port.store(port, Ordering::SeqCst);
+                            }
+                            Listening::Socket(path) => {
+                                log::info!(
+                                    "BB Stream sidecar for profile {} listening on {}",
+                                    sidecar.profile,
+                                    path.display()
+                                );
+                                *sidecar.socket.lock().unwrap() = Some(path);
+                            }
+                        }
+                        spawn_health_checker(
+                            app_handle.clone(),
+                            Arc::clone(&state),
+                            Arc::clone(&sidecar),
+                            instance_id.clone(),
+                        );
+                        watchdog::spawn(
+                            app_handle.clone(),
+                            Arc::clone(&state),
+                            Arc::clone(&sidecar),
+                            instance_id.clone(),
+                        );
+                    }
                 }
                 CommandEvent::Stderr(line) => {
                     let msg = String::from_utf8_lossy(&line);
-                    log::warn!("[bb-stream] {}", msg);
+                    sidecar.crash_log.record_stderr(&msg);
+                    backend_logs::record(&app_handle, &state, &sidecar.profile, backend_logs::Stream::Stderr, &msg);
                 }
                 CommandEvent::Error(err) => {
-                    log::error!("[bb-stream] Error: {}", err);
+                    log::error!("[bb-stream:{}] Error: {}", sidecar.profile, err);
                 }
                 CommandEvent::Terminated(status) => {
-                    log::info!("[bb-stream] Terminated with status: {:?}", status);
-                    state.is_healthy.store(false, Ordering::SeqCst);
+                    log::info!("[bb-stream:{}] Terminated with status: {:?}", sidecar.profile, status);
+
+                    // A sidecar killed to stop or restart it was replaced on purpose
+                    if *sidecar.instance_id.lock().unwrap() != instance_id {
+                        break;
+                    }
+                    sidecar.is_healthy.store(false, Ordering::SeqCst);
+                    *sidecar.child.lock().unwrap() = None;
 
                     // If not shutting down, report crash and request restart
                     if !state.shutdown.load(Ordering::SeqCst) {
-                        let error = format!("Process exited with status: {:?}", status);
-                        emit_backend_status(&app_handle, BackendStatus::Crashed { error });
+                        let report = crash_report::report(&app_handle, &sidecar, &status);
+                        crash_reporter::record_backend(&app_handle, &state, &report);
+                        let error = report.summary();
+                        let Some(delay) = next_restart_delay(&sidecar) else {
+                            log::error!("[bb-stream:{}] Crash loop detected; not restarting", sidecar.profile);
+                            let error = format!(
+                                "The backend crashed {} times within {} minutes and was not restarted. Last error: {}",
+                                MAX_CRASHES + 1,
+                                CRASH_WINDOW.as_secs() / 60,
+                                error
+                            );
+                            emit_backend_status(&app_handle, &sidecar, BackendStatus::Crashed { error });
+                            break;
+                        };
+                        emit_backend_status(&app_handle, &sidecar, BackendStatus::Crashed { error });
 
                         // Request restart via channel
-                        tokio::time::sleep(Duration::from_secs(2)).await;
-                        if !state.shutdown.load(Ordering::SeqCst) {
-                            if let Some(tx) = state.restart_tx.lock().unwrap().as_ref() {
-                                let _ = tx.try_send(());
-                            }
+                        log::info!("[bb-stream:{}] Restarting in {:?}", sidecar.profile, delay);
+                        tokio::time::sleep(delay).await;
+                        // Unless the backend was stopped or started by hand meanwhile
+                        let replaced = *sidecar.instance_id.lock().unwrap() != instance_id;
+                        if !state.shutdown.load(Ordering::SeqCst) && !replaced {
+                            sidecar.request_restart();
                         }
                     }
                     break;
@@ -146,79 +760,300 @@ fn spawn_output_handler(
     });
 }
 
-// Spawn the health checker task
-fn spawn_health_checker(app_handle: AppHandle, state: Arc<AppState>) {
+// Spawn the health checker task for the sidecar spawned as `instance_id`
+fn spawn_health_checker(app_handle: AppHandle, state: Arc<AppState>, sidecar: Arc<Sidecar>, instance_id: String) {
     tauri::async_runtime::spawn(async move {
         let mut consecutive_failures = 0;
-
-        // Wait for initial startup
-        tokio::time::sleep(Duration::from_millis(500)).await;
+        // Why the backend was last reported degraded, to report each new reason once
+        let mut degraded_reason: Option<String> = None;
+        // Whether this sidecar's API version has been checked
+        let mut handshake_done = false;
+        // Only a spawned sidecar knows the instance id to echo back
+        let external = sidecar.external_url().is_some();
 
         loop {
-            if state.shutdown.load(Ordering::SeqCst) {
+            // Stop on shutdown, or once a restart has replaced this sidecar
+            if state.shutdown.load(Ordering::SeqCst) || *sidecar.instance_id.lock().unwrap() != instance_id {
                 break;
             }
 
-            let port = state.port.load(Ordering::SeqCst);
-            let health_url = format!("http://localhost:{}/health", port);
+            let settings = state.health_checks.settings();
+            // The test harness owns this sidecar's health for now, and nothing can be
+            // checked while the system sleeps
+            if test_harness::forced_health(&sidecar.profile).is_some() || state.power.is_asleep() {
+                tokio::time::sleep(settings.interval()).await;
+                continue;
+            }
+            let base_url = sidecar.base_url();
+            let health_url = format!("{}/health", base_url);
+            let ready_url = format!("{}/ready", base_url);
+            let expected_instance = (!external).then_some(instance_id.as_str());
 
-            match check_health(&health_url).await {
-                Ok(()) => {
+            let client = state.health_checks.client(&base_url);
+            let sent = SystemTime::now();
+            let started = Instant::now();
+            let span = tracing::info_span!("sidecar.health_check", profile = %sidecar.profile);
+            let health = async {
+                health::port_accepts(&base_url, settings.timeout()).await?;
+                check_health(&client, &health_url, expected_instance, settings.timeout()).await
+            }
+            .instrument(span.clone())
+            .await;
+            match health {
+                Ok(server_date) => {
+                    latency::record(&app_handle, &sidecar.profile, latency::Source::Health, started.elapsed());
+                    if let Some(date) = server_date {
+                        clock::record(&app_handle, &state, &date, sent, started.elapsed());
+                    }
                     consecutive_failures = 0;
-                    if !state.is_healthy.swap(true, Ordering::SeqCst) {
-                        // Transitioned from unhealthy to healthy
-                        emit_backend_status(&app_handle, BackendStatus::Healthy);
+
+                    if !handshake_done {
+                        match version::handshake(&state, &base_url, settings.timeout()).await {
+                            Ok(_) => {
+                                handshake_done = true;
+                                if state.is_active(&sidecar) {
+                                    emit_to_main(&app_handle, "backend-capabilities", state.versions.capabilities());
+                                }
+                            }
+                            Err(e) => log::warn!("Version handshake failed: {}", e),
+                        }
                     }
+
+                    // Alive; whether it can serve requests yet is a separate check
+                    let ready = check_ready(&client, &ready_url, expected_instance, settings.timeout());
+                    match ready.instrument(span).await {
+                        // Stays down until the user signs in again, even while B2 answers
+                        Ok(()) if state.auth.is_required(&sidecar.profile) => {}
+                        Ok(()) => {
+                            degraded_reason = None;
+                            if !sidecar.is_healthy.swap(true, Ordering::SeqCst) {
+                                // Transitioned from unhealthy or degraded to healthy, unless
+                                // the sidecar can't be used safely anyway
+                                let status = match state.versions.mismatch() {
+                                    Some(mismatch) => BackendStatus::VersionMismatch { message: mismatch.message() },
+                                    None => BackendStatus::Healthy,
+                                };
+                                safe_mode::launch_succeeded(&app_handle, &state);
+                                emit_backend_status(&app_handle, &sidecar, status);
+                            }
+                        }
+                        Err(NotReady { reason, auth_required: true }) => {
+                            degraded_reason = None;
+                            auth::require(&app_handle, &state, &sidecar, reason);
+                        }
+                        Err(NotReady { reason, .. }) => {
+                            let was_healthy = sidecar.is_healthy.swap(false, Ordering::SeqCst);
+                            if was_healthy || degraded_reason.as_ref() != Some(&reason) {
+                                log::warn!("Backend is not ready: {}", reason);
+                                degraded_reason = Some(reason.clone());
+                                emit_backend_status(&app_handle, &sidecar, BackendStatus::Degraded { reason });
+                            }
+                        }
+                    }
+                }
+                // Connections dropped by sleep take a moment to come back
+                Err(e) if state.power.is_settling() => {
+                    log::info!("Health check failed just after waking; not counted: {}", e);
                 }
                 Err(e) => {
                     consecutive_failures += 1;
                     log::warn!("Health check failed ({}): {}", consecutive_failures, e);
 
-                    if consecutive_failures >= 3 {
-                        state.is_healthy.store(false, Ordering::SeqCst);
-                        emit_backend_status(&app_handle, BackendStatus::Unhealthy);
+                    if consecutive_failures >= settings.failure_threshold {
+                        degraded_reason = None;
+                        sidecar.is_healthy.store(false, Ordering::SeqCst);
+                        emit_backend_status(&app_handle, &sidecar, BackendStatus::Unhealthy);
                     }
                 }
             }
 
-            tokio::time::sleep(Duration::from_secs(5)).await;
+            activity::sleep(&app_handle, &state, settings.interval()).await;
         }
     });
 }
 
-// Check health endpoint
-async fn check_health(url: &str) -> Result<(), String> {
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(2))
-        .build()
-        .map_err(|e| e.to_string())?;
+// Check health endpoint, returning the server's Date header for clock-skew tracking.
+// The sidecar echoes the instance id it was spawned with, so a different process that
+// grabbed the port after a crash is never mistaken for our backend. External servers
+// aren't checked for it.
+#[tracing::instrument(name = "sidecar.health", skip_all, err)]
+async fn check_health(
+    client: &reqwest::Client,
+    url: &str,
+    instance_id: Option<&str>,
+    timeout: Duration,
+) -> Result<Option<String>, String> {
+    let resp = client.get(url).timeout(timeout).send().await.map_err(|e| e.to_string())?;
 
-    let resp = client.get(url).send().await.map_err(|e| e.to_string())?;
+    check_instance(&resp, instance_id)?;
 
     if resp.status().is_success() {
-        Ok(())
+        Ok(resp
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string))
     } else {
         Err(format!("Health check returned status: {}", resp.status()))
     }
 }
 
-// Kill existing sidecar process
-fn kill_sidecar(state: &Arc<AppState>) {
-    let mut guard = state.sidecar.lock().unwrap();
-    if let Some(child) = guard.take() {
-        let _ = child.kill();
+// Check the readiness endpoint, returning why the backend isn't ready when it isn't
+#[tracing::instrument(name = "sidecar.ready", skip_all)]
+async fn check_ready(
+    client: &reqwest::Client,
+    url: &str,
+    instance_id: Option<&str>,
+    timeout: Duration,
+) -> Result<(), NotReady> {
+    let resp = client.get(url).timeout(timeout).send().await.map_err(|e| NotReady::from(e.to_string()))?;
+    // A sidecar without readiness reporting is ready once it is alive
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(());
     }
+    check_instance(&resp, instance_id)?;
+
+    match resp.status() {
+        status if status.is_success() => Ok(()),
+        status => {
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            Err(NotReady {
+                reason: body["reason"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("Readiness check returned status: {}", status)),
+                auth_required: body["auth_required"].as_bool().unwrap_or(false),
+            })
+        }
+    }
+}
+
+// Why a live sidecar can't serve requests yet
+struct NotReady {
+    reason: String,
+    // B2 rejected its credentials, so waiting won't help
+    auth_required: bool,
 }
 
-// Emit backend status to frontend
-fn emit_backend_status(app: &AppHandle, status: BackendStatus) {
+impl From<String> for NotReady {
+    fn from(reason: String) -> Self {
+        Self { reason, auth_required: false }
+    }
+}
+
+// Whether the response came from the sidecar spawned as `instance_id`, if one is expected
+fn check_instance(resp: &reqwest::Response, instance_id: Option<&str>) -> Result<(), String> {
+    let Some(instance_id) = instance_id else {
+        return Ok(());
+    };
+    let answered_by = resp
+        .headers()
+        .get("X-BB-Instance")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if answered_by != instance_id {
+        return Err(format!("Port is held by another process (instance {:?})", answered_by));
+    }
+    Ok(())
+}
+
+// Kill existing sidecar process along with everything in its process group; its
+// health checker and exit handler stand down
+#[tracing::instrument(name = "sidecar.kill", skip_all, fields(profile = %sidecar.profile))]
+fn kill_sidecar(sidecar: &Sidecar) {
+    sidecar.instance_id.lock().unwrap().clear();
+    let child = sidecar.child.lock().unwrap().take();
+    if let Some(child) = child {
+        let pid = child.pid();
+        if let Err(e) = child.kill() {
+            log::error!("Failed to kill sidecar (pid {}) for profile {}: {}", pid, sidecar.profile, e);
+        }
+    }
+    sidecar.clear_socket();
+}
+
+// Kill every profile's sidecar for good, as the app quits
+fn stop_sidecars(app: &AppHandle) {
+    let state: tauri::State<Arc<AppState>> = app.state();
+    // Closing the main window and the exit that follows both get here
+    if state.shutdown.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    power::stop(&state);
+    for sidecar in state.sidecars.lock().unwrap().values() {
+        kill_sidecar(sidecar);
+        pidfile::clear(app, &sidecar.profile);
+        status_history::record(app, &state, &sidecar.profile, &BackendStatus::Stopped);
+    }
+    log::info!("BB Stream sidecars stopped");
+    telemetry::shutdown(&state);
+    scratch::clean_up(app, &state);
+}
+
+// Emit a sidecar's status as `backend-status-{profile}`, and as `backend-status`
+// when it belongs to the active profile
+fn emit_backend_status(app: &AppHandle, sidecar: &Sidecar, status: BackendStatus) {
+    sidecar.status.send_replace(Some((status.clone(), chrono::Utc::now().timestamp_millis())));
+    emit_to_main(app, &format!("backend-status-{}", profiles::slug(&sidecar.profile)), status.clone());
+    let state: tauri::State<Arc<AppState>> = app.state();
+    status_history::record(app, &state, &sidecar.profile, &status);
+    if state.is_active(sidecar) {
+        emit_to_main(app, "backend-status", status);
+    }
+}
+
+// Emit an event to the main window, kept for replay to windows that subscribe later
+// when it carries state
+fn emit_to_main<S: serde::Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Some(state) = app.try_state::<Arc<AppState>>() {
+        replay::record(&state, event, &payload);
+    }
     if let Some(window) = app.get_webview_window("main") {
-        let _ = window.emit("backend-status", status);
+        let _ = window.emit(event, payload);
     }
 }
 
-// Spawn the restart handler loop
-fn spawn_restart_handler(app: AppHandle, state: Arc<AppState>, mut rx: mpsc::Receiver<()>) {
+// Payload for menu events; `window` is the label of the window the action targets
+#[derive(Clone, serde::Serialize)]
+struct MenuEventPayload {
+    window: String,
+}
+
+// Frontend event for menu items handled by the webview
+fn menu_event_name(id: &str) -> Option<&'static str> {
+    match id {
+        "upload" => Some("menu-upload"),
+        "download" => Some("menu-download"),
+        "delete" => Some("menu-delete"),
+        "refresh" => Some("menu-refresh"),
+        "copy_url" => Some("menu-copy-url"),
+        "copy_sha256" => Some("menu-copy-sha256"),
+        "toggle_sidebar" => Some("menu-toggle-sidebar"),
+        "preferences" => Some("menu-preferences"),
+        "print" => Some("menu-print"),
+        _ => None,
+    }
+}
+
+// Send a menu event to the focused window, falling back to main
+fn route_menu_event(app: &AppHandle, event: &str) {
+    let state: tauri::State<Arc<AppState>> = app.state();
+    let label = state
+        .focused_window
+        .lock()
+        .unwrap()
+        .clone()
+        .filter(|label| app.get_webview_window(label).is_some())
+        .unwrap_or_else(|| "main".to_string());
+
+    let _ = app.emit_to(label.as_str(), event, MenuEventPayload { window: label.clone() });
+}
+
+// Give a sidecar its restart channel and spawn the handler loop serving it
+fn spawn_restart_handler(app: AppHandle, state: Arc<AppState>, sidecar: Arc<Sidecar>) {
+    let (restart_tx, mut rx) = mpsc::channel::<()>(1);
+    *sidecar.restart_tx.lock().unwrap() = Some(restart_tx);
+
     std::thread::spawn(move || {
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
@@ -230,21 +1065,45 @@ fn spawn_restart_handler(app: AppHandle, state: Arc<AppState>, mut rx: mpsc::Rec
                 if state.shutdown.load(Ordering::SeqCst) {
                     break;
                 }
+                if sidecar.external_url().is_some() {
+                    log::info!("Not restarting profile {}; it uses an external server", sidecar.profile);
+                    continue;
+                }
+                // A restart asked for as the system went to sleep waits until it wakes
+                while state.power.is_asleep() && !state.shutdown.load(Ordering::SeqCst) {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+
+                let span = tracing::info_span!("sidecar.restart", profile = %sidecar.profile);
+                async {
+                    log::info!("Restarting BB Stream sidecar for profile {}...", sidecar.profile);
+                    emit_backend_status(&app, &sidecar, BackendStatus::Restarting);
 
-                log::info!("Restarting BB Stream sidecar...");
-                emit_backend_status(&app, BackendStatus::Restarting);
+                    // Checkpoint running jobs so the new process can carry on with them
+                    let checkpoint = drain::drain(&app, &state, &sidecar).await;
 
-                // Kill existing process
-                kill_sidecar(&state);
+                    // Kill existing process; the new one reports healthy once it is
+                    kill_sidecar(&sidecar);
+                    sidecar.is_healthy.store(false, Ordering::SeqCst);
 
-                // Wait a bit before restarting
-                tokio::time::sleep(Duration::from_millis(500)).await;
+                    // Wait a bit before restarting
+                    tokio::time::sleep(Duration::from_millis(500)).await;
 
-                // Start new process
-                if let Err(e) = start_sidecar_sync(&app, &state) {
-                    log::error!("Failed to restart sidecar: {}", e);
-                    emit_backend_status(&app, BackendStatus::Crashed { error: e });
+                    // Start new process
+                    match start_sidecar_sync(&app, &state, &sidecar) {
+                        Ok(()) => {
+                            if let Some(checkpoint) = checkpoint {
+                                drain::spawn_resume(app.clone(), Arc::clone(&state), Arc::clone(&sidecar), checkpoint);
+                            }
+                        }
+                        Err(e) => {
+                            log::error!("Failed to restart sidecar: {}", e);
+                            emit_backend_status(&app, &sidecar, BackendStatus::Crashed { error: e.to_string() });
+                        }
+                    }
                 }
+                .instrument(span)
+                .await;
             }
         });
     });
@@ -255,17 +1114,177 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .manage(Arc::new(AppState::new()))
-        .invoke_handler(tauri::generate_handler![get_api_port, restart_backend])
+        // Guest windows only get the guest commands
+        .invoke_handler(guest::guarded(tauri::generate_handler![
+            get_api_port,
+            get_backend_status,
+            wait_for_healthy,
+            restart_backend,
+            start_backend,
+            health::get_health_checks,
+            backend_logs::get_backend_logs,
+            backend_logs::copy_backend_logs,
+            log_viewer::show_backend_logs,
+            log_files::open_log_directory,
+            log_files::get_log_level,
+            log_files::set_log_level,
+            safe_mode::get_safe_mode,
+            safe_mode::leave_safe_mode,
+            status_history::get_status_history,
+            auth::retry_auth,
+            health::configure_health_checks,
+            latency::get_health_latency,
+            stop_backend,
+            reset_backend_restarts,
+            remote_copy::create_remote_copy,
+            remote_copy::list_remote_copies,
+            remote_copy::cancel_remote_copy,
+            remote_copy::resume_remote_copy,
+            url_upload::preflight_url,
+            url_upload::upload_from_url,
+            url_upload::list_url_uploads,
+            url_upload::cancel_url_upload,
+            url_upload::resume_url_upload,
+            file_upload::upload_file,
+            file_download::download_file,
+            file_download::list_downloads,
+            file_download::pause_transfer,
+            file_download::resume_transfer,
+            transfer_queue::enqueue_transfer,
+            transfer_queue::list_transfers,
+            transfer_queue::cancel_transfer,
+            transfer_queue::reorder_transfer,
+            transfer_queue::get_transfer_limits,
+            transfer_queue::set_transfer_limits,
+            bandwidth::get_bandwidth_limit,
+            bandwidth::set_bandwidth_limit,
+            bandwidth::set_transfer_bandwidth_limit,
+            guest::open_guest_window,
+            guest::get_guest_scope,
+            guest::list_guest_files,
+            guest::read_guest_file,
+            backend_metrics::get_backend_metrics,
+            import::estimate_import,
+            import::start_import,
+            import::list_imports,
+            import::cancel_import,
+            import::resume_import,
+            accounts::list_accounts,
+            accounts::import_rclone_config,
+            accounts::set_account_secret,
+            resources::get_resource_pressure,
+            sidecar_metrics::get_backend_resources,
+            proxy::backend_request,
+            proxy::api_request,
+            read_retry::get_read_status,
+            telemetry::get_telemetry_settings,
+            telemetry::configure_telemetry,
+            crash_report::get_backend_crash_report,
+            crash_reporter::list_crash_reports,
+            crash_reporter::get_crash_report,
+            crash_reporter::delete_crash_report,
+            crash_reporter::submit_crash_report,
+            crash_reporter::get_crash_reporter_settings,
+            crash_reporter::configure_crash_reporter,
+            diagnostics::export_diagnostics,
+            diagnostics::get_app_info,
+            quit::get_active_transfers,
+            resume::get_pending_resume,
+            resume::resume_all,
+            resume::discard_pending,
+            version::get_version_mismatch,
+            version::get_backend_capabilities,
+            sidecar_config::get_sidecar_config,
+            sidecar_config::set_sidecar_config,
+            sidecar_config::get_effective_sidecar_config,
+            sidecar_config::get_pending_sidecar_changes,
+            sidecar_config::schedule_sidecar_restart,
+            thumbnails::warm_thumbnails,
+            thumbnails::get_thumbnail,
+            thumbnails::get_thumbnail_settings,
+            thumbnails::set_thumbnail_settings,
+            access_stats::get_access_heatmap,
+            access_stats::get_access_stats_settings,
+            access_stats::set_access_stats_settings,
+            scratch::get_scratch_settings,
+            scratch::set_scratch_settings,
+            scratch::get_scratch_usage,
+            view_prefs::get_view_prefs,
+            view_prefs::set_view_prefs,
+            suggestions::suggest_destination,
+            suggestions::record_upload_destination,
+            permissions::list_permissions,
+            permissions::revoke_permission,
+            test_harness::harness_enabled,
+            test_harness::harness_set_time,
+            test_harness::harness_advance_time,
+            test_harness::harness_force_health,
+            test_harness::harness_inject_transfer,
+            network::get_wifi_only,
+            network::configure_wifi_only,
+            network::allow_metered_transfer,
+            profiles::list_profiles,
+            profiles::start_profile,
+            profiles::switch_profile,
+            external::get_external_server,
+            external::set_external_server,
+            sidecar_update::check_sidecar_update,
+            sidecar_update::install_sidecar_update,
+            quiet_hours::get_quiet_hours,
+            quiet_hours::configure_quiet_hours,
+            context_menu::show_context_menu,
+            notifications::notification_action,
+            notifications::dismiss_notification,
+            notifications::notify_download,
+            pending_delete::delete_with_undo,
+            pending_delete::undo_delete,
+            pending_action::list_pending_actions,
+            pending_action::commit_action,
+            pending_action::revert_action,
+            dock_drop::set_current_folder,
+            dock_drop::get_drop_destination,
+            dock_drop::set_drop_destination,
+            print::print_file,
+            copy_urls::copy_urls,
+            copy_urls::get_download_link,
+            selection::selection_add,
+            selection::selection_add_folder,
+            selection::selection_remove,
+            selection::selection_clear,
+            selection::selection_keys,
+            event_stream::get_event_stream_status,
+            replay::subscribe_with_replay,
+            eject::eject_import_source,
+            session::get_api_credentials,
+            session::revoke_session_tokens,
+            checksums::get_object_checksums,
+            clock::get_clock_skew,
+            clock::to_server_time,
+            clock::to_local_time,
+            listing::list_buckets,
+            listing::get_folder_stats,
+            listing::list_objects,
+            export::export_local_data,
+            operations::list_operations,
+            operations::cancel_operation,
+            jobs::list_jobs,
+            jobs::pause_job,
+            jobs::cancel_job,
+            jobs::retry_job,
+            settings_sync::get_settings_sync,
+            settings_sync::set_settings_sync,
+            settings_sync::sync_settings_now,
+            transfers::get_transfer_details,
+            share::share_file,
+        ]))
         .setup(|app| {
-            // Setup logging in debug mode
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
-            }
+            // Log to rotating files in the log directory, and to stdout in debug mode
+            app.handle().plugin(log_files::plugin())?;
+            log_files::init_level();
+            log_files::prune(app.handle());
 
             // Create the application menu
             let app_menu = Submenu::with_items(
@@ -300,6 +1319,10 @@ pub fn run() {
                     &PredefinedMenuItem::separator(app)?,
                     &MenuItem::with_id(app, "refresh", "Refresh", true, Some("CmdOrCtrl+R"))?,
                     &PredefinedMenuItem::separator(app)?,
+                    &MenuItem::with_id(app, "print", "Print...", true, Some("CmdOrCtrl+P"))?,
+                    &PredefinedMenuItem::separator(app)?,
+                    &MenuItem::with_id(app, "eject_card", "Eject Imported Card", true, Some("CmdOrCtrl+E"))?,
+                    &PredefinedMenuItem::separator(app)?,
                     &PredefinedMenuItem::close_window(app, None)?,
                 ],
             )?;
@@ -318,6 +1341,7 @@ pub fn run() {
                     &PredefinedMenuItem::select_all(app, None)?,
                     &PredefinedMenuItem::separator(app)?,
                     &MenuItem::with_id(app, "copy_url", "Copy URL", true, Some("CmdOrCtrl+Shift+C"))?,
+                    &MenuItem::with_id(app, "copy_sha256", "Copy SHA-256", true, None::<&str>)?,
                 ],
             )?;
 
@@ -328,6 +1352,8 @@ pub fn run() {
                 &[
                     &MenuItem::with_id(app, "toggle_sidebar", "Toggle Sidebar", true, Some("CmdOrCtrl+\\"))?,
                     &PredefinedMenuItem::separator(app)?,
+                    &MenuItem::with_id(app, "backend_logs", "Show Backend Logs", true, Some("CmdOrCtrl+Shift+L"))?,
+                    &PredefinedMenuItem::separator(app)?,
                     &PredefinedMenuItem::fullscreen(app, None)?,
                 ],
             )?;
@@ -351,6 +1377,8 @@ pub fn run() {
                 &[
                     &MenuItem::with_id(app, "documentation", "Documentation", true, None::<&str>)?,
                     &MenuItem::with_id(app, "github", "GitHub Repository", true, None::<&str>)?,
+                    &PredefinedMenuItem::separator(app)?,
+                    &MenuItem::with_id(app, "export_diagnostics", "Export Diagnostics...", true, None::<&str>)?,
                 ],
             )?;
 
@@ -361,68 +1389,113 @@ pub fn run() {
 
             app.set_menu(menu)?;
 
-            // Create restart channel
-            let (restart_tx, restart_rx) = mpsc::channel::<()>(1);
-
-            // Store restart sender in state
+            // Spawn the default profile's restart handler on a separate thread; other
+            // profiles get theirs when first started
             let state: tauri::State<Arc<AppState>> = app.state();
-            {
-                let mut guard = state.restart_tx.lock().unwrap();
-                *guard = Some(restart_tx);
-            }
-
-            // Spawn the restart handler on a separate thread
             let app_handle = app.handle().clone();
             let state_clone = Arc::clone(&state);
-            spawn_restart_handler(app_handle, state_clone, restart_rx);
+            spawn_restart_handler(app_handle, state_clone, state.sidecar());
 
-            // Start the sidecar
+            // Start the sidecar, after stopping one a crashed previous run left behind
             let app_handle = app.handle().clone();
             let state_clone = Arc::clone(&state);
-            if let Err(e) = start_sidecar_sync(&app_handle, &state_clone) {
+            crash_reporter::restore(&app_handle, &state_clone);
+            telemetry::restore(&app_handle, &state_clone);
+            safe_mode::restore(&app_handle, &state_clone);
+            let safe = state_clone.safe_mode.is_active();
+            status_history::restore(&app_handle, &state_clone);
+            health::restore(&app_handle, &state_clone);
+            network::restore(&app_handle, &state_clone);
+            quiet_hours::restore(&app_handle, &state_clone);
+            bandwidth::restore(&app_handle, &state_clone);
+            sidecar_config::restore(&app_handle, &state_clone);
+            thumbnails::restore(&app_handle, &state_clone);
+            access_stats::restore(&app_handle, &state_clone);
+            view_prefs::restore(&app_handle, &state_clone);
+            suggestions::restore(&app_handle, &state_clone);
+            permissions::restore(&app_handle, &state_clone);
+            pidfile::kill_stale(&app_handle);
+            external::restore(&app_handle, &state_clone);
+            // Safe mode runs the bundled sidecar, in case a downloaded one is the problem
+            if !safe {
+                sidecar_update::restore(&app_handle, &state_clone);
+            }
+            let sidecar = state_clone.sidecar();
+            if let Err(e) = start_sidecar_sync(&app_handle, &state_clone, &sidecar) {
                 log::error!("Failed to start sidecar: {}", e);
-                emit_backend_status(&app_handle, BackendStatus::Crashed { error: e });
+                emit_backend_status(&app_handle, &sidecar, BackendStatus::Crashed { error: e.to_string() });
+            }
+
+            // The main window opens once the backend is up
+            splash::show(&app_handle, &state_clone);
+
+            accounts::restore(&app_handle, &state_clone);
+            dock_drop::restore(&app_handle, &state_clone);
+            jobs::restore(&app_handle, &state_clone);
+            settings_sync::restore(&app_handle, &state_clone);
+            power::spawn_monitor(app_handle.clone(), Arc::clone(&state_clone));
+
+            // Safe mode stops here: supervision and settings only, nothing that
+            // watches, resumes or runs in the background
+            if safe {
+                return Ok(());
             }
 
+            // Windows and Linux pass files dropped on the taskbar/launcher icon as arguments
+            let dropped = std::env::args_os()
+                .skip(1)
+                .map(std::path::PathBuf::from)
+                .filter(|path| path.exists())
+                .collect();
+            dock_drop::enqueue(&app_handle, dropped);
+            resources::spawn_monitor(app_handle.clone(), Arc::clone(&state_clone));
+            sidecar_metrics::spawn_monitor(app_handle.clone(), Arc::clone(&state_clone));
+            sidecar_config::spawn_monitor(app_handle.clone(), Arc::clone(&state_clone));
+            backend_metrics::spawn_monitor(app_handle.clone(), Arc::clone(&state_clone));
+            network::spawn_monitor(app_handle.clone(), Arc::clone(&state_clone));
+            sidecar_update::spawn_checker(app_handle.clone(), Arc::clone(&state_clone));
+            quiet_hours::spawn_monitor(app_handle.clone(), Arc::clone(&state_clone));
+            settings_sync::spawn_monitor(app_handle.clone(), Arc::clone(&state_clone));
+            access_stats::spawn_monitor(app_handle.clone(), Arc::clone(&state_clone));
+            event_stream::spawn(app_handle.clone(), Arc::clone(&state_clone));
+
+            // Load remote copy, URL upload, download and import jobs, and ask before
+            // continuing the ones the last exit interrupted
+            remote_copy::restore(&app_handle, &state_clone);
+            url_upload::restore(&app_handle, &state_clone);
+            // Before downloads, which leave the ones it started to it
+            transfer_queue::restore(&app_handle, &state_clone);
+            file_download::restore(&app_handle, &state_clone);
+            chunked_upload::restore(&app_handle, &state_clone);
+            // After URL uploads, whose spooled downloads it must keep
+            scratch::restore(&app_handle, &state_clone);
+            import::restore(&app_handle, &state_clone);
+            resume::restore(&app_handle, &state_clone);
+            transfer_queue::start(&app_handle, &state_clone);
+            // And sync and watch jobs checkpointed by a restart the app didn't outlive
+            drain::restore(&app_handle, &state_clone);
+
             Ok(())
         })
         .on_menu_event(|app, event| {
             let id = event.id().as_ref();
+            if context_menu::handle_menu_event(app, id) {
+                return;
+            }
+            if let Some(name) = menu_event_name(id) {
+                route_menu_event(app, name);
+                return;
+            }
             match id {
-                "upload" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.emit("menu-upload", ());
-                    }
-                }
-                "download" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.emit("menu-download", ());
-                    }
-                }
-                "delete" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.emit("menu-delete", ());
-                    }
-                }
-                "refresh" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.emit("menu-refresh", ());
-                    }
-                }
-                "copy_url" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.emit("menu-copy-url", ());
-                    }
-                }
-                "toggle_sidebar" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.emit("menu-toggle-sidebar", ());
-                    }
-                }
-                "preferences" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.emit("menu-preferences", ());
-                    }
+                "eject_card" => eject::eject_latest(app),
+                // Windows can deadlock creating a webview from inside an event handler
+                "backend_logs" => {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = log_viewer::show(&app) {
+                            log::error!("{}", e);
+                        }
+                    });
                 }
                 "documentation" => {
                     let _ = open::that("https://github.com/LayerDynamics/bb-stream#readme");
@@ -430,18 +1503,68 @@ pub fn run() {
                 "github" => {
                     let _ = open::that("https://github.com/LayerDynamics/bb-stream");
                 }
+                "export_diagnostics" => {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = diagnostics::export(&app, None).await {
+                            log::error!("Failed to export diagnostics: {}", e);
+                        }
+                    });
+                }
                 _ => {}
             }
         })
-        .on_window_event(|window, event| {
-            // Kill sidecar when window closes
-            if let tauri::WindowEvent::CloseRequested { .. } = event {
+        .on_window_event(|window, event| match event {
+            // Track focus so menu actions reach the window the user is working in
+            tauri::WindowEvent::Focused(focused) => {
+                let state: tauri::State<Arc<AppState>> = window.state();
+                if *focused {
+                    *state.focused_window.lock().unwrap() = Some(window.label().to_string());
+                }
+                activity::focus_changed(&state, *focused);
+            }
+            tauri::WindowEvent::Destroyed => {
                 let state: tauri::State<Arc<AppState>> = window.state();
-                state.shutdown.store(true, Ordering::SeqCst);
-                kill_sidecar(&state);
-                log::info!("BB Stream sidecar stopped");
+                let mut focused = state.focused_window.lock().unwrap();
+                if focused.as_deref() == Some(window.label()) {
+                    *focused = None;
+                }
+                guest::window_destroyed(&state, window.label());
+            }
+            // Kill every profile's sidecar when the main window, or the splash before
+            // it, is closed; while transfers run, only once the user confirms
+            tauri::WindowEvent::CloseRequested { api, .. } if ["main", splash::LABEL].contains(&window.label()) => {
+                if quit::hold(window.app_handle(), quit::Then::Close(Box::new(window.clone()))) {
+                    api.prevent_close();
+                } else {
+                    stop_sidecars(window.app_handle());
+                }
             }
+            _ => {}
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_page_load(|webview, payload| {
+            if webview.label() == "main" && payload.event() == tauri::webview::PageLoadEvent::Finished {
+                let state: tauri::State<Arc<AppState>> = webview.state();
+                safe_mode::page_loaded(webview.app_handle(), &state);
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| match event {
+            // E.g. quitting from the app menu; confirmed first while transfers run
+            tauri::RunEvent::ExitRequested { api, code, .. } => {
+                if quit::hold(app, quit::Then::Exit(code.unwrap_or(0))) {
+                    api.prevent_exit();
+                } else {
+                    stop_sidecars(app);
+                }
+            }
+            // Files dropped on the dock icon arrive as open requests
+            #[cfg(target_os = "macos")]
+            tauri::RunEvent::Opened { urls } => {
+                let paths = urls.iter().filter_map(|url| url.to_file_path().ok()).collect();
+                dock_drop::enqueue(app, paths);
+            }
+            _ => {}
+        });
 }
@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::backend::{self, RemoteBucket, RemoteObject, RequestError};
+use crate::error::AppError;
+use crate::remote_path::RemotePath;
+use crate::suggestions::Category;
+use crate::{access_stats, collation, emit_to_main, profiles, read_retry, store, AppState, DEFAULT_PROFILE};
+
+// Listings are small and hot; cap how many stay in memory between disk reads
+const MEMORY_ENTRIES: usize = 64;
+
+// Folder stats from a cached listing older than this are refreshed in the background
+const STATS_MAX_AGE_SECS: i64 = 60;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObjectListing {
+    pub bucket: String,
+    pub prefix: String,
+    pub objects: Vec<RemoteObject>,
+    // Unix seconds when the backend produced this listing
+    pub fetched_at: i64,
+    // True when served from cache because the backend is unavailable
+    #[serde(default)]
+    pub stale: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BucketListing {
+    pub buckets: Vec<RemoteBucket>,
+    pub fetched_at: i64,
+    #[serde(default)]
+    pub stale: bool,
+}
+
+// Last known listings, kept on disk under the cache directory so the app stays
+// browsable (read-only) while the backend is down
+pub struct ListingCache {
+    objects: Mutex<HashMap<String, ObjectListing>>,
+    buckets: Mutex<Option<BucketListing>>,
+    // Folders whose stats are being refreshed in the background, by cache key
+    refreshing: Mutex<HashSet<String>>,
+}
+
+impl ListingCache {
+    pub fn new() -> Self {
+        Self {
+            objects: Mutex::new(HashMap::new()),
+            buckets: Mutex::new(None),
+            refreshing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn remember(&self, app: &AppHandle, listing: &ObjectListing) {
+        let key = cache_key(&listing.bucket, &listing.prefix);
+        {
+            let mut objects = self.objects.lock().unwrap();
+            if objects.len() >= MEMORY_ENTRIES && !objects.contains_key(&key) {
+                objects.clear();
+            }
+            objects.insert(key.clone(), listing.clone());
+        }
+        if let Err(e) = cache_file(app, &key).and_then(|path| store::save_json(&path, listing)) {
+            log::warn!("Failed to cache listing: {}", e);
+        }
+    }
+
+    // Drop listings held in memory, e.g. after switching to another profile
+    pub fn clear_memory(&self) {
+        self.objects.lock().unwrap().clear();
+        *self.buckets.lock().unwrap() = None;
+    }
+
+    // Cached listing, marked stale
+    pub fn cached(&self, app: &AppHandle, bucket: &str, prefix: &str) -> Option<ObjectListing> {
+        let key = cache_key(bucket, prefix);
+        let listing = match self.objects.lock().unwrap().get(&key).cloned() {
+            Some(listing) => Some(listing),
+            None => cache_file(app, &key)
+                .ok()
+                .and_then(|path| store::load_json::<Option<ObjectListing>>(&path)),
+        };
+        listing.map(|mut listing| {
+            listing.stale = true;
+            listing
+        })
+    }
+}
+
+fn cache_key(bucket: &str, prefix: &str) -> String {
+    hex::encode(Sha256::digest(format!("{}/{}", bucket, prefix).as_bytes()))
+}
+
+// Profiles see different accounts, so each caches its listings separately; the
+// default profile keeps the top-level directory
+fn profile_dir(app: &AppHandle) -> String {
+    let profile = app.state::<Arc<AppState>>().active_profile.lock().unwrap().clone();
+    if profile == DEFAULT_PROFILE {
+        String::new()
+    } else {
+        format!("profile-{}", profiles::slug(&profile))
+    }
+}
+
+fn cache_file(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to resolve cache directory: {}", e))?
+        .join("listings")
+        .join(profile_dir(app));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    Ok(dir.join(format!("{}.json", name)))
+}
+
+fn http_client(base_url: &str) -> Result<reqwest::Client, String> {
+    backend::client_builder(base_url)
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+// Sort off the async runtime; large folders take a noticeable moment to collate
+async fn sorted<T: Send + 'static>(mut items: Vec<T>, sort: fn(&mut [T])) -> Result<Vec<T>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        sort(&mut items);
+        items
+    })
+    .await
+    .map_err(|e| format!("Failed to sort listing: {}", e))
+}
+
+// List a folder through the backend, in natural order, falling back to the last cached listing
+// (flagged `stale`) while the backend is unhealthy, its reads are failing, or the request fails
+pub async fn list_objects_cached(
+    app: &AppHandle,
+    state: &AppState,
+    bucket: &str,
+    prefix: &str,
+) -> Result<ObjectListing, String> {
+    let fresh = if state.is_healthy() {
+        let what = format!("Listing {}/{}", bucket, prefix);
+        let sidecar = state.sidecar();
+        let listed = read_retry::read(app, &sidecar, &what, |base_url| async move {
+            let client = http_client(&base_url).map_err(RequestError::other)?;
+            backend::request_objects(&client, &base_url, bucket, prefix).await
+        });
+        match listed.await {
+            Ok(objects) => sorted(objects, collation::sort_objects).await,
+            Err(e) => Err(e),
+        }
+    } else {
+        Err("Backend is unavailable".to_string())
+    };
+
+    match fresh {
+        Ok(objects) => {
+            let listing = ObjectListing {
+                bucket: bucket.to_string(),
+                prefix: prefix.to_string(),
+                objects,
+                fetched_at: chrono::Utc::now().timestamp(),
+                stale: false,
+            };
+            state.listings.remember(app, &listing);
+            Ok(listing)
+        }
+        Err(e) => state.listings.cached(app, bucket, prefix).ok_or(e),
+    }
+}
+
+#[tauri::command]
+pub async fn list_objects(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    bucket: String,
+    prefix: Option<String>,
+) -> Result<ObjectListing, AppError> {
+    let prefix = prefix.unwrap_or_default();
+    access_stats::record(&state, &bucket, &prefix, access_stats::Access::Listing);
+    Ok(list_objects_cached(&app, &state, &bucket, &prefix).await?)
+}
+
+#[tauri::command]
+pub async fn list_buckets(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+) -> Result<BucketListing, AppError> {
+    let fresh = if state.is_healthy() {
+        let sidecar = state.sidecar();
+        let listed = read_retry::read(&app, &sidecar, "Listing buckets", |base_url| async move {
+            let client = http_client(&base_url).map_err(RequestError::other)?;
+            backend::request_buckets(&client, &base_url).await
+        });
+        match listed.await {
+            Ok(buckets) => sorted(buckets, collation::sort_buckets).await,
+            Err(e) => Err(e),
+        }
+    } else {
+        Err("Backend is unavailable".to_string())
+    };
+
+    match fresh {
+        Ok(buckets) => {
+            let listing = BucketListing {
+                buckets,
+                fetched_at: chrono::Utc::now().timestamp(),
+                stale: false,
+            };
+            *state.listings.buckets.lock().unwrap() = Some(listing.clone());
+            if let Err(e) = cache_file(&app, "buckets").and_then(|path| store::save_json(&path, &listing)) {
+                log::warn!("Failed to cache bucket list: {}", e);
+            }
+            Ok(listing)
+        }
+        Err(e) => {
+            let cached = state.listings.buckets.lock().unwrap().clone().or_else(|| {
+                cache_file(&app, "buckets")
+                    .ok()
+                    .and_then(|path| store::load_json::<Option<BucketListing>>(&path))
+            });
+            cached
+                .map(|mut listing| {
+                    listing.stale = true;
+                    listing
+                })
+                .ok_or(AppError::BackendUnreachable { message: e })
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct TypeStats {
+    pub count: usize,
+    pub size: u64,
+}
+
+// Summary of a folder's direct contents for the browser's header or footer
+#[derive(Clone, Debug, Serialize)]
+pub struct FolderStats {
+    pub bucket: String,
+    pub prefix: String,
+    pub files: usize,
+    pub folders: usize,
+    pub total_size: u64,
+    // Modification times of the newest and oldest file, as the backend reports them
+    pub newest: Option<i64>,
+    pub oldest: Option<i64>,
+    pub by_type: BTreeMap<Category, TypeStats>,
+    // When the listing these were computed from was fetched
+    pub fetched_at: i64,
+    pub stale: bool,
+    // A fresher listing is being fetched; `folder-stats` follows with the result
+    pub refreshing: bool,
+}
+
+impl FolderStats {
+    fn of(listing: &ObjectListing) -> Self {
+        let mut stats = FolderStats {
+            bucket: listing.bucket.clone(),
+            prefix: listing.prefix.clone(),
+            files: 0,
+            folders: 0,
+            total_size: 0,
+            newest: None,
+            oldest: None,
+            by_type: BTreeMap::new(),
+            fetched_at: listing.fetched_at,
+            stale: listing.stale,
+            refreshing: false,
+        };
+        for object in &listing.objects {
+            // The backend lists subfolders as names ending in '/'
+            if object.name.ends_with('/') {
+                stats.folders += 1;
+                continue;
+            }
+            let size = object.size.max(0) as u64;
+            stats.files += 1;
+            stats.total_size += size;
+            if object.timestamp > 0 {
+                stats.newest = Some(stats.newest.map_or(object.timestamp, |t| t.max(object.timestamp)));
+                stats.oldest = Some(stats.oldest.map_or(object.timestamp, |t| t.min(object.timestamp)));
+            }
+            let by_type = stats.by_type.entry(Category::of(&object.name)).or_default();
+            by_type.count += 1;
+            by_type.size += size;
+        }
+        stats
+    }
+}
+
+// Refresh a folder's listing off the request path and send its stats as `folder-stats`
+fn spawn_stats_refresh(app: &AppHandle, bucket: String, prefix: String) {
+    let key = cache_key(&bucket, &prefix);
+    let state = Arc::clone(&app.state::<Arc<AppState>>());
+    if !state.listings.refreshing.lock().unwrap().insert(key.clone()) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match list_objects_cached(&app, &state, &bucket, &prefix).await {
+            Ok(listing) if !listing.stale => emit_to_main(&app, "folder-stats", FolderStats::of(&listing)),
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to refresh stats of {}/{}: {}", bucket, prefix, e),
+        }
+        state.listings.refreshing.lock().unwrap().remove(&key);
+    });
+}
+
+// Item count, size, date range and type breakdown of a folder ("bucket/path"),
+// answered from the listing cache when it can be so browsing costs the backend
+// nothing extra. Old cached listings are refreshed in the background.
+#[tauri::command]
+pub async fn get_folder_stats(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    path: String,
+) -> Result<FolderStats, AppError> {
+    let path = RemotePath::parse(&path).map_err(AppError::invalid)?;
+    let (bucket, folder) = path.as_str().split_once('/').unwrap_or((path.as_str(), ""));
+    if bucket.is_empty() {
+        return Err(AppError::invalid("Folder stats need a bucket"));
+    }
+    let prefix = RemotePath::parse(folder).map_err(AppError::invalid)?.list_prefix();
+
+    let Some(cached) = state.listings.cached(&app, bucket, &prefix) else {
+        let listing = list_objects_cached(&app, &state, bucket, &prefix).await?;
+        return Ok(FolderStats::of(&listing));
+    };
+    // `cached` flags every listing stale; it only is if it's old
+    let age = chrono::Utc::now().timestamp() - cached.fetched_at;
+    let mut stats = FolderStats::of(&cached);
+    stats.stale = age > STATS_MAX_AGE_SECS;
+    if stats.stale && state.is_healthy() {
+        spawn_stats_refresh(&app, bucket.to_string(), prefix);
+        stats.refreshing = true;
+    }
+    Ok(stats)
+}
@@ -0,0 +1,176 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use log::LevelFilter;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+use crate::error::AppError;
+use crate::{sidecar_config, AppState};
+
+// Target of log lines that are sidecar output, kept in their own file
+pub const SIDECAR_TARGET: &str = "sidecar";
+
+// What the shell logs at until set_log_level changes it
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+// A log file is rotated once it grows past this
+const MAX_FILE_SIZE: u128 = 5 * 1024 * 1024;
+
+// Rotated files kept per log, besides the current one
+const KEEP_ROTATED: usize = 5;
+
+// Rotated files older than this are deleted at startup, however few there are
+const MAX_AGE: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+// Logging to rotating files in the platform log directory: the shell's own log in
+// bb-stream.log and captured sidecar output in sidecar.log, so they survive a crash
+// and can be attached to bug reports. Debug builds also log to stdout.
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    let mut targets = vec![
+        Target::new(TargetKind::LogDir {
+            file_name: Some("bb-stream".to_string()),
+        })
+        .filter(|metadata| metadata.target() != SIDECAR_TARGET),
+        Target::new(TargetKind::LogDir {
+            file_name: Some("sidecar".to_string()),
+        })
+        .filter(|metadata| metadata.target() == SIDECAR_TARGET),
+    ];
+    if cfg!(debug_assertions) {
+        targets.push(Target::new(TargetKind::Stdout));
+    }
+    // Everything reaches the plugin; log::max_level is what set_log_level changes
+    tauri_plugin_log::Builder::default()
+        .level(LevelFilter::Trace)
+        .filter(|metadata| {
+            // Other crates' debug output would drown the shell's own
+            metadata.level() <= log::Level::Info
+                || metadata.target() == SIDECAR_TARGET
+                || metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+        })
+        .targets(targets)
+        .max_file_size(MAX_FILE_SIZE)
+        .rotation_strategy(RotationStrategy::KeepSome(KEEP_ROTATED))
+        .build()
+}
+
+// The plugin lets everything through once attached; start at the default level
+pub fn init_level() {
+    log::set_max_level(DEFAULT_LEVEL);
+}
+
+// Delete rotated log files past the age limit; the current files are left alone
+pub fn prune(app: &AppHandle) {
+    let Ok(dir) = app.path().app_log_dir() else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Rotated files carry a date: bb-stream_2024-01-31_12-00-00.log
+        if !name.ends_with(".log") || !name.contains('_') {
+            continue;
+        }
+        let old = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > MAX_AGE);
+        if old {
+            match std::fs::remove_file(&path) {
+                Ok(()) => log::info!("Deleted old log file {}", path.display()),
+                Err(e) => log::warn!("Failed to delete old log file {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+// The last `lines` lines of the shell's own log, oldest first
+pub fn tail(app: &AppHandle, lines: usize) -> Vec<String> {
+    // Plenty for a few hundred lines without reading a whole rotated-size file
+    const TAIL_BYTES: u64 = 256 * 1024;
+    let Ok(dir) = app.path().app_log_dir() else {
+        return Vec::new();
+    };
+    let Ok(mut file) = std::fs::File::open(dir.join("bb-stream.log")) else {
+        return Vec::new();
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut bytes = Vec::new();
+    if file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES))).is_err() || file.read_to_end(&mut bytes).is_err() {
+        return Vec::new();
+    }
+    let text = String::from_utf8_lossy(&bytes);
+    let mut all: Vec<&str> = text.lines().collect();
+    // The first line is likely cut off unless the whole file was read
+    if len > TAIL_BYTES && !all.is_empty() {
+        all.remove(0);
+    }
+    all[all.len().saturating_sub(lines)..].iter().map(|line| line.to_string()).collect()
+}
+
+// Show the folder with the log files, e.g. to attach them to a bug report
+#[tauri::command]
+pub fn open_log_directory(app: AppHandle) -> Result<(), AppError> {
+    let dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve log directory: {}", e))?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    open::that(&dir).map_err(|e| format!("Failed to open {}: {}", dir.display(), e).into())
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LogLevels {
+    // What the shell's own log and captured sidecar output are written at
+    pub shell: String,
+    // What the sidecars log at
+    pub sidecar: String,
+    // Profiles whose running sidecar took a set_log_level right away; the others take
+    // it when they restart
+    pub applied_live: Vec<String>,
+}
+
+fn levels(state: &AppState, applied_live: Vec<String>) -> LogLevels {
+    LogLevels {
+        shell: log::max_level().to_string().to_lowercase(),
+        sidecar: state.sidecar_config.log_level(),
+        applied_live,
+    }
+}
+
+#[tauri::command]
+pub fn get_log_level(state: tauri::State<Arc<AppState>>) -> LogLevels {
+    levels(&state, Vec::new())
+}
+
+// Log at `level` (debug, info, warn or error) in the shell and every sidecar, e.g.
+// while reproducing an issue, without restarting anything. Lasts until the app quits;
+// None goes back to the default and configured levels.
+#[tauri::command]
+pub async fn set_log_level(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    level: Option<String>,
+) -> Result<LogLevels, AppError> {
+    let level = level.map(|level| level.trim().to_ascii_lowercase());
+    let shell = match &level {
+        Some(level) => {
+            sidecar_config::validate_log_level(level).map_err(AppError::invalid)?;
+            level.parse::<LevelFilter>().map_err(|e| AppError::invalid(e.to_string()))?
+        }
+        None => DEFAULT_LEVEL,
+    };
+    log::set_max_level(shell);
+    log::info!("Logging at {}", level.as_deref().unwrap_or("the configured levels"));
+    let applied_live = sidecar_config::override_log_level(&app, &state, level).await;
+    Ok(levels(&state, applied_live))
+}
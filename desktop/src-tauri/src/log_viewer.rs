@@ -0,0 +1,27 @@
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::error::AppError;
+
+pub const LABEL: &str = "logs";
+
+// Open the backend log window, or bring it to the front when it is already open.
+// The page reads the in-memory log buffer through get_backend_logs.
+pub fn show(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.unminimize();
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+    WebviewWindowBuilder::new(app, LABEL, WebviewUrl::App("logs.html".into()))
+        .title("Backend Logs")
+        .inner_size(960.0, 600.0)
+        .min_inner_size(480.0, 240.0)
+        .build()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open the log window: {}", e))
+}
+
+// Async so the window isn't created on the main thread, which deadlocks on Windows
+#[tauri::command]
+pub async fn show_backend_logs(app: AppHandle) -> Result<(), AppError> {
+    Ok(show(&app)?)
+}
@@ -0,0 +1,114 @@
+use tauri::{AppHandle, Manager};
+
+use crate::notifications::Notification;
+
+// Called with the id of the action picked on the OS notification
+type OnAction = Box<dyn FnOnce(String) + Send>;
+
+// Whether the user would miss a toast: the main window is hidden, minimized or behind
+// another app
+pub fn in_background(app: &AppHandle) -> bool {
+    match app.get_webview_window("main") {
+        Some(window) => !window.is_visible().unwrap_or(false) || !window.is_focused().unwrap_or(false),
+        None => true,
+    }
+}
+
+// Show a notification through the OS notification center with its action buttons.
+// Clicking the notification itself or closing it picks no action.
+pub fn show(app: &AppHandle, notification: &Notification, on_action: OnAction) {
+    if let Err(e) = deliver(app, notification.clone(), on_action) {
+        log::warn!("Failed to show a system notification: {}", e);
+    }
+}
+
+// Linux and the BSDs: org.freedesktop.Notifications over D-Bus. Servers that don't
+// support actions show the notification without buttons.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn deliver(_app: &AppHandle, notification: Notification, on_action: OnAction) -> Result<(), String> {
+    let mut native = notify_rust::Notification::new();
+    native
+        .appname("bb-stream")
+        .summary(&notification.title)
+        .body(&notification.body);
+    for action in &notification.actions {
+        native.action(&action.id, &action.label);
+    }
+    let handle = native.show().map_err(|e| e.to_string())?;
+    if notification.actions.is_empty() {
+        return Ok(());
+    }
+    // Blocks until the notification is acted on or closed
+    std::thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            // "default" for a click on the body and "__closed" when it goes away
+            if notification.actions.iter().any(|a| a.id == action) {
+                on_action(action.to_string());
+            }
+        });
+    });
+    Ok(())
+}
+
+// macOS: one action is the notification's button; several go in a dropdown under it
+#[cfg(target_os = "macos")]
+fn deliver(app: &AppHandle, notification: Notification, on_action: OnAction) -> Result<(), String> {
+    use mac_notification_sys::{MainButton, NotificationResponse};
+
+    let identifier = app.config().identifier.clone();
+    // Blocks until the notification is acted on or closed
+    std::thread::spawn(move || {
+        // Fails harmlessly once set
+        let _ = mac_notification_sys::set_application(&identifier);
+        let labels: Vec<&str> = notification.actions.iter().map(|a| a.label.as_str()).collect();
+        let mut native = mac_notification_sys::Notification::new();
+        native.title(&notification.title).message(&notification.body);
+        match labels.as_slice() {
+            [] => {}
+            [label] => {
+                native.main_button(MainButton::SingleAction(label));
+            }
+            _ => {
+                native.main_button(MainButton::DropdownActions("Actions", &labels));
+            }
+        }
+        match native.send() {
+            Ok(NotificationResponse::ActionButton(label)) => {
+                if let Some(action) = notification.actions.iter().find(|a| a.label == label) {
+                    on_action(action.id.clone());
+                }
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to show a system notification: {}", e),
+        }
+    });
+    Ok(())
+}
+
+// Windows: a toast with a button per action. Installed builds are registered under the
+// bundle identifier, which toasts need to show up.
+#[cfg(target_os = "windows")]
+fn deliver(app: &AppHandle, notification: Notification, on_action: OnAction) -> Result<(), String> {
+    use tauri_winrt_notification::Toast;
+
+    let mut toast = Toast::new(&app.config().identifier)
+        .title(&notification.title)
+        .text1(&notification.body);
+    for action in &notification.actions {
+        toast = toast.add_button(&action.label, &action.id);
+    }
+    let ids: Vec<String> = notification.actions.iter().map(|a| a.id.clone()).collect();
+    let mut on_action = Some(on_action);
+    toast
+        .on_activated(move |action| {
+            // No action when the toast itself is clicked
+            if let Some(action) = action.filter(|action| ids.contains(action)) {
+                if let Some(on_action) = on_action.take() {
+                    on_action(action);
+                }
+            }
+            Ok(())
+        })
+        .show()
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,274 @@
+use std::collections::HashSet;
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::notifications::{self, Notification};
+use crate::{emit_to_main, store, transfers, AppState};
+
+pub const SETTINGS_FILE: &str = "wifi_only.json";
+
+// How often the connection is checked for being metered while Wi-Fi-only is on
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(30);
+
+// How often a held file checks whether it may go ahead
+const WAIT_POLL: Duration = Duration::from_secs(2);
+
+// Hold large files while on a metered connection, e.g. a phone hotspot, so a big
+// upload doesn't eat a data plan
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WifiOnlySettings {
+    pub enabled: bool,
+    // Files at least this large wait for an unmetered connection
+    pub threshold_bytes: u64,
+}
+
+impl Default for WifiOnlySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_bytes: 100 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct WaitingEvent {
+    id: String,
+    key: String,
+    size: u64,
+}
+
+pub struct Network {
+    settings: Mutex<WifiOnlySettings>,
+    // Whether the current connection is metered; None when the platform can't tell
+    metered: Mutex<Option<bool>>,
+    // Transfers the user allowed to continue on a metered connection
+    overrides: Mutex<HashSet<String>>,
+    // Transfers the user was already told are waiting
+    notified: Mutex<HashSet<String>>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(WifiOnlySettings::default()),
+            metered: Mutex::new(None),
+            overrides: Mutex::new(HashSet::new()),
+            notified: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // Known to be on a metered connection
+    pub fn is_metered(&self) -> bool {
+        *self.metered.lock().unwrap() == Some(true)
+    }
+
+    fn must_wait(&self, id: &str, size: u64) -> bool {
+        let settings = *self.settings.lock().unwrap();
+        settings.enabled
+            && size >= settings.threshold_bytes
+            && *self.metered.lock().unwrap() == Some(true)
+            && !self.overrides.lock().unwrap().contains(id)
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn output(command: &mut Command) -> Option<String> {
+    let output = command.output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// NetworkManager's overall metered state: 1 yes, 2 no, 3 guessed yes, 4 guessed no
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn is_metered() -> Option<bool> {
+    let reply = output(Command::new("busctl").args([
+        "get-property",
+        "org.freedesktop.NetworkManager",
+        "/org/freedesktop/NetworkManager",
+        "org.freedesktop.NetworkManager",
+        "Metered",
+    ]))?;
+    match reply.strip_prefix("u ")?.trim() {
+        "1" | "3" => Some(true),
+        "2" | "4" => Some(false),
+        _ => None,
+    }
+}
+
+// Windows reports the cost of the internet connection profile directly
+#[cfg(target_os = "windows")]
+fn is_metered() -> Option<bool> {
+    use windows::Networking::Connectivity::{NetworkCostType, NetworkInformation};
+
+    // No profile while offline
+    let profile = NetworkInformation::GetInternetConnectionProfile().ok()?;
+    match profile.GetConnectionCost().ok()?.NetworkCostType().ok()? {
+        NetworkCostType::Fixed | NetworkCostType::Variable => Some(true),
+        NetworkCostType::Unrestricted => Some(false),
+        _ => None,
+    }
+}
+
+// macOS has no command-line view of a connection's cost, so judge by the hardware
+// port carrying the default route: tethering over USB or Bluetooth is metered
+#[cfg(target_os = "macos")]
+fn is_metered() -> Option<bool> {
+    let route = output(Command::new("route").args(["-n", "get", "default"]))?;
+    let interface = route
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("interface:"))?
+        .trim()
+        .to_string();
+    let ports = output(Command::new("networksetup").arg("-listallhardwareports"))?;
+    let mut port = None;
+    for line in ports.lines() {
+        if let Some(name) = line.strip_prefix("Hardware Port: ") {
+            port = Some(name.to_string());
+        } else if line.strip_prefix("Device: ") == Some(interface.as_str()) {
+            let port = port?;
+            return Some(port.contains("iPhone") || port.contains("Bluetooth") || port.contains("Android"));
+        }
+    }
+    None
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => *state.network.settings.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load Wi-Fi-only settings: {}", e),
+    }
+}
+
+// Check whether the connection is metered now, announcing a change as
+// `network-metered`
+pub async fn sample(app: &AppHandle, state: &AppState) -> Option<bool> {
+    let metered = tauri::async_runtime::spawn_blocking(is_metered).await.unwrap_or(None);
+    let previous = std::mem::replace(&mut *state.network.metered.lock().unwrap(), metered);
+    if previous != metered {
+        log::info!("Metered connection: {:?}", metered);
+        emit_to_main(app, "network-metered", metered);
+    }
+    metered
+}
+
+// Re-check whether the connection is metered periodically, only while Wi-Fi-only
+// holds transfers back; otherwise nothing needs to know between samples
+pub fn spawn_monitor(app: AppHandle, state: Arc<AppState>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if state.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            if state.network.settings.lock().unwrap().enabled {
+                sample(&app, &state).await;
+            }
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+        }
+    });
+}
+
+fn notify_waiting(app: &AppHandle, state: &AppState, id: &str, key: &str) {
+    let id = id.to_string();
+    notifications::notify(
+        app,
+        state,
+        Notification::new(
+            "Waiting for Wi-Fi",
+            format!("{} is large and will transfer once you're off the metered connection.", key),
+        )
+        .action("override", "Transfer Anyway"),
+        Some(Box::new(move |app: &AppHandle, action: &str| {
+            if action == "override" {
+                allow(&app.state::<Arc<AppState>>(), &id);
+            }
+        })),
+    );
+}
+
+fn allow(state: &AppState, id: &str) {
+    log::info!("Transfer {} allowed on a metered connection", id);
+    state.network.overrides.lock().unwrap().insert(id.to_string());
+}
+
+// Called before a file of transfer `id` starts. Holds files at or above the threshold
+// while the connection is metered, until it isn't or the user overrides the hold.
+// Returns false if the transfer is cancelled or the app shuts down while waiting.
+pub async fn wait_for_wifi(
+    app: &AppHandle,
+    state: &AppState,
+    id: &str,
+    key: &str,
+    size: u64,
+    cancel: &AtomicBool,
+) -> bool {
+    if !state.network.must_wait(id, size) {
+        return true;
+    }
+    log::info!("Holding {} of transfer {} until the connection is unmetered", key, id);
+    transfers::wait_file(state, id, key);
+    emit_to_main(
+        app,
+        "waiting-for-wifi",
+        WaitingEvent {
+            id: id.to_string(),
+            key: key.to_string(),
+            size,
+        },
+    );
+    if state.network.notified.lock().unwrap().insert(id.to_string()) {
+        notify_waiting(app, state, id, key);
+    }
+
+    while state.network.must_wait(id, size) {
+        if cancel.load(Ordering::SeqCst) || state.shutdown.load(Ordering::SeqCst) {
+            return false;
+        }
+        tokio::time::sleep(WAIT_POLL).await;
+    }
+    true
+}
+
+#[tauri::command]
+pub fn get_wifi_only(state: tauri::State<Arc<AppState>>) -> WifiOnlySettings {
+    *state.network.settings.lock().unwrap()
+}
+
+#[tauri::command]
+pub fn configure_wifi_only(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    enabled: Option<bool>,
+    threshold_bytes: Option<u64>,
+) -> Result<WifiOnlySettings, AppError> {
+    let mut settings = *state.network.settings.lock().unwrap();
+    settings.enabled = enabled.unwrap_or(settings.enabled);
+    settings.threshold_bytes = threshold_bytes.unwrap_or(settings.threshold_bytes);
+
+    store::save_json(&store::data_file(&app, SETTINGS_FILE)?, &settings)?;
+    let was_enabled = std::mem::replace(&mut *state.network.settings.lock().unwrap(), settings).enabled;
+    if settings.enabled && !was_enabled {
+        // Start holding files right away instead of at the monitor's next sample
+        let state = Arc::clone(&state);
+        tauri::async_runtime::spawn(async move {
+            sample(&app, &state).await;
+        });
+    }
+    Ok(settings)
+}
+
+// Let a transfer held for Wi-Fi continue on the current connection
+#[tauri::command]
+pub fn allow_metered_transfer(state: tauri::State<Arc<AppState>>, id: String) {
+    allow(&state, &id);
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::{emit_to_main, file_manager, native_notifications, AppState};
+
+// Called with the id of the action the user picked
+pub type ActionHandler = Box<dyn FnOnce(&AppHandle, &str) + Send>;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NotificationAction {
+    pub id: String,
+    pub label: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Notification {
+    pub id: String,
+    pub title: String,
+    pub body: String,
+    pub actions: Vec<NotificationAction>,
+}
+
+impl Notification {
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.into(),
+            body: body.into(),
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn action(mut self, id: &str, label: &str) -> Self {
+        self.actions.push(NotificationAction {
+            id: id.to_string(),
+            label: label.to_string(),
+        });
+        self
+    }
+}
+
+// Shell-originated notifications, shown by the frontend as toasts and, while the app is
+// in the background, by the OS as well. Actions the user picks on either come back
+// through `act` and run the registered handler once.
+pub struct Notifications {
+    handlers: Mutex<HashMap<String, ActionHandler>>,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self {
+            handlers: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+pub fn notify(app: &AppHandle, state: &AppState, notification: Notification, handler: Option<ActionHandler>) {
+    if let Some(handler) = handler {
+        state
+            .notifications
+            .handlers
+            .lock()
+            .unwrap()
+            .insert(notification.id.clone(), handler);
+    }
+    // During quiet hours it is kept for the summary shown once they end
+    if let Some(notification) = state.quiet_hours.hold(notification) {
+        show(app, notification);
+    }
+}
+
+fn show(app: &AppHandle, notification: Notification) {
+    if native_notifications::in_background(app) {
+        let id = notification.id.clone();
+        let handle = app.clone();
+        native_notifications::show(
+            app,
+            &notification,
+            Box::new(move |action| {
+                let state = handle.state::<Arc<AppState>>();
+                if act(&handle, &state, &id, &action).is_ok() {
+                    // Answered elsewhere; the toast has nothing left to offer
+                    emit_to_main(&handle, "notification-resolved", id);
+                }
+            }),
+        );
+    }
+    emit_to_main(app, "notification", notification);
+}
+
+// Run the handler of a notification's action, unless one of its copies was acted on already
+pub fn act(app: &AppHandle, state: &AppState, id: &str, action: &str) -> Result<(), String> {
+    let handler = state
+        .notifications
+        .handlers
+        .lock()
+        .unwrap()
+        .remove(id)
+        .ok_or_else(|| format!("Notification {} has no pending actions", id))?;
+    handler(app, action);
+    Ok(())
+}
+
+// Like notify, for a question about something the user just did: it is shown even
+// during quiet hours, since a command is waiting for the answer
+pub fn ask(app: &AppHandle, state: &AppState, notification: Notification, handler: ActionHandler) {
+    state
+        .notifications
+        .handlers
+        .lock()
+        .unwrap()
+        .insert(notification.id.clone(), handler);
+    show(app, notification);
+}
+
+#[tauri::command]
+pub fn notification_action(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    id: String,
+    action: String,
+) -> Result<(), AppError> {
+    Ok(act(&app, &state, &id, &action)?)
+}
+
+// Drop the handler once the frontend dismisses a notification without acting on it
+#[tauri::command]
+pub fn dismiss_notification(state: tauri::State<Arc<AppState>>, id: String) {
+    state.notifications.handlers.lock().unwrap().remove(&id);
+}
+
+// Called by the frontend once it saved a download, to offer opening the file or showing
+// it in the file manager
+#[tauri::command]
+pub fn notify_download(app: AppHandle, state: tauri::State<Arc<AppState>>, path: String) -> Result<(), AppError> {
+    let path = PathBuf::from(path);
+    if !path.is_file() {
+        return Err(AppError::invalid(format!("{} is not a file", path.display())));
+    }
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    notify(
+        &app,
+        &state,
+        Notification::new("Download complete", format!("{} was saved.", name))
+            .action("open", "Open")
+            .action("reveal", "Show in Folder"),
+        Some(Box::new(move |_: &AppHandle, action: &str| {
+            let result = match action {
+                "open" => file_manager::open(&path),
+                "reveal" => file_manager::reveal(&path),
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                log::warn!("{}", e);
+            }
+        })),
+    );
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::{emit_to_main, jobs, AppState};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OperationInfo {
+    pub id: String,
+    // Subsystem that owns the operation, e.g. "import" or "export"
+    pub kind: String,
+    pub label: String,
+    // Unix milliseconds
+    pub started_at: i64,
+}
+
+#[derive(Clone, Serialize)]
+struct OperationFinished {
+    id: String,
+    cancelled: bool,
+}
+
+struct Running {
+    info: OperationInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+// Registry of every long-running shell operation, so the frontend can list them and
+// cancel any of them by id through one command. Subsystems keep checking their own
+// cancel flag; registering just makes that flag reachable.
+pub struct Operations {
+    running: Mutex<HashMap<String, Running>>,
+}
+
+impl Operations {
+    pub fn new() -> Self {
+        Self {
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+// Unregisters the operation when dropped, however the operation ends
+pub struct OperationGuard {
+    app: AppHandle,
+    id: String,
+    cancel: Arc<AtomicBool>,
+}
+
+impl OperationGuard {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn cancel_flag(&self) -> &Arc<AtomicBool> {
+        &self.cancel
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        let state = self.app.state::<Arc<AppState>>();
+        let finished = state.operations.running.lock().unwrap().remove(&self.id);
+        let cancelled = self.cancel.load(Ordering::SeqCst);
+        if let Some(finished) = finished {
+            jobs::operation_finished(&self.app, &finished.info, cancelled);
+        }
+        emit_to_main(
+            &self.app,
+            "operation-finished",
+            OperationFinished {
+                id: self.id.clone(),
+                cancelled,
+            },
+        );
+    }
+}
+
+// Register an operation under `id` (a job id, or one the frontend picked so it can
+// cancel a command it is still awaiting); a fresh id is generated when none is given
+pub fn begin(
+    app: &AppHandle,
+    id: Option<String>,
+    kind: &str,
+    label: impl Into<String>,
+    cancel: Arc<AtomicBool>,
+) -> OperationGuard {
+    let info = OperationInfo {
+        id: id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        kind: kind.to_string(),
+        label: label.into(),
+        started_at: chrono::Utc::now().timestamp_millis(),
+    };
+    let state = app.state::<Arc<AppState>>();
+    state.operations.running.lock().unwrap().insert(
+        info.id.clone(),
+        Running {
+            info: info.clone(),
+            cancel: Arc::clone(&cancel),
+        },
+    );
+    emit_to_main(app, "operation-started", info.clone());
+    jobs::operation_started(app, &info);
+
+    OperationGuard {
+        app: app.clone(),
+        id: info.id,
+        cancel,
+    }
+}
+
+pub fn list(state: &AppState) -> Vec<OperationInfo> {
+    let mut operations: Vec<OperationInfo> = state
+        .operations
+        .running
+        .lock()
+        .unwrap()
+        .values()
+        .map(|running| running.info.clone())
+        .collect();
+    operations.sort_by_key(|info| info.started_at);
+    operations
+}
+
+pub fn cancel(state: &AppState, id: &str) -> Result<(), String> {
+    match state.operations.running.lock().unwrap().get(id) {
+        Some(running) => {
+            log::info!("Cancelling {} operation {}", running.info.kind, id);
+            running.cancel.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        None => Err(format!("Operation {} is not running", id)),
+    }
+}
+
+#[tauri::command]
+pub fn list_operations(state: tauri::State<Arc<AppState>>) -> Vec<OperationInfo> {
+    list(&state)
+}
+
+#[tauri::command]
+pub fn cancel_operation(state: tauri::State<Arc<AppState>>, id: String) -> Result<(), AppError> {
+    Ok(cancel(&state, &id)?)
+}
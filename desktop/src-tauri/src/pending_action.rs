@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::notifications::{self, Notification};
+use crate::{emit_to_main, AppState};
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// What carries out a held action, or what runs when it is taken back; called with the
+// action's id
+pub type Finish = Box<dyn FnOnce(AppHandle, String) -> BoxFuture + Send>;
+
+// A Finish from an async closure
+pub fn callback<F, Fut>(f: F) -> Finish
+where
+    F: FnOnce(AppHandle, String) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    Box::new(move |app: AppHandle, id: String| -> BoxFuture { Box::pin(f(app, id)) })
+}
+
+// Destructive actions that wait out a hold before they reach the backend
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    Delete,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PendingAction {
+    pub id: String,
+    pub kind: ActionKind,
+    // What the undo notification says
+    pub title: String,
+    pub body: String,
+    // The undo notification, for the frontend to dismiss once the action is finished
+    pub notification_id: String,
+    // Unix milliseconds; the action is committed then unless reverted first
+    pub expires_at: i64,
+}
+
+// Sent as `action-finished` once a held action was committed or reverted
+#[derive(Clone, Debug, Serialize)]
+pub struct ActionFinished {
+    pub id: String,
+    pub kind: ActionKind,
+    pub notification_id: String,
+    pub committed: bool,
+}
+
+struct Held {
+    action: PendingAction,
+    commit: Finish,
+    revert: Option<Finish>,
+}
+
+// Actions held for an undo window, committed when it runs out. Ones still held when
+// the app quits don't happen.
+pub struct PendingActions {
+    held: Mutex<HashMap<String, Held>>,
+}
+
+impl PendingActions {
+    pub fn new() -> Self {
+        Self {
+            held: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+// Hold an action for `hold`, showing a notification that can undo it until then.
+// `commit` runs when the hold runs out or commit_action is called; `revert`, if any,
+// when it is taken back. Returns the action's id.
+pub fn stage(
+    app: &AppHandle,
+    state: &AppState,
+    kind: ActionKind,
+    notification: Notification,
+    hold: Duration,
+    commit: Finish,
+    revert: Option<Finish>,
+) -> String {
+    let id = uuid::Uuid::new_v4().to_string();
+    let action = PendingAction {
+        id: id.clone(),
+        kind,
+        title: notification.title.clone(),
+        body: notification.body.clone(),
+        notification_id: notification.id.clone(),
+        expires_at: chrono::Utc::now().timestamp_millis() + hold.as_millis() as i64,
+    };
+    state
+        .pending_actions
+        .held
+        .lock()
+        .unwrap()
+        .insert(id.clone(), Held { action, commit, revert });
+
+    let pending = id.clone();
+    // A reply to what the user just did, so it shows even during quiet hours
+    notifications::ask(
+        app,
+        state,
+        notification.action("undo", "Undo"),
+        Box::new(move |app: &AppHandle, action: &str| {
+            if action == "undo" {
+                let _ = finish(app, &pending, false);
+            }
+        }),
+    );
+
+    let (handle, pending) = (app.clone(), id.clone());
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(hold).await;
+        // Already committed or reverted otherwise
+        let _ = finish(&handle, &pending, true);
+    });
+    id
+}
+
+// Commit or revert a held action; an error when it already was
+fn finish(app: &AppHandle, id: &str, commit: bool) -> Result<(), AppError> {
+    let state = app.state::<Arc<AppState>>();
+    let held = state
+        .pending_actions
+        .held
+        .lock()
+        .unwrap()
+        .remove(id)
+        .ok_or_else(|| AppError::invalid("The action was already carried out or taken back"))?;
+    let finished = ActionFinished {
+        id: id.to_string(),
+        kind: held.action.kind,
+        notification_id: held.action.notification_id.clone(),
+        committed: commit,
+    };
+    let run = if commit { Some(held.commit) } else { held.revert };
+    let (app, id) = (app.clone(), id.to_string());
+    tauri::async_runtime::spawn(async move {
+        if let Some(run) = run {
+            run(app.clone(), id).await;
+        }
+        emit_to_main(&app, "action-finished", finished);
+    });
+    Ok(())
+}
+
+// Actions waiting out their undo window, soonest first
+#[tauri::command]
+pub fn list_pending_actions(state: tauri::State<Arc<AppState>>) -> Vec<PendingAction> {
+    let mut actions: Vec<PendingAction> = state
+        .pending_actions
+        .held
+        .lock()
+        .unwrap()
+        .values()
+        .map(|held| held.action.clone())
+        .collect();
+    actions.sort_by_key(|action| action.expires_at);
+    actions
+}
+
+// Carry out a held action now instead of at the end of its undo window
+#[tauri::command]
+pub fn commit_action(app: AppHandle, id: String) -> Result<(), AppError> {
+    finish(&app, &id, true)
+}
+
+// Take back a held action before it reaches the backend
+#[tauri::command]
+pub fn revert_action(app: AppHandle, id: String) -> Result<(), AppError> {
+    finish(&app, &id, false)
+}
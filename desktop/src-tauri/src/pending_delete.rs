@@ -0,0 +1,99 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::notifications::{self, Notification};
+use crate::pending_action::{self, ActionKind};
+use crate::{backend, emit_to_main, selection, AppState};
+
+// How long a delete waits for an undo before it reaches the backend
+const UNDO_WINDOW: Duration = Duration::from_secs(10);
+
+// Sent as `delete-finished` once a delete went through or was undone
+#[derive(Clone, Debug, Serialize)]
+pub struct DeleteOutcome {
+    pub id: String,
+    pub bucket: String,
+    pub undone: bool,
+    pub deleted: Vec<String>,
+    // Keys that couldn't be deleted, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+fn file_name(key: &str) -> &str {
+    key.rsplit('/').next().unwrap_or(key)
+}
+
+async fn run(app: &AppHandle, mut outcome: DeleteOutcome, keys: Vec<String>) {
+    let state = Arc::clone(&app.state::<Arc<AppState>>());
+    let base_url = state.base_url();
+    let client = backend::shared_client(&base_url);
+    for key in keys {
+        match backend::delete_object(&client, &base_url, &outcome.bucket, &key).await {
+            Ok(()) => outcome.deleted.push(key),
+            Err(e) => {
+                log::warn!("{}", e);
+                outcome.failed.push((key, e));
+            }
+        }
+    }
+    if !outcome.failed.is_empty() {
+        let total = outcome.failed.len() + outcome.deleted.len();
+        let body = format!("{} of {} files are still in {}.", outcome.failed.len(), total, outcome.bucket);
+        notifications::notify(app, &state, Notification::new("Some files weren't deleted", body), None);
+    }
+    emit_to_main(app, "delete-finished", outcome);
+}
+
+// Delete files, given as `keys` or a `selection` token, held for UNDO_WINDOW with a
+// notification that can undo it until then. Returns the id of the held action, for
+// undo_delete, revert_action and the `delete-finished` event.
+#[tauri::command]
+pub fn delete_with_undo(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    bucket: String,
+    keys: Option<Vec<String>>,
+    selection: Option<String>,
+) -> Result<String, AppError> {
+    let keys = selection::resolve(&state, &bucket, keys, selection)?;
+    if keys.is_empty() {
+        return Err(AppError::invalid("Nothing to delete"));
+    }
+    let title = match keys.as_slice() {
+        [key] => format!("Deleted {}", file_name(key)),
+        keys => format!("Deleted {} files", keys.len()),
+    };
+    let notification = Notification::new(title, format!("From {}.", bucket));
+    let outcome = move |id: String, undone: bool| DeleteOutcome {
+        id,
+        bucket,
+        undone,
+        deleted: Vec::new(),
+        failed: Vec::new(),
+    };
+    let (commit_outcome, revert_outcome) = (outcome.clone(), outcome);
+    let count = keys.len();
+    Ok(pending_action::stage(
+        &app,
+        &state,
+        ActionKind::Delete,
+        notification,
+        UNDO_WINDOW,
+        pending_action::callback(move |app, id| async move { run(&app, commit_outcome(id, false), keys).await }),
+        Some(pending_action::callback(move |app, id| async move {
+            let outcome = revert_outcome(id, true);
+            log::info!("Delete of {} files from {} undone", count, outcome.bucket);
+            emit_to_main(&app, "delete-finished", outcome);
+        })),
+    ))
+}
+
+// Keep the files of a delete_with_undo that hasn't gone through yet
+#[tauri::command]
+pub fn undo_delete(app: AppHandle, id: String) -> Result<(), AppError> {
+    pending_action::revert_action(app, id).map_err(|_| AppError::invalid("The files were already deleted"))
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::notifications::{self, Notification};
+use crate::{store, AppState};
+
+const SETTINGS_FILE: &str = "permissions.json";
+
+// How long a prompt waits for an answer before the command gives up
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+// Powerful things the webview can ask the shell to do, each allowed once per profile.
+// The app has no post-download scripts to gate; the sidecar updater is the only thing
+// that runs downloaded code, under RunPrograms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    // Reading local files and folders by path, e.g. an import source or a file to share
+    ReadLocalFiles,
+    // Running a program other than the bundled backend, e.g. an installed sidecar update
+    RunPrograms,
+    // Starting another backend server, e.g. for a second profile
+    StartServers,
+}
+
+impl Capability {
+    fn describe(self) -> &'static str {
+        match self {
+            Capability::ReadLocalFiles => "read files on this computer",
+            Capability::RunPrograms => "run programs it downloaded",
+            Capability::StartServers => "start additional backend servers",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Decision {
+    allowed: bool,
+    // Unix seconds
+    decided_at: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PermissionGrant {
+    pub profile: String,
+    pub capability: Capability,
+    pub allowed: bool,
+    pub decided_at: i64,
+}
+
+// What the user decided for each capability, by profile. Undecided capabilities are
+// asked about the first time they are needed.
+pub struct Permissions {
+    decisions: Mutex<BTreeMap<String, BTreeMap<Capability, Decision>>>,
+}
+
+impl Permissions {
+    pub fn new() -> Self {
+        Self {
+            decisions: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn decision(&self, profile: &str, capability: Capability) -> Option<bool> {
+        let decisions = self.decisions.lock().unwrap();
+        decisions.get(profile)?.get(&capability).map(|d| d.allowed)
+    }
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => *state.permissions.decisions.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load permissions: {}", e),
+    }
+}
+
+fn save(app: &AppHandle, state: &AppState) -> Result<(), String> {
+    let decisions = state.permissions.decisions.lock().unwrap().clone();
+    store::save_json(&store::data_file(app, SETTINGS_FILE)?, &decisions)
+}
+
+fn decide(app: &AppHandle, state: &AppState, profile: &str, capability: Capability, allowed: bool) {
+    let decided_at = chrono::Utc::now().timestamp();
+    state
+        .permissions
+        .decisions
+        .lock()
+        .unwrap()
+        .entry(profile.to_string())
+        .or_default()
+        .insert(capability, Decision { allowed, decided_at });
+    if let Err(e) = save(app, state) {
+        log::error!("Failed to save permissions: {}", e);
+    }
+}
+
+// Succeeds when the active profile may use `capability`, asking the user the first
+// time. `detail` says what it is needed for, e.g. the path about to be read.
+// Dismissing the prompt refuses this once without remembering it.
+pub async fn ensure(app: &AppHandle, state: &AppState, capability: Capability, detail: &str) -> Result<(), String> {
+    let profile = state.active_profile.lock().unwrap().clone();
+    let refused = || format!("Not allowed to {}", capability.describe());
+    match state.permissions.decision(&profile, capability) {
+        Some(true) => return Ok(()),
+        Some(false) => return Err(format!("{}; this can be changed in Settings", refused())),
+        None => {}
+    }
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let notification = Notification::new(
+        format!("Allow BB Stream to {}?", capability.describe()),
+        format!("Needed for {}. Applies to profile {} from now on.", detail, profile),
+    )
+    .action("allow", "Allow")
+    .action("deny", "Don't Allow");
+    notifications::ask(
+        app,
+        state,
+        notification,
+        Box::new(move |_: &AppHandle, action: &str| {
+            let _ = tx.send(action == "allow");
+        }),
+    );
+
+    let allowed = match tokio::time::timeout(PROMPT_TIMEOUT, rx).await {
+        Ok(Ok(allowed)) => allowed,
+        // Dismissed or unanswered
+        _ => return Err(refused()),
+    };
+    log::info!("Profile {} {} to {}", profile, if allowed { "allowed" } else { "refused" }, capability.describe());
+    decide(app, state, &profile, capability, allowed);
+    if allowed {
+        Ok(())
+    } else {
+        Err(refused())
+    }
+}
+
+// Every remembered decision, or one profile's, for review in Settings
+#[tauri::command]
+pub fn list_permissions(state: tauri::State<Arc<AppState>>, profile: Option<String>) -> Vec<PermissionGrant> {
+    let decisions = state.permissions.decisions.lock().unwrap();
+    decisions
+        .iter()
+        .filter(|(name, _)| profile.as_ref().map_or(true, |p| p == *name))
+        .flat_map(|(name, capabilities)| {
+            capabilities.iter().map(move |(capability, decision)| PermissionGrant {
+                profile: name.clone(),
+                capability: *capability,
+                allowed: decision.allowed,
+                decided_at: decision.decided_at,
+            })
+        })
+        .collect()
+}
+
+// Forget a decision so the user is asked again next time
+#[tauri::command]
+pub fn revoke_permission(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    profile: String,
+    capability: Capability,
+) -> Result<(), AppError> {
+    {
+        let mut decisions = state.permissions.decisions.lock().unwrap();
+        if let Some(capabilities) = decisions.get_mut(&profile) {
+            capabilities.remove(&capability);
+            if capabilities.is_empty() {
+                decisions.remove(&profile);
+            }
+        }
+    }
+    Ok(save(&app, &state)?)
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::AppHandle;
+
+use crate::{profiles, store, DEFAULT_PROFILE};
+
+// The default profile's record; other profiles use sidecar-<profile>.json
+const PID_FILE: &str = "sidecar.json";
+
+// How long to wait for a stale sidecar to exit after killing it
+const KILL_TIMEOUT: Duration = Duration::from_secs(3);
+
+// The running sidecar, recorded so the next launch can clean it up if this one
+// crashes without stopping it. Start time guards against the pid being reused.
+#[derive(Default, Serialize, Deserialize)]
+struct SidecarRecord {
+    pid: u32,
+    start_time: u64,
+}
+
+fn refresh(system: &mut System, pid: Pid) {
+    system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, ProcessRefreshKind::nothing());
+}
+
+fn pid_file(profile: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        PID_FILE.to_string()
+    } else {
+        format!("sidecar-{}.json", profiles::slug(profile))
+    }
+}
+
+pub fn record(app: &AppHandle, profile: &str, pid: u32) {
+    let mut system = System::new();
+    refresh(&mut system, Pid::from_u32(pid));
+    let start_time = system.process(Pid::from_u32(pid)).map(|p| p.start_time()).unwrap_or(0);
+
+    let result = store::data_file(app, &pid_file(profile))
+        .and_then(|path| store::save_json(&path, &SidecarRecord { pid, start_time }));
+    if let Err(e) = result {
+        log::warn!("Failed to record sidecar pid: {}", e);
+    }
+}
+
+// Forget the sidecar after stopping it cleanly
+pub fn clear(app: &AppHandle, profile: &str) {
+    if let Ok(path) = store::data_file(app, &pid_file(profile)) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+// Terminate sidecars of any profile left running by a previous run that crashed
+pub fn kill_stale(app: &AppHandle) {
+    let Ok(path) = store::data_file(app, PID_FILE) else {
+        return;
+    };
+    let Some(dir) = path.parent() else {
+        return;
+    };
+    let Ok(listing) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in listing.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == PID_FILE || (name.starts_with("sidecar-") && name.ends_with(".json")) {
+            kill_recorded(&entry.path());
+        }
+    }
+}
+
+fn kill_recorded(path: &std::path::Path) {
+    let record: Option<SidecarRecord> = store::load_json(path);
+    let _ = std::fs::remove_file(path);
+    let Some(record) = record else {
+        return;
+    };
+
+    let pid = Pid::from_u32(record.pid);
+    let mut system = System::new();
+    refresh(&mut system, pid);
+    let Some(process) = system.process(pid) else {
+        return;
+    };
+    let is_sidecar = process.name().to_string_lossy().contains("bb-stream");
+    if !is_sidecar || process.start_time() != record.start_time {
+        return;
+    }
+
+    log::warn!("Stopping bb-stream sidecar (pid {}) left over from a previous run", record.pid);
+    if !kill_tree(record.pid) && !process.kill() {
+        log::error!("Failed to stop stale sidecar (pid {})", record.pid);
+        return;
+    }
+    let deadline = std::time::Instant::now() + KILL_TIMEOUT;
+    while std::time::Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+        refresh(&mut system, pid);
+        if system.process(pid).is_none() {
+            return;
+        }
+    }
+    log::warn!("Stale sidecar (pid {}) is still running", record.pid);
+}
+
+// Kill a sidecar and the helpers it started. Sidecars lead their own process group;
+// on Windows the Job Object went away with the crashed app, so the tree is walked
+// by parent pid instead.
+#[cfg(unix)]
+fn kill_tree(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", pid)])
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn kill_tree(pid: u32) -> bool {
+    std::process::Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid.to_string()])
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn kill_tree(_pid: u32) -> bool {
+    false
+}
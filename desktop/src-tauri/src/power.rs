@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use tauri::AppHandle;
+
+use crate::{emit_to_main, AppState};
+
+// How long after waking failed health checks don't count: the network and the
+// sidecar's connections need a moment to come back
+const WAKE_GRACE: Duration = Duration::from_secs(30);
+
+// How often the clock is checked for a gap left by sleep
+const TICK: Duration = Duration::from_secs(5);
+
+// A tick arriving this much late means the machine was asleep
+const SLEEP_GAP: Duration = Duration::from_secs(15);
+
+// Whether the machine is asleep or just woke up, so supervision doesn't mistake a
+// frozen sidecar for a hung one
+pub struct Power {
+    asleep: AtomicBool,
+    woke_at: Mutex<Option<Instant>>,
+    // Process relaying the OS's sleep notifications, killed as the app quits
+    monitor: Mutex<Option<std::process::Child>>,
+}
+
+impl Power {
+    pub fn new() -> Self {
+        Self {
+            asleep: AtomicBool::new(false),
+            woke_at: Mutex::new(None),
+            monitor: Mutex::new(None),
+        }
+    }
+
+    // Between the OS announcing sleep and the machine waking again
+    pub fn is_asleep(&self) -> bool {
+        self.asleep.load(Ordering::SeqCst)
+    }
+
+    // Asleep, or awake for less than the grace period
+    pub fn is_settling(&self) -> bool {
+        self.is_asleep() || self.woke_at.lock().unwrap().is_some_and(|at| at.elapsed() < WAKE_GRACE)
+    }
+}
+
+fn sleeping(state: &AppState) {
+    if !state.power.asleep.swap(true, Ordering::SeqCst) {
+        log::info!("System is going to sleep; pausing backend supervision");
+    }
+}
+
+fn woke(app: &AppHandle, state: &AppState) {
+    state.power.asleep.store(false, Ordering::SeqCst);
+    let previous = state.power.woke_at.lock().unwrap().replace(Instant::now());
+    // The OS notification and the clock gap usually both report the same wake
+    if previous.is_some_and(|at| at.elapsed() < WAKE_GRACE) {
+        return;
+    }
+    log::info!("System woke up; ignoring backend failures for {}s", WAKE_GRACE.as_secs());
+    emit_to_main(app, "system-resumed", ());
+}
+
+// logind announces sleep and wake with PrepareForSleep(true) and (false)
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn watch_notifications(app: AppHandle, state: Arc<AppState>) {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+
+    std::thread::spawn(move || {
+        let child = Command::new("gdbus")
+            .args([
+                "monitor",
+                "--system",
+                "--dest",
+                "org.freedesktop.login1",
+                "--object-path",
+                "/org/freedesktop/login1",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                log::info!("Not watching for system sleep (gdbus unavailable: {}); relying on clock gaps", e);
+                return;
+            }
+        };
+        let Some(stdout) = child.stdout.take() else {
+            return;
+        };
+        *state.power.monitor.lock().unwrap() = Some(child);
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if state.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            if !line.contains("PrepareForSleep") {
+                continue;
+            }
+            if line.contains("(true,)") {
+                sleeping(&state);
+            } else if line.contains("(false,)") {
+                woke(&app, &state);
+            }
+        }
+    });
+}
+
+// macOS and Windows only deliver sleep notifications to a native run loop or
+// window; the clock gap below catches their wakes instead
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn watch_notifications(_app: AppHandle, _state: Arc<AppState>) {}
+
+// Watch for the system sleeping and waking: the OS's own notifications where it
+// has a command-line view of them, and a gap in the wall clock everywhere, which
+// also catches a wake whose notification was missed
+pub fn spawn_monitor(app: AppHandle, state: Arc<AppState>) {
+    watch_notifications(app.clone(), Arc::clone(&state));
+
+    std::thread::spawn(move || loop {
+        let before = SystemTime::now();
+        std::thread::sleep(TICK);
+        if state.shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let elapsed = SystemTime::now().duration_since(before).unwrap_or_default();
+        if elapsed > TICK + SLEEP_GAP {
+            log::info!("Clock jumped {}s; the system was probably asleep", elapsed.as_secs());
+            woke(&app, &state);
+        }
+    });
+}
+
+pub fn stop(state: &AppState) {
+    if let Some(mut child) = state.power.monitor.lock().unwrap().take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
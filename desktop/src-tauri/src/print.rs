@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
+use std::sync::Arc;
+
+use tauri::AppHandle;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::AppError;
+use crate::scratch::{self, Area};
+use crate::{backend, AppState};
+
+const PRINTABLE_EXTENSIONS: &[&str] = &["pdf", "png", "jpg", "jpeg", "gif", "tif", "tiff", "bmp", "webp"];
+
+// Download an image or PDF (reusing an earlier copy) and hand it to the OS print dialog
+#[tauri::command]
+pub async fn print_file(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    bucket: String,
+    key: String,
+) -> Result<(), AppError> {
+    let extension = Path::new(&key)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if !PRINTABLE_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(AppError::Unsupported {
+            message: format!("Only images and PDFs can be printed, not .{} files", extension),
+        });
+    }
+
+    let path = cached_copy_path(&app, &bucket, &key)?;
+    if !path.exists() {
+        let base_url = state.base_url();
+        download_to(&base_url, &bucket, &key, &path).await?;
+    }
+
+    log::info!("Printing {}/{}", bucket, key);
+    // Handing the file over can wait on the print handler to start
+    tauri::async_runtime::spawn_blocking(move || send_to_printer(&path))
+        .await
+        .map_err(|e| format!("Failed to print: {}", e))??;
+    Ok(())
+}
+
+// Where a downloaded copy of an object is kept for printing
+pub fn cached_copy_path(app: &AppHandle, bucket: &str, key: &str) -> Result<PathBuf, String> {
+    if key.split('/').any(|segment| segment == "..") || bucket.contains(['/', '\\']) {
+        return Err(format!("Invalid object path {}/{}", bucket, key));
+    }
+    Ok(scratch::dir(app, Area::Print)?.join(bucket).join(key))
+}
+
+async fn download_to(base_url: &str, bucket: &str, key: &str, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let mut resp = backend::shared_client(base_url)
+        .get(backend::download_url(base_url, bucket, key)?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", key, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Downloading {} returned status: {}", key, resp.status()));
+    }
+
+    // Write next to the target and rename so a failed download is never reused
+    let partial = PathBuf::from(format!("{}.part", path.display()));
+    let mut file = tokio::fs::File::create(&partial)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+    while let Some(chunk) = resp.chunk().await.map_err(|e| format!("Failed to download {}: {}", key, e))? {
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    tokio::fs::rename(&partial, path).await.map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn send_to_printer(path: &Path) -> Result<(), String> {
+    let script = format!(
+        "tell application \"Preview\" to print POSIX file {:?} with print dialog",
+        path.display().to_string()
+    );
+    Command::new("osascript")
+        .args(["-e", &script])
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open print dialog: {}", e))
+}
+
+#[cfg(target_os = "windows")]
+fn send_to_printer(path: &Path) -> Result<(), String> {
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::UI::Shell::ShellExecuteW;
+    use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+    // The "print" verb runs the file type's registered print handler
+    let instance = unsafe {
+        ShellExecuteW(
+            None,
+            &HSTRING::from("print"),
+            &HSTRING::from(path),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+    // Anything up to 32 is an error code
+    let code = instance.0 as isize;
+    if code <= 32 {
+        return Err(format!("Failed to print {} (error {})", path.display(), code));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn send_to_printer(path: &Path) -> Result<(), String> {
+    // CUPS has no standalone dialog; print to the default printer
+    Command::new("lp")
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to print (is CUPS installed?): {}", e))
+}
@@ -0,0 +1,124 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::permissions::{self, Capability};
+use crate::{
+    emit_to_main, spawn_restart_handler, start_if_stopped, AppState, BackendStatus, Sidecar, DEFAULT_PROFILE,
+};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    // Suffix of this profile's `backend-status-{event}` events
+    pub event: String,
+    pub active: bool,
+    pub running: bool,
+    pub healthy: bool,
+    pub port: u16,
+}
+
+// Profile names end up in event and file names, which allow fewer characters
+pub fn slug(profile: &str) -> String {
+    profile
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+fn info(state: &AppState, name: &str) -> ProfileInfo {
+    let sidecar = state.sidecars.lock().unwrap().get(name).cloned();
+    ProfileInfo {
+        name: name.to_string(),
+        event: slug(name),
+        active: *state.active_profile.lock().unwrap() == name,
+        running: sidecar.as_ref().is_some_and(|s| s.is_running()),
+        healthy: sidecar.as_ref().is_some_and(|s| s.is_healthy.load(Ordering::SeqCst)),
+        port: sidecar.map_or(0, |s| s.port.load(Ordering::SeqCst)),
+    }
+}
+
+// The profile's sidecar, set up with its own restart handler on first use
+fn sidecar(app: &AppHandle, state: &Arc<AppState>, name: &str) -> Result<Arc<Sidecar>, String> {
+    if let Some(sidecar) = state.sidecars.lock().unwrap().get(name) {
+        return Ok(Arc::clone(sidecar));
+    }
+    let env = state.accounts.sidecar_env(name)?;
+    let sidecar = Arc::clone(
+        state
+            .sidecars
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(Sidecar::new(name, env))),
+    );
+    if sidecar.restart_tx.lock().unwrap().is_none() {
+        spawn_restart_handler(app.clone(), Arc::clone(state), Arc::clone(&sidecar));
+    }
+    Ok(sidecar)
+}
+
+// Running a second backend server is the user's call; the default profile's is the
+// app's own
+async fn allow_start(app: &AppHandle, state: &AppState, sidecar: &Sidecar) -> Result<(), String> {
+    if sidecar.profile == DEFAULT_PROFILE || sidecar.is_running() || sidecar.external_url().is_some() {
+        return Ok(());
+    }
+    let detail = format!("profile {}", sidecar.profile);
+    permissions::ensure(app, state, Capability::StartServers, &detail).await
+}
+
+// The default profile plus every account a sidecar can serve
+#[tauri::command]
+pub fn list_profiles(state: tauri::State<Arc<AppState>>) -> Vec<ProfileInfo> {
+    std::iter::once(DEFAULT_PROFILE.to_string())
+        .chain(state.accounts.sidecar_accounts())
+        .map(|name| info(&state, &name))
+        .collect()
+}
+
+// Start a profile's sidecar alongside the others without making it active
+#[tauri::command]
+pub async fn start_profile(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    name: String,
+) -> Result<ProfileInfo, AppError> {
+    let sidecar = sidecar(&app, &state, &name)?;
+    allow_start(&app, &state, &sidecar).await?;
+    start_if_stopped(&app, &state, &sidecar)?;
+    Ok(info(&state, &name))
+}
+
+// Make a profile active, starting its sidecar if needed. Commands and the
+// `backend-status` event follow the active profile; the others keep running.
+#[tauri::command]
+pub async fn switch_profile(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    name: String,
+) -> Result<ProfileInfo, AppError> {
+    let sidecar = sidecar(&app, &state, &name)?;
+    allow_start(&app, &state, &sidecar).await?;
+    start_if_stopped(&app, &state, &sidecar)?;
+
+    let previous = std::mem::replace(&mut *state.active_profile.lock().unwrap(), name.clone());
+    if previous != name {
+        log::info!("Switched from profile {} to {}", previous, name);
+        state.listings.clear_memory();
+        emit_to_main(&app, "profile-switched", name.clone());
+        // Windows listening to `backend-status` learn where the new profile stands
+        let status = if let Some(message) = state.auth.rejection(&name) {
+            BackendStatus::AuthRequired { message }
+        } else if sidecar.is_healthy.load(Ordering::SeqCst) {
+            BackendStatus::Healthy
+        } else {
+            BackendStatus::Starting
+        };
+        emit_to_main(&app, "backend-status", status);
+    }
+    Ok(info(&state, &name))
+}
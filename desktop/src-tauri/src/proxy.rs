@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Method, Url};
+use tauri::AppHandle;
+
+use crate::backend::{self, RequestError};
+use crate::error::AppError;
+use crate::{access_stats, latency, quit, read_retry, session, version, AppState};
+
+// Attempts per request, backing off from RETRY_BASE_DELAY between them
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+
+// Per attempt; listing a large folder can take a while
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Set by the shell for every api_request, so the webview can't override them
+const RESERVED_HEADERS: [&str; 6] = [
+    "authorization",
+    "host",
+    "cookie",
+    "content-length",
+    "connection",
+    "transfer-encoding",
+];
+
+// A `..` segment, percent-encoded or not, which URL parsing would resolve
+fn is_parent_segment(segment: &str) -> bool {
+    matches!(segment.to_ascii_lowercase().as_str(), ".." | ".%2e" | "%2e." | "%2e%2e")
+}
+
+// Token for requests proxied to the app's own sidecar. They go with a webview token
+// instead of the shell credential in the base URL, so the backend refuses them what it
+// refuses the webview. External servers keep the API key in their URL.
+fn webview_token(state: &AppState) -> Option<String> {
+    state
+        .sidecar()
+        .external_url()
+        .is_none()
+        .then(|| session::webview_token(state).0)
+}
+
+fn build_request(base_url: &str, method: &Method, mut url: Url, token: Option<&str>) -> reqwest::RequestBuilder {
+    if token.is_some() {
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+    }
+    let request = backend::shared_client(base_url).request(method.clone(), url);
+    match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+// `path` is relative to /api, e.g. /buckets/photos/files?prefix=2024
+fn request_url(base_url: &str, path: &str) -> Result<Url, String> {
+    let segments = path.split(['?', '#']).next().unwrap_or_default();
+    if !path.starts_with('/') || segments.split(['/', '\\']).any(is_parent_segment) {
+        return Err(format!("Invalid backend path {}", path));
+    }
+    let raw = format!("{}/api{}", base_url, path);
+    let url = Url::parse(&raw).map_err(|e| format!("Invalid backend path {}: {}", path, e))?;
+    let resolved = percent_encoding::percent_decode_str(url.path()).decode_utf8_lossy();
+    if !resolved.starts_with("/api/") {
+        return Err(format!("Invalid backend path {}", path));
+    }
+    Ok(url)
+}
+
+// A request that may be sent again without side effects if the first try got lost
+fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS)
+}
+
+fn is_read(method: &Method) -> bool {
+    *method == Method::GET || *method == Method::HEAD
+}
+
+// The backend reports failures as {"error": "..."}; fall back to the status
+async fn error_message(resp: reqwest::Response) -> String {
+    let status = resp.status();
+    let message = resp
+        .json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|body| body.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .filter(|message| !message.is_empty());
+    match message {
+        Some(message) => message,
+        None => format!("HTTP {}", status.as_u16()),
+    }
+}
+
+async fn send(
+    base_url: &str,
+    token: Option<&str>,
+    method: &Method,
+    url: &Url,
+    body: Option<&serde_json::Value>,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let mut request = build_request(base_url, method, url.clone(), token).timeout(REQUEST_TIMEOUT);
+    if let Some(body) = body {
+        request = request.json(body);
+    }
+    request.send().await
+}
+
+async fn json_body(resp: reqwest::Response) -> Result<serde_json::Value, String> {
+    let bytes = resp
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read backend response: {}", e))?;
+    if bytes.is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    serde_json::from_slice(&bytes).map_err(|e| format!("Invalid backend response: {}", e))
+}
+
+// One try at a read for backend_request; read_retry decides whether to try again
+async fn read_once(
+    app: &AppHandle,
+    profile: &str,
+    base_url: &str,
+    token: Option<&str>,
+    method: &Method,
+    path: &str,
+) -> Result<serde_json::Value, RequestError> {
+    let url = request_url(base_url, path).map_err(RequestError::other)?;
+    let resp = latency::timed(app, profile, send(base_url, token, method, &url, None))
+        .await
+        .map_err(|e| RequestError::send(format!("Failed to reach the backend: {}", e)))?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(RequestError::status(error_message(resp).await, status));
+    }
+    json_body(resp).await.map_err(RequestError::other)
+}
+
+// The backend's /status describes the backend itself, so it skips the read breaker and
+// carries the breaker's state for the UI
+fn is_status(path: &str) -> bool {
+    path.split('?').next() == Some("/status")
+}
+
+// Proxy a frontend API call through the shell: it adds a webview token, retries
+// transient failures and maps error responses to their message. Reads go through the
+// read breaker. Other requests that were never delivered are retried whatever the
+// method; ones that may have reached the backend only when repeating them is safe.
+#[tauri::command]
+pub async fn backend_request(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    method: String,
+    path: String,
+    body: Option<serde_json::Value>,
+) -> Result<serde_json::Value, AppError> {
+    let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+        .map_err(|_| AppError::invalid(format!("Invalid HTTP method {}", method)))?;
+    let token = webview_token(&state);
+    // Reads are harmless against a mismatched backend; writes may not be
+    if !is_read(&method) {
+        version::ensure_compatible(&state)?;
+    } else if !is_status(&path) {
+        let what = format!("{} {}", method, path);
+        let sidecar = state.sidecar();
+        let (app, profile, token, method, path) = (&app, &sidecar.profile, token.as_deref(), &method, &path);
+        let value = read_retry::read(app, &sidecar, &what, |base_url| async move {
+            read_once(app, profile, &base_url, token, method, path).await
+        })
+        .await?;
+        return Ok(value);
+    }
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let sidecar = state.sidecar();
+        if !sidecar.is_reachable() {
+            return Err(AppError::BackendUnreachable {
+                message: "Backend is not running".to_string(),
+            });
+        }
+        let base_url = sidecar.base_url();
+        let url = request_url(&base_url, &path).map_err(AppError::invalid)?;
+        let retry = attempt < MAX_ATTEMPTS;
+        let delay = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+
+        let sent = send(&base_url, token.as_deref(), &method, &url, body.as_ref());
+        let resp = match latency::timed(&app, &sidecar.profile, sent).await {
+            Ok(resp) => resp,
+            Err(e) if retry && (e.is_connect() || (e.is_timeout() && is_idempotent(&method))) => {
+                log::warn!("{} {} failed (attempt {}), retrying: {}", method, path, attempt, e);
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+            Err(e) => {
+                return Err(AppError::Interrupted {
+                    message: format!("Failed to reach the backend: {}", e),
+                })
+            }
+        };
+
+        let status = resp.status();
+        if retry && backend::is_transient_status(status) && is_idempotent(&method) {
+            log::warn!("{} {} returned {} (attempt {}), retrying", method, path, status, attempt);
+            tokio::time::sleep(delay).await;
+            continue;
+        }
+        if !status.is_success() {
+            return Err(AppError::Status {
+                status: status.as_u16(),
+                message: error_message(resp).await,
+            });
+        }
+
+        let mut value = json_body(resp).await?;
+        if is_status(&path) {
+            if let serde_json::Value::Object(fields) = &mut value {
+                let reads = serde_json::to_value(read_retry::active_status(&state)).map_err(|e| e.to_string())?;
+                fields.insert("reads".to_string(), reads);
+            }
+        }
+        return Ok(value);
+    }
+}
+
+// Origin-relative path for api_request: /health, /ready or anything under /api/
+fn raw_request_url(base_url: &str, path: &str) -> Result<Url, String> {
+    match path.split('?').next() {
+        Some("/health" | "/ready") => {
+            Url::parse(&format!("{}{}", base_url, path)).map_err(|e| format!("Invalid backend path {}: {}", path, e))
+        }
+        _ => match path.strip_prefix("/api") {
+            Some(rest) => request_url(base_url, rest),
+            None => Err(format!("Invalid backend path {}", path)),
+        },
+    }
+}
+
+fn header<'a>(request: &'a tauri::ipc::Request<'_>, name: &str) -> Result<&'a str, AppError> {
+    request
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| AppError::Invalid {
+            message: format!("Missing {} header", name),
+        })
+}
+
+// Headers the webview asked to forward, as a JSON object in X-Api-Headers. The shell
+// owns the credential and the connection, so those headers can't be set.
+fn forwarded_headers(request: &tauri::ipc::Request<'_>) -> Result<HeaderMap, AppError> {
+    let mut headers = HeaderMap::new();
+    let Ok(raw) = header(request, "x-api-headers") else {
+        return Ok(headers);
+    };
+    let invalid = |message: String| AppError::Invalid { message };
+    let fields: HashMap<String, String> =
+        serde_json::from_str(raw).map_err(|e| invalid(format!("Invalid X-Api-Headers: {}", e)))?;
+    for (name, value) in fields {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid(format!("Invalid header {}", name)))?;
+        if RESERVED_HEADERS.contains(&name.as_str()) {
+            return Err(invalid(format!("The {} header can't be set", name)));
+        }
+        let value = HeaderValue::from_str(&value).map_err(|_| invalid(format!("Invalid value for {}", name)))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+// Send any request to the backend on the webview's behalf and return the raw response
+// body, adding a webview token. Unlike backend_request this takes and returns
+// bytes, so uploads, downloads and health checks work too, and the webview never needs
+// the backend's address; a sidecar on a Unix socket is only reachable this way. The
+// method and origin-relative path come in the X-Api-Method and X-Api-Path headers,
+// other headers to forward in X-Api-Headers (or the content type alone in
+// X-Api-Content-Type), and the body as the raw invoke payload.
+#[tauri::command]
+pub async fn api_request(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    request: tauri::ipc::Request<'_>,
+) -> Result<tauri::ipc::Response, AppError> {
+    let method = header(&request, "x-api-method")?;
+    let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes()).map_err(|_| AppError::Invalid {
+        message: format!("Invalid HTTP method {}", method),
+    })?;
+    let path = header(&request, "x-api-path")?.to_string();
+    let mut headers = forwarded_headers(&request)?;
+    if let Ok(content_type) = header(&request, "x-api-content-type") {
+        let value = HeaderValue::from_str(content_type).map_err(|_| AppError::Invalid {
+            message: "Invalid X-Api-Content-Type".to_string(),
+        })?;
+        headers.insert(CONTENT_TYPE, value);
+    }
+    let body = match request.body() {
+        tauri::ipc::InvokeBody::Raw(bytes) => bytes.clone(),
+        tauri::ipc::InvokeBody::Json(serde_json::Value::Null) => Vec::new(),
+        tauri::ipc::InvokeBody::Json(value) => serde_json::to_vec(value).map_err(|e| AppError::Invalid {
+            message: e.to_string(),
+        })?,
+    };
+    let token = webview_token(&state);
+    // Counted until this returns, so quitting can warn first
+    let _in_flight = quit::track_request(&state, &path);
+    if !is_read(&method) {
+        version::ensure_compatible(&state)?;
+    } else if path.starts_with("/api/") && body.is_empty() {
+        if method == Method::GET {
+            access_stats::record_download(&state, &path);
+        }
+        return read_through_breaker(&app, &state, token.as_deref(), &method, &path, &headers).await;
+    }
+
+    let sidecar = state.sidecar();
+    if !sidecar.is_reachable() {
+        return Err(AppError::BackendUnreachable {
+            message: "Backend is not running".to_string(),
+        });
+    }
+    let base_url = sidecar.base_url();
+    let url = raw_request_url(&base_url, &path).map_err(|message| AppError::Invalid { message })?;
+
+    // Only requests that never reached the backend are retried: the body may be large
+    let mut attempt = 0;
+    let resp = loop {
+        attempt += 1;
+        let builder = build_request(&base_url, &method, url.clone(), token.as_deref())
+            .headers(headers.clone())
+            .body(body.clone());
+        match latency::timed(&app, &sidecar.profile, builder.send()).await {
+            Ok(resp) => break resp,
+            Err(e) if attempt < MAX_ATTEMPTS && e.is_connect() => {
+                log::warn!("{} {} failed (attempt {}), retrying: {}", method, path, attempt, e);
+                tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+            }
+            Err(e) => {
+                return Err(AppError::Interrupted {
+                    message: format!("Failed to reach the backend: {}", e),
+                });
+            }
+        }
+    };
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(AppError::Status {
+            status: status.as_u16(),
+            message: error_message(resp).await,
+        });
+    }
+    let bytes = resp.bytes().await.map_err(|e| AppError::Interrupted {
+        message: format!("Failed to read backend response: {}", e),
+    })?;
+    Ok(tauri::ipc::Response::new(bytes.to_vec()))
+}
+
+// Downloads and other API reads go through the read breaker; health checks don't
+async fn read_through_breaker(
+    app: &AppHandle,
+    state: &AppState,
+    token: Option<&str>,
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+) -> Result<tauri::ipc::Response, AppError> {
+    let what = format!("{} {}", method, path);
+    let sidecar = state.sidecar();
+    // read_retry only passes the message on; keep what it can't say
+    let answered = Mutex::new(None);
+    let sent = AtomicBool::new(false);
+    let (profile, answered, sent) = (&sidecar.profile, &answered, &sent);
+    let result = read_retry::read(app, &sidecar, &what, |base_url| async move {
+        let url = raw_request_url(&base_url, path).map_err(RequestError::other)?;
+        sent.store(true, Ordering::SeqCst);
+        let request = build_request(&base_url, method, url, token).headers(headers.clone()).send();
+        let resp = latency::timed(app, profile, request)
+            .await
+            .map_err(|e| RequestError::send(format!("Failed to reach the backend: {}", e)))?;
+        let status = resp.status();
+        if !status.is_success() {
+            *answered.lock().unwrap() = Some(status.as_u16());
+            return Err(RequestError::status(error_message(resp).await, status));
+        }
+        resp.bytes()
+            .await
+            .map_err(|e| RequestError::send(format!("Failed to read backend response: {}", e)))
+    })
+    .await;
+    match result {
+        Ok(bytes) => Ok(tauri::ipc::Response::new(bytes.to_vec())),
+        Err(message) => Err(match *answered.lock().unwrap() {
+            Some(status) => AppError::Status { status, message },
+            None if sent.load(Ordering::SeqCst) => AppError::Interrupted { message },
+            None => AppError::BackendUnreachable { message },
+        }),
+    }
+}
@@ -0,0 +1,221 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::notifications::{self, Notification};
+use crate::{emit_to_main, store, test_harness, AppState};
+
+pub const SETTINGS_FILE: &str = "quiet_hours.json";
+
+// How often the monitor checks whether quiet hours started or ended
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// How often deferred background work checks whether it may start
+const WAIT_POLL: Duration = Duration::from_secs(5);
+
+// Titles listed in the summary before it just gives a count
+const SUMMARY_TITLES: usize = 5;
+
+// A daily window, in local time, without notifications. Optional background work
+// (scheduled copies, backend update checks) waits until the window ends.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHoursSettings {
+    pub enabled: bool,
+    // Minutes after local midnight; the window wraps past midnight when end < start
+    pub start_minute: u32,
+    pub end_minute: u32,
+    pub defer_background: bool,
+}
+
+impl Default for QuietHoursSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start_minute: 22 * 60,
+            end_minute: 7 * 60,
+            defer_background: true,
+        }
+    }
+}
+
+impl QuietHoursSettings {
+    fn contains(&self, minute: u32) -> bool {
+        if !self.enabled || self.start_minute == self.end_minute {
+            return false;
+        }
+        if self.start_minute < self.end_minute {
+            (self.start_minute..self.end_minute).contains(&minute)
+        } else {
+            minute >= self.start_minute || minute < self.end_minute
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.start_minute >= 24 * 60 || self.end_minute >= 24 * 60 {
+            return Err("Quiet hours must start and end within the day".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub struct QuietHours {
+    settings: Mutex<QuietHoursSettings>,
+    // Notifications held back while quiet, summarized once quiet hours end
+    held: Mutex<Vec<Notification>>,
+    // Whether quiet hours were in effect at the last check
+    active: AtomicBool,
+}
+
+impl QuietHours {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(QuietHoursSettings::default()),
+            held: Mutex::new(Vec::new()),
+            active: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        let now = test_harness::now();
+        self.settings.lock().unwrap().contains(now.hour() * 60 + now.minute())
+    }
+
+    // Whether optional background work should wait right now
+    pub fn defers_background(&self) -> bool {
+        let defer = self.settings.lock().unwrap().defer_background;
+        defer && self.is_quiet()
+    }
+
+    // Keep a notification for the summary instead of showing it. Returns it back when
+    // it isn't quiet time and the notification should be shown now.
+    pub fn hold(&self, notification: Notification) -> Option<Notification> {
+        if !self.is_quiet() {
+            return Some(notification);
+        }
+        log::info!("Holding notification {:?} until quiet hours end", notification.title);
+        self.held.lock().unwrap().push(notification);
+        None
+    }
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let settings: QuietHoursSettings = match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load quiet hours: {}", e);
+            return;
+        }
+    };
+    match settings.validate() {
+        Ok(()) => *state.quiet_hours.settings.lock().unwrap() = settings,
+        Err(e) => log::warn!("Ignoring saved quiet hours: {}", e),
+    }
+}
+
+// Watch for quiet hours starting and ending, and summarize what was held back
+pub fn spawn_monitor(app: AppHandle, state: Arc<AppState>) {
+    tauri::async_runtime::spawn(async move {
+        while !state.shutdown.load(Ordering::SeqCst) {
+            update(&app, &state);
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+pub fn update(app: &AppHandle, state: &AppState) {
+    let quiet = state.quiet_hours.is_quiet();
+    if state.quiet_hours.active.swap(quiet, Ordering::SeqCst) == quiet {
+        return;
+    }
+    log::info!("Quiet hours {}", if quiet { "started" } else { "ended" });
+    emit_to_main(app, "quiet-hours", quiet);
+    if !quiet {
+        summarize(app, state);
+    }
+}
+
+// One notification listing what arrived while quiet; "Show All" replays them, and
+// their own actions still work since their handlers stayed registered
+fn summarize(app: &AppHandle, state: &AppState) {
+    let held = std::mem::take(&mut *state.quiet_hours.held.lock().unwrap());
+    if held.is_empty() {
+        return;
+    }
+    let mut body = held
+        .iter()
+        .take(SUMMARY_TITLES)
+        .map(|n| n.title.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if held.len() > SUMMARY_TITLES {
+        body.push_str(&format!(" and {} more", held.len() - SUMMARY_TITLES));
+    }
+
+    let title = match held.len() {
+        1 => "1 notification during quiet hours".to_string(),
+        n => format!("{} notifications during quiet hours", n),
+    };
+    notifications::notify(
+        app,
+        state,
+        Notification::new(title, body).action("show", "Show All"),
+        Some(Box::new(move |app: &AppHandle, action: &str| {
+            if action == "show" {
+                for notification in held {
+                    emit_to_main(app, "notification", notification);
+                }
+            }
+        })),
+    );
+}
+
+// Called before optional background work starts. Waits out quiet hours when they
+// defer background work; returns false if cancelled or shutting down meanwhile.
+pub async fn wait_for_background(state: &AppState, cancel: &AtomicBool) -> bool {
+    let mut logged = false;
+    while state.quiet_hours.defers_background() {
+        if cancel.load(Ordering::SeqCst) || state.shutdown.load(Ordering::SeqCst) {
+            return false;
+        }
+        if !logged {
+            log::info!("Deferring background work until quiet hours end");
+            logged = true;
+        }
+        tokio::time::sleep(WAIT_POLL).await;
+    }
+    true
+}
+
+#[tauri::command]
+pub fn get_quiet_hours(state: tauri::State<Arc<AppState>>) -> QuietHoursSettings {
+    *state.quiet_hours.settings.lock().unwrap()
+}
+
+#[tauri::command]
+pub fn configure_quiet_hours(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    enabled: Option<bool>,
+    start_minute: Option<u32>,
+    end_minute: Option<u32>,
+    defer_background: Option<bool>,
+) -> Result<QuietHoursSettings, AppError> {
+    let mut settings = *state.quiet_hours.settings.lock().unwrap();
+    settings.enabled = enabled.unwrap_or(settings.enabled);
+    settings.start_minute = start_minute.unwrap_or(settings.start_minute);
+    settings.end_minute = end_minute.unwrap_or(settings.end_minute);
+    settings.defer_background = defer_background.unwrap_or(settings.defer_background);
+    settings.validate().map_err(AppError::invalid)?;
+
+    store::save_json(&store::data_file(&app, SETTINGS_FILE)?, &settings)?;
+    *state.quiet_hours.settings.lock().unwrap() = settings;
+    // Apply right away, e.g. summarize held notifications when turned off
+    update(&app, &state);
+    Ok(settings)
+}
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Window};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::{drain, stop_sidecars, transfers, AppState};
+
+// What quitting would cut off
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ActiveTransfers {
+    // Requests passing through api_request
+    pub uploads: usize,
+    pub downloads: usize,
+    // Backend sync jobs
+    pub syncs: usize,
+    // Imports, remote copies, URL uploads and dock drops the shell runs itself
+    pub jobs: usize,
+}
+
+impl ActiveTransfers {
+    fn total(&self) -> usize {
+        self.uploads + self.downloads + self.syncs + self.jobs
+    }
+
+    // E.g. "3 uploads and 1 sync"
+    fn summary(&self) -> String {
+        let counts = [
+            (self.uploads, "upload", "uploads"),
+            (self.downloads, "download", "downloads"),
+            (self.syncs, "sync", "syncs"),
+            (self.jobs, "transfer", "transfers"),
+        ];
+        let parts: Vec<String> = counts
+            .into_iter()
+            .filter(|(count, _, _)| *count > 0)
+            .map(|(count, one, many)| format!("{} {}", count, if count == 1 { one } else { many }))
+            .collect();
+        match parts.split_last() {
+            Some((last, [])) => last.clone(),
+            Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+            None => String::new(),
+        }
+    }
+}
+
+pub struct Quit {
+    uploads: AtomicUsize,
+    downloads: AtomicUsize,
+    // Ids of backend syncs seen running on the event stream
+    syncs: Mutex<HashSet<String>>,
+    // The user chose to quit anyway; later close and exit requests go through
+    confirmed: AtomicBool,
+    // The confirmation is showing
+    asking: AtomicBool,
+}
+
+impl Quit {
+    pub fn new() -> Self {
+        Self {
+            uploads: AtomicUsize::new(0),
+            downloads: AtomicUsize::new(0),
+            syncs: Mutex::new(HashSet::new()),
+            confirmed: AtomicBool::new(false),
+            asking: AtomicBool::new(false),
+        }
+    }
+}
+
+// Counts a request as running until dropped, however it ends
+pub struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Count an api_request to `path` while it runs, if it moves file contents
+pub fn track_request<'a>(state: &'a AppState, path: &str) -> Option<InFlight<'a>> {
+    let counter = if path.starts_with("/api/upload") {
+        &state.quit.uploads
+    } else if path.starts_with("/api/download/") || path.starts_with("/api/stream/") {
+        &state.quit.downloads
+    } else {
+        return None;
+    };
+    counter.fetch_add(1, Ordering::SeqCst);
+    Some(InFlight(counter))
+}
+
+// Follow backend syncs through the live events the shell relays
+pub fn observe(state: &AppState, kind: &str, event: &serde_json::Value) {
+    let Some(job_id) = event.pointer("/data/job_id").and_then(|id| id.as_str()) else {
+        return;
+    };
+    let mut syncs = state.quit.syncs.lock().unwrap();
+    match kind {
+        "sync_progress" => {
+            syncs.insert(job_id.to_string());
+        }
+        "sync_complete" => {
+            syncs.remove(job_id);
+        }
+        _ => {}
+    }
+}
+
+// Without the event stream there is no telling when syncs finish
+pub fn stream_lost(state: &AppState) {
+    state.quit.syncs.lock().unwrap().clear();
+}
+
+fn active(state: &AppState) -> ActiveTransfers {
+    ActiveTransfers {
+        uploads: state.quit.uploads.load(Ordering::SeqCst),
+        downloads: state.quit.downloads.load(Ordering::SeqCst),
+        syncs: state.quit.syncs.lock().unwrap().len(),
+        jobs: transfers::running_count(state),
+    }
+}
+
+// What to carry out once the user chose to quit
+pub enum Then {
+    // The main window, or the splash before it, was closed
+    Close(Box<Window>),
+    // The app was asked to exit, e.g. from the app menu
+    Exit(i32),
+}
+
+// Whether a quit has to wait for the user: transfers are running and they haven't
+// chosen to quit anyway. They are asked, and `then` is carried out if they do.
+pub fn hold(app: &AppHandle, then: Then) -> bool {
+    let state = app.state::<Arc<AppState>>();
+    if state.quit.confirmed.load(Ordering::SeqCst) {
+        return false;
+    }
+    let active = active(&state);
+    if active.total() == 0 {
+        return false;
+    }
+    if !state.quit.asking.swap(true, Ordering::SeqCst) {
+        ask(app, active, then);
+    }
+    true
+}
+
+fn ask(app: &AppHandle, active: ActiveTransfers, then: Then) {
+    let handle = app.clone();
+    app.dialog()
+        .message(format!("{} in progress — quit anyway?", active.summary()))
+        .title("Quit BB Stream?")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Quit".to_string(),
+            "Keep Running".to_string(),
+        ))
+        .show(move |quit| {
+            let state = Arc::clone(&handle.state::<Arc<AppState>>());
+            state.quit.asking.store(false, Ordering::SeqCst);
+            if !quit {
+                log::info!("Quit called off; {} still in progress", active.summary());
+                return;
+            }
+            log::info!("Quitting with {} in progress", active.summary());
+            state.quit.confirmed.store(true, Ordering::SeqCst);
+            tauri::async_runtime::spawn(async move {
+                wind_down(&handle, &state).await;
+                match then {
+                    // Closing again stops the sidecars as usual
+                    Then::Close(window) => {
+                        if let Err(e) = window.close() {
+                            log::error!("Failed to close {}: {}", window.label(), e);
+                        }
+                    }
+                    Then::Exit(code) => {
+                        stop_sidecars(&handle);
+                        handle.exit(code);
+                    }
+                }
+            });
+        });
+}
+
+// Have every running sidecar checkpoint its jobs before it is stopped, as a
+// deliberate restart does
+async fn wind_down(app: &AppHandle, state: &AppState) {
+    let sidecars: Vec<_> = state
+        .sidecars
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|sidecar| sidecar.is_running())
+        .cloned()
+        .collect();
+    for sidecar in sidecars {
+        drain::drain(app, state, &sidecar).await;
+    }
+}
+
+// What quitting now would cut off, e.g. to warn before the user quits
+#[tauri::command]
+pub fn get_active_transfers(state: tauri::State<Arc<AppState>>) -> ActiveTransfers {
+    active(&state)
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::backend::{self, RequestError};
+use crate::{emit_to_main, AppState, Sidecar};
+
+// Attempts per read, backing off from RETRY_BASE_DELAY between them
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+
+// Consecutive failed attempts, across all reads of a profile, that open its circuit
+const FAILURE_THRESHOLD: u32 = 5;
+
+// While the circuit is open a probe read goes out this often; one that gets an answer
+// closes it again
+const PROBE_INTERVAL: Duration = Duration::from_secs(15);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Circuit {
+    // Reads go through
+    Closed,
+    // Reads fail straight away until a probe gets through
+    Open,
+    // A probe is in flight
+    HalfOpen,
+}
+
+// Sent as `backend-reads` when the active profile's circuit changes or reads start or
+// stop retrying
+#[derive(Clone, Debug, Serialize)]
+pub struct ReadStatus {
+    pub profile: String,
+    pub circuit: Circuit,
+    // Reads waiting to try again after a transient failure
+    pub retrying: u32,
+    // Consecutive failed attempts
+    pub failures: u32,
+    pub last_error: Option<String>,
+    // Unix milliseconds
+    pub opened_at: Option<i64>,
+    pub next_probe_at: Option<i64>,
+}
+
+impl ReadStatus {
+    fn closed(profile: &str) -> Self {
+        Self {
+            profile: profile.to_string(),
+            circuit: Circuit::Closed,
+            retrying: 0,
+            failures: 0,
+            last_error: None,
+            opened_at: None,
+            next_probe_at: None,
+        }
+    }
+}
+
+// A circuit breaker per profile over every read sent to its backend
+pub struct ReadBreakers {
+    breakers: Mutex<HashMap<String, ReadStatus>>,
+}
+
+impl ReadBreakers {
+    pub fn new() -> Self {
+        Self {
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn status(&self, profile: &str) -> ReadStatus {
+        let breakers = self.breakers.lock().unwrap();
+        breakers.get(profile).cloned().unwrap_or_else(|| ReadStatus::closed(profile))
+    }
+
+    // Change a profile's breaker. Returns the new status when the circuit changed or
+    // reads started or stopped retrying, which is what the UI shows.
+    fn update(&self, profile: &str, change: impl FnOnce(&mut ReadStatus)) -> Option<ReadStatus> {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers
+            .entry(profile.to_string())
+            .or_insert_with(|| ReadStatus::closed(profile));
+        let (circuit, retrying) = (breaker.circuit, breaker.retrying > 0);
+        change(breaker);
+        (breaker.circuit != circuit || (breaker.retrying > 0) != retrying).then(|| breaker.clone())
+    }
+
+    // Why a read can't be sent now, if it can't
+    fn refusal(&self, profile: &str) -> Option<String> {
+        let status = self.status(profile);
+        if status.circuit == Circuit::Closed {
+            return None;
+        }
+        let wait = status
+            .next_probe_at
+            .map(|at| ((at - chrono::Utc::now().timestamp_millis()) / 1000).max(0));
+        Some(match wait {
+            Some(secs) if status.circuit == Circuit::Open => format!(
+                "The backend isn't answering reads; trying again in {}s",
+                secs
+            ),
+            _ => "The backend isn't answering reads; checking whether it's back".to_string(),
+        })
+    }
+}
+
+fn emit(app: &AppHandle, state: &AppState, status: Option<ReadStatus>) {
+    if let Some(status) = status {
+        if *state.active_profile.lock().unwrap() == status.profile {
+            emit_to_main(app, "backend-reads", status);
+        }
+    }
+}
+
+fn answered(app: &AppHandle, state: &AppState, profile: &str) {
+    let status = state.read_breakers.update(profile, |breaker| {
+        breaker.failures = 0;
+        breaker.last_error = None;
+    });
+    emit(app, state, status);
+}
+
+// Count a transient failure; returns whether the circuit is open now
+fn failed(app: &AppHandle, state: &AppState, profile: &str, error: &str) -> bool {
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut opened = false;
+    let status = state.read_breakers.update(profile, |breaker| {
+        breaker.failures += 1;
+        breaker.last_error = Some(error.to_string());
+        if breaker.circuit == Circuit::Closed && breaker.failures >= FAILURE_THRESHOLD {
+            breaker.circuit = Circuit::Open;
+            breaker.opened_at = Some(now);
+            breaker.next_probe_at = Some(now + PROBE_INTERVAL.as_millis() as i64);
+            opened = true;
+        }
+    });
+    emit(app, state, status);
+    if opened {
+        log::warn!("Backend reads of profile {} keep failing; pausing them: {}", profile, error);
+        spawn_probe(app.clone(), profile.to_string());
+    }
+    state.read_breakers.status(profile).circuit != Circuit::Closed
+}
+
+fn set_retrying(app: &AppHandle, state: &AppState, profile: &str, waiting: bool) {
+    let status = state.read_breakers.update(profile, |breaker| {
+        if waiting {
+            breaker.retrying += 1;
+        } else {
+            breaker.retrying = breaker.retrying.saturating_sub(1);
+        }
+    });
+    emit(app, state, status);
+}
+
+// Until the profile's backend answers, send it a cheap read every PROBE_INTERVAL
+fn spawn_probe(app: AppHandle, profile: String) {
+    tauri::async_runtime::spawn(async move {
+        let state = Arc::clone(&app.state::<Arc<AppState>>());
+        loop {
+            tokio::time::sleep(PROBE_INTERVAL).await;
+            let Some(sidecar) = state.sidecars.lock().unwrap().get(&profile).cloned() else {
+                // The profile was removed
+                state.read_breakers.breakers.lock().unwrap().remove(&profile);
+                return;
+            };
+
+            let result = if sidecar.is_reachable() {
+                let status = state.read_breakers.update(&profile, |breaker| breaker.circuit = Circuit::HalfOpen);
+                emit(&app, &state, status);
+                probe(&sidecar.base_url()).await
+            } else {
+                Err("Backend is not running".to_string())
+            };
+            let now = chrono::Utc::now().timestamp_millis();
+            let status = state.read_breakers.update(&profile, |breaker| match &result {
+                Ok(()) => *breaker = ReadStatus::closed(&profile),
+                Err(e) => {
+                    breaker.circuit = Circuit::Open;
+                    breaker.last_error = Some(e.clone());
+                    breaker.next_probe_at = Some(now + PROBE_INTERVAL.as_millis() as i64);
+                }
+            });
+            emit(&app, &state, status);
+            if result.is_ok() {
+                log::info!("Backend reads of profile {} work again", profile);
+                return;
+            }
+        }
+    });
+}
+
+// Whether the backend answers a read at all; an error response still counts as an answer
+async fn probe(base_url: &str) -> Result<(), String> {
+    let client = backend::client_builder(base_url)
+        .timeout(PROBE_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    match backend::request_buckets(&client, base_url).await {
+        Ok(_) => Ok(()),
+        Err(e) if !e.transient => Ok(()),
+        Err(e) => Err(e.message),
+    }
+}
+
+// Send a read to a sidecar through its profile's circuit breaker, trying transient
+// failures again with backoff. `send` gets the sidecar's current base URL each attempt.
+// While the circuit is open reads fail straight away, so callers fall back to what
+// they have instead of waiting on a backend that isn't answering.
+pub async fn read<T, F, Fut>(app: &AppHandle, sidecar: &Sidecar, what: &str, mut send: F) -> Result<T, String>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T, RequestError>>,
+{
+    let state = Arc::clone(&app.state::<Arc<AppState>>());
+    let profile = &sidecar.profile;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        if let Some(refusal) = state.read_breakers.refusal(profile) {
+            return Err(refusal);
+        }
+        if !sidecar.is_reachable() {
+            return Err("Backend is not running".to_string());
+        }
+
+        match send(sidecar.base_url()).await {
+            Ok(value) => {
+                answered(app, &state, profile);
+                return Ok(value);
+            }
+            // The backend answered; the request itself was refused
+            Err(e) if !e.transient => {
+                answered(app, &state, profile);
+                return Err(e.message);
+            }
+            Err(e) => {
+                if failed(app, &state, profile, &e.message) || attempt >= MAX_ATTEMPTS {
+                    return Err(e.message);
+                }
+                log::warn!("{} failed (attempt {}), retrying: {}", what, attempt, e.message);
+                set_retrying(app, &state, profile, true);
+                tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+                set_retrying(app, &state, profile, false);
+            }
+        }
+    }
+}
+
+// The active profile's breaker
+pub fn active_status(state: &AppState) -> ReadStatus {
+    let profile = state.active_profile.lock().unwrap().clone();
+    state.read_breakers.status(&profile)
+}
+
+#[tauri::command]
+pub fn get_read_status(state: tauri::State<Arc<AppState>>) -> ReadStatus {
+    active_status(&state)
+}
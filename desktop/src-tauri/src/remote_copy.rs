@@ -0,0 +1,509 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::backend::{self, RemoteObject};
+use crate::error::AppError;
+use crate::remote_path::{self, RemotePath};
+use crate::{
+    auth, emit_to_main, jobs, journal, network, operations, quiet_hours, resume, store, test_harness, transfers,
+    AppState,
+};
+
+const JOBS_FILE: &str = "remote_copies.json";
+const JOURNAL_FILE: &str = "remote_copies.journal";
+
+// Fold the journal into a fresh snapshot after this many copied objects
+const CHECKPOINT_EVERY: usize = 25;
+
+// One side of a copy: a bucket/prefix on a bb-stream API server
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Endpoint {
+    // Base URL of the API server; None means the bundled sidecar
+    #[serde(default)]
+    pub base_url: Option<String>,
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+}
+
+// Which source objects a job copies; patterns match keys relative to the source prefix
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CopyFilter {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub min_size: Option<i64>,
+    #[serde(default)]
+    pub max_size: Option<i64>,
+}
+
+impl CopyFilter {
+    fn validate(&self) -> Result<(), String> {
+        for pattern in self.include.iter().chain(&self.exclude) {
+            glob::Pattern::new(pattern).map_err(|e| format!("Invalid pattern {:?}: {}", pattern, e))?;
+        }
+        Ok(())
+    }
+
+    fn matches(&self, relative_key: &str, size: i64) -> bool {
+        let hit = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter_map(|p| glob::Pattern::new(p).ok())
+                .any(|p| p.matches(relative_key))
+        };
+        if self.min_size.is_some_and(|min| size < min) || self.max_size.is_some_and(|max| size > max) {
+            return false;
+        }
+        (self.include.is_empty() || hit(&self.include)) && !hit(&self.exclude)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CopyStatus {
+    Scheduled,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoteCopyJob {
+    pub id: String,
+    pub source: Endpoint,
+    pub dest: Endpoint,
+    pub filter: CopyFilter,
+    // Unix timestamp (seconds) before which the job will not start
+    pub start_at: Option<u64>,
+    pub status: CopyStatus,
+    pub total_objects: usize,
+    pub copied_objects: usize,
+    pub copied_bytes: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Persisted {
+    jobs: Vec<RemoteCopyJob>,
+    // Source keys already copied per job, skipped when the job resumes
+    done: HashMap<String, HashSet<String>>,
+}
+
+// Progress recorded between snapshots
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalEntry {
+    Copied { id: String, key: String, bytes: u64 },
+}
+
+impl Persisted {
+    // Returns the updated job, or None if the entry was already applied
+    fn apply(&mut self, entry: JournalEntry) -> Option<RemoteCopyJob> {
+        match entry {
+            JournalEntry::Copied { id, key, bytes } => {
+                let job = self.jobs.iter_mut().find(|j| j.id == id)?;
+                if !self.done.entry(id).or_default().insert(key) {
+                    return None;
+                }
+                job.copied_objects += 1;
+                job.copied_bytes += bytes;
+                Some(job.clone())
+            }
+        }
+    }
+}
+
+// Remote-to-remote copy jobs. Objects stream from the source's download endpoint
+// straight into the destination's stream-upload endpoint, so memory stays bounded
+// by the HTTP chunk size. The bb-stream API has no server-side copy, so even
+// same-account copies stream through this machine.
+pub struct RemoteCopies {
+    inner: Mutex<Persisted>,
+    cancel: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl RemoteCopies {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(Persisted::default()),
+            cancel: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn job(&self, id: &str) -> Option<RemoteCopyJob> {
+        self.inner.lock().unwrap().jobs.iter().find(|j| j.id == id).cloned()
+    }
+
+    pub fn jobs(&self) -> Vec<RemoteCopyJob> {
+        self.inner.lock().unwrap().jobs.clone()
+    }
+
+    fn update<F: FnOnce(&mut RemoteCopyJob)>(&self, id: &str, f: F) -> Option<RemoteCopyJob> {
+        let mut inner = self.inner.lock().unwrap();
+        let job = inner.jobs.iter_mut().find(|j| j.id == id)?;
+        f(job);
+        Some(job.clone())
+    }
+
+    // Snapshot the jobs and empty the journal. The lock is held throughout so no entry
+    // is journaled after the snapshot was taken but before the journal is cleared.
+    fn save(&self, app: &AppHandle) {
+        let inner = self.inner.lock().unwrap();
+        let result = store::data_file(app, JOBS_FILE)
+            .and_then(|path| store::save_json(&path, &*inner))
+            .and_then(|()| journal::clear(app, JOURNAL_FILE));
+        if let Err(e) = result {
+            log::error!("Failed to persist remote copy jobs: {}", e);
+        }
+    }
+
+    // Mark an object copied, journaling it before the progress is reported
+    fn mark_copied(&self, app: &AppHandle, id: &str, key: &str, bytes: u64) -> Option<RemoteCopyJob> {
+        let mut inner = self.inner.lock().unwrap();
+        let entry = JournalEntry::Copied {
+            id: id.to_string(),
+            key: key.to_string(),
+            bytes,
+        };
+        if let Err(e) = journal::append(app, JOURNAL_FILE, &entry) {
+            log::error!("Failed to journal remote copy progress: {}", e);
+        }
+        inner.apply(entry)
+    }
+}
+
+// Load persisted jobs, replaying progress journaled since the last snapshot. Jobs
+// running at exit wait for the user to resume or discard them; scheduled ones start
+// when due.
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let mut persisted: Persisted = match store::data_file(app, JOBS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load remote copy jobs: {}", e);
+            return;
+        }
+    };
+    let entries: Vec<JournalEntry> = journal::replay(app, JOURNAL_FILE);
+    let replayed = !entries.is_empty();
+    for entry in entries {
+        persisted.apply(entry);
+    }
+    let mut interrupted = false;
+    for job in persisted.jobs.iter_mut().filter(|j| j.status == CopyStatus::Running) {
+        // Stopped until the user chooses to resume it
+        job.status = CopyStatus::Cancelled;
+        resume::hold(state, &job.id);
+        interrupted = true;
+    }
+    // Jobs still waiting for their start time were never interrupted
+    let scheduled: Vec<String> = persisted
+        .jobs
+        .iter()
+        .filter(|j| j.status == CopyStatus::Scheduled)
+        .map(|j| j.id.clone())
+        .collect();
+    *state.remote_copies.inner.lock().unwrap() = persisted;
+    if replayed || interrupted {
+        state.remote_copies.save(app);
+    }
+
+    for id in scheduled {
+        log::info!("Rescheduling remote copy job {}", id);
+        spawn_job(app.clone(), Arc::clone(state), id);
+    }
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(source = %source.bucket, dest = %dest.bucket), err)]
+pub fn create_remote_copy(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    source: Endpoint,
+    dest: Endpoint,
+    filter: Option<CopyFilter>,
+    start_at: Option<u64>,
+) -> Result<RemoteCopyJob, AppError> {
+    let filter = filter.unwrap_or_default();
+    filter.validate().map_err(AppError::invalid)?;
+    let source_prefix = RemotePath::parse(&source.prefix).map_err(AppError::invalid)?;
+    let dest_prefix = RemotePath::parse(&dest.prefix).map_err(AppError::invalid)?;
+    if source.base_url == dest.base_url && source.bucket == dest.bucket && dest_prefix.starts_with(&source_prefix) {
+        return Err(AppError::invalid("Can't copy a folder into itself"));
+    }
+
+    let job = RemoteCopyJob {
+        id: uuid::Uuid::new_v4().to_string(),
+        source,
+        dest,
+        filter,
+        start_at,
+        status: CopyStatus::Scheduled,
+        total_objects: 0,
+        copied_objects: 0,
+        copied_bytes: 0,
+        error: None,
+    };
+    state.remote_copies.inner.lock().unwrap().jobs.push(job.clone());
+    state.remote_copies.save(&app);
+
+    spawn_job(app, Arc::clone(&state), job.id.clone());
+    Ok(job)
+}
+
+#[tauri::command]
+pub fn list_remote_copies(state: tauri::State<Arc<AppState>>) -> Vec<RemoteCopyJob> {
+    state.remote_copies.jobs()
+}
+
+#[tauri::command]
+pub fn cancel_remote_copy(state: tauri::State<Arc<AppState>>, id: String) -> Result<(), AppError> {
+    match state.remote_copies.cancel.lock().unwrap().get(&id) {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        None => Err(AppError::invalid(format!("Remote copy {} is not active", id))),
+    }
+}
+
+// Restart a failed or cancelled job; already copied objects are skipped
+#[tauri::command]
+pub fn resume_remote_copy(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    id: String,
+) -> Result<(), AppError> {
+    let job = state
+        .remote_copies
+        .update(&id, |job| {
+            if matches!(job.status, CopyStatus::Failed | CopyStatus::Cancelled) {
+                job.status = CopyStatus::Scheduled;
+                job.error = None;
+            }
+        })
+        .ok_or_else(|| AppError::invalid(format!("Unknown remote copy {}", id)))?;
+    if job.status != CopyStatus::Scheduled || state.remote_copies.cancel.lock().unwrap().contains_key(&id) {
+        return Err(AppError::busy(format!("Remote copy {} is already active", id)));
+    }
+
+    spawn_job(app, Arc::clone(&state), id);
+    Ok(())
+}
+
+fn spawn_job(app: AppHandle, state: Arc<AppState>, id: String) {
+    let cancel = Arc::new(AtomicBool::new(false));
+    state
+        .remote_copies
+        .cancel
+        .lock()
+        .unwrap()
+        .insert(id.clone(), Arc::clone(&cancel));
+    let label = state
+        .remote_copies
+        .update(&id, |_| {})
+        .map(|job| format!("Copy {} to {}", job.source.bucket, job.dest.bucket))
+        .unwrap_or_default();
+    let operation = operations::begin(&app, Some(id.clone()), "remote_copy", label, Arc::clone(&cancel));
+
+    tauri::async_runtime::spawn(async move {
+        let result = run_job(&app, &state, &id, &cancel).await;
+        state.remote_copies.cancel.lock().unwrap().remove(&id);
+        transfers::finish(&state, &id);
+        drop(operation);
+
+        let job = state.remote_copies.update(&id, |job| match result {
+            Ok(()) => job.status = CopyStatus::Completed,
+            Err(_) if cancel.load(Ordering::SeqCst) => job.status = CopyStatus::Cancelled,
+            Err(e) => {
+                log::error!("Remote copy {} failed: {}", job.id, e);
+                job.status = CopyStatus::Failed;
+                job.error = Some(e);
+            }
+        });
+        state.remote_copies.save(&app);
+        if let Some(job) = job {
+            if job.status == CopyStatus::Failed {
+                jobs::notify_failed(&app, &job.id);
+            }
+            jobs::remote_copy_updated(&app, &job);
+            emit_to_main(&app, "remote-copy-progress", job);
+        }
+    });
+}
+
+fn now_secs() -> u64 {
+    test_harness::now().timestamp().max(0) as u64
+}
+
+fn resolve_base_url(state: &AppState, endpoint: &Endpoint) -> String {
+    endpoint
+        .base_url
+        .clone()
+        .unwrap_or_else(|| state.base_url())
+}
+
+#[tracing::instrument(name = "transfer.remote_copy", skip_all, fields(id = %id), err)]
+async fn run_job(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    id: &str,
+    cancel: &Arc<AtomicBool>,
+) -> Result<(), String> {
+    let job = state
+        .remote_copies
+        .job(id)
+        .ok_or_else(|| AppError::invalid(format!("Unknown remote copy {}", id)))?;
+
+    // Wait for the scheduled start time and for the bundled backend if either side uses it
+    let uses_sidecar = job.source.base_url.is_none() || job.dest.base_url.is_none();
+    while job.start_at.is_some_and(|t| now_secs() < t)
+        || (uses_sidecar && !state.is_healthy())
+    {
+        if cancel.load(Ordering::SeqCst) || state.shutdown.load(Ordering::SeqCst) {
+            return Err("Cancelled".to_string());
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    // Scheduled jobs are background work and wait out quiet hours; copies started
+    // by hand run right away
+    if job.start_at.is_some() && !quiet_hours::wait_for_background(state, cancel).await {
+        return Err("Cancelled".to_string());
+    }
+
+    let source_base = resolve_base_url(state, &job.source);
+    let dest_base = resolve_base_url(state, &job.dest);
+
+    let source_prefix = RemotePath::sanitize(&job.source.prefix)?;
+    let dest_prefix = RemotePath::sanitize(&job.dest.prefix)?;
+
+    // Each object with its key relative to the source prefix
+    let source_client = backend::shared_client(&source_base);
+    let objects: Vec<(RemoteObject, RemotePath)> =
+        backend::list_objects(&source_client, &source_base, &job.source.bucket, &source_prefix.list_prefix())
+            .await?
+            .into_iter()
+            .filter_map(|o| match remote_path::relative_key(&source_prefix, &o.name)? {
+                Ok(relative) => Some((o, relative)),
+                Err(e) => {
+                    log::warn!("Skipping {}: {}", o.name, e);
+                    None
+                }
+            })
+            .filter(|(o, relative)| job.filter.matches(relative.as_str(), o.size))
+            .collect();
+
+    let done = {
+        let inner = state.remote_copies.inner.lock().unwrap();
+        let done = inner.done.get(id);
+        transfers::track(
+            app,
+            id,
+            "remote_copy",
+            objects
+                .iter()
+                .map(|(o, _)| {
+                    let skipped = done.is_some_and(|d| d.contains(&o.name));
+                    (o.name.clone(), o.size.max(0) as u64, skipped)
+                })
+                .collect(),
+        );
+        done.map(|d| d.len()).unwrap_or(0)
+    };
+    if let Some(job) = state.remote_copies.update(id, |job| {
+        job.status = CopyStatus::Running;
+        job.total_objects = objects.len();
+        job.copied_objects = done;
+    }) {
+        jobs::remote_copy_updated(app, &job);
+        emit_to_main(app, "remote-copy-progress", job);
+    }
+
+    let mut since_checkpoint = 0;
+    for (object, relative) in objects {
+        // Back off while the machine is short on memory or disk
+        if !state.resources.throttle(state, cancel).await || cancel.load(Ordering::SeqCst) {
+            state.remote_copies.save(app);
+            return Err("Cancelled".to_string());
+        }
+        let already_done = state
+            .remote_copies
+            .inner
+            .lock()
+            .unwrap()
+            .done
+            .get(id)
+            .is_some_and(|d| d.contains(&object.name));
+        if already_done {
+            continue;
+        }
+        if !network::wait_for_wifi(app, state, id, &object.name, object.size.max(0) as u64, cancel).await {
+            state.remote_copies.save(app);
+            return Err("Cancelled".to_string());
+        }
+
+        let dest_key = dest_prefix.join(&relative)?.to_string();
+        let copied = Arc::new(AtomicU64::new(0));
+        transfers::start_file(state, id, &object.name, &copied);
+        let result = copy_object(
+            (&source_base, &job.source.bucket, &object.name),
+            (&dest_base, &job.dest.bucket, &dest_key),
+            cancel,
+            &copied,
+        )
+        .await;
+        transfers::finish_file(state, id, &object.name, &result, cancel.load(Ordering::SeqCst));
+        // Only the bundled backend's credentials can be renewed by signing in
+        if let Err(e) = &result {
+            if job.source.base_url.is_none() && job.dest.base_url.is_none() {
+                auth::report(app, state, e);
+            }
+        }
+        let bytes = result?;
+
+        if let Some(job) = state.remote_copies.mark_copied(app, id, &object.name, bytes) {
+            jobs::remote_copy_updated(app, &job);
+            emit_to_main(app, "remote-copy-progress", job);
+        }
+
+        since_checkpoint += 1;
+        if since_checkpoint >= CHECKPOINT_EVERY {
+            state.remote_copies.save(app);
+            since_checkpoint = 0;
+        }
+    }
+
+    // The checkpoint is only needed while the job can still be resumed
+    state.remote_copies.inner.lock().unwrap().done.remove(id);
+    Ok(())
+}
+
+// Stream one object from source to destination, returning the bytes copied
+async fn copy_object(
+    (source_base, source_bucket, source_key): (&str, &str, &str),
+    (dest_base, dest_bucket, dest_key): (&str, &str, &str),
+    cancel: &Arc<AtomicBool>,
+    copied: &Arc<AtomicU64>,
+) -> Result<u64, String> {
+    let resp = backend::shared_client(source_base)
+        .get(backend::download_url(source_base, source_bucket, source_key)?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", source_key, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Downloading {} returned status: {}", source_key, resp.status()));
+    }
+
+    let body = backend::metered_body(resp.bytes_stream(), Arc::clone(cancel), Arc::clone(copied));
+    backend::upload_stream(&backend::shared_client(dest_base), dest_base, dest_bucket, dest_key, body).await?;
+
+    Ok(copied.load(Ordering::Relaxed))
+}
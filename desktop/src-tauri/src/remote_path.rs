@@ -0,0 +1,267 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+// Longest object key B2 accepts, in bytes of UTF-8
+const MAX_KEY_BYTES: usize = 1024;
+
+// What to do with a path that isn't a clean object key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Policy {
+    // Refuse it; for paths the user typed
+    Reject,
+    // Make it valid: control characters become '_', and empty, '.' and '..'
+    // segments are dropped; for keys and file names coming from elsewhere
+    Replace,
+}
+
+// An object key, or a folder-style prefix, inside a bucket. Always '/'-separated with
+// no leading or trailing '/', no empty, '.' or '..' segments and no control
+// characters; the empty path is the bucket root. Prefixes match whole segments, so
+// "photos" contains "photos/a.jpg" but not "photos2/a.jpg". URLs are built from
+// as_str() by backend::api_url, which encodes each segment.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RemotePath(String);
+
+impl RemotePath {
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    // A path the user entered; surrounding '/' are ignored
+    pub fn parse(path: &str) -> Result<Self, String> {
+        Self::with_policy(path, Policy::Reject)
+    }
+
+    // Any string made into a valid path
+    pub fn sanitize(path: &str) -> Result<Self, String> {
+        Self::with_policy(path, Policy::Replace)
+    }
+
+    fn with_policy(path: &str, policy: Policy) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        for segment in path.split('/') {
+            match policy {
+                Policy::Reject => {
+                    if segment.chars().any(char::is_control) {
+                        return Err(format!("{:?} contains control characters", path));
+                    }
+                    if segment == "." || segment == ".." {
+                        return Err(format!("{:?} contains a {:?} segment", path, segment));
+                    }
+                    segments.push(segment.to_string());
+                }
+                Policy::Replace => {
+                    if segment == "." || segment == ".." {
+                        continue;
+                    }
+                    segments.push(segment.chars().map(|c| if c.is_control() { '_' } else { c }).collect());
+                }
+            }
+        }
+
+        // Only surrounding separators may be empty; inner ones mean "a//b"
+        let first = segments.iter().position(|s| !s.is_empty()).unwrap_or(segments.len());
+        let last = segments.iter().rposition(|s| !s.is_empty()).map_or(first, |i| i + 1);
+        let inner = &segments[first..last];
+        if policy == Policy::Reject && inner.iter().any(String::is_empty) {
+            return Err(format!("{:?} contains an empty segment", path));
+        }
+        let joined = inner.iter().filter(|s| !s.is_empty()).cloned().collect::<Vec<_>>().join("/");
+        Self::checked(joined)
+    }
+
+    fn checked(path: String) -> Result<Self, String> {
+        if path.len() > MAX_KEY_BYTES {
+            return Err(format!("Object keys are limited to {} bytes", MAX_KEY_BYTES));
+        }
+        Ok(Self(path))
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn join(&self, other: &RemotePath) -> Result<Self, String> {
+        match (self.is_root(), other.is_root()) {
+            (true, _) => Ok(other.clone()),
+            (_, true) => Ok(self.clone()),
+            _ => Self::checked(format!("{}/{}", self.0, other.0)),
+        }
+    }
+
+    // Whether `prefix` is this path or one of its folders
+    pub fn starts_with(&self, prefix: &RemotePath) -> bool {
+        self.strip_prefix(prefix).is_some()
+    }
+
+    // This path relative to `prefix`, if it lies inside it
+    pub fn strip_prefix(&self, prefix: &RemotePath) -> Option<Self> {
+        if prefix.is_root() {
+            return Some(self.clone());
+        }
+        match self.0.strip_prefix(&prefix.0)? {
+            "" => Some(Self::root()),
+            rest => rest.strip_prefix('/').map(|rest| Self(rest.to_string())),
+        }
+    }
+
+    // Prefix to list this folder's contents with: "" for the root, otherwise with a
+    // trailing '/'
+    pub fn list_prefix(&self) -> String {
+        if self.is_root() {
+            String::new()
+        } else {
+            format!("{}/", self.0)
+        }
+    }
+}
+
+// A key returned by the backend, relative to `prefix`. Keys outside the prefix give
+// None; the rest are sanitized, since the backend may hold keys this type rejects.
+pub fn relative_key(prefix: &RemotePath, key: &str) -> Option<Result<RemotePath, String>> {
+    let key = key.trim_start_matches('/');
+    let rest = match key.strip_prefix(prefix.as_str())? {
+        rest if prefix.is_root() => rest,
+        "" => "",
+        rest => rest.strip_prefix('/')?,
+    };
+    Some(RemotePath::sanitize(rest))
+}
+
+impl fmt::Display for RemotePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<String> for RemotePath {
+    type Error = String;
+
+    fn try_from(path: String) -> Result<Self, String> {
+        Self::parse(&path)
+    }
+}
+
+impl From<RemotePath> for String {
+    fn from(path: RemotePath) -> String {
+        path.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Property checks over pseudo-random paths drawn from characters that tend to
+    // break path handling; seeded so failures reproduce
+    const CASES: usize = 2000;
+    const ALPHABET: &[char] = &['a', 'Z', '0', '/', '/', '.', ' ', '%', '?', '#', '\\', '\n', '\u{7f}', 'é', '日', '😀'];
+
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            // xorshift64
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn path(&mut self) -> String {
+            let len = (self.next() % 24) as usize;
+            (0..len)
+                .map(|_| ALPHABET[(self.next() % ALPHABET.len() as u64) as usize])
+                .collect()
+        }
+    }
+
+    fn paths() -> impl Iterator<Item = String> {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        (0..CASES).map(move |_| rng.path())
+    }
+
+    fn assert_clean(path: &RemotePath) {
+        let s = path.as_str();
+        assert!(!s.starts_with('/') && !s.ends_with('/'), "{:?}", s);
+        assert!(!s.contains("//"), "{:?}", s);
+        assert!(!s.chars().any(char::is_control), "{:?}", s);
+        assert!(s.split('/').all(|seg| seg != "." && seg != ".."), "{:?}", s);
+    }
+
+    #[test]
+    fn sanitize_always_yields_clean_paths() {
+        for raw in paths() {
+            let path = RemotePath::sanitize(&raw).unwrap();
+            assert_clean(&path);
+            // Sanitizing is idempotent and clean paths parse unchanged
+            assert_eq!(RemotePath::sanitize(path.as_str()).unwrap(), path);
+            assert_eq!(RemotePath::parse(path.as_str()).unwrap(), path);
+        }
+    }
+
+    #[test]
+    fn parse_accepts_only_what_sanitize_leaves_alone() {
+        for raw in paths() {
+            if let Ok(path) = RemotePath::parse(&raw) {
+                assert_clean(&path);
+                assert_eq!(path, RemotePath::sanitize(&raw).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn join_then_strip_prefix_round_trips() {
+        let mut raw = paths();
+        for _ in 0..CASES / 2 {
+            let prefix = RemotePath::sanitize(&raw.next().unwrap()).unwrap();
+            let rest = RemotePath::sanitize(&raw.next().unwrap()).unwrap();
+            let joined = prefix.join(&rest).unwrap();
+            assert_clean(&joined);
+            assert!(joined.starts_with(&prefix));
+            assert_eq!(joined.strip_prefix(&prefix), Some(rest.clone()));
+            assert_eq!(relative_key(&prefix, joined.as_str()), Some(Ok(rest)));
+        }
+    }
+
+    #[test]
+    fn api_urls_round_trip_keys() {
+        for raw in paths() {
+            let path = RemotePath::sanitize(&raw).unwrap();
+            let url = crate::backend::download_url("http://localhost:1", "bucket", path.as_str()).unwrap();
+            let decoded = url
+                .path_segments()
+                .unwrap()
+                .skip(3)
+                .map(|s| percent_encoding::percent_decode_str(s).decode_utf8().unwrap().into_owned())
+                .collect::<Vec<_>>()
+                .join("/");
+            assert_eq!(decoded, path.as_str());
+        }
+    }
+
+    #[test]
+    fn prefixes_match_whole_segments() {
+        let photos = RemotePath::parse("photos").unwrap();
+        assert!(RemotePath::parse("photos/a.jpg").unwrap().starts_with(&photos));
+        assert!(!RemotePath::parse("photos2/a.jpg").unwrap().starts_with(&photos));
+        assert_eq!(relative_key(&photos, "photos2/a.jpg"), None);
+        assert_eq!(photos.list_prefix(), "photos/");
+        assert_eq!(RemotePath::root().list_prefix(), "");
+    }
+
+    #[test]
+    fn parse_rejects_unclean_paths() {
+        for raw in ["a//b", "a/../b", "./a", "a\nb"] {
+            assert!(RemotePath::parse(raw).is_err(), "{:?}", raw);
+        }
+        assert_eq!(RemotePath::parse("/a/b/").unwrap().as_str(), "a/b");
+        assert!(RemotePath::parse(&"x".repeat(MAX_KEY_BYTES + 1)).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::AppState;
+use crate::error::AppError;
+
+// Topics that carry state, with how many of their latest events are kept for windows
+// that subscribe late. Progress and one-off events aren't replayed.
+const TOPICS: [(&str, usize); 15] = [
+    // Enough for e.g. Starting followed by Healthy
+    ("backend-status", 4),
+    ("backend-capabilities", 1),
+    ("backend-crash", 1),
+    ("backend-port-fallback", 1),
+    ("backend-slow", 1),
+    ("auth-required", 1),
+    ("clock-skew", 1),
+    ("network-metered", 1),
+    ("profile-switched", 1),
+    ("quiet-hours", 1),
+    ("resource-pressure", 1),
+    ("resume-available", 1),
+    ("safe-mode", 1),
+    ("sidecar-config-pending", 1),
+    ("stream:status", 1),
+];
+
+// Per-profile status topics are kept like "backend-status"
+const PROFILE_STATUS_PREFIX: &str = "backend-status-";
+
+fn depth(topic: &str) -> Option<usize> {
+    let topic = if topic.starts_with(PROFILE_STATUS_PREFIX) { "backend-status" } else { topic };
+    TOPICS.iter().find(|(name, _)| *name == topic).map(|(_, depth)| *depth)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReplayedEvent {
+    pub topic: String,
+    pub payload: serde_json::Value,
+    // Unix milliseconds
+    pub emitted_at: i64,
+}
+
+// The latest events of each state-bearing topic, oldest first
+pub struct Replay {
+    topics: Mutex<HashMap<String, VecDeque<ReplayedEvent>>>,
+}
+
+impl Replay {
+    pub fn new() -> Self {
+        Self {
+            topics: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+// Keep an event that was just emitted, if its topic is replayed
+pub fn record<S: Serialize>(state: &AppState, topic: &str, payload: &S) {
+    let Some(depth) = depth(topic) else {
+        return;
+    };
+    let payload = match serde_json::to_value(payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            log::debug!("Not keeping {} for replay: {}", topic, e);
+            return;
+        }
+    };
+    let mut topics = state.replay.topics.lock().unwrap();
+    let events = topics.entry(topic.to_string()).or_default();
+    events.push_back(ReplayedEvent {
+        topic: topic.to_string(),
+        payload,
+        emitted_at: chrono::Utc::now().timestamp_millis(),
+    });
+    while events.len() > depth {
+        events.pop_front();
+    }
+}
+
+// The latest events of `topic`, oldest first, for a window that just started listening
+// to it. Events arriving in between may be delivered twice; the last one is current.
+#[tauri::command]
+pub fn subscribe_with_replay(
+    state: tauri::State<Arc<AppState>>,
+    topic: String,
+) -> Result<Vec<ReplayedEvent>, AppError> {
+    if depth(&topic).is_none() {
+        return Err(AppError::invalid(format!("{} isn't replayed", topic)));
+    }
+    let topics = state.replay.topics.lock().unwrap();
+    Ok(topics.get(&topic).map(|events| events.iter().cloned().collect()).unwrap_or_default())
+}
@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use sysinfo::{Disks, System};
+use tauri::{AppHandle, Manager};
+
+use crate::{activity, emit_to_main, thumbnails, AppState};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+
+// Extra delay between work units while the system is under elevated pressure
+const ELEVATED_DELAY: Duration = Duration::from_millis(500);
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PressureLevel {
+    #[default]
+    Normal,
+    Elevated,
+    Critical,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ResourcePressure {
+    pub level: PressureLevel,
+    pub memory_level: PressureLevel,
+    pub disk_level: PressureLevel,
+    pub available_memory: u64,
+    pub total_memory: u64,
+    pub available_disk: u64,
+    pub total_disk: u64,
+    // Human-readable explanation for the UI, empty when Normal
+    pub reason: String,
+}
+
+// Disk and memory pressure as last sampled; background workers consult it between units of work
+pub struct Resources {
+    current: Mutex<ResourcePressure>,
+}
+
+impl Resources {
+    pub fn new() -> Self {
+        Self {
+            current: Mutex::new(ResourcePressure::default()),
+        }
+    }
+
+    pub fn level(&self) -> PressureLevel {
+        self.current.lock().unwrap().level
+    }
+
+    // Slow down under elevated pressure and wait out critical pressure, and let
+    // thumbnails for the visible folder go first. Returns false if the work is
+    // cancelled or the app shuts down while waiting.
+    pub async fn throttle(&self, state: &AppState, cancel: &AtomicBool) -> bool {
+        if !thumbnails::yield_to_foreground(state, cancel).await {
+            return false;
+        }
+        loop {
+            match self.level() {
+                PressureLevel::Normal => return true,
+                PressureLevel::Elevated => {
+                    tokio::time::sleep(ELEVATED_DELAY).await;
+                    return true;
+                }
+                PressureLevel::Critical => {
+                    if cancel.load(Ordering::SeqCst) || state.shutdown.load(Ordering::SeqCst) {
+                        return false;
+                    }
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                }
+            }
+        }
+    }
+}
+
+fn memory_level(available: u64, total: u64) -> PressureLevel {
+    if total == 0 {
+        return PressureLevel::Normal;
+    }
+    let pct = available as f64 / total as f64;
+    if pct < 0.05 || available < GIB / 4 {
+        PressureLevel::Critical
+    } else if pct < 0.15 {
+        PressureLevel::Elevated
+    } else {
+        PressureLevel::Normal
+    }
+}
+
+fn disk_level(available: u64, total: u64) -> PressureLevel {
+    if total == 0 {
+        return PressureLevel::Normal;
+    }
+    let pct = available as f64 / total as f64;
+    if pct < 0.02 || available < GIB {
+        PressureLevel::Critical
+    } else if pct < 0.10 || available < 5 * GIB {
+        PressureLevel::Elevated
+    } else {
+        PressureLevel::Normal
+    }
+}
+
+// Space on the volume holding `path`, picked by the longest matching mount point
+fn disk_space(disks: &Disks, path: &Path) -> (u64, u64) {
+    disks
+        .list()
+        .iter()
+        .filter(|d| path.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| (d.available_space(), d.total_space()))
+        .unwrap_or((0, 0))
+}
+
+// Free space on the volume holding `path`, or 0 when it can't be told
+pub fn available_space(path: &Path) -> u64 {
+    disk_space(&Disks::new_with_refreshed_list(), path).0
+}
+
+fn sample(system: &mut System, data_dir: &Path) -> ResourcePressure {
+    system.refresh_memory();
+    let disks = Disks::new_with_refreshed_list();
+    let (available_disk, total_disk) = disk_space(&disks, data_dir);
+
+    let mut pressure = ResourcePressure {
+        available_memory: system.available_memory(),
+        total_memory: system.total_memory(),
+        available_disk,
+        total_disk,
+        ..Default::default()
+    };
+    pressure.memory_level = memory_level(pressure.available_memory, pressure.total_memory);
+    pressure.disk_level = disk_level(available_disk, total_disk);
+    pressure.level = pressure.memory_level.max(pressure.disk_level);
+
+    let mut reasons = Vec::new();
+    if pressure.memory_level != PressureLevel::Normal {
+        reasons.push(format!("low memory ({} MiB free)", pressure.available_memory / (1024 * 1024)));
+    }
+    if pressure.disk_level != PressureLevel::Normal {
+        reasons.push(format!("low disk space ({} MiB free)", available_disk / (1024 * 1024)));
+    }
+    pressure.reason = reasons.join(", ");
+    pressure
+}
+
+// Sample pressure periodically and emit `resource-pressure` whenever the level changes
+pub fn spawn_monitor(app: AppHandle, state: Arc<AppState>) {
+    let data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("/"));
+
+    tauri::async_runtime::spawn(async move {
+        let mut system = System::new();
+        loop {
+            if state.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let dir = data_dir.clone();
+            let (returned, pressure) = match tauri::async_runtime::spawn_blocking(move || {
+                let pressure = sample(&mut system, &dir);
+                (system, pressure)
+            })
+            .await
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    log::error!("Resource sampling failed: {}", e);
+                    break;
+                }
+            };
+            system = returned;
+
+            let previous = std::mem::replace(&mut *state.resources.current.lock().unwrap(), pressure.clone());
+            if previous.level != pressure.level {
+                if pressure.level == PressureLevel::Normal {
+                    log::info!("Resource pressure cleared; resuming background work");
+                } else {
+                    log::warn!("Resource pressure {:?}: {}", pressure.level, pressure.reason);
+                }
+                emit_to_main(&app, "resource-pressure", pressure);
+            }
+
+            activity::sleep(&app, &state, SAMPLE_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_resource_pressure(state: tauri::State<Arc<AppState>>) -> ResourcePressure {
+    state.resources.current.lock().unwrap().clone()
+}
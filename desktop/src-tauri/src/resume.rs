@@ -0,0 +1,159 @@
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::jobs::{self, Job, JobStatus};
+use crate::{emit_to_main, store, AppState};
+
+// Ids of interrupted jobs still waiting for the user's choice, so the prompt comes
+// back if the app quits before one is made
+const PENDING_FILE: &str = "pending_resume.json";
+
+// Sent as `resume-available` at launch when the last session left work unfinished
+#[derive(Clone, Debug, Serialize)]
+pub struct ResumeSummary {
+    pub jobs: Vec<Job>,
+    pub imports: usize,
+    pub remote_copies: usize,
+    pub url_uploads: usize,
+    pub downloads: usize,
+    // Already transferred; resuming skips it
+    pub bytes: u64,
+}
+
+// Imports, remote copies, URL uploads and downloads the last exit interrupted. They stay stopped,
+// shown as paused, until the user resumes or discards them all.
+pub struct PendingResume {
+    ids: Mutex<Vec<String>>,
+}
+
+impl PendingResume {
+    pub fn new() -> Self {
+        Self {
+            ids: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+// Called by each subsystem's restore for a job it didn't restart
+pub fn hold(state: &AppState, id: &str) {
+    let mut ids = state.pending_resume.ids.lock().unwrap();
+    if !ids.iter().any(|held| held == id) {
+        ids.push(id.to_string());
+    }
+}
+
+fn save(app: &AppHandle, state: &AppState) {
+    let ids = state.pending_resume.ids.lock().unwrap().clone();
+    let result = store::data_file(app, PENDING_FILE).and_then(|path| store::save_json(&path, &ids));
+    if let Err(e) = result {
+        log::error!("Failed to save interrupted jobs: {}", e);
+    }
+}
+
+// Held jobs the user hasn't resumed or cancelled one by one since
+fn pending_jobs(state: &AppState) -> Vec<Job> {
+    let ids = state.pending_resume.ids.lock().unwrap().clone();
+    ids.iter()
+        .filter_map(|id| jobs::local_job(state, id))
+        .filter(|job| job.status == JobStatus::Paused)
+        .collect()
+}
+
+fn summary(state: &AppState) -> Option<ResumeSummary> {
+    let jobs = pending_jobs(state);
+    if jobs.is_empty() {
+        return None;
+    }
+    let count = |kind: &str| jobs.iter().filter(|job| job.kind == kind).count();
+    Some(ResumeSummary {
+        imports: count("import"),
+        remote_copies: count("remote_copy"),
+        url_uploads: count("url_upload"),
+        downloads: count("download"),
+        bytes: jobs.iter().filter_map(|job| job.bytes).sum(),
+        jobs,
+    })
+}
+
+// Run after the subsystems' restores: hold what they found interrupted together with
+// what an earlier launch left waiting, and tell the UI there is a choice to make
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let saved: Vec<String> = match store::data_file(app, PENDING_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load interrupted jobs: {}", e);
+            Vec::new()
+        }
+    };
+    // Saved ones were held by that launch already
+    let interrupted = state.pending_resume.ids.lock().unwrap().clone();
+    jobs::hold(app, state, &interrupted);
+    for id in &saved {
+        hold(state, id);
+    }
+
+    let summary = summary(state);
+    // Forget jobs that were dealt with some other way
+    *state.pending_resume.ids.lock().unwrap() = summary
+        .as_ref()
+        .map(|summary| summary.jobs.iter().map(|job| job.id.clone()).collect())
+        .unwrap_or_default();
+    save(app, state);
+    if let Some(summary) = summary {
+        log::info!("{} jobs were interrupted by the last exit", summary.jobs.len());
+        emit_to_main(app, "resume-available", summary);
+    }
+}
+
+// Stop holding every pending job and hand each to `act`. Errors are collected so one
+// job can't keep the others waiting.
+async fn settle<F, Fut>(app: &AppHandle, mut act: F) -> Result<(), String>
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = Result<(), AppError>>,
+{
+    let state = Arc::clone(&app.state::<Arc<AppState>>());
+    let jobs = pending_jobs(&state);
+    state.pending_resume.ids.lock().unwrap().clear();
+    save(app, &state);
+
+    let mut errors = Vec::new();
+    for job in jobs {
+        if let Err(e) = act(job.id.clone()).await {
+            log::warn!("{}: {}", job.label, e);
+            errors.push(format!("{}: {}", job.label, e));
+        }
+        if let Some(job) = jobs::local_job(&state, &job.id) {
+            emit_to_main(app, "job-updated", job);
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+// What the last session left unfinished, if the user hasn't chosen yet; for a UI that
+// missed `resume-available`
+#[tauri::command]
+pub fn get_pending_resume(state: tauri::State<Arc<AppState>>) -> Option<ResumeSummary> {
+    summary(&state)
+}
+
+// Continue every interrupted job where it stopped
+#[tauri::command]
+pub async fn resume_all(app: AppHandle) -> Result<(), AppError> {
+    log::info!("Resuming interrupted jobs");
+    Ok(settle(&app, |id| jobs::retry_job(app.clone(), app.state(), id)).await?)
+}
+
+// Leave every interrupted job cancelled; each can still be retried from the job list
+#[tauri::command]
+pub async fn discard_pending(app: AppHandle) -> Result<(), AppError> {
+    log::info!("Discarding interrupted jobs");
+    Ok(settle(&app, |id| jobs::cancel_job(app.clone(), app.state(), id)).await?)
+}
@@ -0,0 +1,193 @@
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// RFC 3986 unreserved characters stay literal in SigV4 canonical strings
+const SIGV4_ENCODE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+// Connection details for an S3-compatible bucket, addressed path-style
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct S3Location {
+    // e.g. https://s3.us-west-004.backblazeb2.com or http://nas.local:9000
+    pub endpoint: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+    // Omit both keys for public buckets; the secret is never written to disk
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub secret_access_key: Option<String>,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+#[derive(Clone, Debug)]
+pub struct S3Object {
+    pub key: String,
+    pub size: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListBucketResult {
+    #[serde(default)]
+    contents: Vec<ListEntry>,
+    #[serde(default)]
+    is_truncated: bool,
+    #[serde(default)]
+    next_continuation_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ListEntry {
+    key: String,
+    size: u64,
+}
+
+fn encode(s: &str) -> String {
+    utf8_percent_encode(s, SIGV4_ENCODE).to_string()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+impl S3Location {
+    // Build the request URL with SigV4-style encoding so the signed and sent forms match
+    fn object_url(&self, key: &str, query: &[(&str, &str)]) -> Result<Url, String> {
+        let mut url =
+            Url::parse(&self.endpoint).map_err(|e| format!("Invalid S3 endpoint {}: {}", self.endpoint, e))?;
+        let mut path = format!("{}/{}", url.path().trim_end_matches('/'), encode(&self.bucket));
+        if !key.is_empty() {
+            for segment in key.split('/') {
+                path.push('/');
+                path.push_str(&encode(segment));
+            }
+        }
+        url.set_path(&path);
+
+        let mut pairs: Vec<(String, String)> = query.iter().map(|(k, v)| (encode(k), encode(v))).collect();
+        pairs.sort();
+        let canonical_query = pairs
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+        url.set_query((!canonical_query.is_empty()).then_some(canonical_query.as_str()));
+        Ok(url)
+    }
+
+    // Build a GET request, signed with AWS Signature V4 when credentials are configured
+    fn signed_get(
+        &self,
+        client: &reqwest::Client,
+        key: &str,
+        query: &[(&str, &str)],
+    ) -> Result<reqwest::RequestBuilder, String> {
+        let url = self.object_url(key, query)?;
+        let (Some(access_key), Some(secret)) = (&self.access_key_id, &self.secret_access_key) else {
+            return Ok(client.get(url));
+        };
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let canonical_request = format!(
+            "GET\n{}\n{}\nhost:{}\nx-amz-content-sha256:UNSIGNED-PAYLOAD\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\nUNSIGNED-PAYLOAD",
+            url.path(),
+            url.query().unwrap_or_default(),
+            host,
+            amz_date
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let k_date = hmac(format!("AWS4{}", secret).as_bytes(), &date);
+        let k_region = hmac(&k_date, &self.region);
+        let k_service = hmac(&k_region, "s3");
+        let k_signing = hmac(&k_service, "aws4_request");
+        let signature = hex::encode(hmac(&k_signing, &string_to_sign));
+
+        Ok(client
+            .get(url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", "UNSIGNED-PAYLOAD")
+            .header(
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+                    access_key, scope, signature
+                ),
+            ))
+    }
+
+    // Enumerate every object under the configured prefix (ListObjectsV2, paginated)
+    pub async fn list(&self, client: &reqwest::Client) -> Result<Vec<S3Object>, String> {
+        let mut objects = Vec::new();
+        let mut token: Option<String> = None;
+
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", self.prefix.as_str())];
+            if let Some(token) = token.as_deref() {
+                query.push(("continuation-token", token));
+            }
+            let resp = self
+                .signed_get(client, "", &query)?
+                .send()
+                .await
+                .map_err(|e| format!("Failed to list s3://{}/{}: {}", self.bucket, self.prefix, e))?;
+            if !resp.status().is_success() {
+                return Err(format!("Listing s3://{} returned status: {}", self.bucket, resp.status()));
+            }
+            let body = resp.text().await.map_err(|e| e.to_string())?;
+            let page: ListBucketResult =
+                quick_xml::de::from_str(&body).map_err(|e| format!("Invalid S3 listing: {}", e))?;
+
+            objects.extend(
+                page.contents
+                    .into_iter()
+                    .filter(|e| !e.key.ends_with('/'))
+                    .map(|e| S3Object { key: e.key, size: e.size }),
+            );
+            match page.next_continuation_token {
+                Some(next) if page.is_truncated => token = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(objects)
+    }
+
+    // Start downloading an object; the caller streams the response body
+    pub async fn get(&self, client: &reqwest::Client, key: &str) -> Result<reqwest::Response, String> {
+        let resp = self
+            .signed_get(client, key, &[])?
+            .send()
+            .await
+            .map_err(|e| format!("Failed to download s3://{}/{}: {}", self.bucket, key, e))?;
+        if !resp.status().is_success() {
+            return Err(format!("Downloading s3://{}/{} returned status: {}", self.bucket, key, resp.status()));
+        }
+        Ok(resp)
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{emit_to_main, stop_sidecars, store, AppState};
+
+const LAUNCHES_FILE: &str = "launches.json";
+
+// Launches in a row that never got a healthy backend before the next one starts safe
+const FAILURE_THRESHOLD: u32 = 3;
+
+// Launches that haven't reached a healthy backend yet. Counted up as the app starts
+// and reset once the backend is healthy, so a crash, hang or kill in between is left
+// counted.
+#[derive(Default, Serialize, Deserialize)]
+struct Launches {
+    failed: u32,
+}
+
+// Whether this launch started in safe mode: only the sidecar, its supervision and
+// the settings, without resumed jobs, background monitors or a downloaded sidecar
+// update, any of which could be what keeps the app from starting
+pub struct SafeMode {
+    active: AtomicBool,
+    // Failed launches before this one
+    failed: AtomicU32,
+    // Set once this launch's backend was healthy
+    succeeded: AtomicBool,
+}
+
+impl SafeMode {
+    pub fn new() -> Self {
+        Self {
+            active: AtomicBool::new(false),
+            failed: AtomicU32::new(0),
+            succeeded: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::SeqCst)
+    }
+}
+
+// Sent as `safe-mode`, so the UI can explain what was skipped and offer a way out
+#[derive(Clone, Serialize)]
+pub struct SafeModeStatus {
+    pub active: bool,
+    pub failed_launches: u32,
+    pub threshold: u32,
+}
+
+fn status(state: &AppState) -> SafeModeStatus {
+    SafeModeStatus {
+        active: state.safe_mode.is_active(),
+        failed_launches: state.safe_mode.failed.load(Ordering::SeqCst),
+        threshold: FAILURE_THRESHOLD,
+    }
+}
+
+fn save(app: &AppHandle, launches: &Launches) {
+    let result = store::data_file(app, LAUNCHES_FILE).and_then(|path| store::save_json(&path, launches));
+    if let Err(e) = result {
+        log::warn!("Failed to record launch: {}", e);
+    }
+}
+
+// Count this launch as failed until the backend is healthy, and decide from the
+// launches before it whether to start in safe mode. Call before anything else starts.
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let launches: Launches = match store::data_file(app, LAUNCHES_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load launch history: {}", e);
+            return;
+        }
+    };
+    let safe_mode = &state.safe_mode;
+    safe_mode.failed.store(launches.failed, Ordering::SeqCst);
+    if launches.failed >= FAILURE_THRESHOLD {
+        log::warn!(
+            "The last {} launches failed before the backend was healthy; starting in safe mode",
+            launches.failed
+        );
+        safe_mode.active.store(true, Ordering::SeqCst);
+    }
+    save(
+        app,
+        &Launches {
+            failed: launches.failed.saturating_add(1),
+        },
+    );
+}
+
+// The main window loaded; tell it if this is a safe mode launch
+pub fn page_loaded(app: &AppHandle, state: &AppState) {
+    if state.safe_mode.is_active() {
+        emit_to_main(app, "safe-mode", status(state));
+    }
+}
+
+// The backend became healthy: this launch worked, so the next one starts normally
+pub fn launch_succeeded(app: &AppHandle, state: &AppState) {
+    if state.safe_mode.succeeded.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    if state.safe_mode.failed.load(Ordering::SeqCst) > 0 {
+        log::info!("Backend is healthy; cleared the failed launch count");
+    }
+    save(app, &Launches::default());
+}
+
+#[tauri::command]
+pub fn get_safe_mode(state: tauri::State<Arc<AppState>>) -> SafeModeStatus {
+    status(&state)
+}
+
+// Forget the failed launches and restart the app normally
+#[tauri::command]
+pub fn leave_safe_mode(app: AppHandle) {
+    log::info!("Leaving safe mode; restarting");
+    save(&app, &Launches::default());
+    stop_sidecars(&app);
+    app.restart();
+}
@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::url_upload::UrlUploadStatus;
+use crate::{resources, store, transfers, AppState};
+
+pub const SETTINGS_FILE: &str = "scratch.json";
+
+// Folder created inside a chosen scratch location, so cleanup never touches the user's files
+const CUSTOM_DIR: &str = "bb-stream-scratch";
+
+// What scratch space is used for; each gets its own subdirectory
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Area {
+    // Downloaded copies of objects for printing and local checksums
+    Print,
+    // URL uploads downloaded to disk first so they can resume
+    UrlUploads,
+}
+
+impl Area {
+    const ALL: [Area; 2] = [Area::Print, Area::UrlUploads];
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            Area::Print => "print",
+            Area::UrlUploads => "url-uploads",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScratchSettings {
+    // None for the app's cache directory
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AreaUsage {
+    pub area: Area,
+    pub path: PathBuf,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScratchUsage {
+    pub root: PathBuf,
+    pub areas: Vec<AreaUsage>,
+    pub total_bytes: u64,
+    // Free space on the scratch volume; 0 when it can't be told
+    pub available_bytes: u64,
+}
+
+// Where downloads-in-progress and other temporary copies go, so they can live on a
+// bigger disk than the system one
+pub struct Scratch {
+    settings: Mutex<ScratchSettings>,
+}
+
+impl Scratch {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(ScratchSettings::default()),
+        }
+    }
+}
+
+fn root(app: &AppHandle, settings: &ScratchSettings) -> Result<PathBuf, String> {
+    match &settings.dir {
+        Some(dir) => Ok(dir.join(CUSTOM_DIR)),
+        None => app
+            .path()
+            .app_cache_dir()
+            .map_err(|e| format!("Failed to resolve cache directory: {}", e)),
+    }
+}
+
+// Directory of `area` in the current scratch location, created if needed
+pub fn dir(app: &AppHandle, area: Area) -> Result<PathBuf, String> {
+    let settings = app.state::<Arc<AppState>>().scratch.settings.lock().unwrap().clone();
+    let dir = root(app, &settings)?.join(area.dir_name());
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+fn usage(path: &Path) -> (u64, u64) {
+    let (mut files, mut bytes) = (0, 0);
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(listing) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in listing.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                files += 1;
+                bytes += metadata.len();
+            }
+        }
+    }
+    (files, bytes)
+}
+
+// Spooled downloads of URL uploads that can still resume are kept
+fn resumable(state: &AppState) -> HashSet<String> {
+    state
+        .url_uploads
+        .jobs()
+        .into_iter()
+        .filter(|upload| upload.status != UrlUploadStatus::Completed)
+        .map(|upload| format!("{}.part", upload.id))
+        .collect()
+}
+
+// Empty the scratch areas under `root`, except what unfinished transfers still need
+fn clean(root: &Path, keep: &HashSet<String>) {
+    for area in Area::ALL {
+        let dir = root.join(area.dir_name());
+        let Ok(listing) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in listing.flatten() {
+            let path = entry.path();
+            if area == Area::UrlUploads && keep.contains(&entry.file_name().to_string_lossy().to_string()) {
+                continue;
+            }
+            let result = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+// Called as the app quits
+pub fn clean_up(app: &AppHandle, state: &AppState) {
+    let settings = state.scratch.settings.lock().unwrap().clone();
+    match root(app, &settings) {
+        Ok(root) => clean(&root, &resumable(state)),
+        Err(e) => log::warn!("Failed to clean up scratch space: {}", e),
+    }
+}
+
+// Move the spooled downloads unfinished URL uploads continue from; across volumes that
+// takes a copy
+fn move_kept(from: &Path, to: &Path, keep: &HashSet<String>) -> Result<(), String> {
+    let (from, to) = (from.join(Area::UrlUploads.dir_name()), to.join(Area::UrlUploads.dir_name()));
+    for name in keep {
+        let source = from.join(name);
+        if !source.is_file() {
+            continue;
+        }
+        std::fs::create_dir_all(&to).map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+        let target = to.join(name);
+        if std::fs::rename(&source, &target).is_err() {
+            std::fs::copy(&source, &target).map_err(|e| format!("Failed to move {}: {}", source.display(), e))?;
+            let _ = std::fs::remove_file(&source);
+        }
+    }
+    Ok(())
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => *state.scratch.settings.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load scratch settings: {}", e),
+    }
+    // Left behind if the app didn't get to quit normally
+    let (app, state) = (app.clone(), Arc::clone(state));
+    tauri::async_runtime::spawn_blocking(move || clean_up(&app, &state));
+}
+
+#[tauri::command]
+pub fn get_scratch_settings(state: tauri::State<Arc<AppState>>) -> ScratchSettings {
+    state.scratch.settings.lock().unwrap().clone()
+}
+
+// Switch scratch space to `settings.dir` (None for the default). Files unfinished URL
+// uploads continue from move along; everything else in the old location is removed.
+#[tauri::command]
+pub async fn set_scratch_settings(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    settings: ScratchSettings,
+) -> Result<ScratchSettings, AppError> {
+    if let Some(dir) = &settings.dir {
+        if !dir.is_absolute() || !dir.is_dir() {
+            return Err(AppError::invalid(format!("{} is not a folder", dir.display())));
+        }
+    }
+    if transfers::any_running(&state) {
+        return Err(AppError::busy("Scratch space can't move while transfers are running"));
+    }
+    let previous = state.scratch.settings.lock().unwrap().clone();
+    let (from, to) = (root(&app, &previous)?, root(&app, &settings)?);
+    if from != to {
+        std::fs::create_dir_all(&to).map_err(|e| format!("Failed to use {}: {}", to.display(), e))?;
+        let keep = resumable(&state);
+        tauri::async_runtime::spawn_blocking(move || {
+            move_kept(&from, &to, &keep)?;
+            clean(&from, &HashSet::new());
+            if from.ends_with(CUSTOM_DIR) {
+                let _ = std::fs::remove_dir_all(&from);
+            }
+            Ok::<(), String>(())
+        })
+        .await
+        .map_err(|e| format!("Failed to move scratch space: {}", e))??;
+    }
+    store::save_json(&store::data_file(&app, SETTINGS_FILE)?, &settings)?;
+    log::info!("Scratch space is now {}", root(&app, &settings)?.display());
+    *state.scratch.settings.lock().unwrap() = settings.clone();
+    Ok(settings)
+}
+
+// How much each scratch area holds, and the room left for more
+#[tauri::command]
+pub async fn get_scratch_usage(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+) -> Result<ScratchUsage, AppError> {
+    let settings = state.scratch.settings.lock().unwrap().clone();
+    let root = root(&app, &settings)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let areas: Vec<AreaUsage> = Area::ALL
+            .into_iter()
+            .map(|area| {
+                let path = root.join(area.dir_name());
+                let (files, bytes) = usage(&path);
+                AreaUsage { area, path, files, bytes }
+            })
+            .collect();
+        ScratchUsage {
+            total_bytes: areas.iter().map(|area| area.bytes).sum(),
+            available_bytes: resources::available_space(&root),
+            root,
+            areas,
+        }
+    })
+    .await
+    .map_err(|e| format!("Failed to measure scratch space: {}", e).into())
+}
@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::AppState;
+use crate::error::AppError;
+
+// Selections nobody touched for this long are dropped, e.g. after the window reloaded
+const IDLE_TTL: Duration = Duration::from_secs(60 * 60);
+
+// Selected objects of one bucket, in the order they were added
+struct Selection {
+    bucket: String,
+    keys: Vec<String>,
+    index: HashSet<String>,
+    touched: Instant,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SelectionInfo {
+    pub token: String,
+    pub bucket: String,
+    pub count: usize,
+}
+
+impl Selection {
+    fn info(&self, token: &str) -> SelectionInfo {
+        SelectionInfo {
+            token: token.to_string(),
+            bucket: self.bucket.clone(),
+            count: self.keys.len(),
+        }
+    }
+
+    fn add(&mut self, keys: impl IntoIterator<Item = String>) {
+        for key in keys {
+            if self.index.insert(key.clone()) {
+                self.keys.push(key);
+            }
+        }
+    }
+}
+
+// Selections built up in the shell a batch at a time, so commands over many objects take
+// a token instead of every key through the IPC bridge
+pub struct Selections {
+    sets: Mutex<HashMap<String, Selection>>,
+}
+
+impl Selections {
+    pub fn new() -> Self {
+        Self {
+            sets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Run `f` on a live selection, marking it used
+    fn with<T>(&self, token: &str, f: impl FnOnce(&mut Selection) -> T) -> Result<T, String> {
+        let mut sets = self.sets.lock().unwrap();
+        sets.retain(|_, selection| selection.touched.elapsed() < IDLE_TTL);
+        let selection = sets
+            .get_mut(token)
+            .ok_or_else(|| format!("Selection {} has expired", token))?;
+        selection.touched = Instant::now();
+        Ok(f(selection))
+    }
+}
+
+// Keys for a command that takes either a list or a selection token. A selection must
+// belong to `bucket`.
+pub fn resolve(
+    state: &AppState,
+    bucket: &str,
+    keys: Option<Vec<String>>,
+    selection: Option<String>,
+) -> Result<Vec<String>, String> {
+    match (keys, selection) {
+        (Some(keys), None) => Ok(keys),
+        (None, Some(token)) => state.selections.with(&token, |selection| {
+            if selection.bucket == bucket {
+                Ok(selection.keys.clone())
+            } else {
+                Err(format!("Selection {} is of bucket {}", token, selection.bucket))
+            }
+        })?,
+        _ => Err("Pass either keys or a selection".to_string()),
+    }
+}
+
+// Add objects to a selection, starting a new one for `bucket` when no token is given
+#[tauri::command]
+pub fn selection_add(
+    state: tauri::State<Arc<AppState>>,
+    token: Option<String>,
+    bucket: String,
+    keys: Vec<String>,
+) -> Result<SelectionInfo, AppError> {
+    let Some(token) = token else {
+        let token = uuid::Uuid::new_v4().to_string();
+        let mut selection = Selection {
+            bucket,
+            keys: Vec::with_capacity(keys.len()),
+            index: HashSet::with_capacity(keys.len()),
+            touched: Instant::now(),
+        };
+        selection.add(keys);
+        let info = selection.info(&token);
+        state.selections.sets.lock().unwrap().insert(token, selection);
+        return Ok(info);
+    };
+    state.selections.with(&token, |selection| {
+        if selection.bucket != bucket {
+            return Err(AppError::invalid(format!("Selection {} is of bucket {}", token, selection.bucket)));
+        }
+        selection.add(keys);
+        Ok(selection.info(&token))
+    })?
+}
+
+// Add everything in a folder's last listing, for "select all" in folders too large to
+// send back
+#[tauri::command]
+pub fn selection_add_folder(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    token: Option<String>,
+    bucket: String,
+    prefix: String,
+) -> Result<SelectionInfo, AppError> {
+    let listing = state
+        .listings
+        .cached(&app, &bucket, &prefix)
+        .ok_or_else(|| AppError::invalid(format!("{}/{} hasn't been listed yet", bucket, prefix)))?;
+    // Listed names are full keys
+    let keys = listing.objects.into_iter().map(|object| object.name).collect();
+    selection_add(state, token, bucket, keys)
+}
+
+#[tauri::command]
+pub fn selection_remove(
+    state: tauri::State<Arc<AppState>>,
+    token: String,
+    keys: Vec<String>,
+) -> Result<SelectionInfo, AppError> {
+    Ok(state.selections.with(&token, |selection| {
+        let removed: HashSet<String> = keys.into_iter().filter(|key| selection.index.remove(key)).collect();
+        if !removed.is_empty() {
+            selection.keys.retain(|key| !removed.contains(key));
+        }
+        selection.info(&token)
+    })?)
+}
+
+// Empty a selection, or forget it altogether with `release`
+#[tauri::command]
+pub fn selection_clear(state: tauri::State<Arc<AppState>>, token: String, release: Option<bool>) {
+    let mut sets = state.selections.sets.lock().unwrap();
+    if release.unwrap_or(false) {
+        sets.remove(&token);
+    } else if let Some(selection) = sets.get_mut(&token) {
+        selection.keys.clear();
+        selection.index.clear();
+        selection.touched = Instant::now();
+    }
+}
+
+// A page of a selection's keys, for showing what is selected
+#[tauri::command]
+pub fn selection_keys(
+    state: tauri::State<Arc<AppState>>,
+    token: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<Vec<String>, AppError> {
+    Ok(state.selections.with(&token, |selection| {
+        let offset = offset.unwrap_or(0).min(selection.keys.len());
+        let end = offset.saturating_add(limit.unwrap_or(usize::MAX)).min(selection.keys.len());
+        selection.keys[offset..end].to_vec()
+    })?)
+}
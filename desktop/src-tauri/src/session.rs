@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::{backend, emit_to_main, webview_base_url, AppState};
+
+// How long a webview token stays valid; the frontend refreshes it before then
+const WEBVIEW_TOKEN_TTL: Duration = Duration::from_secs(10 * 60);
+
+// How long a copied download link keeps working, within this launch of the app
+const DOWNLOAD_TOKEN_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Shared with the sidecar at spawn; never leaves the shell otherwise. A fresh one per
+// launch means nothing minted by an earlier run is accepted.
+fn secret() -> &'static [u8; 32] {
+    static SECRET: OnceLock<[u8; 32]> = OnceLock::new();
+    SECRET.get_or_init(|| {
+        let mut secret = [0u8; 32];
+        secret[..16].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+        secret[16..].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+        secret
+    })
+}
+
+// "<scope>.<generation>.<expires>.<nonce>.<signature>", checked by the sidecar
+fn mint(scope: &str, generation: u64, expires_at: i64) -> String {
+    sign(format!("{}.{}.{}.{}", scope, generation, expires_at, uuid::Uuid::new_v4().simple()))
+}
+
+fn sign(payload: String) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret()).expect("HMAC accepts any key length");
+    mac.update(payload.as_bytes());
+    format!("{}.{}", payload, hex::encode(mac.finalize().into_bytes()))
+}
+
+// The shell's own credential for backend calls, sent as Basic auth through the base URL.
+// It doesn't expire and isn't affected by revoking webview tokens.
+pub fn shell_credential() -> &'static str {
+    static CREDENTIAL: OnceLock<String> = OnceLock::new();
+    CREDENTIAL.get_or_init(|| mint("shell", 0, 0))
+}
+
+// What the webview needs to open the live-events WebSocket itself, which it only does
+// outside the shell's event stream
+#[derive(Clone, Debug, Serialize)]
+pub struct ApiCredentials {
+    // None while the sidecar isn't listening yet
+    pub base_url: Option<String>,
+    // True when the sidecar listens on a Unix socket, which the webview can't open
+    pub via_shell: bool,
+    pub token: String,
+    // Unix seconds
+    pub expires_at: i64,
+}
+
+// Webview tokens carry a generation; bumping it revokes every token issued before
+pub struct Sessions {
+    generation: AtomicU64,
+}
+
+impl Sessions {
+    pub fn new() -> Self {
+        Self {
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    // Environment the sidecar needs to verify tokens
+    pub fn sidecar_env(&self) -> [(&'static str, String); 2] {
+        [
+            ("BB_SESSION_SECRET", hex::encode(secret())),
+            ("BB_SESSION_GENERATION", self.generation.load(Ordering::SeqCst).to_string()),
+        ]
+    }
+}
+
+// A short-lived webview-scope token and when it expires, in Unix seconds. Requests the
+// shell proxies for the webview carry one too, so the backend treats them alike.
+pub fn webview_token(state: &AppState) -> (String, i64) {
+    let expires_at = chrono::Utc::now().timestamp() + WEBVIEW_TOKEN_TTL.as_secs() as i64;
+    (mint("webview", state.sessions.generation.load(Ordering::SeqCst), expires_at), expires_at)
+}
+
+// A token for links to one object, which opens nothing else. The SHA-256 of
+// "<bucket>/<key>" stands in for the nonce; revoking webview tokens revokes these too.
+pub fn download_token(state: &AppState, bucket: &str, key: &str) -> String {
+    let expires_at = chrono::Utc::now().timestamp() + DOWNLOAD_TOKEN_TTL.as_secs() as i64;
+    let object = hex::encode(Sha256::digest(format!("{}/{}", bucket, key)));
+    let generation = state.sessions.generation.load(Ordering::SeqCst);
+    sign(format!("download.{}.{}.{}", generation, expires_at, object))
+}
+
+// The only way the webview gets a credential: a short-lived token it sends on direct
+// backend calls, so it never holds a long-lived one. Other local processes can't
+// reach the sidecar without one.
+#[tauri::command]
+pub fn get_api_credentials(state: tauri::State<Arc<AppState>>) -> ApiCredentials {
+    let (token, expires_at) = webview_token(&state);
+    ApiCredentials {
+        base_url: webview_base_url(&state),
+        via_shell: state.sidecar().socket.lock().unwrap().is_some(),
+        token,
+        expires_at,
+    }
+}
+
+// Invalidate every webview token issued so far, e.g. when the app locks or the user
+// logs out. The frontend hears `session-revoked` and fetches a new token when needed.
+pub async fn revoke(app: &AppHandle) {
+    let state = Arc::clone(&app.state::<Arc<AppState>>());
+    let generation = state.sessions.generation.fetch_add(1, Ordering::SeqCst) + 1;
+    log::info!("Revoking webview session tokens (generation {})", generation);
+
+    // A sidecar that isn't running picks the generation up from its environment
+    let sidecar = state.sidecar();
+    if sidecar.external_url().is_none() && sidecar.is_reachable() {
+        let base_url = sidecar.base_url();
+        let result = match backend::client_builder(&base_url).timeout(Duration::from_secs(5)).build() {
+            Ok(client) => backend::revoke_sessions(&client, &base_url, generation).await,
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = result {
+            log::warn!("Failed to revoke session tokens in the backend: {}", e);
+        }
+    }
+    emit_to_main(app, "session-revoked", generation);
+}
+
+#[tauri::command]
+pub async fn revoke_session_tokens(app: AppHandle) {
+    revoke(&app).await;
+}
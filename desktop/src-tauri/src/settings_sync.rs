@@ -0,0 +1,582 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::{
+    backend, dock_drop, emit_to_main, network, quiet_hours, store, suggestions, thumbnails, view_prefs, AppState,
+};
+
+const SETTINGS_FILE: &str = "settings_sync.json";
+
+// What was last synced, per category; the base that tells which side changed what
+const BASE_FILE: &str = "settings_sync_base.json";
+
+// Hidden app folder in the chosen bucket that holds one encrypted file per category
+const REMOTE_PREFIX: &str = ".bb-stream/settings/";
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+// Argon2id cost turning the passphrase into a key: memory in KiB, then passes
+const KDF_MEMORY: u32 = 64 * 1024;
+const KDF_PASSES: u32 = 3;
+
+const ENVELOPE_VERSION: u32 = 2;
+
+// Key under which a file synced as a whole keeps its contents
+const WHOLE_FILE: &str = "";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncCategory {
+    // View preferences, thumbnails, quiet hours and Wi-Fi only
+    Preferences,
+    // The dock drop destination and upload history behind destination suggestions
+    Destinations,
+}
+
+// How a file's changes merge: per top-level key for maps of independent entries, or
+// the whole file when its fields only make sense together
+#[derive(Clone, Copy, PartialEq)]
+enum Granularity {
+    Keys,
+    Whole,
+}
+
+impl SyncCategory {
+    const ALL: [SyncCategory; 2] = [SyncCategory::Preferences, SyncCategory::Destinations];
+
+    fn name(self) -> &'static str {
+        match self {
+            SyncCategory::Preferences => "preferences",
+            SyncCategory::Destinations => "destinations",
+        }
+    }
+
+    fn files(self) -> &'static [(&'static str, Granularity)] {
+        match self {
+            SyncCategory::Preferences => &[
+                (view_prefs::SETTINGS_FILE, Granularity::Keys),
+                (thumbnails::SETTINGS_FILE, Granularity::Whole),
+                (quiet_hours::SETTINGS_FILE, Granularity::Whole),
+                (network::SETTINGS_FILE, Granularity::Whole),
+            ],
+            SyncCategory::Destinations => &[
+                (dock_drop::DESTINATION_FILE, Granularity::Whole),
+                (suggestions::HISTORY_FILE, Granularity::Whole),
+            ],
+        }
+    }
+
+    // Load the category's files again after a sync replaced them
+    fn reload(self, app: &AppHandle, state: &Arc<AppState>) {
+        match self {
+            SyncCategory::Preferences => {
+                view_prefs::restore(app, state);
+                thumbnails::restore(app, state);
+                quiet_hours::restore(app, state);
+                network::restore(app, state);
+            }
+            SyncCategory::Destinations => {
+                dock_drop::restore(app, state);
+                suggestions::restore(app, state);
+            }
+        }
+    }
+
+    fn remote_key(self) -> String {
+        format!("{}{}.json.enc", REMOTE_PREFIX, self.name())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SyncConfig {
+    enabled: bool,
+    bucket: Option<String>,
+    categories: BTreeSet<SyncCategory>,
+    passphrase: Option<String>,
+    // Names this machine in synced entries
+    machine_id: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SettingsSyncInfo {
+    pub enabled: bool,
+    pub bucket: Option<String>,
+    pub categories: BTreeSet<SyncCategory>,
+    pub has_passphrase: bool,
+    pub last_sync: Option<SyncReport>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+// A setting changed on both machines since the last sync; the newer change was kept
+#[derive(Clone, Debug, Serialize)]
+pub struct SyncConflict {
+    pub category: SyncCategory,
+    pub file: String,
+    // None when the whole file conflicted
+    pub key: Option<String>,
+    pub kept: Side,
+}
+
+// Sent as `settings-synced`
+#[derive(Clone, Debug, Serialize)]
+pub struct SyncReport {
+    // Unix milliseconds
+    pub synced_at: i64,
+    // Categories whose changes were sent to the bucket
+    pub uploaded: Vec<SyncCategory>,
+    // Categories changed here by another machine's changes
+    pub downloaded: Vec<SyncCategory>,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+// One synced setting: a top-level key of a file, or a whole file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    value: Value,
+    // Unix milliseconds of the change, to settle conflicts
+    updated_at: i64,
+    machine: String,
+}
+
+// A category's settings: file name -> key -> entry
+type Doc = BTreeMap<String, BTreeMap<String, Entry>>;
+
+#[derive(Default, Serialize, Deserialize)]
+struct Base {
+    // The base only holds for the bucket it was synced with
+    bucket: Option<String>,
+    docs: BTreeMap<SyncCategory, Doc>,
+}
+
+// The encrypted form of a category's Doc in the bucket: XChaCha20-Poly1305 under a key
+// derived from the passphrase and salt by Argon2id, with the version and salt
+// authenticated alongside
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: u32,
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+// Encrypted sync of settings through a hidden folder in the user's own bucket.
+// Only what changed since the last sync moves in either direction.
+pub struct SettingsSync {
+    config: Mutex<SyncConfig>,
+    running: AtomicBool,
+    last: Mutex<Option<SyncReport>>,
+}
+
+impl SettingsSync {
+    pub fn new() -> Self {
+        Self {
+            config: Mutex::new(SyncConfig::default()),
+            running: AtomicBool::new(false),
+            last: Mutex::new(None),
+        }
+    }
+}
+
+fn info(state: &AppState) -> SettingsSyncInfo {
+    let config = state.settings_sync.config.lock().unwrap();
+    SettingsSyncInfo {
+        enabled: config.enabled,
+        bucket: config.bucket.clone(),
+        categories: config.categories.clone(),
+        has_passphrase: config.passphrase.as_deref().is_some_and(|p| !p.is_empty()),
+        last_sync: state.settings_sync.last.lock().unwrap().clone(),
+    }
+}
+
+fn save_config(app: &AppHandle, config: &SyncConfig) -> Result<(), String> {
+    store::data_file(app, SETTINGS_FILE).and_then(|path| store::save_json(&path, config))
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let mut config: SyncConfig = match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load settings sync configuration: {}", e);
+            return;
+        }
+    };
+    if config.machine_id.is_empty() {
+        config.machine_id = uuid::Uuid::new_v4().to_string();
+        if let Err(e) = save_config(app, &config) {
+            log::warn!("Failed to save settings sync configuration: {}", e);
+        }
+    }
+    *state.settings_sync.config.lock().unwrap() = config;
+}
+
+// Sync in the background while enabled
+pub fn spawn_monitor(app: AppHandle, state: Arc<AppState>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SYNC_INTERVAL).await;
+            if state.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            if !state.settings_sync.config.lock().unwrap().enabled || !state.is_healthy() {
+                continue;
+            }
+            if let Err(e) = sync(&app, &state).await {
+                log::warn!("Settings sync failed: {}", e);
+            }
+        }
+    });
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let params = Params::new(KDF_MEMORY, KDF_PASSES, 1, Some(32)).map_err(|e| e.to_string())?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive the settings key: {}", e))?;
+    Ok(key)
+}
+
+// Derived keys by salt, so a sync runs the slow derivation once per salt
+type KeyCache = HashMap<Vec<u8>, [u8; 32]>;
+
+async fn key_for(cache: &mut KeyCache, passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    if let Some(key) = cache.get(salt) {
+        return Ok(*key);
+    }
+    let (passphrase_owned, salt_owned) = (passphrase.to_string(), salt.to_vec());
+    let key = tauri::async_runtime::spawn_blocking(move || derive_key(&passphrase_owned, &salt_owned))
+        .await
+        .map_err(|e| e.to_string())??;
+    cache.insert(salt.to_vec(), key);
+    Ok(key)
+}
+
+// The version and salt aren't secret but mustn't be swapped out
+fn associated_data(salt: &[u8]) -> Vec<u8> {
+    [&ENVELOPE_VERSION.to_be_bytes()[..], salt].concat()
+}
+
+async fn seal(cache: &mut KeyCache, passphrase: &str, salt: &[u8], doc: &Doc) -> Result<Vec<u8>, String> {
+    let key = key_for(cache, passphrase, salt).await?;
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let plaintext = serde_json::to_vec(doc).map_err(|e| e.to_string())?;
+    let aad = associated_data(salt);
+    let data = XChaCha20Poly1305::new(&key.into())
+        .encrypt(&nonce, Payload { msg: &plaintext, aad: &aad })
+        .map_err(|_| "Failed to encrypt settings".to_string())?;
+    let envelope = Envelope {
+        version: ENVELOPE_VERSION,
+        salt: hex::encode(salt),
+        nonce: hex::encode(nonce),
+        data: hex::encode(data),
+    };
+    serde_json::to_vec(&envelope).map_err(|e| e.to_string())
+}
+
+// Decrypt a category's Doc, returning it with the salt it was sealed with
+async fn open(cache: &mut KeyCache, passphrase: &str, bytes: &[u8]) -> Result<(Doc, Vec<u8>), String> {
+    let envelope: Envelope =
+        serde_json::from_slice(bytes).map_err(|e| format!("Synced settings are unreadable: {}", e))?;
+    if envelope.version != ENVELOPE_VERSION {
+        return Err(format!("Synced settings use version {}; update BB Stream", envelope.version));
+    }
+    let decode = |text: &str| hex::decode(text).map_err(|e| format!("Synced settings are unreadable: {}", e));
+    let (salt, nonce, data) = (decode(&envelope.salt)?, decode(&envelope.nonce)?, decode(&envelope.data)?);
+    if nonce.len() != 24 {
+        return Err("Synced settings are unreadable: bad nonce".to_string());
+    }
+    let key = key_for(cache, passphrase, &salt).await?;
+    let aad = associated_data(&salt);
+    let plaintext = XChaCha20Poly1305::new(&key.into())
+        .decrypt(XNonce::from_slice(&nonce), Payload { msg: &data, aad: &aad })
+        .map_err(|_| "Wrong passphrase, or the synced settings were changed outside BB Stream".to_string())?;
+    let doc = serde_json::from_slice(&plaintext).map_err(|e| format!("Synced settings are unreadable: {}", e))?;
+    Ok((doc, salt))
+}
+
+// The category's files as they are on disk. Entries unchanged since the base keep
+// its date; changed ones are dated by the file's modification time.
+fn local_doc(app: &AppHandle, category: SyncCategory, base: &Doc, machine: &str, now: i64) -> Doc {
+    let mut doc = Doc::new();
+    for &(file, granularity) in category.files() {
+        let Ok(path) = store::data_file(app, file) else {
+            continue;
+        };
+        let Ok(bytes) = std::fs::read(&path) else {
+            continue;
+        };
+        let modified = std::fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(now, |since| since.as_millis() as i64);
+        let Ok(value) = serde_json::from_slice::<Value>(&bytes) else {
+            log::warn!("Not syncing unreadable {}", path.display());
+            continue;
+        };
+        let values: Vec<(String, Value)> = match value {
+            Value::Object(map) if granularity == Granularity::Keys => map.into_iter().collect(),
+            value => vec![(WHOLE_FILE.to_string(), value)],
+        };
+        let base_file = base.get(file);
+        let entries = values
+            .into_iter()
+            .map(|(key, value)| {
+                let entry = match base_file.and_then(|entries| entries.get(&key)) {
+                    Some(synced) if synced.value == value => synced.clone(),
+                    _ => Entry {
+                        value,
+                        updated_at: modified,
+                        machine: machine.to_string(),
+                    },
+                };
+                (key, entry)
+            })
+            .collect();
+        doc.insert(file.to_string(), entries);
+    }
+    doc
+}
+
+fn value_of<'a>(doc: &'a Doc, file: &str, key: &str) -> Option<&'a Value> {
+    doc.get(file).and_then(|entries| entries.get(key)).map(|entry| &entry.value)
+}
+
+fn entry_of(doc: &Doc, file: &str, key: &str) -> Option<Entry> {
+    doc.get(file).and_then(|entries| entries.get(key)).cloned()
+}
+
+// Three-way merge: a side's change since the base wins over no change, and when both
+// sides changed a setting differently the later change wins
+fn merge(
+    category: SyncCategory,
+    local: &Doc,
+    remote: &Doc,
+    base: &Doc,
+    now: i64,
+    conflicts: &mut Vec<SyncConflict>,
+) -> Doc {
+    let mut keys = BTreeSet::new();
+    for doc in [local, remote, base] {
+        for (file, entries) in doc {
+            keys.extend(entries.keys().map(|key| (file.clone(), key.clone())));
+        }
+    }
+
+    let mut merged = Doc::new();
+    for (file, key) in keys {
+        let base_value = value_of(base, &file, &key);
+        let local_changed = value_of(local, &file, &key) != base_value;
+        let remote_changed = value_of(remote, &file, &key) != base_value;
+        let (local_entry, remote_entry) = (entry_of(local, &file, &key), entry_of(remote, &file, &key));
+        let kept = match (local_changed, remote_changed) {
+            (_, false) => local_entry,
+            (false, true) => remote_entry,
+            (true, true) if local_entry.as_ref().map(|e| &e.value) == remote_entry.as_ref().map(|e| &e.value) => {
+                local_entry
+            }
+            (true, true) => {
+                // A deletion isn't dated; it counts as made at the time of the sync
+                let local_at = local_entry.as_ref().map_or(now, |e| e.updated_at);
+                let remote_at = remote_entry.as_ref().map_or(now, |e| e.updated_at);
+                let side = if remote_at > local_at { Side::Remote } else { Side::Local };
+                conflicts.push(SyncConflict {
+                    category,
+                    file: file.clone(),
+                    key: (key != WHOLE_FILE).then(|| key.clone()),
+                    kept: side,
+                });
+                match side {
+                    Side::Local => local_entry,
+                    Side::Remote => remote_entry,
+                }
+            }
+        };
+        if let Some(entry) = kept {
+            merged.entry(file).or_default().insert(key, entry);
+        }
+    }
+    merged
+}
+
+fn same_values(a: &Doc, b: &Doc) -> bool {
+    let values = |doc: &Doc| -> BTreeMap<(String, String), Value> {
+        doc.iter()
+            .flat_map(|(file, entries)| {
+                entries.iter().map(move |(key, entry)| ((file.clone(), key.clone()), entry.value.clone()))
+            })
+            .collect()
+    };
+    values(a) == values(b)
+}
+
+// Write merged settings over the category's files
+fn write_local(app: &AppHandle, category: SyncCategory, merged: &Doc) -> Result<(), String> {
+    for &(file, _) in category.files() {
+        let path = store::data_file(app, file)?;
+        match merged.get(file).filter(|entries| !entries.is_empty()) {
+            None => {
+                if path.exists() {
+                    std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+                }
+            }
+            Some(entries) => {
+                let value = match entries.get(WHOLE_FILE) {
+                    Some(entry) if entries.len() == 1 => entry.value.clone(),
+                    _ => Value::Object(entries.iter().map(|(key, entry)| (key.clone(), entry.value.clone())).collect()),
+                };
+                store::save_json(&path, &value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn download(base_url: &str, bucket: &str, key: &str) -> Result<Vec<u8>, String> {
+    let resp = backend::shared_client(base_url)
+        .get(backend::download_url(base_url, bucket, key)?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", key, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Downloading {} returned status: {}", key, resp.status()));
+    }
+    resp.bytes().await.map(|bytes| bytes.to_vec()).map_err(|e| e.to_string())
+}
+
+async fn sync(app: &AppHandle, state: &Arc<AppState>) -> Result<SyncReport, String> {
+    if state.settings_sync.running.swap(true, Ordering::SeqCst) {
+        return Err("Settings are already being synced".to_string());
+    }
+    let result = run_sync(app, state).await;
+    state.settings_sync.running.store(false, Ordering::SeqCst);
+
+    let report = result?;
+    if !report.downloaded.is_empty() || !report.uploaded.is_empty() {
+        log::info!(
+            "Synced settings: sent {:?}, received {:?}, {} conflicts",
+            report.uploaded,
+            report.downloaded,
+            report.conflicts.len()
+        );
+    }
+    *state.settings_sync.last.lock().unwrap() = Some(report.clone());
+    emit_to_main(app, "settings-synced", report.clone());
+    Ok(report)
+}
+
+async fn run_sync(app: &AppHandle, state: &Arc<AppState>) -> Result<SyncReport, String> {
+    let config = state.settings_sync.config.lock().unwrap().clone();
+    let bucket = config.bucket.clone().ok_or("Choose a bucket to sync settings through")?;
+    let passphrase = config.passphrase.clone().filter(|p| !p.is_empty()).ok_or("Set a passphrase first")?;
+
+    let base_path = store::data_file(app, BASE_FILE)?;
+    let mut base: Base = store::load_json(&base_path);
+    if base.bucket.as_deref() != Some(bucket.as_str()) {
+        base = Base {
+            bucket: Some(bucket.clone()),
+            docs: BTreeMap::new(),
+        };
+    }
+
+    let base_url = state.base_url();
+    let client = backend::shared_client(&base_url);
+    let present: BTreeSet<String> = backend::list_objects(&client, &base_url, &bucket, REMOTE_PREFIX)
+        .await?
+        .into_iter()
+        .map(|object| object.name)
+        .collect();
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut cache = KeyCache::new();
+    let mut report = SyncReport {
+        synced_at: now,
+        uploaded: Vec::new(),
+        downloaded: Vec::new(),
+        conflicts: Vec::new(),
+    };
+    for category in SyncCategory::ALL {
+        if !config.categories.contains(&category) {
+            continue;
+        }
+        let key = category.remote_key();
+        let (remote, salt) = if present.contains(&key) {
+            let (doc, salt) = open(&mut cache, &passphrase, &download(&base_url, &bucket, &key).await?).await?;
+            (doc, Some(salt))
+        } else {
+            (Doc::new(), None)
+        };
+        let category_base = base.docs.get(&category).cloned().unwrap_or_default();
+        let local = local_doc(app, category, &category_base, &config.machine_id, now);
+        let merged = merge(category, &local, &remote, &category_base, now, &mut report.conflicts);
+
+        if !same_values(&merged, &local) {
+            write_local(app, category, &merged)?;
+            category.reload(app, state);
+            report.downloaded.push(category);
+        }
+        if !present.contains(&key) || !same_values(&merged, &remote) {
+            let salt = salt.unwrap_or_else(|| uuid::Uuid::new_v4().as_bytes().to_vec());
+            let sealed = seal(&mut cache, &passphrase, &salt, &merged).await?;
+            backend::upload_stream(&client, &base_url, &bucket, &key, reqwest::Body::from(sealed)).await?;
+            report.uploaded.push(category);
+        }
+        base.docs.insert(category, merged);
+        store::save_json(&base_path, &base)?;
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn get_settings_sync(state: tauri::State<Arc<AppState>>) -> SettingsSyncInfo {
+    info(&state)
+}
+
+// Turn settings sync on or off and choose what syncs where. A passphrase of None
+// keeps the current one; changing it means other machines need the new one too.
+#[tauri::command]
+pub fn set_settings_sync(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    enabled: bool,
+    bucket: Option<String>,
+    categories: BTreeSet<SyncCategory>,
+    passphrase: Option<String>,
+) -> Result<SettingsSyncInfo, AppError> {
+    let bucket = bucket.map(|b| b.trim().to_string()).filter(|b| !b.is_empty());
+    let mut config = state.settings_sync.config.lock().unwrap().clone();
+    if let Some(passphrase) = passphrase {
+        config.passphrase = Some(passphrase).filter(|p| !p.is_empty());
+    }
+    if enabled && (bucket.is_none() || config.passphrase.is_none()) {
+        return Err(AppError::invalid("Settings sync needs a bucket and a passphrase"));
+    }
+    config.enabled = enabled;
+    config.bucket = bucket;
+    config.categories = categories;
+    save_config(&app, &config)?;
+    *state.settings_sync.config.lock().unwrap() = config;
+    Ok(info(&state))
+}
+
+#[tauri::command]
+pub async fn sync_settings_now(app: AppHandle, state: tauri::State<'_, Arc<AppState>>) -> Result<SyncReport, AppError> {
+    Ok(sync(&app, &state).await?)
+}
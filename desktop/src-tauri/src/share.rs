@@ -0,0 +1,148 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::AppError;
+use crate::permissions::{self, Capability};
+use crate::AppState;
+
+// What gets handed to the share sheet
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+enum ShareTarget {
+    File(PathBuf),
+    Link(String),
+}
+
+impl ShareTarget {
+    fn parse(path_or_link: &str) -> Result<Self, String> {
+        if path_or_link.starts_with("https://") || path_or_link.starts_with("http://") {
+            return Ok(ShareTarget::Link(path_or_link.to_string()));
+        }
+        let path = PathBuf::from(path_or_link);
+        if !path.is_file() {
+            return Err(format!("{} is not a downloaded file or a link", path_or_link));
+        }
+        Ok(ShareTarget::File(path))
+    }
+}
+
+// Show the OS share sheet for a downloaded file or a share link, anchored to the
+// calling window, so content can go straight to Mail, Messages, AirDrop and the like
+#[tauri::command]
+pub async fn share_file(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    window: WebviewWindow,
+    path_or_link: String,
+) -> Result<(), AppError> {
+    let target = ShareTarget::parse(&path_or_link).map_err(AppError::invalid)?;
+    if let ShareTarget::File(path) = &target {
+        let detail = format!("sharing {}", path.display());
+        permissions::ensure(&app, &state, Capability::ReadLocalFiles, &detail).await?;
+    }
+
+    // Share UI belongs to the window's UI thread
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let anchor = window.clone();
+    window
+        .run_on_main_thread(move || {
+            let _ = tx.send(show_share_sheet(&anchor, target));
+        })
+        .map_err(|e| format!("Failed to open share sheet: {}", e))?;
+    Ok(rx.await.map_err(|e| format!("Failed to open share sheet: {}", e))??)
+}
+
+#[cfg(target_os = "macos")]
+fn show_share_sheet(window: &WebviewWindow, target: ShareTarget) -> Result<(), String> {
+    use std::cell::RefCell;
+
+    use objc2::rc::Retained;
+    use objc2::runtime::AnyObject;
+    use objc2_app_kit::{NSSharingServicePicker, NSView};
+    use objc2_foundation::{NSArray, NSPoint, NSRect, NSRectEdge, NSSize, NSString, NSURL};
+
+    thread_local! {
+        // AppKit doesn't retain the picker while it is on screen
+        static PICKER: RefCell<Option<Retained<NSSharingServicePicker>>> = const { RefCell::new(None) };
+    }
+
+    let item: Retained<AnyObject> = match target {
+        ShareTarget::File(path) => NSURL::fileURLWithPath(&NSString::from_str(&path.to_string_lossy())).into(),
+        ShareTarget::Link(link) => NSURL::URLWithString(&NSString::from_str(&link))
+            .ok_or_else(|| format!("Invalid link: {}", link))?
+            .into(),
+    };
+    let items: Retained<NSArray> = NSArray::from_retained_slice(&[item]);
+
+    let view = window
+        .ns_view()
+        .map_err(|e| format!("Failed to open share sheet: {}", e))?;
+    // SAFETY: the webview's NSView outlives this call, which runs on the main thread
+    let view = unsafe { &*view.cast::<NSView>() };
+    let bounds = view.bounds();
+    let anchor = NSRect::new(
+        NSPoint::new(bounds.size.width / 2.0, bounds.size.height / 2.0),
+        NSSize::new(1.0, 1.0),
+    );
+
+    // SAFETY: every item is an NSURL, which the picker accepts
+    let picker = unsafe { NSSharingServicePicker::initWithItems(NSSharingServicePicker::alloc(), &items) };
+    picker.showRelativeToRect_ofView_preferredEdge(anchor, view, NSRectEdge::NSMinYEdge);
+    PICKER.with(|current| *current.borrow_mut() = Some(picker));
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn show_share_sheet(window: &WebviewWindow, target: ShareTarget) -> Result<(), String> {
+    use std::sync::Mutex;
+
+    use windows::core::{factory, Interface, Ref, HSTRING};
+    use windows::ApplicationModel::DataTransfer::{DataRequestedEventArgs, DataTransferManager};
+    use windows::Foundation::{TypedEventHandler, Uri};
+    use windows::Storage::{IStorageItem, StorageFile};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::Shell::IDataTransferManagerInterop;
+    use windows_collections::IIterable;
+
+    // Handler registered by the previous share, replaced by each new one
+    static HANDLER_TOKEN: Mutex<Option<i64>> = Mutex::new(None);
+
+    let hwnd = HWND(window.hwnd().map_err(|e| format!("Failed to open share sheet: {}", e))?.0);
+    let share_error = |e: windows::core::Error| format!("Failed to open share sheet: {}", e);
+    let interop = factory::<DataTransferManager, IDataTransferManagerInterop>().map_err(share_error)?;
+    let manager: DataTransferManager = unsafe { interop.GetForWindow(hwnd) }.map_err(share_error)?;
+
+    let handler = TypedEventHandler::<DataTransferManager, DataRequestedEventArgs>::new(
+        move |_, args: Ref<DataRequestedEventArgs>| {
+            let data = args.ok()?.Request()?.Data()?;
+            match &target {
+                ShareTarget::File(path) => {
+                    let name = path.file_name().unwrap_or(path.as_os_str());
+                    data.Properties()?.SetTitle(&HSTRING::from(name))?;
+                    let file = StorageFile::GetFileFromPathAsync(&HSTRING::from(path.as_path()))?.join()?;
+                    let items: IIterable<IStorageItem> = vec![Some(file.cast::<IStorageItem>()?)].into();
+                    data.SetStorageItemsReadOnly(&items)?;
+                }
+                ShareTarget::Link(link) => {
+                    data.Properties()?.SetTitle(&HSTRING::from(link.as_str()))?;
+                    data.SetWebLink(&Uri::CreateUri(&HSTRING::from(link.as_str()))?)?;
+                }
+            }
+            Ok(())
+        },
+    );
+
+    let mut token = HANDLER_TOKEN.lock().unwrap();
+    if let Some(previous) = token.take() {
+        let _ = manager.RemoveDataRequested(previous);
+    }
+    *token = Some(manager.DataRequested(&handler).map_err(share_error)?);
+    unsafe { interop.ShowShareUIForWindow(hwnd) }.map_err(share_error)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn show_share_sheet(_window: &WebviewWindow, _target: ShareTarget) -> Result<(), String> {
+    // Neither GTK nor the freedesktop portals offer a system share sheet
+    Err("Sharing isn't available on this platform".to_string())
+}
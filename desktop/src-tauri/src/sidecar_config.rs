@@ -0,0 +1,539 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::{backend, emit_to_main, external, sidecar_update, store, transfers, AppState, Sidecar};
+
+const SETTINGS_FILE: &str = "sidecar_config.json";
+
+// How long a running sidecar gets to take a live change before it waits for a restart
+const LIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How often a scheduled restart checks whether the sidecars are idle
+const RESTART_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// Arguments every spawned sidecar gets, followed by the port or socket path; it
+// reports the port or socket it listens on on stdout
+const TCP_ARGS: [&str; 2] = ["serve", "--port"];
+const SOCKET_ARGS: [&str; 2] = ["serve", "--socket"];
+
+const LOG_LEVELS: [&str; 4] = ["debug", "info", "warn", "error"];
+
+// Variables the shell sets itself on every spawn; user settings can't replace them
+const RESERVED_ENV: [&str; 3] = ["BB_SESSION_SECRET", "BB_SESSION_GENERATION", "BB_INSTANCE_ID"];
+
+pub fn validate_log_level(level: &str) -> Result<(), String> {
+    if !LOG_LEVELS.contains(&level) {
+        return Err(format!("Log level must be one of {}", LOG_LEVELS.join(", ")));
+    }
+    Ok(())
+}
+
+// How the shell reaches a spawned sidecar
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    // A free localhost port
+    #[default]
+    Tcp,
+    // A Unix domain socket only this user can open: no port conflicts and nothing
+    // reachable over the network. The webview's requests go through api_request
+    // and its live events through the shell's event stream anyway.
+    Socket,
+}
+
+// Ports to try, inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+// Extra configuration handed to every spawned sidecar. The log level changes on running
+// sidecars too; everything else applies the next time a sidecar starts.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SidecarSettings {
+    // Passed as BB_CONFIG, replacing config.yaml in the data directory
+    pub config_file: Option<PathBuf>,
+    // Passed as BB_LOG_LEVEL
+    pub log_level: Option<String>,
+    // Passed as BB_DATA_DIR
+    pub data_dir: Option<PathBuf>,
+    // Any other variables, e.g. proxy settings
+    pub env: BTreeMap<String, String>,
+    pub transport: Transport,
+    // Port to listen on with the TCP transport, e.g. one a firewall rule allows; the
+    // range is tried when it is taken. Without either any free port is used.
+    pub preferred_port: Option<u16>,
+    pub port_range: Option<PortRange>,
+}
+
+impl SidecarSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.transport == Transport::Socket && !cfg!(unix) {
+            return Err("The socket transport is only available on macOS and Linux".to_string());
+        }
+        if self.preferred_port == Some(0) {
+            return Err("The preferred port must be 1 to 65535".to_string());
+        }
+        if let Some(range) = self.port_range {
+            if range.start == 0 || range.start > range.end {
+                return Err(format!("{}-{} is not a valid port range", range.start, range.end));
+            }
+        }
+        if let Some(path) = &self.config_file {
+            if !path.is_absolute() || !path.is_file() {
+                return Err(format!("Config file {} must be an existing absolute path", path.display()));
+            }
+        }
+        if let Some(level) = &self.log_level {
+            validate_log_level(level)?;
+        }
+        if let Some(dir) = &self.data_dir {
+            if !dir.is_absolute() {
+                return Err(format!("Data directory {} must be an absolute path", dir.display()));
+            }
+        }
+        for name in self.env.keys() {
+            let valid = !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(format!("{:?} is not a valid environment variable name", name));
+            }
+            if RESERVED_ENV.contains(&name.as_str()) {
+                return Err(format!("{} is set by the app and can't be overridden", name));
+            }
+        }
+        Ok(())
+    }
+
+    // Settings changed from `previous` that a running sidecar only picks up by restarting
+    fn restart_changes(&self, previous: &SidecarSettings) -> Vec<&'static str> {
+        let changes = [
+            ("config_file", self.config_file != previous.config_file),
+            ("data_dir", self.data_dir != previous.data_dir),
+            ("env", self.env != previous.env),
+            ("transport", self.transport != previous.transport),
+            ("preferred_port", self.preferred_port != previous.preferred_port),
+            ("port_range", self.port_range != previous.port_range),
+        ];
+        changes.into_iter().filter(|(_, changed)| *changed).map(|(name, _)| name).collect()
+    }
+
+    // Variables for the spawned command, before the shell's own
+    pub fn env(&self) -> Vec<(String, String)> {
+        let mut env: Vec<(String, String)> = self.env.clone().into_iter().collect();
+        if let Some(path) = &self.config_file {
+            env.push(("BB_CONFIG".to_string(), path.display().to_string()));
+        }
+        if let Some(level) = &self.log_level {
+            env.push(("BB_LOG_LEVEL".to_string(), level.clone()));
+        }
+        if let Some(dir) = &self.data_dir {
+            env.push(("BB_DATA_DIR".to_string(), dir.display().to_string()));
+        }
+        env
+    }
+}
+
+// Socket a sidecar spawned as `instance_id` listens on with the socket transport.
+// Kept short: socket paths are limited to about 100 bytes. The per-user runtime
+// directory is preferred over the shared temp directory where there is one.
+pub fn socket_path(instance_id: &str) -> PathBuf {
+    let id: String = instance_id.chars().filter(char::is_ascii_alphanumeric).take(12).collect();
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    dir.join(format!("bb-stream-{}.sock", id))
+}
+
+// Command-line arguments for a sidecar listening on `socket`, or on `port` when None
+pub fn args(socket: Option<&Path>, port: u16) -> Vec<String> {
+    match socket {
+        Some(path) => SOCKET_ARGS
+            .iter()
+            .map(|arg| arg.to_string())
+            .chain([path.display().to_string()])
+            .collect(),
+        None => TCP_ARGS
+            .iter()
+            .map(|arg| arg.to_string())
+            .chain([port.to_string()])
+            .collect(),
+    }
+}
+
+// The sidecar listens on all interfaces, so that is where a port must be free
+fn is_free(port: u16) -> bool {
+    TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok()
+}
+
+// Port a sidecar is told to listen on: the preferred one, else the first free one in
+// the range, else one the OS picks. Ports are only probed, so another process can
+// still take one first; the sidecar then fails to start and is retried.
+pub struct PortChoice {
+    pub port: u16,
+    // Set when the preferred port was taken and another had to be used
+    pub preferred_taken: Option<u16>,
+}
+
+pub fn choose_port(settings: &SidecarSettings) -> PortChoice {
+    if settings.preferred_port.is_none() && settings.port_range.is_none() {
+        // The sidecar picks a free port itself
+        return PortChoice {
+            port: 0,
+            preferred_taken: None,
+        };
+    }
+    if let Some(port) = settings.preferred_port.filter(|port| is_free(*port)) {
+        return PortChoice {
+            port,
+            preferred_taken: None,
+        };
+    }
+    let port = settings
+        .port_range
+        .and_then(|range| (range.start..=range.end).find(|port| is_free(*port)))
+        .or_else(|| {
+            // Pick it here so the warning can say which port was used
+            TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))
+                .and_then(|listener| listener.local_addr())
+                .map(|addr| addr.port())
+                .ok()
+        })
+        .unwrap_or(0);
+    PortChoice {
+        port,
+        preferred_taken: settings.preferred_port,
+    }
+}
+
+// Settings a running sidecar is still without, until it restarts
+#[derive(Clone, Debug, Serialize)]
+pub struct PendingRestart {
+    pub profile: String,
+    pub settings: Vec<String>,
+}
+
+// Sent as `sidecar-config-pending` when what awaits a restart changes
+#[derive(Clone, Debug, Serialize)]
+pub struct PendingChanges {
+    pub restarts: Vec<PendingRestart>,
+    // The sidecars restart on their own once no transfers are running
+    pub restart_scheduled: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConfigChange {
+    pub settings: SidecarSettings,
+    // Profiles whose running sidecar took the change without a restart
+    pub applied_live: Vec<String>,
+    pub pending: PendingChanges,
+}
+
+pub struct SidecarConfig {
+    settings: Mutex<SidecarSettings>,
+    // Changed settings by profile that its running sidecar needs a restart for
+    pending: Mutex<BTreeMap<String, BTreeSet<&'static str>>>,
+    restart_scheduled: AtomicBool,
+    // Set by set_log_level for this run, in place of the configured level
+    log_level_override: Mutex<Option<String>>,
+}
+
+impl SidecarConfig {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(SidecarSettings::default()),
+            pending: Mutex::new(BTreeMap::new()),
+            restart_scheduled: AtomicBool::new(false),
+            log_level_override: Mutex::new(None),
+        }
+    }
+
+    pub fn settings(&self) -> SidecarSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    // What sidecars log at: set_log_level's level, else the configured one
+    pub fn log_level(&self) -> String {
+        let level = self.log_level_override.lock().unwrap().clone();
+        level
+            .or_else(|| self.settings.lock().unwrap().log_level.clone())
+            // What the sidecar uses without BB_LOG_LEVEL
+            .unwrap_or_else(|| "info".to_string())
+    }
+
+    fn pending(&self) -> PendingChanges {
+        let restarts = self
+            .pending
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(profile, settings)| PendingRestart {
+                profile: profile.clone(),
+                settings: settings.iter().map(|name| name.to_string()).collect(),
+            })
+            .collect();
+        PendingChanges {
+            restarts,
+            restart_scheduled: self.restart_scheduled.load(Ordering::SeqCst),
+        }
+    }
+
+    // A sidecar of `profile` is being spawned with the current settings
+    pub fn started(&self, profile: &str) {
+        self.pending.lock().unwrap().remove(profile);
+    }
+}
+
+// Body of POST /api/admin/config
+#[derive(Serialize)]
+struct LiveConfig<'a> {
+    log_level: &'a str,
+}
+
+// Sidecars the shell spawned that are running; external servers are configured separately
+fn running(state: &AppState) -> Vec<Arc<Sidecar>> {
+    state
+        .sidecars
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|sidecar| sidecar.is_running() && sidecar.external_url().is_none())
+        .cloned()
+        .collect()
+}
+
+// Hand the settings that apply without a restart to a running sidecar
+async fn push_live(state: &AppState, sidecar: &Sidecar, log_level: &str) -> Result<(), String> {
+    if state.is_active(sidecar) && state.versions.capabilities().is_some_and(|c| !c.live_config) {
+        return Err("the backend can't change its configuration while running".to_string());
+    }
+    let base_url = sidecar.base_url();
+    let live = LiveConfig { log_level };
+    let resp = backend::shared_client(&base_url)
+        .post(backend::api_url(&base_url, &["admin", "config"])?)
+        .json(&live)
+        .timeout(LIVE_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("Live configuration returned status: {}", resp.status()));
+    }
+    Ok(())
+}
+
+// Everything a sidecar is spawned with, in the order later values win
+pub fn command_env(state: &AppState, sidecar: &Sidecar, instance_id: &str) -> Vec<(String, String)> {
+    let mut env = state.sidecar_config.settings().env();
+    if let Some(level) = state.sidecar_config.log_level_override.lock().unwrap().clone() {
+        env.push(("BB_LOG_LEVEL".to_string(), level));
+    }
+    env.extend(sidecar.env.iter().cloned());
+    env.extend(
+        state
+            .sessions
+            .sidecar_env()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value)),
+    );
+    env.push(("BB_INSTANCE_ID".to_string(), instance_id.to_string()));
+    env
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let settings: SidecarSettings = match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load sidecar configuration: {}", e);
+            return;
+        }
+    };
+    match settings.validate() {
+        Ok(()) => *state.sidecar_config.settings.lock().unwrap() = settings,
+        Err(e) => log::warn!("Ignoring saved sidecar configuration: {}", e),
+    }
+}
+
+#[tauri::command]
+pub fn get_sidecar_config(state: tauri::State<Arc<AppState>>) -> SidecarSettings {
+    state.sidecar_config.settings()
+}
+
+// Replace the sidecar configuration. Running sidecars take the log level right away;
+// for other changes they restart once idle, unless `restart_when_idle` is false and
+// they wait for the next restart.
+#[tauri::command]
+pub async fn set_sidecar_config(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    settings: SidecarSettings,
+    restart_when_idle: Option<bool>,
+) -> Result<ConfigChange, AppError> {
+    settings.validate().map_err(AppError::invalid)?;
+    store::save_json(&store::data_file(&app, SETTINGS_FILE)?, &settings)?;
+    let level_before = state.sidecar_config.log_level();
+    let previous = std::mem::replace(&mut *state.sidecar_config.settings.lock().unwrap(), settings.clone());
+
+    let restart = settings.restart_changes(&previous);
+    // Unchanged while set_log_level overrides it
+    let log_level = state.sidecar_config.log_level();
+    let live_changed = log_level != level_before;
+    let mut applied_live = Vec::new();
+    for sidecar in running(&state) {
+        let mut needs = restart.clone();
+        if live_changed {
+            match push_live(&state, &sidecar, &log_level).await {
+                Ok(()) => applied_live.push(sidecar.profile.clone()),
+                Err(e) => {
+                    log::warn!("Sidecar for profile {} keeps its log level until restarted: {}", sidecar.profile, e);
+                    needs.push("log_level");
+                }
+            }
+        }
+        if !needs.is_empty() {
+            let mut pending = state.sidecar_config.pending.lock().unwrap();
+            pending.entry(sidecar.profile.clone()).or_default().extend(needs);
+        }
+    }
+
+    let waiting = !state.sidecar_config.pending.lock().unwrap().is_empty();
+    if waiting && restart_when_idle.unwrap_or(true) {
+        state.sidecar_config.restart_scheduled.store(true, Ordering::SeqCst);
+        log::info!("Sidecar configuration changed; restarting once no transfers are running");
+    } else if waiting {
+        log::info!("Sidecar configuration changed; applies on the next start");
+    } else {
+        log::info!("Sidecar configuration changed");
+    }
+    let pending = state.sidecar_config.pending();
+    emit_to_main(&app, "sidecar-config-pending", pending.clone());
+    Ok(ConfigChange {
+        settings,
+        applied_live,
+        pending,
+    })
+}
+
+// Have every sidecar log at `level` for the rest of this run without saving it, or at
+// the configured level again with None. Returns the profiles whose running sidecar took
+// it right away; the others take it when they restart.
+pub async fn override_log_level(app: &AppHandle, state: &AppState, level: Option<String>) -> Vec<String> {
+    *state.sidecar_config.log_level_override.lock().unwrap() = level;
+    let log_level = state.sidecar_config.log_level();
+    let mut applied_live = Vec::new();
+    let mut waiting = false;
+    for sidecar in running(state) {
+        match push_live(state, &sidecar, &log_level).await {
+            Ok(()) => applied_live.push(sidecar.profile.clone()),
+            Err(e) => {
+                log::warn!("Sidecar for profile {} keeps its log level until restarted: {}", sidecar.profile, e);
+                let mut pending = state.sidecar_config.pending.lock().unwrap();
+                pending.entry(sidecar.profile.clone()).or_default().insert("log_level");
+                waiting = true;
+            }
+        }
+    }
+    if waiting {
+        emit_to_main(app, "sidecar-config-pending", state.sidecar_config.pending());
+    }
+    applied_live
+}
+
+// Configuration changes the running sidecars still need a restart for
+#[tauri::command]
+pub fn get_pending_sidecar_changes(state: tauri::State<Arc<AppState>>) -> PendingChanges {
+    state.sidecar_config.pending()
+}
+
+// Have the sidecars with pending changes restart once idle, or call that off
+#[tauri::command]
+pub fn schedule_sidecar_restart(state: tauri::State<Arc<AppState>>, scheduled: bool) -> PendingChanges {
+    state.sidecar_config.restart_scheduled.store(scheduled, Ordering::SeqCst);
+    state.sidecar_config.pending()
+}
+
+// Carry out a scheduled restart once no transfers would be cut off by it
+pub fn spawn_monitor(app: AppHandle, state: Arc<AppState>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(RESTART_CHECK_INTERVAL).await;
+            if state.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            let config = &state.sidecar_config;
+            if !config.restart_scheduled.load(Ordering::SeqCst) || transfers::any_running(&state) {
+                continue;
+            }
+            if state.power.is_settling() {
+                continue;
+            }
+            config.restart_scheduled.store(false, Ordering::SeqCst);
+            let profiles: Vec<String> = config.pending.lock().unwrap().keys().cloned().collect();
+            for sidecar in state.sidecars.lock().unwrap().values() {
+                if profiles.contains(&sidecar.profile) && sidecar.is_running() {
+                    log::info!("Restarting profile {} to apply its sidecar configuration", sidecar.profile);
+                    sidecar.request_restart();
+                }
+            }
+            emit_to_main(&app, "sidecar-config-pending", config.pending());
+        }
+    });
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct EffectiveSidecarConfig {
+    pub profile: String,
+    // None for the bundled binary
+    pub program: Option<PathBuf>,
+    pub args: Vec<String>,
+    // Values of secrets are replaced
+    pub env: Vec<(String, String)>,
+    // Set when the profile uses an external server and nothing is spawned
+    pub external_url: Option<String>,
+}
+
+fn is_secret(name: &str) -> bool {
+    ["SECRET", "KEY", "TOKEN", "PASSWORD"]
+        .iter()
+        .any(|word| name.to_ascii_uppercase().contains(word))
+}
+
+// What the active profile's sidecar is (or would next be) spawned with, for debugging
+#[tauri::command]
+pub fn get_effective_sidecar_config(state: tauri::State<Arc<AppState>>) -> EffectiveSidecarConfig {
+    let sidecar = state.sidecar();
+    let instance_id = sidecar.instance_id.lock().unwrap().clone();
+    let socket = sidecar.socket.lock().unwrap().clone().or_else(|| {
+        (state.sidecar_config.settings().transport == Transport::Socket).then(|| socket_path(&instance_id))
+    });
+    // Preferences decide the port only when the next sidecar starts
+    let port = match sidecar.port.load(Ordering::SeqCst) {
+        0 => state.sidecar_config.settings().preferred_port.unwrap_or(0),
+        port => port,
+    };
+    let env = command_env(&state, &sidecar, &instance_id)
+        .into_iter()
+        .map(|(name, value)| {
+            let value = if is_secret(&name) { "<redacted>".to_string() } else { value };
+            (name, value)
+        })
+        .collect();
+    EffectiveSidecarConfig {
+        profile: sidecar.profile.clone(),
+        program: sidecar_update::staged_path(&state),
+        args: args(socket.as_deref(), port),
+        env,
+        external_url: sidecar.external_url().map(|url| external::display_url(&url)),
+    }
+}
@@ -0,0 +1,186 @@
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Manager};
+
+use crate::notifications::{self, Notification};
+use crate::resources::PressureLevel;
+use crate::{activity, emit_to_main, AppState};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+// Warn once the sidecar holds this share of physical memory, well before the OS
+// starts killing processes
+const MEMORY_WARN_FRACTION: f64 = 0.4;
+
+// ...or once memory is critically low and the sidecar is a large part of the reason
+const CRITICAL_PRESSURE_RSS: u64 = 1024 * 1024 * 1024;
+
+// Warn when this share of the sidecar's file descriptor limit is in use
+const OPEN_FILES_WARN_FRACTION: f64 = 0.8;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SidecarResources {
+    pub pid: u32,
+    // Share of the whole machine, 0-100, averaged since the previous sample
+    pub cpu_percent: f32,
+    // Resident set size in bytes
+    pub memory: u64,
+    pub virtual_memory: u64,
+    pub total_memory: u64,
+    // Open file descriptors or handles; None where the platform doesn't report them
+    pub open_files: Option<usize>,
+    pub open_files_limit: Option<usize>,
+    // Why the sidecar looks close to running out of memory or handles, empty otherwise
+    pub warning: String,
+}
+
+// The sidecar's resource usage as last sampled
+pub struct SidecarMetrics {
+    current: Mutex<Option<SidecarResources>>,
+}
+
+impl SidecarMetrics {
+    pub fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+        }
+    }
+}
+
+fn sidecar_pid(state: &AppState) -> Option<u32> {
+    state.sidecar().pid()
+}
+
+fn warning(resources: &SidecarResources, pressure: PressureLevel) -> String {
+    let mut reasons = Vec::new();
+    let memory_share = resources.memory as f64 / resources.total_memory.max(1) as f64;
+    if memory_share >= MEMORY_WARN_FRACTION
+        || (pressure == PressureLevel::Critical && resources.memory >= CRITICAL_PRESSURE_RSS)
+    {
+        reasons.push(format!(
+            "using {} MiB of memory ({:.0}% of the system)",
+            resources.memory / (1024 * 1024),
+            memory_share * 100.0
+        ));
+    }
+    if let (Some(open), Some(limit)) = (resources.open_files, resources.open_files_limit) {
+        if limit > 0 && open as f64 >= limit as f64 * OPEN_FILES_WARN_FRACTION {
+            reasons.push(format!("{} of {} file handles open", open, limit));
+        }
+    }
+    reasons.join(", ")
+}
+
+// Sample the process; the first sample of a new pid reads 0% CPU since usage is
+// measured between refreshes
+fn sample(system: &mut System, pid: u32, pressure: PressureLevel) -> Option<SidecarResources> {
+    system.refresh_memory();
+    let sys_pid = Pid::from_u32(pid);
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[sys_pid]),
+        true,
+        ProcessRefreshKind::nothing().with_cpu().with_memory(),
+    );
+    let process = system.process(sys_pid)?;
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut resources = SidecarResources {
+        pid,
+        cpu_percent: process.cpu_usage() / cores as f32,
+        memory: process.memory(),
+        virtual_memory: process.virtual_memory(),
+        total_memory: system.total_memory(),
+        open_files: process.open_files(),
+        open_files_limit: process.open_files_limit(),
+        warning: String::new(),
+    };
+    resources.warning = warning(&resources, pressure);
+    Some(resources)
+}
+
+fn warn(app: &AppHandle, state: &AppState, warning: &str) {
+    log::warn!("Backend is {}", warning);
+    notifications::notify(
+        app,
+        state,
+        Notification::new(
+            "Backend is running low on resources",
+            format!("The backend is {}. Restarting it frees them.", warning),
+        )
+        .action("restart", "Restart Backend"),
+        Some(Box::new(|app: &AppHandle, action: &str| {
+            if action == "restart" {
+                app.state::<Arc<AppState>>().sidecar().request_restart();
+            }
+        })),
+    );
+}
+
+// Sample the sidecar periodically, emit `backend-resources` with each sample and warn
+// once when it gets close to running out of memory or file handles
+pub fn spawn_monitor(app: AppHandle, state: Arc<AppState>) {
+    tauri::async_runtime::spawn(async move {
+        let mut system = System::new();
+        // Whether the current sidecar was already warned about
+        let mut warned = false;
+        let mut last_pid = None;
+
+        loop {
+            if state.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let pid = sidecar_pid(&state);
+            if pid != last_pid {
+                // A new sidecar starts without a warning against it
+                last_pid = pid;
+                warned = false;
+            }
+
+            let resources = match pid {
+                Some(pid) => {
+                    let pressure = state.resources.level();
+                    match tauri::async_runtime::spawn_blocking(move || {
+                        let resources = sample(&mut system, pid, pressure);
+                        (system, resources)
+                    })
+                    .await
+                    {
+                        Ok((returned, resources)) => {
+                            system = returned;
+                            resources
+                        }
+                        Err(e) => {
+                            log::error!("Backend resource sampling failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+                None => None,
+            };
+
+            *state.sidecar_metrics.current.lock().unwrap() = resources.clone();
+            if let Some(resources) = resources {
+                if resources.warning.is_empty() {
+                    warned = false;
+                } else if !warned {
+                    warned = true;
+                    warn(&app, &state, &resources.warning);
+                }
+                emit_to_main(&app, "backend-resources", resources);
+            }
+
+            activity::sleep(&app, &state, SAMPLE_INTERVAL).await;
+        }
+    });
+}
+
+// Latest sample of the sidecar's CPU, memory and open handles; None while it isn't running
+#[tauri::command]
+pub fn get_backend_resources(state: tauri::State<Arc<AppState>>) -> Option<SidecarResources> {
+    state.sidecar_metrics.current.lock().unwrap().clone()
+}
@@ -0,0 +1,548 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::notifications::{self, Notification};
+use crate::permissions::{self, Capability};
+use crate::{integrity, quiet_hours, store, AppState};
+
+// Sidecar builds are published with each release as the CLI binaries
+const RELEASES_URL: &str = "https://api.github.com/repos/LayerDynamics/bb-stream/releases/latest";
+const CHECKSUMS_ASSET: &str = "checksums.txt";
+// Each sidecar asset has a minisign signature next to it, as `<asset>.minisig`
+const SIGNATURE_EXTENSION: &str = "minisig";
+
+// Minisign public key the release binaries are signed with, pinned at build time.
+// Builds without one can't install updates.
+const PUBLIC_KEY: Option<&str> = option_env!("BB_SIDECAR_UPDATE_PUBKEY");
+
+// The bundled sidecar is released together with the app
+const BUNDLED_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const RECORD_FILE: &str = "sidecar_update.json";
+const STAGING_DIR: &str = "sidecar";
+
+// How long a new sidecar has to report healthy before it is rolled back
+const TRIAL_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Background checks for a newer sidecar; the first waits for startup to settle
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Release asset holding the sidecar for this platform
+fn asset_name() -> Option<&'static str> {
+    if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        Some("bb-stream-darwin-arm64")
+    } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
+        Some("bb-stream-darwin-amd64")
+    } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+        Some("bb-stream-linux-amd64")
+    } else if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
+        Some("bb-stream-windows-amd64.exe")
+    } else {
+        None
+    }
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+    size: u64,
+}
+
+// A newer sidecar available from the release feed
+#[derive(Clone, Debug, Serialize)]
+pub struct SidecarUpdate {
+    pub version: String,
+    pub current_version: String,
+    pub size: u64,
+    #[serde(skip)]
+    url: String,
+    #[serde(skip)]
+    sha256: String,
+    #[serde(skip)]
+    signature: String,
+}
+
+// A downloaded sidecar in app data, verified against the release checksums and signature
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StagedBinary {
+    pub version: String,
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+// Which sidecar runs: the staged `current`, or the bundled one when there is none.
+// `previous` is what a failed update rolls back to.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct UpdateRecord {
+    current: Option<StagedBinary>,
+    previous: Option<StagedBinary>,
+    // Whether `current` has passed its health checks
+    confirmed: bool,
+}
+
+pub struct SidecarUpdates {
+    record: Mutex<UpdateRecord>,
+    installing: AtomicBool,
+    // Newest version the user was already told about
+    notified: Mutex<Option<String>>,
+}
+
+impl SidecarUpdates {
+    pub fn new() -> Self {
+        Self {
+            record: Mutex::new(UpdateRecord::default()),
+            installing: AtomicBool::new(false),
+            notified: Mutex::new(None),
+        }
+    }
+
+    fn save(&self, app: &AppHandle) {
+        let record = self.record.lock().unwrap().clone();
+        let result = store::data_file(app, RECORD_FILE).and_then(|path| store::save_json(&path, &record));
+        if let Err(e) = result {
+            log::error!("Failed to save sidecar update state: {}", e);
+        }
+    }
+}
+
+// Numeric components of a version like v1.2.3, for ordering
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| {
+            part.chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>()
+                .parse()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+// Release tags become directory names, so only plain versions like 1.2.3-rc.1 are taken
+fn valid_version(version: &str) -> bool {
+    version.len() <= 64
+        && version.starts_with(|c: char| c.is_ascii_digit())
+        && version.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+}
+
+fn same_version(a: &str, b: &str) -> bool {
+    a.trim_start_matches('v') == b.trim_start_matches('v')
+}
+
+fn current_version(state: &AppState) -> String {
+    state
+        .versions
+        .running()
+        .or_else(|| state.sidecar_updates.record.lock().unwrap().current.as_ref().map(|c| c.version.clone()))
+        .unwrap_or_else(|| BUNDLED_VERSION.to_string())
+}
+
+fn client(timeout: Option<Duration>) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(concat!("bb-stream-desktop/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(10));
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+// The latest release's sidecar for this platform, if it is newer than the running one
+async fn find_update(state: &AppState) -> Result<Option<SidecarUpdate>, String> {
+    let asset_name = asset_name().ok_or("Sidecar updates aren't available for this platform")?;
+    if PUBLIC_KEY.is_none() {
+        return Err("This build has no key to verify sidecar updates with".to_string());
+    }
+    let client = client(Some(Duration::from_secs(30)))?;
+    let resp = client
+        .get(RELEASES_URL)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to check for sidecar updates: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Checking for sidecar updates returned status: {}", resp.status()));
+    }
+    let release: Release = resp
+        .json()
+        .await
+        .map_err(|e| format!("Invalid release feed: {}", e))?;
+
+    let version = release.tag_name.trim_start_matches('v');
+    if !valid_version(version) {
+        return Err(format!("Release feed has an invalid version: {:?}", release.tag_name));
+    }
+    let current_version = current_version(state);
+    if version_key(version) <= version_key(&current_version) {
+        return Ok(None);
+    }
+    let signature_name = format!("{}.{}", asset_name, SIGNATURE_EXTENSION);
+    let find = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| format!("Release {} has no {}", release.tag_name, name))
+    };
+    let binary = find(asset_name)?;
+    let checksums = find(CHECKSUMS_ASSET)?;
+    let signature = find(&signature_name)?;
+
+    let download_text = |asset: &ReleaseAsset| {
+        let request = client.get(&asset.browser_download_url);
+        let name = asset.name.clone();
+        async move {
+            request
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status())
+                .map_err(|e| format!("Failed to download {}: {}", name, e))?
+                .text()
+                .await
+                .map_err(|e| format!("Failed to download {}: {}", name, e))
+        }
+    };
+    // sha256sum output: "<hex>  <file name>"
+    let listing = download_text(checksums).await?;
+    let sha256 = listing
+        .lines()
+        .find_map(|line| {
+            let (hash, name) = line.split_once(char::is_whitespace)?;
+            (name.trim().trim_start_matches('*') == asset_name).then(|| hash.to_ascii_lowercase())
+        })
+        .ok_or_else(|| format!("{} has no checksum for {}", CHECKSUMS_ASSET, asset_name))?;
+    let signature = download_text(signature).await?;
+
+    Ok(Some(SidecarUpdate {
+        version: version.to_string(),
+        current_version,
+        size: binary.size,
+        url: binary.browser_download_url.clone(),
+        sha256,
+        signature,
+    }))
+}
+
+// Download the update into app data, checking its size, hash and signature before it
+// can be used
+async fn stage(app: &AppHandle, update: &SidecarUpdate) -> Result<StagedBinary, String> {
+    let public_key = PublicKey::from_base64(PUBLIC_KEY.ok_or("This build has no key to verify sidecar updates with")?)
+        .map_err(|e| format!("Invalid sidecar update key: {}", e))?;
+    let signature = Signature::decode(&update.signature)
+        .map_err(|e| format!("Invalid signature for sidecar {}: {}", update.version, e))?;
+    let mut verifier = public_key
+        .verify_stream(&signature)
+        .map_err(|e| format!("Unusable signature for sidecar {}: {}", update.version, e))?;
+
+    let dir = store::data_file(app, STAGING_DIR)?.join(&update.version);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let name = if cfg!(windows) { "bb-stream.exe" } else { "bb-stream" };
+    let path = dir.join(name);
+    let partial = dir.join(format!("{}.part", name));
+
+    log::info!("Downloading sidecar {} from {}", update.version, update.url);
+    let mut resp = client(None)?
+        .get(&update.url)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("Failed to download sidecar {}: {}", update.version, e))?;
+    let mut file =
+        std::fs::File::create(&partial).map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to download sidecar {}: {}", update.version, e))?
+    {
+        hasher.update(&chunk);
+        verifier.update(&chunk);
+        size += chunk.len() as u64;
+        file.write_all(&chunk)
+            .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+    }
+    drop(file);
+
+    let sha256 = hex::encode(hasher.finalize());
+    if size != update.size || sha256 != update.sha256 {
+        let _ = std::fs::remove_file(&partial);
+        return Err(format!(
+            "Downloaded sidecar {} doesn't match the release ({} bytes, SHA-256 {})",
+            update.version, size, sha256
+        ));
+    }
+    if let Err(e) = verifier.finalize() {
+        let _ = std::fs::remove_file(&partial);
+        return Err(format!("Downloaded sidecar {} isn't signed by the release key: {}", update.version, e));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make {} executable: {}", partial.display(), e))?;
+    }
+    // Gatekeeper refuses binaries whose code signature doesn't hold up; no-op elsewhere
+    if let Err(e) = integrity::verify_signature(&partial) {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    std::fs::rename(&partial, &path).map_err(|e| format!("Failed to stage {}: {}", path.display(), e))?;
+
+    Ok(StagedBinary {
+        version: update.version.clone(),
+        path,
+        sha256,
+    })
+}
+
+// Every profile runs the same binary, so restart all sidecars that are up, and the
+// active one even if it is down
+fn request_restart(state: &AppState) {
+    for sidecar in state.sidecars.lock().unwrap().values() {
+        if sidecar.is_running() || state.is_active(sidecar) {
+            sidecar.request_restart();
+        }
+    }
+}
+
+// Wait for the restarted sidecar to report `version` and pass its health checks
+async fn trial(state: &AppState, version: &str) -> Result<(), String> {
+    let deadline = Instant::now() + TRIAL_TIMEOUT;
+    while Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let running = state.versions.running();
+        if !running.as_deref().is_some_and(|running| same_version(running, version)) {
+            continue;
+        }
+        if let Some(mismatch) = state.versions.mismatch() {
+            return Err(mismatch.message());
+        }
+        if state.is_healthy() {
+            return Ok(());
+        }
+    }
+    Err(format!("it wasn't healthy within {} seconds", TRIAL_TIMEOUT.as_secs()))
+}
+
+// Go back to the sidecar that ran before the current one and remove the failed one
+fn roll_back(app: &AppHandle, state: &AppState) {
+    let failed = {
+        let mut record = state.sidecar_updates.record.lock().unwrap();
+        let failed = record.current.take();
+        record.current = record.previous.take();
+        record.confirmed = true;
+        failed
+    };
+    state.sidecar_updates.save(app);
+    if let Some(dir) = failed.as_ref().and_then(|f| f.path.parent()) {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+// Delete staged versions other than the current and previous ones
+fn clean_up(app: &AppHandle, state: &AppState) {
+    let keep: Vec<PathBuf> = {
+        let record = state.sidecar_updates.record.lock().unwrap();
+        [&record.current, &record.previous]
+            .into_iter()
+            .flatten()
+            .filter_map(|staged| staged.path.parent().map(Path::to_path_buf))
+            .collect()
+    };
+    let Ok(root) = store::data_file(app, STAGING_DIR) else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(&root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if !keep.contains(&entry.path()) {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+async fn install_update(app: &AppHandle, state: &AppState) -> Result<String, String> {
+    if state.sidecar().external_url().is_some() {
+        return Err("The app uses an external server, which is updated separately".to_string());
+    }
+    let update = find_update(state)
+        .await?
+        .ok_or("The backend is already up to date")?;
+    let staged = stage(app, &update).await?;
+    let version = staged.version.clone();
+    {
+        let mut record = state.sidecar_updates.record.lock().unwrap();
+        // An unconfirmed current can't be trusted as a rollback target
+        let previous = if record.confirmed { record.current.take() } else { record.previous.take() };
+        *record = UpdateRecord {
+            current: Some(staged),
+            previous,
+            confirmed: false,
+        };
+    }
+    state.sidecar_updates.save(app);
+
+    log::info!("Switching to sidecar {}", version);
+    request_restart(state);
+    match trial(state, &version).await {
+        Ok(()) => {
+            state.sidecar_updates.record.lock().unwrap().confirmed = true;
+            state.sidecar_updates.save(app);
+            clean_up(app, state);
+            log::info!("Sidecar {} is healthy", version);
+            Ok(version)
+        }
+        Err(e) => {
+            log::error!("Sidecar {} failed its trial, rolling back: {}", version, e);
+            roll_back(app, state);
+            request_restart(state);
+            Err(format!("Backend {} was rolled back because {}", version, e))
+        }
+    }
+}
+
+// Sidecar to spawn instead of the bundled one, if an update is installed, intact and
+// newer than the bundled one. An app update can bring a newer sidecar than was staged.
+pub fn staged_binary(app: &AppHandle, state: &AppState) -> Option<PathBuf> {
+    loop {
+        let current = state.sidecar_updates.record.lock().unwrap().current.clone()?;
+        if version_key(&current.version) <= version_key(BUNDLED_VERSION) {
+            log::info!(
+                "Bundled sidecar {} supersedes staged {}; dropping it",
+                BUNDLED_VERSION,
+                current.version
+            );
+        } else {
+            match integrity::sha256_file(&current.path) {
+                Ok(sha256) if sha256 == current.sha256 => return Some(current.path),
+                Ok(_) => log::error!("Staged sidecar {} was modified; rolling back", current.version),
+                Err(e) => log::error!("Staged sidecar {} is unusable, rolling back: {}", current.version, e),
+            }
+        }
+        roll_back(app, state);
+    }
+}
+
+// Path of the staged binary the next spawn would use, without verifying it
+pub fn staged_path(state: &AppState) -> Option<PathBuf> {
+    let record = state.sidecar_updates.record.lock().unwrap();
+    record.current.as_ref().map(|current| current.path.clone())
+}
+
+// Load the update record before the first spawn. An update whose trial never finished,
+// e.g. because the app quit or crashed meanwhile, is rolled back.
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let record: UpdateRecord = match store::data_file(app, RECORD_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load sidecar update state: {}", e);
+            return;
+        }
+    };
+    let unconfirmed = record.current.is_some() && !record.confirmed;
+    *state.sidecar_updates.record.lock().unwrap() = record;
+    if unconfirmed {
+        log::warn!("Sidecar update didn't finish its health checks; rolling back");
+        roll_back(app, state);
+    }
+}
+
+// Check the feed daily and offer newer sidecars; installing is left to the user
+pub fn spawn_checker(app: AppHandle, state: Arc<AppState>) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FIRST_CHECK_DELAY).await;
+        let never_cancelled = AtomicBool::new(false);
+        while !state.shutdown.load(Ordering::SeqCst) {
+            // A routine check isn't urgent enough to run during quiet hours
+            if !quiet_hours::wait_for_background(&state, &never_cancelled).await {
+                break;
+            }
+            match find_update(&state).await {
+                Ok(Some(update)) => {
+                    let mut notified = state.sidecar_updates.notified.lock().unwrap();
+                    if notified.as_deref() != Some(update.version.as_str()) {
+                        *notified = Some(update.version.clone());
+                        drop(notified);
+                        notify_available(&app, &state, &update);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("{}", e),
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+fn notify_available(app: &AppHandle, state: &AppState, update: &SidecarUpdate) {
+    notifications::notify(
+        app,
+        state,
+        Notification::new(
+            "Backend update available",
+            format!("Version {} of the backend is available (you have {}).", update.version, update.current_version),
+        )
+        .action("install", "Update Backend"),
+        Some(Box::new(|app: &AppHandle, action: &str| {
+            if action == "install" {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move { install_and_notify(&app).await });
+            }
+        })),
+    );
+}
+
+async fn install_and_notify(app: &AppHandle) {
+    let state = Arc::clone(&app.state::<Arc<AppState>>());
+    let notification = match install(app, &state).await {
+        Ok(version) => Notification::new("Backend updated", format!("The backend is now running version {}.", version)),
+        Err(e) => Notification::new("Backend not updated", e),
+    };
+    notifications::notify(app, &state, notification, None);
+}
+
+async fn install(app: &AppHandle, state: &AppState) -> Result<String, String> {
+    if state.sidecar_updates.installing.swap(true, Ordering::SeqCst) {
+        return Err("A backend update is already being installed".to_string());
+    }
+    let result = install_update(app, state).await;
+    state.sidecar_updates.installing.store(false, Ordering::SeqCst);
+    result
+}
+
+#[tauri::command]
+pub async fn check_sidecar_update(state: tauri::State<'_, Arc<AppState>>) -> Result<Option<SidecarUpdate>, AppError> {
+    Ok(find_update(&state).await?)
+}
+
+// Download, verify and switch to the latest sidecar, rolling back if it doesn't come up
+// healthy. Returns the version now running.
+#[tauri::command]
+pub async fn install_sidecar_update(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+) -> Result<String, AppError> {
+    permissions::ensure(&app, &state, Capability::RunPrograms, "installing a backend update").await?;
+    Ok(install(&app, &state).await?)
+}
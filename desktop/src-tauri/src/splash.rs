@@ -0,0 +1,133 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::{stop_sidecars, AppState};
+
+pub const LABEL: &str = "splash";
+const MAIN_LABEL: &str = "main";
+
+// How long the backend gets to become healthy before the splash shows an error
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(45);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Links on the splash page point here; the shell intercepts them
+const ACTION_HOST: &str = "splash.invalid";
+
+// Startup gate: a small window saying the backend is starting, replaced by the main
+// window (created with "create": false) once the active backend is healthy, so the
+// frontend never loads against a backend that isn't there yet
+pub struct Splash {
+    // Bumped on each wait, so a retry's wait replaces the one that timed out
+    attempt: AtomicU64,
+}
+
+impl Splash {
+    pub fn new() -> Self {
+        Self {
+            attempt: AtomicU64::new(0),
+        }
+    }
+}
+
+pub fn show(app: &AppHandle, state: &Arc<AppState>) {
+    let handle = app.clone();
+    let built = WebviewWindowBuilder::new(app, LABEL, WebviewUrl::App("splash.html".into()))
+        .title("BB Stream")
+        .inner_size(360.0, 220.0)
+        .resizable(false)
+        .decorations(false)
+        .center()
+        .on_navigation(move |url| {
+            if url.host_str() != Some(ACTION_HOST) {
+                return true;
+            }
+            act(&handle, url.path().trim_start_matches('/'));
+            false
+        })
+        .build();
+    match built {
+        Ok(_) => wait_for_backend(app.clone(), Arc::clone(state)),
+        Err(e) => {
+            log::error!("Failed to show the splash window: {}", e);
+            open_main(app);
+        }
+    }
+}
+
+fn act(app: &AppHandle, action: &str) {
+    let state: tauri::State<Arc<AppState>> = app.state();
+    match action {
+        "retry" => {
+            let sidecar = state.sidecar();
+            sidecar.crashes.lock().unwrap().clear();
+            sidecar.request_restart();
+            set_status(app, "Starting…");
+            wait_for_backend(app.clone(), Arc::clone(&state));
+        }
+        // Windows can deadlock creating a webview from inside another's event handler
+        "open" => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move { open_main(&app) });
+        }
+        "quit" => {
+            stop_sidecars(app);
+            app.exit(0);
+        }
+        _ => log::warn!("Unknown splash action {:?}", action),
+    }
+}
+
+fn wait_for_backend(app: AppHandle, state: Arc<AppState>) {
+    let attempt = state.splash.attempt.fetch_add(1, Ordering::SeqCst) + 1;
+    tauri::async_runtime::spawn(async move {
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        while !state.is_healthy() {
+            if state.splash.attempt.load(Ordering::SeqCst) != attempt || app.get_webview_window(LABEL).is_none() {
+                return;
+            }
+            if Instant::now() >= deadline {
+                log::error!("Backend did not become healthy within {}s of starting", STARTUP_TIMEOUT.as_secs());
+                let detail = state.sidecar().crash_log.last().map(|report| report.summary());
+                show_error(&app, "The backend didn't start", detail.as_deref());
+                return;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        open_main(&app);
+    });
+}
+
+fn set_status(app: &AppHandle, text: &str) {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.eval(format!("setStatus({})", serde_json::json!(text)));
+    }
+}
+
+fn show_error(app: &AppHandle, text: &str, detail: Option<&str>) {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.eval(format!("showError({}, {})", serde_json::json!(text), serde_json::json!(detail)));
+    }
+}
+
+// Create the main window from its configuration, then drop the splash. In that order:
+// the app quits when its last window goes.
+fn open_main(app: &AppHandle) {
+    if app.get_webview_window(MAIN_LABEL).is_none() {
+        let config = app.config().app.windows.iter().find(|w| w.label == MAIN_LABEL).cloned();
+        let built = match config {
+            Some(config) => WebviewWindowBuilder::from_config(app, &config).and_then(|builder| builder.build()),
+            None => WebviewWindowBuilder::new(app, MAIN_LABEL, WebviewUrl::App("index.html".into())).build(),
+        };
+        if let Err(e) = built {
+            log::error!("Failed to open the main window: {}", e);
+            show_error(app, "The main window couldn't be opened", Some(&e.to_string()));
+            return;
+        }
+    }
+    if let Some(splash) = app.get_webview_window(LABEL) {
+        let _ = splash.destroy();
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{store, AppState, BackendStatus};
+
+const HISTORY_FILE: &str = "status_history.json";
+
+// Transitions kept across all profiles; the oldest go first
+const CAPACITY: usize = 2000;
+
+// How far back get_status_history looks when not told
+const DEFAULT_WINDOW_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatusChange {
+    // Unix milliseconds
+    pub at: i64,
+    pub profile: String,
+    pub status: BackendStatus,
+}
+
+#[derive(Clone, Serialize)]
+pub struct StatusHistory {
+    // Oldest first. The status in effect when the window starts comes first, even
+    // when it changed before then.
+    pub changes: Vec<StatusChange>,
+    pub crashes: usize,
+    pub restarts: usize,
+    // Time spent healthy, and running at all, within the window; the app being closed
+    // or the backend stopped on purpose counts as neither
+    pub healthy_ms: i64,
+    pub running_ms: i64,
+}
+
+// Every backend status transition, kept across launches for an uptime timeline
+pub struct StatusLog {
+    changes: Mutex<VecDeque<StatusChange>>,
+}
+
+impl StatusLog {
+    pub fn new() -> Self {
+        Self {
+            changes: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+fn kind(status: &BackendStatus) -> std::mem::Discriminant<BackendStatus> {
+    std::mem::discriminant(status)
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, HISTORY_FILE) {
+        Ok(path) => *state.status_log.changes.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load backend status history: {}", e),
+    }
+}
+
+// Record a profile's new status, unless it is the status it already had
+pub fn record(app: &AppHandle, state: &AppState, profile: &str, status: &BackendStatus) {
+    let mut changes = state.status_log.changes.lock().unwrap();
+    let previous = changes.iter().rev().find(|change| change.profile == profile);
+    if previous.is_some_and(|change| kind(&change.status) == kind(status)) {
+        return;
+    }
+    changes.push_back(StatusChange {
+        at: chrono::Utc::now().timestamp_millis(),
+        profile: profile.to_string(),
+        status: status.clone(),
+    });
+    while changes.len() > CAPACITY {
+        changes.pop_front();
+    }
+    let result = store::data_file(app, HISTORY_FILE).and_then(|path| store::save_json(&path, &*changes));
+    if let Err(e) = result {
+        log::warn!("Failed to save backend status history: {}", e);
+    }
+}
+
+// The last `count` transitions of every profile, oldest first, for crash reports. Empty
+// when the log is busy, since this also runs from the panic hook.
+pub fn recent(state: &AppState, count: usize) -> Vec<StatusChange> {
+    match state.status_log.changes.try_lock() {
+        Ok(changes) => changes.iter().skip(changes.len().saturating_sub(count)).cloned().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// A profile's transitions since `since` (Unix milliseconds, a week ago by default),
+// with how often it crashed and how long it was up. Defaults to the active profile.
+#[tauri::command]
+pub fn get_status_history(
+    state: tauri::State<Arc<AppState>>,
+    profile: Option<String>,
+    since: Option<i64>,
+) -> StatusHistory {
+    let profile = profile.unwrap_or_else(|| state.active_profile.lock().unwrap().clone());
+    let now = chrono::Utc::now().timestamp_millis();
+    let since = since.unwrap_or(now - DEFAULT_WINDOW_MS);
+
+    let all = state.status_log.changes.lock().unwrap();
+    let mine: Vec<&StatusChange> = all.iter().filter(|change| change.profile == profile).collect();
+    let first = mine.iter().rposition(|change| change.at <= since).unwrap_or(0);
+    let changes: Vec<StatusChange> = mine[first..].iter().map(|change| (*change).clone()).collect();
+
+    let mut history = StatusHistory {
+        changes,
+        crashes: 0,
+        restarts: 0,
+        healthy_ms: 0,
+        running_ms: 0,
+    };
+    for (i, change) in history.changes.iter().enumerate() {
+        let start = change.at.max(since);
+        let end = history.changes.get(i + 1).map_or(now, |next| next.at).max(start);
+        if change.at >= since {
+            match change.status {
+                BackendStatus::Crashed { .. } => history.crashes += 1,
+                BackendStatus::Restarting => history.restarts += 1,
+                _ => {}
+            }
+        }
+        match change.status {
+            BackendStatus::Stopped => {}
+            BackendStatus::Healthy => {
+                history.healthy_ms += end - start;
+                history.running_ms += end - start;
+            }
+            _ => history.running_ms += end - start,
+        }
+    }
+    history
+}
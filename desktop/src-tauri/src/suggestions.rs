@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::remote_path::RemotePath;
+use crate::{store, AppState};
+
+pub const HISTORY_FILE: &str = "upload_history.json";
+
+// Destinations remembered per file type; the least recently used are forgotten beyond this
+const MAX_USAGES: usize = 500;
+
+const MAX_SUGGESTIONS: usize = 5;
+
+// A destination's weight halves for every this many days it goes unused
+const HALF_LIFE_DAYS: f64 = 30.0;
+
+// Weight of a destination only used for other kinds of files, relative to one used
+// for the same kind
+const OTHER_TYPE_WEIGHT: f64 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Image,
+    Video,
+    Audio,
+    Document,
+    Archive,
+    Other,
+}
+
+impl Category {
+    // By extension only; good enough to tell photos from invoices
+    pub fn of(name: &str) -> Self {
+        let ext = Path::new(name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "jpg" | "jpeg" | "png" | "gif" | "webp" | "heic" | "heif" | "tif" | "tiff" | "bmp" | "svg" | "raw"
+            | "cr2" | "cr3" | "nef" | "arw" | "dng" | "orf" | "rw2" | "raf" => Self::Image,
+            "mp4" | "mov" | "m4v" | "mkv" | "avi" | "webm" | "mts" | "m2ts" | "3gp" | "wmv" => Self::Video,
+            "mp3" | "wav" | "flac" | "aac" | "m4a" | "ogg" | "opus" | "aiff" | "wma" => Self::Audio,
+            "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "odt" | "ods" | "odp" | "txt" | "md"
+            | "rtf" | "csv" | "pages" | "numbers" | "key" => Self::Document,
+            "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" | "rar" | "zst" | "dmg" | "iso" => Self::Archive,
+            _ => Self::Other,
+        }
+    }
+}
+
+// How often files of one kind went to one folder
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Usage {
+    bucket: String,
+    prefix: String,
+    category: Category,
+    // Batches, not files, so one large import doesn't drown out everything else
+    count: u32,
+    // Unix seconds
+    last_used: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    // Files like these have gone here before
+    SameType,
+    // Other files went here recently
+    Recent,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Suggestion {
+    pub bucket: String,
+    pub prefix: String,
+    // Relative to the best suggestion, which scores 1
+    pub score: f64,
+    pub reason: Reason,
+}
+
+// Where uploads went, learned from finished transfers
+pub struct UploadHistory {
+    usages: Mutex<Vec<Usage>>,
+}
+
+impl UploadHistory {
+    pub fn new() -> Self {
+        Self {
+            usages: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, HISTORY_FILE) {
+        Ok(path) => *state.upload_history.usages.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load upload history: {}", e),
+    }
+}
+
+// Remember that `names` were uploaded to a folder. Best effort: a bad folder or a
+// failed save is only logged.
+pub fn learn(app: &AppHandle, state: &AppState, bucket: &str, prefix: &str, names: &[String]) {
+    if bucket.is_empty() || names.is_empty() {
+        return;
+    }
+    let prefix = match RemotePath::parse(prefix) {
+        Ok(prefix) => prefix.to_string(),
+        Err(e) => {
+            log::warn!("Not learning upload destination: {}", e);
+            return;
+        }
+    };
+    let mut categories: Vec<Category> = names.iter().map(|name| Category::of(name)).collect();
+    categories.sort();
+    categories.dedup();
+
+    let usages = {
+        let mut usages = state.upload_history.usages.lock().unwrap();
+        let now = chrono::Utc::now().timestamp();
+        for category in categories {
+            match usages
+                .iter_mut()
+                .find(|u| u.bucket == bucket && u.prefix == prefix && u.category == category)
+            {
+                Some(usage) => {
+                    usage.count = usage.count.saturating_add(1);
+                    usage.last_used = now;
+                }
+                None => usages.push(Usage {
+                    bucket: bucket.to_string(),
+                    prefix: prefix.clone(),
+                    category,
+                    count: 1,
+                    last_used: now,
+                }),
+            }
+        }
+        if usages.len() > MAX_USAGES {
+            usages.sort_by_key(|u| std::cmp::Reverse(u.last_used));
+            usages.truncate(MAX_USAGES);
+        }
+        usages.clone()
+    };
+    let saved = store::data_file(app, HISTORY_FILE).and_then(|path| store::save_json(&path, &usages));
+    if let Err(e) = saved {
+        log::error!("Failed to save upload history: {}", e);
+    }
+}
+
+fn decay(last_used: i64, now: i64) -> f64 {
+    let days = (now - last_used).max(0) as f64 / 86_400.0;
+    0.5f64.powf(days / HALF_LIFE_DAYS)
+}
+
+// Folders the given files probably belong in, best first. Each destination scores its
+// uses weighted by how many of the files share the kind it was used for, fading with
+// age; folders only used for other kinds count for less.
+fn suggest(usages: &[Usage], names: &[String], now: i64) -> Vec<Suggestion> {
+    if names.is_empty() {
+        return Vec::new();
+    }
+    let mut shares: HashMap<Category, f64> = HashMap::new();
+    for name in names {
+        *shares.entry(Category::of(name)).or_default() += 1.0 / names.len() as f64;
+    }
+
+    // (bucket, prefix) -> (same-type score, other-type score)
+    let mut scores: BTreeMap<(&str, &str), (f64, f64)> = BTreeMap::new();
+    for usage in usages {
+        let weight = usage.count as f64 * decay(usage.last_used, now);
+        let entry = scores.entry((usage.bucket.as_str(), usage.prefix.as_str())).or_default();
+        match shares.get(&usage.category) {
+            Some(share) => entry.0 += weight * share,
+            None => entry.1 += weight * OTHER_TYPE_WEIGHT,
+        }
+    }
+
+    let mut suggestions: Vec<Suggestion> = scores
+        .into_iter()
+        .map(|((bucket, prefix), (same, other))| Suggestion {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            score: same + other,
+            reason: if same >= other { Reason::SameType } else { Reason::Recent },
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    if let Some(best) = suggestions.first().map(|s| s.score).filter(|best| *best > 0.0) {
+        for suggestion in &mut suggestions {
+            suggestion.score /= best;
+        }
+    }
+    suggestions
+}
+
+// Destinations to offer in the upload dialog for these file names; empty until
+// something has been uploaded
+#[tauri::command]
+pub fn suggest_destination(state: tauri::State<Arc<AppState>>, files: Vec<String>) -> Vec<Suggestion> {
+    let usages = state.upload_history.usages.lock().unwrap();
+    suggest(&usages, &files, chrono::Utc::now().timestamp())
+}
+
+// For uploads the webview makes itself; shell transfers are learned as they finish
+#[tauri::command]
+pub fn record_upload_destination(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    files: Vec<String>,
+    bucket: String,
+    prefix: String,
+) {
+    learn(&app, &state, &bucket, &prefix, &files);
+}
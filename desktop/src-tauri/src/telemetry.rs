@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{reload, Registry};
+
+use crate::error::AppError;
+use crate::{store, AppState};
+
+pub const SETTINGS_FILE: &str = "telemetry.json";
+
+const SERVICE_NAME: &str = "bb-stream-desktop";
+
+// Per export request; spans that can't be sent in time are dropped
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long quitting waits for the last spans to go out
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+type ExportLayer = OpenTelemetryLayer<Registry, SdkTracer>;
+
+// Opt-in export of the shell's tracing spans (sidecar lifecycle, health checks,
+// restarts, transfers) to an OTLP/HTTP collector, for people watching many installs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetrySettings {
+    pub enabled: bool,
+    // Collector base URL; spans go to its /v1/traces unless the URL already names a path
+    pub endpoint: String,
+    // Sent with every export, e.g. an API key for a hosted collector
+    pub headers: BTreeMap<String, String>,
+    // Tells this install apart from others; the host name when empty
+    pub instance: String,
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "http://localhost:4318".to_string(),
+            headers: BTreeMap::new(),
+            instance: String::new(),
+        }
+    }
+}
+
+impl TelemetrySettings {
+    fn traces_url(&self) -> Result<Url, String> {
+        let mut url = Url::parse(self.endpoint.trim()).map_err(|e| format!("Invalid collector URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("The collector URL must use http or https".to_string());
+        }
+        if url.path() == "/" {
+            url.set_path("/v1/traces");
+        }
+        Ok(url)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.traces_url()?;
+        for (name, value) in &self.headers {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Invalid header name {:?}", name))?;
+            reqwest::header::HeaderValue::from_str(value)
+                .map_err(|_| format!("Invalid value for header {}", name))?;
+        }
+        Ok(())
+    }
+
+    fn instance_id(&self) -> String {
+        match self.instance.trim() {
+            "" => sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string()),
+            instance => instance.to_string(),
+        }
+    }
+}
+
+pub struct Telemetry {
+    settings: Mutex<TelemetrySettings>,
+    // Exporting spans while enabled
+    provider: Mutex<Option<SdkTracerProvider>>,
+    // Swaps the exporting layer in and out of the global subscriber
+    layer: OnceLock<reload::Handle<Option<ExportLayer>, Registry>>,
+}
+
+impl Telemetry {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(TelemetrySettings::default()),
+            provider: Mutex::new(None),
+            layer: OnceLock::new(),
+        }
+    }
+}
+
+// Install the subscriber the spans go through, then start exporting if enabled
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let (layer, handle) = reload::Layer::new(None::<ExportLayer>);
+    match tracing::subscriber::set_global_default(Registry::default().with(layer)) {
+        Ok(()) => {
+            let _ = state.telemetry.layer.set(handle);
+        }
+        Err(e) => log::warn!("Failed to install the tracing subscriber: {}", e),
+    }
+
+    let settings: TelemetrySettings = match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load telemetry settings: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = settings.validate() {
+        log::warn!("Ignoring saved telemetry settings: {}", e);
+        return;
+    }
+    *state.telemetry.settings.lock().unwrap() = settings;
+    if let Err(e) = apply(app, state) {
+        log::warn!("Failed to start exporting traces: {}", e);
+    }
+}
+
+fn exporter_provider(app: &AppHandle, settings: &TelemetrySettings) -> Result<SdkTracerProvider, String> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(settings.traces_url()?.to_string())
+        .with_headers(settings.headers.clone().into_iter().collect())
+        .with_timeout(EXPORT_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to set up the trace exporter: {}", e))?;
+    let resource = Resource::builder()
+        .with_service_name(SERVICE_NAME)
+        .with_attributes([
+            KeyValue::new("service.version", app.package_info().version.to_string()),
+            KeyValue::new("service.instance.id", settings.instance_id()),
+            KeyValue::new("os.type", std::env::consts::OS),
+        ])
+        .build();
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource)
+        .build())
+}
+
+// Start or stop exporting to match the settings; spans already queued by a previous
+// exporter are flushed off the calling thread
+fn apply(app: &AppHandle, state: &AppState) -> Result<(), String> {
+    let Some(handle) = state.telemetry.layer.get() else {
+        return Err("Tracing isn't set up".to_string());
+    };
+    let settings = state.telemetry.settings.lock().unwrap().clone();
+    let provider = if settings.enabled {
+        Some(exporter_provider(app, &settings)?)
+    } else {
+        None
+    };
+    let layer: Option<ExportLayer> = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)));
+    handle
+        .reload(layer)
+        .map_err(|e| format!("Failed to switch trace exporters: {}", e))?;
+
+    if settings.enabled {
+        log::info!("Exporting traces to {}", settings.endpoint);
+    }
+    let previous = std::mem::replace(&mut *state.telemetry.provider.lock().unwrap(), provider);
+    if let Some(previous) = previous {
+        std::thread::spawn(move || {
+            if let Err(e) = previous.shutdown_with_timeout(FLUSH_TIMEOUT) {
+                log::warn!("Failed to flush traces: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+// Send what is still queued before the app quits
+pub fn shutdown(state: &AppState) {
+    if let Some(provider) = state.telemetry.provider.lock().unwrap().take() {
+        if let Err(e) = provider.shutdown_with_timeout(FLUSH_TIMEOUT) {
+            log::warn!("Failed to flush traces: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_telemetry_settings(state: tauri::State<Arc<AppState>>) -> TelemetrySettings {
+    state.telemetry.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn configure_telemetry(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    enabled: Option<bool>,
+    endpoint: Option<String>,
+    headers: Option<BTreeMap<String, String>>,
+    instance: Option<String>,
+) -> Result<TelemetrySettings, AppError> {
+    let mut settings = state.telemetry.settings.lock().unwrap().clone();
+    settings.enabled = enabled.unwrap_or(settings.enabled);
+    settings.endpoint = endpoint.map(|e| e.trim().to_string()).unwrap_or(settings.endpoint);
+    settings.headers = headers.unwrap_or(settings.headers);
+    settings.instance = instance.map(|i| i.trim().to_string()).unwrap_or(settings.instance);
+    settings.validate().map_err(AppError::invalid)?;
+
+    let path = store::data_file(&app, SETTINGS_FILE)?;
+    store::save_json(&path, &settings)?;
+    *state.telemetry.settings.lock().unwrap() = settings.clone();
+    apply(&app, &state)?;
+    Ok(settings)
+}
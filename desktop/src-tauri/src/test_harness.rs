@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::transfers::{self, FileDetail};
+use crate::{emit_backend_status, quiet_hours, AppState, BackendStatus};
+
+// Hidden flag that turns on the hooks below, for integration tests driving the app
+// over IPC. Never set by the app itself.
+const CLI_FLAG: &str = "--test-harness";
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+// Time the schedulers see while frozen
+static FROZEN_AT: Mutex<Option<chrono::DateTime<chrono::Local>>> = Mutex::new(None);
+
+// Health reported per profile regardless of what the checker finds
+static FORCED_HEALTH: Mutex<BTreeMap<String, bool>> = Mutex::new(BTreeMap::new());
+
+pub fn enabled() -> bool {
+    *ENABLED.get_or_init(|| {
+        let enabled = std::env::args().skip(1).any(|arg| arg == CLI_FLAG);
+        if enabled {
+            log::warn!("Test harness enabled; backend health and time can be faked over IPC");
+        }
+        enabled
+    })
+}
+
+fn require() -> Result<(), String> {
+    if enabled() {
+        Ok(())
+    } else {
+        Err(format!("Only available when started with {}", CLI_FLAG))
+    }
+}
+
+// Current time for schedulers (quiet hours, scheduled copies); frozen under the harness
+pub fn now() -> chrono::DateTime<chrono::Local> {
+    FROZEN_AT.lock().unwrap().unwrap_or_else(chrono::Local::now)
+}
+
+// Health forced for a profile, which the health checker must report instead of its own
+pub fn forced_health(profile: &str) -> Option<bool> {
+    FORCED_HEALTH.lock().unwrap().get(profile).copied()
+}
+
+// Quiet hours are re-evaluated right away, so tests needn't wait for the monitor
+fn time_changed(app: &AppHandle, state: &AppState) {
+    quiet_hours::update(app, state);
+}
+
+#[tauri::command]
+pub fn harness_enabled() -> bool {
+    enabled()
+}
+
+// Freeze the schedulers' clock at `at_ms` (Unix milliseconds), or let it run again
+#[tauri::command]
+pub fn harness_set_time(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    at_ms: Option<i64>,
+) -> Result<(), AppError> {
+    require()?;
+    let at = match at_ms {
+        Some(ms) => Some(
+            chrono::DateTime::from_timestamp_millis(ms)
+                .ok_or_else(|| AppError::invalid(format!("{} is out of range", ms)))?
+                .with_timezone(&chrono::Local),
+        ),
+        None => None,
+    };
+    *FROZEN_AT.lock().unwrap() = at;
+    time_changed(&app, &state);
+    Ok(())
+}
+
+// Move the frozen clock forward; freezes it at the current time first if it runs
+#[tauri::command]
+pub fn harness_advance_time(app: AppHandle, state: tauri::State<Arc<AppState>>, secs: i64) -> Result<(), AppError> {
+    require()?;
+    {
+        let mut frozen = FROZEN_AT.lock().unwrap();
+        let at = frozen.unwrap_or_else(chrono::Local::now);
+        *frozen = Some(at + chrono::Duration::seconds(secs));
+    }
+    time_changed(&app, &state);
+    Ok(())
+}
+
+// Report a profile's backend as healthy or unhealthy until released with None
+#[tauri::command]
+pub fn harness_force_health(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    profile: Option<String>,
+    healthy: Option<bool>,
+) -> Result<(), AppError> {
+    require()?;
+    let sidecar = match profile {
+        Some(profile) => state
+            .sidecars
+            .lock()
+            .unwrap()
+            .get(&profile)
+            .cloned()
+            .ok_or_else(|| AppError::invalid(format!("No profile {:?}", profile)))?,
+        None => state.sidecar(),
+    };
+    match healthy {
+        Some(healthy) => {
+            FORCED_HEALTH.lock().unwrap().insert(sidecar.profile.clone(), healthy);
+            sidecar.is_healthy.store(healthy, Ordering::SeqCst);
+            let status = if healthy { BackendStatus::Healthy } else { BackendStatus::Unhealthy };
+            emit_backend_status(&app, &sidecar, status);
+        }
+        // The health checker takes over again on its next round
+        None => {
+            FORCED_HEALTH.lock().unwrap().remove(&sidecar.profile);
+        }
+    }
+    Ok(())
+}
+
+// Make a transfer with the given per-file state appear, as if an import had run
+#[tauri::command]
+pub fn harness_inject_transfer(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    id: String,
+    kind: String,
+    files: Vec<FileDetail>,
+    running: bool,
+) -> Result<(), AppError> {
+    require()?;
+    transfers::track(&app, &id, &kind, files.iter().map(|f| (f.key.clone(), f.size, false)).collect());
+    for file in files {
+        transfers::set_file(&state, &id, file);
+    }
+    if !running {
+        transfers::finish(&state, &id);
+    }
+    Ok(())
+}
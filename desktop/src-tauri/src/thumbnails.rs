@@ -0,0 +1,468 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Semaphore;
+
+use crate::error::AppError;
+use crate::{backend, emit_to_main, store, version, AppState};
+
+pub const SETTINGS_FILE: &str = "thumbnails.json";
+
+// Thumbnails generated at once; enough to fill a screen quickly without starving
+// the backend
+const PARALLELISM: usize = 4;
+
+// Longest side in pixels, matching the grid's tiles on high-DPI screens
+const SIZE: u32 = 256;
+
+// How often background transfers check whether warming has finished
+const YIELD_POLL: Duration = Duration::from_millis(250);
+
+// Only these are worth asking the backend for
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "gif"];
+
+// Largest original read into memory for the platform decoder; bigger ones go to the
+// backend, which refuses them cheaply
+const MAX_PLATFORM_SOURCE: u64 = 64 << 20;
+
+// JPEG quality of generated thumbnails, as the backend uses
+#[cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+const QUALITY: u32 = 80;
+
+// Who decodes originals into thumbnails
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decoder {
+    // The OS's imaging stack in process (ImageIO on macOS, Windows.Graphics.Imaging on
+    // Windows), which decodes JPEGs at reduced size and may use the GPU; falls back
+    // to the backend when it fails. Not available elsewhere.
+    Platform,
+    // The backend's portable decoder only
+    Builtin,
+}
+
+// The OS's decoder wherever there is one
+impl Default for Decoder {
+    fn default() -> Self {
+        if platform_available() {
+            Decoder::Platform
+        } else {
+            Decoder::Builtin
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThumbnailSettings {
+    pub decoder: Decoder,
+}
+
+pub struct Thumbnails {
+    permits: Arc<Semaphore>,
+    // Bumped by every warm_thumbnails call; work queued for an older folder or scroll
+    // position is skipped
+    generation: AtomicU64,
+    // Thumbnails queued or being generated
+    pending: AtomicUsize,
+    settings: Mutex<ThumbnailSettings>,
+}
+
+impl Thumbnails {
+    pub fn new() -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(PARALLELISM)),
+            generation: AtomicU64::new(0),
+            pending: AtomicUsize::new(0),
+            settings: Mutex::new(ThumbnailSettings::default()),
+        }
+    }
+
+    pub fn is_warming(&self) -> bool {
+        self.pending.load(Ordering::SeqCst) > 0
+    }
+}
+
+// Counts a queued thumbnail until it is done or skipped
+struct Pending<'a>(&'a AtomicUsize);
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ThumbnailReady {
+    pub bucket: String,
+    pub key: String,
+    pub path: PathBuf,
+}
+
+fn is_image(key: &str) -> bool {
+    key.rsplit_once('.')
+        .is_some_and(|(_, ext)| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+fn cache_path(app: &AppHandle, bucket: &str, key: &str) -> Result<PathBuf, String> {
+    let name = hex::encode(Sha256::digest(format!("{}/{}/{}", bucket, key, SIZE).as_bytes()));
+    Ok(app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to resolve cache directory: {}", e))?
+        .join("thumbnails")
+        .join(format!("{}.jpg", name)))
+}
+
+// Background transfers call this first so visible thumbnails get the bandwidth.
+// Returns false if cancelled or shutting down meanwhile.
+pub async fn yield_to_foreground(state: &AppState, cancel: &AtomicBool) -> bool {
+    while state.thumbnails.is_warming() {
+        if cancel.load(Ordering::SeqCst) || state.shutdown.load(Ordering::SeqCst) {
+            return false;
+        }
+        tokio::time::sleep(YIELD_POLL).await;
+    }
+    true
+}
+
+// Ask the backend to decode and scale the original
+async fn backend_thumbnail(base_url: &str, bucket: &str, key: &str, out: &Path) -> Result<(), String> {
+    let mut url = backend::api_url(base_url, &["thumbnail", bucket, key])?;
+    url.query_pairs_mut().append_pair("size", &SIZE.to_string());
+    let resp = backend::shared_client(base_url)
+        .get(url)
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get thumbnail for {}: {}", key, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Thumbnail for {} returned status: {}", key, resp.status()));
+    }
+    let bytes = resp
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read thumbnail for {}: {}", key, e))?;
+    tokio::fs::write(out, &bytes)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", out.display(), e))
+}
+
+// Whether this OS has an imaging stack to decode with, checked once
+fn platform_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let available = probe();
+        log::info!("Platform thumbnail decoder {}", if available { "available" } else { "unavailable" });
+        available
+    })
+}
+
+// Read the original into memory and scale it with the OS's decoder
+async fn platform_thumbnail(base_url: &str, bucket: &str, key: &str, out: &Path) -> Result<(), String> {
+    let resp = backend::shared_client(base_url)
+        .get(backend::download_url(base_url, bucket, key)?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", key, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Downloading {} returned status: {}", key, resp.status()));
+    }
+    let too_large = || format!("{} is too large to decode here", key);
+    if resp.content_length().is_some_and(|len| len > MAX_PLATFORM_SOURCE) {
+        return Err(too_large());
+    }
+
+    let mut source = Vec::with_capacity(resp.content_length().unwrap_or(0) as usize);
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Failed to download {}: {}", key, e))?;
+        if (source.len() + chunk.len()) as u64 > MAX_PLATFORM_SOURCE {
+            return Err(too_large());
+        }
+        source.extend_from_slice(&chunk);
+    }
+    let thumbnail = tauri::async_runtime::spawn_blocking(move || scale(&source))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to decode {}: {}", key, e))?;
+    tokio::fs::write(out, &thumbnail)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", out.display(), e))
+}
+
+#[cfg(target_os = "macos")]
+fn probe() -> bool {
+    true
+}
+
+// ImageIO builds the thumbnail while decoding, shrinking JPEGs at load
+#[cfg(target_os = "macos")]
+fn scale(source: &[u8]) -> Result<Vec<u8>, String> {
+    use objc2_core_foundation::{CFBoolean, CFData, CFDictionary, CFMutableData, CFNumber, CFString, CFType};
+    use objc2_image_io::{
+        kCGImageDestinationLossyCompressionQuality, kCGImageSourceCreateThumbnailFromImageAlways,
+        kCGImageSourceCreateThumbnailWithTransform, kCGImageSourceThumbnailMaxPixelSize, CGImageDestination,
+        CGImageSource,
+    };
+
+    let data = CFData::from_bytes(source);
+    let size = CFNumber::new_i32(SIZE as i32);
+    let quality = CFNumber::new_f64(f64::from(QUALITY) / 100.0);
+    let yes: &CFType = CFBoolean::new(true);
+    // SAFETY: the keys are ImageIO's constant strings, and every value has the type
+    // ImageIO expects for its key
+    unsafe {
+        let source = CGImageSource::with_data(&data, None).ok_or("unsupported image")?;
+        let options = CFDictionary::<CFString, CFType>::from_slices(
+            &[
+                kCGImageSourceCreateThumbnailFromImageAlways,
+                kCGImageSourceCreateThumbnailWithTransform,
+                kCGImageSourceThumbnailMaxPixelSize,
+            ],
+            &[yes, yes, &size],
+        );
+        let image = source
+            .thumbnail_at_index(0, Some(options.as_opaque()))
+            .ok_or("unsupported image")?;
+
+        let out = CFMutableData::new(None, 0).ok_or("out of memory")?;
+        let destination = CGImageDestination::with_data(&out, &CFString::from_static_str("public.jpeg"), 1, None)
+            .ok_or("JPEG encoding is unavailable")?;
+        let properties =
+            CFDictionary::<CFString, CFType>::from_slices(&[kCGImageDestinationLossyCompressionQuality], &[&quality]);
+        destination.add_image(&image, Some(properties.as_opaque()));
+        if !destination.finalize() {
+            return Err("failed to encode the thumbnail".to_string());
+        }
+        Ok(out.to_vec())
+    }
+}
+
+// Activating a WinRT imaging class fails where Windows.Graphics.Imaging is missing,
+// e.g. on stripped-down Windows editions
+#[cfg(target_os = "windows")]
+fn probe() -> bool {
+    windows::Graphics::Imaging::BitmapEncoder::JpegEncoderId().is_ok()
+}
+
+// Windows.Graphics.Imaging (WIC underneath) scales while decoding
+#[cfg(target_os = "windows")]
+fn scale(source: &[u8]) -> Result<Vec<u8>, String> {
+    use windows::core::HSTRING;
+    use windows::Foundation::{PropertyType, PropertyValue};
+    use windows::Graphics::Imaging::{
+        BitmapAlphaMode, BitmapDecoder, BitmapEncoder, BitmapInterpolationMode, BitmapPixelFormat, BitmapPropertySet,
+        BitmapTransform, BitmapTypedValue, ColorManagementMode, ExifOrientationMode,
+    };
+    use windows::Storage::Streams::{Buffer, DataReader, DataWriter, InMemoryRandomAccessStream, InputStreamOptions};
+
+    let result = || -> windows::core::Result<Vec<u8>> {
+        let input = InMemoryRandomAccessStream::new()?;
+        let writer = DataWriter::new()?;
+        writer.WriteBytes(source)?;
+        input.WriteAsync(&writer.DetachBuffer()?)?.join()?;
+        input.Seek(0)?;
+        let decoder = BitmapDecoder::CreateAsync(&input)?.join()?;
+
+        let (width, height) = (decoder.OrientedPixelWidth()?, decoder.OrientedPixelHeight()?);
+        let scale = (f64::from(SIZE) / f64::from(width.max(height).max(1))).min(1.0);
+        let transform = BitmapTransform::new()?;
+        transform.SetScaledWidth(((f64::from(width) * scale).round() as u32).max(1))?;
+        transform.SetScaledHeight(((f64::from(height) * scale).round() as u32).max(1))?;
+        transform.SetInterpolationMode(BitmapInterpolationMode::Fant)?;
+        let bitmap = decoder
+            .GetSoftwareBitmapTransformedAsync(
+                BitmapPixelFormat::Bgra8,
+                BitmapAlphaMode::Ignore,
+                &transform,
+                ExifOrientationMode::RespectExifOrientation,
+                ColorManagementMode::ColorManageToSRgb,
+            )?
+            .join()?;
+
+        let output = InMemoryRandomAccessStream::new()?;
+        let options = BitmapPropertySet::new()?;
+        let quality = PropertyValue::CreateSingle(QUALITY as f32 / 100.0)?;
+        options.Insert(&HSTRING::from("ImageQuality"), &BitmapTypedValue::Create(&quality, PropertyType::Single)?)?;
+        let encoder =
+            BitmapEncoder::CreateWithEncodingOptionsAsync(BitmapEncoder::JpegEncoderId()?, &output, &options)?.join()?;
+        encoder.SetSoftwareBitmap(&bitmap)?;
+        encoder.FlushAsync()?.join()?;
+
+        let size = output.Size()? as u32;
+        output.Seek(0)?;
+        let buffer = output.ReadAsync(&Buffer::Create(size)?, size, InputStreamOptions::None)?.join()?;
+        let mut bytes = vec![0; buffer.Length()? as usize];
+        DataReader::FromBuffer(&buffer)?.ReadBytes(&mut bytes)?;
+        Ok(bytes)
+    };
+    result().map_err(|e| e.to_string())
+}
+
+// No imaging stack that can be used in process; libvips would have to be linked
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn probe() -> bool {
+    false
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn scale(_source: &[u8]) -> Result<Vec<u8>, String> {
+    Err("there is no platform decoder on this OS".to_string())
+}
+
+async fn generate(app: &AppHandle, state: &AppState, bucket: &str, key: &str, path: &Path) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    // Write then rename so the grid never loads a partial file
+    let tmp = path.with_extension("tmp");
+    let base_url = state.base_url();
+    let decoder = state.thumbnails.settings.lock().unwrap().decoder;
+    let decoded = decoder == Decoder::Platform
+        && platform_available()
+        && match platform_thumbnail(&base_url, bucket, key, &tmp).await {
+            Ok(()) => true,
+            Err(e) => {
+                log::debug!("Platform decoder failed for {}, asking the backend: {}", key, e);
+                false
+            }
+        };
+    if !decoded {
+        backend_thumbnail(&base_url, bucket, key, &tmp).await?;
+    }
+    tokio::fs::rename(&tmp, path)
+        .await
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+
+    emit_to_main(
+        app,
+        "thumbnail-ready",
+        ThumbnailReady {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            path: path.to_path_buf(),
+        },
+    );
+    Ok(())
+}
+
+// Pre-generate thumbnails for the objects visible in the grid, in the order given.
+// Objects are identified by their keys. Already cached thumbnails are skipped; each
+// new one is announced with `thumbnail-ready`. A later call replaces the queue.
+#[tauri::command]
+pub fn warm_thumbnails(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    bucket: String,
+    ids: Vec<String>,
+) -> Result<usize, AppError> {
+    version::ensure_capability(&state, "thumbnails", |c| c.thumbnails)?;
+    let generation = state.thumbnails.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+    let mut queued = 0;
+    for key in ids.into_iter().filter(|key| is_image(key)) {
+        let path = cache_path(&app, &bucket, &key)?;
+        if path.is_file() {
+            continue;
+        }
+        queued += 1;
+        state.thumbnails.pending.fetch_add(1, Ordering::SeqCst);
+
+        let app = app.clone();
+        let state = Arc::clone(&state);
+        let bucket = bucket.clone();
+        tauri::async_runtime::spawn(async move {
+            let _pending = Pending(&state.thumbnails.pending);
+            let Ok(_permit) = Arc::clone(&state.thumbnails.permits).acquire_owned().await else {
+                return;
+            };
+            // Scrolled past or left the folder while waiting
+            if state.thumbnails.generation.load(Ordering::SeqCst) != generation || path.is_file() {
+                return;
+            }
+            if let Err(e) = generate(&app, &state, &bucket, &key, &path).await {
+                log::debug!("{}", e);
+            }
+        });
+    }
+    Ok(queued)
+}
+
+// Generate missing thumbnails of `keys` one at a time in the background, for folders
+// likely to be opened soon. Stops as soon as the grid asks for thumbnails of its own.
+// Returns how many were generated.
+pub async fn pregenerate(app: &AppHandle, state: &AppState, bucket: &str, keys: Vec<String>) -> usize {
+    if version::ensure_capability(state, "thumbnails", |c| c.thumbnails).is_err() {
+        return 0;
+    }
+    let mut generated = 0;
+    for key in keys.into_iter().filter(|key| is_image(key)) {
+        let Ok(path) = cache_path(app, bucket, &key) else {
+            break;
+        };
+        if path.is_file() {
+            continue;
+        }
+        if state.thumbnails.is_warming() || state.shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(_permit) = state.thumbnails.permits.acquire().await else {
+            break;
+        };
+        match generate(app, state, bucket, &key, &path).await {
+            Ok(()) => generated += 1,
+            Err(e) => log::debug!("{}", e),
+        }
+    }
+    generated
+}
+
+// Path of a cached thumbnail, if one has been generated
+#[tauri::command]
+pub fn get_thumbnail(app: AppHandle, bucket: String, key: String) -> Result<Option<PathBuf>, AppError> {
+    let path = cache_path(&app, &bucket, &key)?;
+    Ok(path.is_file().then_some(path))
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => *state.thumbnails.settings.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load thumbnail settings: {}", e),
+    }
+}
+
+fn validate(settings: &ThumbnailSettings) -> Result<(), AppError> {
+    if settings.decoder == Decoder::Platform && !platform_available() {
+        return Err(AppError::Unsupported {
+            message: "This OS has no imaging stack to decode thumbnails with".to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_thumbnail_settings(state: tauri::State<Arc<AppState>>) -> ThumbnailSettings {
+    *state.thumbnails.settings.lock().unwrap()
+}
+
+// Choose the decoder for thumbnails generated from now on; cached ones are kept
+#[tauri::command]
+pub fn set_thumbnail_settings(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    settings: ThumbnailSettings,
+) -> Result<ThumbnailSettings, AppError> {
+    validate(&settings)?;
+    store::save_json(&store::data_file(&app, SETTINGS_FILE)?, &settings)?;
+    *state.thumbnails.settings.lock().unwrap() = settings;
+    Ok(settings)
+}
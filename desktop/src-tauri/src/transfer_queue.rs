@@ -0,0 +1,497 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::permissions::{self, Capability};
+use crate::remote_path::RemotePath;
+use crate::{emit_to_main, file_download, file_upload, operations, store, transfers, AppState};
+
+const QUEUE_FILE: &str = "transfer_queue.json";
+const LIMITS_FILE: &str = "transfer_limits.json";
+
+// How often aggregate progress is reported while the queue is busy
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+// Finished transfers kept in the list
+const KEEP_FINISHED: usize = 200;
+
+// Upper bound for either limit; more only adds contention
+const MAX_CONCURRENT: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Upload,
+    Download,
+}
+
+// Higher priorities start first; within one, transfers start in queue order
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedTransfer {
+    // Also the id of the upload or download once it runs
+    pub id: String,
+    pub direction: Direction,
+    pub bucket: String,
+    pub remote_path: String,
+    // The file uploaded, or where a download goes
+    pub local_path: PathBuf,
+    pub priority: Priority,
+    pub status: QueueStatus,
+    // Known up front for uploads, once started for downloads
+    pub size: Option<u64>,
+    pub error: Option<String>,
+    // Transfers queued before the queue runs dry share a batch, which the aggregate
+    // progress covers
+    #[serde(default)]
+    batch: u64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TransferLimits {
+    pub uploads: usize,
+    pub downloads: usize,
+}
+
+impl Default for TransferLimits {
+    fn default() -> Self {
+        Self { uploads: 3, downloads: 3 }
+    }
+}
+
+// Sent as `transfer-queue-progress` while the queue works through a batch
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct QueueProgress {
+    pub queued: usize,
+    pub running: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub transferred: u64,
+    // Of the transfers whose size is known so far
+    pub total: u64,
+}
+
+// Uploads and downloads waiting their turn. The queue lives in the shell and on disk,
+// so a reloaded webview finds it as it was and a restart picks up where it stopped;
+// uploads and downloads cut off by the backend going away are queued again.
+pub struct TransferQueue {
+    queue: Mutex<Vec<QueuedTransfer>>,
+    limits: Mutex<TransferLimits>,
+    batch: Mutex<u64>,
+    // The progress reporter is running
+    reporting: AtomicBool,
+}
+
+impl TransferQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: Mutex::new(Vec::new()),
+            limits: Mutex::new(TransferLimits::default()),
+            batch: Mutex::new(0),
+            reporting: AtomicBool::new(false),
+        }
+    }
+
+    fn update<F: FnOnce(&mut QueuedTransfer)>(&self, id: &str, f: F) -> Option<QueuedTransfer> {
+        let mut queue = self.queue.lock().unwrap();
+        let transfer = queue.iter_mut().find(|t| t.id == id)?;
+        f(transfer);
+        Some(transfer.clone())
+    }
+
+    fn save(&self, app: &AppHandle) {
+        let queue = self.queue.lock().unwrap();
+        let result = store::data_file(app, QUEUE_FILE).and_then(|path| store::save_json(&path, &*queue));
+        if let Err(e) = result {
+            log::error!("Failed to persist the transfer queue: {}", e);
+        }
+    }
+}
+
+// Load the queue and its limits; transfers running at exit go back in line
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, LIMITS_FILE) {
+        Ok(path) => *state.transfer_queue.limits.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load transfer limits: {}", e),
+    }
+    let mut queue: Vec<QueuedTransfer> = match store::data_file(app, QUEUE_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load the transfer queue: {}", e);
+            return;
+        }
+    };
+    for transfer in queue.iter_mut().filter(|t| t.status == QueueStatus::Running) {
+        transfer.status = QueueStatus::Queued;
+    }
+    let batch = queue.iter().map(|t| t.batch).max().unwrap_or(0);
+    *state.transfer_queue.batch.lock().unwrap() = batch;
+    *state.transfer_queue.queue.lock().unwrap() = queue;
+}
+
+// Start what the restored queue holds; run once the other transfer subsystems are restored
+pub fn start(app: &AppHandle, state: &Arc<AppState>) {
+    pump(app, state);
+}
+
+// Whether `id` is a transfer the queue started, so its subsystem leaves restarting it
+// to the queue
+pub fn owns(state: &AppState, id: &str) -> bool {
+    let queue = state.transfer_queue.queue.lock().unwrap();
+    queue.iter().any(|t| t.id == id && t.status != QueueStatus::Completed)
+}
+
+fn is_finished(status: QueueStatus) -> bool {
+    matches!(status, QueueStatus::Completed | QueueStatus::Failed | QueueStatus::Cancelled)
+}
+
+fn report(app: &AppHandle, transfer: &QueuedTransfer) {
+    emit_to_main(app, "transfer-queue-updated", transfer.clone());
+}
+
+// Start queued transfers while there is room under the limits
+fn pump(app: &AppHandle, state: &Arc<AppState>) {
+    let starting: Vec<QueuedTransfer> = {
+        let limits = *state.transfer_queue.limits.lock().unwrap();
+        let mut queue = state.transfer_queue.queue.lock().unwrap();
+        let running = |direction| {
+            queue
+                .iter()
+                .filter(|t| t.direction == direction && t.status == QueueStatus::Running)
+                .count()
+        };
+        let (mut uploads, mut downloads) = (running(Direction::Upload), running(Direction::Download));
+        let mut waiting: Vec<usize> = (0..queue.len()).filter(|&i| queue[i].status == QueueStatus::Queued).collect();
+        // Stable, so queue order holds within a priority
+        waiting.sort_by_key(|&i| queue[i].priority);
+
+        let mut starting = Vec::new();
+        for i in waiting {
+            let (slots, limit) = match queue[i].direction {
+                Direction::Upload => (&mut uploads, limits.uploads),
+                Direction::Download => (&mut downloads, limits.downloads),
+            };
+            if *slots >= limit {
+                continue;
+            }
+            *slots += 1;
+            queue[i].status = QueueStatus::Running;
+            queue[i].error = None;
+            starting.push(queue[i].clone());
+        }
+        starting
+    };
+    if starting.is_empty() {
+        return;
+    }
+    state.transfer_queue.save(app);
+    spawn_reporter(app);
+
+    for transfer in starting {
+        let result = match transfer.direction {
+            Direction::Upload => file_upload::start(
+                app,
+                state,
+                transfer.id.clone(),
+                transfer.local_path.clone(),
+                transfer.bucket.clone(),
+                &transfer.remote_path,
+            ),
+            // A download stopped earlier continues from its part file
+            Direction::Download if state.downloads.job(&transfer.id).is_some() => {
+                file_download::resume_download(app.clone(), state, transfer.id.clone())
+            }
+            Direction::Download => file_download::start(
+                app,
+                state,
+                transfer.id.clone(),
+                transfer.bucket.clone(),
+                &transfer.remote_path,
+                transfer.local_path.clone(),
+            )
+            .map(|_| ()),
+        };
+        match result {
+            Ok(()) => {
+                if let Some(transfer) = state.transfer_queue.update(&transfer.id, |_| {}) {
+                    report(app, &transfer);
+                }
+            }
+            Err(e) => {
+                log::warn!("Queued transfer {} couldn't start: {}", transfer.id, e);
+                settle(app, state, &transfer.id, QueueStatus::Failed, Some(e.to_string()));
+            }
+        }
+    }
+}
+
+fn settle(app: &AppHandle, state: &Arc<AppState>, id: &str, status: QueueStatus, error: Option<String>) {
+    let size = state.downloads.job(id).and_then(|d| d.size);
+    let transfer = state.transfer_queue.update(id, |transfer| {
+        transfer.status = status;
+        transfer.error = error;
+        transfer.size = transfer.size.or(size);
+    });
+    prune(state);
+    state.transfer_queue.save(app);
+    if let Some(transfer) = transfer {
+        report(app, &transfer);
+    }
+}
+
+// Drop the oldest finished transfers beyond KEEP_FINISHED
+fn prune(state: &AppState) {
+    let mut queue = state.transfer_queue.queue.lock().unwrap();
+    let mut excess = queue.iter().filter(|t| is_finished(t.status)).count().saturating_sub(KEEP_FINISHED);
+    queue.retain(|t| {
+        if excess > 0 && is_finished(t.status) {
+            excess -= 1;
+            return false;
+        }
+        true
+    });
+}
+
+// Called by the upload and download runners when a transfer ends, however it ends
+pub fn finished(app: &AppHandle, state: &Arc<AppState>, id: &str, result: &Result<(), String>, cancelled: bool) {
+    let queued = state.transfer_queue.queue.lock().unwrap().iter().any(|t| t.id == id);
+    if !queued {
+        return;
+    }
+    match result {
+        Ok(()) => settle(app, state, id, QueueStatus::Completed, None),
+        Err(_) if cancelled => settle(app, state, id, QueueStatus::Cancelled, None),
+        // The backend went away mid-transfer; try again once it is back
+        Err(e) if !state.is_healthy() && !state.shutdown.load(Ordering::SeqCst) => {
+            log::info!("Queued transfer {} interrupted, queueing it again: {}", id, e);
+            settle(app, state, id, QueueStatus::Queued, Some(e.clone()));
+        }
+        Err(e) => settle(app, state, id, QueueStatus::Failed, Some(e.clone())),
+    }
+    pump(app, state);
+}
+
+fn progress(state: &AppState) -> QueueProgress {
+    let batch = *state.transfer_queue.batch.lock().unwrap();
+    let transfers: Vec<QueuedTransfer> = {
+        let queue = state.transfer_queue.queue.lock().unwrap();
+        queue.iter().filter(|t| t.batch == batch).cloned().collect()
+    };
+    let mut progress = QueueProgress::default();
+    for transfer in transfers {
+        let size = transfer.size.or_else(|| state.downloads.job(&transfer.id).and_then(|d| d.size));
+        progress.total += size.unwrap_or(0);
+        match transfer.status {
+            QueueStatus::Queued => progress.queued += 1,
+            QueueStatus::Running => {
+                progress.running += 1;
+                progress.transferred += transfers::progress(state, &transfer.id).map_or(0, |(moved, _)| moved);
+            }
+            QueueStatus::Completed => {
+                progress.completed += 1;
+                progress.transferred += size.unwrap_or(0);
+            }
+            QueueStatus::Failed => progress.failed += 1,
+            QueueStatus::Cancelled => progress.cancelled += 1,
+        }
+    }
+    progress
+}
+
+// Report aggregate progress until nothing is queued or running, then once more
+fn spawn_reporter(app: &AppHandle) {
+    let state = app.state::<Arc<AppState>>();
+    if state.transfer_queue.reporting.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(PROGRESS_INTERVAL).await;
+            let state = app.state::<Arc<AppState>>();
+            let progress = progress(&state);
+            let idle = progress.queued == 0 && progress.running == 0;
+            if idle {
+                state.transfer_queue.reporting.store(false, Ordering::SeqCst);
+            }
+            emit_to_main(&app, "transfer-queue-progress", progress);
+            if idle {
+                break;
+            }
+        }
+    });
+}
+
+// Queue an upload of local_path to bucket/remote_path, or a download the other way.
+// It starts once fewer than the limit of its direction are running.
+#[tauri::command]
+pub async fn enqueue_transfer(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    direction: Direction,
+    bucket: String,
+    remote_path: String,
+    local_path: PathBuf,
+    priority: Option<Priority>,
+) -> Result<QueuedTransfer, AppError> {
+    if direction == Direction::Upload {
+        let detail = format!("uploading {}", local_path.display());
+        permissions::ensure(&app, &state, Capability::ReadLocalFiles, &detail).await?;
+    }
+    enqueue(&app, &state, direction, bucket, remote_path, local_path, priority)
+}
+
+// enqueue_transfer, for transfers the shell queues itself
+pub fn enqueue(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    direction: Direction,
+    bucket: String,
+    remote_path: String,
+    local_path: PathBuf,
+    priority: Option<Priority>,
+) -> Result<QueuedTransfer, AppError> {
+    let remote_path: String = RemotePath::parse(&remote_path).map_err(AppError::invalid)?.into();
+    if remote_path.is_empty() {
+        return Err(AppError::invalid("Choose a file to transfer"));
+    }
+    let size = match direction {
+        Direction::Upload => {
+            let meta = std::fs::metadata(&local_path)
+                .map_err(|e| AppError::invalid(format!("Failed to read {}: {}", local_path.display(), e)))?;
+            if !meta.is_file() {
+                return Err(AppError::invalid(format!("{} is not a file", local_path.display())));
+            }
+            Some(meta.len())
+        }
+        Direction::Download => {
+            if !local_path.parent().is_some_and(|dir| dir.is_dir()) {
+                return Err(AppError::invalid(format!("The folder for {} doesn't exist", local_path.display())));
+            }
+            None
+        }
+    };
+
+    let transfer = {
+        let mut queue = state.transfer_queue.queue.lock().unwrap();
+        let mut batch = state.transfer_queue.batch.lock().unwrap();
+        // A new batch starts once the last one is through
+        if queue.iter().all(|t| is_finished(t.status)) {
+            *batch += 1;
+        }
+        let transfer = QueuedTransfer {
+            id: uuid::Uuid::new_v4().to_string(),
+            direction,
+            bucket,
+            remote_path,
+            local_path,
+            priority: priority.unwrap_or_default(),
+            status: QueueStatus::Queued,
+            size,
+            error: None,
+            batch: *batch,
+        };
+        queue.push(transfer.clone());
+        transfer
+    };
+    state.transfer_queue.save(app);
+    report(app, &transfer);
+    pump(app, state);
+    Ok(state.transfer_queue.update(&transfer.id, |_| {}).unwrap_or(transfer))
+}
+
+// Every transfer in the queue, in queue order, with the most recently finished ones
+#[tauri::command]
+pub fn list_transfers(state: tauri::State<Arc<AppState>>) -> Vec<QueuedTransfer> {
+    state.transfer_queue.queue.lock().unwrap().clone()
+}
+
+// Take a waiting transfer out of line, or stop a running one
+#[tauri::command]
+pub fn cancel_transfer(app: AppHandle, state: tauri::State<Arc<AppState>>, id: String) -> Result<(), AppError> {
+    let status = state
+        .transfer_queue
+        .update(&id, |_| {})
+        .map(|t| t.status)
+        .ok_or_else(|| AppError::invalid(format!("Unknown transfer {}", id)))?;
+    match status {
+        QueueStatus::Queued => {
+            settle(&app, &state, &id, QueueStatus::Cancelled, None);
+            Ok(())
+        }
+        // The runner reports back through `finished`
+        QueueStatus::Running => Ok(operations::cancel(&state, &id)?),
+        _ => Err(AppError::invalid(format!("Transfer {} has already finished", id))),
+    }
+}
+
+// Move a transfer to `position` in the queue; among transfers of one priority, the
+// earlier ones start first
+#[tauri::command]
+pub fn reorder_transfer(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    id: String,
+    position: usize,
+) -> Result<(), AppError> {
+    {
+        let mut queue = state.transfer_queue.queue.lock().unwrap();
+        let from = queue
+            .iter()
+            .position(|t| t.id == id)
+            .ok_or_else(|| AppError::invalid(format!("Unknown transfer {}", id)))?;
+        let transfer = queue.remove(from);
+        let to = position.min(queue.len());
+        queue.insert(to, transfer);
+    }
+    state.transfer_queue.save(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_transfer_limits(state: tauri::State<Arc<AppState>>) -> TransferLimits {
+    *state.transfer_queue.limits.lock().unwrap()
+}
+
+// Change how many uploads and downloads run at once; raising a limit starts waiting
+// transfers right away, lowering one lets running ones finish
+#[tauri::command]
+pub fn set_transfer_limits(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    limits: TransferLimits,
+) -> Result<(), AppError> {
+    for (name, limit) in [("uploads", limits.uploads), ("downloads", limits.downloads)] {
+        if !(1..=MAX_CONCURRENT).contains(&limit) {
+            return Err(AppError::invalid(format!("Concurrent {} must be 1 to {}", name, MAX_CONCURRENT)));
+        }
+    }
+    *state.transfer_queue.limits.lock().unwrap() = limits;
+    store::save_json(&store::data_file(&app, LIMITS_FILE)?, &limits)?;
+    pump(&app, &state);
+    Ok(())
+}
@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::{activity, emit_to_main, AppState};
+
+// How often the files currently moving are reported while a transfer runs
+const ACTIVE_FILES_INTERVAL: Duration = Duration::from_secs(1);
+
+// Finished transfers whose per-file details are kept for drill-down
+const KEEP_FINISHED: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Pending,
+    // Held until the connection is no longer metered
+    WaitingForWifi,
+    Active,
+    Done,
+    // Already transferred by an earlier run
+    Skipped,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileDetail {
+    pub key: String,
+    pub size: u64,
+    pub transferred: u64,
+    pub status: FileStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TransferDetails {
+    pub id: String,
+    pub kind: String,
+    pub running: bool,
+    pub files: Vec<FileDetail>,
+}
+
+#[derive(Clone, Serialize)]
+struct ActiveFilesEvent {
+    id: String,
+    files: Vec<FileDetail>,
+}
+
+struct FileSlot {
+    detail: FileDetail,
+    // Live byte count while the file is active
+    counter: Option<Arc<AtomicU64>>,
+}
+
+impl FileSlot {
+    fn snapshot(&self) -> FileDetail {
+        let mut detail = self.detail.clone();
+        if let Some(counter) = &self.counter {
+            detail.transferred = counter.load(Ordering::Relaxed);
+        }
+        detail
+    }
+}
+
+struct Tracked {
+    kind: String,
+    files: Vec<FileSlot>,
+    index: HashMap<String, usize>,
+    running: Arc<AtomicBool>,
+}
+
+// Per-file state of folder-level transfers (imports, remote copies, dock drops), so
+// the frontend can drill into an aggregate transfer and see which file is stuck
+pub struct Transfers {
+    tracked: Mutex<HashMap<String, Tracked>>,
+    // Ids of finished transfers, oldest first
+    finished: Mutex<Vec<String>>,
+}
+
+impl Transfers {
+    pub fn new() -> Self {
+        Self {
+            tracked: Mutex::new(HashMap::new()),
+            finished: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn with_file<F: FnOnce(&mut FileSlot)>(&self, id: &str, key: &str, f: F) {
+        let mut tracked = self.tracked.lock().unwrap();
+        if let Some(transfer) = tracked.get_mut(id) {
+            if let Some(&i) = transfer.index.get(key) {
+                f(&mut transfer.files[i]);
+            }
+        }
+    }
+}
+
+// Start tracking a transfer's files (key, size, already transferred), replacing details
+// from an earlier run, and report its active files until `finish` is called
+pub fn track(app: &AppHandle, id: &str, kind: &str, files: Vec<(String, u64, bool)>) {
+    let state = app.state::<Arc<AppState>>();
+    let running = Arc::new(AtomicBool::new(true));
+    let mut index = HashMap::with_capacity(files.len());
+    let files = files
+        .into_iter()
+        .enumerate()
+        .map(|(i, (key, size, skipped))| {
+            index.insert(key.clone(), i);
+            FileSlot {
+                detail: FileDetail {
+                    key,
+                    size,
+                    transferred: if skipped { size } else { 0 },
+                    status: if skipped { FileStatus::Skipped } else { FileStatus::Pending },
+                    error: None,
+                },
+                counter: None,
+            }
+        })
+        .collect();
+    let previous = state.transfers.tracked.lock().unwrap().insert(
+        id.to_string(),
+        Tracked {
+            kind: kind.to_string(),
+            files,
+            index,
+            running: Arc::clone(&running),
+        },
+    );
+    if let Some(previous) = previous {
+        previous.running.store(false, Ordering::SeqCst);
+    }
+    state.transfers.finished.lock().unwrap().retain(|f| f != id);
+    activity::transfer_started(&state);
+
+    let app = app.clone();
+    let id = id.to_string();
+    tauri::async_runtime::spawn(async move {
+        while running.load(Ordering::SeqCst) {
+            tokio::time::sleep(ACTIVE_FILES_INTERVAL).await;
+            let state = app.state::<Arc<AppState>>();
+            let files: Vec<FileDetail> = match state.transfers.tracked.lock().unwrap().get(&id) {
+                Some(transfer) => transfer
+                    .files
+                    .iter()
+                    .filter(|slot| slot.detail.status == FileStatus::Active)
+                    .map(FileSlot::snapshot)
+                    .collect(),
+                None => break,
+            };
+            if !files.is_empty() {
+                emit_to_main(&app, "transfer-active-files", ActiveFilesEvent { id: id.clone(), files });
+            }
+        }
+    });
+}
+
+pub fn start_file(state: &AppState, id: &str, key: &str, counter: &Arc<AtomicU64>) {
+    state.transfers.with_file(id, key, |slot| {
+        slot.detail.status = FileStatus::Active;
+        slot.detail.error = None;
+        slot.counter = Some(Arc::clone(counter));
+    });
+}
+
+pub fn wait_file(state: &AppState, id: &str, key: &str) {
+    state.transfers.with_file(id, key, |slot| slot.detail.status = FileStatus::WaitingForWifi);
+}
+
+// Record how a file ended; a cancelled file goes back to pending
+pub fn finish_file<T>(state: &AppState, id: &str, key: &str, result: &Result<T, String>, cancelled: bool) {
+    state.transfers.with_file(id, key, |slot| {
+        slot.detail = slot.snapshot();
+        slot.counter = None;
+        match result {
+            Ok(_) => slot.detail.status = FileStatus::Done,
+            Err(_) if cancelled => slot.detail.status = FileStatus::Pending,
+            Err(e) => {
+                slot.detail.status = FileStatus::Failed;
+                slot.detail.error = Some(e.clone());
+            }
+        }
+    });
+}
+
+// Overwrite a file's recorded state outright; for the test harness
+pub fn set_file(state: &AppState, id: &str, detail: FileDetail) {
+    let key = detail.key.clone();
+    state.transfers.with_file(id, &key, |slot| {
+        slot.detail = detail;
+        slot.counter = None;
+    });
+}
+
+// Stop reporting a transfer; its details stay available until enough newer ones finish
+pub fn finish(state: &AppState, id: &str) {
+    let mut tracked = state.transfers.tracked.lock().unwrap();
+    let Some(transfer) = tracked.get(id) else {
+        return;
+    };
+    transfer.running.store(false, Ordering::SeqCst);
+
+    let mut finished = state.transfers.finished.lock().unwrap();
+    finished.push(id.to_string());
+    while finished.len() > KEEP_FINISHED {
+        let oldest = finished.remove(0);
+        tracked.remove(&oldest);
+    }
+}
+
+// Whether any folder-level transfer is still running
+pub fn any_running(state: &AppState) -> bool {
+    let tracked = state.transfers.tracked.lock().unwrap();
+    tracked.values().any(|transfer| transfer.running.load(Ordering::SeqCst))
+}
+
+// How many folder-level transfers are still running
+pub fn running_count(state: &AppState) -> usize {
+    let tracked = state.transfers.tracked.lock().unwrap();
+    tracked.values().filter(|transfer| transfer.running.load(Ordering::SeqCst)).count()
+}
+
+// Bytes moved so far and in total across a transfer's files
+pub fn progress(state: &AppState, id: &str) -> Option<(u64, u64)> {
+    let tracked = state.transfers.tracked.lock().unwrap();
+    let files = &tracked.get(id)?.files;
+    Some(files.iter().map(FileSlot::snapshot).fold((0, 0), |(moved, total), file| {
+        (moved + file.transferred, total + file.size)
+    }))
+}
+
+// Per-file drill-down of a transfer, optionally only files in one state
+#[tauri::command]
+pub fn get_transfer_details(
+    state: tauri::State<Arc<AppState>>,
+    id: String,
+    status: Option<FileStatus>,
+) -> Result<TransferDetails, AppError> {
+    let tracked = state.transfers.tracked.lock().unwrap();
+    let transfer = tracked.get(&id).ok_or_else(|| AppError::invalid(format!("No details for transfer {}", id)))?;
+    Ok(TransferDetails {
+        id: id.clone(),
+        kind: transfer.kind.clone(),
+        running: transfer.running.load(Ordering::SeqCst),
+        files: transfer
+            .files
+            .iter()
+            .filter(|slot| status.map_or(true, |s| s == slot.detail.status))
+            .map(FileSlot::snapshot)
+            .collect(),
+    })
+}
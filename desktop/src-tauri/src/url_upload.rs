@@ -0,0 +1,552 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tokio::io::AsyncWriteExt;
+
+use crate::backend::SourceChangePolicy;
+use crate::bandwidth::{self, Throttle};
+use crate::error::AppError;
+use crate::remote_copy::Endpoint;
+use crate::scratch::{self, Area};
+use crate::transfer_queue::Direction;
+use crate::{
+    auth, backend, emit_to_main, import, jobs, network, operations, resources, resume, store, transfers, version,
+    AppState,
+};
+
+const UPLOADS_FILE: &str = "url_uploads.json";
+
+// B2's largest object
+const MAX_SIZE: u64 = 10 * 1000 * 1000 * 1000 * 1000;
+
+const PREFLIGHT_TIMEOUT: Duration = Duration::from_secs(15);
+
+// Download attempts before a spooled download gives up
+const MAX_ATTEMPTS: u32 = 5;
+
+// What a URL serves, as far as its headers tell before downloading it
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UrlInfo {
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    // From Content-Disposition, or else the URL's last path segment
+    pub name: String,
+    // The server honours byte ranges, so an interrupted download can pick up where it stopped
+    pub resumable: bool,
+    // Strong ETag or Last-Modified, sent with range requests so a changed file starts over
+    #[serde(default)]
+    pub validator: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlUploadStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UrlUpload {
+    pub id: String,
+    pub url: String,
+    pub dest: Endpoint,
+    pub key: String,
+    pub info: UrlInfo,
+    pub status: UrlUploadStatus,
+    pub transferred: u64,
+    pub error: Option<String>,
+}
+
+// Uploads fetched from a URL. The download streams straight into the destination's
+// stream-upload endpoint; only when that fails and the server supports ranges does
+// it go through a file in the cache, which later runs resume.
+pub struct UrlUploads {
+    uploads: Mutex<Vec<UrlUpload>>,
+    cancel: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl UrlUploads {
+    pub fn new() -> Self {
+        Self {
+            uploads: Mutex::new(Vec::new()),
+            cancel: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn job(&self, id: &str) -> Option<UrlUpload> {
+        self.uploads.lock().unwrap().iter().find(|u| u.id == id).cloned()
+    }
+
+    pub fn jobs(&self) -> Vec<UrlUpload> {
+        self.uploads.lock().unwrap().clone()
+    }
+
+    fn update<F: FnOnce(&mut UrlUpload)>(&self, id: &str, f: F) -> Option<UrlUpload> {
+        let mut uploads = self.uploads.lock().unwrap();
+        let upload = uploads.iter_mut().find(|u| u.id == id)?;
+        f(upload);
+        Some(upload.clone())
+    }
+
+    fn save(&self, app: &AppHandle) {
+        let uploads = self.uploads.lock().unwrap();
+        let result = store::data_file(app, UPLOADS_FILE).and_then(|path| store::save_json(&path, &*uploads));
+        if let Err(e) = result {
+            log::error!("Failed to persist URL uploads: {}", e);
+        }
+    }
+}
+
+// Load persisted uploads; the ones running at exit wait for the user to resume or
+// discard them
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    let mut uploads: Vec<UrlUpload> = match store::data_file(app, UPLOADS_FILE) {
+        Ok(path) => store::load_json(&path),
+        Err(e) => {
+            log::error!("Failed to load URL uploads: {}", e);
+            return;
+        }
+    };
+    let mut interrupted = false;
+    for upload in uploads.iter_mut().filter(|u| u.status == UrlUploadStatus::Running) {
+        // Stopped until the user chooses to resume it
+        upload.status = UrlUploadStatus::Cancelled;
+        resume::hold(state, &upload.id);
+        interrupted = true;
+    }
+    *state.url_uploads.uploads.lock().unwrap() = uploads;
+    if interrupted {
+        state.url_uploads.save(app);
+    }
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .user_agent(concat!("bb-stream-desktop/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn parse_url(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(parsed),
+        scheme => Err(format!("Can't upload from {} URLs", scheme)),
+    }
+}
+
+fn header(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+// filename="..." from a Content-Disposition header
+fn disposition_name(value: &str) -> Option<String> {
+    value.split(';').map(str::trim).find_map(|part| {
+        let name = part.strip_prefix("filename=")?.trim_matches('"');
+        (!name.is_empty()).then(|| name.to_string())
+    })
+}
+
+fn url_name(url: &Url) -> Option<String> {
+    let segment = url.path_segments()?.rev().find(|s| !s.is_empty())?;
+    let name = percent_encoding::percent_decode_str(segment).decode_utf8().ok()?;
+    Some(name.into_owned())
+}
+
+fn info_from(resp: &reqwest::Response) -> UrlInfo {
+    let headers = resp.headers();
+    // A ranged reply to the GET fallback gives the full size after the slash
+    let size = match resp.status() {
+        StatusCode::PARTIAL_CONTENT => header(headers, CONTENT_RANGE)
+            .and_then(|range| range.rsplit_once('/'))
+            .and_then(|(_, total)| total.parse().ok()),
+        _ => header(headers, CONTENT_LENGTH).and_then(|len| len.parse().ok()),
+    };
+    let name = header(headers, CONTENT_DISPOSITION)
+        .and_then(disposition_name)
+        .or_else(|| url_name(resp.url()))
+        .unwrap_or_else(|| "download".to_string());
+    let validator = header(headers, reqwest::header::ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(headers, reqwest::header::LAST_MODIFIED))
+        .map(str::to_string);
+    UrlInfo {
+        size,
+        content_type: header(headers, CONTENT_TYPE).map(str::to_string),
+        // Keys can't nest through a downloaded name
+        name: name.replace(['/', '\\'], "_"),
+        resumable: resp.status() == StatusCode::PARTIAL_CONTENT
+            || header(headers, ACCEPT_RANGES).is_some_and(|r| r.eq_ignore_ascii_case("bytes")),
+        validator,
+    }
+}
+
+async fn preflight(client: &reqwest::Client, url: &Url) -> Result<UrlInfo, String> {
+    let resp = client
+        .head(url.clone())
+        .timeout(PREFLIGHT_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach {}: {}", url, e))?;
+    // Some servers refuse HEAD; ask for the first byte instead
+    let resp = match resp.status() {
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED | StatusCode::FORBIDDEN => client
+            .get(url.clone())
+            .header(reqwest::header::RANGE, "bytes=0-0")
+            .timeout(PREFLIGHT_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach {}: {}", url, e))?,
+        _ => resp,
+    };
+    if !resp.status().is_success() {
+        return Err(format!("{} returned status: {}", url, resp.status()));
+    }
+    let info = info_from(&resp);
+    if info.size.is_some_and(|size| size > MAX_SIZE) {
+        return Err(format!("{} is larger than the largest object storage accepts", url));
+    }
+    Ok(info)
+}
+
+// Size, type and name of what a URL serves, for confirming before uploading it
+#[tauri::command]
+pub async fn preflight_url(url: String) -> Result<UrlInfo, AppError> {
+    let url = parse_url(&url).map_err(AppError::invalid)?;
+    Ok(preflight(&http_client()?, &url).await?)
+}
+
+// Download `url` into dest's bucket and prefix, named after the download unless `name`
+// is given. Progress is reported like other transfers, and as `url-upload-progress`.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(bucket = %dest.bucket), err)]
+pub async fn upload_from_url(
+    app: AppHandle,
+    state: tauri::State<'_, Arc<AppState>>,
+    url: String,
+    dest: Endpoint,
+    name: Option<String>,
+) -> Result<UrlUpload, AppError> {
+    if dest.base_url.is_none() {
+        version::ensure_compatible(&state)?;
+    }
+    let parsed = parse_url(&url).map_err(AppError::invalid)?;
+    let info = preflight(&http_client()?, &parsed).await?;
+    let name = name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| info.name.clone());
+    let key = import::dest_key(&dest.prefix, &name)?;
+
+    let upload = UrlUpload {
+        id: uuid::Uuid::new_v4().to_string(),
+        url: parsed.to_string(),
+        dest,
+        key,
+        info,
+        status: UrlUploadStatus::Running,
+        transferred: 0,
+        error: None,
+    };
+    state.url_uploads.uploads.lock().unwrap().push(upload.clone());
+    state.url_uploads.save(&app);
+
+    spawn_upload(app, Arc::clone(&state), upload.id.clone());
+    Ok(upload)
+}
+
+#[tauri::command]
+pub fn list_url_uploads(state: tauri::State<Arc<AppState>>) -> Vec<UrlUpload> {
+    state.url_uploads.jobs()
+}
+
+#[tauri::command]
+pub fn cancel_url_upload(state: tauri::State<Arc<AppState>>, id: String) -> Result<(), AppError> {
+    match state.url_uploads.cancel.lock().unwrap().get(&id) {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        None => Err(AppError::invalid(format!("URL upload {} is not active", id))),
+    }
+}
+
+// Run a failed or cancelled upload again, continuing a partial download when the
+// server allows it
+#[tauri::command]
+pub fn resume_url_upload(app: AppHandle, state: tauri::State<Arc<AppState>>, id: String) -> Result<(), AppError> {
+    if state.url_uploads.cancel.lock().unwrap().contains_key(&id) {
+        return Err(AppError::busy(format!("URL upload {} is already active", id)));
+    }
+    let upload = state
+        .url_uploads
+        .update(&id, |upload| {
+            if matches!(upload.status, UrlUploadStatus::Failed | UrlUploadStatus::Cancelled) {
+                upload.status = UrlUploadStatus::Running;
+                upload.error = None;
+            }
+        })
+        .ok_or_else(|| AppError::invalid(format!("Unknown URL upload {}", id)))?;
+    if upload.status != UrlUploadStatus::Running {
+        return Err(AppError::invalid(format!("URL upload {} has already completed", id)));
+    }
+
+    spawn_upload(app, Arc::clone(&state), id);
+    Ok(())
+}
+
+fn report(app: &AppHandle, upload: &UrlUpload) {
+    jobs::url_upload_updated(app, upload);
+    emit_to_main(app, "url-upload-progress", upload.clone());
+}
+
+fn spawn_upload(app: AppHandle, state: Arc<AppState>, id: String) {
+    let cancel = Arc::new(AtomicBool::new(false));
+    state.url_uploads.cancel.lock().unwrap().insert(id.clone(), Arc::clone(&cancel));
+    let label = state
+        .url_uploads
+        .job(&id)
+        .map(|upload| format!("Upload {} to {}", upload.info.name, upload.dest.bucket))
+        .unwrap_or_default();
+    let operation = operations::begin(&app, Some(id.clone()), "url_upload", label, Arc::clone(&cancel));
+
+    tauri::async_runtime::spawn(async move {
+        let counter = Arc::new(AtomicU64::new(0));
+        let result = run_upload(&app, &state, &id, &cancel, &counter).await;
+        state.url_uploads.cancel.lock().unwrap().remove(&id);
+        transfers::finish(&state, &id);
+        drop(operation);
+
+        let upload = state.url_uploads.update(&id, |upload| {
+            upload.transferred = counter.load(Ordering::Relaxed);
+            match result {
+                Ok(()) => upload.status = UrlUploadStatus::Completed,
+                Err(_) if cancel.load(Ordering::SeqCst) => upload.status = UrlUploadStatus::Cancelled,
+                Err(e) => {
+                    log::error!("Uploading {} failed: {}", upload.url, e);
+                    upload.status = UrlUploadStatus::Failed;
+                    upload.error = Some(e);
+                }
+            }
+        });
+        state.url_uploads.save(&app);
+        if let Some(upload) = upload {
+            if upload.status == UrlUploadStatus::Failed {
+                jobs::notify_failed(&app, &upload.id);
+            }
+            report(&app, &upload);
+        }
+    });
+}
+
+fn spool_path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    Ok(scratch::dir(app, Area::UrlUploads)?.join(format!("{}.part", id)))
+}
+
+#[tracing::instrument(name = "transfer.url_upload", skip_all, fields(id = %id), err)]
+async fn run_upload(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    id: &str,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let upload = state
+        .url_uploads
+        .job(id)
+        .ok_or_else(|| AppError::invalid(format!("Unknown URL upload {}", id)))?;
+    while upload.dest.base_url.is_none() && !state.is_healthy() {
+        if cancel.load(Ordering::SeqCst) || state.shutdown.load(Ordering::SeqCst) {
+            return Err("Cancelled".to_string());
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    let size = upload.info.size.unwrap_or(0);
+    transfers::track(app, id, "url_upload", vec![(upload.key.clone(), size, false)]);
+    if !network::wait_for_wifi(app, state, id, &upload.key, size, cancel).await {
+        return Err("Cancelled".to_string());
+    }
+
+    transfers::start_file(state, id, &upload.key, counter);
+    let result = transfer(app, state, &upload, cancel, counter).await;
+    transfers::finish_file(state, id, &upload.key, &result, cancel.load(Ordering::SeqCst));
+    if let Err(e) = &result {
+        if upload.dest.base_url.is_none() {
+            auth::report(app, state, e);
+        }
+    }
+    result
+}
+
+async fn transfer(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    upload: &UrlUpload,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let client = http_client()?;
+    let base_url = upload.dest.base_url.clone().unwrap_or_else(|| state.base_url());
+    let dest_client = backend::shared_client(&base_url);
+    let part = spool_path(app, &upload.id)?;
+    let throttle = Throttle::new(state, Direction::Upload, Some(&upload.id));
+
+    // A partial download from an earlier run is continued rather than streamed again
+    if !part.exists() {
+        match stream_direct(&client, (&dest_client, &base_url), upload, &throttle, cancel, counter).await {
+            Ok(()) => return Ok(()),
+            Err(e) if cancel.load(Ordering::SeqCst) || !upload.info.resumable => return Err(e),
+            Err(e) => log::warn!("Streaming {} failed, downloading it to resume instead: {}", upload.url, e),
+        }
+    }
+
+    if let Some(size) = upload.info.size {
+        let have = std::fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+        let available = resources::available_space(&part);
+        if available < size.saturating_sub(have) {
+            return Err(format!("Not enough disk space to download {} before uploading it", upload.info.name));
+        }
+    }
+    spool(&client, upload, &part, cancel, counter).await?;
+    backend::upload_local_file(
+        &dest_client,
+        &base_url,
+        &upload.dest.bucket,
+        &upload.key,
+        &part,
+        SourceChangePolicy::Abort,
+        &throttle,
+        cancel,
+        counter,
+    )
+    .await?;
+    if let Err(e) = std::fs::remove_file(&part) {
+        log::warn!("Failed to remove {}: {}", part.display(), e);
+    }
+    Ok(())
+}
+
+// Pipe the download into the destination without touching the disk
+async fn stream_direct(
+    client: &reqwest::Client,
+    (dest_client, base_url): (&reqwest::Client, &str),
+    upload: &UrlUpload,
+    throttle: &Throttle,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let resp = client
+        .get(&upload.url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", upload.url, e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Downloading {} returned status: {}", upload.url, resp.status()));
+    }
+    counter.store(0, Ordering::Relaxed);
+    let stream = bandwidth::throttled(resp.bytes_stream(), throttle.clone());
+    let body = backend::metered_body(stream, Arc::clone(cancel), Arc::clone(counter));
+    backend::upload_stream(dest_client, base_url, &upload.dest.bucket, &upload.key, body).await?;
+    // A dropped connection can end the body early without an error
+    match upload.info.size {
+        Some(size) if counter.load(Ordering::Relaxed) != size => {
+            Err(format!("Download of {} ended early", upload.url))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Download into `part`, continuing from its length and retrying with backoff
+async fn spool(
+    client: &reqwest::Client,
+    upload: &UrlUpload,
+    part: &Path,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let mut attempt = 0;
+    loop {
+        match spool_once(client, upload, part, cancel, counter).await {
+            Ok(()) => return Ok(()),
+            Err(e) if cancel.load(Ordering::SeqCst) => return Err(e),
+            Err(e) => {
+                attempt += 1;
+                if attempt >= MAX_ATTEMPTS {
+                    return Err(e);
+                }
+                log::warn!("Downloading {} failed (attempt {}): {}", upload.url, attempt, e);
+                tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+            }
+        }
+    }
+}
+
+async fn spool_once(
+    client: &reqwest::Client,
+    upload: &UrlUpload,
+    part: &Path,
+    cancel: &Arc<AtomicBool>,
+    counter: &Arc<AtomicU64>,
+) -> Result<(), String> {
+    let offset = tokio::fs::metadata(part).await.map(|m| m.len()).unwrap_or(0);
+    if upload.info.size.is_some_and(|size| offset == size) {
+        counter.store(offset, Ordering::Relaxed);
+        return Ok(());
+    }
+
+    let mut request = client.get(&upload.url);
+    if offset > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = &upload.info.validator {
+            request = request.header(reqwest::header::IF_RANGE, validator);
+        }
+    }
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", upload.url, e))?;
+    // A full response to a range request means the file changed since; start over
+    let append = match resp.status() {
+        StatusCode::PARTIAL_CONTENT => offset > 0,
+        status if status.is_success() => false,
+        status => return Err(format!("Downloading {} returned status: {}", upload.url, status)),
+    };
+    let mut file = if append {
+        tokio::fs::OpenOptions::new().append(true).open(part).await
+    } else {
+        tokio::fs::File::create(part).await
+    }
+    .map_err(|e| format!("Failed to open {}: {}", part.display(), e))?;
+    counter.store(if append { offset } else { 0 }, Ordering::Relaxed);
+
+    let mut body = resp.bytes_stream();
+    while let Some(chunk) = body.next().await {
+        if cancel.load(Ordering::SeqCst) {
+            let _ = file.flush().await;
+            return Err("Cancelled".to_string());
+        }
+        let chunk = chunk.map_err(|e| format!("Failed to download {}: {}", upload.url, e))?;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+        counter.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+
+    match upload.info.size {
+        Some(size) if counter.load(Ordering::Relaxed) != size => {
+            Err(format!("Download of {} ended early", upload.url))
+        }
+        _ => Ok(()),
+    }
+}
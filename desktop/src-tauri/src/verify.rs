@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::transfer_queue::{self, Direction, Priority};
+use crate::{backend, checksums, emit_to_main, version, AppState};
+
+// Times a transfer that came out corrupt is queued again before it is left as is
+const MAX_RETRIES: u32 = 2;
+
+// How long a verification waits for the backend before giving up
+const BACKEND_WAIT: Duration = Duration::from_secs(60);
+
+// Sent as `transfer-verified` when a finished transfer's local file matches the
+// object in the bucket, and as `transfer-corrupt` when it doesn't
+#[derive(Clone, Debug, Serialize)]
+pub struct TransferVerification {
+    pub id: String,
+    pub direction: Direction,
+    pub bucket: String,
+    pub key: String,
+    pub local_path: PathBuf,
+    pub sha1: String,
+    // SHA-1 B2 stored, as large_file_sha1 for large files; None for large files
+    // stored without one, e.g. by other tools, which only have their size compared
+    pub expected_sha1: Option<String>,
+    pub size: u64,
+    pub expected_size: u64,
+    // Times the transfer was already redone because it came out corrupt
+    pub retries: u32,
+    // Queued transfer that redoes a corrupt one
+    pub requeued_as: Option<String>,
+}
+
+impl TransferVerification {
+    fn matches(&self) -> bool {
+        self.size == self.expected_size
+            && self
+                .expected_sha1
+                .as_ref()
+                .map_or(true, |sha1| sha1.eq_ignore_ascii_case(&self.sha1))
+    }
+}
+
+pub struct Verifier {
+    // Retries so far of transfers queued to redo corrupt ones, by transfer id
+    retries: Mutex<HashMap<String, u32>>,
+}
+
+impl Verifier {
+    pub fn new() -> Self {
+        Self {
+            retries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+// Hash the local side of a finished transfer in the background and compare it with
+// what B2 stored, queueing the transfer again if they differ
+pub fn spawn(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    id: &str,
+    direction: Direction,
+    bucket: &str,
+    key: &str,
+    local_path: &Path,
+) {
+    let (app, state) = (app.clone(), Arc::clone(state));
+    let (id, bucket, key, local_path) = (id.to_string(), bucket.to_string(), key.to_string(), local_path.to_path_buf());
+    tauri::async_runtime::spawn(async move {
+        match verify(&state, &id, direction, &bucket, &key, &local_path).await {
+            Ok(verification) => report(&app, &state, verification),
+            Err(e) => log::warn!("Couldn't verify {}/{}: {}", bucket, key, e),
+        }
+    });
+}
+
+async fn verify(
+    state: &AppState,
+    id: &str,
+    direction: Direction,
+    bucket: &str,
+    key: &str,
+    local_path: &Path,
+) -> Result<TransferVerification, String> {
+    let mut waited = Duration::ZERO;
+    while !state.is_healthy() {
+        if state.shutdown.load(Ordering::SeqCst) || waited >= BACKEND_WAIT {
+            return Err("the backend is unavailable".to_string());
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        waited += Duration::from_secs(1);
+    }
+    version::ensure_capability(state, "checksums", |c| c.checksums).map_err(|e| e.to_string())?;
+
+    let base_url = state.base_url();
+    let stored = backend::object_checksums(&backend::shared_client(&base_url), &base_url, bucket, key).await?;
+    // Hashed on a blocking thread, a chunk at a time
+    let hashes = checksums::local_hashes(state, local_path).await?;
+    let size = std::fs::metadata(local_path)
+        .map_err(|e| format!("Failed to read {}: {}", local_path.display(), e))?
+        .len();
+    Ok(TransferVerification {
+        id: id.to_string(),
+        direction,
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        local_path: local_path.to_path_buf(),
+        sha1: hashes.sha1,
+        expected_sha1: Some(stored.sha1).filter(|sha1| !sha1.is_empty()),
+        size,
+        expected_size: stored.size.max(0) as u64,
+        retries: 0,
+        requeued_as: None,
+    })
+}
+
+fn report(app: &AppHandle, state: &Arc<AppState>, mut verification: TransferVerification) {
+    let retries = state.verifier.retries.lock().unwrap().remove(&verification.id).unwrap_or(0);
+    verification.retries = retries;
+    if verification.matches() {
+        log::info!("Verified {}/{}", verification.bucket, verification.key);
+        emit_to_main(app, "transfer-verified", verification);
+        return;
+    }
+
+    log::error!(
+        "{} doesn't match {}/{} after transfer {} (SHA-1 {}, expected {:?}; {} bytes, expected {})",
+        verification.local_path.display(),
+        verification.bucket,
+        verification.key,
+        verification.id,
+        verification.sha1,
+        verification.expected_sha1,
+        verification.size,
+        verification.expected_size
+    );
+    if retries < MAX_RETRIES {
+        let requeued = transfer_queue::enqueue(
+            app,
+            state,
+            verification.direction,
+            verification.bucket.clone(),
+            verification.key.clone(),
+            verification.local_path.clone(),
+            Some(Priority::High),
+        );
+        match requeued {
+            Ok(transfer) => {
+                log::info!("Transferring {} again as {}", verification.key, transfer.id);
+                state.verifier.retries.lock().unwrap().insert(transfer.id.clone(), retries + 1);
+                verification.requeued_as = Some(transfer.id);
+            }
+            Err(e) => log::error!("Failed to queue {} again: {}", verification.key, e),
+        }
+    }
+    emit_to_main(app, "transfer-corrupt", verification);
+}
@@ -0,0 +1,221 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::{backend, AppState};
+
+// API version of the sidecar this shell was built against; bump together with
+// APIVersion in internal/api/server.go
+pub const EXPECTED_API_VERSION: u32 = 1;
+
+// As returned by GET /api/version
+#[derive(Debug, Deserialize)]
+struct VersionInfo {
+    version: String,
+    api_version: u32,
+}
+
+// Optional operations a backend supports, as returned by GET /api/capabilities. The
+// UI and shell enable features from this instead of failing at call time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    pub server_side_copy: bool,
+    pub versioning: bool,
+    pub hide_unhide: bool,
+    pub share_links: bool,
+    pub checksums: bool,
+    pub stream_upload: bool,
+    pub sync: bool,
+    pub watch: bool,
+    pub thumbnails: bool,
+    // Running jobs can be checkpointed before a restart and resumed after it
+    pub drain: bool,
+    // Some settings can be changed on the running process through /api/admin/config
+    pub live_config: bool,
+    // Large files can be uploaded a part at a time through /api/upload/large
+    pub large_file_parts: bool,
+}
+
+impl Capabilities {
+    // What backends from before the manifest existed support
+    fn baseline() -> Self {
+        Self {
+            checksums: true,
+            stream_upload: true,
+            sync: true,
+            watch: true,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CapabilitiesInfo {
+    capabilities: Capabilities,
+}
+
+// A sidecar that speaks a different API than the shell expects
+#[derive(Clone, Debug, Serialize)]
+pub struct VersionMismatch {
+    pub expected_api_version: u32,
+    // None when the sidecar predates version reporting
+    pub api_version: Option<u32>,
+    pub version: Option<String>,
+}
+
+impl VersionMismatch {
+    pub fn message(&self) -> String {
+        match (&self.api_version, &self.version) {
+            (Some(api), Some(version)) => format!(
+                "The bundled backend ({}) uses API version {}, but this app needs version {}. Reinstall the app to fix this.",
+                version, api, self.expected_api_version
+            ),
+            _ => format!(
+                "The bundled backend doesn't report its API version; this app needs version {}. Reinstall the app to fix this.",
+                self.expected_api_version
+            ),
+        }
+    }
+}
+
+// Outcome of the handshake with the current sidecar
+pub struct Versions {
+    mismatch: Mutex<Option<VersionMismatch>>,
+    // Version the sidecar reported, once it has
+    running: Mutex<Option<String>>,
+    // Capability manifest, once fetched
+    capabilities: Mutex<Option<Capabilities>>,
+}
+
+impl Versions {
+    pub fn new() -> Self {
+        Self {
+            mismatch: Mutex::new(None),
+            running: Mutex::new(None),
+            capabilities: Mutex::new(None),
+        }
+    }
+
+    pub fn mismatch(&self) -> Option<VersionMismatch> {
+        self.mismatch.lock().unwrap().clone()
+    }
+
+    pub fn running(&self) -> Option<String> {
+        self.running.lock().unwrap().clone()
+    }
+
+    pub fn capabilities(&self) -> Option<Capabilities> {
+        *self.capabilities.lock().unwrap()
+    }
+
+    // Forget the previous sidecar's result when a new one is spawned
+    pub fn reset(&self) {
+        *self.mismatch.lock().unwrap() = None;
+        *self.running.lock().unwrap() = None;
+        *self.capabilities.lock().unwrap() = None;
+    }
+}
+
+// Ask the sidecar which API it speaks and record whether it matches. Errors mean the
+// question couldn't be answered (yet) and the handshake should be tried again.
+pub async fn handshake(state: &AppState, base_url: &str, timeout: Duration) -> Result<Option<VersionMismatch>, String> {
+    let client = backend::client_builder(base_url)
+        .timeout(timeout)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let resp = client
+        .get(backend::api_url(base_url, &["version"])?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get backend version: {}", e))?;
+
+    let mismatch = if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        Some(VersionMismatch {
+            expected_api_version: EXPECTED_API_VERSION,
+            api_version: None,
+            version: None,
+        })
+    } else if !resp.status().is_success() {
+        return Err(format!("Getting backend version returned status: {}", resp.status()));
+    } else {
+        let info: VersionInfo = resp
+            .json()
+            .await
+            .map_err(|e| format!("Invalid version response: {}", e))?;
+        log::info!("Backend version {} (API version {})", info.version, info.api_version);
+        *state.versions.running.lock().unwrap() = Some(info.version.clone());
+        (info.api_version != EXPECTED_API_VERSION).then_some(VersionMismatch {
+            expected_api_version: EXPECTED_API_VERSION,
+            api_version: Some(info.api_version),
+            version: Some(info.version),
+        })
+    };
+
+    if let Some(mismatch) = &mismatch {
+        log::error!("{}", mismatch.message());
+    }
+    let capabilities = fetch_capabilities(&client, base_url).await?;
+    log::info!("Backend capabilities: {:?}", capabilities);
+    *state.versions.capabilities.lock().unwrap() = Some(capabilities);
+    *state.versions.mismatch.lock().unwrap() = mismatch.clone();
+    Ok(mismatch)
+}
+
+async fn fetch_capabilities(client: &reqwest::Client, base_url: &str) -> Result<Capabilities, String> {
+    let resp = client
+        .get(backend::api_url(base_url, &["capabilities"])?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get backend capabilities: {}", e))?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(Capabilities::baseline());
+    }
+    if !resp.status().is_success() {
+        return Err(format!("Getting backend capabilities returned status: {}", resp.status()));
+    }
+    let info: CapabilitiesInfo = resp
+        .json()
+        .await
+        .map_err(|e| format!("Invalid capabilities response: {}", e))?;
+    Ok(info.capabilities)
+}
+
+// Refuse operations that write to the bucket through a sidecar with a mismatched API,
+// rather than letting them fail part way through
+pub fn ensure_compatible(state: &AppState) -> Result<(), AppError> {
+    match state.versions.mismatch() {
+        Some(mismatch) => Err(AppError::Incompatible {
+            message: mismatch.message(),
+        }),
+        None => Ok(()),
+    }
+}
+
+// Refuse an operation the backend doesn't support. Before the manifest has been
+// fetched the backend gets the benefit of the doubt.
+pub fn ensure_capability(
+    state: &AppState,
+    name: &str,
+    supported: fn(&Capabilities) -> bool,
+) -> Result<(), AppError> {
+    match state.versions.capabilities() {
+        Some(capabilities) if !supported(&capabilities) => Err(AppError::Unsupported {
+            message: format!("The backend doesn't support {}", name),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// None until the backend has been reached
+#[tauri::command]
+pub fn get_backend_capabilities(state: tauri::State<Arc<AppState>>) -> Option<Capabilities> {
+    state.versions.capabilities()
+}
+
+#[tauri::command]
+pub fn get_version_mismatch(state: tauri::State<Arc<AppState>>) -> Option<VersionMismatch> {
+    state.versions.mismatch()
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::remote_path::RemotePath;
+use crate::{store, AppState};
+
+pub const SETTINGS_FILE: &str = "view_prefs.json";
+
+// Folders remembered; the least recently changed are forgotten beyond this
+const MAX_FOLDERS: usize = 5000;
+
+const SORT_KEYS: [&str; 4] = ["name", "size", "modified", "type"];
+
+// Narrowest and widest a list column may be dragged, in CSS pixels
+const COLUMN_WIDTHS: std::ops::RangeInclusive<u32> = 24..=2000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewMode {
+    #[default]
+    List,
+    Grid,
+}
+
+// How a remote folder is shown
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewPrefs {
+    pub sort_key: String,
+    pub sort_direction: SortDirection,
+    pub view_mode: ViewMode,
+    // By column name
+    pub column_widths: BTreeMap<String, u32>,
+}
+
+impl Default for ViewPrefs {
+    fn default() -> Self {
+        Self {
+            sort_key: SORT_KEYS[0].to_string(),
+            sort_direction: SortDirection::default(),
+            view_mode: ViewMode::default(),
+            column_widths: BTreeMap::new(),
+        }
+    }
+}
+
+impl ViewPrefs {
+    fn validate(&self) -> Result<(), String> {
+        if !SORT_KEYS.contains(&self.sort_key.as_str()) {
+            return Err(format!("Sort key must be one of {}", SORT_KEYS.join(", ")));
+        }
+        if let Some((column, width)) = self.column_widths.iter().find(|(_, w)| !COLUMN_WIDTHS.contains(*w)) {
+            return Err(format!(
+                "Column {} is {}px wide; widths must be {} to {}px",
+                column,
+                width,
+                COLUMN_WIDTHS.start(),
+                COLUMN_WIDTHS.end()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Saved {
+    prefs: ViewPrefs,
+    // Unix seconds; decides which folders are forgotten first
+    updated_at: i64,
+}
+
+// Saved preferences by folder, "bucket/prefix"
+pub struct ViewPrefsStore {
+    folders: Mutex<BTreeMap<String, Saved>>,
+}
+
+impl ViewPrefsStore {
+    pub fn new() -> Self {
+        Self {
+            folders: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+pub fn restore(app: &AppHandle, state: &Arc<AppState>) {
+    match store::data_file(app, SETTINGS_FILE) {
+        Ok(path) => *state.view_prefs.folders.lock().unwrap() = store::load_json(&path),
+        Err(e) => log::error!("Failed to load view preferences: {}", e),
+    }
+}
+
+// "bucket/prefix" in a canonical form, so "photos/2024/" and "photos/2024" match
+fn folder_key(path: &str) -> Result<String, String> {
+    let path = RemotePath::parse(path)?;
+    if path.is_root() {
+        return Err("A bucket is needed".to_string());
+    }
+    Ok(path.to_string())
+}
+
+// Preferences for a folder: its own, or else those of its nearest folder that has
+// some, so a media folder's grid carries into its subfolders. None when nothing
+// applies and the app's defaults should be used.
+#[tauri::command]
+pub fn get_view_prefs(state: tauri::State<Arc<AppState>>, path: String) -> Result<Option<ViewPrefs>, AppError> {
+    let key = folder_key(&path).map_err(AppError::invalid)?;
+    let folders = state.view_prefs.folders.lock().unwrap();
+    let mut candidate = key.as_str();
+    loop {
+        if let Some(saved) = folders.get(candidate) {
+            return Ok(Some(saved.prefs.clone()));
+        }
+        match candidate.rsplit_once('/') {
+            Some((parent, _)) => candidate = parent,
+            None => return Ok(None),
+        }
+    }
+}
+
+// Remember how a folder is shown, or forget it when `prefs` is None
+#[tauri::command]
+pub fn set_view_prefs(
+    app: AppHandle,
+    state: tauri::State<Arc<AppState>>,
+    path: String,
+    prefs: Option<ViewPrefs>,
+) -> Result<(), AppError> {
+    let key = folder_key(&path).map_err(AppError::invalid)?;
+    if let Some(prefs) = &prefs {
+        prefs.validate().map_err(AppError::invalid)?;
+    }
+
+    let folders = {
+        let mut folders = state.view_prefs.folders.lock().unwrap();
+        match prefs {
+            Some(prefs) => {
+                let updated_at = chrono::Utc::now().timestamp();
+                folders.insert(key, Saved { prefs, updated_at });
+            }
+            None => {
+                folders.remove(&key);
+            }
+        }
+        while folders.len() > MAX_FOLDERS {
+            let Some(oldest) = folders.iter().min_by_key(|(_, s)| s.updated_at).map(|(k, _)| k.clone()) else {
+                break;
+            };
+            folders.remove(&oldest);
+        }
+        folders.clone()
+    };
+    Ok(store::save_json(&store::data_file(&app, SETTINGS_FILE)?, &folders)?)
+}
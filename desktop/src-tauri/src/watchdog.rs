@@ -0,0 +1,75 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tauri::AppHandle;
+
+use crate::{activity, emit_backend_status, health, test_harness, AppState, BackendStatus, Sidecar};
+
+// How often a healthy sidecar's API is exercised
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+// An API call taking this long while the port still accepts connections means the
+// sidecar is stuck: /health answers from its own goroutine, but requests that touch
+// the job tables never return
+const STALL_THRESHOLD: Duration = Duration::from_secs(30);
+
+// Authenticated and cheap, but takes the locks a deadlocked sidecar would hold
+const PROBE_PATH: &str = "/api/status";
+
+// Watch a spawned sidecar for hangs /health can't see, and restart it when one is
+// found. Stands down once the sidecar is replaced or the app shuts down.
+pub fn spawn(app: AppHandle, state: Arc<AppState>, sidecar: Arc<Sidecar>, instance_id: String) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            activity::sleep(&app, &state, CHECK_INTERVAL).await;
+            if state.shutdown.load(Ordering::SeqCst) || *sidecar.instance_id.lock().unwrap() != instance_id {
+                break;
+            }
+            // Only a sidecar that looks healthy can be hung; the health checker deals
+            // with the rest. Around sleep a slow answer means nothing.
+            if !sidecar.is_healthy.load(Ordering::SeqCst)
+                || test_harness::forced_health(&sidecar.profile).is_some()
+                || state.power.is_settling()
+            {
+                continue;
+            }
+
+            let base_url = sidecar.base_url();
+            let client = state.health_checks.client(&base_url);
+            let probe = client
+                .get(format!("{}{}", base_url, PROBE_PATH))
+                .timeout(STALL_THRESHOLD)
+                .send()
+                .await;
+            match probe {
+                // Any answer, even an error status, means it isn't stuck
+                Ok(_) => continue,
+                Err(e) if !e.is_timeout() => continue,
+                Err(_) => {}
+            }
+            if *sidecar.instance_id.lock().unwrap() != instance_id {
+                break;
+            }
+            // The probe may have been frozen through a sleep
+            if state.power.is_settling() {
+                continue;
+            }
+            let timeout = state.health_checks.settings().timeout();
+            if health::port_accepts(&base_url, timeout).await.is_err() {
+                continue;
+            }
+
+            log::error!(
+                "Sidecar for profile {} accepts connections but {} stalled for {}s; restarting it",
+                sidecar.profile,
+                PROBE_PATH,
+                STALL_THRESHOLD.as_secs()
+            );
+            sidecar.is_healthy.store(false, Ordering::SeqCst);
+            emit_backend_status(&app, &sidecar, BackendStatus::Unhealthy);
+            sidecar.request_restart();
+            break;
+        }
+    });
+}
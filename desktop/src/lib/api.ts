@@ -1,58 +1,211 @@
 // API client for communicating with the Go backend
-import { fetch as tauriFetch } from '@tauri-apps/plugin-http';
-import { invoke } from '@tauri-apps/api/core';
+import { invoke as tauriInvoke, isTauri, type InvokeArgs, type InvokeOptions } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
 // Expected backend version for compatibility check
 const EXPECTED_VERSION = '0.1.0';
 const EXPECTED_API_VERSION = 1;
 
-// Dynamic port management
-let apiPort: number | null = null;
+// Backend origin from the shell's credentials; only used for the live-events WebSocket
+// and outside the desktop shell. Null until the backend is listening.
+let apiOrigin: string | null = null;
 
-async function getApiPort(): Promise<number> {
-  if (apiPort !== null) {
-    return apiPort;
+// Where the backend listens when the frontend runs in a plain browser during development
+const DEV_ORIGIN = 'http://localhost:8765';
+
+// Base URL for direct requests outside the desktop shell
+function getApiBase(): string {
+  return `${apiOrigin ?? DEV_ORIGIN}/api`;
+}
+
+// Forget the cached credentials (useful when the backend restarts)
+export function resetApi(): void {
+  apiOrigin = null;
+  sessionToken = null;
+}
+
+// Fetch credentials early in app startup
+export async function initApi(): Promise<void> {
+  await getSessionToken();
+}
+
+// Where the backend is and a short-lived token for it, from the shell's
+// get_api_credentials; the webview never holds a long-lived backend credential
+interface ApiCredentials {
+  base_url: string | null;
+  via_shell: boolean;
+  token: string;
+  expires_at: number;
+}
+
+// Refresh this many seconds before the token expires
+const TOKEN_REFRESH_MARGIN = 60;
+
+let sessionToken: ApiCredentials | null = null;
+let refreshTimer: ReturnType<typeof setTimeout> | null = null;
+
+export async function getSessionToken(): Promise<string> {
+  const now = Date.now() / 1000;
+  if (sessionToken && sessionToken.expires_at - now > TOKEN_REFRESH_MARGIN) {
+    return sessionToken.token;
   }
   try {
-    apiPort = await invoke<number>('get_api_port');
-    return apiPort;
+    sessionToken = await invoke<ApiCredentials>('get_api_credentials');
+    apiOrigin = sessionToken.base_url;
   } catch {
-    // Fallback for development
-    return 8765;
+    // Outside the desktop shell (development) the backend doesn't check tokens
+    return '';
+  }
+  // Keep a fresh token around for synchronous callers (XHR)
+  if (refreshTimer) {
+    clearTimeout(refreshTimer);
   }
+  const refreshIn = Math.max(sessionToken.expires_at - now - TOKEN_REFRESH_MARGIN, 1);
+  refreshTimer = setTimeout(() => getSessionToken(), refreshIn * 1000);
+  return sessionToken.token;
+}
+
+// Backend origin from the last credentials; null until the backend is listening
+export function getApiOrigin(): string | null {
+  return apiOrigin;
 }
 
-async function getApiBase(): Promise<string> {
-  const port = await getApiPort();
-  return `http://localhost:${port}/api`;
+// In the app requests go through the shell, which knows where the backend is and adds
+// its credential; a plain browser during development talks to it directly, and so do
+// file uploads and downloads (see bodiesViaShell)
+export function viaShell(): boolean {
+  return isTauri();
 }
 
-// Synchronous version for XHR calls - uses cached port or fallback
-function getApiBaseSync(): string {
-  const port = apiPort ?? 8765;
-  return `http://localhost:${port}/api`;
+// Whether the backend listens on a Unix socket, which the webview can't open a
+// WebSocket to
+export function onSocket(): boolean {
+  return sessionToken?.via_shell ?? false;
 }
 
-// Reset port cache (useful when backend restarts)
-export function resetApiPort(): void {
-  apiPort = null;
+// File bodies go to the backend directly with the webview's token, so they stream
+// with progress and can be cancelled. Only a backend the webview can't reach gets
+// them through the shell, which buffers the whole body.
+function bodiesViaShell(): boolean {
+  return viaShell() && (onSocket() || apiOrigin === null);
 }
 
-// Initialize the port cache (call this early in app startup)
-export async function initApiPort(): Promise<number> {
-  return getApiPort();
+// What a shell command failed with, as sent by the shell
+export type AppErrorKind =
+  | 'port_unavailable'
+  | 'sidecar_spawn_failed'
+  | 'backend_unreachable'
+  | 'interrupted'
+  | 'status'
+  | 'unauthorized'
+  | 'incompatible'
+  | 'unsupported'
+  | 'busy'
+  | 'invalid'
+  | 'cancelled'
+  | 'timeout'
+  | 'other';
+
+export interface AppError {
+  kind: AppErrorKind;
+  message: string;
+  // With port_unavailable, sidecar_spawn_failed and status respectively
+  port?: number;
+  source?: string;
+  status?: number;
+  // With timeout: how long was waited, and where the backend stood then (null before
+  // its first status)
+  timeout_ms?: number;
+  backend_status?: BackendStatus | null;
 }
 
-// Use Tauri's fetch for cross-origin requests in webview
-const safeFetch = async (url: string, options?: RequestInit): Promise<Response> => {
+// Rejection of a shell command, so callers can branch on `kind`: e.g. a backend that
+// is down, a rejected key, or a write refused for speaking another API version
+export class CommandError extends Error {
+  readonly kind: AppErrorKind;
+  readonly port: number | null;
+  readonly source: string | null;
+  readonly status: number | null;
+  readonly timeoutMs: number | null;
+  readonly backendStatus: BackendStatus | null;
+
+  constructor(error: AppError) {
+    super(error.message);
+    this.name = 'CommandError';
+    this.kind = error.kind;
+    this.port = error.port ?? null;
+    this.source = error.source ?? null;
+    this.status = error.status ?? null;
+    this.timeoutMs = error.timeout_ms ?? null;
+    this.backendStatus = error.backend_status ?? null;
+  }
+
+  // Just the message, so errors interpolated into text read as before
+  toString(): string {
+    return this.message;
+  }
+}
+
+function isAppError(e: unknown): e is AppError {
+  return typeof e === 'object' && e !== null && 'kind' in e && 'message' in e;
+}
+
+// Call a shell command, rejecting with a CommandError
+export async function invoke<T>(cmd: string, args?: InvokeArgs, options?: InvokeOptions): Promise<T> {
   try {
-    // Try Tauri fetch first (works in Tauri app)
-    return await tauriFetch(url, options as any);
-  } catch {
-    // Fall back to browser fetch (for dev/testing)
-    return await fetch(url, options);
+    return await tauriInvoke<T>(cmd, args, options);
+  } catch (e) {
+    throw new CommandError(isAppError(e) ? e : { kind: 'other', message: String(e) });
+  }
+}
+
+// Send a request through the shell's api_request and get the raw response body.
+// `path` is relative to the backend origin, e.g. /health or /api/buckets.
+async function apiRequest(
+  method: string,
+  path: string,
+  body?: Uint8Array,
+  contentType?: string,
+  extraHeaders?: Record<string, string>
+): Promise<ArrayBuffer> {
+  const headers: Record<string, string> = { 'X-Api-Method': method, 'X-Api-Path': path };
+  if (contentType) {
+    headers['X-Api-Content-Type'] = contentType;
+  }
+  if (extraHeaders) {
+    headers['X-Api-Headers'] = JSON.stringify(extraHeaders);
   }
-};
+  return invoke<ArrayBuffer>('api_request', body ?? new Uint8Array(), { headers });
+}
+
+// Multipart upload through the shell when the backend is on a Unix socket; there is
+// no progress until it completes
+async function uploadViaShell(bucket: string, path: string, file: File): Promise<UploadResult> {
+  const formData = new FormData();
+  formData.append('file', file);
+  // Let the browser encode the form, boundary and all
+  const encoded = new Response(formData);
+  const body = new Uint8Array(await encoded.arrayBuffer());
+  const query = `bucket=${encodeURIComponent(bucket)}&path=${encodeURIComponent(path)}`;
+  const response = await apiRequest(
+    'POST',
+    `/api/upload?${query}`,
+    body,
+    encoded.headers.get('Content-Type') ?? undefined
+  );
+  return JSON.parse(new TextDecoder().decode(response));
+}
+
+// Cached token for synchronous callers; refreshed in the background
+function getSessionTokenSync(): string {
+  return sessionToken?.token ?? '';
+}
+
+// Drop the cached token once the shell revokes it (lock, logout, backend stop)
+listen('session-revoked', () => {
+  sessionToken = null;
+  getSessionToken();
+}).catch(() => {});
 
 export interface BucketInfo {
   Name: string;
@@ -82,7 +235,8 @@ export interface SyncJob {
   progress?: string;
 }
 
-export interface Job {
+// A job as the backend lists it; the shell's Job covers these too
+export interface BackendJob {
   id: string;
   type: string;
   status: string;
@@ -106,6 +260,651 @@ export interface VersionInfo {
   api_version: number;
 }
 
+export interface Capabilities {
+  server_side_copy: boolean;
+  versioning: boolean;
+  hide_unhide: boolean;
+  share_links: boolean;
+  checksums: boolean;
+  stream_upload: boolean;
+  sync: boolean;
+  watch: boolean;
+  thumbnails: boolean;
+  drain: boolean;
+  live_config: boolean;
+  large_file_parts: boolean;
+}
+
+// Sidecar configuration a running sidecar still needs a restart for; also sent as a
+// 'sidecar-config-pending' event
+export interface PendingSidecarChanges {
+  restarts: { profile: string; settings: string[] }[];
+  // The sidecars restart on their own once no transfers are running
+  restart_scheduled: boolean;
+}
+
+export type LogLevel = 'debug' | 'info' | 'warn' | 'error';
+
+export interface LogLevels {
+  // What the shell's own log and captured sidecar output are written at
+  shell: LogLevel;
+  // What the sidecars log at
+  sidecar: LogLevel;
+  // Profiles whose running sidecar took a setLogLevel right away; the others take it
+  // when they restart
+  applied_live: string[];
+}
+
+export interface ViewPrefs {
+  sort_key: 'name' | 'size' | 'modified' | 'type';
+  sort_direction: 'asc' | 'desc';
+  view_mode: 'list' | 'grid';
+  column_widths: Record<string, number>;
+}
+
+export type Capability = 'read_local_files' | 'run_programs' | 'start_servers';
+
+// A remembered answer to a permission prompt
+export interface PermissionGrant {
+  profile: string;
+  capability: Capability;
+  allowed: boolean;
+  decided_at: number;
+}
+
+export interface DestinationSuggestion {
+  bucket: string;
+  prefix: string;
+  // 0-1, relative to the best suggestion
+  score: number;
+  reason: 'same_type' | 'recent';
+}
+
+export type BackendLogLevel = 'debug' | 'info' | 'warn' | 'error';
+
+// Sent as 'safe-mode' when the app started in safe mode after repeated failed launches:
+// only the backend and settings run, without resumed jobs or background monitors
+export interface SafeModeStatus {
+  active: boolean;
+  failed_launches: number;
+  threshold: number;
+}
+
+// A backend status as sent in 'backend-status' events
+export type BackendStatus =
+  | 'starting'
+  | 'healthy'
+  | 'unhealthy'
+  | 'restarting'
+  | 'stopped'
+  | { degraded: { reason: string } }
+  | { crashed: { error: string } }
+  | { version_mismatch: { message: string } }
+  | { auth_required: { message: string } };
+
+// The active profile's backend as the last 'backend-status' event left it; ask at mount
+// time so windows opened later don't wait for the next change
+export interface BackendStatusInfo {
+  profile: string;
+  status: BackendStatus;
+  // ms of the last change; null before the first
+  since: number | null;
+  healthy: boolean;
+  port: number;
+  socket: string | null;
+  pid: number | null;
+  uptime_secs: number | null;
+  external: boolean;
+}
+
+// Versions and folders to quote in bug reports
+export interface AppInfo {
+  app_version: string;
+  // Null until the backend has been reached
+  sidecar_version: string | null;
+  tauri_version: string;
+  webview_version: string | null;
+  // E.g. aarch64-apple-darwin
+  platform: string;
+  data_dir: string | null;
+  log_dir: string | null;
+  cache_dir: string | null;
+  // The active backend's; null with the socket transport or before it is listening
+  port: number | null;
+  socket: string | null;
+}
+
+// What quitting now would cut off
+export interface ActiveTransfers {
+  uploads: number;
+  downloads: number;
+  syncs: number;
+  // Imports, remote copies, URL uploads and dock drops the shell runs itself
+  jobs: number;
+}
+
+// A state-bearing event kept by the shell for windows that subscribe late
+export interface ReplayedEvent<T = unknown> {
+  topic: string;
+  payload: T;
+  emitted_at: number;
+}
+
+export interface StatusChange {
+  at: number;
+  profile: string;
+  status: BackendStatus;
+}
+
+// A profile's status transitions, oldest first, starting with the status in effect when
+// the window opened. Time while the app was closed or the backend stopped counts as neither.
+export interface StatusHistory {
+  changes: StatusChange[];
+  crashes: number;
+  restarts: number;
+  healthy_ms: number;
+  running_ms: number;
+}
+
+// The shell's circuit breaker over backend reads, sent as 'backend-reads'. While 'open',
+// reads fail straight away (listings fall back to the cache) until a probe gets through;
+// 'half_open' means a probe is in flight. `retrying` counts reads waiting to try again.
+export interface ReadStatus {
+  profile: string;
+  circuit: 'closed' | 'open' | 'half_open';
+  retrying: number;
+  failures: number;
+  last_error: string | null;
+  opened_at: number | null;
+  next_probe_at: number | null;
+}
+
+// Response times of recent health checks or proxied requests; percentiles are null
+// without samples
+export interface LatencyStats {
+  samples: number;
+  p50_ms: number | null;
+  p95_ms: number | null;
+  threshold_ms: number;
+}
+
+// Sent as 'backend-slow' when the active backend's p95 crosses a threshold, and again
+// with `slow: false` once it recovers
+export interface LatencyStatus {
+  profile: string;
+  slow: boolean;
+  slow_since: number | null;
+  health: LatencyStats;
+  proxy: LatencyStats;
+}
+
+export interface RouteMetrics {
+  // Router pattern, e.g. /api/buckets/{name}/files
+  route: string;
+  requests_per_sec: number;
+  errors_per_sec: number;
+  mean_ms: number | null;
+  p50_ms: number | null;
+  p95_ms: number | null;
+  p99_ms: number | null;
+}
+
+// Sent as 'backend-metrics' after each scrape of the backend's /metrics: rates and
+// latencies over about the last minute, and runtime gauges as of the scrape
+export interface BackendMetrics {
+  at: number;
+  profile: string;
+  window_secs: number;
+  requests_per_sec: number;
+  errors_per_sec: number;
+  bytes_in_per_sec: number;
+  bytes_out_per_sec: number;
+  p50_ms: number | null;
+  p95_ms: number | null;
+  p99_ms: number | null;
+  in_flight: number | null;
+  goroutines: number | null;
+  heap_bytes: number | null;
+  websocket_clients: number | null;
+  uptime_secs: number | null;
+  routes: RouteMetrics[];
+}
+
+export interface BackendMetricsReport {
+  current: BackendMetrics | null;
+  // Oldest first, about an hour
+  history: BackendMetrics[];
+}
+
+// A line of sidecar output; 'backend-log' events carry batches of these. JSON log
+// records are split into their message, target and remaining fields.
+export interface BackendLogLine {
+  seq: number;
+  at: number;
+  profile: string;
+  level: BackendLogLevel;
+  stream: 'stdout' | 'stderr';
+  target?: string;
+  message: string;
+  fields?: Record<string, unknown>;
+}
+
+export interface BackendLogFilter {
+  profile?: string;
+  min_level?: BackendLogLevel;
+  contains?: string;
+  // Only lines after this seq, e.g. the last one already shown
+  after_seq?: number;
+}
+
+// Sent as 'backend-log-flood' when a sidecar starts logging faster than the shell
+// keeps up with, and again when it stops. While flooding, most lines are dropped and
+// '[N lines suppressed]' markers take their place.
+export interface BackendLogFlood {
+  profile: string;
+  flooding: boolean;
+  // Lines dropped during the flood, once it has ended
+  suppressed: number;
+}
+
+export type FileCategory = 'image' | 'video' | 'audio' | 'document' | 'archive' | 'other';
+
+// A folder's direct contents at a glance; 'folder-stats' events carry refreshed ones
+export interface FolderStats {
+  bucket: string;
+  prefix: string;
+  files: number;
+  folders: number;
+  total_size: number;
+  newest: number | null;
+  oldest: number | null;
+  by_type: Partial<Record<FileCategory, { count: number; size: number }>>;
+  fetched_at: number;
+  stale: boolean;
+  refreshing: boolean;
+}
+
+export type UrlListFormat = 'plain' | 'markdown' | 'html';
+
+// Payload of the 'copy-urls-progress' event while a large selection's links are built
+export interface CopyUrlsProgress {
+  operation_id: string;
+  done: number;
+  total: number;
+}
+
+export type JobStatus = 'queued' | 'running' | 'paused' | 'completed' | 'failed' | 'cancelled';
+
+// Any long-running work in one shape; 'job-updated' events carry changed jobs
+export interface Job {
+  id: string;
+  // 'import', 'remote_copy', 'url_upload', 'download', 'sync', 'watch', or an operation's kind such as 'export'
+  kind: string;
+  label: string;
+  status: JobStatus;
+  done: number | null;
+  total: number | null;
+  bytes: number | null;
+  detail: string | null;
+  error: string | null;
+  can_pause: boolean;
+  can_cancel: boolean;
+  can_retry: boolean;
+}
+
+// Payload of the 'resume-available' event: work the last session left unfinished,
+// held as paused until resumeAll or discardPending
+export interface ResumeSummary {
+  jobs: Job[];
+  imports: number;
+  remote_copies: number;
+  url_uploads: number;
+  downloads: number;
+  // Already transferred; resuming skips it
+  bytes: number;
+}
+
+// A selection held by the shell, so commands over many objects don't send every key
+export interface SelectionInfo {
+  token: string;
+  bucket: string;
+  count: number;
+}
+
+// Objects for a command: their keys, or a selection built with selectionAdd
+export type Items = string[] | { selection: string };
+
+function itemArgs(items: Items): { keys: string[] | null; selection: string | null } {
+  return Array.isArray(items) ? { keys: items, selection: null } : { keys: null, selection: items.selection };
+}
+
+// Payload of the 'delete-finished' event for a deleteWithUndo
+export interface DeleteOutcome {
+  id: string;
+  bucket: string;
+  undone: boolean;
+  deleted: string[];
+  // [key, reason] for files that are still there
+  failed: [string, string][];
+}
+
+// A destructive action held for an undo window before it reaches the backend
+export interface PendingAction {
+  id: string;
+  kind: 'delete';
+  title: string;
+  body: string;
+  // The undo toast, to dismiss once the action is finished
+  notification_id: string;
+  // ms; committed then unless reverted first
+  expires_at: number;
+}
+
+// Payload of the 'action-finished' event
+export interface ActionFinished {
+  id: string;
+  kind: PendingAction['kind'];
+  notification_id: string;
+  committed: boolean;
+}
+
+// What a URL serves, as far as its headers tell before downloading it
+export interface UrlInfo {
+  size: number | null;
+  content_type: string | null;
+  name: string;
+  // The server supports byte ranges, so an interrupted download resumes
+  resumable: boolean;
+  validator: string | null;
+}
+
+// Where an upload lands; base_url null means the bundled backend
+export interface UploadDestination {
+  base_url?: string | null;
+  bucket: string;
+  prefix?: string;
+}
+
+export type UrlUploadStatus = 'running' | 'completed' | 'failed' | 'cancelled';
+
+// Sent as 'url-upload-progress' when an upload from a URL changes status
+export interface UrlUpload {
+  id: string;
+  url: string;
+  dest: UploadDestination;
+  key: string;
+  info: UrlInfo;
+  status: UrlUploadStatus;
+  transferred: number;
+  error: string | null;
+}
+
+// Sent as 'transfer-progress' while a file from uploadFile or downloadFile moves, and once
+// more when done
+export interface TransferProgress {
+  id: string;
+  bucket: string;
+  key: string;
+  transferred: number;
+  total: number;
+  bytes_per_sec: number;
+  // Null until there is a rate to go by
+  eta_secs: number | null;
+  done: boolean;
+  // Set on the last event of a failed upload; null when cancelled
+  error: string | null;
+}
+
+export type DownloadStatus = 'running' | 'completed' | 'failed' | 'cancelled';
+
+// Sent as 'download-progress' when a download from downloadFile changes status
+export interface FileDownload {
+  id: string;
+  bucket: string;
+  key: string;
+  // Written to as <dest>.<id>.bbstream-part until the whole object has arrived
+  dest: string;
+  // Null until the backend has answered
+  size: number | null;
+  validator: string | null;
+  status: DownloadStatus;
+  transferred: number;
+  error: string | null;
+}
+
+export type TransferDirection = 'upload' | 'download';
+export type TransferPriority = 'high' | 'normal' | 'low';
+export type QueueStatus = 'queued' | 'running' | 'completed' | 'failed' | 'cancelled';
+
+// Sent as 'transfer-queue-updated' when a queued transfer changes status
+export interface QueuedTransfer {
+  // Also the upload's or download's id once it runs
+  id: string;
+  direction: TransferDirection;
+  bucket: string;
+  remote_path: string;
+  // The file uploaded, or where a download goes
+  local_path: string;
+  priority: TransferPriority;
+  status: QueueStatus;
+  // Known up front for uploads, once started for downloads
+  size: number | null;
+  error: string | null;
+  batch: number;
+}
+
+export interface TransferLimits {
+  uploads: number;
+  downloads: number;
+}
+
+// Caps on the app's own transfers; limits are bytes per second, null for unlimited
+export interface BandwidthSettings {
+  upload_limit: number | null;
+  download_limit: number | null;
+  // Apply the caps only between start_minute and end_minute (local time)
+  work_hours_only: boolean;
+  start_minute: number;
+  end_minute: number;
+  weekdays_only: boolean;
+}
+
+export interface BandwidthStatus extends BandwidthSettings {
+  // Whether the caps apply right now
+  in_effect: boolean;
+}
+
+// Sent as 'transfer-verified' when a finished upload or download matches the object
+// in the bucket, and as 'transfer-corrupt' when it doesn't
+export interface TransferVerification {
+  id: string;
+  direction: TransferDirection;
+  bucket: string;
+  key: string;
+  local_path: string;
+  sha1: string;
+  // Null for large files stored without a SHA-1, e.g. by other tools; only their
+  // size is compared
+  expected_sha1: string | null;
+  size: number;
+  expected_size: number;
+  // Times the transfer was already redone because it came out corrupt
+  retries: number;
+  // Id of the queued transfer redoing a corrupt one, until the retry limit
+  requeued_as: string | null;
+}
+
+// Sent as 'transfer-queue-progress' while the queue works through a batch
+export interface QueueProgress {
+  queued: number;
+  running: number;
+  completed: number;
+  failed: number;
+  cancelled: number;
+  transferred: number;
+  // Of the transfers whose size is known so far
+  total: number;
+}
+
+// What a guest window can see: one folder of one bucket, read-only
+export interface GuestScope {
+  profile: string;
+  bucket: string;
+  prefix: string;
+  title: string;
+}
+
+export type SyncCategory = 'preferences' | 'destinations';
+
+// A setting changed on both machines since the last sync; the newer change was kept
+export interface SyncConflict {
+  category: SyncCategory;
+  file: string;
+  key: string | null;
+  kept: 'local' | 'remote';
+}
+
+// Payload of the 'settings-synced' event
+export interface SettingsSyncReport {
+  synced_at: number;
+  uploaded: SyncCategory[];
+  downloaded: SyncCategory[];
+  conflicts: SyncConflict[];
+}
+
+export interface SettingsSyncInfo {
+  enabled: boolean;
+  bucket: string | null;
+  categories: SyncCategory[];
+  has_passphrase: boolean;
+  last_sync: SettingsSyncReport | null;
+}
+
+// Opt-in export of the shell's tracing spans to an OTLP/HTTP collector
+export interface TelemetrySettings {
+  enabled: boolean;
+  // Collector base URL, e.g. http://localhost:4318; spans go to its /v1/traces
+  endpoint: string;
+  headers: Record<string, string>;
+  // Tells this install apart from others; the host name when empty
+  instance: string;
+}
+
+export interface ThumbnailSettings {
+  decoder: 'platform' | 'builtin';
+}
+
+export interface ScratchSettings {
+  // null for the app's cache directory
+  dir: string | null;
+}
+
+export type ScratchArea = 'print' | 'url_uploads';
+
+export interface ScratchAreaUsage {
+  area: ScratchArea;
+  path: string;
+  files: number;
+  bytes: number;
+}
+
+export interface ScratchUsage {
+  root: string;
+  areas: ScratchAreaUsage[];
+  total_bytes: number;
+  // 0 when the volume's free space can't be told
+  available_bytes: number;
+}
+
+export interface AccessStatsSettings {
+  enabled: boolean;
+}
+
+export interface PrefixHeat {
+  profile: string;
+  bucket: string;
+  prefix: string;
+  listings: number;
+  downloads: number;
+  // Access count decayed by age; halves after a week without access
+  score: number;
+  last_access: number;
+}
+
+export interface AccessHeatmap {
+  enabled: boolean;
+  // Hottest first
+  prefixes: PrefixHeat[];
+}
+
+// Why a backend process exited on its own, with its last stderr lines
+export interface BackendCrashReport {
+  profile: string;
+  exit_code: number | null;
+  signal: number | null;
+  uptime_secs: number | null;
+  stderr_tail: string[];
+  crashed_at: number;
+}
+
+export type CrashKind = 'shell' | 'backend';
+
+// A crash kept on disk: a shell panic or a backend exit, with the last status
+// transitions and log lines. Nothing is sent unless the user submits it.
+export interface StoredCrash {
+  id: string;
+  kind: CrashKind;
+  at: number;
+  app_version: string;
+  os: string;
+  arch: string;
+  summary: string;
+  location: string | null;
+  thread: string | null;
+  backtrace: string | null;
+  backend: BackendCrashReport | null;
+  status_changes: StatusChange[];
+  log_tail: string[];
+  submitted_at: number | null;
+}
+
+export interface CrashSummary {
+  id: string;
+  kind: CrashKind;
+  at: number;
+  summary: string;
+  submitted_at: number | null;
+}
+
+export interface CrashReporterSettings {
+  enabled: boolean;
+  endpoint: string;
+  include_logs: boolean;
+}
+
+// Payload of the 'backend-port-fallback' event: the preferred port was taken
+export interface BackendPortFallback {
+  profile: string;
+  preferred_port: number;
+  port: number;
+}
+
+// Payload of the 'backend-restart-progress' event: running sync and watch jobs being
+// carried over a restart
+export interface BackendRestartProgress {
+  profile: string;
+  phase: 'draining' | 'restarting' | 'resumed' | 'failed';
+  jobs: number;
+  error: string | null;
+}
+
+// Payload of the 'auth-required' event: the backend's storage credentials were
+// rejected and transfers wait until the user signs in again
+export interface AuthRequired {
+  profile: string;
+  message: string;
+}
+
 export interface StatusInfo {
   version: string;
   api_version: number;
@@ -113,23 +912,33 @@ export interface StatusInfo {
   active_sync_jobs: number;
   active_watch_jobs: number;
   websocket_clients: number;
+  // Added by the shell when the request goes through it
+  reads?: ReadStatus;
 }
 
 class ApiClient {
-  private async getBaseUrl(): Promise<string> {
-    return getApiBase();
-  }
-
   private async request<T>(
     endpoint: string,
     options: RequestInit = {}
   ): Promise<T> {
-    const baseUrl = await this.getBaseUrl();
+    // In the app the shell sends the request, adding auth and retrying transient
+    // failures; errors come back as the backend's message
+    if (isTauri()) {
+      return invoke<T>('backend_request', {
+        method: options.method ?? 'GET',
+        path: endpoint,
+        body: typeof options.body === 'string' ? JSON.parse(options.body) : undefined,
+      });
+    }
+
+    const baseUrl = getApiBase();
     const url = `${baseUrl}${endpoint}`;
-    const response = await safeFetch(url, {
+    const token = await getSessionToken();
+    const response = await fetch(url, {
       ...options,
       headers: {
         'Content-Type': 'application/json',
+        Authorization: `Bearer ${token}`,
         ...options.headers,
       },
     });
@@ -145,8 +954,16 @@ class ApiClient {
   // Health check
   async health(): Promise<boolean> {
     try {
-      const baseUrl = await this.getBaseUrl();
-      const response = await safeFetch(`${baseUrl.replace('/api', '')}/health`);
+      await getSessionToken();
+      if (viaShell()) {
+        await apiRequest('GET', '/health');
+        return true;
+      }
+      const baseUrl = getApiBase();
+      const token = await getSessionToken();
+      const response = await fetch(`${baseUrl.replace('/api', '')}/health`, {
+        headers: { Authorization: `Bearer ${token}` },
+      });
       return response.ok;
     } catch {
       return false;
@@ -158,6 +975,461 @@ class ApiClient {
     return this.request<VersionInfo>('/version');
   }
 
+  // Optional operations the backend supports; null until the shell has reached it.
+  // Outside the desktop shell the backend is asked directly.
+  async getCapabilities(): Promise<Capabilities | null> {
+    try {
+      return await invoke<Capabilities | null>('get_backend_capabilities');
+    } catch {
+      const info = await this.request<{ capabilities: Capabilities }>('/capabilities');
+      return info.capabilities;
+    }
+  }
+
+  // Configuration changes waiting for the sidecars to restart
+  async getPendingSidecarChanges(): Promise<PendingSidecarChanges> {
+    return invoke<PendingSidecarChanges>('get_pending_sidecar_changes');
+  }
+
+  // Restart the sidecars with pending changes once idle, or call that off
+  async scheduleSidecarRestart(scheduled: boolean): Promise<PendingSidecarChanges> {
+    return invoke<PendingSidecarChanges>('schedule_sidecar_restart', { scheduled });
+  }
+
+  async getLogLevel(): Promise<LogLevels> {
+    return invoke<LogLevels>('get_log_level');
+  }
+
+  // Log at `level` in the shell and every sidecar until the app quits, e.g. while
+  // reproducing an issue; null goes back to the default and configured levels
+  async setLogLevel(level: LogLevel | null): Promise<LogLevels> {
+    return invoke<LogLevels>('set_log_level', { level });
+  }
+
+  // Queue thumbnails for the visible objects of a folder, identified by key; each
+  // arrives as a 'thumbnail-ready' event with the cached file's path. Returns how
+  // many were queued.
+  async warmThumbnails(bucket: string, ids: string[]): Promise<number> {
+    return invoke<number>('warm_thumbnails', { bucket, ids });
+  }
+
+  // Path of an already generated thumbnail, or null
+  async getThumbnail(bucket: string, key: string): Promise<string | null> {
+    return invoke<string | null>('get_thumbnail', { bucket, key });
+  }
+
+  // The active profile's last backend crash, also sent as a 'backend-crash' event
+  async getBackendCrashReport(): Promise<BackendCrashReport | null> {
+    return invoke<BackendCrashReport | null>('get_backend_crash_report');
+  }
+
+  async listCrashReports(): Promise<CrashSummary[]> {
+    return invoke<CrashSummary[]>('list_crash_reports');
+  }
+
+  async getCrashReport(id: string): Promise<StoredCrash> {
+    return invoke<StoredCrash>('get_crash_report', { id });
+  }
+
+  async deleteCrashReport(id: string): Promise<void> {
+    return invoke<void>('delete_crash_report', { id });
+  }
+
+  // Sends the report only with reporting enabled and `consent` given for it
+  async submitCrashReport(id: string, consent: boolean, note?: string): Promise<StoredCrash> {
+    return invoke<StoredCrash>('submit_crash_report', { id, consent, note: note ?? null });
+  }
+
+  async getCrashReporterSettings(): Promise<CrashReporterSettings> {
+    return invoke<CrashReporterSettings>('get_crash_reporter_settings');
+  }
+
+  async configureCrashReporter(changes: Partial<CrashReporterSettings>): Promise<CrashReporterSettings> {
+    return invoke<CrashReporterSettings>('configure_crash_reporter', {
+      enabled: changes.enabled ?? null,
+      endpoint: changes.endpoint ?? null,
+      includeLogs: changes.include_logs ?? null,
+    });
+  }
+
+  // Zip logs, status history, redacted settings and version and port info for a bug
+  // report, into `dest` (the downloads folder by default), and show it. Returns its path.
+  async exportDiagnostics(dest?: string): Promise<string> {
+    return invoke<string>('export_diagnostics', { dest: dest ?? null });
+  }
+
+  // Versions, platform, folders and port of this install, for an About panel or a
+  // bug report
+  async getAppInfo(): Promise<AppInfo> {
+    return invoke<AppInfo>('get_app_info');
+  }
+
+  async getActiveTransfers(): Promise<ActiveTransfers> {
+    return invoke<ActiveTransfers>('get_active_transfers');
+  }
+
+  // Whether thumbnails are decoded only by the backend ('builtin', the default) or
+  // by the OS in process ('platform', falling back to the backend). Choosing
+  // 'platform' fails as unsupported where the OS has no imaging stack to use.
+  async getThumbnailSettings(): Promise<ThumbnailSettings> {
+    return invoke<ThumbnailSettings>('get_thumbnail_settings');
+  }
+
+  async setThumbnailSettings(settings: ThumbnailSettings): Promise<ThumbnailSettings> {
+    return invoke<ThumbnailSettings>('set_thumbnail_settings', { settings });
+  }
+
+  async getScratchSettings(): Promise<ScratchSettings> {
+    return invoke<ScratchSettings>('get_scratch_settings');
+  }
+
+  // Refused while transfers run; unfinished URL uploads' downloads move along and
+  // everything else in the old location is removed
+  async setScratchSettings(settings: ScratchSettings): Promise<ScratchSettings> {
+    return invoke<ScratchSettings>('set_scratch_settings', { settings });
+  }
+
+  async getScratchUsage(): Promise<ScratchUsage> {
+    return invoke<ScratchUsage>('get_scratch_usage');
+  }
+
+  // The active profile's most browsed and downloaded folders
+  async getAccessHeatmap(limit?: number): Promise<AccessHeatmap> {
+    return invoke<AccessHeatmap>('get_access_heatmap', { limit: limit ?? null });
+  }
+
+  async getAccessStatsSettings(): Promise<AccessStatsSettings> {
+    return invoke<AccessStatsSettings>('get_access_stats_settings');
+  }
+
+  // Disabling forgets the collected stats
+  async setAccessStatsSettings(settings: AccessStatsSettings): Promise<AccessStatsSettings> {
+    return invoke<AccessStatsSettings>('set_access_stats_settings', { settings });
+  }
+
+  // How a folder ("bucket/prefix") is shown: its own preferences, else its nearest
+  // parent's, else null for the defaults
+  async getViewPrefs(path: string): Promise<ViewPrefs | null> {
+    return invoke<ViewPrefs | null>('get_view_prefs', { path });
+  }
+
+  // Remember how a folder is shown; null forgets it
+  async setViewPrefs(path: string, prefs: ViewPrefs | null): Promise<void> {
+    return invoke<void>('set_view_prefs', { path, prefs });
+  }
+
+  // Upload destination suggestions, learned from where earlier uploads went
+  async suggestDestination(files: string[]): Promise<DestinationSuggestion[]> {
+    return invoke<DestinationSuggestion[]>('suggest_destination', { files });
+  }
+
+  // Permission decisions, for all profiles or one
+  async listPermissions(profile?: string): Promise<PermissionGrant[]> {
+    return invoke<PermissionGrant[]>('list_permissions', { profile: profile ?? null });
+  }
+
+  // Forget a decision so the user is asked again
+  async revokePermission(profile: string, capability: Capability): Promise<void> {
+    return invoke<void>('revoke_permission', { profile, capability });
+  }
+
+  // Let uploads made from the webview count towards suggestions
+  async recordUploadDestination(files: string[], bucket: string, prefix: string): Promise<void> {
+    return invoke<void>('record_upload_destination', { files, bucket, prefix });
+  }
+
+  // Copy links to every selected object (folders included) to the clipboard in one go;
+  // resolves to how many were copied
+  async copyUrls(
+    bucket: string,
+    items: Items,
+    format: UrlListFormat = 'plain',
+    operationId?: string
+  ): Promise<number> {
+    return invoke<number>('copy_urls', { bucket, ...itemArgs(items), format, operationId: operationId ?? null });
+  }
+
+  // Add keys to a selection, starting a new one when no token is given; add large
+  // selections in batches
+  async selectionAdd(bucket: string, keys: string[], token?: string): Promise<SelectionInfo> {
+    return invoke<SelectionInfo>('selection_add', { token: token ?? null, bucket, keys });
+  }
+
+  // Add everything in a folder's last listing ("select all")
+  async selectionAddFolder(bucket: string, prefix: string, token?: string): Promise<SelectionInfo> {
+    return invoke<SelectionInfo>('selection_add_folder', { token: token ?? null, bucket, prefix });
+  }
+
+  async selectionRemove(token: string, keys: string[]): Promise<SelectionInfo> {
+    return invoke<SelectionInfo>('selection_remove', { token, keys });
+  }
+
+  // Empty a selection; `release` forgets it once it's no longer needed
+  async selectionClear(token: string, release = false): Promise<void> {
+    return invoke<void>('selection_clear', { token, release });
+  }
+
+  async selectionKeys(token: string, offset?: number, limit?: number): Promise<string[]> {
+    return invoke<string[]>('selection_keys', { token, offset: offset ?? null, limit: limit ?? null });
+  }
+
+  // Stats for a folder given as "bucket/path", from cached listings where possible
+  async getFolderStats(path: string): Promise<FolderStats> {
+    return invoke<FolderStats>('get_folder_stats', { path });
+  }
+
+  // Recent sidecar output, oldest first; 'backend-log' events carry what follows
+  async getBackendLogs(filter?: BackendLogFilter, limit?: number): Promise<BackendLogLine[]> {
+    return invoke<BackendLogLine[]>('get_backend_logs', { filter: filter ?? null, limit: limit ?? null });
+  }
+
+  // Copy the lines with these seqs to the clipboard; resolves to how many were copied
+  async copyBackendLogs(seqs: number[]): Promise<number> {
+    return invoke<number>('copy_backend_logs', { seqs });
+  }
+
+  // Open the backend log window, or bring it to the front
+  async showBackendLogs(): Promise<void> {
+    return invoke<void>('show_backend_logs');
+  }
+
+  // Show the folder with the shell and sidecar log files, to attach to bug reports
+  async openLogDirectory(): Promise<void> {
+    return invoke<void>('open_log_directory');
+  }
+
+  async getSafeMode(): Promise<SafeModeStatus> {
+    return invoke<SafeModeStatus>('get_safe_mode');
+  }
+
+  // Forget the failed launches and restart the app normally
+  async leaveSafeMode(): Promise<void> {
+    return invoke<void>('leave_safe_mode');
+  }
+
+  // Request rates and latencies of the active backend; with `history`, also the
+  // earlier aggregates for a dashboard
+  async getBackendMetrics(history?: boolean): Promise<BackendMetricsReport> {
+    return invoke<BackendMetricsReport>('get_backend_metrics', { history: history ?? null });
+  }
+
+  async getBackendStatus(): Promise<BackendStatusInfo> {
+    return invoke<BackendStatusInfo>('get_backend_status');
+  }
+
+  // Listen to a state-bearing event such as 'backend-status', first handing `handler`
+  // the latest ones emitted before this window was listening
+  async subscribeWithReplay<T>(topic: string, handler: (payload: T) => void): Promise<UnlistenFn> {
+    const unlisten = await listen<T>(topic, (event) => handler(event.payload));
+    try {
+      const missed = await invoke<ReplayedEvent<T>[]>('subscribe_with_replay', { topic });
+      missed.forEach((event) => handler(event.payload));
+    } catch (e) {
+      console.warn(`No replay of ${topic}:`, e);
+    }
+    return unlisten;
+  }
+
+  // Resolves once the active backend is healthy; rejects with a CommandError of kind
+  // 'timeout' after `timeoutMs`
+  async waitForHealthy(timeoutMs: number): Promise<void> {
+    return invoke<void>('wait_for_healthy', { timeoutMs });
+  }
+
+  // Backend status transitions since `since` (ms, default a week ago), for an uptime
+  // timeline; defaults to the active profile
+  async getStatusHistory(profile?: string, since?: number): Promise<StatusHistory> {
+    return invoke<StatusHistory>('get_status_history', { profile: profile ?? null, since: since ?? null });
+  }
+
+  async getReadStatus(): Promise<ReadStatus> {
+    return invoke<ReadStatus>('get_read_status');
+  }
+
+  // Defaults to the active profile
+  async getHealthLatency(profile?: string): Promise<LatencyStatus> {
+    return invoke<LatencyStatus>('get_health_latency', { profile: profile ?? null });
+  }
+
+  async getTelemetrySettings(): Promise<TelemetrySettings> {
+    return invoke<TelemetrySettings>('get_telemetry_settings');
+  }
+
+  // Saves the given fields and starts or stops exporting right away
+  async configureTelemetry(changes: Partial<TelemetrySettings>): Promise<TelemetrySettings> {
+    return invoke<TelemetrySettings>('configure_telemetry', {
+      enabled: changes.enabled ?? null,
+      endpoint: changes.endpoint ?? null,
+      headers: changes.headers ?? null,
+      instance: changes.instance ?? null,
+    });
+  }
+
+  // Check a profile's credentials again after signing in; transfers resume if they work
+  async retryAuth(profile?: string): Promise<void> {
+    return invoke<void>('retry_auth', { profile: profile ?? null });
+  }
+
+  // Imports, remote copies, backend syncs and watches, and other running operations
+  async listJobs(): Promise<Job[]> {
+    return invoke<Job[]>('list_jobs');
+  }
+
+  async pauseJob(id: string): Promise<void> {
+    return invoke<void>('pause_job', { id });
+  }
+
+  async cancelJob(id: string): Promise<void> {
+    return invoke<void>('cancel_job', { id });
+  }
+
+  // Resume a paused job, or run a failed or cancelled one again
+  async retryJob(id: string): Promise<void> {
+    return invoke<void>('retry_job', { id });
+  }
+
+  // Interrupted work still waiting for a choice, for a UI that missed 'resume-available'
+  async getPendingResume(): Promise<ResumeSummary | null> {
+    return invoke<ResumeSummary | null>('get_pending_resume');
+  }
+
+  async resumeAll(): Promise<void> {
+    return invoke<void>('resume_all');
+  }
+
+  // Leave the interrupted jobs cancelled; each can still be retried later
+  async discardPending(): Promise<void> {
+    return invoke<void>('discard_pending');
+  }
+
+  // Size, type and name of what a URL serves, to confirm before uploading it
+  async preflightUrl(url: string): Promise<UrlInfo> {
+    return invoke<UrlInfo>('preflight_url', { url });
+  }
+
+  // Download a URL straight into a bucket folder; progress comes as transfer events
+  async uploadFromUrl(url: string, dest: UploadDestination, name?: string): Promise<UrlUpload> {
+    return invoke<UrlUpload>('upload_from_url', { url, dest, name: name ?? null });
+  }
+
+  async listUrlUploads(): Promise<UrlUpload[]> {
+    return invoke<UrlUpload[]>('list_url_uploads');
+  }
+
+  async cancelUrlUpload(id: string): Promise<void> {
+    return invoke<void>('cancel_url_upload', { id });
+  }
+
+  // Run a failed or cancelled upload again, continuing a partial download if possible
+  async resumeUrlUpload(id: string): Promise<void> {
+    return invoke<void>('resume_url_upload', { id });
+  }
+
+  // Upload a file from disk without reading it into the webview; resolves with the
+  // upload's id once it has started. Cancel it with cancelJob.
+  async uploadLocalFile(localPath: string, bucket: string, remotePath: string): Promise<string> {
+    return invoke<string>('upload_file', { localPath, bucket, remotePath });
+  }
+
+  // Download bucket/remotePath to dest on disk; dest only appears once complete
+  async downloadFile(bucket: string, remotePath: string, dest: string): Promise<FileDownload> {
+    return invoke<FileDownload>('download_file', { bucket, remotePath, dest });
+  }
+
+  async listDownloads(): Promise<FileDownload[]> {
+    return invoke<FileDownload[]>('list_downloads');
+  }
+
+  async pauseTransfer(id: string): Promise<void> {
+    return invoke<void>('pause_transfer', { id });
+  }
+
+  // Continue a paused, cancelled or failed download where it stopped
+  async resumeTransfer(id: string): Promise<void> {
+    return invoke<void>('resume_transfer', { id });
+  }
+
+  // Queue an upload of localPath to bucket/remotePath, or a download the other way;
+  // it starts once fewer than the limit of its direction are running
+  async enqueueTransfer(
+    direction: TransferDirection,
+    bucket: string,
+    remotePath: string,
+    localPath: string,
+    priority?: TransferPriority
+  ): Promise<QueuedTransfer> {
+    return invoke<QueuedTransfer>('enqueue_transfer', {
+      direction,
+      bucket,
+      remotePath,
+      localPath,
+      priority: priority ?? null,
+    });
+  }
+
+  async listTransfers(): Promise<QueuedTransfer[]> {
+    return invoke<QueuedTransfer[]>('list_transfers');
+  }
+
+  async cancelTransfer(id: string): Promise<void> {
+    return invoke<void>('cancel_transfer', { id });
+  }
+
+  // Move a transfer to position in the queue; earlier ones of a priority start first
+  async reorderTransfer(id: string, position: number): Promise<void> {
+    return invoke<void>('reorder_transfer', { id, position });
+  }
+
+  async getTransferLimits(): Promise<TransferLimits> {
+    return invoke<TransferLimits>('get_transfer_limits');
+  }
+
+  async setTransferLimits(limits: TransferLimits): Promise<void> {
+    return invoke<void>('set_transfer_limits', { limits });
+  }
+
+  async getBandwidthLimit(): Promise<BandwidthStatus> {
+    return invoke<BandwidthStatus>('get_bandwidth_limit');
+  }
+
+  async setBandwidthLimit(limits: BandwidthSettings): Promise<BandwidthStatus> {
+    return invoke<BandwidthStatus>('set_bandwidth_limit', { limits });
+  }
+
+  // Cap one running transfer on top of the global caps; null lifts its own cap
+  async setTransferBandwidthLimit(id: string, limit: number | null): Promise<void> {
+    return invoke<void>('set_transfer_bandwidth_limit', { id, limit });
+  }
+
+  // Open a read-only window onto bucket/prefix for someone else to browse; only the
+  // shell's guest commands work there. Returns the window label.
+  async openGuestWindow(bucket: string, prefix?: string, title?: string): Promise<string> {
+    return invoke<string>('open_guest_window', { bucket, prefix: prefix ?? null, title: title ?? null });
+  }
+
+  async getSettingsSync(): Promise<SettingsSyncInfo> {
+    return invoke<SettingsSyncInfo>('get_settings_sync');
+  }
+
+  // Sync settings through an encrypted file in the bucket; leave the passphrase out to keep it
+  async setSettingsSync(
+    enabled: boolean,
+    bucket: string | null,
+    categories: SyncCategory[],
+    passphrase?: string
+  ): Promise<SettingsSyncInfo> {
+    return invoke<SettingsSyncInfo>('set_settings_sync', {
+      enabled,
+      bucket,
+      categories,
+      passphrase: passphrase ?? null,
+    });
+  }
+
+  async syncSettingsNow(): Promise<SettingsSyncReport> {
+    return invoke<SettingsSyncReport>('sync_settings_now');
+  }
+
   // Status
   async getStatus(): Promise<StatusInfo> {
     return this.request<StatusInfo>('/status');
@@ -202,6 +1474,13 @@ class ApiClient {
     file: File,
     onProgress?: (percent: number) => void
   ): Promise<UploadResult> {
+    await getSessionToken();
+    if (bodiesViaShell()) {
+      const result = await uploadViaShell(bucket, path, file);
+      onProgress?.(100);
+      return result;
+    }
+
     const formData = new FormData();
     formData.append('file', file);
 
@@ -226,14 +1505,19 @@ class ApiClient {
         reject(new Error('Upload failed'));
       });
 
-      xhr.open('POST', `${getApiBaseSync()}/upload?bucket=${bucket}&path=${path}`);
+      xhr.open('POST', `${getApiBase()}/upload?bucket=${encodeURIComponent(bucket)}&path=${encodeURIComponent(path)}`);
+      xhr.setRequestHeader('Authorization', `Bearer ${getSessionTokenSync()}`);
       xhr.send(formData);
     });
   }
 
-  // Download URL
-  getDownloadUrl(bucket: string, path: string): string {
-    return `${getApiBaseSync()}/download/${bucket}/${path}`;
+  // A link to share or open outside the app. The shell adds a token that opens only
+  // this object, for a day; the dev server has no tokens.
+  async getDownloadUrl(bucket: string, path: string): Promise<string> {
+    if (isTauri()) {
+      return invoke<string>('get_download_link', { bucket, key: path });
+    }
+    return `${getApiBase()}/download/${bucket}/${path}`;
   }
 
   // Download with progress tracking
@@ -242,6 +1526,18 @@ class ApiClient {
     path: string,
     onProgress?: (percent: number, loaded: number, total: number) => void
   ): { promise: Promise<Blob>; cancel: () => void } {
+    if (bodiesViaShell()) {
+      const encodedPath = path.split('/').map(encodeURIComponent).join('/');
+      const promise = apiRequest('GET', `/api/download/${encodeURIComponent(bucket)}/${encodedPath}`).then(
+        (body) => {
+          onProgress?.(100, body.byteLength, body.byteLength);
+          return new Blob([body]);
+        }
+      );
+      // The shell finishes the request either way
+      return { promise, cancel: () => {} };
+    }
+
     const xhr = new XMLHttpRequest();
     let cancelled = false;
 
@@ -274,7 +1570,8 @@ class ApiClient {
         reject(new Error('Download cancelled'));
       });
 
-      xhr.open('GET', this.getDownloadUrl(bucket, path));
+      xhr.open('GET', `${getApiBase()}/download/${bucket}/${path}`);
+      xhr.setRequestHeader('Authorization', `Bearer ${getSessionTokenSync()}`);
       xhr.send();
     });
 
@@ -288,11 +1585,15 @@ class ApiClient {
 
   // Delete - use XMLHttpRequest to ensure DELETE method works
   async deleteFile(bucket: string, path: string): Promise<void> {
+    // Encode each path segment separately to handle special characters
+    const encodedPath = path.split('/').map(encodeURIComponent).join('/');
+    if (viaShell()) {
+      await apiRequest('DELETE', `/api/delete/${encodeURIComponent(bucket)}/${encodedPath}`);
+      return;
+    }
     return new Promise((resolve, reject) => {
       const xhr = new XMLHttpRequest();
-      // Encode each path segment separately to handle special characters
-      const encodedPath = path.split('/').map(encodeURIComponent).join('/');
-      const url = `${getApiBaseSync()}/delete/${encodeURIComponent(bucket)}/${encodedPath}`;
+      const url = `${getApiBase()}/delete/${encodeURIComponent(bucket)}/${encodedPath}`;
 
       xhr.addEventListener('load', () => {
         if (xhr.status >= 200 && xhr.status < 300) {
@@ -312,11 +1613,42 @@ class ApiClient {
       });
 
       xhr.open('DELETE', url);
+      xhr.setRequestHeader('Authorization', `Bearer ${getSessionTokenSync()}`);
       xhr.setRequestHeader('Content-Type', 'application/json');
       xhr.send();
     });
   }
 
+  // Delete after a short delay, with a notification that can undo it until then.
+  // Returns an id for undoDelete; 'delete-finished' reports the outcome.
+  async deleteWithUndo(bucket: string, items: Items): Promise<string> {
+    return invoke<string>('delete_with_undo', { bucket, ...itemArgs(items) });
+  }
+
+  async undoDelete(id: string): Promise<void> {
+    return invoke<void>('undo_delete', { id });
+  }
+
+  // Actions waiting out their undo window, soonest first
+  async listPendingActions(): Promise<PendingAction[]> {
+    return invoke<PendingAction[]>('list_pending_actions');
+  }
+
+  // Carry out a held action now rather than when its undo window ends
+  async commitAction(id: string): Promise<void> {
+    return invoke<void>('commit_action', { id });
+  }
+
+  // Take back a held action; 'action-finished' reports it
+  async revertAction(id: string): Promise<void> {
+    return invoke<void>('revert_action', { id });
+  }
+
+  // Offer to open a saved download or show it in the file manager
+  async notifyDownload(path: string): Promise<void> {
+    return invoke<void>('notify_download', { path });
+  }
+
   // Upload with cancel support
   uploadFileWithCancel(
     bucket: string,
@@ -324,6 +1656,15 @@ class ApiClient {
     file: File,
     onProgress?: (percent: number) => void
   ): { promise: Promise<UploadResult>; cancel: () => void } {
+    if (bodiesViaShell()) {
+      const promise = uploadViaShell(bucket, path, file).then((result) => {
+        onProgress?.(100);
+        return result;
+      });
+      // The shell finishes the request either way
+      return { promise, cancel: () => {} };
+    }
+
     const xhr = new XMLHttpRequest();
     let cancelled = false;
 
@@ -356,7 +1697,8 @@ class ApiClient {
 
       const formData = new FormData();
       formData.append('file', file);
-      xhr.open('POST', `${getApiBaseSync()}/upload?bucket=${bucket}&path=${path}`);
+      xhr.open('POST', `${getApiBase()}/upload?bucket=${encodeURIComponent(bucket)}&path=${encodeURIComponent(path)}`);
+      xhr.setRequestHeader('Authorization', `Bearer ${getSessionTokenSync()}`);
       xhr.send(formData);
     });
 
@@ -417,7 +1759,7 @@ class ApiClient {
   }
 
   // Jobs
-  async listJobs(): Promise<Job[]> {
+  async listBackendJobs(): Promise<BackendJob[]> {
     return this.request('/jobs');
   }
 
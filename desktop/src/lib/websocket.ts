@@ -1,5 +1,7 @@
 // WebSocket client for real-time events
-import { invoke } from '@tauri-apps/api/core';
+import { invoke, isTauri } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import { getApiOrigin, getSessionToken, onSocket } from './api';
 
 export interface WebSocketEvent {
   type: string;
@@ -7,28 +9,47 @@ export interface WebSocketEvent {
   timestamp: string;
 }
 
+// Sent by the shell as 'stream:status' when its connection to the backend comes or goes
+export interface StreamStatus {
+  profile: string;
+  connected: boolean;
+}
+
 type EventHandler = (event: WebSocketEvent) => void;
 
+// Events the shell re-emits to every window from its one connection to the backend;
+// the payload keeps the backend's own type
+const STREAM_EVENTS = ['stream:progress', 'stream:completed', 'stream:event'];
+
 class WebSocketClient {
   private ws: WebSocket | null = null;
-  private port: number = 8765;
+  // Backend origin from the shell's credentials; null outside the shell during development
+  private origin: string | null = null;
   private handlers: Map<string, Set<EventHandler>> = new Map();
   private reconnectAttempts = 0;
   private maxReconnectAttempts = 5;
   private reconnectDelay = 1000;
   private connected = false;
+  private token = '';
+  // Listeners for the shell's events, once subscribed
+  private bridged: UnlistenFn[] | null = null;
 
+  // Browsers can't set headers on WebSocket connections, so the token goes in the query
   private getUrl(): string {
-    return `ws://localhost:${this.port}/api/ws`;
+    const origin = this.origin?.replace(/^http/, 'ws') ?? 'ws://localhost:8765';
+    return `${origin}/api/ws?token=${encodeURIComponent(this.token)}`;
   }
 
   async connect(): Promise<void> {
-    // Get the dynamic port from Tauri
-    try {
-      this.port = await invoke<number>('get_api_port');
-    } catch {
-      // Fallback for development
-      this.port = 8765;
+    // The shell keeps the connection, over the backend's socket too, and reconnects it
+    if (isTauri()) {
+      return this.bridge();
+    }
+    this.token = await getSessionToken();
+    this.origin = getApiOrigin();
+    // A backend on a Unix socket has no address the webview can open a WebSocket to
+    if (onSocket()) {
+      throw new Error('Live events are unavailable while the backend uses a socket');
     }
 
     return new Promise((resolve, reject) => {
@@ -44,9 +65,7 @@ class WebSocketClient {
 
         this.ws.onmessage = (event) => {
           try {
-            const data: WebSocketEvent = JSON.parse(event.data);
-            this.emit(data.type, data);
-            this.emit('*', data); // Wildcard handler
+            this.dispatch(JSON.parse(event.data));
           } catch (err) {
             console.error('Failed to parse WebSocket message:', err);
           }
@@ -70,6 +89,21 @@ class WebSocketClient {
     });
   }
 
+  private async bridge(): Promise<void> {
+    if (this.bridged) {
+      return;
+    }
+    this.bridged = await Promise.all([
+      ...STREAM_EVENTS.map((name) =>
+        listen<WebSocketEvent>(name, ({ payload }) => this.dispatch(payload))
+      ),
+      listen<StreamStatus>('stream:status', ({ payload }) => {
+        this.connected = payload.connected;
+      }),
+    ]);
+    this.connected = (await invoke<StreamStatus>('get_event_stream_status')).connected;
+  }
+
   private attemptReconnect() {
     if (this.reconnectAttempts >= this.maxReconnectAttempts) {
       console.log('Max reconnect attempts reached');
@@ -89,6 +123,8 @@ class WebSocketClient {
   }
 
   disconnect() {
+    this.bridged?.forEach((unlisten) => unlisten());
+    this.bridged = null;
     if (this.ws) {
       this.ws.close();
       this.ws = null;
@@ -111,6 +147,11 @@ class WebSocketClient {
     this.handlers.get(eventType)?.delete(handler);
   }
 
+  private dispatch(event: WebSocketEvent) {
+    this.emit(event.type, event);
+    this.emit('*', event); // Wildcard handler
+  }
+
   private emit(eventType: string, event: WebSocketEvent) {
     const handlers = this.handlers.get(eventType);
     if (handlers) {
@@ -131,6 +172,9 @@ class WebSocketClient {
   }
 
   isConnected(): boolean {
+    if (this.bridged) {
+      return this.connected;
+    }
     return this.connected && this.ws?.readyState === WebSocket.OPEN;
   }
 }
@@ -0,0 +1,63 @@
+package api
+
+import (
+	"encoding/json"
+	"net/http"
+	"strings"
+
+	"github.com/ryanoboyle/bb-stream/pkg/logging"
+)
+
+// RuntimeConfig is the configuration the desktop shell can change on a running
+// server. Fields left out of a change keep their value; anything else needs a
+// restart with new settings.
+type RuntimeConfig struct {
+	LogLevel string `json:"log_level,omitempty"`
+}
+
+var logLevels = []string{"debug", "info", "warn", "error"}
+
+func currentRuntimeConfig() RuntimeConfig {
+	return RuntimeConfig{
+		LogLevel: logging.LevelName(logging.Level()),
+	}
+}
+
+// handleGetRuntimeConfig returns the configuration in effect
+func (s *Server) handleGetRuntimeConfig(w http.ResponseWriter, r *http.Request) {
+	if !s.fromShell(w, r) {
+		return
+	}
+	respondJSON(w, http.StatusOK, currentRuntimeConfig())
+}
+
+// handleSetRuntimeConfig applies a change to the running server. Unknown fields
+// are refused, so the shell knows to restart instead.
+func (s *Server) handleSetRuntimeConfig(w http.ResponseWriter, r *http.Request) {
+	if !s.fromShell(w, r) {
+		return
+	}
+	var req RuntimeConfig
+	decoder := json.NewDecoder(r.Body)
+	decoder.DisallowUnknownFields()
+	if err := decoder.Decode(&req); err != nil {
+		respondError(w, http.StatusBadRequest, "Invalid request body: "+err.Error())
+		return
+	}
+
+	if req.LogLevel != "" {
+		name := strings.ToLower(strings.TrimSpace(req.LogLevel))
+		valid := false
+		for _, level := range logLevels {
+			valid = valid || name == level
+		}
+		if !valid {
+			respondError(w, http.StatusBadRequest, "Log level must be one of "+strings.Join(logLevels, ", "))
+			return
+		}
+		logging.SetLevel(logging.ParseLevel(name))
+		logging.Logger().Info("log level changed", "level", name)
+	}
+
+	respondJSON(w, http.StatusOK, currentRuntimeConfig())
+}
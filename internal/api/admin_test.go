@@ -0,0 +1,68 @@
+package api
+
+import (
+	"bytes"
+	"encoding/json"
+	"log/slog"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+
+	"github.com/ryanoboyle/bb-stream/pkg/logging"
+)
+
+func TestHandleSetRuntimeConfig_ChangesLogLevel(t *testing.T) {
+	original := logging.Level()
+	defer logging.SetLevel(original)
+
+	server := &Server{}
+	rr := httptest.NewRecorder()
+	body := bytes.NewBufferString(`{"log_level": "debug"}`)
+	server.handleSetRuntimeConfig(rr, httptest.NewRequest("POST", "/api/admin/config", body))
+
+	if rr.Code != http.StatusOK {
+		t.Fatalf("Expected status %d, got %d: %s", http.StatusOK, rr.Code, rr.Body.String())
+	}
+	var config RuntimeConfig
+	if err := json.Unmarshal(rr.Body.Bytes(), &config); err != nil {
+		t.Fatalf("Failed to parse response: %v", err)
+	}
+	if config.LogLevel != "debug" {
+		t.Errorf("Expected log level debug, got %q", config.LogLevel)
+	}
+	if logging.Level() != slog.LevelDebug {
+		t.Errorf("Expected the logger to be at debug, got %v", logging.Level())
+	}
+}
+
+func TestHandleSetRuntimeConfig_RefusesWhatNeedsRestart(t *testing.T) {
+	original := logging.Level()
+	defer logging.SetLevel(original)
+
+	server := &Server{}
+	tests := []string{
+		`{"log_level": "verbose"}`,
+		`{"data_dir": "/tmp/elsewhere"}`,
+		`not json`,
+	}
+	for _, body := range tests {
+		rr := httptest.NewRecorder()
+		server.handleSetRuntimeConfig(rr, httptest.NewRequest("POST", "/api/admin/config", bytes.NewBufferString(body)))
+		if rr.Code != http.StatusBadRequest {
+			t.Errorf("Expected status %d for %s, got %d", http.StatusBadRequest, body, rr.Code)
+		}
+	}
+	if logging.Level() != original {
+		t.Errorf("Expected the log level to stay %v, got %v", original, logging.Level())
+	}
+}
+
+func TestHandleGetRuntimeConfig_OnlyForShell(t *testing.T) {
+	server := &Server{session: &SessionAuth{}}
+	rr := httptest.NewRecorder()
+	server.handleGetRuntimeConfig(rr, httptest.NewRequest("GET", "/api/admin/config", nil))
+
+	if rr.Code != http.StatusForbidden {
+		t.Errorf("Expected status %d without a shell session, got %d", http.StatusForbidden, rr.Code)
+	}
+}
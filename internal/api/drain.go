@@ -0,0 +1,139 @@
+package api
+
+import (
+	"encoding/json"
+	"net/http"
+	"time"
+
+	"github.com/ryanoboyle/bb-stream/pkg/logging"
+)
+
+// SyncCheckpoint is a sync job that was running when the server drained
+type SyncCheckpoint struct {
+	JobID   string      `json:"job_id"`
+	Request SyncRequest `json:"request"`
+}
+
+// WatchCheckpoint is a watch job that was running when the server drained
+type WatchCheckpoint struct {
+	JobID   string       `json:"job_id"`
+	Request WatchRequest `json:"request"`
+}
+
+// Checkpoint is what a drained server hands back so a new process can pick up
+// its work. Syncs start over, skipping what they already transferred; watches
+// start watching again.
+type Checkpoint struct {
+	DrainedAt time.Time         `json:"drained_at"`
+	Syncs     []SyncCheckpoint  `json:"syncs"`
+	Watches   []WatchCheckpoint `json:"watches"`
+}
+
+// ResumedJob maps a checkpointed job to the job that replaced it
+type ResumedJob struct {
+	PreviousID string `json:"previous_id"`
+	JobID      string `json:"job_id,omitempty"`
+	Error      string `json:"error,omitempty"`
+}
+
+// handleDrain stops new jobs from starting, stops watchers and returns a
+// checkpoint of the running jobs. The desktop shell calls it before restarting
+// the process and passes the checkpoint to /api/resume on the new one.
+func (s *Server) handleDrain(w http.ResponseWriter, r *http.Request) {
+	if !s.fromShell(w, r) {
+		return
+	}
+	s.draining.Store(true)
+	checkpoint := Checkpoint{
+		DrainedAt: time.Now(),
+		Syncs:     []SyncCheckpoint{},
+		Watches:   []WatchCheckpoint{},
+	}
+
+	syncJobsMu.RLock()
+	for _, job := range syncJobs {
+		if job.Status == "running" {
+			checkpoint.Syncs = append(checkpoint.Syncs, SyncCheckpoint{JobID: job.ID, Request: job.request})
+		}
+	}
+	syncJobsMu.RUnlock()
+
+	watchJobsMu.Lock()
+	for _, job := range watchJobs {
+		if job.Status != "running" {
+			continue
+		}
+		checkpoint.Watches = append(checkpoint.Watches, WatchCheckpoint{
+			JobID: job.ID,
+			Request: WatchRequest{
+				LocalPath: job.LocalPath,
+				Bucket:    job.Bucket,
+				Path:      job.Path,
+			},
+		})
+		// Nothing new is picked up once drained; the next process watches again
+		if job.uploader != nil {
+			job.uploader.Stop()
+		}
+		job.Status = "drained"
+		job.StoppedAt = time.Now()
+	}
+	watchJobsMu.Unlock()
+
+	logging.Logger().Info("server drained for restart",
+		"syncs", len(checkpoint.Syncs),
+		"watches", len(checkpoint.Watches))
+	respondJSON(w, http.StatusOK, checkpoint)
+}
+
+// handleResume starts the jobs of a checkpoint taken by /api/drain on the
+// previous process
+func (s *Server) handleResume(w http.ResponseWriter, r *http.Request) {
+	if !s.fromShell(w, r) {
+		return
+	}
+	var checkpoint Checkpoint
+	if err := json.NewDecoder(r.Body).Decode(&checkpoint); err != nil {
+		respondError(w, http.StatusBadRequest, "Invalid request body")
+		return
+	}
+	if s.draining.Load() {
+		respondError(w, http.StatusServiceUnavailable, "Server is draining for a restart")
+		return
+	}
+
+	resumed := make([]ResumedJob, 0, len(checkpoint.Syncs)+len(checkpoint.Watches))
+	for _, c := range checkpoint.Syncs {
+		resumed = append(resumed, ResumedJob{PreviousID: c.JobID, JobID: s.startSync(c.Request)})
+	}
+	for _, c := range checkpoint.Watches {
+		job := ResumedJob{PreviousID: c.JobID}
+		jobID, err := s.startWatch(c.Request)
+		if err != nil {
+			job.Error = err.Error()
+			logging.Logger().Error("failed to resume watch job",
+				logging.JobID(c.JobID),
+				logging.Err(err))
+		} else {
+			job.JobID = jobID
+		}
+		resumed = append(resumed, job)
+	}
+
+	respondJSON(w, http.StatusOK, map[string]interface{}{
+		"resumed": resumed,
+	})
+}
+
+// fromShell rejects webview sessions; only the desktop shell restarts or
+// reconfigures the process
+func (s *Server) fromShell(w http.ResponseWriter, r *http.Request) bool {
+	if s.session == nil {
+		return true
+	}
+	if scope, _ := r.Context().Value(sessionScopeKey{}).(string); scope != scopeShell {
+		respondError(w, http.StatusForbidden, "Only the desktop shell can manage the server")
+		return false
+	}
+	return true
+}
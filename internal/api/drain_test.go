@@ -0,0 +1,86 @@
+package api
+
+import (
+	"bytes"
+	"encoding/json"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+func TestHandleDrain_CheckpointsRunningJobs(t *testing.T) {
+	server := &Server{
+		hub: NewWebSocketHub(),
+	}
+
+	request := SyncRequest{LocalPath: "/tmp/photos", Bucket: "photos", Direction: "to_remote", Delete: true}
+	syncJobsMu.Lock()
+	syncJobs = map[string]*SyncJob{
+		"sync-1": {ID: "sync-1", Status: "running", StartTime: time.Now(), request: request},
+		"sync-2": {ID: "sync-2", Status: "completed", StartTime: time.Now()},
+	}
+	syncJobsMu.Unlock()
+
+	watchJobsMu.Lock()
+	watchJobs = map[string]*WatchJob{
+		"watch-1": {ID: "watch-1", Status: "running", LocalPath: "/tmp/inbox", Bucket: "inbox", Path: "scans"},
+		"watch-2": {ID: "watch-2", Status: "stopped", LocalPath: "/tmp/old", Bucket: "inbox"},
+	}
+	watchJobsMu.Unlock()
+
+	rr := httptest.NewRecorder()
+	server.handleDrain(rr, httptest.NewRequest("POST", "/api/drain", nil))
+
+	if rr.Code != http.StatusOK {
+		t.Fatalf("Expected status %d, got %d", http.StatusOK, rr.Code)
+	}
+	var checkpoint Checkpoint
+	if err := json.Unmarshal(rr.Body.Bytes(), &checkpoint); err != nil {
+		t.Fatalf("Failed to parse checkpoint: %v", err)
+	}
+	if len(checkpoint.Syncs) != 1 || checkpoint.Syncs[0].JobID != "sync-1" || checkpoint.Syncs[0].Request != request {
+		t.Errorf("Expected only the running sync with its request, got %+v", checkpoint.Syncs)
+	}
+	if len(checkpoint.Watches) != 1 || checkpoint.Watches[0].Request.Path != "scans" {
+		t.Errorf("Expected only the running watch, got %+v", checkpoint.Watches)
+	}
+	if watchJobs["watch-1"].Status != "drained" {
+		t.Errorf("Expected the running watch to be drained, got %q", watchJobs["watch-1"].Status)
+	}
+
+	// Nothing new starts once drained
+	body := `{"local_path": "/tmp/a", "bucket": "b", "direction": "to_remote"}`
+	rr = httptest.NewRecorder()
+	server.handleSyncStart(rr, httptest.NewRequest("POST", "/api/sync/start", bytes.NewBufferString(body)))
+	if rr.Code != http.StatusServiceUnavailable {
+		t.Errorf("Expected status %d for a sync while drained, got %d", http.StatusServiceUnavailable, rr.Code)
+	}
+	rr = httptest.NewRecorder()
+	server.handleResume(rr, httptest.NewRequest("POST", "/api/resume", bytes.NewBufferString(`{}`)))
+	if rr.Code != http.StatusServiceUnavailable {
+		t.Errorf("Expected status %d for a resume while drained, got %d", http.StatusServiceUnavailable, rr.Code)
+	}
+}
+
+func TestHandleResume_EmptyCheckpoint(t *testing.T) {
+	server := &Server{
+		hub: NewWebSocketHub(),
+	}
+
+	rr := httptest.NewRecorder()
+	server.handleResume(rr, httptest.NewRequest("POST", "/api/resume", bytes.NewBufferString(`{"syncs": [], "watches": []}`)))
+
+	if rr.Code != http.StatusOK {
+		t.Fatalf("Expected status %d, got %d", http.StatusOK, rr.Code)
+	}
+	var result struct {
+		Resumed []ResumedJob `json:"resumed"`
+	}
+	if err := json.Unmarshal(rr.Body.Bytes(), &result); err != nil {
+		t.Fatalf("Failed to parse response: %v", err)
+	}
+	if result.Resumed == nil || len(result.Resumed) != 0 {
+		t.Errorf("Expected an empty list, got %+v", result.Resumed)
+	}
+}
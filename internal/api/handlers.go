@@ -8,6 +8,7 @@ import (
 	"net/http"
 	"path/filepath"
 	"runtime/debug"
+	"strconv"
 	"strings"
 	"sync"
 	"time"
@@ -92,8 +93,14 @@ func respondError(w http.ResponseWriter, status int, message string) {
 }
 
 // handleError logs the error with context and sends a sanitized error response.
-// The internal error is logged but not exposed to clients.
+// The internal error is logged but not exposed to clients. Authentication
+// failures are always 401, so clients can tell expired credentials from other
+// failures and ask for new ones.
 func handleError(w http.ResponseWriter, err error, status int, operation string, attrs ...any) {
+	if errors.IsUnauthorized(err) {
+		status = http.StatusUnauthorized
+	}
+
 	// Build log attributes
 	logAttrs := []any{
 		logging.Operation(operation),
@@ -315,19 +322,75 @@ func (s *Server) handleDownload(w http.ResponseWriter, r *http.Request) {
 		return
 	}
 
+	lastModified := time.Unix(info.Timestamp, 0).UTC().Format(http.TimeFormat)
+	w.Header().Set("Accept-Ranges", "bytes")
+	w.Header().Set("Last-Modified", lastModified)
+
+	// A ranged request lets an interrupted download continue; If-Range makes sure
+	// the object wasn't replaced in the meantime, or else it is sent whole
+	var byteRange *b2.ByteRange
+	if ifRange := r.Header.Get("If-Range"); ifRange == "" || ifRange == lastModified {
+		byteRange, err = parseByteRange(r.Header.Get("Range"), info.Size)
+		if err != nil {
+			w.Header().Set("Content-Range", fmt.Sprintf("bytes */%d", info.Size))
+			respondError(w, http.StatusRequestedRangeNotSatisfiable, err.Error())
+			return
+		}
+	}
+
 	// Set headers
 	w.Header().Set("Content-Type", info.ContentType)
-	w.Header().Set("Content-Length", fmt.Sprintf("%d", info.Size))
 	w.Header().Set("Content-Disposition", fmt.Sprintf("attachment; filename=\"%s\"", filepath.Base(path)))
 
+	var opts *b2.DownloadOptions
+	if byteRange != nil {
+		opts = b2.DefaultDownloadOptions()
+		opts.Range = byteRange
+		w.Header().Set("Content-Length", fmt.Sprintf("%d", byteRange.End-byteRange.Start))
+		w.Header().Set("Content-Range", fmt.Sprintf("bytes %d-%d/%d", byteRange.Start, byteRange.End-1, info.Size))
+		w.WriteHeader(http.StatusPartialContent)
+	} else {
+		w.Header().Set("Content-Length", fmt.Sprintf("%d", info.Size))
+	}
+
 	// Stream the file
-	err = s.client.Download(ctx, bucket, path, w, nil)
+	err = s.client.Download(ctx, bucket, path, w, opts)
 	if err != nil {
 		// Can't send error response after headers are sent
 		return
 	}
 }
 
+// parseByteRange reads a single "bytes=start-" or "bytes=start-end" Range header into
+// a range whose End is exclusive. Without a header, or with one it can't read, it
+// returns nil and the whole object is sent; a range past the end is an error.
+func parseByteRange(header string, size int64) (*b2.ByteRange, error) {
+	spec, ok := strings.CutPrefix(header, "bytes=")
+	if !ok || strings.Contains(spec, ",") {
+		return nil, nil
+	}
+	startText, endText, ok := strings.Cut(spec, "-")
+	if !ok || startText == "" {
+		return nil, nil
+	}
+	start, err := strconv.ParseInt(startText, 10, 64)
+	if err != nil || start < 0 {
+		return nil, nil
+	}
+	end := size - 1
+	if endText != "" {
+		end, err = strconv.ParseInt(endText, 10, 64)
+		if err != nil || end < start {
+			return nil, nil
+		}
+		end = min(end, size-1)
+	}
+	if start >= size {
+		return nil, fmt.Errorf("range starts at %d but the object is %d bytes", start, size)
+	}
+	return &b2.ByteRange{Start: start, End: end + 1}, nil
+}
+
 func (s *Server) handleStreamDownload(w http.ResponseWriter, r *http.Request) {
 	bucket := chi.URLParam(r, "bucket")
 	if err := validateBucketName(bucket); err != nil {
@@ -406,6 +469,7 @@ type SyncJob struct {
 	CompletedAt time.Time                `json:"completed_at,omitempty"`
 	Progress    string                   `json:"progress,omitempty"`
 	Result      *internalSync.SyncResult `json:"result,omitempty"`
+	request     SyncRequest
 }
 
 type SyncRequest struct {
@@ -437,7 +501,19 @@ func (s *Server) handleSyncStart(w http.ResponseWriter, r *http.Request) {
 		respondError(w, http.StatusBadRequest, "direction must be 'to_remote' or 'to_local'")
 		return
 	}
+	if s.draining.Load() {
+		respondError(w, http.StatusServiceUnavailable, "Server is draining for a restart")
+		return
+	}
 
+	respondJSON(w, http.StatusAccepted, map[string]string{
+		"job_id": s.startSync(req),
+		"status": "started",
+	})
+}
+
+// startSync runs a validated sync request in the background and returns its job ID
+func (s *Server) startSync(req SyncRequest) string {
 	// Generate job ID
 	jobID := fmt.Sprintf("sync-%d", time.Now().UnixNano())
 
@@ -450,6 +526,7 @@ func (s *Server) handleSyncStart(w http.ResponseWriter, r *http.Request) {
 		Path:      req.Path,
 		Direction: req.Direction,
 		StartTime: time.Now(),
+		request:   req,
 	}
 
 	syncJobsMu.Lock()
@@ -511,10 +588,7 @@ func (s *Server) handleSyncStart(w http.ResponseWriter, r *http.Request) {
 		})
 	})
 
-	respondJSON(w, http.StatusAccepted, map[string]string{
-		"job_id": jobID,
-		"status": "started",
-	})
+	return jobID
 }
 
 func (s *Server) handleSyncStatus(w http.ResponseWriter, r *http.Request) {
@@ -572,16 +646,33 @@ func (s *Server) handleWatchStart(w http.ResponseWriter, r *http.Request) {
 		respondError(w, http.StatusBadRequest, "bucket is required")
 		return
 	}
+	if s.draining.Load() {
+		respondError(w, http.StatusServiceUnavailable, "Server is draining for a restart")
+		return
+	}
 
+	jobID, err := s.startWatch(req)
+	if err != nil {
+		handleError(w, err, http.StatusInternalServerError, "watch_start",
+			logging.Bucket(req.Bucket), logging.Path(req.LocalPath))
+		return
+	}
+
+	respondJSON(w, http.StatusOK, map[string]string{
+		"job_id": jobID,
+		"status": "started",
+	})
+}
+
+// startWatch starts watching a validated request's folder and returns its job ID
+func (s *Server) startWatch(req WatchRequest) (string, error) {
 	// Generate job ID
 	jobID := fmt.Sprintf("watch-%d", time.Now().UnixNano())
 
 	// Create auto uploader
 	uploader, err := watch.NewAutoUploader(s.client, req.LocalPath, req.Bucket, req.Path, nil)
 	if err != nil {
-		handleError(w, err, http.StatusInternalServerError, "watch_start",
-			logging.Bucket(req.Bucket), logging.Path(req.LocalPath))
-		return
+		return "", err
 	}
 
 	uploader.OnUpload = func(path string, err error) {
@@ -616,10 +707,7 @@ func (s *Server) handleWatchStart(w http.ResponseWriter, r *http.Request) {
 		_ = uploader.Start(context.Background())
 	}()
 
-	respondJSON(w, http.StatusOK, map[string]string{
-		"job_id": jobID,
-		"status": "started",
-	})
+	return jobID, nil
 }
 
 func (s *Server) handleWatchStop(w http.ResponseWriter, r *http.Request) {
@@ -662,9 +750,15 @@ func (s *Server) handleListJobs(w http.ResponseWriter, r *http.Request) {
 	syncJobsMu.RLock()
 	for _, job := range syncJobs {
 		jobs = append(jobs, map[string]interface{}{
-			"id":     job.ID,
-			"type":   "sync",
-			"status": job.Status,
+			"id":         job.ID,
+			"type":       "sync",
+			"status":     job.Status,
+			"local_path": job.LocalPath,
+			"bucket":     job.Bucket,
+			"path":       job.Path,
+			"direction":  job.Direction,
+			"progress":   job.Progress,
+			"start_time": job.StartTime,
 		})
 	}
 	syncJobsMu.RUnlock()
@@ -672,9 +766,13 @@ func (s *Server) handleListJobs(w http.ResponseWriter, r *http.Request) {
 	watchJobsMu.RLock()
 	for _, job := range watchJobs {
 		jobs = append(jobs, map[string]interface{}{
-			"id":     job.ID,
-			"type":   "watch",
-			"status": job.Status,
+			"id":         job.ID,
+			"type":       "watch",
+			"status":     job.Status,
+			"local_path": job.LocalPath,
+			"bucket":     job.Bucket,
+			"path":       job.Path,
+			"start_time": job.StartTime,
 		})
 	}
 	watchJobsMu.RUnlock()
@@ -717,6 +815,29 @@ func (s *Server) handleDelete(w http.ResponseWriter, r *http.Request) {
 	})
 }
 
+func (s *Server) handleChecksums(w http.ResponseWriter, r *http.Request) {
+	bucket := chi.URLParam(r, "bucket")
+	if err := validateBucketName(bucket); err != nil {
+		respondError(w, http.StatusBadRequest, err.Error())
+		return
+	}
+
+	path, err := getPathFromURL(r)
+	if err != nil {
+		respondError(w, http.StatusBadRequest, err.Error())
+		return
+	}
+
+	checksums, err := s.client.GetObjectChecksums(r.Context(), bucket, path)
+	if err != nil {
+		handleError(w, err, http.StatusInternalServerError, "checksums",
+			logging.Bucket(bucket), logging.Object(path))
+		return
+	}
+
+	respondJSON(w, http.StatusOK, checksums)
+}
+
 // URL parameter helper
 func getPathFromURL(r *http.Request) (string, error) {
 	path := chi.URLParam(r, "*")
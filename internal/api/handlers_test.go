@@ -3,6 +3,7 @@ package api
 import (
 	"bytes"
 	"encoding/json"
+	"fmt"
 	"net/http"
 	"net/http/httptest"
 	"testing"
@@ -53,6 +54,23 @@ func TestRespondError(t *testing.T) {
 	}
 }
 
+func TestHandleError_AuthFailureIsUnauthorized(t *testing.T) {
+	rr := httptest.NewRecorder()
+
+	handleError(rr, fmt.Errorf("b2 request: 401: expired_auth_token: unauthorized"), http.StatusInternalServerError, "list_files")
+
+	if rr.Code != http.StatusUnauthorized {
+		t.Errorf("Expected status %d, got %d", http.StatusUnauthorized, rr.Code)
+	}
+
+	rr = httptest.NewRecorder()
+	handleError(rr, fmt.Errorf("connection refused"), http.StatusInternalServerError, "list_files")
+
+	if rr.Code != http.StatusInternalServerError {
+		t.Errorf("Expected status %d, got %d", http.StatusInternalServerError, rr.Code)
+	}
+}
+
 func TestHandleSyncStart_InvalidJSON(t *testing.T) {
 	server := &Server{
 		hub: NewWebSocketHub(),
@@ -282,6 +300,96 @@ func TestHandleListJobs_Empty(t *testing.T) {
 	}
 }
 
+func TestHandleListJobs_Details(t *testing.T) {
+	server := &Server{
+		hub: NewWebSocketHub(),
+	}
+
+	syncJobsMu.Lock()
+	syncJobs = map[string]*SyncJob{
+		"sync-1": {ID: "sync-1", Status: "failed", LocalPath: "/tmp/photos", Bucket: "photos", Path: "2024",
+			Direction: "to_remote", Progress: "connection reset"},
+	}
+	syncJobsMu.Unlock()
+
+	watchJobsMu.Lock()
+	watchJobs = map[string]*WatchJob{
+		"watch-1": {ID: "watch-1", Status: "stopped", LocalPath: "/tmp/inbox", Bucket: "inbox", Path: "scans"},
+	}
+	watchJobsMu.Unlock()
+
+	defer func() {
+		syncJobsMu.Lock()
+		syncJobs = make(map[string]*SyncJob)
+		syncJobsMu.Unlock()
+		watchJobsMu.Lock()
+		watchJobs = make(map[string]*WatchJob)
+		watchJobsMu.Unlock()
+	}()
+
+	req := httptest.NewRequest("GET", "/api/jobs", nil)
+	rr := httptest.NewRecorder()
+
+	server.handleListJobs(rr, req)
+
+	var result []map[string]interface{}
+	if err := json.Unmarshal(rr.Body.Bytes(), &result); err != nil {
+		t.Fatalf("Failed to unmarshal response: %v", err)
+	}
+	if len(result) != 2 {
+		t.Fatalf("Expected 2 jobs, got %d", len(result))
+	}
+
+	for _, job := range result {
+		switch job["type"] {
+		case "sync":
+			if job["local_path"] != "/tmp/photos" || job["bucket"] != "photos" || job["path"] != "2024" {
+				t.Errorf("Expected sync job locations, got %v", job)
+			}
+			if job["progress"] != "connection reset" {
+				t.Errorf("Expected sync job progress, got %v", job["progress"])
+			}
+		case "watch":
+			if job["local_path"] != "/tmp/inbox" || job["bucket"] != "inbox" || job["path"] != "scans" {
+				t.Errorf("Expected watch job locations, got %v", job)
+			}
+		default:
+			t.Errorf("Unexpected job type %v", job["type"])
+		}
+	}
+}
+
+func TestHandleCapabilities(t *testing.T) {
+	server := &Server{}
+
+	req := httptest.NewRequest("GET", "/api/capabilities", nil)
+	rr := httptest.NewRecorder()
+
+	server.handleCapabilities(rr, req)
+
+	if rr.Code != http.StatusOK {
+		t.Errorf("Expected status %d, got %d", http.StatusOK, rr.Code)
+	}
+
+	var result struct {
+		APIVersion   int          `json:"api_version"`
+		Capabilities Capabilities `json:"capabilities"`
+	}
+	if err := json.Unmarshal(rr.Body.Bytes(), &result); err != nil {
+		t.Fatalf("Failed to unmarshal response: %v", err)
+	}
+
+	if result.APIVersion != APIVersion {
+		t.Errorf("Expected api_version %d, got %d", APIVersion, result.APIVersion)
+	}
+	if !result.Capabilities.StreamUpload || !result.Capabilities.Checksums {
+		t.Errorf("Expected stream upload and checksums to be supported, got %+v", result.Capabilities)
+	}
+	if result.Capabilities.ServerSideCopy {
+		t.Error("Expected server-side copy to be unsupported")
+	}
+}
+
 func TestHandleAuth_InvalidJSON(t *testing.T) {
 	server := &Server{
 		hub: NewWebSocketHub(),
@@ -376,6 +484,50 @@ func TestValidatePath(t *testing.T) {
 	}
 }
 
+func TestParseByteRange(t *testing.T) {
+	tests := []struct {
+		header    string
+		start     int64
+		end       int64
+		whole     bool
+		expectErr bool
+	}{
+		{"", 0, 0, true, false},
+		{"bytes=0-", 0, 100, false, false},
+		{"bytes=40-", 40, 100, false, false},
+		{"bytes=10-19", 10, 20, false, false},
+		{"bytes=90-500", 90, 100, false, false},
+		{"bytes=-20", 0, 0, true, false},       // Suffix ranges aren't supported
+		{"bytes=0-9,20-29", 0, 0, true, false}, // Nor are multiple ranges
+		{"items=0-9", 0, 0, true, false},
+		{"bytes=20-10", 0, 0, true, false},
+		{"bytes=100-", 0, 0, false, true},
+	}
+
+	for _, tt := range tests {
+		result, err := parseByteRange(tt.header, 100)
+		if tt.expectErr {
+			if err == nil {
+				t.Errorf("parseByteRange(%q) expected error, got none", tt.header)
+			}
+			continue
+		}
+		if err != nil {
+			t.Errorf("parseByteRange(%q) unexpected error: %v", tt.header, err)
+			continue
+		}
+		if tt.whole {
+			if result != nil {
+				t.Errorf("parseByteRange(%q) = %+v, expected nil", tt.header, result)
+			}
+			continue
+		}
+		if result == nil || result.Start != tt.start || result.End != tt.end {
+			t.Errorf("parseByteRange(%q) = %+v, expected %d-%d", tt.header, result, tt.start, tt.end)
+		}
+	}
+}
+
 func TestValidateBucketName(t *testing.T) {
 	tests := []struct {
 		bucket    string
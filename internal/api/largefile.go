@@ -0,0 +1,158 @@
+package api
+
+import (
+	"encoding/json"
+	"encoding/hex"
+	"fmt"
+	"net/http"
+	"strconv"
+
+	"github.com/go-chi/chi/v5"
+	"github.com/ryanoboyle/bb-stream/internal/b2"
+	"github.com/ryanoboyle/bb-stream/pkg/errors"
+	"github.com/ryanoboyle/bb-stream/pkg/logging"
+)
+
+// Large file parts let a client upload a file one part at a time and keep its
+// own checkpoint, so an interrupted upload picks up after the parts B2 already
+// has. Parts are identified by the large file ID StartLargeFile returns.
+
+type largeFileStartRequest struct {
+	Bucket      string `json:"bucket"`
+	Path        string `json:"path"`
+	ContentType string `json:"content_type"`
+	// SHA1 is the whole file's hex SHA-1, stored as large_file_sha1 so the
+	// finished object can be verified like a small one
+	SHA1 string `json:"sha1"`
+}
+
+// isHexSHA1 reports whether s is a hex-encoded SHA-1
+func isHexSHA1(s string) bool {
+	decoded, err := hex.DecodeString(s)
+	return err == nil && len(decoded) == 20
+}
+
+// handleLargeFileStart starts a large file and returns its ID
+func (s *Server) handleLargeFileStart(w http.ResponseWriter, r *http.Request) {
+	var req largeFileStartRequest
+	if err := json.NewDecoder(r.Body).Decode(&req); err != nil {
+		respondError(w, http.StatusBadRequest, "Invalid request body")
+		return
+	}
+	if err := validateBucketName(req.Bucket); err != nil {
+		respondError(w, http.StatusBadRequest, err.Error())
+		return
+	}
+	path, err := validatePath(req.Path)
+	if err != nil {
+		respondError(w, http.StatusBadRequest, err.Error())
+		return
+	}
+	if req.SHA1 != "" && !isHexSHA1(req.SHA1) {
+		respondError(w, http.StatusBadRequest, "sha1 must be the file's hex SHA-1")
+		return
+	}
+
+	id, err := s.client.StartLargeFile(r.Context(), req.Bucket, path, req.ContentType, req.SHA1)
+	if err != nil {
+		handleError(w, err, http.StatusInternalServerError, "large_file_start",
+			logging.Bucket(req.Bucket), logging.Object(path))
+		return
+	}
+	respondJSON(w, http.StatusOK, map[string]string{"id": id})
+}
+
+// largeFileTarget reads the bucket and large file ID every part route takes
+func largeFileTarget(w http.ResponseWriter, r *http.Request) (bucket, id string, ok bool) {
+	bucket = r.URL.Query().Get("bucket")
+	if err := validateBucketName(bucket); err != nil {
+		respondError(w, http.StatusBadRequest, err.Error())
+		return "", "", false
+	}
+	id = chi.URLParam(r, "id")
+	if id == "" {
+		respondError(w, http.StatusBadRequest, "large file ID is required")
+		return "", "", false
+	}
+	return bucket, id, true
+}
+
+// largeFileError answers with 404 once the large file is gone, so the client
+// knows to start over rather than retry
+func largeFileError(w http.ResponseWriter, err error, operation, bucket string) {
+	status := http.StatusInternalServerError
+	if errors.IsNotFound(err) {
+		status = http.StatusNotFound
+	}
+	handleError(w, err, status, operation, logging.Bucket(bucket))
+}
+
+// handleLargeFileParts lists the parts B2 has stored so far
+func (s *Server) handleLargeFileParts(w http.ResponseWriter, r *http.Request) {
+	bucket, id, ok := largeFileTarget(w, r)
+	if !ok {
+		return
+	}
+	parts, err := s.client.ListLargeFileParts(r.Context(), bucket, id)
+	if err != nil {
+		largeFileError(w, err, "large_file_parts", bucket)
+		return
+	}
+	respondJSON(w, http.StatusOK, map[string]interface{}{"parts": parts})
+}
+
+// handleLargeFilePart stores one part. The body is the part's bytes, its
+// Content-Length the part size and X-Bz-Content-Sha1 their SHA-1.
+func (s *Server) handleLargeFilePart(w http.ResponseWriter, r *http.Request) {
+	bucket, id, ok := largeFileTarget(w, r)
+	if !ok {
+		return
+	}
+	number, err := strconv.Atoi(chi.URLParam(r, "number"))
+	if err != nil || number < 1 || number > b2.MaxLargeFileParts {
+		respondError(w, http.StatusBadRequest, fmt.Sprintf("part number must be between 1 and %d", b2.MaxLargeFileParts))
+		return
+	}
+	sha1 := r.Header.Get("X-Bz-Content-Sha1")
+	if !isHexSHA1(sha1) {
+		respondError(w, http.StatusBadRequest, "X-Bz-Content-Sha1 must be the part's hex SHA-1")
+		return
+	}
+	if r.ContentLength <= 0 {
+		respondError(w, http.StatusBadRequest, "Content-Length is required")
+		return
+	}
+
+	err = s.client.UploadLargeFilePart(r.Context(), bucket, id, number, sha1, r.ContentLength, r.Body)
+	if err != nil {
+		largeFileError(w, err, "large_file_part", bucket)
+		return
+	}
+	respondJSON(w, http.StatusOK, b2.LargeFilePart{Number: number, SHA1: sha1, Size: r.ContentLength})
+}
+
+// handleLargeFileFinish assembles the stored parts into the object
+func (s *Server) handleLargeFileFinish(w http.ResponseWriter, r *http.Request) {
+	bucket, id, ok := largeFileTarget(w, r)
+	if !ok {
+		return
+	}
+	if err := s.client.FinishLargeFile(r.Context(), bucket, id); err != nil {
+		largeFileError(w, err, "large_file_finish", bucket)
+		return
+	}
+	respondJSON(w, http.StatusOK, map[string]string{"status": "uploaded"})
+}
+
+// handleLargeFileCancel discards an unfinished large file
+func (s *Server) handleLargeFileCancel(w http.ResponseWriter, r *http.Request) {
+	bucket, id, ok := largeFileTarget(w, r)
+	if !ok {
+		return
+	}
+	if err := s.client.CancelLargeFile(r.Context(), bucket, id); err != nil {
+		largeFileError(w, err, "large_file_cancel", bucket)
+		return
+	}
+	respondJSON(w, http.StatusOK, map[string]string{"status": "cancelled"})
+}
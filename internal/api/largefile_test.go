@@ -0,0 +1,93 @@
+package api
+
+import (
+	"bytes"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+
+	"github.com/go-chi/chi/v5"
+)
+
+func largeFileRouter() http.Handler {
+	server := &Server{
+		hub: NewWebSocketHub(),
+	}
+
+	r := chi.NewRouter()
+	r.Post("/api/upload/large/start", server.handleLargeFileStart)
+	r.Get("/api/upload/large/{id}/parts", server.handleLargeFileParts)
+	r.Put("/api/upload/large/{id}/parts/{number}", server.handleLargeFilePart)
+	return r
+}
+
+func TestHandleLargeFileStart_Invalid(t *testing.T) {
+	tests := []struct {
+		name string
+		body string
+	}{
+		{"invalid JSON", "invalid"},
+		{"missing bucket", `{"path": "video.mov"}`},
+		{"path traversal", `{"bucket": "test-bucket", "path": "../video.mov"}`},
+		{"short checksum", `{"bucket": "test-bucket", "path": "video.mov", "sha1": "abcd"}`},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			req := httptest.NewRequest("POST", "/api/upload/large/start", bytes.NewBufferString(tt.body))
+			rr := httptest.NewRecorder()
+
+			largeFileRouter().ServeHTTP(rr, req)
+
+			if rr.Code != http.StatusBadRequest {
+				t.Errorf("Expected status %d, got %d", http.StatusBadRequest, rr.Code)
+			}
+		})
+	}
+}
+
+func TestHandleLargeFileParts_MissingBucket(t *testing.T) {
+	req := httptest.NewRequest("GET", "/api/upload/large/file-id/parts", nil)
+	rr := httptest.NewRecorder()
+
+	largeFileRouter().ServeHTTP(rr, req)
+
+	if rr.Code != http.StatusBadRequest {
+		t.Errorf("Expected status %d, got %d", http.StatusBadRequest, rr.Code)
+	}
+}
+
+func TestHandleLargeFilePart_Invalid(t *testing.T) {
+	sha1 := strings.Repeat("ab", 20)
+	tests := []struct {
+		name   string
+		number string
+		sha1   string
+		body   string
+	}{
+		{"part zero", "0", sha1, "data"},
+		{"too many parts", "10001", sha1, "data"},
+		{"not a number", "first", sha1, "data"},
+		{"missing checksum", "1", "", "data"},
+		{"short checksum", "1", "abcd", "data"},
+		{"empty part", "1", sha1, ""},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			url := "/api/upload/large/file-id/parts/" + tt.number + "?bucket=test-bucket"
+			req := httptest.NewRequest("PUT", url, bytes.NewBufferString(tt.body))
+			if tt.sha1 != "" {
+				req.Header.Set("X-Bz-Content-Sha1", tt.sha1)
+			}
+			rr := httptest.NewRecorder()
+
+			largeFileRouter().ServeHTTP(rr, req)
+
+			if rr.Code != http.StatusBadRequest {
+				t.Errorf("Expected status %d, got %d", http.StatusBadRequest, rr.Code)
+			}
+		})
+	}
+}
@@ -0,0 +1,223 @@
+package api
+
+import (
+	"fmt"
+	"io"
+	"net/http"
+	"runtime"
+	"sort"
+	"strconv"
+	"strings"
+	"sync"
+	"sync/atomic"
+	"time"
+
+	"github.com/go-chi/chi/v5"
+	"github.com/go-chi/chi/v5/middleware"
+)
+
+// Upper bounds of the request duration histogram, in seconds
+var durationBuckets = []float64{0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1, 2.5, 5, 10, 30, 60}
+
+type requestKey struct {
+	method string
+	route  string
+	status string
+}
+
+type histogram struct {
+	// Cumulative counts per bucket in durationBuckets; the +Inf bucket is count
+	buckets []uint64
+	count   uint64
+	sum     float64
+}
+
+func (h *histogram) observe(seconds float64) {
+	for i, bound := range durationBuckets {
+		if seconds <= bound {
+			h.buckets[i]++
+		}
+	}
+	h.count++
+	h.sum += seconds
+}
+
+// Metrics counts HTTP requests per route and serves them, with a few runtime
+// gauges, in the Prometheus text format on /metrics
+type Metrics struct {
+	mu        sync.Mutex
+	requests  map[requestKey]uint64
+	durations map[string]*histogram
+	bytesIn   atomic.Uint64
+	bytesOut  atomic.Uint64
+	inFlight  atomic.Int64
+	startTime time.Time
+	// Extra gauges read at scrape time, by metric name
+	gauges map[string]func() float64
+}
+
+// NewMetrics creates an empty set of metrics
+func NewMetrics() *Metrics {
+	return &Metrics{
+		requests:  make(map[requestKey]uint64),
+		durations: make(map[string]*histogram),
+		startTime: time.Now(),
+		gauges:    make(map[string]func() float64),
+	}
+}
+
+// Gauge registers a value read whenever metrics are scraped
+func (m *Metrics) Gauge(name string, read func() float64) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.gauges[name] = read
+}
+
+type countingReader struct {
+	io.ReadCloser
+	counter *atomic.Uint64
+}
+
+func (c countingReader) Read(p []byte) (int, error) {
+	n, err := c.ReadCloser.Read(p)
+	c.counter.Add(uint64(n))
+	return n, err
+}
+
+// Middleware records each request's route, status, duration and size. A nil
+// Metrics records nothing.
+func (m *Metrics) Middleware(next http.Handler) http.Handler {
+	if m == nil {
+		return next
+	}
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		start := time.Now()
+		m.inFlight.Add(1)
+		defer m.inFlight.Add(-1)
+		if r.Body != nil {
+			r.Body = countingReader{ReadCloser: r.Body, counter: &m.bytesIn}
+		}
+		ww := middleware.NewWrapResponseWriter(w, r.ProtoMajor)
+
+		next.ServeHTTP(ww, r)
+
+		// The pattern is only known once the router has matched the request
+		route := "unmatched"
+		if rctx := chi.RouteContext(r.Context()); rctx != nil && rctx.RoutePattern() != "" {
+			route = rctx.RoutePattern()
+		}
+		status := ww.Status()
+		if status == 0 {
+			status = http.StatusOK
+		}
+		m.bytesOut.Add(uint64(ww.BytesWritten()))
+
+		m.mu.Lock()
+		defer m.mu.Unlock()
+		m.requests[requestKey{method: r.Method, route: route, status: strconv.Itoa(status)}]++
+		h, ok := m.durations[route]
+		if !ok {
+			h = &histogram{buckets: make([]uint64, len(durationBuckets))}
+			m.durations[route] = h
+		}
+		h.observe(time.Since(start).Seconds())
+	})
+}
+
+func formatFloat(v float64) string {
+	return strconv.FormatFloat(v, 'g', -1, 64)
+}
+
+func quoteLabel(v string) string {
+	return strconv.Quote(v)
+}
+
+// Write renders every metric in the Prometheus text exposition format
+func (m *Metrics) Write(w io.Writer) {
+	var mem runtime.MemStats
+	runtime.ReadMemStats(&mem)
+
+	m.mu.Lock()
+	keys := make([]requestKey, 0, len(m.requests))
+	for k := range m.requests {
+		keys = append(keys, k)
+	}
+	sort.Slice(keys, func(i, j int) bool {
+		a, b := keys[i], keys[j]
+		if a.route != b.route {
+			return a.route < b.route
+		}
+		if a.method != b.method {
+			return a.method < b.method
+		}
+		return a.status < b.status
+	})
+	var b strings.Builder
+	b.WriteString("# HELP bbstream_http_requests_total HTTP requests by method, route and status.\n")
+	b.WriteString("# TYPE bbstream_http_requests_total counter\n")
+	for _, k := range keys {
+		fmt.Fprintf(&b, "bbstream_http_requests_total{method=%s,route=%s,status=%s} %d\n",
+			quoteLabel(k.method), quoteLabel(k.route), quoteLabel(k.status), m.requests[k])
+	}
+
+	routes := make([]string, 0, len(m.durations))
+	for route := range m.durations {
+		routes = append(routes, route)
+	}
+	sort.Strings(routes)
+	b.WriteString("# HELP bbstream_http_request_duration_seconds Time to serve HTTP requests by route.\n")
+	b.WriteString("# TYPE bbstream_http_request_duration_seconds histogram\n")
+	for _, route := range routes {
+		h := m.durations[route]
+		for i, bound := range durationBuckets {
+			fmt.Fprintf(&b, "bbstream_http_request_duration_seconds_bucket{route=%s,le=%s} %d\n",
+				quoteLabel(route), quoteLabel(formatFloat(bound)), h.buckets[i])
+		}
+		fmt.Fprintf(&b, "bbstream_http_request_duration_seconds_bucket{route=%s,le=\"+Inf\"} %d\n",
+			quoteLabel(route), h.count)
+		fmt.Fprintf(&b, "bbstream_http_request_duration_seconds_sum{route=%s} %s\n", quoteLabel(route), formatFloat(h.sum))
+		fmt.Fprintf(&b, "bbstream_http_request_duration_seconds_count{route=%s} %d\n", quoteLabel(route), h.count)
+	}
+
+	gauges := make([]string, 0, len(m.gauges))
+	for name := range m.gauges {
+		gauges = append(gauges, name)
+	}
+	sort.Strings(gauges)
+	reads := make(map[string]func() float64, len(gauges))
+	for _, name := range gauges {
+		reads[name] = m.gauges[name]
+	}
+	m.mu.Unlock()
+
+	b.WriteString("# TYPE bbstream_http_request_bytes_total counter\n")
+	fmt.Fprintf(&b, "bbstream_http_request_bytes_total %d\n", m.bytesIn.Load())
+	b.WriteString("# TYPE bbstream_http_response_bytes_total counter\n")
+	fmt.Fprintf(&b, "bbstream_http_response_bytes_total %d\n", m.bytesOut.Load())
+	b.WriteString("# TYPE bbstream_http_requests_in_flight gauge\n")
+	fmt.Fprintf(&b, "bbstream_http_requests_in_flight %d\n", m.inFlight.Load())
+	b.WriteString("# TYPE bbstream_uptime_seconds gauge\n")
+	fmt.Fprintf(&b, "bbstream_uptime_seconds %s\n", formatFloat(time.Since(m.startTime).Seconds()))
+	b.WriteString("# TYPE bbstream_goroutines gauge\n")
+	fmt.Fprintf(&b, "bbstream_goroutines %d\n", runtime.NumGoroutine())
+	b.WriteString("# TYPE bbstream_heap_bytes gauge\n")
+	fmt.Fprintf(&b, "bbstream_heap_bytes %d\n", mem.HeapAlloc)
+	b.WriteString("# TYPE bbstream_gc_total counter\n")
+	fmt.Fprintf(&b, "bbstream_gc_total %d\n", mem.NumGC)
+	// Registered gauges are read without the lock, as they may take their own
+	for _, name := range gauges {
+		fmt.Fprintf(&b, "# TYPE %s gauge\n%s %s\n", name, name, formatFloat(reads[name]()))
+	}
+
+	_, _ = io.WriteString(w, b.String())
+}
+
+// ServeHTTP serves the metrics to a scraper
+func (m *Metrics) ServeHTTP(w http.ResponseWriter, r *http.Request) {
+	if m == nil {
+		http.NotFound(w, r)
+		return
+	}
+	w.Header().Set("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+	m.Write(w)
+}
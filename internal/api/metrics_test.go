@@ -0,0 +1,70 @@
+package api
+
+import (
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/go-chi/chi/v5"
+)
+
+func TestMetrics_CountsRequestsByRoute(t *testing.T) {
+	m := NewMetrics()
+	r := chi.NewRouter()
+	r.Use(m.Middleware)
+	r.Get("/api/buckets/{name}/files", func(w http.ResponseWriter, r *http.Request) {
+		_, _ = w.Write([]byte("[]"))
+	})
+	r.Get("/metrics", m.ServeHTTP)
+
+	for _, bucket := range []string{"a", "b"} {
+		rr := httptest.NewRecorder()
+		r.ServeHTTP(rr, httptest.NewRequest("GET", "/api/buckets/"+bucket+"/files", nil))
+	}
+	rr := httptest.NewRecorder()
+	r.ServeHTTP(rr, httptest.NewRequest("GET", "/missing", nil))
+
+	rr = httptest.NewRecorder()
+	r.ServeHTTP(rr, httptest.NewRequest("GET", "/metrics", nil))
+	body := rr.Body.String()
+	for _, want := range []string{
+		`bbstream_http_requests_total{method="GET",route="/api/buckets/{name}/files",status="200"} 2`,
+		`bbstream_http_requests_total{method="GET",route="unmatched",status="404"} 1`,
+		`bbstream_http_request_duration_seconds_count{route="/api/buckets/{name}/files"} 2`,
+		`bbstream_http_request_duration_seconds_bucket{route="/api/buckets/{name}/files",le="+Inf"} 2`,
+		"bbstream_goroutines ",
+	} {
+		if !strings.Contains(body, want) {
+			t.Errorf("Expected metrics to contain %q, got:\n%s", want, body)
+		}
+	}
+}
+
+func TestMetrics_HistogramBuckets(t *testing.T) {
+	h := &histogram{buckets: make([]uint64, len(durationBuckets))}
+	h.observe((30 * time.Millisecond).Seconds())
+
+	for i, bound := range durationBuckets {
+		want := uint64(0)
+		if bound >= 0.05 {
+			want = 1
+		}
+		if h.buckets[i] != want {
+			t.Errorf("Bucket le=%v: expected %d, got %d", bound, want, h.buckets[i])
+		}
+	}
+	if h.count != 1 {
+		t.Errorf("Expected count 1, got %d", h.count)
+	}
+}
+
+func TestMetrics_NilServesNothing(t *testing.T) {
+	var m *Metrics
+	rr := httptest.NewRecorder()
+	m.ServeHTTP(rr, httptest.NewRequest("GET", "/metrics", nil))
+	if rr.Code != http.StatusNotFound {
+		t.Errorf("Expected status %d, got %d", http.StatusNotFound, rr.Code)
+	}
+}
@@ -30,8 +30,8 @@ func CORSMiddleware(next http.Handler) http.Handler {
 // AuthMiddleware validates API authentication using API key
 func AuthMiddleware(next http.Handler) http.Handler {
 	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
-		// Skip auth for health check
-		if r.URL.Path == "/health" {
+		// Skip auth for health and readiness checks
+		if r.URL.Path == "/health" || r.URL.Path == "/ready" {
 			next.ServeHTTP(w, r)
 			return
 		}
@@ -49,6 +49,11 @@ func AuthMiddleware(next http.Handler) http.Handler {
 			auth := r.Header.Get("Authorization")
 			if strings.HasPrefix(auth, "Bearer ") {
 				apiKey = strings.TrimPrefix(auth, "Bearer ")
+			} else if _, password, ok := r.BasicAuth(); ok && password != "" && password == config.Get().APIKey {
+				// The desktop app sends the key as the Basic auth password when
+				// connecting to an external server. Any other Basic auth password is
+				// the app's session credential, which SessionAuth checks instead.
+				apiKey = password
 			}
 		}
 
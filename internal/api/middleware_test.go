@@ -125,6 +125,27 @@ func TestAuthMiddleware_ValidBearerToken(t *testing.T) {
 	config.SetAPIKey("")
 }
 
+func TestAuthMiddleware_ValidBasicAuthPassword(t *testing.T) {
+	_ = config.Get()
+	config.SetAPIKey("basic-secret")
+
+	handler := AuthMiddleware(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.WriteHeader(http.StatusOK)
+	}))
+
+	req := httptest.NewRequest("GET", "/api/buckets", nil)
+	req.SetBasicAuth("shell", "basic-secret")
+	req.RemoteAddr = "192.168.1.100:12345"
+	rr := httptest.NewRecorder()
+	handler.ServeHTTP(rr, req)
+
+	if rr.Code != http.StatusOK {
+		t.Errorf("API key as Basic auth password should be accepted, got status %d", rr.Code)
+	}
+
+	config.SetAPIKey("")
+}
+
 func TestAuthMiddleware_InvalidAPIKey(t *testing.T) {
 	_ = config.Get()
 	config.SetAPIKey("correct-key")
@@ -0,0 +1,95 @@
+package api
+
+import (
+	"context"
+	"fmt"
+	"net/http"
+	"sync"
+	"time"
+
+	"github.com/ryanoboyle/bb-stream/pkg/errors"
+)
+
+const (
+	// How often B2 is probed to decide readiness
+	readinessInterval = 30 * time.Second
+	// How long a single probe may take before B2 counts as unreachable
+	readinessProbeTimeout = 10 * time.Second
+)
+
+// readiness tracks whether the server can do useful work, as opposed to /health,
+// which only shows that the process accepts connections. B2 is probed in the
+// background so frequent polling of /ready never turns into B2 traffic.
+type readiness struct {
+	probe func(ctx context.Context) error
+
+	mu      sync.RWMutex
+	checked bool
+	err     error
+}
+
+func newReadiness(probe func(ctx context.Context) error) *readiness {
+	return &readiness{probe: probe}
+}
+
+// probeOnce runs the probe and records its result
+func (rd *readiness) probeOnce() {
+	ctx, cancel := context.WithTimeout(context.Background(), readinessProbeTimeout)
+	defer cancel()
+	err := rd.probe(ctx)
+
+	rd.mu.Lock()
+	defer rd.mu.Unlock()
+	rd.checked = true
+	rd.err = err
+}
+
+// run probes until shutdown is closed
+func (rd *readiness) run(shutdown <-chan struct{}) {
+	for {
+		rd.probeOnce()
+		select {
+		case <-shutdown:
+			return
+		case <-time.After(readinessInterval):
+		}
+	}
+}
+
+// status reports whether the server is ready, and why not when it isn't
+func (rd *readiness) status() (bool, string) {
+	rd.mu.RLock()
+	defer rd.mu.RUnlock()
+	if !rd.checked {
+		return false, "warming up"
+	}
+	if rd.err != nil {
+		return false, fmt.Sprintf("cannot reach Backblaze B2: %v", rd.err)
+	}
+	return true, ""
+}
+
+// authFailed reports whether the last probe failed because B2 rejected the
+// credentials, which retrying won't fix
+func (rd *readiness) authFailed() bool {
+	rd.mu.RLock()
+	defer rd.mu.RUnlock()
+	return rd.err != nil && errors.IsUnauthorized(rd.err)
+}
+
+// handleReady answers 200 once the server can serve B2 requests, and 503 with
+// the reason while it is warming up, B2 is unreachable or B2 rejects the
+// credentials
+func (s *Server) handleReady(w http.ResponseWriter, r *http.Request) {
+	setInstanceHeader(w)
+	ready, reason := s.ready.status()
+	if !ready {
+		respondJSON(w, http.StatusServiceUnavailable, map[string]interface{}{
+			"ready":         false,
+			"reason":        reason,
+			"auth_required": s.ready.authFailed(),
+		})
+		return
+	}
+	respondJSON(w, http.StatusOK, map[string]interface{}{"ready": true})
+}
@@ -0,0 +1,82 @@
+package api
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+)
+
+func TestReadiness_WarmingUp(t *testing.T) {
+	rd := newReadiness(func(ctx context.Context) error { return nil })
+
+	ready, reason := rd.status()
+	if ready {
+		t.Error("Expected not ready before the first probe")
+	}
+	if reason != "warming up" {
+		t.Errorf("Expected reason 'warming up', got %q", reason)
+	}
+}
+
+func TestReadiness_ProbeResult(t *testing.T) {
+	probeErr := errors.New("connection refused")
+	rd := newReadiness(func(ctx context.Context) error { return probeErr })
+
+	rd.probeOnce()
+	if ready, reason := rd.status(); ready || reason == "" {
+		t.Errorf("Expected not ready with a reason after a failed probe, got %v %q", ready, reason)
+	}
+
+	probeErr = nil
+	rd.probeOnce()
+	if ready, reason := rd.status(); !ready || reason != "" {
+		t.Errorf("Expected ready after a successful probe, got %v %q", ready, reason)
+	}
+}
+
+func TestHandleReady(t *testing.T) {
+	probeErr := errors.New("no route to host")
+	s := &Server{ready: newReadiness(func(ctx context.Context) error { return probeErr })}
+	s.ready.probeOnce()
+
+	rr := httptest.NewRecorder()
+	s.handleReady(rr, httptest.NewRequest("GET", "/ready", nil))
+	if rr.Code != http.StatusServiceUnavailable {
+		t.Errorf("Expected status %d, got %d", http.StatusServiceUnavailable, rr.Code)
+	}
+	var body map[string]interface{}
+	if err := json.Unmarshal(rr.Body.Bytes(), &body); err != nil {
+		t.Fatalf("Failed to parse response: %v", err)
+	}
+	if body["reason"] == "" || body["reason"] == nil {
+		t.Error("Expected a reason when not ready")
+	}
+
+	probeErr = nil
+	s.ready.probeOnce()
+	rr = httptest.NewRecorder()
+	s.handleReady(rr, httptest.NewRequest("GET", "/ready", nil))
+	if rr.Code != http.StatusOK {
+		t.Errorf("Expected status %d, got %d", http.StatusOK, rr.Code)
+	}
+}
+
+func TestHandleReady_AuthRequired(t *testing.T) {
+	s := &Server{ready: newReadiness(func(ctx context.Context) error {
+		return errors.New("b2: 401 bad_auth_token: unauthorized")
+	})}
+	s.ready.probeOnce()
+
+	rr := httptest.NewRecorder()
+	s.handleReady(rr, httptest.NewRequest("GET", "/ready", nil))
+	var body map[string]interface{}
+	if err := json.Unmarshal(rr.Body.Bytes(), &body); err != nil {
+		t.Fatalf("Failed to parse response: %v", err)
+	}
+	if body["auth_required"] != true {
+		t.Errorf("Expected auth_required for rejected credentials, got %v", body["auth_required"])
+	}
+}
@@ -3,8 +3,11 @@ package api
 import (
 	"context"
 	"fmt"
+	"net"
 	"net/http"
+	"os"
 	"sync"
+	"sync/atomic"
 	"time"
 
 	"github.com/go-chi/chi/v5"
@@ -25,10 +28,16 @@ type Server struct {
 	router     chi.Router
 	httpServer *http.Server
 	port       int
+	socket     string
 	hub        *WebSocketHub
 	shutdown   chan struct{}
 	wg         sync.WaitGroup
 	startTime  time.Time
+	ready      *readiness
+	session    *SessionAuth
+	metrics    *Metrics
+	// Set by /api/drain; no new sync or watch jobs start until the process restarts
+	draining   atomic.Bool
 }
 
 // NewServer creates a new API server
@@ -39,7 +48,16 @@ func NewServer(client *b2.Client, port int) *Server {
 		hub:       NewWebSocketHub(),
 		shutdown:  make(chan struct{}),
 		startTime: time.Now(),
+		session:   NewSessionAuthFromEnv(),
+		metrics:   NewMetrics(),
 	}
+	s.metrics.Gauge("bbstream_websocket_clients", func() float64 {
+		return float64(s.hub.ClientCount())
+	})
+	s.ready = newReadiness(func(ctx context.Context) error {
+		_, err := client.ListBuckets(ctx)
+		return err
+	})
 
 	s.setupRouter()
 	return s
@@ -53,21 +71,48 @@ func (s *Server) setupRouter() {
 	r.Use(middleware.Logger)
 	r.Use(middleware.Recoverer)
 	r.Use(middleware.RequestID)
+	r.Use(s.metrics.Middleware)
 	r.Use(middleware.RealIP)
 	r.Use(middleware.Timeout(60 * time.Second))
 	r.Use(SecurityHeadersMiddleware)
 	r.Use(CORSMiddleware)
 
-	// Health check
-	r.Get("/health", func(w http.ResponseWriter, r *http.Request) {
-		w.WriteHeader(http.StatusOK)
-		_, _ = w.Write([]byte("OK"))
+	// Probes need a session token too when the desktop shell launched this
+	// server, so other local processes learn nothing from it
+	r.Group(func(r chi.Router) {
+		r.Use(s.session.Middleware)
+
+		// Liveness: the process is up and accepting connections
+		r.Get("/health", func(w http.ResponseWriter, r *http.Request) {
+			setInstanceHeader(w)
+			w.WriteHeader(http.StatusOK)
+			_, _ = w.Write([]byte("OK"))
+		})
+
+		// Readiness: B2 is reachable, so requests can actually be served
+		r.Get("/ready", s.handleReady)
+
+		// Request counts, latencies and runtime gauges for the desktop shell
+		r.Get("/metrics", s.metrics.ServeHTTP)
 	})
 
 	// API routes
 	r.Route("/api", func(r chi.Router) {
+		// Session tokens from the desktop shell, when it launched this server
+		r.Use(s.session.Middleware)
+		r.Post("/session/revoke", s.handleSessionRevoke)
+
+		// Restarts that keep running jobs
+		r.Post("/drain", s.handleDrain)
+		r.Post("/resume", s.handleResume)
+
+		// Configuration changes that apply without a restart
+		r.Get("/admin/config", s.handleGetRuntimeConfig)
+		r.Post("/admin/config", s.handleSetRuntimeConfig)
+
 		// Version and status
 		r.Get("/version", s.handleVersion)
+		r.Get("/capabilities", s.handleCapabilities)
 		r.Get("/status", s.handleStatus)
 
 		// Auth
@@ -80,11 +125,22 @@ func (s *Server) setupRouter() {
 		// Upload
 		r.Post("/upload", s.handleUpload)
 		r.Post("/upload/stream", s.handleStreamUpload)
+		r.Post("/upload/large/start", s.handleLargeFileStart)
+		r.Get("/upload/large/{id}/parts", s.handleLargeFileParts)
+		r.Put("/upload/large/{id}/parts/{number}", s.handleLargeFilePart)
+		r.Post("/upload/large/{id}/finish", s.handleLargeFileFinish)
+		r.Delete("/upload/large/{id}", s.handleLargeFileCancel)
 
 		// Download
 		r.Get("/download/{bucket}/*", s.handleDownload)
 		r.Get("/stream/{bucket}/*", s.handleStreamDownload)
 
+		// Checksums
+		r.Get("/checksums/{bucket}/*", s.handleChecksums)
+
+		// Thumbnails
+		r.Get("/thumbnail/{bucket}/*", s.handleThumbnail)
+
 		// Delete
 		r.Delete("/delete/{bucket}/*", s.handleDelete)
 
@@ -110,17 +166,71 @@ func (s *Server) setupRouter() {
 	s.router = r
 }
 
-// Start starts the HTTP server
+// setInstanceHeader echoes the instance id the desktop shell launched this
+// process with, so the shell can tell it apart from whatever else may later bind
+// the same port
+func setInstanceHeader(w http.ResponseWriter) {
+	if id := os.Getenv("BB_INSTANCE_ID"); id != "" {
+		w.Header().Set("X-BB-Instance", id)
+	}
+}
+
+// SetSocket makes Start listen on a Unix domain socket at path instead of a TCP
+// port, so nothing is exposed on the network and no port can be taken
+func (s *Server) SetSocket(path string) {
+	s.socket = path
+}
+
+// listen binds the configured socket or port, returning the line that announces
+// where the server listens
+func (s *Server) listen() (net.Listener, string, error) {
+	if s.socket != "" {
+		// A socket file left by a process that was killed would make Listen fail
+		if err := os.Remove(s.socket); err != nil && !os.IsNotExist(err) {
+			return nil, "", fmt.Errorf("failed to remove stale socket: %w", err)
+		}
+		listener, err := net.Listen("unix", s.socket)
+		if err != nil {
+			return nil, "", fmt.Errorf("failed to listen: %w", err)
+		}
+		// Only this user may connect
+		if err := os.Chmod(s.socket, 0o600); err != nil {
+			listener.Close()
+			return nil, "", fmt.Errorf("failed to restrict socket: %w", err)
+		}
+		return listener, fmt.Sprintf("READY socket=%s", s.socket), nil
+	}
+
+	listener, err := net.Listen("tcp", fmt.Sprintf(":%d", s.port))
+	if err != nil {
+		return nil, "", fmt.Errorf("failed to listen: %w", err)
+	}
+	s.port = listener.Addr().(*net.TCPAddr).Port
+	return listener, fmt.Sprintf("READY port=%d", s.port), nil
+}
+
+// Start starts the HTTP server. Port 0 binds any free port; either way the
+// bound port is announced on stdout as "READY port=<n>" once the server is
+// accepting connections, which the desktop shell waits for. With a socket set
+// the line is "READY socket=<path>" instead.
 func (s *Server) Start() error {
+	listener, ready, err := s.listen()
+	if err != nil {
+		return err
+	}
+
 	s.httpServer = &http.Server{
-		Addr:    fmt.Sprintf(":%d", s.port),
 		Handler: s.router,
 	}
 
 	// Start WebSocket hub
 	go s.hub.Run()
 
-	return s.httpServer.ListenAndServe()
+	// Probe B2 in the background for /ready
+	go s.ready.run(s.shutdown)
+
+	fmt.Println(ready)
+	return s.httpServer.Serve(listener)
 }
 
 // Shutdown gracefully shuts down the server
@@ -151,7 +261,11 @@ func (s *Server) Shutdown(ctx context.Context) error {
 	}
 
 	// Shutdown HTTP server
-	return s.httpServer.Shutdown(ctx)
+	err := s.httpServer.Shutdown(ctx)
+	if s.socket != "" {
+		os.Remove(s.socket)
+	}
+	return err
 }
 
 // stopAllWatchJobs stops all running watch jobs
@@ -195,6 +309,46 @@ func (s *Server) handleVersion(w http.ResponseWriter, r *http.Request) {
 	})
 }
 
+// Capabilities lists the optional operations this server supports, so clients can
+// enable features up front instead of failing at call time
+type Capabilities struct {
+	ServerSideCopy bool `json:"server_side_copy"`
+	Versioning     bool `json:"versioning"`
+	HideUnhide     bool `json:"hide_unhide"`
+	ShareLinks     bool `json:"share_links"`
+	Checksums      bool `json:"checksums"`
+	StreamUpload   bool `json:"stream_upload"`
+	Sync           bool `json:"sync"`
+	Watch          bool `json:"watch"`
+	Thumbnails     bool `json:"thumbnails"`
+	Drain          bool `json:"drain"`
+	LiveConfig     bool `json:"live_config"`
+	LargeFileParts bool `json:"large_file_parts"`
+}
+
+// serverCapabilities reports what the routes registered in setupRouter support
+func serverCapabilities() Capabilities {
+	return Capabilities{
+		Checksums:      true,
+		StreamUpload:   true,
+		Sync:           true,
+		Watch:          true,
+		Thumbnails:     true,
+		Drain:          true,
+		LiveConfig:     true,
+		LargeFileParts: true,
+	}
+}
+
+// handleCapabilities returns the capability manifest
+func (s *Server) handleCapabilities(w http.ResponseWriter, r *http.Request) {
+	respondJSON(w, http.StatusOK, map[string]interface{}{
+		"version":      Version,
+		"api_version":  APIVersion,
+		"capabilities": serverCapabilities(),
+	})
+}
+
 // handleStatus returns server status information
 func (s *Server) handleStatus(w http.ResponseWriter, r *http.Request) {
 	// Count active jobs
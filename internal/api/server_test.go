@@ -0,0 +1,52 @@
+package api
+
+import (
+	"os"
+	"path/filepath"
+	"runtime"
+	"strings"
+	"testing"
+)
+
+func TestListen_Socket(t *testing.T) {
+	if runtime.GOOS == "windows" {
+		t.Skip("socket permissions are not enforced on Windows")
+	}
+	path := filepath.Join(t.TempDir(), "api.sock")
+	// Left behind by a killed process
+	if err := os.WriteFile(path, nil, 0o644); err != nil {
+		t.Fatal(err)
+	}
+
+	s := &Server{}
+	s.SetSocket(path)
+	listener, ready, err := s.listen()
+	if err != nil {
+		t.Fatalf("listen: %v", err)
+	}
+	defer listener.Close()
+
+	if ready != "READY socket="+path {
+		t.Errorf("Expected ready line for %s, got %q", path, ready)
+	}
+	info, err := os.Stat(path)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if info.Mode().Perm() != 0o600 {
+		t.Errorf("Expected socket mode 0600, got %o", info.Mode().Perm())
+	}
+}
+
+func TestListen_Port(t *testing.T) {
+	s := &Server{}
+	listener, ready, err := s.listen()
+	if err != nil {
+		t.Fatalf("listen: %v", err)
+	}
+	defer listener.Close()
+
+	if s.port == 0 || !strings.HasPrefix(ready, "READY port=") {
+		t.Errorf("Expected a bound port, got %d and %q", s.port, ready)
+	}
+}
@@ -0,0 +1,183 @@
+package api
+
+import (
+	"context"
+	"crypto/hmac"
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"errors"
+	"net/http"
+	"os"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
+)
+
+// Token scopes. Webview tokens are short-lived and revocable; the shell's own
+// token never leaves the desktop process and doesn't expire. Download tokens go
+// into copied links and only open the object they were issued for, whose
+// SHA-256 of "<bucket>/<key>" takes the nonce's place.
+const (
+	scopeShell    = "shell"
+	scopeWebview  = "webview"
+	scopeDownload = "download"
+)
+
+const downloadPrefix = "/api/download/"
+
+type sessionScopeKey struct{}
+
+// SessionAuth verifies session tokens minted by the desktop shell, which shares
+// a per-launch secret with this process through BB_SESSION_SECRET. A token is
+// "<scope>.<generation>.<expires>.<nonce>.<signature>", signed with
+// HMAC-SHA256 over everything before the signature. Raising the minimum
+// generation revokes every webview token issued before it.
+type SessionAuth struct {
+	secret []byte
+
+	mu            sync.RWMutex
+	minGeneration uint64
+}
+
+// NewSessionAuthFromEnv returns nil when the server wasn't launched by the
+// desktop shell, leaving the API open as before
+func NewSessionAuthFromEnv() *SessionAuth {
+	secret, err := hex.DecodeString(os.Getenv("BB_SESSION_SECRET"))
+	if err != nil || len(secret) == 0 {
+		return nil
+	}
+	generation, _ := strconv.ParseUint(os.Getenv("BB_SESSION_GENERATION"), 10, 64)
+	return &SessionAuth{secret: secret, minGeneration: generation}
+}
+
+// verify checks a token and returns its scope
+func (a *SessionAuth) verify(token string, now time.Time) (string, error) {
+	cut := strings.LastIndex(token, ".")
+	if cut < 0 {
+		return "", errors.New("malformed token")
+	}
+	payload, signature := token[:cut], token[cut+1:]
+	sig, err := hex.DecodeString(signature)
+	if err != nil {
+		return "", errors.New("malformed token")
+	}
+	mac := hmac.New(sha256.New, a.secret)
+	mac.Write([]byte(payload))
+	if !hmac.Equal(sig, mac.Sum(nil)) {
+		return "", errors.New("invalid token")
+	}
+
+	parts := strings.Split(payload, ".")
+	if len(parts) != 4 {
+		return "", errors.New("malformed token")
+	}
+	scope := parts[0]
+	if scope == scopeShell {
+		return scope, nil
+	}
+	if scope != scopeWebview && scope != scopeDownload {
+		return "", errors.New("unknown token scope")
+	}
+	generation, err := strconv.ParseUint(parts[1], 10, 64)
+	if err != nil {
+		return "", errors.New("malformed token")
+	}
+	expires, err := strconv.ParseInt(parts[2], 10, 64)
+	if err != nil {
+		return "", errors.New("malformed token")
+	}
+	if now.Unix() >= expires {
+		return "", errors.New("token expired")
+	}
+	a.mu.RLock()
+	revoked := generation < a.minGeneration
+	a.mu.RUnlock()
+	if revoked {
+		return "", errors.New("token revoked")
+	}
+	return scope, nil
+}
+
+// revokeBefore invalidates webview tokens older than the given generation
+func (a *SessionAuth) revokeBefore(generation uint64) {
+	a.mu.Lock()
+	defer a.mu.Unlock()
+	if generation > a.minGeneration {
+		a.minGeneration = generation
+	}
+}
+
+// tokenFromRequest reads a token from "Authorization: Bearer", from the password
+// of Basic auth (used by the shell), or from the token query parameter, which
+// WebSocket connections need because browsers can't set their headers
+func tokenFromRequest(r *http.Request) string {
+	if auth := r.Header.Get("Authorization"); strings.HasPrefix(auth, "Bearer ") {
+		return strings.TrimPrefix(auth, "Bearer ")
+	}
+	if _, password, ok := r.BasicAuth(); ok {
+		return password
+	}
+	return r.URL.Query().Get("token")
+}
+
+// allowsRequest reports whether a verified download token was issued for the
+// object this request reads
+func allowsRequest(token string, r *http.Request) bool {
+	if r.Method != http.MethodGet && r.Method != http.MethodHead {
+		return false
+	}
+	object, ok := strings.CutPrefix(r.URL.Path, downloadPrefix)
+	if !ok {
+		return false
+	}
+	parts := strings.Split(token[:strings.LastIndex(token, ".")], ".")
+	digest := sha256.Sum256([]byte(object))
+	return parts[3] == hex.EncodeToString(digest[:])
+}
+
+// Middleware rejects requests without a valid session token. A nil
+// SessionAuth lets everything through.
+func (a *SessionAuth) Middleware(next http.Handler) http.Handler {
+	if a == nil {
+		return next
+	}
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		token := tokenFromRequest(r)
+		scope, err := a.verify(token, time.Now())
+		if err != nil {
+			respondError(w, http.StatusUnauthorized, err.Error())
+			return
+		}
+		if scope == scopeDownload && !allowsRequest(token, r) {
+			respondError(w, http.StatusForbidden, "This link is for another file")
+			return
+		}
+		next.ServeHTTP(w, r.WithContext(context.WithValue(r.Context(), sessionScopeKey{}, scope)))
+	})
+}
+
+// RevokeRequest raises the minimum generation of accepted webview tokens
+type RevokeRequest struct {
+	MinGeneration uint64 `json:"min_generation"`
+}
+
+func (s *Server) handleSessionRevoke(w http.ResponseWriter, r *http.Request) {
+	if s.session == nil {
+		respondError(w, http.StatusNotFound, "Session tokens are not enabled")
+		return
+	}
+	if scope, _ := r.Context().Value(sessionScopeKey{}).(string); scope != scopeShell {
+		respondError(w, http.StatusForbidden, "Only the desktop shell can revoke sessions")
+		return
+	}
+
+	var req RevokeRequest
+	if err := json.NewDecoder(r.Body).Decode(&req); err != nil {
+		respondError(w, http.StatusBadRequest, "Invalid request body")
+		return
+	}
+	s.session.revokeBefore(req.MinGeneration)
+	respondJSON(w, http.StatusOK, map[string]string{"status": "revoked"})
+}
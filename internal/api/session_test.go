@@ -0,0 +1,168 @@
+package api
+
+import (
+	"crypto/hmac"
+	"crypto/sha256"
+	"encoding/hex"
+	"fmt"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+func signToken(secret []byte, payload string) string {
+	mac := hmac.New(sha256.New, secret)
+	mac.Write([]byte(payload))
+	return payload + "." + hex.EncodeToString(mac.Sum(nil))
+}
+
+func TestSessionAuth_Verify(t *testing.T) {
+	secret := []byte("test-secret")
+	auth := &SessionAuth{secret: secret, minGeneration: 2}
+	now := time.Unix(1000, 0)
+
+	tests := []struct {
+		name    string
+		token   string
+		wantErr bool
+	}{
+		{"valid webview token", signToken(secret, "webview.2.2000.abc"), false},
+		{"shell token never expires", signToken(secret, "shell.0.0.abc"), false},
+		{"expired", signToken(secret, "webview.2.999.abc"), true},
+		{"revoked generation", signToken(secret, "webview.1.2000.abc"), true},
+		{"wrong secret", signToken([]byte("other"), "webview.2.2000.abc"), true},
+		{"unknown scope", signToken(secret, "admin.2.2000.abc"), true},
+		{"tampered", signToken(secret, "webview.2.2000.abc") + "00", true},
+		{"empty", "", true},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			_, err := auth.verify(tt.token, now)
+			if (err != nil) != tt.wantErr {
+				t.Errorf("verify() error = %v, wantErr %v", err, tt.wantErr)
+			}
+		})
+	}
+}
+
+func TestSessionAuth_RevokeBefore(t *testing.T) {
+	secret := []byte("test-secret")
+	auth := &SessionAuth{secret: secret}
+	token := signToken(secret, fmt.Sprintf("webview.0.%d.abc", time.Now().Add(time.Minute).Unix()))
+
+	if _, err := auth.verify(token, time.Now()); err != nil {
+		t.Fatalf("Expected token to be valid before revocation: %v", err)
+	}
+	auth.revokeBefore(1)
+	if _, err := auth.verify(token, time.Now()); err == nil {
+		t.Error("Expected token to be rejected after revocation")
+	}
+	// Never lowered again
+	auth.revokeBefore(0)
+	if _, err := auth.verify(token, time.Now()); err == nil {
+		t.Error("Expected revocation to stick")
+	}
+}
+
+func TestSessionAuth_Middleware(t *testing.T) {
+	secret := []byte("test-secret")
+	auth := &SessionAuth{secret: secret}
+	handler := auth.Middleware(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.WriteHeader(http.StatusOK)
+	}))
+	token := signToken(secret, fmt.Sprintf("webview.0.%d.abc", time.Now().Add(time.Minute).Unix()))
+
+	req := httptest.NewRequest("GET", "/api/buckets", nil)
+	rr := httptest.NewRecorder()
+	handler.ServeHTTP(rr, req)
+	if rr.Code != http.StatusUnauthorized {
+		t.Errorf("Expected status %d without a token, got %d", http.StatusUnauthorized, rr.Code)
+	}
+
+	req = httptest.NewRequest("GET", "/api/buckets", nil)
+	req.Header.Set("Authorization", "Bearer "+token)
+	rr = httptest.NewRecorder()
+	handler.ServeHTTP(rr, req)
+	if rr.Code != http.StatusOK {
+		t.Errorf("Expected status %d with a bearer token, got %d", http.StatusOK, rr.Code)
+	}
+
+	req = httptest.NewRequest("GET", "/api/ws?token="+token, nil)
+	rr = httptest.NewRecorder()
+	handler.ServeHTTP(rr, req)
+	if rr.Code != http.StatusOK {
+		t.Errorf("Expected status %d with a query token, got %d", http.StatusOK, rr.Code)
+	}
+}
+
+func TestSessionAuth_DownloadToken(t *testing.T) {
+	secret := []byte("test-secret")
+	auth := &SessionAuth{secret: secret}
+	handler := auth.Middleware(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.WriteHeader(http.StatusOK)
+	}))
+	digest := sha256.Sum256([]byte("photos/2024/a b.jpg"))
+	token := signToken(secret, fmt.Sprintf("download.0.%d.%s", time.Now().Add(time.Minute).Unix(), hex.EncodeToString(digest[:])))
+
+	tests := []struct {
+		name   string
+		method string
+		target string
+		want   int
+	}{
+		{"the object it was issued for", "GET", "/api/download/photos/2024/a%20b.jpg", http.StatusOK},
+		{"headers of that object", "HEAD", "/api/download/photos/2024/a%20b.jpg", http.StatusOK},
+		{"another object", "GET", "/api/download/photos/2024/other.jpg", http.StatusForbidden},
+		{"another endpoint", "GET", "/api/buckets", http.StatusForbidden},
+		{"a write", "DELETE", "/api/download/photos/2024/a%20b.jpg", http.StatusForbidden},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			req := httptest.NewRequest(tt.method, tt.target+"?token="+token, nil)
+			rr := httptest.NewRecorder()
+			handler.ServeHTTP(rr, req)
+			if rr.Code != tt.want {
+				t.Errorf("Expected status %d, got %d", tt.want, rr.Code)
+			}
+		})
+	}
+}
+
+func TestSessionAuth_ProtectsHealthChecks(t *testing.T) {
+	secret := []byte("test-secret")
+	s := &Server{
+		hub:     NewWebSocketHub(),
+		session: &SessionAuth{secret: secret},
+	}
+	s.setupRouter()
+
+	rr := httptest.NewRecorder()
+	s.router.ServeHTTP(rr, httptest.NewRequest("GET", "/health", nil))
+	if rr.Code != http.StatusUnauthorized {
+		t.Errorf("Expected status %d without a token, got %d", http.StatusUnauthorized, rr.Code)
+	}
+
+	req := httptest.NewRequest("GET", "/health", nil)
+	req.SetBasicAuth("shell", signToken(secret, "shell.0.0.abc"))
+	rr = httptest.NewRecorder()
+	s.router.ServeHTTP(rr, req)
+	if rr.Code != http.StatusOK {
+		t.Errorf("Expected status %d with the shell token, got %d", http.StatusOK, rr.Code)
+	}
+}
+
+func TestSessionAuth_NilAllowsAll(t *testing.T) {
+	var auth *SessionAuth
+	handler := auth.Middleware(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.WriteHeader(http.StatusOK)
+	}))
+
+	rr := httptest.NewRecorder()
+	handler.ServeHTTP(rr, httptest.NewRequest("GET", "/api/buckets", nil))
+	if rr.Code != http.StatusOK {
+		t.Errorf("Expected status %d, got %d", http.StatusOK, rr.Code)
+	}
+}
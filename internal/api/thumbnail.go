@@ -0,0 +1,173 @@
+package api
+
+import (
+	"bytes"
+	"fmt"
+	"image"
+	"image/color"
+	_ "image/gif" // register decoders for image.Decode
+	"image/jpeg"
+	_ "image/png"
+	"io"
+	"net/http"
+	"strconv"
+
+	"github.com/go-chi/chi/v5"
+	"github.com/ryanoboyle/bb-stream/pkg/logging"
+)
+
+// Thumbnail limits. Sources are read fully into memory, so both their size and
+// their decoded pixel count are capped before decoding.
+const (
+	thumbnailDefaultSize = 256
+	thumbnailMaxSize     = 1024
+	thumbnailMaxSource   = 32 << 20
+	thumbnailMaxPixels   = 64 << 20
+)
+
+// handleThumbnail returns a JPEG no larger than ?size= pixels on its longest side
+func (s *Server) handleThumbnail(w http.ResponseWriter, r *http.Request) {
+	bucket := chi.URLParam(r, "bucket")
+	if err := validateBucketName(bucket); err != nil {
+		respondError(w, http.StatusBadRequest, err.Error())
+		return
+	}
+
+	path, err := getPathFromURL(r)
+	if err != nil {
+		respondError(w, http.StatusBadRequest, err.Error())
+		return
+	}
+
+	size := thumbnailDefaultSize
+	if raw := r.URL.Query().Get("size"); raw != "" {
+		size, err = strconv.Atoi(raw)
+		if err != nil || size < 1 || size > thumbnailMaxSize {
+			respondError(w, http.StatusBadRequest, fmt.Sprintf("size must be between 1 and %d", thumbnailMaxSize))
+			return
+		}
+	}
+
+	reader, err := s.client.GetDownloadReader(r.Context(), bucket, path)
+	if err != nil {
+		handleError(w, err, http.StatusNotFound, "thumbnail",
+			logging.Bucket(bucket), logging.Object(path))
+		return
+	}
+	defer reader.Close()
+
+	data, err := io.ReadAll(io.LimitReader(reader, thumbnailMaxSource+1))
+	if err != nil {
+		handleError(w, err, http.StatusInternalServerError, "thumbnail",
+			logging.Bucket(bucket), logging.Object(path))
+		return
+	}
+	if len(data) > thumbnailMaxSource {
+		respondError(w, http.StatusRequestEntityTooLarge, "Image is too large for a thumbnail")
+		return
+	}
+
+	thumb, status, err := makeThumbnail(data, size)
+	if err != nil {
+		respondError(w, status, err.Error())
+		return
+	}
+
+	w.Header().Set("Content-Type", "image/jpeg")
+	w.Header().Set("Cache-Control", "private, max-age=3600")
+	_ = jpeg.Encode(w, thumb, &jpeg.Options{Quality: 80})
+}
+
+// makeThumbnail decodes an image and scales it down, returning the HTTP status
+// to report when it can't
+func makeThumbnail(data []byte, size int) (image.Image, int, error) {
+	config, _, err := image.DecodeConfig(bytes.NewReader(data))
+	if err != nil {
+		return nil, http.StatusUnsupportedMediaType, fmt.Errorf("not a supported image")
+	}
+	if config.Width <= 0 || config.Height <= 0 || config.Width*config.Height > thumbnailMaxPixels {
+		return nil, http.StatusRequestEntityTooLarge, fmt.Errorf("image dimensions are too large for a thumbnail")
+	}
+	img, _, err := image.Decode(bytes.NewReader(data))
+	if err != nil {
+		return nil, http.StatusUnsupportedMediaType, fmt.Errorf("failed to decode image")
+	}
+	return scaleDown(img, size), http.StatusOK, nil
+}
+
+// scaleDown shrinks an image to fit in size x size, averaging the source pixels
+// each target pixel covers. Smaller images are returned unchanged.
+func scaleDown(src image.Image, size int) image.Image {
+	bounds := src.Bounds()
+	w, h := bounds.Dx(), bounds.Dy()
+	if w <= size && h <= size {
+		return src
+	}
+
+	tw, th := size, size
+	if w > h {
+		th = max(1, h*size/w)
+	} else {
+		tw = max(1, w*size/h)
+	}
+
+	if ycc, ok := src.(*image.YCbCr); ok {
+		return scaleDownYCbCr(ycc, tw, th)
+	}
+
+	dst := image.NewRGBA(image.Rect(0, 0, tw, th))
+	for ty := 0; ty < th; ty++ {
+		y0, y1 := bounds.Min.Y+ty*h/th, bounds.Min.Y+(ty+1)*h/th
+		for tx := 0; tx < tw; tx++ {
+			x0, x1 := bounds.Min.X+tx*w/tw, bounds.Min.X+(tx+1)*w/tw
+			var r, g, b, a, n uint64
+			for y := y0; y < y1; y++ {
+				for x := x0; x < x1; x++ {
+					pr, pg, pb, pa := src.At(x, y).RGBA()
+					r, g, b, a = r+uint64(pr), g+uint64(pg), b+uint64(pb), a+uint64(pa)
+					n++
+				}
+			}
+			i := dst.PixOffset(tx, ty)
+			dst.Pix[i+0] = uint8(r / n >> 8)
+			dst.Pix[i+1] = uint8(g / n >> 8)
+			dst.Pix[i+2] = uint8(b / n >> 8)
+			dst.Pix[i+3] = uint8(a / n >> 8)
+		}
+	}
+	return dst
+}
+
+// scaleDownYCbCr is scaleDown for decoded JPEGs. It averages the luma and chroma
+// planes directly rather than converting every source pixel through At, which
+// takes seconds for photos of tens of megapixels.
+func scaleDownYCbCr(src *image.YCbCr, tw, th int) *image.RGBA {
+	bounds := src.Bounds()
+	w, h := bounds.Dx(), bounds.Dy()
+
+	dst := image.NewRGBA(image.Rect(0, 0, tw, th))
+	for ty := 0; ty < th; ty++ {
+		y0, y1 := bounds.Min.Y+ty*h/th, bounds.Min.Y+(ty+1)*h/th
+		for tx := 0; tx < tw; tx++ {
+			x0, x1 := bounds.Min.X+tx*w/tw, bounds.Min.X+(tx+1)*w/tw
+			var yy, cb, cr, n uint64
+			for y := y0; y < y1; y++ {
+				row := src.YOffset(x0, y)
+				for _, v := range src.Y[row : row+x1-x0] {
+					yy += uint64(v)
+				}
+				// Chroma may be subsampled; sampling it per pixel weights it the same
+				for x := x0; x < x1; x++ {
+					c := src.COffset(x, y)
+					cb += uint64(src.Cb[c])
+					cr += uint64(src.Cr[c])
+				}
+				n += uint64(x1 - x0)
+			}
+			r, g, b := color.YCbCrToRGB(uint8(yy/n), uint8(cb/n), uint8(cr/n))
+			i := dst.PixOffset(tx, ty)
+			dst.Pix[i+0], dst.Pix[i+1], dst.Pix[i+2], dst.Pix[i+3] = r, g, b, 0xff
+		}
+	}
+	return dst
+}
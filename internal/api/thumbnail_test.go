@@ -0,0 +1,95 @@
+package api
+
+import (
+	"bytes"
+	"image"
+	"image/color"
+	"image/png"
+	"net/http"
+	"testing"
+)
+
+func TestScaleDown(t *testing.T) {
+	tests := []struct {
+		name          string
+		width, height int
+		wantW, wantH  int
+	}{
+		{"landscape", 1000, 500, 256, 128},
+		{"portrait", 300, 1200, 64, 256},
+		{"already small", 100, 80, 100, 80},
+		{"very thin", 5000, 2, 256, 1},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			src := image.NewRGBA(image.Rect(0, 0, tt.width, tt.height))
+			got := scaleDown(src, 256).Bounds()
+			if got.Dx() != tt.wantW || got.Dy() != tt.wantH {
+				t.Errorf("Expected %dx%d, got %dx%d", tt.wantW, tt.wantH, got.Dx(), got.Dy())
+			}
+		})
+	}
+}
+
+func TestScaleDown_AveragesPixels(t *testing.T) {
+	// Alternating black and white columns average to mid grey
+	src := image.NewRGBA(image.Rect(0, 0, 4, 2))
+	for x := 0; x < 4; x++ {
+		for y := 0; y < 2; y++ {
+			if x%2 == 0 {
+				src.Set(x, y, color.White)
+			} else {
+				src.Set(x, y, color.Black)
+			}
+		}
+	}
+
+	got := scaleDown(src, 2).(*image.RGBA)
+	if r := got.RGBAAt(0, 0).R; r < 120 || r > 135 {
+		t.Errorf("Expected mid grey, got red channel %d", r)
+	}
+}
+
+func TestScaleDown_YCbCrMatchesGeneric(t *testing.T) {
+	src := image.NewYCbCr(image.Rect(0, 0, 64, 48), image.YCbCrSubsampleRatio420)
+	// Values whose colors don't clip, so only rounding differs between the paths
+	for i := range src.Y {
+		src.Y[i] = uint8(64 + i%128)
+	}
+	for i := range src.Cb {
+		src.Cb[i] = uint8(100 + i%56)
+		src.Cr[i] = uint8(156 - i%56)
+	}
+
+	fast := scaleDown(src, 16).(*image.RGBA)
+	// Wrapping hides the concrete type, forcing the generic path
+	slow := scaleDown(struct{ image.Image }{src}, 16).(*image.RGBA)
+	if fast.Bounds() != slow.Bounds() {
+		t.Fatalf("Expected %v, got %v", slow.Bounds(), fast.Bounds())
+	}
+	for i := range fast.Pix {
+		if d := int(fast.Pix[i]) - int(slow.Pix[i]); d < -6 || d > 6 {
+			t.Fatalf("Byte %d differs: %d vs %d", i, fast.Pix[i], slow.Pix[i])
+		}
+	}
+}
+
+func TestMakeThumbnail(t *testing.T) {
+	var buf bytes.Buffer
+	if err := png.Encode(&buf, image.NewRGBA(image.Rect(0, 0, 600, 300))); err != nil {
+		t.Fatalf("Failed to encode test image: %v", err)
+	}
+
+	thumb, status, err := makeThumbnail(buf.Bytes(), 200)
+	if err != nil {
+		t.Fatalf("makeThumbnail failed: %v", err)
+	}
+	if status != http.StatusOK || thumb.Bounds().Dx() != 200 || thumb.Bounds().Dy() != 100 {
+		t.Errorf("Expected a 200x100 thumbnail, got %v with status %d", thumb.Bounds(), status)
+	}
+
+	if _, status, err := makeThumbnail([]byte("not an image"), 200); err == nil || status != http.StatusUnsupportedMediaType {
+		t.Errorf("Expected status %d for non-images, got %d (%v)", http.StatusUnsupportedMediaType, status, err)
+	}
+}
@@ -13,6 +13,11 @@ import (
 type Client struct {
 	client *b2.Client
 	mu     sync.RWMutex
+
+	// Credentials for the low-level client large file parts go through
+	keyID  string
+	appKey string
+	raw    rawClient
 }
 
 var (
@@ -29,6 +34,8 @@ func New(ctx context.Context, keyID, appKey string) (*Client, error) {
 
 	return &Client{
 		client: client,
+		keyID:  keyID,
+		appKey: appKey,
 	}, nil
 }
 
@@ -156,6 +163,41 @@ func (c *Client) ListObjects(ctx context.Context, bucketName, prefix string) ([]
 	return objects, nil
 }
 
+// ObjectChecksums holds the hashes B2 stored for an object at upload
+type ObjectChecksums struct {
+	Name      string
+	Size      int64
+	Timestamp int64
+	// Empty for large files uploaded without a whole-file SHA1
+	SHA1 string
+}
+
+// GetObjectChecksums returns the stored hashes of an object
+func (c *Client) GetObjectChecksums(ctx context.Context, bucketName, objectName string) (*ObjectChecksums, error) {
+	bucket, err := c.Bucket(ctx, bucketName)
+	if err != nil {
+		return nil, err
+	}
+
+	attrs, err := bucket.Object(objectName).Attrs(ctx)
+	if err != nil {
+		return nil, fmt.Errorf("failed to get attributes of %s: %w", objectName, err)
+	}
+
+	// Large files report "none" and may carry the hash in their file info instead
+	sha1 := attrs.SHA1
+	if sha1 == "" || sha1 == "none" {
+		sha1 = attrs.Info["large_file_sha1"]
+	}
+
+	return &ObjectChecksums{
+		Name:      objectName,
+		Size:      attrs.Size,
+		Timestamp: attrs.UploadTimestamp.UnixMilli(),
+		SHA1:      sha1,
+	}, nil
+}
+
 // DeleteObject deletes an object from a bucket
 // B2 requires deleting by file version, so we list versions and delete the latest
 func (c *Client) DeleteObject(ctx context.Context, bucketName, objectName string) error {
@@ -0,0 +1,200 @@
+package b2
+
+import (
+	"context"
+	"fmt"
+	"io"
+	"sync"
+	"time"
+
+	"github.com/Backblaze/blazer/base"
+)
+
+// Large files can also be uploaded one part at a time by a caller that keeps
+// track of the parts itself, so an interrupted upload continues from the last
+// part B2 confirmed instead of starting over. Blazer's Writer hides the parts,
+// so these go through its low-level API.
+
+// rawAuthLifetime is how long a low-level authorization is reused; B2 tokens
+// last 24 hours
+const rawAuthLifetime = 12 * time.Hour
+
+// MaxLargeFileParts is the most parts B2 accepts for one file
+const MaxLargeFileParts = 10000
+
+// LargeFilePart is a part B2 has stored
+type LargeFilePart struct {
+	Number int    `json:"number"`
+	SHA1   string `json:"sha1"`
+	Size   int64  `json:"size"`
+}
+
+type rawClient struct {
+	mu         sync.Mutex
+	b2         *base.B2
+	authorized time.Time
+}
+
+// rawB2 returns the low-level client, authorizing it again once it gets old
+func (c *Client) rawB2(ctx context.Context) (*base.B2, error) {
+	c.raw.mu.Lock()
+	defer c.raw.mu.Unlock()
+
+	if c.raw.b2 == nil || time.Since(c.raw.authorized) > rawAuthLifetime {
+		b2, err := base.AuthorizeAccount(ctx, c.keyID, c.appKey)
+		if err != nil {
+			return nil, fmt.Errorf("failed to authorize: %w", err)
+		}
+		c.raw.b2 = b2
+		c.raw.authorized = time.Now()
+	}
+	return c.raw.b2, nil
+}
+
+func (c *Client) rawBucket(ctx context.Context, bucketName string) (*base.Bucket, error) {
+	b2, err := c.rawB2(ctx)
+	if err != nil {
+		return nil, err
+	}
+	buckets, err := b2.ListBuckets(ctx, bucketName)
+	if err != nil {
+		return nil, fmt.Errorf("failed to list buckets: %w", err)
+	}
+	for _, bucket := range buckets {
+		if bucket.Name == bucketName {
+			return bucket, nil
+		}
+	}
+	return nil, fmt.Errorf("bucket %q not found", bucketName)
+}
+
+// ErrLargeFileNotFound means the large file was finished, cancelled or never
+// started; the caller has to start over
+var ErrLargeFileNotFound = fmt.Errorf("large file not found")
+
+// unfinishedLargeFile finds a started but unfinished large file by ID
+func (c *Client) unfinishedLargeFile(ctx context.Context, bucketName, fileID string) (*base.File, error) {
+	bucket, err := c.rawBucket(ctx, bucketName)
+	if err != nil {
+		return nil, err
+	}
+	continuation := ""
+	for {
+		files, next, err := bucket.ListUnfinishedLargeFiles(ctx, 100, continuation)
+		if err != nil {
+			return nil, fmt.Errorf("failed to list unfinished large files: %w", err)
+		}
+		for _, file := range files {
+			if file.ID == fileID {
+				return file, nil
+			}
+		}
+		if next == "" {
+			return nil, ErrLargeFileNotFound
+		}
+		continuation = next
+	}
+}
+
+// StartLargeFile starts a large file upload and returns its ID. B2 doesn't
+// checksum large files as a whole, so a non-empty sha1 is stored with the file
+// as large_file_sha1.
+func (c *Client) StartLargeFile(ctx context.Context, bucketName, objectName, contentType,
+	sha1 string) (string, error) {
+	bucket, err := c.rawBucket(ctx, bucketName)
+	if err != nil {
+		return "", err
+	}
+	if contentType == "" {
+		contentType = "application/octet-stream"
+	}
+	var info map[string]string
+	if sha1 != "" {
+		info = map[string]string{"large_file_sha1": sha1}
+	}
+	file, err := bucket.StartLargeFile(ctx, objectName, contentType, info)
+	if err != nil {
+		return "", fmt.Errorf("failed to start large file: %w", err)
+	}
+	return file.ID, nil
+}
+
+// ListLargeFileParts returns the parts B2 has stored for an unfinished large file
+func (c *Client) ListLargeFileParts(ctx context.Context, bucketName, fileID string) ([]LargeFilePart, error) {
+	file, err := c.unfinishedLargeFile(ctx, bucketName, fileID)
+	if err != nil {
+		return nil, err
+	}
+	return listParts(ctx, file)
+}
+
+func listParts(ctx context.Context, file *base.File) ([]LargeFilePart, error) {
+	parts := []LargeFilePart{}
+	next := 1
+	for next > 0 {
+		page, n, err := file.ListParts(ctx, next, 1000)
+		if err != nil {
+			return nil, fmt.Errorf("failed to list parts: %w", err)
+		}
+		for _, part := range page {
+			parts = append(parts, LargeFilePart{Number: part.Number, SHA1: part.SHA1, Size: part.Size})
+		}
+		next = n
+	}
+	return parts, nil
+}
+
+// UploadLargeFilePart stores part `number` of a large file. B2 checks the part
+// against sha1 and rejects it if they differ.
+func (c *Client) UploadLargeFilePart(ctx context.Context, bucketName, fileID string, number int, sha1 string,
+	size int64, reader io.Reader) error {
+	file, err := c.unfinishedLargeFile(ctx, bucketName, fileID)
+	if err != nil {
+		return err
+	}
+	chunk, err := file.CompileParts(0, map[int]string{}).GetUploadPartURL(ctx)
+	if err != nil {
+		return fmt.Errorf("failed to get an upload URL: %w", err)
+	}
+	if _, err := chunk.UploadPart(ctx, reader, sha1, int(size), number); err != nil {
+		return fmt.Errorf("failed to upload part %d: %w", number, err)
+	}
+	return nil
+}
+
+// FinishLargeFile assembles the stored parts into the object
+func (c *Client) FinishLargeFile(ctx context.Context, bucketName, fileID string) error {
+	file, err := c.unfinishedLargeFile(ctx, bucketName, fileID)
+	if err != nil {
+		return err
+	}
+	parts, err := listParts(ctx, file)
+	if err != nil {
+		return err
+	}
+	if len(parts) == 0 {
+		return fmt.Errorf("large file has no parts")
+	}
+	seen := make(map[int]string, len(parts))
+	var size int64
+	for _, part := range parts {
+		seen[part.Number] = part.SHA1
+		size += part.Size
+	}
+	if _, err := file.CompileParts(size, seen).FinishLargeFile(ctx); err != nil {
+		return fmt.Errorf("failed to finish large file: %w", err)
+	}
+	return nil
+}
+
+// CancelLargeFile discards an unfinished large file and its parts
+func (c *Client) CancelLargeFile(ctx context.Context, bucketName, fileID string) error {
+	file, err := c.unfinishedLargeFile(ctx, bucketName, fileID)
+	if err != nil {
+		return err
+	}
+	if err := file.CompileParts(0, map[int]string{}).CancelLargeFile(ctx); err != nil {
+		return fmt.Errorf("failed to cancel large file: %w", err)
+	}
+	return nil
+}
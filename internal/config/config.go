@@ -22,15 +22,31 @@ var (
 	configPath string
 )
 
-// Init initializes the configuration system
-func Init() error {
+// DataDir returns the directory bb-stream keeps its files in: BB_DATA_DIR when set,
+// otherwise ~/.config/bb-stream
+func DataDir() (string, error) {
+	if dir := os.Getenv("BB_DATA_DIR"); dir != "" {
+		return dir, nil
+	}
 	home, err := os.UserHomeDir()
 	if err != nil {
-		return fmt.Errorf("failed to get home directory: %w", err)
+		return "", fmt.Errorf("failed to get home directory: %w", err)
 	}
+	return filepath.Join(home, ".config", "bb-stream"), nil
+}
 
-	configDir := filepath.Join(home, ".config", "bb-stream")
+// Init initializes the configuration system. BB_CONFIG names a config file to use
+// instead of config.yaml in the data directory.
+func Init() error {
+	configDir, err := DataDir()
+	if err != nil {
+		return err
+	}
 	configPath = filepath.Join(configDir, "config.yaml")
+	if path := os.Getenv("BB_CONFIG"); path != "" {
+		configPath = path
+		configDir = filepath.Dir(path)
+	}
 
 	// Create config directory if it doesn't exist
 	if err := os.MkdirAll(configDir, 0755); err != nil {
@@ -2,6 +2,7 @@ package config
 
 import (
 	"os"
+	"path/filepath"
 	"testing"
 )
 
@@ -118,6 +119,30 @@ func TestEnvVariableOverride(t *testing.T) {
 	// This is a placeholder for the concept
 }
 
+func TestDataDirFromEnv(t *testing.T) {
+	t.Setenv("BB_DATA_DIR", "/tmp/bb-stream-data")
+
+	dir, err := DataDir()
+	if err != nil {
+		t.Fatalf("DataDir() failed: %v", err)
+	}
+	if dir != "/tmp/bb-stream-data" {
+		t.Errorf("Expected data dir from BB_DATA_DIR, got '%s'", dir)
+	}
+}
+
+func TestInitUsesConfigFromEnv(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "custom.yaml")
+	t.Setenv("BB_CONFIG", path)
+
+	if err := Init(); err != nil {
+		t.Fatalf("Init() failed: %v", err)
+	}
+	if GetConfigPath() != path {
+		t.Errorf("Expected config path '%s', got '%s'", path, GetConfigPath())
+	}
+}
+
 func TestGetReturnsNonNil(t *testing.T) {
 	cfg = nil
 
@@ -5,24 +5,58 @@ import (
 	"context"
 	"log/slog"
 	"os"
+	"strings"
 	"sync"
 )
 
 var (
 	defaultLogger *slog.Logger
 	once          sync.Once
+	// Minimum level of the default logger; SetLevel changes it while running
+	level slog.LevelVar
 )
 
 // init initializes the default logger with JSON output for production.
+// BB_LOG_LEVEL (debug, info, warn or error) sets the minimum level.
 func init() {
 	once.Do(func() {
+		level.Set(ParseLevel(os.Getenv("BB_LOG_LEVEL")))
 		handler := slog.NewJSONHandler(os.Stderr, &slog.HandlerOptions{
-			Level: slog.LevelInfo,
+			Level: &level,
 		})
 		defaultLogger = slog.New(handler)
 	})
 }
 
+// ParseLevel converts a level name to a slog level, defaulting to info.
+func ParseLevel(name string) slog.Level {
+	switch strings.ToLower(strings.TrimSpace(name)) {
+	case "debug":
+		return slog.LevelDebug
+	case "warn", "warning":
+		return slog.LevelWarn
+	case "error":
+		return slog.LevelError
+	default:
+		return slog.LevelInfo
+	}
+}
+
+// LevelName returns the lowercase name of a level ParseLevel accepts.
+func LevelName(l slog.Level) string {
+	return strings.ToLower(l.String())
+}
+
+// Level returns the default logger's current minimum level.
+func Level() slog.Level {
+	return level.Level()
+}
+
+// SetLevel changes the default logger's minimum level without a restart.
+func SetLevel(l slog.Level) {
+	level.Set(l)
+}
+
 // Logger returns the default logger.
 func Logger() *slog.Logger {
 	return defaultLogger
@@ -40,6 +40,27 @@ func TestSetLogger(t *testing.T) {
 	}
 }
 
+func TestParseLevel(t *testing.T) {
+	tests := []struct {
+		name     string
+		expected slog.Level
+	}{
+		{"debug", slog.LevelDebug},
+		{"INFO", slog.LevelInfo},
+		{"warn", slog.LevelWarn},
+		{"warning", slog.LevelWarn},
+		{" error ", slog.LevelError},
+		{"", slog.LevelInfo},
+		{"verbose", slog.LevelInfo},
+	}
+
+	for _, tt := range tests {
+		if got := ParseLevel(tt.name); got != tt.expected {
+			t.Errorf("ParseLevel(%q) = %v, expected %v", tt.name, got, tt.expected)
+		}
+	}
+}
+
 func TestAttributeHelpers(t *testing.T) {
 	tests := []struct {
 		name     string
@@ -144,3 +165,16 @@ func TestLoggerOutputFormat(t *testing.T) {
 		t.Errorf("got status %v, want 200", logEntry["status"])
 	}
 }
+
+func TestSetLevel(t *testing.T) {
+	original := Level()
+	defer SetLevel(original)
+
+	SetLevel(slog.LevelWarn)
+	if Level() != slog.LevelWarn || LevelName(Level()) != "warn" {
+		t.Errorf("Expected level warn, got %v", Level())
+	}
+	if Logger().Enabled(context.Background(), slog.LevelInfo) {
+		t.Error("Expected info messages to be dropped at warn")
+	}
+}